@@ -0,0 +1,328 @@
+//! Process-image ABI version.
+//!
+//! The compiler records the process-image layout a module was built against
+//! in the [`SECTION_NAME`] custom section, so the runtime can refuse modules
+//! that expect a different layout. The payload is one `key value` line per
+//! field:
+//!
+//! ```text
+//! version 1.2
+//! di 32
+//! do 32
+//! ai 16
+//! ao 16
+//! sysinfo 32
+//! user 104
+//! ```
+//!
+//! A module is compatible with a runtime if the major versions match, the
+//! module's minor version is not newer, and its I/O dimensions fit the
+//! runtime's image. Older minor versions are adapted by the host; see
+//! `docs/process-image-abi.md`.
+//!
+//! The regions follow each other in the order digital inputs, digital
+//! outputs, analog inputs, analog outputs, system info, so their offsets
+//! follow from the dimensions; see [`ProcessImageLayout::do_offset`] and
+//! friends.
+
+use crate::config::ProcessImageConfig;
+use crate::error::{PlcError, PlcResult};
+use std::fmt;
+
+/// Custom section holding the layout.
+pub const SECTION_NAME: &str = "plc.abi";
+
+/// ABI version, `major.minor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AbiVersion {
+    /// Incremented for layout changes that move existing fields.
+    pub major: u16,
+    /// Incremented for backwards-compatible extensions.
+    pub minor: u16,
+}
+
+impl fmt::Display for AbiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Process-image layout a module was built against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessImageLayout {
+    /// ABI version.
+    pub version: AbiVersion,
+    /// Digital input bits.
+    pub di_bits: u32,
+    /// Digital output bits.
+    pub do_bits: u32,
+    /// Analog input channels.
+    pub ai_channels: u32,
+    /// Analog output channels.
+    pub ao_channels: u32,
+    /// Bytes of system info the module expects the host to write.
+    pub sysinfo_size: u32,
+    /// First byte of linear memory owned by the module.
+    pub user_data_offset: u32,
+}
+
+impl ProcessImageLayout {
+    /// ABI 1.0: 8 bytes of system info (cycle time, flags).
+    pub const V1_0: Self = Self {
+        version: AbiVersion { major: 1, minor: 0 },
+        di_bits: 32,
+        do_bits: 32,
+        ai_channels: 16,
+        ao_channels: 16,
+        sysinfo_size: 8,
+        user_data_offset: 0x50,
+    };
+
+    /// ABI 1.1: system info extended to 32 bytes (cycle count, fault code).
+    pub const V1_1: Self = Self {
+        version: AbiVersion { major: 1, minor: 1 },
+        sysinfo_size: 32,
+        user_data_offset: 0x68,
+        ..Self::V1_0
+    };
+
+    /// ABI 1.2: the I/O dimensions are configurable and the region offsets
+    /// follow from them. The default dimensions give the 1.1 offsets.
+    pub const V1_2: Self = Self {
+        version: AbiVersion { major: 1, minor: 2 },
+        ..Self::V1_1
+    };
+
+    /// Layout produced by this compiler and expected by this runtime, with
+    /// the default dimensions.
+    pub const CURRENT: Self = Self::V1_2;
+
+    /// The current layout with other I/O dimensions. Digital bit counts are
+    /// rounded up to whole 32-bit words.
+    pub const fn with_dimensions(
+        di_bits: u32,
+        do_bits: u32,
+        ai_channels: u32,
+        ao_channels: u32,
+    ) -> Self {
+        let mut layout = Self {
+            di_bits: di_bits.div_ceil(32) * 32,
+            do_bits: do_bits.div_ceil(32) * 32,
+            ai_channels,
+            ao_channels,
+            ..Self::CURRENT
+        };
+        layout.user_data_offset = layout.sysinfo_offset() + layout.sysinfo_size;
+        layout
+    }
+
+    /// The current layout with the configured dimensions.
+    pub const fn for_config(config: &ProcessImageConfig) -> Self {
+        Self::with_dimensions(
+            config.digital_inputs,
+            config.digital_outputs,
+            config.analog_inputs,
+            config.analog_outputs,
+        )
+    }
+
+    /// Number of 32-bit digital input words.
+    pub const fn di_words(&self) -> u32 {
+        self.di_bits.div_ceil(32)
+    }
+
+    /// Number of 32-bit digital output words.
+    pub const fn do_words(&self) -> u32 {
+        self.do_bits.div_ceil(32)
+    }
+
+    /// Byte offset of the digital inputs.
+    pub const fn di_offset(&self) -> u32 {
+        0
+    }
+
+    /// Byte offset of the digital outputs.
+    pub const fn do_offset(&self) -> u32 {
+        self.di_offset() + self.di_words() * 4
+    }
+
+    /// Byte offset of the analog inputs.
+    pub const fn ai_offset(&self) -> u32 {
+        self.do_offset() + self.do_words() * 4
+    }
+
+    /// Byte offset of the analog outputs.
+    pub const fn ao_offset(&self) -> u32 {
+        self.ai_offset() + self.ai_channels * 2
+    }
+
+    /// Byte offset of the system info.
+    pub const fn sysinfo_offset(&self) -> u32 {
+        self.ao_offset() + self.ao_channels * 2
+    }
+
+    /// Check that a module built against `self` can run on a host using
+    /// `host`.
+    pub fn check_compatible(&self, host: &Self) -> PlcResult<()> {
+        let incompatible = |reason: String| {
+            Err(PlcError::Config(format!(
+                "incompatible process-image ABI: {reason}"
+            )))
+        };
+
+        if self.version.major != host.version.major || self.version > host.version {
+            return incompatible(format!(
+                "module was built for ABI {}, runtime supports {}.0 to {}",
+                self.version, host.version.major, host.version
+            ));
+        }
+        let dimensions = [
+            ("digital inputs", self.di_bits, host.di_bits),
+            ("digital outputs", self.do_bits, host.do_bits),
+            ("analog inputs", self.ai_channels, host.ai_channels),
+            ("analog outputs", self.ao_channels, host.ao_channels),
+        ];
+        for (what, module, host) in dimensions {
+            if module > host {
+                return incompatible(format!(
+                    "module expects {module} {what}, runtime provides {host}"
+                ));
+            }
+        }
+        if self.sysinfo_size > host.sysinfo_size {
+            return incompatible(format!(
+                "module expects {} bytes of system info, runtime writes {}",
+                self.sysinfo_size, host.sysinfo_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for ProcessImageLayout {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+/// Encode a layout as the section payload.
+pub fn encode_layout(layout: &ProcessImageLayout) -> Vec<u8> {
+    format!(
+        "version {}\ndi {}\ndo {}\nai {}\nao {}\nsysinfo {}\nuser {}\n",
+        layout.version,
+        layout.di_bits,
+        layout.do_bits,
+        layout.ai_channels,
+        layout.ao_channels,
+        layout.sysinfo_size,
+        layout.user_data_offset
+    )
+    .into_bytes()
+}
+
+/// Decode a section payload.
+pub fn decode_layout(payload: &[u8]) -> PlcResult<ProcessImageLayout> {
+    let malformed = || PlcError::Config("malformed process-image ABI section".into());
+    let text = String::from_utf8_lossy(payload);
+    let field = |key: &str| {
+        text.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(' '))
+            .ok_or_else(malformed)
+    };
+    let number = |key: &str| field(key)?.parse::<u32>().map_err(|_| malformed());
+
+    let (major, minor) = field("version")?.split_once('.').ok_or_else(malformed)?;
+    Ok(ProcessImageLayout {
+        version: AbiVersion {
+            major: major.parse().map_err(|_| malformed())?,
+            minor: minor.parse().map_err(|_| malformed())?,
+        },
+        di_bits: number("di")?,
+        do_bits: number("do")?,
+        ai_channels: number("ai")?,
+        ao_channels: number("ao")?,
+        sysinfo_size: number("sysinfo")?,
+        user_data_offset: number("user")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_roundtrip() {
+        let payload = encode_layout(&ProcessImageLayout::CURRENT);
+        assert!(payload.starts_with(b"version 1.2\n"));
+        assert_eq!(
+            decode_layout(&payload).unwrap(),
+            ProcessImageLayout::CURRENT
+        );
+        assert!(decode_layout(b"version 1.1\ndi 32\n").is_err());
+    }
+
+    #[test]
+    fn test_offsets() {
+        for layout in [
+            ProcessImageLayout::V1_0,
+            ProcessImageLayout::V1_1,
+            ProcessImageLayout::CURRENT,
+        ] {
+            assert_eq!(layout.do_offset(), 0x04);
+            assert_eq!(layout.ai_offset(), 0x08);
+            assert_eq!(layout.ao_offset(), 0x28);
+            assert_eq!(layout.sysinfo_offset(), 0x48);
+            assert_eq!(
+                layout.user_data_offset,
+                layout.sysinfo_offset() + layout.sysinfo_size
+            );
+        }
+        assert_eq!(
+            ProcessImageLayout::for_config(&ProcessImageConfig::default()),
+            ProcessImageLayout::CURRENT
+        );
+
+        let large = ProcessImageLayout::with_dimensions(400, 300, 8, 4);
+        assert_eq!(large.di_bits, 416);
+        assert_eq!(large.do_offset(), 52);
+        assert_eq!(large.ai_offset(), 52 + 40);
+        assert_eq!(large.ao_offset(), 92 + 16);
+        assert_eq!(large.sysinfo_offset(), 108 + 8);
+        assert_eq!(large.user_data_offset, 116 + 32);
+        assert_eq!(decode_layout(&encode_layout(&large)).unwrap(), large);
+    }
+
+    #[test]
+    fn test_compatibility() {
+        let host = ProcessImageLayout::CURRENT;
+        host.check_compatible(&host).unwrap();
+        // Older minor versions are adapted by the host.
+        ProcessImageLayout::V1_0.check_compatible(&host).unwrap();
+        ProcessImageLayout::V1_1.check_compatible(&host).unwrap();
+
+        let newer = ProcessImageLayout {
+            version: AbiVersion { major: 1, minor: 3 },
+            ..host
+        };
+        let err = newer.check_compatible(&host).unwrap_err().to_string();
+        assert!(err.contains("built for ABI 1.3"), "{err}");
+
+        let other_major = ProcessImageLayout {
+            version: AbiVersion { major: 2, minor: 0 },
+            ..host
+        };
+        assert!(other_major.check_compatible(&host).is_err());
+
+        let more_inputs = ProcessImageLayout {
+            ai_channels: 32,
+            ..host
+        };
+        let err = more_inputs.check_compatible(&host).unwrap_err().to_string();
+        assert!(err.contains("32 analog inputs"), "{err}");
+
+        // A larger configured image runs modules built for a smaller one.
+        let large = ProcessImageLayout::with_dimensions(400, 400, 16, 16);
+        host.check_compatible(&large).unwrap();
+        assert!(large.check_compatible(&host).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Content-addressed store for build artifacts.
+//!
+//! Artifacts live under `<dir>/<kind>/<key>`, where the key is a SHA-256
+//! over everything the artifact was built from (see [`CacheKey`]). The
+//! compiler stores finished modules under [`MODULE`] and the runtime stores
+//! Wasmtime-precompiled code under [`PRECOMPILED`]. Entries are never
+//! changed in place: different inputs give a different key, so stale entries
+//! are simply not found again and the directory can be deleted at any time.
+//!
+//! Each file starts with a SHA-256 over the kind, the key and the artifact.
+//! [`ArtifactStore::get`] checks it and treats a corrupt or truncated entry,
+//! or one copied under another key, as missing. Since the key of
+//! [`PRECOMPILED`] entries includes the engine fingerprint, the digest binds
+//! native code to the engine it was built for. The digest is not a signature:
+//! whoever can write the directory can write a matching one, so the store is
+//! created accessible to its owner only.
+
+use crate::error::{PlcError, PlcResult};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Kind of compiled Wasm modules, keyed by their project sources.
+pub const MODULE: &str = "module";
+
+/// Kind of Wasmtime-precompiled modules, keyed by Wasm bytes and engine.
+pub const PRECOMPILED: &str = "cwasm";
+
+/// Length of the digest heading every entry.
+const DIGEST_LEN: usize = 32;
+
+/// SHA-256 over the inputs of a build step.
+///
+/// Every input is length-prefixed, so `("ab", "c")` and `("a", "bc")` give
+/// different keys.
+#[derive(Debug, Clone)]
+pub struct CacheKey(Sha256);
+
+impl CacheKey {
+    /// Start a key; `domain` separates keys of different build steps.
+    pub fn new(domain: &str) -> Self {
+        let mut key = Self(Sha256::new());
+        key.add(domain);
+        key
+    }
+
+    /// Add one input.
+    pub fn add(&mut self, input: impl AsRef<[u8]>) -> &mut Self {
+        let input = input.as_ref();
+        self.0.update((input.len() as u64).to_le_bytes());
+        self.0.update(input);
+        self
+    }
+
+    /// Finish the key as a lower-case hex string.
+    pub fn finish(self) -> String {
+        self.0
+            .finalize()
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+}
+
+/// Artifact directory on disk.
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    dir: PathBuf,
+}
+
+impl ArtifactStore {
+    /// Use `dir` as the store; it is created on the first [`put`](Self::put).
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Root directory of the store.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, kind: &str, key: &str) -> PathBuf {
+        self.dir.join(kind).join(key)
+    }
+
+    /// Read an artifact; `None` if it has not been stored or fails its
+    /// digest check.
+    pub fn get(&self, kind: &str, key: &str) -> Option<Vec<u8>> {
+        let path = self.path(kind, key);
+        let mut entry = std::fs::read(&path).ok()?;
+        if entry.len() < DIGEST_LEN
+            || entry[..DIGEST_LEN] != entry_digest(kind, key, &entry[DIGEST_LEN..])
+        {
+            warn!(path = %path.display(), "Ignoring build cache entry that fails its digest check");
+            return None;
+        }
+        Some(entry.split_off(DIGEST_LEN))
+    }
+
+    /// Store an artifact.
+    ///
+    /// The file is written under a temporary name and renamed into place,
+    /// so concurrent readers never see a partial artifact.
+    pub fn put(&self, kind: &str, key: &str, bytes: &[u8]) -> PlcResult<()> {
+        let path = self.path(kind, key);
+        let io_error =
+            |e: std::io::Error| PlcError::IoError(format!("build cache {}: {e}", path.display()));
+        let mut dirs = std::fs::DirBuilder::new();
+        dirs.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut dirs, 0o700);
+        dirs.create(self.dir.join(kind)).map_err(io_error)?;
+        let partial = path.with_extension(format!("partial-{}", std::process::id()));
+        let entry = [&entry_digest(kind, key, bytes)[..], bytes].concat();
+        std::fs::write(&partial, entry).map_err(io_error)?;
+        std::fs::rename(&partial, &path).map_err(io_error)
+    }
+}
+
+/// Digest of an entry, bound to where it is stored.
+fn entry_digest(kind: &str, key: &str, bytes: &[u8]) -> [u8; DIGEST_LEN] {
+    let mut digest = Sha256::new();
+    for input in [kind.as_bytes(), key.as_bytes(), bytes] {
+        digest.update((input.len() as u64).to_le_bytes());
+        digest.update(input);
+    }
+    digest.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let key = |inputs: &[&str]| {
+            let mut key = CacheKey::new("test");
+            for input in inputs {
+                key.add(input);
+            }
+            key.finish()
+        };
+        assert_eq!(key(&["ab", "c"]).len(), 64);
+        assert_eq!(key(&["ab", "c"]), key(&["ab", "c"]));
+        assert_ne!(key(&["ab", "c"]), key(&["a", "bc"]));
+        assert_ne!(CacheKey::new("a").finish(), CacheKey::new("b").finish());
+    }
+
+    #[test]
+    fn test_artifact_store() {
+        let dir = std::env::temp_dir().join(format!("plc-build-cache-{}", std::process::id()));
+        let store = ArtifactStore::new(&dir);
+        assert_eq!(store.get(MODULE, "abc"), None);
+        store.put(MODULE, "abc", b"\0asm").unwrap();
+        assert_eq!(store.get(MODULE, "abc").as_deref(), Some(&b"\0asm"[..]));
+        assert_eq!(store.get(PRECOMPILED, "abc"), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_artifact_store_rejects_altered_entries() {
+        let dir = std::env::temp_dir().join(format!("plc-build-cache-alt-{}", std::process::id()));
+        let store = ArtifactStore::new(&dir);
+        store.put(PRECOMPILED, "abc", b"native code").unwrap();
+
+        // One flipped byte
+        let path = dir.join(PRECOMPILED).join("abc");
+        let mut entry = std::fs::read(&path).unwrap();
+        *entry.last_mut().unwrap() ^= 0x01;
+        std::fs::write(&path, &entry).unwrap();
+        assert_eq!(store.get(PRECOMPILED, "abc"), None);
+
+        // A valid entry copied under another key
+        store.put(PRECOMPILED, "abc", b"native code").unwrap();
+        std::fs::copy(&path, dir.join(PRECOMPILED).join("def")).unwrap();
+        assert_eq!(store.get(PRECOMPILED, "def"), None);
+
+        // Truncated, and written without a digest
+        std::fs::write(&path, b"short").unwrap();
+        assert_eq!(store.get(PRECOMPILED, "abc"), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join(PRECOMPILED))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o077, 0, "store is private to its owner");
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
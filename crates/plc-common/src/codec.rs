@@ -0,0 +1,96 @@
+//! Little-endian binary encoding shared by the on-disk and on-wire formats.
+//!
+//! Integers are written little-endian and byte strings carry a `u32`
+//! length prefix. Encoding appends to a caller's buffer, so one can be
+//! reused; [`Reader`] decodes without copying and returns `None` on
+//! truncated input.
+
+/// Append `bytes` with a `u32` length prefix.
+pub fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    // Every format using this limits its blobs to 4 GiB, like Wasm modules
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// Cursor over encoded bytes.
+#[derive(Debug, Clone)]
+pub struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    /// Read from the start of `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+
+    /// The bytes not read yet.
+    pub fn rest(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Whether everything was read.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The next `len` bytes.
+    pub fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    /// The next `u8`.
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    /// The next `u16`.
+    pub fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    /// The next `u32`.
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    /// The next `u64`.
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    /// Bytes written by [`put_bytes`].
+    pub fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut out = vec![7];
+        out.extend_from_slice(&0x1234u16.to_le_bytes());
+        out.extend_from_slice(&u64::MAX.to_le_bytes());
+        put_bytes(&mut out, b"plc");
+        put_bytes(&mut out, b"");
+
+        let mut reader = Reader::new(&out);
+        assert_eq!(reader.u8(), Some(7));
+        assert_eq!(reader.u16(), Some(0x1234));
+        assert_eq!(reader.u64(), Some(u64::MAX));
+        assert_eq!(reader.bytes(), Some(&b"plc"[..]));
+        assert_eq!(reader.bytes(), Some(&b""[..]));
+        assert!(reader.is_empty());
+
+        // Truncated input
+        assert_eq!(Reader::new(&out[..1]).u16(), None);
+        assert_eq!(Reader::new(&[9, 0, 0, 0, 1]).bytes(), None);
+    }
+}
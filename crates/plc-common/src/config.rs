@@ -41,6 +41,28 @@ pub struct RuntimeConfig {
 
     /// WebAssembly runtime configuration.
     pub wasm: WasmConfig,
+
+    /// Process image dimensions.
+    pub process_image: ProcessImageConfig,
+
+    /// Cyclic tasks. Without any, a single task runs `step` every
+    /// `cycle_time` (see [`RuntimeConfig::scheduled_tasks`]).
+    pub tasks: Vec<TaskConfig>,
+
+    /// Forcing of I/O points and variables.
+    pub forcing: ForcingConfig,
+
+    /// Debug mode (pause, single cycle, breakpoints).
+    pub debug: DebugConfig,
+
+    /// Signal tracing.
+    pub trace: TraceConfig,
+
+    /// Recording of scan cycles for replay.
+    pub record: RecordConfig,
+
+    /// Hot-standby redundancy with a second runtime.
+    pub redundancy: RedundancyConfig,
 }
 
 impl Default for RuntimeConfig {
@@ -55,6 +77,13 @@ impl Default for RuntimeConfig {
             metrics: MetricsConfig::default(),
             fault_policy: FaultPolicyConfig::default(),
             wasm: WasmConfig::default(),
+            process_image: ProcessImageConfig::default(),
+            tasks: Vec::new(),
+            forcing: ForcingConfig::default(),
+            debug: DebugConfig::default(),
+            trace: TraceConfig::default(),
+            record: RecordConfig::default(),
+            redundancy: RedundancyConfig::default(),
         }
     }
 }
@@ -147,6 +176,9 @@ pub struct FaultPolicyConfig {
     /// Number of fault frames to retain for postmortem diagnosis.
     /// Defaults to 64 if not specified.
     pub fault_frame_count: Option<usize>,
+    /// Directory the daemon writes a post-mortem file to when a fault
+    /// freezes the fault recorder. None (default) writes no file.
+    pub postmortem_dir: Option<PathBuf>,
 }
 
 /// Scheduler policy for real-time threads.
@@ -412,10 +444,17 @@ impl Default for MetricsConfig {
     }
 }
 
+/// Interval of the Wasmtime epoch ticker; `max_epochs_per_cycle` counts
+/// these.
+pub const EPOCH_INTERVAL: Duration = Duration::from_micros(10);
+
 /// WebAssembly runtime configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct WasmConfig {
+    /// Engine that runs the logic module.
+    pub engine: WasmEngine,
+
     /// Maximum linear memory size in bytes.
     pub max_memory_bytes: usize,
 
@@ -459,11 +498,35 @@ pub struct WasmConfig {
     ///
     /// Typical values: 100,000 - 10,000,000 depending on logic complexity.
     pub fuel_per_cycle: u64,
+
+    /// Build cache directory.
+    ///
+    /// When set, Wasmtime-precompiled modules are stored there and reused on
+    /// load and hot-reload, so a module that was compiled before skips
+    /// Cranelift. The daemon also caches modules it compiles from sources.
+    /// Precompiled modules are not cached while `trusted_keys` are set, as
+    /// cached native code carries no signature.
+    pub cache_dir: Option<PathBuf>,
+
+    /// Report the state migration on hot-reload without replacing the
+    /// running module.
+    ///
+    /// Lets an online change be checked against the running program before
+    /// it is made.
+    pub reload_dry_run: bool,
+
+    /// Ed25519 public keys, hex, whose signatures the runtime trusts.
+    ///
+    /// When any are listed, a module is loaded or hot-reloaded only if it
+    /// carries a valid signature by one of them; unsigned and tampered
+    /// modules are refused. Empty disables signature checks.
+    pub trusted_keys: Vec<String>,
 }
 
 impl Default for WasmConfig {
     fn default() -> Self {
         Self {
+            engine: WasmEngine::Wasmtime,
             // Secure default: 1MB (16 pages of 64KB). Increase for larger programs.
             max_memory_bytes: 1024 * 1024, // 1 MB
             max_epochs_per_cycle: 100,
@@ -473,11 +536,362 @@ impl Default for WasmConfig {
             // Secure default: fuel enabled to trap infinite loops.
             use_fuel: true,
             fuel_per_cycle: 1_000_000, // 1M instructions default
+            cache_dir: None,
+            reload_dry_run: false,
+            trusted_keys: Vec::new(),
+        }
+    }
+}
+
+impl WasmConfig {
+    /// Longest a single call into the logic module runs before the engine
+    /// stops it. `None` for the interpreter, which only counts fuel.
+    pub fn call_budget(&self) -> Option<Duration> {
+        match self.engine {
+            WasmEngine::Wasmtime => Some(
+                EPOCH_INTERVAL
+                    .saturating_mul(u32::try_from(self.max_epochs_per_cycle).unwrap_or(u32::MAX)),
+            ),
+            WasmEngine::Interpreter => None,
+        }
+    }
+}
+
+/// Engines that can run the logic module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WasmEngine {
+    /// Wasmtime with Cranelift JIT (fastest cycles).
+    #[default]
+    Wasmtime,
+    /// Pure Wasm interpreter (wasmi): no JIT and fast start-up, for small
+    /// targets. Cycles are bounded by fuel only, so `use_fuel` must be set.
+    Interpreter,
+}
+
+/// Forcing of I/O points and variables from the control plane.
+///
+/// Forces are always released when the runtime faults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForcingConfig {
+    /// Accept force requests. Disable on machines where forcing must not
+    /// be possible.
+    pub enabled: bool,
+
+    /// Release all forces when the runtime stops.
+    pub clear_on_stop: bool,
+}
+
+impl Default for ForcingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            clear_on_stop: true,
+        }
+    }
+}
+
+/// What the outputs do while the runtime is in DEBUG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugOutputPolicy {
+    /// Outputs are set safe (see `fault_policy.safe_outputs`) and stepped
+    /// cycles do not drive them.
+    #[default]
+    Safe,
+    /// Outputs keep their values; each stepped cycle commits its outputs.
+    Hold,
+}
+
+/// Debug mode configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    /// Output behavior while debugging.
+    pub outputs: DebugOutputPolicy,
+}
+
+/// Signal trace configuration.
+///
+/// With signals configured, a trace is armed when the runtime starts; it
+/// can be re-armed from the control plane.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TraceConfig {
+    /// Signals to record: process image addresses (`%IX0.1`, `%QW2`) or
+    /// variables (`Main.speed`).
+    pub signals: Vec<String>,
+
+    /// When the capture triggers: `immediate`, `fault`, `rising <signal>`,
+    /// `falling <signal>`, or a threshold such as `Main.level > 80`.
+    pub trigger: String,
+
+    /// Samples kept from before the trigger.
+    pub pre_trigger: usize,
+
+    /// Samples recorded after the trigger.
+    pub post_trigger: usize,
+
+    /// Record every Nth scan.
+    pub divider: u32,
+
+    /// Directory the daemon writes finished captures to, as CSV and VCD.
+    pub output_dir: Option<PathBuf>,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            signals: Vec::new(),
+            trigger: "immediate".into(),
+            pre_trigger: 100,
+            post_trigger: 100,
+            divider: 1,
+            output_dir: None,
+        }
+    }
+}
+
+/// Recording of scan cycles for replay.
+///
+/// With a directory configured, every task step's inputs, system info and
+/// outputs are recorded to segment files, each starting with a checkpoint of
+/// the logic's memory. Only the newest segments are kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordConfig {
+    /// Directory for the segment files. None (default) records nothing.
+    pub dir: Option<PathBuf>,
+
+    /// Task steps per segment file.
+    pub segment_steps: u64,
+
+    /// Segment files kept; older ones are deleted.
+    pub max_segments: usize,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            segment_steps: 10_000,
+            max_segments: 8,
+        }
+    }
+}
+
+/// Role a node of a redundant pair prefers when the pair negotiates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RedundancyRole {
+    /// Primary unless the peer prefers to be; ties go to the lower node id.
+    #[default]
+    Auto,
+    /// Prefer to be primary.
+    Primary,
+    /// Prefer to be standby. Never becomes primary on its own at start-up,
+    /// only by taking over from a primary it has followed.
+    Standby,
+}
+
+/// Hot-standby redundancy between two runtimes.
+///
+/// The primary runs the logic and drives the outputs; after every scan it
+/// sends the changes to the logic's memory and the latched inputs and
+/// outputs to the standby. The standby follows without running the logic
+/// and takes over, from the primary's last state, when it misses the
+/// primary for `takeover_cycles` scans. One node `listen`s, the other
+/// `connect`s; either may end up primary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedundancyConfig {
+    /// Run as one node of a redundant pair.
+    pub enabled: bool,
+
+    /// Identifier of this node; the two nodes must differ.
+    pub node_id: u32,
+
+    /// Preferred role.
+    pub role: RedundancyRole,
+
+    /// Address to accept the peer's connection on: `host:port` or
+    /// `unix:<path>`.
+    pub listen: Option<String>,
+
+    /// Address of the peer to connect to, in the same form.
+    pub connect: Option<String>,
+
+    /// Scans without a message from the primary before the standby takes
+    /// over.
+    pub takeover_cycles: u32,
+
+    /// How long a starting node waits for its peer before becoming primary
+    /// on its own.
+    #[serde(with = "humantime_serde")]
+    pub startup_timeout: Duration,
+
+    /// Secret shared by both nodes and the witness, at least 16
+    /// characters. It authenticates every message between them; keep the
+    /// configuration file readable only by the daemon.
+    pub secret: Option<String>,
+
+    /// Address of a `plc-daemon witness` (`host:port` or `unix:<path>`),
+    /// ideally on a third machine. Driving the outputs then takes a lease
+    /// from it, so a broken link between the nodes cannot leave two
+    /// primaries. Required unless `allow_split_brain` is set.
+    pub witness: Option<String>,
+
+    /// Run the pair without a witness, accepting that a broken link
+    /// between the nodes leaves both driving the outputs until it heals.
+    pub allow_split_brain: bool,
+}
+
+impl Default for RedundancyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            node_id: 1,
+            role: RedundancyRole::Auto,
+            listen: None,
+            connect: None,
+            takeover_cycles: 10,
+            startup_timeout: Duration::from_secs(2),
+            secret: None,
+            witness: None,
+            allow_split_brain: false,
+        }
+    }
+}
+
+/// Size of the process image shared by the fieldbus and the logic.
+///
+/// Digital points are stored in 32-bit words, so their counts are rounded up
+/// to a multiple of 32.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessImageConfig {
+    /// Digital input points.
+    pub digital_inputs: u32,
+
+    /// Digital output points.
+    pub digital_outputs: u32,
+
+    /// Analog input channels (16-bit).
+    pub analog_inputs: u32,
+
+    /// Analog output channels (16-bit).
+    pub analog_outputs: u32,
+}
+
+impl Default for ProcessImageConfig {
+    fn default() -> Self {
+        Self {
+            digital_inputs: 32,
+            digital_outputs: 32,
+            analog_inputs: 16,
+            analog_outputs: 16,
         }
     }
 }
 
+impl ProcessImageConfig {
+    /// Number of 32-bit digital input words.
+    pub fn di_words(&self) -> usize {
+        self.digital_inputs.div_ceil(32) as usize
+    }
+
+    /// Number of 32-bit digital output words.
+    pub fn do_words(&self) -> usize {
+        self.digital_outputs.div_ceil(32) as usize
+    }
+
+    /// Number of analog input channels.
+    pub fn ai_channels(&self) -> usize {
+        self.analog_inputs as usize
+    }
+
+    /// Number of analog output channels.
+    pub fn ao_channels(&self) -> usize {
+        self.analog_outputs as usize
+    }
+}
+
+/// A cyclic task: an exported entry point of the logic module, run at its
+/// own period.
+///
+/// Tasks released at the same time run one after another on the scheduler
+/// thread, in priority order. A running task is not preempted, so a task can
+/// be held up by one call of any other task; the configuration is refused
+/// unless that call budget ([`WasmConfig::call_budget`]) fits in the fastest
+/// period and in every task's watchdog margin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskConfig {
+    /// Task name, used in logs and metrics.
+    pub name: String,
+
+    /// Exported function the task calls. Defaults to the task name, which
+    /// is the export of the ST `PROGRAM` of that name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+
+    /// Task period.
+    #[serde(with = "humantime_serde")]
+    pub period: Duration,
+
+    /// Priority among tasks released at the same time; 0 is the highest.
+    /// Tasks of equal priority run shortest period first.
+    #[serde(default)]
+    pub priority: u32,
+
+    /// Overrun tolerated before the overrun policy applies. Defaults to
+    /// the global `max_overrun`.
+    #[serde(
+        default,
+        with = "humantime_serde::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_overrun: Option<Duration>,
+
+    /// Overrun policy. Defaults to `fault_policy.on_overrun`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_overrun: Option<OverrunPolicy>,
+
+    /// Watchdog timeout. Defaults to three periods.
+    #[serde(
+        default,
+        with = "humantime_serde::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub watchdog_timeout: Option<Duration>,
+}
+
+impl TaskConfig {
+    /// Exported function the task calls.
+    pub fn entry(&self) -> &str {
+        self.entry.as_deref().unwrap_or(&self.name)
+    }
+}
+
 impl RuntimeConfig {
+    /// The tasks to schedule: the configured `tasks`, or a single task
+    /// `main` running `step` every `cycle_time` with the global overrun
+    /// and watchdog settings.
+    pub fn scheduled_tasks(&self) -> Vec<TaskConfig> {
+        if !self.tasks.is_empty() {
+            return self.tasks.clone();
+        }
+        vec![TaskConfig {
+            name: "main".into(),
+            entry: Some("step".into()),
+            period: self.cycle_time,
+            priority: 0,
+            max_overrun: None,
+            on_overrun: None,
+            watchdog_timeout: Some(self.watchdog_timeout),
+        }]
+    }
+
     /// Load configuration from a TOML file.
     ///
     /// # Errors
@@ -497,7 +911,131 @@ impl RuntimeConfig {
     ///
     /// Returns an error if the TOML is invalid.
     pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
-        toml::from_str(content).map_err(ConfigError::Parse)
+        let config: Self = toml::from_str(content).map_err(ConfigError::Parse)?;
+        config.check_tasks()?;
+        config.check_engine()?;
+        config.check_redundancy()?;
+        config.check_trusted_keys()?;
+        Ok(config)
+    }
+
+    /// Reject trusted keys that are not 32-byte hex strings.
+    fn check_trusted_keys(&self) -> Result<(), ConfigError> {
+        for key in &self.wasm.trusted_keys {
+            if key.len() != 64 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ConfigError::Invalid(format!(
+                    "wasm.trusted_keys entry '{key}' is not a 64-digit hex Ed25519 public key"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject a redundancy section that cannot form a pair.
+    fn check_redundancy(&self) -> Result<(), ConfigError> {
+        let redundancy = &self.redundancy;
+        if !redundancy.enabled {
+            return Ok(());
+        }
+        if redundancy.listen.is_some() == redundancy.connect.is_some() {
+            return Err(ConfigError::Invalid(
+                "redundancy needs exactly one of listen and connect".into(),
+            ));
+        }
+        if redundancy.takeover_cycles == 0 {
+            return Err(ConfigError::Invalid(
+                "redundancy.takeover_cycles must be at least 1".into(),
+            ));
+        }
+        if redundancy
+            .secret
+            .as_ref()
+            .map_or(true, |secret| secret.len() < 16)
+        {
+            return Err(ConfigError::Invalid(
+                "redundancy.secret must be set, with at least 16 characters".into(),
+            ));
+        }
+        if redundancy.witness.is_none() && !redundancy.allow_split_brain {
+            return Err(ConfigError::Invalid(
+                "redundancy needs a witness, or allow_split_brain = true to run without one".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reject engine settings that leave cycles unbounded.
+    fn check_engine(&self) -> Result<(), ConfigError> {
+        if self.wasm.engine == WasmEngine::Interpreter && !self.wasm.use_fuel {
+            return Err(ConfigError::Invalid(
+                "wasm.engine = \"interpreter\" has no epoch timeout and requires wasm.use_fuel"
+                    .into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reject tasks that cannot be scheduled.
+    fn check_tasks(&self) -> Result<(), ConfigError> {
+        for (i, task) in self.tasks.iter().enumerate() {
+            if task.name.is_empty() {
+                return Err(ConfigError::Invalid(format!("task {i} has no name")));
+            }
+            if task.period.is_zero() {
+                return Err(ConfigError::Invalid(format!(
+                    "task {} has a zero period",
+                    task.name
+                )));
+            }
+            if self.tasks[..i].iter().any(|t| t.name == task.name) {
+                return Err(ConfigError::Invalid(format!(
+                    "task {} is defined twice",
+                    task.name
+                )));
+            }
+        }
+        self.check_task_blocking()
+    }
+
+    /// Reject tasks that another task could hold up past their deadline.
+    ///
+    /// Tasks share one thread and are not preempted, so the worst a task
+    /// waits for others is one call of the slowest, bounded by the engine's
+    /// call budget.
+    fn check_task_blocking(&self) -> Result<(), ConfigError> {
+        let Some(fastest) = self.tasks.iter().min_by_key(|task| task.period) else {
+            return Ok(());
+        };
+        if self.tasks.len() < 2 {
+            return Ok(());
+        }
+        let Some(budget) = self.wasm.call_budget() else {
+            return Err(ConfigError::Invalid(
+                "several tasks need wasm.engine = \"wasmtime\": the interpreter does not bound \
+                 how long one task can hold up another"
+                    .into(),
+            ));
+        };
+        if budget > fastest.period {
+            return Err(ConfigError::Invalid(format!(
+                "a task may run for {budget:?} (wasm.max_epochs_per_cycle), longer than the \
+                 period of task {} ({:?}); tasks are not preempted, so lower \
+                 wasm.max_epochs_per_cycle or lengthen the period",
+                fastest.name, fastest.period
+            )));
+        }
+        for task in &self.tasks {
+            if let Some(timeout) = task.watchdog_timeout {
+                if timeout < task.period + budget {
+                    return Err(ConfigError::Invalid(format!(
+                        "watchdog timeout of task {} ({timeout:?}) does not cover its period \
+                         plus {budget:?} another task may run first",
+                        task.name
+                    )));
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Serialize configuration to TOML string.
@@ -530,6 +1068,10 @@ pub enum ConfigError {
     /// TOML serialization error.
     #[error("failed to serialize TOML: {0}")]
     Serialize(#[from] toml::ser::Error),
+
+    /// Semantically invalid configuration.
+    #[error("invalid configuration: {0}")]
+    Invalid(String),
 }
 
 /// Serde helper module for `Duration` using humantime format.
@@ -552,6 +1094,31 @@ mod humantime_serde {
         let s = String::deserialize(deserializer)?;
         humantime::parse_duration(&s).map_err(serde::de::Error::custom)
     }
+
+    /// The same for an optional `Duration`.
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::time::Duration;
+
+        pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match duration {
+                Some(duration) => super::serialize(duration, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Option::<String>::deserialize(deserializer)?
+                .map(|s| humantime::parse_duration(&s).map_err(serde::de::Error::custom))
+                .transpose()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -597,6 +1164,169 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_process_image_config() {
+        let config = RuntimeConfig::default();
+        assert_eq!(config.process_image.di_words(), 1);
+        assert_eq!(config.process_image.ai_channels(), 16);
+
+        let toml = r#"
+            [process_image]
+            digital_inputs = 400
+            analog_outputs = 4
+        "#;
+        let config = RuntimeConfig::from_toml(toml).unwrap();
+        assert_eq!(config.process_image.di_words(), 13);
+        assert_eq!(config.process_image.do_words(), 1);
+        assert_eq!(config.process_image.ao_channels(), 4);
+    }
+
+    #[test]
+    fn test_tasks() {
+        let config = RuntimeConfig::default();
+        let tasks = config.scheduled_tasks();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].entry(), "step");
+        assert_eq!(tasks[0].period, config.cycle_time);
+        assert_eq!(tasks[0].watchdog_timeout, Some(config.watchdog_timeout));
+
+        let toml = r#"
+            [[tasks]]
+            name = "Motion"
+            period = "1ms"
+            max_overrun = "100us"
+            on_overrun = "warn"
+
+            [[tasks]]
+            name = "housekeeping"
+            entry = "Housekeeping"
+            period = "100ms"
+            priority = 2
+        "#;
+        let config = RuntimeConfig::from_toml(toml).unwrap();
+        let tasks = config.scheduled_tasks();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].entry(), "Motion");
+        assert_eq!(tasks[0].max_overrun, Some(Duration::from_micros(100)));
+        assert_eq!(tasks[0].on_overrun, Some(OverrunPolicy::Warn));
+        assert_eq!(tasks[1].entry(), "Housekeeping");
+        assert_eq!(tasks[1].priority, 2);
+        assert_eq!(tasks[1].watchdog_timeout, None);
+
+        let roundtrip = RuntimeConfig::from_toml(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(roundtrip.tasks, config.tasks);
+
+        let twice = "[[tasks]]\nname = \"a\"\nperiod = \"1ms\"\n".repeat(2);
+        assert!(RuntimeConfig::from_toml(&twice).is_err());
+        let zero = "[[tasks]]\nname = \"a\"\nperiod = \"0s\"\n";
+        assert!(RuntimeConfig::from_toml(zero).is_err());
+    }
+
+    #[test]
+    fn test_task_blocking_limit() {
+        let tasks = |fast: &str, extra: &str| {
+            format!(
+                "{extra}\n[[tasks]]\nname = \"fast\"\nperiod = \"{fast}\"\n\
+                 [[tasks]]\nname = \"slow\"\nperiod = \"100ms\"\n"
+            )
+        };
+        assert_eq!(
+            RuntimeConfig::default().wasm.call_budget(),
+            Some(Duration::from_millis(1))
+        );
+
+        // The default budget of 1ms fits a 1ms task, not a 500us one
+        assert!(RuntimeConfig::from_toml(&tasks("1ms", "")).is_ok());
+        let err = RuntimeConfig::from_toml(&tasks("500us", "")).unwrap_err();
+        assert!(err.to_string().contains("task fast"), "{err}");
+        let tighter = "[wasm]\nmax_epochs_per_cycle = 40";
+        assert!(RuntimeConfig::from_toml(&tasks("500us", tighter)).is_ok());
+
+        // A single task is not held up by others
+        let single = "[[tasks]]\nname = \"fast\"\nperiod = \"100us\"\n";
+        assert!(RuntimeConfig::from_toml(single).is_ok());
+
+        // The interpreter has no time bound on a call
+        let interpreter = "[wasm]\nengine = \"interpreter\"";
+        assert!(RuntimeConfig::from_toml(&tasks("1ms", interpreter)).is_err());
+
+        // A watchdog must allow for the wait
+        let watchdog =
+            "[[tasks]]\nname = \"fast\"\nperiod = \"2ms\"\nwatchdog_timeout = \"2500us\"\n\
+                        [[tasks]]\nname = \"slow\"\nperiod = \"100ms\"\n";
+        assert!(RuntimeConfig::from_toml(watchdog).is_err());
+        let relaxed = watchdog.replace("2500us", "3ms");
+        assert!(RuntimeConfig::from_toml(&relaxed).is_ok());
+    }
+
+    #[test]
+    fn test_wasm_engine() {
+        assert_eq!(RuntimeConfig::default().wasm.engine, WasmEngine::Wasmtime);
+
+        let config = RuntimeConfig::from_toml("[wasm]\nengine = \"interpreter\"\n").unwrap();
+        assert_eq!(config.wasm.engine, WasmEngine::Interpreter);
+
+        let unbounded = "[wasm]\nengine = \"interpreter\"\nuse_fuel = false\n";
+        assert!(RuntimeConfig::from_toml(unbounded).is_err());
+    }
+
+    #[test]
+    fn test_redundancy_config() {
+        assert!(!RuntimeConfig::default().redundancy.enabled);
+
+        let config = RuntimeConfig::from_toml(
+            "[redundancy]\nenabled = true\nnode_id = 2\nrole = \"standby\"\n\
+             connect = \"unix:/run/plc/sync.sock\"\ntakeover_cycles = 5\n\
+             secret = \"0123456789abcdef\"\nwitness = \"10.0.0.3:7701\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.redundancy.node_id, 2);
+        assert_eq!(config.redundancy.role, RedundancyRole::Standby);
+        assert_eq!(config.redundancy.takeover_cycles, 5);
+
+        // Exactly one side listens
+        assert!(RuntimeConfig::from_toml("[redundancy]\nenabled = true\n").is_err());
+        let both = "[redundancy]\nenabled = true\nlisten = \"0.0.0.0:7700\"\n\
+                    connect = \"10.0.0.2:7700\"\n";
+        assert!(RuntimeConfig::from_toml(both).is_err());
+        let never = "[redundancy]\nenabled = true\nlisten = \"0.0.0.0:7700\"\n\
+                     takeover_cycles = 0\n";
+        assert!(RuntimeConfig::from_toml(never).is_err());
+
+        // The link is authenticated with a secret long enough to matter
+        let open = "[redundancy]\nenabled = true\nlisten = \"0.0.0.0:7700\"\n";
+        assert!(RuntimeConfig::from_toml(open).is_err());
+        let short = format!("{open}secret = \"abc\"\n");
+        assert!(RuntimeConfig::from_toml(&short).is_err());
+
+        // Without a witness only on explicit request
+        let alone = format!("{open}secret = \"0123456789abcdef\"\n");
+        assert!(RuntimeConfig::from_toml(&alone).is_err());
+        let split = format!("{alone}allow_split_brain = true\n");
+        assert!(
+            RuntimeConfig::from_toml(&split)
+                .unwrap()
+                .redundancy
+                .allow_split_brain
+        );
+    }
+
+    #[test]
+    fn test_trusted_keys_config() {
+        assert!(RuntimeConfig::default().wasm.trusted_keys.is_empty());
+
+        let key = "a".repeat(64);
+        let config =
+            RuntimeConfig::from_toml(&format!("[wasm]\ntrusted_keys = [\"{key}\"]\n")).unwrap();
+        assert_eq!(config.wasm.trusted_keys, vec![key]);
+
+        assert!(RuntimeConfig::from_toml("[wasm]\ntrusted_keys = [\"abc\"]\n").is_err());
+        let not_hex = "z".repeat(64);
+        assert!(
+            RuntimeConfig::from_toml(&format!("[wasm]\ntrusted_keys = [\"{not_hex}\"]\n")).is_err()
+        );
+    }
+
     #[test]
     fn test_cpu_affinity_variants() {
         let single: CpuAffinity = serde_json::from_str("3").unwrap();
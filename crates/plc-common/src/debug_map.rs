@@ -0,0 +1,212 @@
+//! Source-line debug map.
+//!
+//! The compiler records which source line each stretch of Wasm code was
+//! generated from in the [`SECTION_NAME`] custom section, so the runtime can
+//! report where a trap happened. The payload has one
+//! `offset line kind pou file` line per entry, where `offset` is the byte
+//! offset of the first instruction of the line within the module binary (the
+//! offset wasmtime reports in trap backtraces).
+
+use std::fmt::{self, Write};
+
+/// Custom section holding the line table.
+pub const SECTION_NAME: &str = "plc.debug_lines";
+
+/// Kind of Program Organization Unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PouKind {
+    /// `PROGRAM`.
+    Program,
+    /// `FUNCTION_BLOCK`.
+    FunctionBlock,
+    /// `FUNCTION`.
+    Function,
+}
+
+impl PouKind {
+    pub(crate) fn tag(self) -> &'static str {
+        match self {
+            PouKind::Program => "PROGRAM",
+            PouKind::FunctionBlock => "FB",
+            PouKind::Function => "FUNCTION",
+        }
+    }
+
+    pub(crate) fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "PROGRAM" => Some(PouKind::Program),
+            "FB" => Some(PouKind::FunctionBlock),
+            "FUNCTION" => Some(PouKind::Function),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PouKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+/// A position in the PLC source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Source file; empty if the compiler was given no file name.
+    pub file: String,
+    /// Line number (1-based).
+    pub line: u32,
+    /// Kind of the enclosing POU.
+    pub pou_kind: PouKind,
+    /// Name of the enclosing POU.
+    pub pou: String,
+}
+
+impl fmt::Display for SourceLocation {
+    /// Formats as `conveyor.st:42 in FB Conveyor`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.file.is_empty() {
+            write!(f, "line {}", self.line)?;
+        } else {
+            write!(f, "{}:{}", self.file, self.line)?;
+        }
+        write!(f, " in {} {}", self.pou_kind, self.pou)
+    }
+}
+
+/// One entry of the line table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEntry {
+    /// Module byte offset where the code for this line starts.
+    pub offset: u32,
+    /// Source position of that code.
+    pub location: SourceLocation,
+}
+
+/// Encode a line table as the section payload.
+pub fn encode_lines(entries: &[LineEntry]) -> Vec<u8> {
+    let mut out = String::new();
+    for entry in entries {
+        let loc = &entry.location;
+        let _ = writeln!(
+            out,
+            "{} {} {} {} {}",
+            entry.offset,
+            loc.line,
+            loc.pou_kind.tag(),
+            loc.pou,
+            loc.file
+        );
+    }
+    out.into_bytes()
+}
+
+/// Decode a section payload; malformed lines are skipped.
+pub fn decode_lines(payload: &[u8]) -> DebugMap {
+    let entries = String::from_utf8_lossy(payload)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, ' ');
+            let offset = fields.next()?.parse().ok()?;
+            let line = fields.next()?.parse().ok()?;
+            let pou_kind = PouKind::from_tag(fields.next()?)?;
+            let pou = fields.next()?.to_string();
+            let file = fields.next()?.to_string();
+            Some(LineEntry {
+                offset,
+                location: SourceLocation {
+                    file,
+                    line,
+                    pou_kind,
+                    pou,
+                },
+            })
+        })
+        .collect();
+    DebugMap::new(entries)
+}
+
+/// A line table ordered by code offset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugMap {
+    entries: Vec<LineEntry>,
+}
+
+impl DebugMap {
+    /// Build a map from entries in any order.
+    pub fn new(mut entries: Vec<LineEntry>) -> Self {
+        entries.sort_by_key(|entry| entry.offset);
+        Self { entries }
+    }
+
+    /// Whether the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries, ordered by offset.
+    pub fn entries(&self) -> &[LineEntry] {
+        &self.entries
+    }
+
+    /// The source line whose code contains the given module offset.
+    ///
+    /// The compiler starts every function with an entry for the POU
+    /// declaration, so an offset never resolves into a preceding function.
+    pub fn lookup(&self, offset: u32) -> Option<&SourceLocation> {
+        let index = self.entries.partition_point(|entry| entry.offset <= offset);
+        index
+            .checked_sub(1)
+            .map(|index| &self.entries[index].location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(offset: u32, line: u32, pou_kind: PouKind, pou: &str, file: &str) -> LineEntry {
+        LineEntry {
+            offset,
+            location: SourceLocation {
+                file: file.into(),
+                line,
+                pou_kind,
+                pou: pou.into(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_line_table_roundtrip() {
+        let entries = vec![
+            entry(120, 10, PouKind::FunctionBlock, "Conveyor", "conveyor.st"),
+            entry(131, 42, PouKind::FunctionBlock, "Conveyor", "conveyor.st"),
+            entry(150, 3, PouKind::Program, "Main", "my project/main.st"),
+            entry(160, 5, PouKind::Function, "Scale", ""),
+        ];
+        let payload = encode_lines(&entries);
+        assert!(payload.starts_with(b"120 10 FB Conveyor conveyor.st\n"));
+        let map = decode_lines(&payload);
+        assert_eq!(map.entries(), &entries[..]);
+        assert!(decode_lines(b"broken\n1 2 TASK x y\n").is_empty());
+    }
+
+    #[test]
+    fn test_lookup() {
+        let map = DebugMap::new(vec![
+            entry(150, 3, PouKind::Program, "Main", "main.st"),
+            entry(120, 10, PouKind::FunctionBlock, "Conveyor", "conveyor.st"),
+            entry(131, 42, PouKind::FunctionBlock, "Conveyor", "conveyor.st"),
+        ]);
+        assert_eq!(map.lookup(100), None);
+        assert_eq!(map.lookup(131).unwrap().line, 42);
+        assert_eq!(
+            map.lookup(140).unwrap().to_string(),
+            "conveyor.st:42 in FB Conveyor"
+        );
+        assert_eq!(map.lookup(500).unwrap().pou, "Main");
+
+        let unnamed = entry(0, 7, PouKind::Function, "Scale", "");
+        assert_eq!(unnamed.location.to_string(), "line 7 in FUNCTION Scale");
+    }
+}
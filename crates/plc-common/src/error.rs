@@ -41,6 +41,10 @@ pub enum PlcError {
     #[error("wasm trap: {0}")]
     WasmTrap(String),
 
+    /// Logic module refused by the signature check.
+    #[error("untrusted module: {0}")]
+    UntrustedModule(String),
+
     /// I/O operation error.
     #[error("I/O error: {0}")]
     IoError(String),
@@ -0,0 +1,277 @@
+//! Host functions a logic module can import.
+//!
+//! [`HOST_FUNCTIONS`] is the single description of the host API. The
+//! compiler derives the ST signatures and the Wasm imports from it, and the
+//! runtime registers exactly these functions with these Wasm types, so the
+//! two sides cannot disagree. Functions without a result are `VOID` in ST and
+//! can only be called as statements.
+
+use std::fmt;
+
+/// Wasm module name of all host imports.
+pub const MODULE: &str = "plc";
+
+/// IEC type of a host function parameter or result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostType {
+    /// `BOOL`, passed as `i32` 0 or 1.
+    Bool,
+    /// `INT`, passed as `i32`.
+    Int,
+    /// `DINT`, passed as `i32`.
+    Dint,
+    /// `LINT`, passed as `i64`.
+    Lint,
+    /// `USINT`, passed as `i32`.
+    Usint,
+    /// `UINT`, passed as `i32`.
+    Uint,
+    /// `UDINT`, passed as `i32` with the same bits.
+    Udint,
+    /// `REAL`, passed as `f32`.
+    Real,
+}
+
+/// Wasm value type of a [`HostType`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmValType {
+    /// `i32`.
+    I32,
+    /// `i64`.
+    I64,
+    /// `f32`.
+    F32,
+}
+
+impl HostType {
+    /// The Wasm value type the value is passed as.
+    pub fn wasm_type(self) -> WasmValType {
+        match self {
+            HostType::Bool
+            | HostType::Int
+            | HostType::Dint
+            | HostType::Usint
+            | HostType::Uint
+            | HostType::Udint => WasmValType::I32,
+            HostType::Lint => WasmValType::I64,
+            HostType::Real => WasmValType::F32,
+        }
+    }
+}
+
+impl fmt::Display for HostType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HostType::Bool => "BOOL",
+            HostType::Int => "INT",
+            HostType::Dint => "DINT",
+            HostType::Lint => "LINT",
+            HostType::Usint => "USINT",
+            HostType::Uint => "UINT",
+            HostType::Udint => "UDINT",
+            HostType::Real => "REAL",
+        })
+    }
+}
+
+impl fmt::Display for WasmValType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WasmValType::I32 => "i32",
+            WasmValType::I64 => "i64",
+            WasmValType::F32 => "f32",
+        })
+    }
+}
+
+/// One host function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostFunction {
+    /// Import name, which is also the ST name.
+    pub name: &'static str,
+    /// Parameter names and types.
+    pub params: &'static [(&'static str, HostType)],
+    /// Result type; `None` for `VOID`.
+    pub result: Option<HostType>,
+    /// One-line description.
+    pub doc: &'static str,
+}
+
+impl HostFunction {
+    /// Wasm parameter types.
+    pub fn wasm_params(&self) -> Vec<WasmValType> {
+        self.params.iter().map(|(_, ty)| ty.wasm_type()).collect()
+    }
+
+    /// Wasm result types (empty or one).
+    pub fn wasm_results(&self) -> Vec<WasmValType> {
+        self.result.iter().map(|ty| ty.wasm_type()).collect()
+    }
+}
+
+impl fmt::Display for HostFunction {
+    /// The ST signature, e.g. `write_do(bit : INT, value : BOOL) : VOID`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (i, (name, ty)) in self.params.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{name} : {ty}")?;
+        }
+        match self.result {
+            Some(ty) => write!(f, ") : {ty}"),
+            None => f.write_str(") : VOID"),
+        }
+    }
+}
+
+/// All host functions, in import order.
+pub const HOST_FUNCTIONS: &[HostFunction] = &[
+    HostFunction {
+        name: "read_di",
+        params: &[("bit", HostType::Int)],
+        result: Some(HostType::Int),
+        doc: "Read a digital input bit (0 or 1)",
+    },
+    HostFunction {
+        name: "write_do",
+        params: &[("bit", HostType::Int), ("value", HostType::Bool)],
+        result: None,
+        doc: "Write a digital output bit",
+    },
+    HostFunction {
+        name: "read_ai",
+        params: &[("channel", HostType::Int)],
+        result: Some(HostType::Int),
+        doc: "Read an analog input channel",
+    },
+    HostFunction {
+        name: "write_ao",
+        params: &[("channel", HostType::Int), ("value", HostType::Int)],
+        result: None,
+        doc: "Write an analog output channel",
+    },
+    HostFunction {
+        name: "read_bit",
+        params: &[("offset", HostType::Dint), ("bit", HostType::Int)],
+        result: Some(HostType::Bool),
+        doc: "Read bit `bit` (0-7) of the process-image byte at `offset`",
+    },
+    HostFunction {
+        name: "write_bit",
+        params: &[
+            ("offset", HostType::Dint),
+            ("bit", HostType::Int),
+            ("value", HostType::Bool),
+        ],
+        result: None,
+        doc: "Write bit `bit` (0-7) of the output byte at `offset`",
+    },
+    HostFunction {
+        name: "read_byte",
+        params: &[("offset", HostType::Dint)],
+        result: Some(HostType::Usint),
+        doc: "Read the process-image byte at `offset`",
+    },
+    HostFunction {
+        name: "write_byte",
+        params: &[("offset", HostType::Dint), ("value", HostType::Usint)],
+        result: None,
+        doc: "Write the output byte at `offset`",
+    },
+    HostFunction {
+        name: "read_word",
+        params: &[("offset", HostType::Dint)],
+        result: Some(HostType::Uint),
+        doc: "Read the little-endian 16-bit word at `offset`",
+    },
+    HostFunction {
+        name: "write_word",
+        params: &[("offset", HostType::Dint), ("value", HostType::Uint)],
+        result: None,
+        doc: "Write the little-endian 16-bit output word at `offset`",
+    },
+    HostFunction {
+        name: "read_dword",
+        params: &[("offset", HostType::Dint)],
+        result: Some(HostType::Udint),
+        doc: "Read the little-endian 32-bit word at `offset`",
+    },
+    HostFunction {
+        name: "write_dword",
+        params: &[("offset", HostType::Dint), ("value", HostType::Udint)],
+        result: None,
+        doc: "Write the little-endian 32-bit output word at `offset`",
+    },
+    HostFunction {
+        name: "read_real",
+        params: &[("offset", HostType::Dint)],
+        result: Some(HostType::Real),
+        doc: "Read the little-endian IEEE 754 float at `offset`",
+    },
+    HostFunction {
+        name: "write_real",
+        params: &[("offset", HostType::Dint), ("value", HostType::Real)],
+        result: None,
+        doc: "Write the little-endian IEEE 754 output float at `offset`",
+    },
+    HostFunction {
+        name: "get_cycle_time",
+        params: &[],
+        result: Some(HostType::Dint),
+        doc: "Cycle time in nanoseconds, capped at the DINT range",
+    },
+    HostFunction {
+        name: "get_cycle_count",
+        params: &[],
+        result: Some(HostType::Lint),
+        doc: "Number of completed cycles",
+    },
+    HostFunction {
+        name: "is_first_cycle",
+        params: &[],
+        result: Some(HostType::Bool),
+        doc: "TRUE in the first cycle after initialization",
+    },
+    HostFunction {
+        name: "log_message",
+        params: &[("ptr", HostType::Dint), ("len", HostType::Dint)],
+        result: None,
+        doc: "Log `len` bytes of UTF-8 text at linear-memory address `ptr`",
+    },
+];
+
+/// Look up a host function by name.
+pub fn find(name: &str) -> Option<&'static HostFunction> {
+    HOST_FUNCTIONS.iter().find(|func| func.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_functions() {
+        let write_do = find("write_do").unwrap();
+        assert_eq!(
+            write_do.to_string(),
+            "write_do(bit : INT, value : BOOL) : VOID"
+        );
+        assert_eq!(write_do.wasm_params(), [WasmValType::I32, WasmValType::I32]);
+        assert!(write_do.wasm_results().is_empty());
+        assert_eq!(
+            find("get_cycle_count").unwrap().wasm_results(),
+            [WasmValType::I64]
+        );
+        assert!(find("missing").is_none());
+
+        for (i, func) in HOST_FUNCTIONS.iter().enumerate() {
+            assert!(
+                HOST_FUNCTIONS[..i].iter().all(|f| f.name != func.name),
+                "duplicate host function {}",
+                func.name
+            );
+        }
+    }
+}
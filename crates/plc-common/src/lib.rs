@@ -1,11 +1,21 @@
 #![doc = "Common types shared across the vPLC workspace."]
 
+pub mod abi;
+pub mod build_cache;
+pub mod codec;
 pub mod config;
+pub mod debug_map;
 pub mod error;
+pub mod host_api;
 pub mod iec_types;
 pub mod metrics;
+pub mod sfc;
 pub mod state;
+pub mod symbols;
 pub mod time;
+pub mod unit_test;
+pub mod wasm_meta;
+pub mod wcet;
 
 pub use config::*;
 pub use error::*;
@@ -0,0 +1,65 @@
+//! Sequential Function Chart step metadata.
+//!
+//! The compiler stores the linear-memory address of every SFC step flag in
+//! the [`SECTION_NAME`] custom section, one `Pou.Step offset` line per step,
+//! so the runtime can report which steps are active.
+
+use std::fmt::Write;
+
+/// Custom section holding the step table.
+pub const SECTION_NAME: &str = "plc.sfc";
+
+/// Location of one step's activity flag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepFlag {
+    /// Qualified step name (`Pou.Step`).
+    pub name: String,
+    /// Byte offset of the BOOL flag in linear memory.
+    pub offset: u32,
+}
+
+/// Encode a step table as the section payload.
+pub fn encode_steps(steps: &[StepFlag]) -> Vec<u8> {
+    let mut out = String::new();
+    for step in steps {
+        let _ = writeln!(out, "{} {}", step.name, step.offset);
+    }
+    out.into_bytes()
+}
+
+/// Decode a section payload; malformed lines are skipped.
+pub fn decode_steps(payload: &[u8]) -> Vec<StepFlag> {
+    String::from_utf8_lossy(payload)
+        .lines()
+        .filter_map(|line| {
+            let (name, offset) = line.rsplit_once(' ')?;
+            Some(StepFlag {
+                name: name.to_string(),
+                offset: offset.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_table_roundtrip() {
+        let steps = vec![
+            StepFlag {
+                name: "Main.Idle".into(),
+                offset: 256,
+            },
+            StepFlag {
+                name: "Main.Fill".into(),
+                offset: 270,
+            },
+        ];
+        let payload = encode_steps(&steps);
+        assert_eq!(payload, b"Main.Idle 256\nMain.Fill 270\n");
+        assert_eq!(decode_steps(&payload), steps);
+        assert!(decode_steps(b"broken\nMain.Idle x\n").is_empty());
+    }
+}
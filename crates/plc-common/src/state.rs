@@ -3,6 +3,9 @@
 //! State transitions follow IEC-inspired lifecycle:
 //! BOOT → INIT → PRE_OP → RUN → FAULT → SAFE_STOP
 //!
+//! From RUN the PLC can enter DEBUG, where the scan continues but the logic
+//! only executes on command, and return to RUN from there.
+//!
 //! Fault transitions are allowed from most states to ensure
 //! rapid response to error conditions.
 
@@ -23,6 +26,9 @@ pub enum RuntimeState {
     PreOp,
     /// Normal cyclic operation.
     Run,
+    /// Scan continues but the logic only runs on command (single cycle,
+    /// N cycles, or until a breakpoint); outputs are held or safe.
+    Debug,
     /// Fault detected; outputs may be in undefined state.
     Fault,
     /// Safe shutdown: outputs set to safe values.
@@ -36,6 +42,7 @@ impl fmt::Display for RuntimeState {
             Self::Init => write!(f, "INIT"),
             Self::PreOp => write!(f, "PRE_OP"),
             Self::Run => write!(f, "RUN"),
+            Self::Debug => write!(f, "DEBUG"),
             Self::Fault => write!(f, "FAULT"),
             Self::SafeStop => write!(f, "SAFE_STOP"),
         }
@@ -46,7 +53,7 @@ impl RuntimeState {
     /// Check if a transition to `target` is valid from the current state.
     #[must_use]
     pub fn can_transition_to(&self, target: RuntimeState) -> bool {
-        use RuntimeState::{Boot, Fault, Init, PreOp, Run, SafeStop};
+        use RuntimeState::{Boot, Debug, Fault, Init, PreOp, Run, SafeStop};
 
         matches!(
             (self, target),
@@ -59,9 +66,14 @@ impl RuntimeState {
                 | (Init, Fault)
                 | (PreOp, Fault)
                 | (Run, Fault)
+                | (Debug, Fault)
+                // Debugging, entered from and left to run
+                | (Run, Debug)
+                | (Debug, Run)
                 // Safe stop from fault or run
                 | (Fault, SafeStop)
                 | (Run, SafeStop)
+                | (Debug, SafeStop)
                 // Recovery: fault -> init to retry
                 | (Fault, Init)
                 // Restart after safe stop
@@ -90,6 +102,12 @@ impl RuntimeState {
         matches!(self, Self::PreOp | Self::Run)
     }
 
+    /// Returns true if the scan cycle is executing (RUN or DEBUG).
+    #[must_use]
+    pub fn is_scanning(&self) -> bool {
+        matches!(self, Self::Run | Self::Debug)
+    }
+
     /// Returns true if the PLC is in a fault or stopped state.
     #[must_use]
     pub fn is_stopped(&self) -> bool {
@@ -243,6 +261,24 @@ mod tests {
         assert_eq!(sm.previous_state(), Some(RuntimeState::PreOp));
     }
 
+    #[test]
+    fn test_debug_transitions() {
+        let mut sm = StateMachine::new();
+        sm.transition(RuntimeState::Init).unwrap();
+        sm.transition(RuntimeState::PreOp).unwrap();
+        // Debugging starts from a running PLC
+        assert!(sm.transition(RuntimeState::Debug).is_err());
+        sm.transition(RuntimeState::Run).unwrap();
+
+        sm.transition(RuntimeState::Debug).unwrap();
+        assert!(sm.state().is_scanning());
+        sm.transition(RuntimeState::Run).unwrap();
+        sm.transition(RuntimeState::Debug).unwrap();
+        sm.enter_fault();
+        assert_eq!(sm.state(), RuntimeState::Fault);
+        assert_eq!(sm.previous_state(), Some(RuntimeState::Debug));
+    }
+
     #[test]
     fn test_boot_to_fault() {
         // Boot failures should be able to transition directly to Fault
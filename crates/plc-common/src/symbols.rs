@@ -0,0 +1,190 @@
+//! Variable symbol table.
+//!
+//! The compiler describes every variable in linear memory in the
+//! [`SECTION_NAME`] custom section so tools can access variables by name
+//! instead of by address. The payload starts with a `v<version>` line,
+//! followed by one `name offset size direction retain type` line per
+//! variable. Names are qualified with their POU (`Main.speed`); globals are
+//! unqualified. The type comes last because it may contain spaces
+//! (`ARRAY[0..7] OF DINT`).
+
+use crate::error::{PlcError, PlcResult};
+use std::fmt::{self, Write};
+
+/// Custom section holding the symbol table.
+pub const SECTION_NAME: &str = "plc.symbols";
+
+/// Version of the payload format written by [`encode_symbols`].
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Which declaration block a variable comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// `VAR_INPUT`.
+    Input,
+    /// `VAR_OUTPUT`.
+    Output,
+    /// `VAR_IN_OUT`.
+    InOut,
+    /// `VAR`.
+    Local,
+    /// `VAR_TEMP`; only meaningful during a call.
+    Temp,
+    /// `VAR_GLOBAL`.
+    Global,
+}
+
+impl Direction {
+    fn tag(self) -> &'static str {
+        match self {
+            Direction::Input => "in",
+            Direction::Output => "out",
+            Direction::InOut => "inout",
+            Direction::Local => "local",
+            Direction::Temp => "temp",
+            Direction::Global => "global",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "in" => Some(Direction::Input),
+            "out" => Some(Direction::Output),
+            "inout" => Some(Direction::InOut),
+            "local" => Some(Direction::Local),
+            "temp" => Some(Direction::Temp),
+            "global" => Some(Direction::Global),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+/// One variable in linear memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// Qualified name (`Main.speed`).
+    pub name: String,
+    /// IEC type as written in the source (`INT`, `ARRAY[0..7] OF DINT`).
+    pub data_type: String,
+    /// Byte offset in linear memory.
+    pub offset: u32,
+    /// Size in bytes.
+    pub size: u32,
+    /// Declaration block.
+    pub direction: Direction,
+    /// Whether the variable is declared `RETAIN`.
+    pub retain: bool,
+}
+
+/// Encode a symbol table as the section payload.
+pub fn encode_symbols(symbols: &[Symbol]) -> Vec<u8> {
+    let mut out = format!("v{FORMAT_VERSION}\n");
+    for symbol in symbols {
+        let _ = writeln!(
+            out,
+            "{} {} {} {} {} {}",
+            symbol.name,
+            symbol.offset,
+            symbol.size,
+            symbol.direction.tag(),
+            u8::from(symbol.retain),
+            symbol.data_type
+        );
+    }
+    out.into_bytes()
+}
+
+/// Decode a section payload; malformed lines are skipped.
+///
+/// Fails if the payload was written in a newer format version.
+pub fn decode_symbols(payload: &[u8]) -> PlcResult<Vec<Symbol>> {
+    let text = String::from_utf8_lossy(payload);
+    let mut lines = text.lines();
+    let version = lines
+        .next()
+        .and_then(|header| header.strip_prefix('v'))
+        .and_then(|version| version.parse::<u32>().ok())
+        .ok_or_else(|| PlcError::Config("symbol table has no version header".into()))?;
+    if version > FORMAT_VERSION {
+        return Err(PlcError::Config(format!(
+            "symbol table version {version} is newer than supported version {FORMAT_VERSION}"
+        )));
+    }
+
+    Ok(lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(6, ' ');
+            let name = fields.next()?.to_string();
+            let offset = fields.next()?.parse().ok()?;
+            let size = fields.next()?.parse().ok()?;
+            let direction = Direction::from_tag(fields.next()?)?;
+            let retain = match fields.next()? {
+                "0" => false,
+                "1" => true,
+                _ => return None,
+            };
+            let data_type = fields.next()?.to_string();
+            Some(Symbol {
+                name,
+                data_type,
+                offset,
+                size,
+                direction,
+                retain,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_table_roundtrip() {
+        let symbols = vec![
+            Symbol {
+                name: "Main.speed".into(),
+                data_type: "INT".into(),
+                offset: 256,
+                size: 2,
+                direction: Direction::Local,
+                retain: true,
+            },
+            Symbol {
+                name: "Main.values".into(),
+                data_type: "ARRAY[0..7] OF DINT".into(),
+                offset: 260,
+                size: 32,
+                direction: Direction::Output,
+                retain: false,
+            },
+            Symbol {
+                name: "load".into(),
+                data_type: "REAL".into(),
+                offset: 292,
+                size: 4,
+                direction: Direction::Global,
+                retain: false,
+            },
+        ];
+        let payload = encode_symbols(&symbols);
+        assert!(payload.starts_with(b"v1\nMain.speed 256 2 local 1 INT\n"));
+        assert_eq!(decode_symbols(&payload).unwrap(), symbols);
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_versions() {
+        assert!(decode_symbols(b"").is_err());
+        assert!(decode_symbols(b"Main.speed 256 2 local 0 INT\n").is_err());
+        assert!(decode_symbols(b"v2\n").is_err());
+        let symbols = decode_symbols(b"v1\nbroken\nMain.x 1 1 sideways 0 BOOL\n").unwrap();
+        assert!(symbols.is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+//! Unit tests written in Structured Text.
+//!
+//! A test is a function block whose name starts with [`PREFIX`]. Its body
+//! runs once per scan cycle, either for a single cycle or, if it declares a
+//! [`DONE`] output, until it sets `DONE`. `ASSERT_TRUE(cond)` and
+//! `ASSERT_EQ(actual, expected)` record the first failing assertion in
+//! hidden variables of the test ([`FAILED`], [`ACTUAL`], [`EXPECTED`]),
+//! which the test runner reads by name through the symbol table.
+//!
+//! The compiler describes the test and its assertions in the
+//! [`SECTION_NAME`] custom section. The payload is tab-separated: a
+//! `test name line file` line followed by one
+//! `assert id line value_type message` line per assertion, where
+//! `value_type` is `-` if the values are not recorded.
+
+use crate::debug_map::{PouKind, SourceLocation};
+use std::fmt::Write;
+
+/// Custom section describing the test of a test module.
+pub const SECTION_NAME: &str = "plc.test";
+
+/// Name prefix marking a function block as a test.
+pub const PREFIX: &str = "TEST_";
+
+/// Optional `BOOL` output a multi-cycle test sets once it is finished.
+pub const DONE: &str = "DONE";
+
+/// Hidden `UDINT`: id of the first failed assertion, 0 while none failed.
+pub const FAILED: &str = "__test_failed";
+
+/// Hidden `LREAL`: actual value of the failed `ASSERT_EQ`.
+pub const ACTUAL: &str = "__test_actual";
+
+/// Hidden `LREAL`: expected value of the failed `ASSERT_EQ`.
+pub const EXPECTED: &str = "__test_expected";
+
+/// Whether a POU name marks a test.
+pub fn is_test(name: &str) -> bool {
+    name.get(..PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(PREFIX))
+}
+
+/// One assertion in a test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assertion {
+    /// Id stored in [`FAILED`] when the assertion fails; starts at 1.
+    pub id: u32,
+    /// Source line.
+    pub line: u32,
+    /// Type of the compared values if they are recorded in [`ACTUAL`] and
+    /// [`EXPECTED`].
+    pub value_type: Option<String>,
+    /// The assertion as written (`ASSERT_EQ(count, 5)`).
+    pub message: String,
+}
+
+/// A test function block and its assertions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestCase {
+    /// Function block name.
+    pub name: String,
+    /// Source file, if known.
+    pub file: String,
+    /// Line of the declaration.
+    pub line: u32,
+    /// Assertions in id order.
+    pub assertions: Vec<Assertion>,
+}
+
+impl TestCase {
+    /// Source location of a line in the test.
+    pub fn location(&self, line: u32) -> SourceLocation {
+        SourceLocation {
+            file: self.file.clone(),
+            line,
+            pou_kind: PouKind::FunctionBlock,
+            pou: self.name.clone(),
+        }
+    }
+
+    /// Look up an assertion by id.
+    pub fn assertion(&self, id: u32) -> Option<&Assertion> {
+        self.assertions.iter().find(|a| a.id == id)
+    }
+}
+
+/// Encode a test as the section payload.
+pub fn encode_test(test: &TestCase) -> Vec<u8> {
+    let mut out = format!("test\t{}\t{}\t{}\n", test.name, test.line, test.file);
+    for assertion in &test.assertions {
+        let _ = writeln!(
+            out,
+            "assert\t{}\t{}\t{}\t{}",
+            assertion.id,
+            assertion.line,
+            assertion.value_type.as_deref().unwrap_or("-"),
+            assertion.message
+        );
+    }
+    out.into_bytes()
+}
+
+/// Decode a section payload; returns `None` if the test line is missing.
+pub fn decode_test(payload: &[u8]) -> Option<TestCase> {
+    let text = String::from_utf8_lossy(payload);
+    let mut lines = text.lines();
+    let mut fields = lines.next()?.strip_prefix("test\t")?.splitn(3, '\t');
+    let name = fields.next()?.to_string();
+    let line = fields.next()?.parse().ok()?;
+    let file = fields.next().unwrap_or_default().to_string();
+
+    let assertions = lines
+        .filter_map(|line| {
+            let mut fields = line.strip_prefix("assert\t")?.splitn(4, '\t');
+            let id = fields.next()?.parse().ok()?;
+            let line = fields.next()?.parse().ok()?;
+            let value_type = match fields.next()? {
+                "-" => None,
+                ty => Some(ty.to_string()),
+            };
+            let message = fields.next()?.to_string();
+            Some(Assertion {
+                id,
+                line,
+                value_type,
+                message,
+            })
+        })
+        .collect();
+
+    Some(TestCase {
+        name,
+        file,
+        line,
+        assertions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_case_roundtrip() {
+        let test = TestCase {
+            name: "TEST_Counter".into(),
+            file: "tests/counter.st".into(),
+            line: 3,
+            assertions: vec![
+                Assertion {
+                    id: 1,
+                    line: 8,
+                    value_type: Some("INT".into()),
+                    message: "ASSERT_EQ(Counter.count, 5)".into(),
+                },
+                Assertion {
+                    id: 2,
+                    line: 9,
+                    value_type: None,
+                    message: "ASSERT_TRUE(Counter.done)".into(),
+                },
+            ],
+        };
+        let decoded = decode_test(&encode_test(&test)).unwrap();
+        assert_eq!(decoded, test);
+        assert_eq!(
+            decoded.location(8).to_string(),
+            "tests/counter.st:8 in FB TEST_Counter"
+        );
+        assert_eq!(decode_test(b"assert\t1\t2\t-\tx\n"), None);
+    }
+
+    #[test]
+    fn test_is_test() {
+        assert!(is_test("TEST_Counter"));
+        assert!(is_test("test_counter"));
+        assert!(!is_test("Tester"));
+        assert!(!is_test("TEST"));
+    }
+}
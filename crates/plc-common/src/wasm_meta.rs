@@ -0,0 +1,141 @@
+//! Custom-section metadata embedded in compiled logic modules.
+//!
+//! The compiler attaches descriptive data to the Wasm binary as custom
+//! sections; the runtime reads them back without a full Wasm parser.
+
+/// Find the payload of the first custom section called `name`.
+///
+/// Returns `None` if the bytes are not a Wasm module, are truncated, or the
+/// section is absent.
+pub fn custom_section<'a>(wasm: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    let mut rest = wasm.strip_prefix(b"\0asm")?.get(4..)?;
+    while !rest.is_empty() {
+        let id = rest[0];
+        let (size, len) = read_leb_u32(&rest[1..])?;
+        let start = 1 + len;
+        let payload = rest.get(start..start + size as usize)?;
+        rest = &rest[start + size as usize..];
+
+        if id == 0 {
+            let (name_len, len) = read_leb_u32(payload)?;
+            let section_name = payload.get(len..len + name_len as usize)?;
+            if section_name == name.as_bytes() {
+                return Some(&payload[len + name_len as usize..]);
+            }
+        }
+    }
+    None
+}
+
+/// Find a custom section called `name` that is the last section of the
+/// module, returning the offset it starts at and its payload.
+///
+/// Returns `None` if the bytes are not a well-formed module or its last
+/// section is another one.
+pub fn trailing_custom_section<'a>(wasm: &'a [u8], name: &str) -> Option<(usize, &'a [u8])> {
+    let mut rest = wasm.strip_prefix(b"\0asm")?.get(4..)?;
+    let mut last = None;
+    while !rest.is_empty() {
+        let start = wasm.len() - rest.len();
+        let id = rest[0];
+        let (size, len) = read_leb_u32(&rest[1..])?;
+        let payload = rest.get(1 + len..1 + len + size as usize)?;
+        rest = &rest[1 + len + size as usize..];
+
+        last = None;
+        if id == 0 {
+            let (name_len, len) = read_leb_u32(payload)?;
+            let section_name = payload.get(len..len + name_len as usize)?;
+            if section_name == name.as_bytes() {
+                last = Some((start, &payload[len + name_len as usize..]));
+            }
+        }
+    }
+    last
+}
+
+/// Append a custom section called `name` to `wasm`.
+pub fn append_custom_section(wasm: &mut Vec<u8>, name: &str, payload: &[u8]) {
+    let mut name_len = Vec::new();
+    write_leb_u32(&mut name_len, name.len() as u32);
+    wasm.push(0);
+    write_leb_u32(wasm, (name_len.len() + name.len() + payload.len()) as u32);
+    wasm.extend_from_slice(&name_len);
+    wasm.extend_from_slice(name.as_bytes());
+    wasm.extend_from_slice(payload);
+}
+
+fn write_leb_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_leb_u32(bytes: &[u8]) -> Option<(u32, usize)> {
+    let mut value = 0u32;
+    for (i, byte) in bytes.iter().take(5).enumerate() {
+        value |= u32::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module_with_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+        // An empty type section before the custom section.
+        bytes.extend_from_slice(&[1, 1, 0]);
+        bytes.push(0);
+        bytes.push((1 + name.len() + payload.len()) as u8);
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn test_custom_section_found() {
+        let wasm = module_with_section("plc.test", b"hello");
+        assert_eq!(custom_section(&wasm, "plc.test"), Some(&b"hello"[..]));
+        assert_eq!(custom_section(&wasm, "other"), None);
+    }
+
+    #[test]
+    fn test_trailing_custom_section() {
+        let mut wasm = module_with_section("plc.test", b"hello");
+        assert_eq!(custom_section(&wasm, "plc.test"), Some(&b"hello"[..]));
+        assert_eq!(
+            trailing_custom_section(&wasm, "plc.test"),
+            Some((11, &b"hello"[..]))
+        );
+
+        // Only the last section counts
+        let end = wasm.len();
+        append_custom_section(&mut wasm, "plc.other", &[7; 200]);
+        assert_eq!(trailing_custom_section(&wasm, "plc.test"), None);
+        assert_eq!(
+            trailing_custom_section(&wasm, "plc.other"),
+            Some((end, &[7; 200][..]))
+        );
+        assert_eq!(custom_section(&wasm, "plc.other"), Some(&[7; 200][..]));
+    }
+
+    #[test]
+    fn test_custom_section_rejects_garbage() {
+        assert_eq!(custom_section(b"not wasm", "plc.test"), None);
+        let mut wasm = module_with_section("plc.test", b"hello");
+        wasm.truncate(wasm.len() - 2);
+        assert_eq!(custom_section(&wasm, "plc.test"), None);
+    }
+}
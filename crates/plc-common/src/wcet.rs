@@ -0,0 +1,119 @@
+//! Worst-case execution estimate.
+//!
+//! The compiler stores an upper bound on the fuel one `step` call can
+//! consume in the [`SECTION_NAME`] custom section, so deployment tools can
+//! check it against the configured fuel budget and cycle time. The payload
+//! is tab-separated: a `fuel <units>` line (`fuel unbounded` if no bound
+//! exists), followed by one `unbounded line kind pou file reason` line per
+//! construct that prevents a bound.
+
+use crate::debug_map::{PouKind, SourceLocation};
+use std::fmt::Write;
+
+/// Custom section holding the estimate.
+pub const SECTION_NAME: &str = "plc.wcet";
+
+/// A construct without a static execution bound.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unbounded {
+    /// Where it is.
+    pub location: SourceLocation,
+    /// Why it has no bound (`loop has no iteration bound`).
+    pub reason: String,
+}
+
+/// Fuel estimate for one `step` call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FuelEstimate {
+    /// Upper bound on fuel units, or `None` if some path is unbounded.
+    pub step_fuel: Option<u64>,
+    /// Constructs that prevent a bound.
+    pub unbounded: Vec<Unbounded>,
+}
+
+/// Encode an estimate as the section payload.
+pub fn encode_estimate(estimate: &FuelEstimate) -> Vec<u8> {
+    let mut out = match estimate.step_fuel {
+        Some(fuel) => format!("fuel\t{fuel}\n"),
+        None => "fuel\tunbounded\n".to_string(),
+    };
+    for entry in &estimate.unbounded {
+        let loc = &entry.location;
+        let _ = writeln!(
+            out,
+            "unbounded\t{}\t{}\t{}\t{}\t{}",
+            loc.line,
+            loc.pou_kind.tag(),
+            loc.pou,
+            loc.file,
+            entry.reason
+        );
+    }
+    out.into_bytes()
+}
+
+/// Decode a section payload; returns `None` if the fuel line is missing.
+pub fn decode_estimate(payload: &[u8]) -> Option<FuelEstimate> {
+    let text = String::from_utf8_lossy(payload);
+    let mut lines = text.lines();
+    let step_fuel = match lines.next()?.strip_prefix("fuel\t")? {
+        "unbounded" => None,
+        fuel => Some(fuel.parse().ok()?),
+    };
+
+    let unbounded = lines
+        .filter_map(|line| {
+            let mut fields = line.strip_prefix("unbounded\t")?.splitn(5, '\t');
+            let line = fields.next()?.parse().ok()?;
+            let pou_kind = PouKind::from_tag(fields.next()?)?;
+            let pou = fields.next()?.to_string();
+            let file = fields.next()?.to_string();
+            let reason = fields.next()?.to_string();
+            Some(Unbounded {
+                location: SourceLocation {
+                    file,
+                    line,
+                    pou_kind,
+                    pou,
+                },
+                reason,
+            })
+        })
+        .collect();
+
+    Some(FuelEstimate {
+        step_fuel,
+        unbounded,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_roundtrip() {
+        let bounded = FuelEstimate {
+            step_fuel: Some(1234),
+            unbounded: Vec::new(),
+        };
+        assert_eq!(encode_estimate(&bounded), b"fuel\t1234\n");
+        assert_eq!(decode_estimate(b"fuel\t1234\n"), Some(bounded));
+
+        let unbounded = FuelEstimate {
+            step_fuel: None,
+            unbounded: vec![Unbounded {
+                location: SourceLocation {
+                    file: "my project/main.st".into(),
+                    line: 12,
+                    pou_kind: PouKind::Program,
+                    pou: "Main".into(),
+                },
+                reason: "loop has no iteration bound".into(),
+            }],
+        };
+        let payload = encode_estimate(&unbounded);
+        assert_eq!(decode_estimate(&payload), Some(unbounded));
+        assert_eq!(decode_estimate(b"garbage\n"), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! Incremental compilation.
+//!
+//! A [`BuildCache`] remembers the typed and lowered IR form of every POU.
+//! Entries are keyed by a hash of the POU's source, the memory offset its
+//! variables were allocated from and the global variables, and are only
+//! reused while the interfaces of the POUs it calls are unchanged. After an
+//! edit, a rebuild through [`Compiler::compile_sources_cached`] checks and
+//! lowers only the POUs that changed or depend on a changed interface.
+//!
+//! Typed and IR entries live in memory, for tools that rebuild a project
+//! repeatedly (the daemon recompiling on reload). With an
+//! [`ArtifactStore`], finished modules are also stored on disk, keyed by the
+//! whole project, so an unchanged project is not compiled at all, even by a
+//! new process.
+//!
+//! [`Compiler::compile_sources_cached`]: crate::Compiler::compile_sources_cached
+
+use crate::ir::IrFunction;
+use crate::optimize::OptLevel;
+use crate::typechecker::TypedPou;
+use crate::SourceFile;
+use plc_common::abi::{self, ProcessImageLayout};
+use plc_common::build_cache::{self, ArtifactStore, CacheKey};
+use plc_common::sfc::StepFlag;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Interface a POU was checked against.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Dependency {
+    /// Signature of a called function or function block.
+    Function(String),
+    /// Variables of a function block, accessed by a test as `Fb.var`.
+    FunctionBlock(String),
+}
+
+/// A type-checked POU.
+#[derive(Debug, Clone)]
+pub(crate) struct TypedEntry {
+    pub pou: TypedPou,
+    /// Next free memory offset after the POU's variables.
+    pub end_offset: usize,
+    /// Interfaces used, with their fingerprint at the time of the check.
+    pub deps: Vec<(Dependency, String)>,
+}
+
+/// The IR lowered from one POU.
+#[derive(Debug, Clone)]
+pub(crate) struct LoweredEntry {
+    pub functions: Vec<IrFunction>,
+    pub sfc_steps: Vec<StepFlag>,
+}
+
+/// What the last build reused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// POUs type checked.
+    pub checked: usize,
+    /// POUs whose typed form was reused.
+    pub checked_reused: usize,
+    /// POUs lowered to IR.
+    pub lowered: usize,
+    /// POUs whose IR was reused.
+    pub lowered_reused: usize,
+    /// Whether the whole module came from the artifact store.
+    pub module_reused: bool,
+}
+
+/// Per-POU results of earlier builds.
+#[derive(Debug, Default)]
+pub struct BuildCache {
+    store: Option<ArtifactStore>,
+    typed: HashMap<String, TypedEntry>,
+    lowered: HashMap<String, LoweredEntry>,
+    /// Keys used by the current build; everything else is dropped after it.
+    used: HashSet<String>,
+    stats: CacheStats,
+}
+
+impl BuildCache {
+    /// Create an in-memory cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a cache that also stores finished modules in `dir`.
+    pub fn open(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            store: Some(ArtifactStore::new(dir)),
+            ..Self::default()
+        }
+    }
+
+    /// The on-disk artifact store, if any.
+    pub fn store(&self) -> Option<&ArtifactStore> {
+        self.store.as_ref()
+    }
+
+    /// What the last build reused.
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub(crate) fn start_build(&mut self) {
+        self.used.clear();
+        self.stats = CacheStats::default();
+    }
+
+    /// Drop the entries the last build did not use, so that a long-running
+    /// process does not accumulate every version of every POU.
+    pub(crate) fn finish_build(&mut self) {
+        self.typed.retain(|key, _| self.used.contains(key));
+        self.lowered.retain(|key, _| self.used.contains(key));
+    }
+
+    pub(crate) fn typed(&mut self, key: &str) -> Option<&TypedEntry> {
+        let entry = self.typed.get(key)?;
+        self.used.insert(key.to_string());
+        Some(entry)
+    }
+
+    pub(crate) fn reused_typed(&mut self) {
+        self.stats.checked_reused += 1;
+    }
+
+    pub(crate) fn insert_typed(&mut self, key: String, entry: TypedEntry) {
+        self.stats.checked += 1;
+        self.used.insert(key.clone());
+        self.typed.insert(key, entry);
+    }
+
+    pub(crate) fn lowered(&mut self, key: &str) -> Option<&LoweredEntry> {
+        let entry = self.lowered.get(key)?;
+        self.used.insert(key.to_string());
+        self.stats.lowered_reused += 1;
+        Some(entry)
+    }
+
+    pub(crate) fn insert_lowered(&mut self, key: String, entry: LoweredEntry) {
+        self.stats.lowered += 1;
+        self.used.insert(key.clone());
+        self.lowered.insert(key, entry);
+    }
+
+    pub(crate) fn module(&mut self, key: &str) -> Option<Vec<u8>> {
+        let wasm = self.store.as_ref()?.get(build_cache::MODULE, key)?;
+        self.stats.module_reused = true;
+        Some(wasm)
+    }
+
+    pub(crate) fn insert_module(&self, key: &str, wasm: &[u8]) {
+        if let Some(store) = &self.store {
+            // The cache is an optimisation; a full disk must not fail the build.
+            if let Err(e) = store.put(build_cache::MODULE, key, wasm) {
+                warn!(error = %e, "Failed to store module in build cache");
+            }
+        }
+    }
+}
+
+/// Key of the module built from `sources`.
+pub(crate) fn module_key(
+    sources: &[SourceFile],
+    opt_level: OptLevel,
+    layout: &ProcessImageLayout,
+) -> String {
+    let mut key = CacheKey::new(build_cache::MODULE);
+    key.add(env!("CARGO_PKG_VERSION"))
+        .add(format!("{opt_level:?}"))
+        .add(abi::encode_layout(layout));
+    for file in sources {
+        key.add(&file.name)
+            .add(format!("{:?}", file.language))
+            .add(&file.source);
+    }
+    key.finish()
+}
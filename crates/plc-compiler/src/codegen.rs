@@ -2,19 +2,38 @@
 //!
 //! Generates Wasm binary using wasm-encoder from the IR module.
 
-use crate::ir::{Instruction, Module as IrModule};
+use crate::ir::{Instruction, IrFunction, Module as IrModule};
+use crate::wcet;
 use anyhow::{anyhow, Result};
+use plc_common::abi;
+use plc_common::debug_map::{self, LineEntry, PouKind, SourceLocation};
+use plc_common::host_api::{self, WasmValType};
+use plc_common::{sfc, symbols, unit_test};
+use std::collections::HashSet;
 use wasm_encoder::{
-    CodeSection, ExportKind, ExportSection, Function, FunctionSection, ImportSection,
-    Instruction as WasmInstr, MemorySection, MemoryType, Module, TypeSection, ValType,
+    CodeSection, CustomSection, ExportKind, ExportSection, Function, FunctionSection,
+    ImportSection, Instruction as WasmInstr, MemorySection, MemoryType, Module, Section,
+    TypeSection, ValType,
 };
 
+/// Export names the runtime gives a fixed meaning; programs with these names
+/// are not exported as task entry points.
+const RESERVED_EXPORTS: &[&str] = &["step", "init", "fault", "memory"];
+
 /// Emit WebAssembly binary from an IR module.
 pub fn emit(ir_module: &IrModule) -> Result<Vec<u8>> {
     let mut emitter = WasmEmitter::new();
     emitter.emit(ir_module)
 }
 
+fn val_type(ty: WasmValType) -> ValType {
+    match ty {
+        WasmValType::I32 => ValType::I32,
+        WasmValType::I64 => ValType::I64,
+        WasmValType::F32 => ValType::F32,
+    }
+}
+
 struct WasmEmitter {
     /// Type section.
     types: TypeSection,
@@ -34,6 +53,9 @@ struct WasmEmitter {
     host_funcs: std::collections::HashMap<String, u32>,
     /// User function indices (by name).
     user_funcs: std::collections::HashMap<String, u32>,
+    /// Per function, the body-relative byte offset where each source line's
+    /// code starts.
+    line_offsets: Vec<Vec<(usize, u32)>>,
 }
 
 impl WasmEmitter {
@@ -48,6 +70,7 @@ impl WasmEmitter {
             next_func_idx: 0,
             host_funcs: std::collections::HashMap::new(),
             user_funcs: std::collections::HashMap::new(),
+            line_offsets: Vec::new(),
         }
     }
 
@@ -56,10 +79,14 @@ impl WasmEmitter {
         self.define_types();
 
         // Import host functions
-        self.import_host_functions();
-
-        // Define memory (1 page = 64KB, matches IR memory_size)
-        let pages = ir_module.memory_size.div_ceil(0x10000) as u64;
+        self.import_host_functions(ir_module);
+
+        // Define memory (1 page = 64KB, matches IR memory_size), large enough
+        // for the process image
+        let pages = ir_module
+            .memory_size
+            .max(ir_module.layout.user_data_offset as usize)
+            .div_ceil(0x10000) as u64;
         self.memory.memory(MemoryType {
             minimum: pages.max(1),
             maximum: Some(pages.max(1)),
@@ -76,6 +103,7 @@ impl WasmEmitter {
         }
 
         // Generate code for each function
+        let mut step_exported = false;
         for func in &ir_module.functions {
             let type_idx = 0; // () -> () for step function
             self.functions.function(type_idx);
@@ -97,18 +125,39 @@ impl WasmEmitter {
 
             let mut f = Function::new(wasm_locals);
 
-            // Emit instructions
+            // Emit instructions, noting where each source line starts. The
+            // function itself starts at the POU declaration.
+            let mut lines = vec![(f.byte_len(), func.line)];
             for instr in &func.body {
+                if let Instruction::SourceLine(line) = instr {
+                    match lines.last_mut() {
+                        // A line that produced no code.
+                        Some(last) if last.0 == f.byte_len() => last.1 = *line,
+                        _ => lines.push((f.byte_len(), *line)),
+                    }
+                    continue;
+                }
                 self.emit_instruction(&mut f, instr)?;
             }
+            self.line_offsets.push(lines);
 
             f.instruction(&WasmInstr::End);
             self.code.function(&f);
 
-            // Export step function
-            if func.is_step {
+            // Export step function: the first program is the default task
+            if func.is_step && !step_exported {
                 self.exports
                     .export("step", ExportKind::Func, self.next_func_idx);
+                step_exported = true;
+            }
+
+            // Export each program under its own name as a task entry point
+            if func.is_step
+                && func.pou_kind == PouKind::Program
+                && !RESERVED_EXPORTS.contains(&func.pou.as_str())
+            {
+                self.exports
+                    .export(&func.pou, ExportKind::Func, self.next_func_idx);
             }
 
             self.next_func_idx += 1;
@@ -126,66 +175,113 @@ impl WasmEmitter {
         module.section(&self.exports);
         module.section(&self.code);
 
-        Ok(module.finish())
+        module.section(&CustomSection {
+            name: abi::SECTION_NAME.into(),
+            data: abi::encode_layout(&ir_module.layout).into(),
+        });
+
+        if !ir_module.sfc_steps.is_empty() {
+            module.section(&CustomSection {
+                name: sfc::SECTION_NAME.into(),
+                data: sfc::encode_steps(&ir_module.sfc_steps).into(),
+            });
+        }
+
+        module.section(&CustomSection {
+            name: plc_common::wcet::SECTION_NAME.into(),
+            data: plc_common::wcet::encode_estimate(&wcet::analyze(ir_module)).into(),
+        });
+
+        if let Some(test) = &ir_module.test {
+            module.section(&CustomSection {
+                name: unit_test::SECTION_NAME.into(),
+                data: unit_test::encode_test(test).into(),
+            });
+        }
+
+        if !ir_module.symbols.is_empty() {
+            module.section(&CustomSection {
+                name: symbols::SECTION_NAME.into(),
+                data: symbols::encode_symbols(&ir_module.symbols).into(),
+            });
+        }
+
+        // Code offsets are only known once the module is assembled, so the
+        // line table goes last.
+        let mut wasm = module.finish();
+        let lines = self.line_table(&wasm, &ir_module.functions)?;
+        if !lines.is_empty() {
+            CustomSection {
+                name: debug_map::SECTION_NAME.into(),
+                data: debug_map::encode_lines(&lines).into(),
+            }
+            .append_to(&mut wasm);
+        }
+
+        Ok(wasm)
+    }
+
+    /// Resolve the recorded line offsets to module offsets.
+    fn line_table(&self, wasm: &[u8], functions: &[IrFunction]) -> Result<Vec<LineEntry>> {
+        let mut bodies = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(wasm) {
+            if let wasmparser::Payload::CodeSectionEntry(body) = payload? {
+                bodies.push(body.range().start);
+            }
+        }
+
+        let mut entries = Vec::new();
+        for ((func, lines), start) in functions.iter().zip(&self.line_offsets).zip(bodies) {
+            for &(offset, line) in lines {
+                entries.push(LineEntry {
+                    offset: (start + offset) as u32,
+                    location: SourceLocation {
+                        file: func.file.clone(),
+                        line,
+                        pou_kind: func.pou_kind,
+                        pou: func.pou.clone(),
+                    },
+                });
+            }
+        }
+        Ok(entries)
     }
 
     fn define_types(&mut self) {
         // Type 0: () -> () for step function
         self.types.ty().function(vec![], vec![]);
-
-        // Type 1: (i32) -> i32 for read_di
-        self.types
-            .ty()
-            .function(vec![ValType::I32], vec![ValType::I32]);
-
-        // Type 2: (i32, i32) -> () for write_do
-        self.types
-            .ty()
-            .function(vec![ValType::I32, ValType::I32], vec![]);
-
-        // Type 3: () -> i32 for get_cycle_time
-        self.types.ty().function(vec![], vec![ValType::I32]);
     }
 
-    fn import_host_functions(&mut self) {
-        // Import PLC host functions
-        // read_di: (bit: i32) -> i32
-        self.imports
-            .import("plc", "read_di", wasm_encoder::EntityType::Function(1));
-        self.host_funcs
-            .insert("read_di".to_string(), self.next_func_idx);
-        self.next_func_idx += 1;
-
-        // write_do: (bit: i32, value: i32) -> ()
-        self.imports
-            .import("plc", "write_do", wasm_encoder::EntityType::Function(2));
-        self.host_funcs
-            .insert("write_do".to_string(), self.next_func_idx);
-        self.next_func_idx += 1;
-
-        // read_ai: (channel: i32) -> i32
-        self.imports
-            .import("plc", "read_ai", wasm_encoder::EntityType::Function(1));
-        self.host_funcs
-            .insert("read_ai".to_string(), self.next_func_idx);
-        self.next_func_idx += 1;
-
-        // write_ao: (channel: i32, value: i32) -> ()
-        self.imports
-            .import("plc", "write_ao", wasm_encoder::EntityType::Function(2));
-        self.host_funcs
-            .insert("write_ao".to_string(), self.next_func_idx);
-        self.next_func_idx += 1;
-
-        // get_cycle_time: () -> i32
-        self.imports.import(
-            "plc",
-            "get_cycle_time",
-            wasm_encoder::EntityType::Function(3),
-        );
-        self.host_funcs
-            .insert("get_cycle_time".to_string(), self.next_func_idx);
-        self.next_func_idx += 1;
+    /// Import the host functions the module calls, in table order.
+    fn import_host_functions(&mut self, ir_module: &IrModule) {
+        let called: HashSet<&str> = ir_module
+            .functions
+            .iter()
+            .flat_map(|func| &func.body)
+            .filter_map(|instr| match instr {
+                Instruction::CallHost(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        for host in host_api::HOST_FUNCTIONS {
+            if !called.contains(host.name) {
+                continue;
+            }
+            let type_idx = self.types.len();
+            self.types.ty().function(
+                host.wasm_params().into_iter().map(val_type),
+                host.wasm_results().into_iter().map(val_type),
+            );
+            self.imports.import(
+                host_api::MODULE,
+                host.name,
+                wasm_encoder::EntityType::Function(type_idx),
+            );
+            self.host_funcs
+                .insert(host.name.to_string(), self.next_func_idx);
+            self.next_func_idx += 1;
+        }
     }
 
     fn emit_instruction(&self, f: &mut Function, instr: &Instruction) -> Result<()> {
@@ -385,6 +481,26 @@ impl WasmEmitter {
                 f.instruction(&WasmInstr::I32Eqz);
             }
 
+            // i64 comparison
+            Instruction::I64Eq => {
+                f.instruction(&WasmInstr::I64Eq);
+            }
+            Instruction::I64Ne => {
+                f.instruction(&WasmInstr::I64Ne);
+            }
+            Instruction::I64LtS => {
+                f.instruction(&WasmInstr::I64LtS);
+            }
+            Instruction::I64LeS => {
+                f.instruction(&WasmInstr::I64LeS);
+            }
+            Instruction::I64GtS => {
+                f.instruction(&WasmInstr::I64GtS);
+            }
+            Instruction::I64GeS => {
+                f.instruction(&WasmInstr::I64GeS);
+            }
+
             // f32 comparison
             Instruction::F32Eq => {
                 f.instruction(&WasmInstr::F32Eq);
@@ -395,6 +511,15 @@ impl WasmEmitter {
             Instruction::F32Gt => {
                 f.instruction(&WasmInstr::F32Gt);
             }
+            Instruction::F32Ne => {
+                f.instruction(&WasmInstr::F32Ne);
+            }
+            Instruction::F32Le => {
+                f.instruction(&WasmInstr::F32Le);
+            }
+            Instruction::F32Ge => {
+                f.instruction(&WasmInstr::F32Ge);
+            }
 
             // f64 comparison
             Instruction::F64Eq => {
@@ -406,6 +531,15 @@ impl WasmEmitter {
             Instruction::F64Gt => {
                 f.instruction(&WasmInstr::F64Gt);
             }
+            Instruction::F64Ne => {
+                f.instruction(&WasmInstr::F64Ne);
+            }
+            Instruction::F64Le => {
+                f.instruction(&WasmInstr::F64Le);
+            }
+            Instruction::F64Ge => {
+                f.instruction(&WasmInstr::F64Ge);
+            }
 
             // Bitwise
             Instruction::I32And => {
@@ -449,6 +583,18 @@ impl WasmEmitter {
             Instruction::F32DemoteF64 => {
                 f.instruction(&WasmInstr::F32DemoteF64);
             }
+            Instruction::F32ConvertI64S => {
+                f.instruction(&WasmInstr::F32ConvertI64S);
+            }
+            Instruction::F64ConvertI64S => {
+                f.instruction(&WasmInstr::F64ConvertI64S);
+            }
+            Instruction::I64TruncF32S => {
+                f.instruction(&WasmInstr::I64TruncF32S);
+            }
+            Instruction::I64TruncF64S => {
+                f.instruction(&WasmInstr::I64TruncF64S);
+            }
 
             // Control flow
             Instruction::Br(depth) => {
@@ -506,6 +652,12 @@ impl WasmEmitter {
             Instruction::Nop => {
                 f.instruction(&WasmInstr::Nop);
             }
+
+            // Handled by the caller.
+            Instruction::SourceLine(_) => {}
+
+            // Only used by the execution-time analysis.
+            Instruction::LoopBound(_) => {}
         }
         Ok(())
     }
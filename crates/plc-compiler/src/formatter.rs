@@ -0,0 +1,1119 @@
+//! Canonical Structured Text formatter.
+//!
+//! Pretty-prints a [`CompilationUnit`] in a single canonical layout so that
+//! style never needs to be discussed in review:
+//!
+//! - Keywords, type names and boolean literals are upper-case; identifiers
+//!   keep the spelling they were declared with.
+//! - POU headers, `VAR` blocks and POU bodies start in column 0; nested
+//!   blocks and declarations are indented by [`FormatOptions::indent_width`].
+//! - Every declaration gets its own line (`a, b : INT;` is split), and runs
+//!   of consecutive declarations are aligned on `:` and on trailing comments.
+//! - Every statement is terminated with `;`, and empty statements are dropped.
+//! - Call statements and assignments of calls that exceed
+//!   [`FormatOptions::max_width`] are wrapped one argument per line.
+//! - At most one blank line is kept between items; POUs are separated by one.
+//!
+//! Comments are captured with [`parse_comments`] and re-attached by source
+//! position: a comment that shares a line with the preceding code stays a
+//! trailing comment, anything else is placed on its own line before the next
+//! item. Comments inside expressions are moved in front of the next item.
+//! Integer literals keep their spelling, including the radix and `_`
+//! separators; REAL and time literals are printed in canonical form.
+//!
+//! # Example
+//!
+//! ```
+//! use plc_compiler::formatter::{format_source, FormatOptions};
+//!
+//! let source = "program main var x:int:=0; end_var x:=x+1; end_program";
+//! let formatted = format_source(source, &FormatOptions::default()).unwrap();
+//! assert_eq!(
+//!     formatted,
+//!     "PROGRAM main\nVAR\n    x : INT := 0;\nEND_VAR\nx := x + 1;\nEND_PROGRAM\n"
+//! );
+//! ```
+
+use crate::frontend::{
+    parse, parse_comments, BinaryOp, CallArgument, CaseValue, Comment, CompilationUnit, Expression,
+    Literal, ProgramUnit, SfcElement, SfcNetwork, Span, Spanned, Statement, UnaryOp, VarBlock,
+};
+use anyhow::Result;
+
+/// Layout options for the formatter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Number of spaces per indentation level.
+    pub indent_width: usize,
+    /// Preferred maximum line width before call arguments are wrapped.
+    pub max_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            max_width: 100,
+        }
+    }
+}
+
+/// Format Structured Text source in canonical form, preserving comments.
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String> {
+    let unit = parse(source)?;
+    let comments = parse_comments(source)?;
+    let mut printer = Printer::new(options, source, comments);
+    printer.unit(&unit);
+    Ok(printer.finish())
+}
+
+/// Check whether source is already in canonical form.
+///
+/// Returns `Ok(false)` when [`format_source`] would change the text.
+pub fn is_formatted(source: &str, options: &FormatOptions) -> Result<bool> {
+    Ok(format_source(source, options)? == source)
+}
+
+/// Pretty-print a compilation unit without any source text.
+///
+/// Since the AST does not carry comments or blank lines, the output contains
+/// neither. Use [`format_source`] to reformat existing files.
+pub fn format_unit(unit: &CompilationUnit, options: &FormatOptions) -> String {
+    let mut printer = Printer::new(options, "", Vec::new());
+    printer.unit(unit);
+    printer.finish()
+}
+
+/// Pretty-print a statement list at column 0, as it would appear in a POU body.
+pub fn format_statements(statements: &[Spanned<Statement>], options: &FormatOptions) -> String {
+    let mut printer = Printer::new(options, "", Vec::new());
+    printer.statements(statements);
+    printer.finish()
+}
+
+/// Render an expression on a single line.
+pub fn format_expression(expression: &Expression) -> String {
+    expr(expression, "")
+}
+
+/// Binding strength of a binary operator (higher binds tighter).
+fn precedence(op: BinaryOp) -> u8 {
+    match op {
+        BinaryOp::Or | BinaryOp::BitOr => 1,
+        BinaryOp::Xor | BinaryOp::BitXor => 2,
+        BinaryOp::And | BinaryOp::BitAnd => 3,
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            4
+        }
+        BinaryOp::Add | BinaryOp::Sub => 5,
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 6,
+        BinaryOp::Pow => 7,
+        // Printed as function calls, see `expr`.
+        BinaryOp::Shl | BinaryOp::Shr => 9,
+    }
+}
+
+/// Precedence of an expression when used as an operand.
+fn expr_precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Binary { op, .. } => precedence(*op),
+        Expression::Unary { .. } => 8,
+        _ => 9,
+    }
+}
+
+/// Render an expression on a single line; `source` is the text it was
+/// parsed from, or empty.
+fn expr(e: &Expression, source: &str) -> String {
+    match e {
+        Expression::Literal(lit) => literal(lit),
+        Expression::Variable(name) => name.clone(),
+        Expression::ArrayAccess { array, index } => {
+            format!("{}[{}]", spanned(array, source), spanned(index, source))
+        }
+        Expression::FieldAccess { object, field } => {
+            format!("{}.{}", spanned(object, source), field)
+        }
+        Expression::Binary { left, op, right } => match op {
+            BinaryOp::Shl | BinaryOp::Shr => {
+                let name = if *op == BinaryOp::Shl { "SHL" } else { "SHR" };
+                format!(
+                    "{name}({}, {})",
+                    spanned(left, source),
+                    spanned(right, source)
+                )
+            }
+            _ => {
+                let prec = precedence(*op);
+                let lhs = operand(left, prec, false, source);
+                let rhs = operand(right, prec, true, source);
+                let symbol = match op {
+                    BinaryOp::BitAnd => "AND".to_string(),
+                    BinaryOp::BitOr => "OR".to_string(),
+                    BinaryOp::BitXor => "XOR".to_string(),
+                    other => other.to_string(),
+                };
+                format!("{lhs} {symbol} {rhs}")
+            }
+        },
+        Expression::Unary { op, operand } => {
+            // The grammar only accepts a primary expression after a unary operator.
+            let inner = if expr_precedence(&operand.node) < 9 {
+                format!("({})", spanned(operand, source))
+            } else {
+                spanned(operand, source)
+            };
+            match op {
+                UnaryOp::Neg => format!("-{inner}"),
+                UnaryOp::Not => format!("NOT {inner}"),
+            }
+        }
+        Expression::Call { name, arguments } => {
+            format!("{name}({})", arguments_inline(arguments, source))
+        }
+        Expression::Paren(inner) => format!("({})", spanned(inner, source)),
+    }
+}
+
+/// Render a parsed expression. An integer literal keeps the spelling it has
+/// in `source`, so `16#FF` is not turned into `255`.
+fn spanned(e: &Spanned<Expression>, source: &str) -> String {
+    if let Expression::Literal(Literal::Integer(value)) = e.node {
+        if let Some(text) = integer_lexeme(source, e.span, value) {
+            return text.to_string();
+        }
+    }
+    expr(&e.node, source)
+}
+
+/// The text at `span` if it is an integer literal denoting `value`, with
+/// its radix prefix and `_` separators.
+fn integer_lexeme(source: &str, span: Span, value: i64) -> Option<&str> {
+    let text = source.get(span.start..span.end)?;
+    let digits = text.replace('_', "");
+    let parsed = match digits.split_once('#') {
+        Some(("2", rest)) => i64::from_str_radix(rest, 2),
+        Some(("8", rest)) => i64::from_str_radix(rest, 8),
+        Some(("16", rest)) => i64::from_str_radix(rest, 16),
+        Some(_) => return None,
+        None => digits.parse(),
+    };
+    (parsed.ok()? == value).then_some(text)
+}
+
+/// Render a binary operand, parenthesising it where precedence requires.
+fn operand(e: &Spanned<Expression>, parent: u8, right: bool, source: &str) -> String {
+    let prec = expr_precedence(&e.node);
+    if prec < parent || (right && prec == parent) {
+        format!("({})", spanned(e, source))
+    } else {
+        spanned(e, source)
+    }
+}
+
+fn argument(arg: &CallArgument, source: &str) -> String {
+    match &arg.name {
+        Some(name) => format!("{name} := {}", spanned(&arg.value, source)),
+        None => spanned(&arg.value, source),
+    }
+}
+
+fn arguments_inline(args: &[CallArgument], source: &str) -> String {
+    args.iter()
+        .map(|arg| argument(arg, source))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format an SFC step list: a single name, or a parenthesized list for
+/// simultaneous divergence and convergence.
+fn sfc_steps(steps: &[String]) -> String {
+    match steps {
+        [single] => single.clone(),
+        _ => format!("({})", steps.join(", ")),
+    }
+}
+
+fn literal(lit: &Literal) -> String {
+    match lit {
+        Literal::Bool(true) => "TRUE".to_string(),
+        Literal::Bool(false) => "FALSE".to_string(),
+        Literal::Integer(n) => n.to_string(),
+        Literal::Real(r) => real_literal(*r),
+        Literal::String(s) => {
+            if s.contains('\'') {
+                format!("\"{s}\"")
+            } else {
+                format!("'{s}'")
+            }
+        }
+        Literal::Time(ns) => format!("T#{}", duration_units(*ns)),
+        Literal::Date { year, month, day } => format!("D#{year:04}-{month:02}-{day:02}"),
+        Literal::TimeOfDay(ns) => format!("TOD#{}", clock(*ns)),
+        Literal::DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            nanosecond,
+        } => {
+            let ns = ((i64::from(*hour) * 60 + i64::from(*minute)) * 60 + i64::from(*second))
+                * 1_000_000_000
+                + i64::from(*nanosecond);
+            format!("DT#{year:04}-{month:02}-{day:02}-{}", clock(ns))
+        }
+    }
+}
+
+/// Render a REAL literal so that it re-parses as a real (`digits.digits[e..]`).
+fn real_literal(value: f64) -> String {
+    let text = format!("{value:?}");
+    match text.find(['e', 'E']) {
+        Some(pos) if !text[..pos].contains('.') => {
+            format!("{}.0{}", &text[..pos], &text[pos..])
+        }
+        _ => text,
+    }
+}
+
+/// Render a duration as IEC time units, largest unit first (e.g. `1m30s`).
+fn duration_units(ns: i64) -> String {
+    const UNITS: [(&str, i64); 7] = [
+        ("d", 86_400_000_000_000),
+        ("h", 3_600_000_000_000),
+        ("m", 60_000_000_000),
+        ("s", 1_000_000_000),
+        ("ms", 1_000_000),
+        ("us", 1_000),
+        ("ns", 1),
+    ];
+
+    if ns == 0 {
+        return "0s".to_string();
+    }
+    let mut rest = ns.unsigned_abs();
+    let mut out = String::new();
+    for (unit, size) in UNITS {
+        let size = size.unsigned_abs();
+        if rest >= size {
+            out.push_str(&format!("{}{unit}", rest / size));
+            rest %= size;
+        }
+    }
+    out
+}
+
+/// Render nanoseconds since midnight as `hh:mm:ss[.fff]`.
+fn clock(ns: i64) -> String {
+    let secs = ns / 1_000_000_000;
+    let frac = ns % 1_000_000_000;
+    let base = format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    if frac == 0 {
+        base
+    } else {
+        let digits = format!("{frac:09}");
+        format!("{base}.{}", digits.trim_end_matches('0'))
+    }
+}
+
+/// One line of a run of aligned declarations.
+struct DeclLine {
+    name: String,
+    rest: String,
+    comment: Option<String>,
+}
+
+/// Line-oriented printer that threads comments through the AST walk.
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    /// Original source, or empty when printing a bare AST.
+    source: &'a str,
+    comments: Vec<Comment>,
+    /// Index of the next comment that has not been emitted.
+    next_comment: usize,
+    /// Source offset up to which the input has been consumed.
+    pos: usize,
+    lines: Vec<String>,
+    indent: usize,
+    /// Whether the last emitted line opened a block.
+    at_block_start: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(options: &'a FormatOptions, source: &'a str, comments: Vec<Comment>) -> Self {
+        Self {
+            options,
+            source,
+            comments,
+            next_comment: 0,
+            pos: 0,
+            lines: Vec::new(),
+            indent: 0,
+            at_block_start: true,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.leading(usize::MAX);
+        let mut out = self.lines.join("\n");
+        out.push('\n');
+        out
+    }
+
+    fn indent_str(&self) -> String {
+        " ".repeat(self.indent * self.options.indent_width)
+    }
+
+    fn line(&mut self, text: &str) {
+        let line = format!("{}{}", self.indent_str(), text);
+        self.lines.push(line.trim_end().to_string());
+        self.at_block_start = false;
+    }
+
+    /// Emit a loop's `{bound N}` pragma on its own line.
+    fn loop_bound(&mut self, bound: Option<u64>) {
+        if let Some(bound) = bound {
+            self.line(&format!("{{bound {bound}}}"));
+        }
+    }
+
+    /// Emit a block header and indent the following lines.
+    fn open(&mut self, text: &str) {
+        self.line(text);
+        self.indent += 1;
+        self.at_block_start = true;
+    }
+
+    fn dedent(&mut self) {
+        self.indent = self.indent.saturating_sub(1);
+    }
+
+    fn blank(&mut self) {
+        if !self.at_block_start && self.lines.last().is_some_and(|l| !l.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    /// Keep a single blank line if the source had one directly above `offset`.
+    fn gap(&mut self, offset: usize) {
+        let Some(before) = self.source.get(..offset) else {
+            return;
+        };
+        let newlines = before
+            .chars()
+            .rev()
+            .take_while(|c| c.is_whitespace())
+            .filter(|&c| c == '\n')
+            .count();
+        if newlines >= 2 {
+            self.blank();
+        }
+    }
+
+    /// Emit, on their own lines, all pending comments that start before `offset`.
+    fn leading(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.start >= offset {
+                break;
+            }
+            let (start, end, text) = (comment.span.start, comment.span.end, comment.text.clone());
+            self.gap(start);
+            self.line(&text);
+            self.pos = self.pos.max(end);
+            self.next_comment += 1;
+        }
+    }
+
+    /// Take the comments that follow `anchor` on the same source line.
+    fn take_trailing(&mut self, anchor: usize) -> Option<String> {
+        let mut parts = Vec::new();
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            let same_line = self
+                .source
+                .get(anchor..comment.span.start)
+                .is_some_and(|between| !between.contains('\n'));
+            if !same_line {
+                break;
+            }
+            parts.push(comment.text.clone());
+            self.pos = self.pos.max(comment.span.end);
+            self.next_comment += 1;
+        }
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// Append same-line comments after `anchor` to the last emitted line.
+    fn trailing(&mut self, anchor: usize) {
+        if let Some(text) = self.take_trailing(anchor) {
+            if let Some(last) = self.lines.last_mut() {
+                last.push(' ');
+                last.push_str(&text);
+            }
+        }
+    }
+
+    /// Locate the next occurrence of keyword `kw` at or after the cursor,
+    /// skipping comments and string literals.
+    fn find_keyword(&self, kw: &str) -> Option<usize> {
+        let bytes = self.source.as_bytes();
+        let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+        let mut i = self.pos;
+        while i < bytes.len() {
+            let rest = &bytes[i..];
+            if rest.starts_with(b"//") {
+                i += rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+            } else if rest.starts_with(b"(*") {
+                i += self.source[i..].find("*)").map_or(rest.len(), |p| p + 2);
+            } else if rest[0] == b'\'' || rest[0] == b'"' {
+                let quote = rest[0];
+                i += rest[1..]
+                    .iter()
+                    .position(|&b| b == quote)
+                    .map_or(rest.len(), |p| p + 2);
+            } else if is_ident(rest[0]) {
+                let len = rest.iter().take_while(|&&b| is_ident(b)).count();
+                if rest[..len].eq_ignore_ascii_case(kw.as_bytes()) {
+                    return Some(i);
+                }
+                i += len;
+            } else {
+                i += 1;
+            }
+        }
+        None
+    }
+
+    /// Flush comments that precede keyword `kw` and move the cursor past it.
+    ///
+    /// Returns the offset just after the keyword, if it was found.
+    fn consume_keyword(&mut self, kw: &str) -> Option<usize> {
+        let at = self.find_keyword(kw)?;
+        self.leading(at);
+        self.pos = at + kw.len();
+        Some(self.pos)
+    }
+
+    /// Close the current block with `kw` and open a sibling section.
+    fn section(&mut self, kw: &str, header: &str, anchor: Option<usize>) {
+        let kw_end = self.consume_keyword(kw);
+        self.dedent();
+        self.open(header);
+        if let Some(anchor) = anchor.or(kw_end) {
+            self.pos = self.pos.max(anchor);
+            self.trailing(anchor);
+        }
+    }
+
+    /// Close the current block with its `END_*` keyword.
+    fn end(&mut self, kw: &str, terminator: &str) {
+        let kw_end = self.consume_keyword(kw);
+        self.dedent();
+        self.line(&format!("{kw}{terminator}"));
+        if let Some(end) = kw_end {
+            self.trailing(end);
+        }
+    }
+
+    fn unit(&mut self, unit: &CompilationUnit) {
+        for (index, pou) in unit.units.iter().enumerate() {
+            if index > 0 {
+                self.at_block_start = false;
+                self.blank();
+            }
+            self.leading(pou.span.start);
+            self.gap(pou.span.start);
+            self.pos = pou.span.start;
+            self.pou(&pou.node, pou.span.start);
+            self.pos = self.pos.max(pou.span.end);
+        }
+    }
+
+    fn pou(&mut self, pou: &ProgramUnit, start: usize) {
+        let (header, end_kw, variables, body, sfc) = match pou {
+            ProgramUnit::Program(p) => (
+                format!("PROGRAM {}", p.name),
+                "END_PROGRAM",
+                &p.variables,
+                &p.body,
+                p.sfc.as_ref(),
+            ),
+            ProgramUnit::FunctionBlock(fb) => (
+                format!("FUNCTION_BLOCK {}", fb.name),
+                "END_FUNCTION_BLOCK",
+                &fb.variables,
+                &fb.body,
+                fb.sfc.as_ref(),
+            ),
+            ProgramUnit::Function(f) => (
+                format!("FUNCTION {} : {}", f.name, f.return_type),
+                "END_FUNCTION",
+                &f.variables,
+                &f.body,
+                None,
+            ),
+        };
+
+        self.line(&header);
+        self.trailing(start);
+        self.at_block_start = true;
+
+        for block in variables {
+            self.var_block(block);
+        }
+        match sfc {
+            Some(network) => self.sfc(network),
+            None => self.statements(body),
+        }
+
+        let kw_end = self.consume_keyword(end_kw);
+        self.line(end_kw);
+        if let Some(end) = kw_end {
+            self.trailing(end);
+        }
+    }
+
+    fn var_block(&mut self, block: &Spanned<VarBlock>) {
+        self.leading(block.span.start);
+        self.gap(block.span.start);
+
+        let mut header = block.node.kind.to_string();
+        if block.node.retain {
+            header.push_str(" RETAIN");
+        }
+        if block.node.constant {
+            header.push_str(" CONSTANT");
+        }
+        self.open(&header);
+        self.trailing(block.span.start);
+        self.pos = self.pos.max(block.span.start);
+
+        let mut group: Vec<DeclLine> = Vec::new();
+        for decl in &block.node.declarations {
+            let starts_group = self
+                .comments
+                .get(self.next_comment)
+                .is_some_and(|c| c.span.start < decl.span.start);
+            // Declarations split from one `a, b : T;` line share a span.
+            let has_gap =
+                decl.span.start >= self.pos && self.has_blank_line_before(decl.span.start);
+            if (starts_group || has_gap) && !group.is_empty() {
+                self.flush_decls(&mut group);
+            }
+            self.leading(decl.span.start);
+            if has_gap {
+                self.blank();
+            }
+
+            let mut rest = format!(": {}", decl.node.data_type);
+            if let Some(init) = &decl.node.initial_value {
+                rest.push_str(&format!(" := {}", spanned(init, self.source)));
+            }
+            rest.push(';');
+            self.pos = self.pos.max(decl.span.end);
+            let comment = self.take_trailing(decl.span.end);
+            group.push(DeclLine {
+                name: decl.node.name.clone(),
+                rest,
+                comment,
+            });
+        }
+        self.flush_decls(&mut group);
+
+        self.end("END_VAR", "");
+    }
+
+    fn has_blank_line_before(&self, offset: usize) -> bool {
+        self.source.get(..offset).is_some_and(|before| {
+            before
+                .chars()
+                .rev()
+                .take_while(|c| c.is_whitespace())
+                .filter(|&c| c == '\n')
+                .count()
+                >= 2
+        })
+    }
+
+    /// Emit a run of declarations aligned on `:` and on trailing comments.
+    fn flush_decls(&mut self, group: &mut Vec<DeclLine>) {
+        let name_width = group.iter().map(|d| d.name.len()).max().unwrap_or(0);
+        let texts: Vec<String> = group
+            .iter()
+            .map(|d| format!("{:name_width$} {}", d.name, d.rest))
+            .collect();
+        let comment_col = texts
+            .iter()
+            .zip(group.iter())
+            .filter(|(_, d)| d.comment.is_some())
+            .map(|(t, _)| t.len())
+            .max()
+            .unwrap_or(0);
+        for (text, decl) in texts.into_iter().zip(group.drain(..)) {
+            match decl.comment {
+                Some(comment) => self.line(&format!("{text:comment_col$} {comment}")),
+                None => self.line(&text),
+            }
+        }
+    }
+
+    fn sfc(&mut self, network: &SfcNetwork) {
+        for element in &network.elements {
+            let start = element.span.start;
+            self.leading(start);
+            self.gap(start);
+            self.pos = self.pos.max(start);
+
+            match &element.node {
+                SfcElement::Step(step) => {
+                    let kw = if step.initial { "INITIAL_STEP" } else { "STEP" };
+                    self.open(&format!("{kw} {}:", step.name));
+                    for assoc in &step.actions {
+                        let mut args = assoc.qualifier.to_string();
+                        if let Some(duration) = &assoc.duration {
+                            args.push_str(&format!(", {}", spanned(duration, self.source)));
+                        }
+                        self.line(&format!("{}({args});", assoc.action));
+                    }
+                    self.end("END_STEP", "");
+                }
+                SfcElement::Transition(transition) => {
+                    let mut header = String::from("TRANSITION");
+                    if let Some(name) = &transition.name {
+                        header.push_str(&format!(" {name}"));
+                    }
+                    if let Some(priority) = transition.priority {
+                        header.push_str(&format!(" (PRIORITY := {priority})"));
+                    }
+                    let cond = &transition.condition;
+                    header.push_str(&format!(
+                        " FROM {} TO {} := {};",
+                        sfc_steps(&transition.from),
+                        sfc_steps(&transition.to),
+                        spanned(cond, self.source)
+                    ));
+                    self.open(&header);
+                    self.pos = self.pos.max(cond.span.end);
+                    self.trailing(cond.span.end);
+                    self.end("END_TRANSITION", "");
+                }
+                SfcElement::Action(action) => {
+                    self.open(&format!("ACTION {}:", action.name));
+                    self.statements(&action.body);
+                    self.end("END_ACTION", "");
+                }
+            }
+            self.pos = self.pos.max(element.span.end);
+        }
+    }
+
+    fn statements(&mut self, statements: &[Spanned<Statement>]) {
+        for stmt in statements {
+            if matches!(stmt.node, Statement::Empty) {
+                continue;
+            }
+            self.leading(stmt.span.start);
+            self.gap(stmt.span.start);
+            self.pos = self.pos.max(stmt.span.start);
+            self.statement(stmt);
+            self.pos = self.pos.max(stmt.span.end);
+            self.trailing(stmt.span.end);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Spanned<Statement>) {
+        match &stmt.node {
+            Statement::Assignment(assign) => {
+                let target = spanned(&assign.target, self.source);
+                match &assign.value.node {
+                    Expression::Call { name, arguments } => {
+                        self.call(&format!("{target} := {name}"), arguments);
+                    }
+                    _ => self.line(&format!(
+                        "{target} := {};",
+                        spanned(&assign.value, self.source)
+                    )),
+                }
+            }
+            Statement::If(if_stmt) => {
+                let cond = &if_stmt.condition;
+                self.open(&format!("IF {} THEN", spanned(cond, self.source)));
+                self.pos = self.pos.max(cond.span.end);
+                self.trailing(cond.span.end);
+                self.statements(&if_stmt.then_branch);
+
+                for branch in &if_stmt.elsif_branches {
+                    let cond = &branch.condition;
+                    let header = format!("ELSIF {} THEN", spanned(cond, self.source));
+                    self.section("ELSIF", &header, Some(cond.span.end));
+                    self.statements(&branch.statements);
+                }
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.section("ELSE", "ELSE", None);
+                    self.statements(else_branch);
+                }
+                self.end("END_IF", ";");
+            }
+            Statement::Case(case) => {
+                let selector = &case.selector;
+                self.open(&format!("CASE {} OF", spanned(selector, self.source)));
+                self.pos = self.pos.max(selector.span.end);
+                self.trailing(selector.span.end);
+
+                for branch in &case.branches {
+                    let (first, last) = match (branch.values.first(), branch.values.last()) {
+                        (Some(first), Some(last)) => (first, last),
+                        _ => continue,
+                    };
+                    let start = match first {
+                        CaseValue::Single(v) | CaseValue::Range(v, _) => v.span.start,
+                    };
+                    let end = match last {
+                        CaseValue::Single(v) | CaseValue::Range(_, v) => v.span.end,
+                    };
+                    self.leading(start);
+                    self.gap(start);
+                    let label = branch
+                        .values
+                        .iter()
+                        .map(|value| match value {
+                            CaseValue::Single(v) => spanned(v, self.source),
+                            CaseValue::Range(lo, hi) => {
+                                format!(
+                                    "{}..{}",
+                                    spanned(lo, self.source),
+                                    spanned(hi, self.source)
+                                )
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    self.open(&format!("{label}:"));
+                    self.pos = self.pos.max(end);
+                    self.trailing(end);
+                    self.statements(&branch.statements);
+                    self.dedent();
+                }
+                if let Some(else_branch) = &case.else_branch {
+                    self.indent += 1;
+                    self.section("ELSE", "ELSE", None);
+                    self.statements(else_branch);
+                    self.dedent();
+                }
+                self.end("END_CASE", ";");
+            }
+            Statement::For(for_stmt) => {
+                let mut header = format!(
+                    "FOR {} := {} TO {}",
+                    for_stmt.variable,
+                    spanned(&for_stmt.from, self.source),
+                    spanned(&for_stmt.to, self.source)
+                );
+                let mut anchor = for_stmt.to.span.end;
+                if let Some(by) = &for_stmt.by {
+                    header.push_str(&format!(" BY {}", spanned(by, self.source)));
+                    anchor = by.span.end;
+                }
+                header.push_str(" DO");
+                self.loop_bound(for_stmt.bound);
+                self.open(&header);
+                self.pos = self.pos.max(anchor);
+                self.trailing(anchor);
+                self.statements(&for_stmt.body);
+                self.end("END_FOR", ";");
+            }
+            Statement::While(while_stmt) => {
+                let cond = &while_stmt.condition;
+                self.loop_bound(while_stmt.bound);
+                self.open(&format!("WHILE {} DO", spanned(cond, self.source)));
+                self.pos = self.pos.max(cond.span.end);
+                self.trailing(cond.span.end);
+                self.statements(&while_stmt.body);
+                self.end("END_WHILE", ";");
+            }
+            Statement::Repeat(repeat) => {
+                self.loop_bound(repeat.bound);
+                self.open("REPEAT");
+                if let Some(kw_end) = self.consume_keyword("REPEAT") {
+                    self.trailing(kw_end);
+                }
+                self.statements(&repeat.body);
+                let until = &repeat.until;
+                self.consume_keyword("UNTIL");
+                self.dedent();
+                self.line(&format!("UNTIL {}", spanned(until, self.source)));
+                self.pos = self.pos.max(until.span.end);
+                self.trailing(until.span.end);
+                self.indent += 1;
+                self.end("END_REPEAT", ";");
+            }
+            Statement::Exit => self.line("EXIT;"),
+            Statement::Continue => self.line("CONTINUE;"),
+            Statement::Return(None) => self.line("RETURN;"),
+            Statement::Return(Some(value)) => {
+                self.line(&format!("RETURN {};", spanned(value, self.source)));
+            }
+            Statement::Call(call) => self.call(&call.name, &call.arguments),
+            Statement::Empty => {}
+        }
+    }
+
+    /// Emit `prefix(args);`, wrapping one argument per line when too wide.
+    fn call(&mut self, prefix: &str, arguments: &[CallArgument]) {
+        let single = format!("{prefix}({});", arguments_inline(arguments, self.source));
+        let width = self.indent * self.options.indent_width + single.len();
+        if width <= self.options.max_width || arguments.len() < 2 {
+            self.line(&single);
+            return;
+        }
+
+        self.open(&format!("{prefix}("));
+        let last = arguments.len() - 1;
+        for (index, arg) in arguments.iter().enumerate() {
+            let sep = if index == last { "" } else { "," };
+            self.line(&format!("{}{sep}", argument(arg, self.source)));
+        }
+        self.dedent();
+        self.line(");");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::parse_expr;
+
+    fn fmt(source: &str) -> String {
+        format_source(source, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_keyword_casing_and_indentation() {
+        let source = "program Main\nvar\ncount:int:=0; flag:bool;\nend_var\n\
+                      if flag then count:=count+1; else count:=0; end_if\nend_program\n";
+        let expected = "\
+PROGRAM Main
+VAR
+    count : INT := 0;
+    flag  : BOOL;
+END_VAR
+IF flag THEN
+    count := count + 1;
+ELSE
+    count := 0;
+END_IF;
+END_PROGRAM
+";
+        assert_eq!(fmt(source), expected);
+    }
+
+    #[test]
+    fn test_comments_preserved() {
+        let source = "\
+(* header *)
+PROGRAM Main // main program
+VAR
+    a : INT; (* first *)
+    // section
+    long_name : BOOL := TRUE; (* second *)
+END_VAR
+(* before if *)
+IF a > 0 THEN // positive
+    a := 0;
+    // dangling
+ELSE
+    a := 1;
+END_IF; // done
+END_PROGRAM
+";
+        let formatted = fmt(source);
+        assert_eq!(formatted, source);
+        assert_eq!(
+            parse_comments(&formatted).unwrap().len(),
+            parse_comments(source).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_declarations_aligned_and_split() {
+        let source = "PROGRAM P\nVAR\n  a, bb : INT := 1; (* x *)\n  ccc : REAL; (* y *)\nEND_VAR\nEND_PROGRAM";
+        let expected = "\
+PROGRAM P
+VAR
+    a   : INT := 1; (* x *)
+    bb  : INT := 1;
+    ccc : REAL;     (* y *)
+END_VAR
+END_PROGRAM
+";
+        assert_eq!(fmt(source), expected);
+    }
+
+    #[test]
+    fn test_blank_lines_collapsed() {
+        let source =
+            "PROGRAM P\nVAR\n    a : INT;\nEND_VAR\n\n\n\na := 1;\n\n\n\na := 2;\nEND_PROGRAM\n";
+        assert_eq!(
+            fmt(source),
+            "PROGRAM P\nVAR\n    a : INT;\nEND_VAR\n\na := 1;\n\na := 2;\nEND_PROGRAM\n"
+        );
+    }
+
+    #[test]
+    fn test_long_call_is_wrapped() {
+        let options = FormatOptions {
+            max_width: 30,
+            ..FormatOptions::default()
+        };
+        let source = "PROGRAM P\nTimer(IN := start_button, PT := T#1s500ms);\nEND_PROGRAM";
+        let expected = "\
+PROGRAM P
+Timer(
+    IN := start_button,
+    PT := T#1s500ms
+);
+END_PROGRAM
+";
+        assert_eq!(format_source(source, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_case_and_loops() {
+        let source = "\
+PROGRAM P
+CASE state OF
+    0: (* idle *)
+        state := 1;
+    1, 2..4:
+        FOR i := 1 TO 10 BY 2 DO
+            x := x + i;
+        END_FOR;
+    ELSE
+        {bound 100}
+        REPEAT
+            x := x - 1;
+        UNTIL x < 0
+        END_REPEAT;
+END_CASE;
+{bound 10}
+WHILE x < 10 DO
+    x := x + 1;
+END_WHILE;
+END_PROGRAM
+";
+        assert_eq!(fmt(source), source);
+    }
+
+    #[test]
+    fn test_sfc_network() {
+        let source = "\
+program Chart
+initial_step Idle: end_step
+STEP Run:
+Motor(N); Horn(L, T#2s);
+END_STEP
+
+TRANSITION (PRIORITY:=1) FROM Idle TO (Run) := go; // start
+END_TRANSITION
+ACTION Motor:
+speed := 10;
+END_ACTION
+END_PROGRAM
+";
+        let expected = "\
+PROGRAM Chart
+INITIAL_STEP Idle:
+END_STEP
+STEP Run:
+    Motor(N);
+    Horn(L, T#2s);
+END_STEP
+
+TRANSITION (PRIORITY := 1) FROM Idle TO Run := go; // start
+END_TRANSITION
+ACTION Motor:
+    speed := 10;
+END_ACTION
+END_PROGRAM
+";
+        assert_eq!(fmt(source), expected);
+        assert_eq!(fmt(expected), expected);
+    }
+
+    #[test]
+    fn test_expression_parentheses_preserved() {
+        let source = "PROGRAM P\nx := (a + b) * c - -d;\ny := NOT (a AND b);\nEND_PROGRAM\n";
+        assert_eq!(fmt(source), source);
+    }
+
+    #[test]
+    fn test_literals_round_trip() {
+        let source =
+            "PROGRAM P\nt := T#1m30s;\nr := 1.5;\ns := 'text';\nb := FALSE;\nEND_PROGRAM\n";
+        assert_eq!(fmt(source), source);
+        assert_eq!(real_literal(1e-7), "1.0e-7");
+    }
+
+    #[test]
+    fn test_integer_literals_keep_their_spelling() {
+        let source = "PROGRAM P
+VAR
+    mask : WORD := 16#00FF;
+END_VAR
+flags := (flags AND 2#1010_0000) OR SHL(8#17, 1_000);
+CASE code OF
+    16#10..16#1F:
+        x := -16#FF;
+END_CASE;
+Log(level := 16#3, count := 42);
+END_PROGRAM
+";
+        assert_eq!(fmt(source), source);
+        assert_eq!(
+            format_expression(&parse_expr("16#FF + 2#11").unwrap()),
+            "255 + 3"
+        );
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent() {
+        for example in [
+            include_str!("../../../examples/blink.st"),
+            include_str!("../../../examples/motor_control.st"),
+            include_str!("../../../examples/pid_control.st"),
+            include_str!("../../../examples/state_machine.st"),
+        ] {
+            let once = fmt(example);
+            let twice = fmt(&once);
+            assert_eq!(once, twice);
+            assert!(is_formatted(&once, &FormatOptions::default()).unwrap());
+            assert_eq!(
+                parse_comments(&once).unwrap().len(),
+                parse_comments(example).unwrap().len()
+            );
+            assert_eq!(
+                crate::frontend::parse(&once).unwrap().units.len(),
+                crate::frontend::parse(example).unwrap().units.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_unit_without_source() {
+        let unit = crate::frontend::parse(
+            "FUNCTION Add : INT VAR_INPUT a : INT; b : INT; END_VAR Add := a + b; END_FUNCTION",
+        )
+        .unwrap();
+        let expected = "\
+FUNCTION Add : INT
+VAR_INPUT
+    a : INT;
+    b : INT;
+END_VAR
+Add := a + b;
+END_FUNCTION
+";
+        assert_eq!(format_unit(&unit, &FormatOptions::default()), expected);
+    }
+}
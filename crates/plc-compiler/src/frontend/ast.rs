@@ -60,11 +60,32 @@ impl<T> Spanned<T> {
     }
 }
 
+/// A source comment, as captured by [`parse_comments`](super::parse_comments).
+///
+/// Comments are not part of the syntax tree proper; tooling such as the
+/// formatter re-attaches them to nodes by position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// Comment text including its delimiters (`//` or `(* ... *)`).
+    pub text: String,
+    /// Source location.
+    pub span: Span,
+}
+
+impl Comment {
+    /// Whether this is a `(* ... *)` block comment.
+    pub fn is_block(&self) -> bool {
+        self.text.starts_with("(*")
+    }
+}
+
 /// Top-level compilation unit.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CompilationUnit {
     /// Program Organization Units (POUs).
     pub units: Vec<Spanned<ProgramUnit>>,
+    /// Configuration-level VAR_GLOBAL blocks, shared by all POUs.
+    pub globals: Vec<Spanned<VarBlock>>,
 }
 
 /// A Program Organization Unit (POU).
@@ -87,6 +108,8 @@ pub struct Program {
     pub variables: Vec<Spanned<VarBlock>>,
     /// Program body statements.
     pub body: Vec<Spanned<Statement>>,
+    /// Sequential Function Chart body, used instead of `body`.
+    pub sfc: Option<SfcNetwork>,
 }
 
 /// A FUNCTION_BLOCK declaration.
@@ -98,6 +121,8 @@ pub struct FunctionBlock {
     pub variables: Vec<Spanned<VarBlock>>,
     /// Function block body statements.
     pub body: Vec<Spanned<Statement>>,
+    /// Sequential Function Chart body, used instead of `body`.
+    pub sfc: Option<SfcNetwork>,
 }
 
 /// A FUNCTION declaration.
@@ -113,6 +138,128 @@ pub struct Function {
     pub body: Vec<Spanned<Statement>>,
 }
 
+/// A Sequential Function Chart in IEC 61131-3 textual form.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SfcNetwork {
+    /// Steps, transitions and actions in source order.
+    pub elements: Vec<Spanned<SfcElement>>,
+}
+
+impl SfcNetwork {
+    /// Iterate over the steps of the chart.
+    pub fn steps(&self) -> impl Iterator<Item = &SfcStep> {
+        self.elements.iter().filter_map(|e| match &e.node {
+            SfcElement::Step(step) => Some(step),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the transitions of the chart.
+    pub fn transitions(&self) -> impl Iterator<Item = &SfcTransition> {
+        self.elements.iter().filter_map(|e| match &e.node {
+            SfcElement::Transition(transition) => Some(transition),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the actions of the chart.
+    pub fn actions(&self) -> impl Iterator<Item = &SfcAction> {
+        self.elements.iter().filter_map(|e| match &e.node {
+            SfcElement::Action(action) => Some(action),
+            _ => None,
+        })
+    }
+}
+
+/// One element of an SFC network.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SfcElement {
+    /// STEP / INITIAL_STEP ... END_STEP.
+    Step(SfcStep),
+    /// TRANSITION ... END_TRANSITION.
+    Transition(SfcTransition),
+    /// ACTION ... END_ACTION.
+    Action(SfcAction),
+}
+
+/// An SFC step with its action associations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SfcStep {
+    /// Step name.
+    pub name: String,
+    /// Whether this is the INITIAL_STEP.
+    pub initial: bool,
+    /// Actions associated with the step.
+    pub actions: Vec<ActionAssociation>,
+}
+
+/// Association of an action with a step: `Fill(D, T#2s);`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionAssociation {
+    /// Action name, or a BOOL variable driven by the association.
+    pub action: String,
+    /// Action qualifier.
+    pub qualifier: ActionQualifier,
+    /// Duration for the time qualifiers (L, D).
+    pub duration: Option<Spanned<Expression>>,
+}
+
+/// SFC action qualifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActionQualifier {
+    /// Non-stored: active while the step is active.
+    #[default]
+    N,
+    /// Set (stored) until reset by an R association.
+    S,
+    /// Reset a stored action.
+    R,
+    /// Pulse: active for the scan in which the step becomes active.
+    P,
+    /// Time limited: active for at most the given duration.
+    L,
+    /// Time delayed: active once the step has been active for the duration.
+    D,
+}
+
+impl fmt::Display for ActionQualifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ActionQualifier::N => "N",
+            ActionQualifier::S => "S",
+            ActionQualifier::R => "R",
+            ActionQualifier::P => "P",
+            ActionQualifier::L => "L",
+            ActionQualifier::D => "D",
+        };
+        write!(f, "{text}")
+    }
+}
+
+/// An SFC transition between steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SfcTransition {
+    /// Optional transition name.
+    pub name: Option<String>,
+    /// Optional PRIORITY; lower values are evaluated first.
+    pub priority: Option<u32>,
+    /// Steps that must all be active (more than one for a convergence).
+    pub from: Vec<String>,
+    /// Steps activated when the transition fires (more than one for a divergence).
+    pub to: Vec<String>,
+    /// Transition condition.
+    pub condition: Spanned<Expression>,
+}
+
+/// A named SFC action body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SfcAction {
+    /// Action name.
+    pub name: String,
+    /// Action body.
+    pub body: Vec<Spanned<Statement>>,
+}
+
 /// Variable declaration block.
 #[derive(Debug, Clone, PartialEq)]
 pub struct VarBlock {
@@ -478,6 +625,8 @@ pub struct ForStatement {
     pub by: Option<Spanned<Expression>>,
     /// Loop body.
     pub body: Vec<Spanned<Statement>>,
+    /// Iteration bound from a `{bound N}` pragma.
+    pub bound: Option<u64>,
 }
 
 /// WHILE loop statement.
@@ -487,6 +636,8 @@ pub struct WhileStatement {
     pub condition: Spanned<Expression>,
     /// Loop body.
     pub body: Vec<Spanned<Statement>>,
+    /// Iteration bound from a `{bound N}` pragma.
+    pub bound: Option<u64>,
 }
 
 /// REPEAT loop statement.
@@ -496,6 +647,8 @@ pub struct RepeatStatement {
     pub body: Vec<Spanned<Statement>>,
     /// Exit condition.
     pub until: Spanned<Expression>,
+    /// Iteration bound from a `{bound N}` pragma.
+    pub bound: Option<u64>,
 }
 
 /// Function/FB call as a statement.
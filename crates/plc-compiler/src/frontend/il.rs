@@ -0,0 +1,789 @@
+//! Parser for IEC 61131-3 Instruction List.
+//!
+//! IL shares its declaration syntax with Structured Text, so POU headers and
+//! VAR blocks go through the ST parser helpers. Instruction bodies are
+//! translated into the ST AST by tracking the accumulator (the *current
+//! result*, CR) symbolically: `LD a  AND b  ST c` becomes `c := a AND b;`.
+//! The translated POUs then share the typechecker, IR and codegen with ST,
+//! so IL and ST POUs can be mixed freely in one project.
+//!
+//! Jumps have no ST equivalent. A body that uses them is split into blocks at
+//! labels and after every jump, and the blocks are dispatched by a
+//! `WHILE`/`CASE` state machine over a hidden program counter.
+
+use super::ast::*;
+use super::parser::{
+    parse_arguments, parse_data_type, parse_literal, parse_var_block, parse_variable,
+    span_from_pair, PairsExt, Rule, StParser,
+};
+use anyhow::{anyhow, Result};
+use pest::iterators::Pair;
+use pest::Parser;
+use std::collections::HashMap;
+
+/// Hidden BOOL temporary that holds a materialized current result.
+const CR_TEMP: &str = "__il_cr";
+/// Hidden DINT temporary that holds the block index of the jump state machine.
+const PC_TEMP: &str = "__il_pc";
+
+/// Parse Instruction List source code into an AST.
+pub fn parse_il(source: &str) -> Result<CompilationUnit> {
+    let pairs = StParser::parse(Rule::il_compilation_unit, source)
+        .map_err(|e| anyhow!("Parse error: {}", e))?;
+
+    let mut units = Vec::new();
+    for pair in pairs.flat_map(|p| p.into_inner()) {
+        if pair.as_rule() == Rule::il_pou {
+            let span = span_from_pair(&pair);
+            let inner = pair
+                .into_inner()
+                .next()
+                .ok_or_else(|| anyhow!("Expected program unit content"))?;
+            units.push(Spanned::new(parse_il_pou(inner)?, span));
+        }
+    }
+
+    Ok(CompilationUnit {
+        units,
+        globals: Vec::new(),
+    })
+}
+
+/// Translate a bare IL instruction list, without a surrounding POU; `pou` is
+/// only used in error messages.
+pub fn parse_il_body(pou: &str, source: &str) -> Result<IlBody> {
+    let body = StParser::parse(Rule::il_statement_body, source)
+        .map_err(|e| anyhow!("Parse error: {}", e))?
+        .next()
+        .and_then(|body| body.into_inner().next())
+        .ok_or_else(|| anyhow!("Expected instruction list"))?;
+    Translator::new(pou).translate(body)
+}
+
+fn parse_il_pou(pair: Pair<Rule>) -> Result<ProgramUnit> {
+    let rule = pair.as_rule();
+    let mut inner = pair.into_inner();
+    let name = inner.expect_next("POU name")?.as_str().to_string();
+
+    let mut return_type = None;
+    let mut variables = Vec::new();
+    let mut body = Vec::new();
+
+    for item in inner {
+        match item.as_rule() {
+            Rule::data_type => return_type = Some(parse_data_type(item)?),
+            Rule::var_block => {
+                let span = span_from_pair(&item);
+                variables.push(Spanned::new(parse_var_block(item)?, span));
+            }
+            Rule::il_body => {
+                let translated = Translator::new(&name).translate(item)?;
+                body = translated.body;
+                if !translated.temps.is_empty() {
+                    variables.push(Spanned::new(
+                        VarBlock {
+                            kind: VarBlockKind::Temp,
+                            retain: false,
+                            constant: false,
+                            declarations: translated.temps,
+                        },
+                        Span::default(),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match rule {
+        Rule::il_program => Ok(ProgramUnit::Program(Program {
+            name,
+            variables,
+            body,
+            sfc: None,
+        })),
+        Rule::il_function_block => Ok(ProgramUnit::FunctionBlock(FunctionBlock {
+            name,
+            variables,
+            body,
+            sfc: None,
+        })),
+        Rule::il_function => Ok(ProgramUnit::Function(Function {
+            name,
+            return_type: return_type.ok_or_else(|| anyhow!("Expected function return type"))?,
+            variables,
+            body,
+        })),
+        other => Err(anyhow!("Unexpected POU type: {:?}", other)),
+    }
+}
+
+/// How control leaves a basic block.
+enum BlockExit {
+    /// Continue with the next block in source order.
+    FallThrough,
+    /// Unconditional `JMP`.
+    Jump(String),
+    /// `JMPC` / `JMPCN`; the condition is already negated for `JMPCN`.
+    JumpIf(Expression, String),
+}
+
+struct Block {
+    label: Option<String>,
+    statements: Vec<Spanned<Statement>>,
+    exit: BlockExit,
+}
+
+impl Block {
+    fn new(label: Option<String>) -> Self {
+        Self {
+            label,
+            statements: Vec::new(),
+            exit: BlockExit::FallThrough,
+        }
+    }
+}
+
+/// A `(`-deferred operation waiting for its closing `)`.
+struct Deferred {
+    op: BinaryOp,
+    negate: bool,
+    left: Expression,
+}
+
+/// Result of translating one IL body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IlBody {
+    /// Equivalent ST statements.
+    pub body: Vec<Spanned<Statement>>,
+    /// Hidden VAR_TEMP declarations used by the statements.
+    pub temps: Vec<Spanned<VarDecl>>,
+}
+
+/// Translates an IL instruction sequence into ST statements.
+struct Translator<'a> {
+    pou: &'a str,
+    blocks: Vec<Block>,
+    cr: Option<Expression>,
+    deferred: Vec<Deferred>,
+    uses_cr_temp: bool,
+    has_jumps: bool,
+}
+
+impl<'a> Translator<'a> {
+    fn new(pou: &'a str) -> Self {
+        Self {
+            pou,
+            blocks: vec![Block::new(None)],
+            cr: None,
+            deferred: Vec::new(),
+            uses_cr_temp: false,
+            has_jumps: false,
+        }
+    }
+
+    fn translate(mut self, body: Pair<Rule>) -> Result<IlBody> {
+        for line in body.into_inner() {
+            for item in line.into_inner() {
+                match item.as_rule() {
+                    Rule::il_label => {
+                        let label = item.into_inner().expect_next("label name")?;
+                        self.start_block(Some(label.as_str().to_string()));
+                    }
+                    Rule::il_instruction => {
+                        let span = span_from_pair(&item);
+                        let instr = item.into_inner().expect_next("instruction")?;
+                        self.instruction(instr).map_err(|e| {
+                            anyhow!("{} (in {} at line {})", e, self.pou, span.line)
+                        })?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if !self.deferred.is_empty() {
+            return Err(anyhow!("Unclosed '(' in {}", self.pou));
+        }
+
+        let mut temps = Vec::new();
+        if self.uses_cr_temp {
+            temps.push(temp_decl(CR_TEMP, DataType::Bool));
+        }
+        let body = if self.has_jumps {
+            temps.push(temp_decl(PC_TEMP, DataType::Dint));
+            self.state_machine()?
+        } else {
+            self.blocks
+                .into_iter()
+                .flat_map(|block| block.statements)
+                .collect()
+        };
+
+        Ok(IlBody { body, temps })
+    }
+
+    fn start_block(&mut self, label: Option<String>) {
+        // The CR is undefined at a jump target; programs must reload it.
+        self.cr = None;
+        self.blocks.push(Block::new(label));
+    }
+
+    fn emit(&mut self, stmt: Statement, span: Span) {
+        if let Some(block) = self.blocks.last_mut() {
+            block.statements.push(Spanned::new(stmt, span));
+        }
+    }
+
+    fn current(&self, mnemonic: &str) -> Result<Expression> {
+        self.cr.clone().ok_or_else(|| {
+            anyhow!(
+                "'{}' needs a current result; load one with LD first",
+                mnemonic
+            )
+        })
+    }
+
+    /// Store a non-trivial CR in the hidden temporary so that statements which
+    /// modify its operands (S, R) do not change its value retroactively.
+    fn materialize(&mut self, mnemonic: &str, span: Span) -> Result<Expression> {
+        let cr = self.current(mnemonic)?;
+        if matches!(cr, Expression::Literal(_)) || cr == Expression::Variable(CR_TEMP.into()) {
+            return Ok(cr);
+        }
+        self.uses_cr_temp = true;
+        let temp = Expression::Variable(CR_TEMP.to_string());
+        self.emit(assign(temp.clone(), cr, span), span);
+        self.cr = Some(temp.clone());
+        Ok(temp)
+    }
+
+    fn instruction(&mut self, pair: Pair<Rule>) -> Result<()> {
+        let span = span_from_pair(&pair);
+        match pair.as_rule() {
+            Rule::il_simple => self.simple(pair, span),
+            Rule::il_close => {
+                let deferred = self
+                    .deferred
+                    .pop()
+                    .ok_or_else(|| anyhow!("')' without matching '('"))?;
+                let right = self.current(")")?;
+                self.cr = Some(binary(deferred.left, deferred.op, deferred.negate, right));
+                Ok(())
+            }
+            Rule::il_not => {
+                self.cr = Some(not(self.current("NOT")?));
+                Ok(())
+            }
+            Rule::il_jump => {
+                let mut inner = pair.into_inner();
+                let op = inner.expect_next("jump operator")?.as_str().to_uppercase();
+                let label = inner.expect_next("jump label")?.as_str().to_string();
+                let exit = match op.as_str() {
+                    "JMP" => BlockExit::Jump(label),
+                    "JMPC" => BlockExit::JumpIf(self.current(&op)?, label),
+                    _ => BlockExit::JumpIf(not(self.current(&op)?), label),
+                };
+                self.has_jumps = true;
+                if let Some(block) = self.blocks.last_mut() {
+                    block.exit = exit;
+                }
+                self.start_block(None);
+                Ok(())
+            }
+            Rule::il_cal => {
+                let mut inner = pair.into_inner();
+                let op = inner.expect_next("call operator")?.as_str().to_uppercase();
+                let name = inner.expect_next("call target")?.as_str().to_string();
+                let arguments = inner
+                    .next()
+                    .map(parse_arguments)
+                    .transpose()?
+                    .unwrap_or_default();
+                let call = Statement::Call(CallStatement { name, arguments });
+                match op.as_str() {
+                    "CAL" => self.emit(call, span),
+                    "CALC" => {
+                        let cond = self.current(&op)?;
+                        self.emit(if_then(cond, call, span), span);
+                    }
+                    _ => {
+                        let cond = not(self.current(&op)?);
+                        self.emit(if_then(cond, call, span), span);
+                    }
+                }
+                // The CR is undefined after a call.
+                self.cr = None;
+                Ok(())
+            }
+            Rule::il_return => {
+                let op = pair.as_str().to_uppercase();
+                let ret = Statement::Return(None);
+                match op.as_str() {
+                    "RET" => self.emit(ret, span),
+                    "RETC" => {
+                        let cond = self.current(&op)?;
+                        self.emit(if_then(cond, ret, span), span);
+                    }
+                    _ => {
+                        let cond = not(self.current(&op)?);
+                        self.emit(if_then(cond, ret, span), span);
+                    }
+                }
+                Ok(())
+            }
+            Rule::il_call => {
+                let mut inner = pair.into_inner();
+                let name = inner.expect_next("function name")?.as_str().to_string();
+                let mut arguments = vec![CallArgument {
+                    name: None,
+                    value: Spanned::new(self.current(&name)?, span),
+                }];
+                for operand in inner {
+                    arguments.push(CallArgument {
+                        name: None,
+                        value: Spanned::new(parse_operand(operand)?, span),
+                    });
+                }
+                self.cr = Some(Expression::Call { name, arguments });
+                Ok(())
+            }
+            other => Err(anyhow!("Unexpected IL instruction: {:?}", other)),
+        }
+    }
+
+    fn simple(&mut self, pair: Pair<Rule>, span: Span) -> Result<()> {
+        let mut inner = pair.into_inner();
+        let op = inner.expect_next("operator")?.as_str().to_uppercase();
+        let mut next = inner.next();
+        let open = next.as_ref().map(|p| p.as_rule()) == Some(Rule::il_open);
+        if open {
+            next = inner.next();
+        }
+        let operand = next.map(parse_operand).transpose()?;
+
+        if let Some((bin_op, negate)) = binary_operator(&op) {
+            let left = self.current(&op)?;
+            if open {
+                self.deferred.push(Deferred {
+                    op: bin_op,
+                    negate,
+                    left,
+                });
+                self.cr = operand;
+            } else {
+                let right = operand.ok_or_else(|| anyhow!("'{}' needs an operand", op))?;
+                self.cr = Some(binary(left, bin_op, negate, right));
+            }
+            return Ok(());
+        }
+
+        if open {
+            return Err(anyhow!("'(' is not allowed after {}", op));
+        }
+        let operand = operand.ok_or_else(|| anyhow!("'{}' needs an operand", op))?;
+
+        match op.as_str() {
+            "LD" => self.cr = Some(operand),
+            "LDN" => self.cr = Some(not(operand)),
+            "ST" | "STN" => {
+                check_target(&op, &operand)?;
+                let value = self.current(&op)?;
+                if op == "ST" {
+                    self.emit(assign(operand.clone(), value, span), span);
+                    self.cr = Some(operand);
+                } else {
+                    self.emit(assign(operand.clone(), not(value), span), span);
+                    self.cr = Some(not(operand));
+                }
+            }
+            _ => {
+                // S / R
+                check_target(&op, &operand)?;
+                let cond = self.materialize(&op, span)?;
+                let value = Expression::Literal(Literal::Bool(op == "S"));
+                self.emit(if_then(cond, assign(operand, value, span), span), span);
+            }
+        }
+        Ok(())
+    }
+
+    /// Assemble the blocks into `WHILE pc >= 0 DO CASE pc OF ... END_CASE END_WHILE`.
+    fn state_machine(self) -> Result<Vec<Spanned<Statement>>> {
+        let labels: HashMap<String, i64> = self
+            .blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| b.label.clone().map(|l| (l.to_uppercase(), i as i64)))
+            .collect();
+        let target = |label: &str| {
+            labels
+                .get(&label.to_uppercase())
+                .copied()
+                .ok_or_else(|| anyhow!("Undefined label '{}' in {}", label, self.pou))
+        };
+
+        let span = Span::default();
+        let pc = Expression::Variable(PC_TEMP.to_string());
+        let set_pc = |value: i64| assign(pc.clone(), int(value), span);
+        let count = self.blocks.len() as i64;
+
+        // Without backward jumps every pass moves the pc forward, so the
+        // dispatch loop runs at most once per block.
+        let mut forward_only = true;
+        let mut branches = Vec::new();
+        for (i, block) in self.blocks.iter().enumerate() {
+            let i = i as i64;
+            let next = if i + 1 < count { i + 1 } else { -1 };
+            let mut statements = block.statements.clone();
+            let mut jump = |label: &str| {
+                let to = target(label)?;
+                forward_only &= to > i;
+                Ok::<_, anyhow::Error>(set_pc(to))
+            };
+            let exit = match &block.exit {
+                BlockExit::FallThrough => set_pc(next),
+                BlockExit::Jump(label) => jump(label)?,
+                BlockExit::JumpIf(cond, label) => Statement::If(IfStatement {
+                    condition: Spanned::new(cond.clone(), span),
+                    then_branch: vec![Spanned::new(jump(label)?, span)],
+                    elsif_branches: Vec::new(),
+                    else_branch: Some(vec![Spanned::new(set_pc(next), span)]),
+                }),
+            };
+            statements.push(Spanned::new(exit, span));
+            branches.push(CaseBranch {
+                values: vec![CaseValue::Single(Spanned::new(int(i), span))],
+                statements,
+            });
+        }
+
+        let dispatch = Statement::Case(CaseStatement {
+            selector: Spanned::new(pc.clone(), span),
+            branches,
+            else_branch: None,
+        });
+        let running = Expression::Binary {
+            left: Box::new(Spanned::new(pc.clone(), span)),
+            op: BinaryOp::Ge,
+            right: Box::new(Spanned::new(int(0), span)),
+        };
+
+        Ok(vec![
+            Spanned::new(set_pc(0), span),
+            Spanned::new(
+                Statement::While(WhileStatement {
+                    condition: Spanned::new(running, span),
+                    body: vec![Spanned::new(dispatch, span)],
+                    bound: forward_only.then_some(count as u64),
+                }),
+                span,
+            ),
+        ])
+    }
+}
+
+fn parse_operand(pair: Pair<Rule>) -> Result<Expression> {
+    let inner = pair.into_inner().expect_next("operand")?;
+    match inner.as_rule() {
+        Rule::literal => parse_literal(inner),
+        Rule::variable => parse_variable(inner),
+        Rule::il_neg_literal => {
+            match parse_literal_token(inner.into_inner().expect_next("literal")?)? {
+                Literal::Integer(v) => Ok(Expression::Literal(Literal::Integer(-v))),
+                Literal::Real(v) => Ok(Expression::Literal(Literal::Real(-v))),
+                other => Err(anyhow!("Cannot negate literal {:?}", other)),
+            }
+        }
+        other => Err(anyhow!("Unexpected IL operand: {:?}", other)),
+    }
+}
+
+/// Parse a bare `integer_literal` / `real_literal` token.
+fn parse_literal_token(pair: Pair<Rule>) -> Result<Literal> {
+    let text = pair.as_str().replace('_', "");
+    match pair.as_rule() {
+        Rule::real_literal => text
+            .parse()
+            .map(Literal::Real)
+            .map_err(|_| anyhow!("Invalid real literal: {}", text)),
+        _ => {
+            let (radix, digits) = if let Some(rest) = text.strip_prefix("16#") {
+                (16, rest)
+            } else if let Some(rest) = text.strip_prefix("8#") {
+                (8, rest)
+            } else if let Some(rest) = text.strip_prefix("2#") {
+                (2, rest)
+            } else {
+                (10, text.as_str())
+            };
+            i64::from_str_radix(digits, radix)
+                .map(Literal::Integer)
+                .map_err(|_| anyhow!("Invalid integer literal: {}", text))
+        }
+    }
+}
+
+fn binary_operator(mnemonic: &str) -> Option<(BinaryOp, bool)> {
+    let op = match mnemonic {
+        "AND" | "ANDN" => BinaryOp::And,
+        "OR" | "ORN" => BinaryOp::Or,
+        "XOR" | "XORN" => BinaryOp::Xor,
+        "ADD" => BinaryOp::Add,
+        "SUB" => BinaryOp::Sub,
+        "MUL" => BinaryOp::Mul,
+        "DIV" => BinaryOp::Div,
+        "MOD" => BinaryOp::Mod,
+        "GT" => BinaryOp::Gt,
+        "GE" => BinaryOp::Ge,
+        "EQ" => BinaryOp::Eq,
+        "NE" => BinaryOp::Ne,
+        "LE" => BinaryOp::Le,
+        "LT" => BinaryOp::Lt,
+        _ => return None,
+    };
+    Some((op, matches!(mnemonic, "ANDN" | "ORN" | "XORN")))
+}
+
+fn check_target(mnemonic: &str, operand: &Expression) -> Result<()> {
+    match operand {
+        Expression::Variable(_)
+        | Expression::ArrayAccess { .. }
+        | Expression::FieldAccess { .. } => Ok(()),
+        _ => Err(anyhow!("'{}' needs a variable operand", mnemonic)),
+    }
+}
+
+fn binary(left: Expression, op: BinaryOp, negate: bool, right: Expression) -> Expression {
+    let right = if negate { not(right) } else { right };
+    Expression::Binary {
+        left: Box::new(Spanned::new(left, Span::default())),
+        op,
+        right: Box::new(Spanned::new(right, Span::default())),
+    }
+}
+
+fn not(expr: Expression) -> Expression {
+    Expression::Unary {
+        op: UnaryOp::Not,
+        operand: Box::new(Spanned::new(expr, Span::default())),
+    }
+}
+
+fn int(value: i64) -> Expression {
+    Expression::Literal(Literal::Integer(value))
+}
+
+fn assign(target: Expression, value: Expression, span: Span) -> Statement {
+    Statement::Assignment(Assignment {
+        target: Spanned::new(target, span),
+        value: Spanned::new(value, span),
+    })
+}
+
+fn if_then(condition: Expression, stmt: Statement, span: Span) -> Statement {
+    Statement::If(IfStatement {
+        condition: Spanned::new(condition, span),
+        then_branch: vec![Spanned::new(stmt, span)],
+        elsif_branches: Vec::new(),
+        else_branch: None,
+    })
+}
+
+fn temp_decl(name: &str, data_type: DataType) -> Spanned<VarDecl> {
+    Spanned::new(
+        VarDecl {
+            name: name.to_string(),
+            data_type,
+            initial_value: None,
+            address: None,
+        },
+        Span::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body_of(source: &str) -> Vec<Spanned<Statement>> {
+        let unit = parse_il(source).expect("IL parse failed");
+        match &unit.units[0].node {
+            ProgramUnit::Program(p) => p.body.clone(),
+            ProgramUnit::FunctionBlock(fb) => fb.body.clone(),
+            ProgramUnit::Function(f) => f.body.clone(),
+        }
+    }
+
+    fn var(name: &str) -> Expression {
+        Expression::Variable(name.to_string())
+    }
+
+    #[test]
+    fn test_load_and_store() {
+        let body = body_of(
+            "PROGRAM Main
+             VAR a : BOOL; b : BOOL; c : BOOL; END_VAR
+                LD a
+                ANDN b
+                ST c
+             END_PROGRAM",
+        );
+        assert_eq!(body.len(), 1);
+        let Statement::Assignment(assign) = &body[0].node else {
+            panic!("expected assignment, got {:?}", body[0].node);
+        };
+        assert_eq!(assign.target.node, var("c"));
+        assert_eq!(
+            assign.value.node,
+            binary(var("a"), BinaryOp::And, true, var("b"))
+        );
+    }
+
+    #[test]
+    fn test_not_on_its_own_line() {
+        let body = body_of(
+            "PROGRAM Main
+             VAR a : BOOL; b : BOOL; END_VAR
+                LD a
+                NOT
+                ST b
+             END_PROGRAM",
+        );
+        let Statement::Assignment(assign) = &body[0].node else {
+            panic!("expected assignment, got {:?}", body[0].node);
+        };
+        assert_eq!(assign.value.node, not(var("a")));
+    }
+
+    #[test]
+    fn test_parenthesized_operand() {
+        let body = body_of(
+            "PROGRAM Main
+             VAR a : INT; b : INT; c : INT; d : INT; END_VAR
+                LD a
+                MUL( b
+                ADD c
+                )
+                ST d
+             END_PROGRAM",
+        );
+        let Statement::Assignment(assign) = &body[0].node else {
+            panic!("expected assignment");
+        };
+        let sum = binary(var("b"), BinaryOp::Add, false, var("c"));
+        assert_eq!(
+            assign.value.node,
+            binary(var("a"), BinaryOp::Mul, false, sum)
+        );
+    }
+
+    #[test]
+    fn test_mnemonics_are_case_insensitive_and_not_prefixes() {
+        // `SUB` must not be read as `S UB`, nor `Start` as `ST art`.
+        let body = body_of(
+            "program Main
+             var Start : INT; Result : INT; END_VAR
+                ld Start
+                sub 1
+                st Result
+             end_program",
+        );
+        assert_eq!(body.len(), 1);
+    }
+
+    #[test]
+    fn test_set_reset_materialize_current_result() {
+        let unit = parse_il(
+            "PROGRAM Main
+             VAR x : BOOL; y : BOOL; END_VAR
+                LD x
+                AND y
+                R x
+                S y
+             END_PROGRAM",
+        )
+        .unwrap();
+        let ProgramUnit::Program(p) = &unit.units[0].node else {
+            panic!("expected program");
+        };
+        // __il_cr := x AND y; IF __il_cr THEN x := FALSE; IF __il_cr THEN y := TRUE
+        assert_eq!(p.body.len(), 3);
+        let temps = &p.variables.last().unwrap().node;
+        assert_eq!(temps.kind, VarBlockKind::Temp);
+        assert_eq!(temps.declarations[0].node.name, CR_TEMP);
+    }
+
+    #[test]
+    fn test_jumps_build_state_machine() {
+        let unit = parse_il(
+            "PROGRAM Main
+             VAR n : INT; END_VAR
+                LD 0
+                ST n
+             loop:
+                LD n
+                ADD 1
+                ST n
+                LT 10
+                JMPC loop
+             END_PROGRAM",
+        )
+        .unwrap();
+        let ProgramUnit::Program(p) = &unit.units[0].node else {
+            panic!("expected program");
+        };
+        assert_eq!(p.body.len(), 2);
+        let Statement::While(w) = &p.body[1].node else {
+            panic!("expected dispatch loop");
+        };
+        let Statement::Case(case) = &w.body[0].node else {
+            panic!("expected dispatch CASE");
+        };
+        // entry block, `loop:` block, and the block after JMPC
+        assert_eq!(case.branches.len(), 3);
+    }
+
+    #[test]
+    fn test_cal_and_function_call() {
+        let body = body_of(
+            "FUNCTION_BLOCK Wrapper
+             VAR_INPUT a : INT; END_VAR
+             VAR r : INT; END_VAR
+                CAL Timer(pt := 5)
+                LD a
+                LIMIT 0, 100
+                ST r
+             END_FUNCTION_BLOCK",
+        );
+        let Statement::Call(call) = &body[0].node else {
+            panic!("expected CAL");
+        };
+        assert_eq!(call.name, "Timer");
+        assert_eq!(call.arguments[0].name.as_deref(), Some("pt"));
+        let Statement::Assignment(assign) = &body[1].node else {
+            panic!("expected assignment");
+        };
+        let Expression::Call { name, arguments } = &assign.value.node else {
+            panic!("expected function call");
+        };
+        assert_eq!(name, "LIMIT");
+        assert_eq!(arguments.len(), 3);
+        assert_eq!(arguments[0].value.node, var("a"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let missing_load = parse_il("PROGRAM Main VAR x : BOOL; END_VAR ST x END_PROGRAM");
+        assert!(missing_load.unwrap_err().to_string().contains("LD first"));
+
+        let bad_label = parse_il("PROGRAM Main JMP nowhere END_PROGRAM");
+        assert!(bad_label
+            .unwrap_err()
+            .to_string()
+            .contains("Undefined label"));
+
+        let unclosed = parse_il("PROGRAM Main VAR x : BOOL; END_VAR LD x AND( x END_PROGRAM");
+        assert!(unclosed.unwrap_err().to_string().contains("Unclosed"));
+    }
+}
@@ -1,14 +1,19 @@
-//! Frontend module for IEC 61131-3 Structured Text parsing.
+//! Frontend module for IEC 61131-3 Structured Text and Instruction List parsing.
 //!
 //! This module contains:
 //! - [`ast`] - Abstract Syntax Tree definitions
 //! - [`lexer`] - Token definitions and lexical analysis
 //! - [`parser`] - ST grammar and parsing
+//! - [`il`] - IL grammar and translation into the ST AST
+//! - [`sfc`] - Lowering of textual SFC networks into ST
 
 pub mod ast;
+pub mod il;
 pub mod lexer;
 pub mod parser;
+pub mod sfc;
 
 pub use ast::*;
+pub use il::{parse_il, parse_il_body, IlBody};
 pub use lexer::*;
 pub use parser::*;
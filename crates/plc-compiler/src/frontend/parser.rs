@@ -10,10 +10,11 @@ use pest_derive::Parser;
 
 #[derive(Parser)]
 #[grammar = "frontend/st.pest"]
-struct StParser;
+#[grammar = "frontend/il.pest"]
+pub(super) struct StParser;
 
 /// Helper trait for extracting the next element from a pest iterator with context.
-trait PairsExt<'i> {
+pub(super) trait PairsExt<'i> {
     /// Get the next pair, returning an error with context if missing.
     fn expect_next(&mut self, context: &str) -> Result<Pair<'i, Rule>>;
 }
@@ -47,10 +48,54 @@ pub fn parse(source: &str) -> Result<CompilationUnit> {
         }
     }
 
-    Ok(CompilationUnit { units })
+    Ok(CompilationUnit {
+        units,
+        globals: Vec::new(),
+    })
+}
+
+/// Parse a bare ST statement list, without a surrounding POU.
+pub fn parse_statements(source: &str) -> Result<Vec<Spanned<Statement>>> {
+    let pair = StParser::parse(Rule::statement_body, source)
+        .map_err(|e| anyhow!("Parse error: {}", e))?
+        .next()
+        .and_then(|body| body.into_inner().next())
+        .ok_or_else(|| anyhow!("Expected statement list"))?;
+    parse_statement_list(pair)
+}
+
+/// Parse a single ST expression.
+pub fn parse_expr(source: &str) -> Result<Expression> {
+    let pair = StParser::parse(Rule::expression_body, source)
+        .map_err(|e| anyhow!("Parse error: {}", e))?
+        .next()
+        .and_then(|body| body.into_inner().next())
+        .ok_or_else(|| anyhow!("Expected expression"))?;
+    parse_expression(pair)
+}
+
+/// Collect all comments in Structured Text source, in source order.
+///
+/// The regular [`parse`] entry point discards comments; this walks the same
+/// source with the grammar's `comment_stream` rule so that tools which
+/// rewrite source (such as the formatter) can preserve them.
+pub fn parse_comments(source: &str) -> Result<Vec<Comment>> {
+    let pairs =
+        StParser::parse(Rule::comment_stream, source).map_err(|e| anyhow!("Parse error: {}", e))?;
+
+    let comments = pairs
+        .flat_map(|pair| pair.into_inner())
+        .filter(|pair| pair.as_rule() == Rule::comment)
+        .map(|pair| Comment {
+            text: pair.as_str().trim_end().to_string(),
+            span: span_from_pair(&pair),
+        })
+        .collect();
+
+    Ok(comments)
 }
 
-fn span_from_pair(pair: &Pair<Rule>) -> Span {
+pub(super) fn span_from_pair(pair: &Pair<Rule>) -> Span {
     let pest_span = pair.as_span();
     let (line, col) = pest_span.start_pos().line_col();
     Span::new(pest_span.start(), pest_span.end(), line, col)
@@ -75,6 +120,7 @@ fn parse_program(pair: Pair<Rule>) -> Result<Program> {
 
     let mut variables = Vec::new();
     let mut body = Vec::new();
+    let mut sfc = None;
 
     for item in inner {
         match item.as_rule() {
@@ -85,6 +131,9 @@ fn parse_program(pair: Pair<Rule>) -> Result<Program> {
             Rule::statement_list => {
                 body = parse_statement_list(item)?;
             }
+            Rule::sfc_network => {
+                sfc = Some(parse_sfc_network(item)?);
+            }
             _ => {}
         }
     }
@@ -93,6 +142,7 @@ fn parse_program(pair: Pair<Rule>) -> Result<Program> {
         name,
         variables,
         body,
+        sfc,
     })
 }
 
@@ -105,6 +155,7 @@ fn parse_function_block(pair: Pair<Rule>) -> Result<FunctionBlock> {
 
     let mut variables = Vec::new();
     let mut body = Vec::new();
+    let mut sfc = None;
 
     for item in inner {
         match item.as_rule() {
@@ -115,6 +166,9 @@ fn parse_function_block(pair: Pair<Rule>) -> Result<FunctionBlock> {
             Rule::statement_list => {
                 body = parse_statement_list(item)?;
             }
+            Rule::sfc_network => {
+                sfc = Some(parse_sfc_network(item)?);
+            }
             _ => {}
         }
     }
@@ -123,6 +177,7 @@ fn parse_function_block(pair: Pair<Rule>) -> Result<FunctionBlock> {
         name,
         variables,
         body,
+        sfc,
     })
 }
 
@@ -155,7 +210,125 @@ fn parse_function(pair: Pair<Rule>) -> Result<Function> {
     })
 }
 
-fn parse_var_block(pair: Pair<Rule>) -> Result<VarBlock> {
+fn parse_sfc_network(pair: Pair<Rule>) -> Result<SfcNetwork> {
+    let mut elements = Vec::new();
+    for item in pair.into_inner() {
+        let span = span_from_pair(&item);
+        let element = match item.as_rule() {
+            Rule::sfc_step => SfcElement::Step(parse_sfc_step(item)?),
+            Rule::sfc_transition => SfcElement::Transition(parse_sfc_transition(item)?),
+            Rule::sfc_action => SfcElement::Action(parse_sfc_action(item)?),
+            other => return Err(anyhow!("Unexpected SFC element: {:?}", other)),
+        };
+        elements.push(Spanned::new(element, span));
+    }
+    Ok(SfcNetwork { elements })
+}
+
+fn parse_sfc_step(pair: Pair<Rule>) -> Result<SfcStep> {
+    let mut inner = pair.into_inner();
+    let initial = inner.expect_next("STEP keyword")?.as_rule() == Rule::sfc_initial;
+    let name = inner.expect_next("step name")?.as_str().to_string();
+
+    let mut actions = Vec::new();
+    for assoc in inner {
+        let mut parts = assoc.into_inner();
+        let action = parts.expect_next("action name")?.as_str().to_string();
+        let qualifier = match parts.next() {
+            Some(q) => match q.as_str().to_uppercase().as_str() {
+                "N" => ActionQualifier::N,
+                "S" => ActionQualifier::S,
+                "R" => ActionQualifier::R,
+                "P" => ActionQualifier::P,
+                "L" => ActionQualifier::L,
+                "D" => ActionQualifier::D,
+                other => return Err(anyhow!("Unknown action qualifier: {}", other)),
+            },
+            None => ActionQualifier::N,
+        };
+        let duration = match parts.next() {
+            Some(expr) => {
+                let span = span_from_pair(&expr);
+                Some(Spanned::new(parse_expression(expr)?, span))
+            }
+            None => None,
+        };
+        let timed = matches!(qualifier, ActionQualifier::L | ActionQualifier::D);
+        if timed != duration.is_some() {
+            return Err(anyhow!(
+                "Action {} in step {}: qualifier {} {} a duration",
+                action,
+                name,
+                qualifier,
+                if timed { "requires" } else { "does not take" }
+            ));
+        }
+        actions.push(ActionAssociation {
+            action,
+            qualifier,
+            duration,
+        });
+    }
+
+    Ok(SfcStep {
+        name,
+        initial,
+        actions,
+    })
+}
+
+fn parse_sfc_transition(pair: Pair<Rule>) -> Result<SfcTransition> {
+    let mut name = None;
+    let mut priority = None;
+    let mut lists = Vec::new();
+    let mut condition = None;
+
+    for item in pair.into_inner() {
+        match item.as_rule() {
+            Rule::identifier => name = Some(item.as_str().to_string()),
+            Rule::integer_literal => {
+                priority = Some(
+                    item.as_str()
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid transition priority: {}", item.as_str()))?,
+                )
+            }
+            Rule::sfc_steps => lists.push(
+                item.into_inner()
+                    .map(|step| step.as_str().to_string())
+                    .collect::<Vec<_>>(),
+            ),
+            Rule::expression => {
+                let span = span_from_pair(&item);
+                condition = Some(Spanned::new(parse_expression(item)?, span));
+            }
+            _ => {}
+        }
+    }
+
+    let mut lists = lists.into_iter();
+    Ok(SfcTransition {
+        name,
+        priority,
+        from: lists.next().unwrap_or_default(),
+        to: lists.next().unwrap_or_default(),
+        condition: condition.ok_or_else(|| anyhow!("Expected transition condition"))?,
+    })
+}
+
+fn parse_sfc_action(pair: Pair<Rule>) -> Result<SfcAction> {
+    let mut inner = pair.into_inner();
+    inner.expect_next("ACTION keyword")?;
+    let name = inner.expect_next("action name")?.as_str().to_string();
+    let body = inner
+        .next()
+        .map(parse_statement_list)
+        .transpose()?
+        .unwrap_or_default();
+    Ok(SfcAction { name, body })
+}
+
+pub(super) fn parse_var_block(pair: Pair<Rule>) -> Result<VarBlock> {
     let mut inner = pair.into_inner();
 
     let kind_str = inner
@@ -249,7 +422,7 @@ fn parse_var_decl(pair: Pair<Rule>) -> Result<Vec<VarDecl>> {
     Ok(decls)
 }
 
-fn parse_data_type(pair: Pair<Rule>) -> Result<DataType> {
+pub(super) fn parse_data_type(pair: Pair<Rule>) -> Result<DataType> {
     let inner = pair
         .into_inner()
         .next()
@@ -495,8 +668,24 @@ fn parse_case_values(pair: Pair<Rule>) -> Result<Vec<CaseValue>> {
     Ok(values)
 }
 
+/// Take a leading `{bound N}` pragma off a loop statement's children.
+fn parse_loop_bound(inner: &mut Pairs<Rule>) -> Result<Option<u64>> {
+    if inner.peek().map(|p| p.as_rule()) != Some(Rule::loop_bound) {
+        return Ok(None);
+    }
+    let pragma = inner.expect_next("loop bound")?;
+    let value = pragma.into_inner().expect_next("loop bound value")?;
+    value
+        .as_str()
+        .replace('_', "")
+        .parse()
+        .map(Some)
+        .map_err(|_| anyhow!("Invalid loop bound: {}", value.as_str()))
+}
+
 fn parse_for(pair: Pair<Rule>) -> Result<Statement> {
     let mut inner = pair.into_inner();
+    let bound = parse_loop_bound(&mut inner)?;
 
     let variable = inner.expect_next("FOR loop variable")?.as_str().to_string();
 
@@ -530,11 +719,13 @@ fn parse_for(pair: Pair<Rule>) -> Result<Statement> {
         to,
         by,
         body,
+        bound,
     }))
 }
 
 fn parse_while(pair: Pair<Rule>) -> Result<Statement> {
     let mut inner = pair.into_inner();
+    let bound = parse_loop_bound(&mut inner)?;
 
     let cond_pair = inner.expect_next("WHILE condition")?;
     let cond_span = span_from_pair(&cond_pair);
@@ -543,11 +734,16 @@ fn parse_while(pair: Pair<Rule>) -> Result<Statement> {
     let body_pair = inner.expect_next("WHILE body")?;
     let body = parse_statement_list(body_pair)?;
 
-    Ok(Statement::While(WhileStatement { condition, body }))
+    Ok(Statement::While(WhileStatement {
+        condition,
+        body,
+        bound,
+    }))
 }
 
 fn parse_repeat(pair: Pair<Rule>) -> Result<Statement> {
     let mut inner = pair.into_inner();
+    let bound = parse_loop_bound(&mut inner)?;
 
     let body_pair = inner.expect_next("REPEAT body")?;
     let body = parse_statement_list(body_pair)?;
@@ -556,7 +752,7 @@ fn parse_repeat(pair: Pair<Rule>) -> Result<Statement> {
     let until_span = span_from_pair(&until_pair);
     let until = Spanned::new(parse_expression(until_pair)?, until_span);
 
-    Ok(Statement::Repeat(RepeatStatement { body, until }))
+    Ok(Statement::Repeat(RepeatStatement { body, until, bound }))
 }
 
 fn parse_call_stmt(pair: Pair<Rule>) -> Result<Statement> {
@@ -574,7 +770,7 @@ fn parse_call_stmt(pair: Pair<Rule>) -> Result<Statement> {
     Ok(Statement::Call(CallStatement { name, arguments }))
 }
 
-fn parse_arguments(pair: Pair<Rule>) -> Result<Vec<CallArgument>> {
+pub(super) fn parse_arguments(pair: Pair<Rule>) -> Result<Vec<CallArgument>> {
     let mut args = Vec::new();
     for item in pair.into_inner() {
         if item.as_rule() == Rule::argument {
@@ -626,6 +822,7 @@ fn parse_or_expr(pair: Pair<Rule>) -> Result<Expression> {
     let first = inner
         .next()
         .ok_or_else(|| anyhow!("Expected OR expression operand"))?;
+    let mut left_span = span_from_pair(&first);
     let mut left = parse_xor_expr(first)?;
 
     while let Some(op_pair) = inner.next() {
@@ -633,23 +830,23 @@ fn parse_or_expr(pair: Pair<Rule>) -> Result<Expression> {
             let right_pair = inner
                 .next()
                 .ok_or_else(|| anyhow!("Expected right operand after OR operator"))?;
+            let right_span = span_from_pair(&right_pair);
             let right = parse_xor_expr(right_pair)?;
-            let left_span = Span::default(); // Simplified
-            let right_span = Span::default();
             left = Expression::Binary {
                 left: Box::new(Spanned::new(left, left_span)),
                 op: BinaryOp::Or,
                 right: Box::new(Spanned::new(right, right_span)),
             };
+            left_span = left_span.merge(right_span);
         } else {
+            let right_span = span_from_pair(&op_pair);
             let right = parse_xor_expr(op_pair)?;
-            let left_span = Span::default();
-            let right_span = Span::default();
             left = Expression::Binary {
                 left: Box::new(Spanned::new(left, left_span)),
                 op: BinaryOp::Or,
                 right: Box::new(Spanned::new(right, right_span)),
             };
+            left_span = left_span.merge(right_span);
         }
     }
 
@@ -661,6 +858,7 @@ fn parse_xor_expr(pair: Pair<Rule>) -> Result<Expression> {
     let first = inner
         .next()
         .ok_or_else(|| anyhow!("Expected XOR expression operand"))?;
+    let mut left_span = span_from_pair(&first);
     let mut left = parse_and_expr(first)?;
 
     while let Some(op_pair) = inner.next() {
@@ -668,14 +866,14 @@ fn parse_xor_expr(pair: Pair<Rule>) -> Result<Expression> {
             let right_pair = inner
                 .next()
                 .ok_or_else(|| anyhow!("Expected right operand after XOR operator"))?;
+            let right_span = span_from_pair(&right_pair);
             let right = parse_and_expr(right_pair)?;
-            let left_span = Span::default();
-            let right_span = Span::default();
             left = Expression::Binary {
                 left: Box::new(Spanned::new(left, left_span)),
                 op: BinaryOp::Xor,
                 right: Box::new(Spanned::new(right, right_span)),
             };
+            left_span = left_span.merge(right_span);
         }
     }
 
@@ -687,6 +885,7 @@ fn parse_and_expr(pair: Pair<Rule>) -> Result<Expression> {
     let first = inner
         .next()
         .ok_or_else(|| anyhow!("Expected AND expression operand"))?;
+    let mut left_span = span_from_pair(&first);
     let mut left = parse_comparison(first)?;
 
     while let Some(op_pair) = inner.next() {
@@ -694,14 +893,14 @@ fn parse_and_expr(pair: Pair<Rule>) -> Result<Expression> {
             let right_pair = inner
                 .next()
                 .ok_or_else(|| anyhow!("Expected right operand after AND operator"))?;
+            let right_span = span_from_pair(&right_pair);
             let right = parse_comparison(right_pair)?;
-            let left_span = Span::default();
-            let right_span = Span::default();
             left = Expression::Binary {
                 left: Box::new(Spanned::new(left, left_span)),
                 op: BinaryOp::And,
                 right: Box::new(Spanned::new(right, right_span)),
             };
+            left_span = left_span.merge(right_span);
         }
     }
 
@@ -713,6 +912,7 @@ fn parse_comparison(pair: Pair<Rule>) -> Result<Expression> {
     let first = inner
         .next()
         .ok_or_else(|| anyhow!("Expected comparison operand"))?;
+    let mut left_span = span_from_pair(&first);
     let mut left = parse_add_expr(first)?;
 
     while let Some(op_pair) = inner.next() {
@@ -729,14 +929,14 @@ fn parse_comparison(pair: Pair<Rule>) -> Result<Expression> {
             let right_pair = inner
                 .next()
                 .ok_or_else(|| anyhow!("Expected right operand after comparison operator"))?;
+            let right_span = span_from_pair(&right_pair);
             let right = parse_add_expr(right_pair)?;
-            let left_span = Span::default();
-            let right_span = Span::default();
             left = Expression::Binary {
                 left: Box::new(Spanned::new(left, left_span)),
                 op,
                 right: Box::new(Spanned::new(right, right_span)),
             };
+            left_span = left_span.merge(right_span);
         }
     }
 
@@ -748,6 +948,7 @@ fn parse_add_expr(pair: Pair<Rule>) -> Result<Expression> {
     let first = inner
         .next()
         .ok_or_else(|| anyhow!("Expected additive expression operand"))?;
+    let mut left_span = span_from_pair(&first);
     let mut left = parse_mul_expr(first)?;
 
     while let Some(op_pair) = inner.next() {
@@ -760,14 +961,14 @@ fn parse_add_expr(pair: Pair<Rule>) -> Result<Expression> {
             let right_pair = inner
                 .next()
                 .ok_or_else(|| anyhow!("Expected right operand after +/- operator"))?;
+            let right_span = span_from_pair(&right_pair);
             let right = parse_mul_expr(right_pair)?;
-            let left_span = Span::default();
-            let right_span = Span::default();
             left = Expression::Binary {
                 left: Box::new(Spanned::new(left, left_span)),
                 op,
                 right: Box::new(Spanned::new(right, right_span)),
             };
+            left_span = left_span.merge(right_span);
         }
     }
 
@@ -779,6 +980,7 @@ fn parse_mul_expr(pair: Pair<Rule>) -> Result<Expression> {
     let first = inner
         .next()
         .ok_or_else(|| anyhow!("Expected multiplicative expression operand"))?;
+    let mut left_span = span_from_pair(&first);
     let mut left = parse_power_expr(first)?;
 
     while let Some(op_pair) = inner.next() {
@@ -792,14 +994,14 @@ fn parse_mul_expr(pair: Pair<Rule>) -> Result<Expression> {
             let right_pair = inner
                 .next()
                 .ok_or_else(|| anyhow!("Expected right operand after */MOD operator"))?;
+            let right_span = span_from_pair(&right_pair);
             let right = parse_power_expr(right_pair)?;
-            let left_span = Span::default();
-            let right_span = Span::default();
             left = Expression::Binary {
                 left: Box::new(Spanned::new(left, left_span)),
                 op,
                 right: Box::new(Spanned::new(right, right_span)),
             };
+            left_span = left_span.merge(right_span);
         }
     }
 
@@ -811,17 +1013,18 @@ fn parse_power_expr(pair: Pair<Rule>) -> Result<Expression> {
     let first = inner
         .next()
         .ok_or_else(|| anyhow!("Expected power expression operand"))?;
+    let mut left_span = span_from_pair(&first);
     let mut left = parse_unary_expr(first)?;
 
     for right_pair in inner {
+        let right_span = span_from_pair(&right_pair);
         let right = parse_unary_expr(right_pair)?;
-        let left_span = Span::default();
-        let right_span = Span::default();
         left = Expression::Binary {
             left: Box::new(Spanned::new(left, left_span)),
             op: BinaryOp::Pow,
             right: Box::new(Spanned::new(right, right_span)),
         };
+        left_span = left_span.merge(right_span);
     }
 
     Ok(left)
@@ -843,8 +1046,8 @@ fn parse_unary_expr(pair: Pair<Rule>) -> Result<Expression> {
         let primary = inner
             .next()
             .ok_or_else(|| anyhow!("Expected operand after unary operator"))?;
+        let span = span_from_pair(&primary);
         let operand = parse_primary_expr_inner(primary)?;
-        let span = Span::default();
         Ok(Expression::Unary {
             op,
             operand: Box::new(Spanned::new(operand, span)),
@@ -863,11 +1066,9 @@ fn parse_primary_expr_inner(pair: Pair<Rule>) -> Result<Expression> {
         .ok_or_else(|| anyhow!("Expected primary expression content"))?;
     match inner.as_rule() {
         Rule::expression => {
+            let span = span_from_pair(&inner);
             let expr = parse_expression(inner)?;
-            Ok(Expression::Paren(Box::new(Spanned::new(
-                expr,
-                Span::default(),
-            ))))
+            Ok(Expression::Paren(Box::new(Spanned::new(expr, span))))
         }
         Rule::function_call => parse_function_call(inner),
         Rule::literal => parse_literal(inner),
@@ -895,7 +1096,7 @@ fn parse_function_call(pair: Pair<Rule>) -> Result<Expression> {
     Ok(Expression::Call { name, arguments })
 }
 
-fn parse_literal(pair: Pair<Rule>) -> Result<Expression> {
+pub(super) fn parse_literal(pair: Pair<Rule>) -> Result<Expression> {
     let inner = pair
         .into_inner()
         .next()
@@ -941,16 +1142,16 @@ fn parse_literal(pair: Pair<Rule>) -> Result<Expression> {
     }
 }
 
-fn parse_variable(pair: Pair<Rule>) -> Result<Expression> {
+pub(super) fn parse_variable(pair: Pair<Rule>) -> Result<Expression> {
     let mut inner = pair.into_inner();
-    let name = inner
+    let name_pair = inner
         .next()
-        .ok_or_else(|| anyhow!("Expected variable name"))?
-        .as_str()
-        .to_string();
-    let mut expr = Expression::Variable(name);
+        .ok_or_else(|| anyhow!("Expected variable name"))?;
+    let mut span = span_from_pair(&name_pair);
+    let mut expr = Expression::Variable(name_pair.as_str().to_string());
 
     for item in inner {
+        let item_span = span_from_pair(&item);
         match item.as_rule() {
             Rule::array_index => {
                 let index_pair = item
@@ -960,7 +1161,7 @@ fn parse_variable(pair: Pair<Rule>) -> Result<Expression> {
                 let index_span = span_from_pair(&index_pair);
                 let index = parse_expression(index_pair)?;
                 expr = Expression::ArrayAccess {
-                    array: Box::new(Spanned::new(expr, Span::default())),
+                    array: Box::new(Spanned::new(expr, span)),
                     index: Box::new(Spanned::new(index, index_span)),
                 };
             }
@@ -972,12 +1173,13 @@ fn parse_variable(pair: Pair<Rule>) -> Result<Expression> {
                     .as_str()
                     .to_string();
                 expr = Expression::FieldAccess {
-                    object: Box::new(Spanned::new(expr, Span::default())),
+                    object: Box::new(Spanned::new(expr, span)),
                     field,
                 };
             }
             _ => {}
         }
+        span = span.merge(item_span);
     }
 
     Ok(expr)
@@ -1109,6 +1311,94 @@ mod tests {
         assert!(result.is_ok(), "Parse failed: {:?}", result.err());
     }
 
+    #[test]
+    fn test_parse_time_literal_units() {
+        let ms = 1_000_000;
+        for (source, ns) in [
+            ("T#100ms", 100 * ms),
+            ("T#1s500ms", 1_500 * ms),
+            ("T#1m30s", 90_000 * ms),
+            ("T#2m5ms", 120_005 * ms),
+            ("TIME#10us", 10_000),
+            ("t#250ns", 250),
+        ] {
+            assert_eq!(
+                parse_expr(source).unwrap(),
+                Expression::Literal(Literal::Time(ns)),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_comments() {
+        let source = r#"
+            (* header *)
+            PROGRAM Test
+            VAR
+                s : STRING := '(* not a comment *)';
+            END_VAR
+                s := 'x'; // trailing
+            END_PROGRAM
+        "#;
+
+        let comments = parse_comments(source).unwrap();
+        let texts: Vec<&str> = comments.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["(* header *)", "// trailing"]);
+        assert_eq!(comments[0].span.line, 2);
+        assert!(comments[0].is_block());
+        assert!(!comments[1].is_block());
+    }
+
+    #[test]
+    fn test_parse_sfc_network() {
+        let source = r#"
+            PROGRAM Chart
+            VAR
+                go : BOOL;
+            END_VAR
+                INITIAL_STEP Start:
+                    Init(P);
+                END_STEP
+                STEP Run:
+                    Motor(N);
+                    Horn(L, T#2s);
+                END_STEP
+                TRANSITION T1 (PRIORITY := 2) FROM Start TO (Run) := go;
+                END_TRANSITION
+                ACTION Init:
+                    go := FALSE;
+                END_ACTION
+            END_PROGRAM
+        "#;
+
+        let unit = parse(source).unwrap();
+        let ProgramUnit::Program(program) = &unit.units[0].node else {
+            panic!("expected program");
+        };
+        assert!(program.body.is_empty());
+        let sfc = program.sfc.as_ref().expect("SFC body");
+
+        let steps: Vec<_> = sfc.steps().collect();
+        assert_eq!(steps.len(), 2);
+        assert!(steps[0].initial);
+        assert_eq!(steps[0].actions[0].qualifier, ActionQualifier::P);
+        assert_eq!(steps[1].actions[1].qualifier, ActionQualifier::L);
+        assert!(steps[1].actions[1].duration.is_some());
+
+        let transition = sfc.transitions().next().unwrap();
+        assert_eq!(transition.name.as_deref(), Some("T1"));
+        assert_eq!(transition.priority, Some(2));
+        assert_eq!(transition.from, vec!["Start"]);
+        assert_eq!(transition.to, vec!["Run"]);
+
+        assert_eq!(sfc.actions().next().unwrap().body.len(), 1);
+
+        // Time qualifiers need a duration, the others must not have one.
+        let bad = source.replace("Horn(L, T#2s)", "Horn(L)");
+        assert!(parse(&bad).unwrap_err().to_string().contains("duration"));
+    }
+
     #[test]
     fn test_parse_comma_separated_vars() {
         let source = r#"
@@ -0,0 +1,644 @@
+//! Lowering of textual Sequential Function Charts into Structured Text.
+//!
+//! An SFC network has no runtime of its own: every step becomes a pair of
+//! hidden variables (`__sfc_<step>_X`, the activity flag, and
+//! `__sfc_<step>_T`, the elapsed time) and the chart itself becomes one ST
+//! scan that
+//!
+//! 1. activates the initial step on the first scan,
+//! 2. advances the elapsed time of every active step,
+//! 3. decides which transitions fire, in PRIORITY order; a step feeds at most
+//!    one transition per scan,
+//! 4. moves activity from the source to the target steps, and
+//! 5. evaluates the action qualifiers and runs the active actions.
+//!
+//! `Step.X` and `Step.T` in transition conditions and action bodies are
+//! rewritten to the hidden variables, so the rest of the pipeline only sees
+//! ordinary variables.
+
+use super::ast::*;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+/// Prefix of all hidden SFC variables.
+pub const SFC_PREFIX: &str = "__sfc_";
+
+/// Name of the hidden activity flag of a step.
+pub fn step_flag(step: &str) -> String {
+    format!("{SFC_PREFIX}{step}_X")
+}
+
+/// Name of the hidden elapsed-time variable of a step.
+pub fn step_time(step: &str) -> String {
+    format!("{SFC_PREFIX}{step}_T")
+}
+
+fn step_prev(step: &str) -> String {
+    format!("{SFC_PREFIX}{step}_prev")
+}
+
+fn stored_flag(action: &str) -> String {
+    format!("{SFC_PREFIX}{action}_S")
+}
+
+fn fire_flag(index: usize) -> String {
+    format!("{SFC_PREFIX}t{index}")
+}
+
+const STARTED: &str = "__sfc_started";
+const DELTA: &str = "__sfc_dt";
+
+/// An SFC network lowered to ST.
+#[derive(Debug, Clone)]
+pub struct LoweredSfc {
+    /// Hidden state variables.
+    pub variables: VarBlock,
+    /// Statements executing one scan of the chart.
+    pub body: Vec<Spanned<Statement>>,
+    /// Step names in declaration order.
+    pub steps: Vec<String>,
+}
+
+/// Lower an SFC network of the POU `pou` to ST.
+pub fn lower(pou: &str, network: &SfcNetwork) -> Result<LoweredSfc> {
+    Lowering::new(pou, network)?.run()
+}
+
+struct Lowering<'a> {
+    pou: &'a str,
+    network: &'a SfcNetwork,
+    /// Upper-cased step name -> declared step name.
+    steps: HashMap<String, String>,
+    declarations: Vec<Spanned<VarDecl>>,
+    body: Vec<Spanned<Statement>>,
+}
+
+impl<'a> Lowering<'a> {
+    fn new(pou: &'a str, network: &'a SfcNetwork) -> Result<Self> {
+        let mut steps = HashMap::new();
+        for step in network.steps() {
+            if steps
+                .insert(step.name.to_uppercase(), step.name.clone())
+                .is_some()
+            {
+                return Err(anyhow!("{}: step {} is defined twice", pou, step.name));
+            }
+        }
+
+        let initial: Vec<_> = network.steps().filter(|s| s.initial).collect();
+        if initial.len() != 1 {
+            return Err(anyhow!(
+                "{}: an SFC needs exactly one INITIAL_STEP, found {}",
+                pou,
+                initial.len()
+            ));
+        }
+
+        Ok(Self {
+            pou,
+            network,
+            steps,
+            declarations: Vec::new(),
+            body: Vec::new(),
+        })
+    }
+
+    fn run(mut self) -> Result<LoweredSfc> {
+        let network = self.network;
+        let step_names: Vec<String> = network.steps().map(|s| s.name.clone()).collect();
+
+        self.declare(STARTED, DataType::Bool);
+        self.declare(DELTA, DataType::Dint);
+        for step in network.steps() {
+            self.declare(&step_flag(&step.name), DataType::Bool);
+            self.declare(&step_time(&step.name), DataType::Time);
+            if step
+                .actions
+                .iter()
+                .any(|a| a.qualifier == ActionQualifier::P)
+            {
+                self.declare(&step_prev(&step.name), DataType::Bool);
+            }
+        }
+
+        self.startup(network);
+        self.timers(network);
+        self.transitions(network)?;
+        self.actions(network)?;
+
+        for step in network.steps() {
+            if step
+                .actions
+                .iter()
+                .any(|a| a.qualifier == ActionQualifier::P)
+            {
+                self.emit(assign(&step_prev(&step.name), var(&step_flag(&step.name))));
+            }
+        }
+
+        Ok(LoweredSfc {
+            variables: VarBlock {
+                kind: VarBlockKind::Var,
+                retain: false,
+                constant: false,
+                declarations: self.declarations,
+            },
+            body: self.body,
+            steps: step_names,
+        })
+    }
+
+    fn startup(&mut self, network: &SfcNetwork) {
+        let initial = network
+            .steps()
+            .find(|s| s.initial)
+            .map(|s| s.name.as_str())
+            .unwrap_or_default();
+        self.emit(if_then(
+            not(var(STARTED)),
+            vec![
+                assign(STARTED, Expression::Literal(Literal::Bool(true))),
+                assign(
+                    &step_flag(initial),
+                    Expression::Literal(Literal::Bool(true)),
+                ),
+                assign(&step_time(initial), Expression::Literal(Literal::Time(0))),
+            ],
+        ));
+    }
+
+    fn timers(&mut self, network: &SfcNetwork) {
+        self.emit(assign(
+            DELTA,
+            Expression::Call {
+                name: "get_cycle_time".to_string(),
+                arguments: Vec::new(),
+            },
+        ));
+        for step in network.steps() {
+            let time = step_time(&step.name);
+            self.emit(if_then(
+                var(&step_flag(&step.name)),
+                vec![assign(&time, binary(var(&time), BinaryOp::Add, var(DELTA)))],
+            ));
+        }
+    }
+
+    fn transitions(&mut self, network: &SfcNetwork) -> Result<()> {
+        let mut ordered: Vec<(Vec<String>, Vec<String>, Expression)> = Vec::new();
+        let mut transitions: Vec<&SfcTransition> = network.transitions().collect();
+        // Stable: transitions without a PRIORITY keep their source order.
+        transitions.sort_by_key(|t| t.priority.unwrap_or(u32::MAX));
+        for transition in transitions {
+            let from = self.resolve_steps(&transition.from)?;
+            let to = self.resolve_steps(&transition.to)?;
+            let condition = self.rewrite_expr(&transition.condition.node)?;
+            ordered.push((from, to, condition));
+        }
+
+        // Decide first, then apply, so that a transition cannot see the
+        // effect of one that fired earlier in the same scan.
+        for (index, (from, _, condition)) in ordered.iter().enumerate() {
+            let flag = fire_flag(index);
+            self.declare(&flag, DataType::Bool);
+
+            let mut value = from
+                .iter()
+                .map(|s| var(&step_flag(s)))
+                .reduce(|a, b| binary(a, BinaryOp::And, b))
+                .ok_or_else(|| anyhow!("{}: transition without source steps", self.pou))?;
+            value = binary(value, BinaryOp::And, paren(condition.clone()));
+            for (earlier, (other, _, _)) in ordered[..index].iter().enumerate() {
+                if other.iter().any(|s| from.contains(s)) {
+                    value = binary(value, BinaryOp::And, not(var(&fire_flag(earlier))));
+                }
+            }
+            self.emit(assign(&flag, value));
+        }
+
+        for (index, (from, _, _)) in ordered.iter().enumerate() {
+            let statements = from
+                .iter()
+                .map(|s| assign(&step_flag(s), Expression::Literal(Literal::Bool(false))))
+                .collect();
+            self.emit(if_then(var(&fire_flag(index)), statements));
+        }
+        for (index, (_, to, _)) in ordered.iter().enumerate() {
+            let mut statements = Vec::new();
+            for step in to {
+                statements.push(assign(
+                    &step_flag(step),
+                    Expression::Literal(Literal::Bool(true)),
+                ));
+                statements.push(assign(
+                    &step_time(step),
+                    Expression::Literal(Literal::Time(0)),
+                ));
+            }
+            self.emit(if_then(var(&fire_flag(index)), statements));
+        }
+        Ok(())
+    }
+
+    fn actions(&mut self, network: &SfcNetwork) -> Result<()> {
+        // Action name -> (step, association), in order of first use.
+        let mut order: Vec<String> = Vec::new();
+        let mut uses: HashMap<String, Vec<(&SfcStep, &ActionAssociation)>> = HashMap::new();
+        for step in network.steps() {
+            for assoc in &step.actions {
+                let key = assoc.action.to_uppercase();
+                if !uses.contains_key(&key) {
+                    order.push(key.clone());
+                }
+                uses.entry(key).or_default().push((step, assoc));
+            }
+        }
+
+        let bodies: HashMap<String, &SfcAction> = network
+            .actions()
+            .map(|a| (a.name.to_uppercase(), a))
+            .collect();
+
+        for key in order {
+            let uses = &uses[&key];
+            let name = uses[0].1.action.clone();
+            let stored = uses
+                .iter()
+                .any(|(_, a)| matches!(a.qualifier, ActionQualifier::S | ActionQualifier::R));
+
+            if stored {
+                let flag = stored_flag(&name);
+                self.declare(&flag, DataType::Bool);
+                // Resets come last, so R wins over S in the same scan.
+                for qualifier in [ActionQualifier::S, ActionQualifier::R] {
+                    for (step, _) in uses.iter().filter(|(_, a)| a.qualifier == qualifier) {
+                        let value = qualifier == ActionQualifier::S;
+                        self.emit(if_then(
+                            var(&step_flag(&step.name)),
+                            vec![assign(&flag, Expression::Literal(Literal::Bool(value)))],
+                        ));
+                    }
+                }
+            }
+
+            let mut terms = Vec::new();
+            if uses.iter().any(|(_, a)| a.qualifier == ActionQualifier::S) {
+                terms.push(var(&stored_flag(&name)));
+            }
+            for (step, assoc) in uses {
+                let active = var(&step_flag(&step.name));
+                let time = || var(&step_time(&step.name));
+                let duration = || -> Result<Expression> {
+                    let duration = assoc
+                        .duration
+                        .as_ref()
+                        .ok_or_else(|| anyhow!("{}: {} needs a duration", self.pou, name))?;
+                    self.rewrite_expr(&duration.node)
+                };
+                let term = match assoc.qualifier {
+                    ActionQualifier::N => active,
+                    ActionQualifier::S | ActionQualifier::R => continue,
+                    ActionQualifier::P => {
+                        binary(active, BinaryOp::And, not(var(&step_prev(&step.name))))
+                    }
+                    ActionQualifier::L => binary(
+                        active,
+                        BinaryOp::And,
+                        binary(time(), BinaryOp::Lt, duration()?),
+                    ),
+                    ActionQualifier::D => binary(
+                        active,
+                        BinaryOp::And,
+                        binary(time(), BinaryOp::Ge, duration()?),
+                    ),
+                };
+                terms.push(term);
+            }
+
+            let condition = terms
+                .into_iter()
+                .reduce(|a, b| binary(a, BinaryOp::Or, b))
+                .unwrap_or(Expression::Literal(Literal::Bool(false)));
+
+            match bodies.get(&key) {
+                Some(action) => {
+                    let body = action
+                        .body
+                        .iter()
+                        .map(|s| Ok(Spanned::new(self.rewrite_stmt(&s.node)?, s.span)))
+                        .collect::<Result<Vec<_>>>()?;
+                    self.body.push(Spanned::new(
+                        Statement::If(IfStatement {
+                            condition: Spanned::new(condition, Span::default()),
+                            then_branch: body,
+                            elsif_branches: Vec::new(),
+                            else_branch: None,
+                        }),
+                        Span::default(),
+                    ));
+                }
+                // Without an ACTION body the association drives a BOOL variable.
+                None => self.emit(assign(&name, condition)),
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_step(&self, name: &str) -> Option<&String> {
+        self.steps.get(&name.to_uppercase())
+    }
+
+    fn resolve_steps(&self, names: &[String]) -> Result<Vec<String>> {
+        names
+            .iter()
+            .map(|name| {
+                self.resolve_step(name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("{}: unknown step {}", self.pou, name))
+            })
+            .collect()
+    }
+
+    fn rewrite_stmt(&self, stmt: &Statement) -> Result<Statement> {
+        let block = |stmts: &[Spanned<Statement>]| -> Result<Vec<Spanned<Statement>>> {
+            stmts
+                .iter()
+                .map(|s| Ok(Spanned::new(self.rewrite_stmt(&s.node)?, s.span)))
+                .collect()
+        };
+        Ok(match stmt {
+            Statement::Assignment(a) => Statement::Assignment(Assignment {
+                target: self.rewrite_spanned(&a.target)?,
+                value: self.rewrite_spanned(&a.value)?,
+            }),
+            Statement::If(s) => Statement::If(IfStatement {
+                condition: self.rewrite_spanned(&s.condition)?,
+                then_branch: block(&s.then_branch)?,
+                elsif_branches: s
+                    .elsif_branches
+                    .iter()
+                    .map(|b| {
+                        Ok(ElsifBranch {
+                            condition: self.rewrite_spanned(&b.condition)?,
+                            statements: block(&b.statements)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+                else_branch: s.else_branch.as_deref().map(block).transpose()?,
+            }),
+            Statement::Case(s) => Statement::Case(CaseStatement {
+                selector: self.rewrite_spanned(&s.selector)?,
+                branches: s
+                    .branches
+                    .iter()
+                    .map(|b| {
+                        Ok(CaseBranch {
+                            values: b.values.clone(),
+                            statements: block(&b.statements)?,
+                        })
+                    })
+                    .collect::<Result<_>>()?,
+                else_branch: s.else_branch.as_deref().map(block).transpose()?,
+            }),
+            Statement::For(s) => Statement::For(ForStatement {
+                variable: s.variable.clone(),
+                from: self.rewrite_spanned(&s.from)?,
+                to: self.rewrite_spanned(&s.to)?,
+                by: s.by.as_ref().map(|e| self.rewrite_spanned(e)).transpose()?,
+                body: block(&s.body)?,
+                bound: s.bound,
+            }),
+            Statement::While(s) => Statement::While(WhileStatement {
+                condition: self.rewrite_spanned(&s.condition)?,
+                body: block(&s.body)?,
+                bound: s.bound,
+            }),
+            Statement::Repeat(s) => Statement::Repeat(RepeatStatement {
+                body: block(&s.body)?,
+                until: self.rewrite_spanned(&s.until)?,
+                bound: s.bound,
+            }),
+            Statement::Return(e) => {
+                Statement::Return(e.as_ref().map(|e| self.rewrite_spanned(e)).transpose()?)
+            }
+            Statement::Call(c) => Statement::Call(CallStatement {
+                name: c.name.clone(),
+                arguments: self.rewrite_args(&c.arguments)?,
+            }),
+            Statement::Exit | Statement::Continue | Statement::Empty => stmt.clone(),
+        })
+    }
+
+    fn rewrite_spanned(&self, expr: &Spanned<Expression>) -> Result<Spanned<Expression>> {
+        Ok(Spanned::new(self.rewrite_expr(&expr.node)?, expr.span))
+    }
+
+    fn rewrite_args(&self, args: &[CallArgument]) -> Result<Vec<CallArgument>> {
+        args.iter()
+            .map(|a| {
+                Ok(CallArgument {
+                    name: a.name.clone(),
+                    value: self.rewrite_spanned(&a.value)?,
+                })
+            })
+            .collect()
+    }
+
+    fn rewrite_expr(&self, expr: &Expression) -> Result<Expression> {
+        Ok(match expr {
+            Expression::FieldAccess { object, field } => {
+                let step = match &object.node {
+                    Expression::Variable(name) => self.resolve_step(name),
+                    _ => None,
+                };
+                match step {
+                    Some(step) => match field.to_uppercase().as_str() {
+                        "X" => var(&step_flag(step)),
+                        "T" => var(&step_time(step)),
+                        _ => {
+                            return Err(anyhow!(
+                                "{}: step {} has no field {}; use .X or .T",
+                                self.pou,
+                                step,
+                                field
+                            ))
+                        }
+                    },
+                    None => Expression::FieldAccess {
+                        object: Box::new(self.rewrite_spanned(object)?),
+                        field: field.clone(),
+                    },
+                }
+            }
+            Expression::ArrayAccess { array, index } => Expression::ArrayAccess {
+                array: Box::new(self.rewrite_spanned(array)?),
+                index: Box::new(self.rewrite_spanned(index)?),
+            },
+            Expression::Binary { left, op, right } => Expression::Binary {
+                left: Box::new(self.rewrite_spanned(left)?),
+                op: *op,
+                right: Box::new(self.rewrite_spanned(right)?),
+            },
+            Expression::Unary { op, operand } => Expression::Unary {
+                op: *op,
+                operand: Box::new(self.rewrite_spanned(operand)?),
+            },
+            Expression::Call { name, arguments } => Expression::Call {
+                name: name.clone(),
+                arguments: self.rewrite_args(arguments)?,
+            },
+            Expression::Paren(inner) => Expression::Paren(Box::new(self.rewrite_spanned(inner)?)),
+            Expression::Literal(_) | Expression::Variable(_) => expr.clone(),
+        })
+    }
+
+    fn declare(&mut self, name: &str, data_type: DataType) {
+        self.declarations.push(Spanned::new(
+            VarDecl {
+                name: name.to_string(),
+                data_type,
+                initial_value: None,
+                address: None,
+            },
+            Span::default(),
+        ));
+    }
+
+    fn emit(&mut self, stmt: Statement) {
+        self.body.push(Spanned::new(stmt, Span::default()));
+    }
+}
+
+fn var(name: &str) -> Expression {
+    Expression::Variable(name.to_string())
+}
+
+fn paren(expr: Expression) -> Expression {
+    Expression::Paren(Box::new(Spanned::new(expr, Span::default())))
+}
+
+fn not(expr: Expression) -> Expression {
+    Expression::Unary {
+        op: UnaryOp::Not,
+        operand: Box::new(Spanned::new(expr, Span::default())),
+    }
+}
+
+fn binary(left: Expression, op: BinaryOp, right: Expression) -> Expression {
+    Expression::Binary {
+        left: Box::new(Spanned::new(left, Span::default())),
+        op,
+        right: Box::new(Spanned::new(right, Span::default())),
+    }
+}
+
+fn assign(target: &str, value: Expression) -> Statement {
+    Statement::Assignment(Assignment {
+        target: Spanned::new(var(target), Span::default()),
+        value: Spanned::new(value, Span::default()),
+    })
+}
+
+fn if_then(condition: Expression, statements: Vec<Statement>) -> Statement {
+    Statement::If(IfStatement {
+        condition: Spanned::new(condition, Span::default()),
+        then_branch: statements
+            .into_iter()
+            .map(|s| Spanned::new(s, Span::default()))
+            .collect(),
+        elsif_branches: Vec::new(),
+        else_branch: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::parse;
+
+    fn network(source: &str) -> SfcNetwork {
+        let unit = parse(source).unwrap();
+        match &unit.units[0].node {
+            ProgramUnit::Program(p) => p.sfc.clone().expect("SFC body"),
+            other => panic!("expected program, got {other:?}"),
+        }
+    }
+
+    const FILLING: &str = r#"
+        PROGRAM Filling
+        VAR
+            start : BOOL;
+            full : BOOL;
+            valve : BOOL;
+        END_VAR
+            INITIAL_STEP Idle:
+            END_STEP
+            STEP Fill:
+                OpenValve(N);
+                Alarm(D, T#10s);
+            END_STEP
+            TRANSITION FROM Idle TO Fill := start;
+            END_TRANSITION
+            TRANSITION FROM Fill TO Idle := full OR Fill.T > T#20s;
+            END_TRANSITION
+            ACTION OpenValve:
+                valve := TRUE;
+            END_ACTION
+        END_PROGRAM
+    "#;
+
+    #[test]
+    fn test_lower_declares_step_state() {
+        let lowered = lower("Filling", &network(FILLING)).unwrap();
+        assert_eq!(lowered.steps, vec!["Idle", "Fill"]);
+
+        let names: Vec<_> = lowered
+            .variables
+            .declarations
+            .iter()
+            .map(|d| d.node.name.as_str())
+            .collect();
+        for expected in ["__sfc_Idle_X", "__sfc_Fill_X", "__sfc_Fill_T", "__sfc_t0"] {
+            assert!(names.contains(&expected), "missing {expected}: {names:?}");
+        }
+    }
+
+    #[test]
+    fn test_lower_rewrites_step_fields() {
+        let lowered = lower("Filling", &network(FILLING)).unwrap();
+        let text = format!("{:?}", lowered.body);
+        assert!(text.contains("__sfc_Fill_T"));
+        assert!(!text.contains("FieldAccess"));
+        // An association without an ACTION drives the variable directly.
+        assert!(lowered.body.iter().any(|s| matches!(
+            &s.node,
+            Statement::Assignment(a) if a.target.node == var("Alarm")
+        )));
+    }
+
+    #[test]
+    fn test_lower_errors() {
+        let err = lower(
+            "P",
+            &network(
+                "PROGRAM P
+                    STEP A: END_STEP
+                 END_PROGRAM",
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("INITIAL_STEP"));
+
+        let err = lower(
+            "P",
+            &network(
+                "PROGRAM P
+                    INITIAL_STEP A: END_STEP
+                    TRANSITION FROM A TO B := TRUE; END_TRANSITION
+                 END_PROGRAM",
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown step B"));
+    }
+}
@@ -2,12 +2,21 @@
 //!
 //! Uses a stack-based IR similar to WebAssembly for easy code generation.
 
-use crate::frontend::{BinaryOp, DataType, UnaryOp};
+use crate::cache::{BuildCache, Dependency, LoweredEntry};
+use crate::frontend::{sfc, BinaryOp, DataType, Spanned, UnaryOp, VarBlockKind};
 use crate::typechecker::{
-    TypedExpr, TypedExprKind, TypedFunction, TypedFunctionBlock, TypedLiteral, TypedPou,
-    TypedProgram, TypedStatement, TypedUnit,
+    host_data_type, SymbolInfo, SymbolTable, TypedExpr, TypedExprKind, TypedFunction,
+    TypedFunctionBlock, TypedLiteral, TypedPou, TypedProgram, TypedStatement, TypedUnit,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use plc_common::abi::ProcessImageLayout;
+use plc_common::build_cache::CacheKey;
+use plc_common::debug_map::PouKind;
+use plc_common::host_api::{self, HostFunction};
+use plc_common::sfc::StepFlag;
+use plc_common::symbols::{Direction, Symbol};
+use plc_common::unit_test::TestCase;
+use std::collections::HashMap;
 
 /// An IR module containing functions and data.
 #[derive(Debug, Clone)]
@@ -18,6 +27,14 @@ pub struct Module {
     pub data: Vec<u8>,
     /// Total memory size needed.
     pub memory_size: usize,
+    /// Activity flags of all SFC steps.
+    pub sfc_steps: Vec<StepFlag>,
+    /// Variables in linear memory, ordered by offset.
+    pub symbols: Vec<Symbol>,
+    /// The test this module runs, for modules built by the test runner.
+    pub test: Option<TestCase>,
+    /// Process image the module was compiled for.
+    pub layout: ProcessImageLayout,
 }
 
 /// An IR function.
@@ -27,10 +44,32 @@ pub struct IrFunction {
     pub name: String,
     /// Whether this is the main step function.
     pub is_step: bool,
+    /// Name of the POU this function was lowered from.
+    pub pou: String,
+    /// Kind of the POU.
+    pub pou_kind: PouKind,
+    /// Source line of the POU declaration.
+    pub line: u32,
+    /// Source file of the POU, if known; recorded in the debug line map.
+    pub file: String,
     /// Local variable count (for Wasm).
     pub locals: Vec<LocalVar>,
     /// IR instructions.
     pub body: Vec<Instruction>,
+    /// Memory slots of the POU's scalar `VAR_TEMP` variables.
+    ///
+    /// Their values do not need to survive the call, which lets the
+    /// optimiser keep them in Wasm locals instead.
+    pub temp_slots: Vec<TempSlot>,
+}
+
+/// The memory slot of a `VAR_TEMP` variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TempSlot {
+    /// Byte offset in memory.
+    pub offset: u32,
+    /// Size in bytes.
+    pub size: u32,
 }
 
 /// A local variable.
@@ -78,7 +117,7 @@ impl WasmType {
 }
 
 /// IR instructions (stack-based).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     // Constants
     /// Push i32 constant.
@@ -206,6 +245,20 @@ pub enum Instruction {
     /// i32 equal to zero.
     I32Eqz,
 
+    // Comparison - i64
+    /// i64 equal.
+    I64Eq,
+    /// i64 not equal.
+    I64Ne,
+    /// i64 signed less than.
+    I64LtS,
+    /// i64 signed less or equal.
+    I64LeS,
+    /// i64 signed greater than.
+    I64GtS,
+    /// i64 signed greater or equal.
+    I64GeS,
+
     // Comparison - f32
     /// f32 equal.
     F32Eq,
@@ -213,6 +266,12 @@ pub enum Instruction {
     F32Lt,
     /// f32 greater than.
     F32Gt,
+    /// f32 not equal.
+    F32Ne,
+    /// f32 less or equal.
+    F32Le,
+    /// f32 greater or equal.
+    F32Ge,
 
     // Comparison - f64
     /// f64 equal.
@@ -221,6 +280,12 @@ pub enum Instruction {
     F64Lt,
     /// f64 greater than.
     F64Gt,
+    /// f64 not equal.
+    F64Ne,
+    /// f64 less or equal.
+    F64Le,
+    /// f64 greater or equal.
+    F64Ge,
 
     // Logical/Bitwise
     /// i32 bitwise and.
@@ -251,6 +316,14 @@ pub enum Instruction {
     F64PromoteF32,
     /// Demote f64 to f32.
     F32DemoteF64,
+    /// Convert i64 to f32.
+    F32ConvertI64S,
+    /// Convert i64 to f64.
+    F64ConvertI64S,
+    /// Truncate f32 to i64.
+    I64TruncF32S,
+    /// Truncate f64 to i64.
+    I64TruncF64S,
 
     // Control flow
     /// Unconditional branch.
@@ -286,12 +359,127 @@ pub enum Instruction {
 
     // No operation (for labels).
     Nop,
+
+    // Debug information
+    /// Marks the start of the code generated for a source line; emits no
+    /// Wasm instruction.
+    SourceLine(u32),
+    /// Maximum iterations of the `Loop` that follows, for execution-time
+    /// analysis; emits no Wasm instruction.
+    LoopBound(u64),
+}
+
+/// Memory slots of the scalar `VAR_TEMP` variables in a symbol table,
+/// ordered by offset.
+fn temp_slots(symbols: &SymbolTable) -> Vec<TempSlot> {
+    let mut slots: Vec<TempSlot> = symbols
+        .variables
+        .values()
+        .filter(|info| info.kind == VarBlockKind::Temp)
+        .filter(|info| {
+            !matches!(
+                info.data_type,
+                DataType::Array { .. }
+                    | DataType::String(_)
+                    | DataType::WString(_)
+                    | DataType::Named(_)
+            )
+        })
+        .map(|info| TempSlot {
+            offset: info.offset as u32,
+            size: info.size as u32,
+        })
+        .collect();
+    slots.sort_by_key(|slot| slot.offset);
+    slots
+}
+
+/// The type both operands of a binary operation are converted to: the
+/// result type for arithmetic, the wider operand type for comparisons.
+fn operand_type(op: BinaryOp, result: &DataType, left: &DataType, right: &DataType) -> DataType {
+    match op {
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            let rank = |ty: &DataType| match WasmType::from_data_type(ty) {
+                WasmType::I32 => 0,
+                WasmType::I64 => 1,
+                WasmType::F32 => 2,
+                WasmType::F64 => 3,
+            };
+            if rank(right) > rank(left) {
+                right.clone()
+            } else {
+                left.clone()
+            }
+        }
+        BinaryOp::Shl | BinaryOp::Shr => left.clone(),
+        _ => result.clone(),
+    }
 }
 
 /// Lower typed AST to IR.
 pub fn lower(typed: &TypedUnit) -> Result<Module> {
     let mut lowerer = IrLowerer::new();
-    lowerer.lower_unit(typed)
+    lowerer.lower_unit(typed, None)
+}
+
+/// Lower typed AST to IR, reusing POUs lowered by earlier builds.
+///
+/// `typed` must come from [`check_cached`](crate::typechecker::check_cached)
+/// with the same cache.
+pub fn lower_cached(typed: &TypedUnit, cache: &mut BuildCache) -> Result<Module> {
+    let mut lowerer = IrLowerer::new();
+    lowerer.lower_unit(typed, Some(cache))
+}
+
+/// Describe every variable of the unit for the symbol table section.
+///
+/// POU variables are qualified with the POU name; globals are listed once,
+/// unqualified. Hidden SFC variables and variables of unknown size are left
+/// out.
+fn collect_symbols(typed: &TypedUnit) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = typed
+        .globals
+        .values()
+        .filter_map(|info| symbol(info.name.clone(), info, Direction::Global))
+        .collect();
+
+    for pou in &typed.units {
+        let (pou_name, table) = match pou {
+            TypedPou::Program(p) => (&p.name, &p.symbols),
+            TypedPou::FunctionBlock(fb) => (&fb.name, &fb.symbols),
+            TypedPou::Function(f) => (&f.name, &f.symbols),
+        };
+        for info in table.variables.values() {
+            let direction = match info.kind {
+                VarBlockKind::Var => Direction::Local,
+                VarBlockKind::Input => Direction::Input,
+                VarBlockKind::Output => Direction::Output,
+                VarBlockKind::InOut => Direction::InOut,
+                VarBlockKind::Temp => Direction::Temp,
+                // Listed with the globals.
+                VarBlockKind::Global | VarBlockKind::External => continue,
+            };
+            if info.name.starts_with(sfc::SFC_PREFIX) {
+                continue;
+            }
+            symbols.extend(symbol(format!("{pou_name}.{}", info.name), info, direction));
+        }
+    }
+
+    symbols.sort_by(|a, b| (a.offset, &a.name).cmp(&(b.offset, &b.name)));
+    symbols
+}
+
+fn symbol(name: String, info: &SymbolInfo, direction: Direction) -> Option<Symbol> {
+    let size = info.data_type.size_bytes()?;
+    Some(Symbol {
+        name,
+        data_type: info.data_type.to_string(),
+        offset: info.offset as u32,
+        size: size as u32,
+        direction,
+        retain: info.retain,
+    })
 }
 
 struct IrLowerer {
@@ -305,6 +493,39 @@ struct IrLowerer {
     loop_depth: u32,
     /// Memory size.
     memory_size: usize,
+    /// Parameter and result slots of every callable POU.
+    frames: HashMap<String, CallFrame>,
+    /// SFC step flags collected from the lowered POUs.
+    sfc_steps: Vec<StepFlag>,
+}
+
+/// Where a caller writes a user POU's inputs and reads its result.
+///
+/// User functions and function blocks are compiled to `() -> ()` Wasm
+/// functions that work on their own memory region, so arguments are passed
+/// by storing them into the callee's VAR_INPUT slots before the call.
+#[derive(Debug, Clone, Default)]
+struct CallFrame {
+    /// VAR_INPUT slots in declaration order: (offset, type).
+    inputs: Vec<(usize, DataType)>,
+    /// Return value slot, for functions.
+    result: Option<(usize, DataType)>,
+}
+
+impl CallFrame {
+    fn from_symbols(symbols: &SymbolTable, result_var: Option<&str>) -> Self {
+        let inputs = symbols
+            .layout
+            .iter()
+            .filter_map(|slot| symbols.variables.get(&slot.name))
+            .filter(|info| info.kind == VarBlockKind::Input)
+            .map(|info| (info.offset, info.data_type.clone()))
+            .collect();
+        let result = result_var
+            .and_then(|name| symbols.variables.get(name))
+            .map(|info| (info.offset, info.data_type.clone()));
+        Self { inputs, result }
+    }
 }
 
 impl IrLowerer {
@@ -315,6 +536,8 @@ impl IrLowerer {
             current_locals: Vec::new(),
             loop_depth: 0,
             memory_size: 0x1000, // 4KB default
+            frames: HashMap::new(),
+            sfc_steps: Vec::new(),
         }
     }
 
@@ -328,25 +551,95 @@ impl IrLowerer {
         idx
     }
 
-    fn lower_unit(&mut self, typed: &TypedUnit) -> Result<Module> {
+    fn lower_unit(
+        &mut self,
+        typed: &TypedUnit,
+        mut cache: Option<&mut BuildCache>,
+    ) -> Result<Module> {
         for pou in &typed.units {
+            match pou {
+                TypedPou::FunctionBlock(fb) => {
+                    let frame = CallFrame::from_symbols(&fb.symbols, None);
+                    self.frames.insert(fb.name.clone(), frame);
+                }
+                TypedPou::Function(f) => {
+                    let frame = CallFrame::from_symbols(&f.symbols, Some(&f.name));
+                    self.frames.insert(f.name.clone(), frame);
+                }
+                TypedPou::Program(_) => {}
+            }
+        }
+
+        for (i, pou) in typed.units.iter().enumerate() {
+            let key = match (cache.as_deref_mut(), typed.cache_keys.get(i)) {
+                (Some(cache), Some(typed_key)) => Some(self.cache_key(cache, typed_key)),
+                _ => None,
+            };
+            if let (Some(cache), Some(key)) = (cache.as_deref_mut(), &key) {
+                if let Some(entry) = cache.lowered(key) {
+                    self.functions.extend(entry.functions.iter().cloned());
+                    self.sfc_steps.extend(entry.sfc_steps.iter().cloned());
+                    continue;
+                }
+            }
+
+            let (functions, sfc_steps) = (self.functions.len(), self.sfc_steps.len());
             match pou {
                 TypedPou::Program(p) => self.lower_program(p)?,
                 TypedPou::FunctionBlock(fb) => self.lower_function_block(fb)?,
                 TypedPou::Function(f) => self.lower_function(f)?,
             }
+            if let (Some(cache), Some(key)) = (cache.as_deref_mut(), key) {
+                let entry = LoweredEntry {
+                    functions: self.functions[functions..].to_vec(),
+                    sfc_steps: self.sfc_steps[sfc_steps..].to_vec(),
+                };
+                cache.insert_lowered(key, entry);
+            }
         }
 
         Ok(Module {
             functions: self.functions.clone(),
             data: Vec::new(),
             memory_size: self.memory_size,
+            sfc_steps: self.sfc_steps.clone(),
+            symbols: collect_symbols(typed),
+            test: None,
+            layout: ProcessImageLayout::CURRENT,
         })
     }
 
+    /// Cache key of a POU's IR: its typed form, which depends on the
+    /// interfaces it was checked against, and the call frames of the POUs it
+    /// calls, which fix where arguments are stored.
+    fn cache_key(&self, cache: &mut BuildCache, typed_key: &str) -> String {
+        let mut key = CacheKey::new("lowered");
+        key.add(typed_key);
+        let deps = cache.typed(typed_key).map(|entry| entry.deps.as_slice());
+        for (dep, fingerprint) in deps.unwrap_or_default() {
+            key.add(fingerprint);
+            if let Dependency::Function(name) = dep {
+                key.add(format!("{:?}", self.frames.get(name)));
+            }
+        }
+        key.finish()
+    }
+
+    fn record_sfc_steps(&mut self, pou: &str, steps: &[String], symbols: &SymbolTable) {
+        for step in steps {
+            if let Some(info) = symbols.variables.get(&sfc::step_flag(step)) {
+                self.sfc_steps.push(StepFlag {
+                    name: format!("{pou}.{step}"),
+                    offset: info.offset as u32,
+                });
+            }
+        }
+    }
+
     fn lower_program(&mut self, program: &TypedProgram) -> Result<()> {
         self.current_body.clear();
         self.current_locals.clear();
+        self.record_sfc_steps(&program.name, &program.sfc_steps, &program.symbols);
 
         // Lower all statements
         for stmt in &program.body {
@@ -357,8 +650,13 @@ impl IrLowerer {
         let step_fn = IrFunction {
             name: "step".to_string(),
             is_step: true,
+            pou: program.name.clone(),
+            pou_kind: PouKind::Program,
+            line: program.line as u32,
+            file: String::new(),
             locals: std::mem::take(&mut self.current_locals),
             body: std::mem::take(&mut self.current_body),
+            temp_slots: temp_slots(&program.symbols),
         };
 
         self.functions.push(step_fn);
@@ -368,6 +666,7 @@ impl IrLowerer {
     fn lower_function_block(&mut self, fb: &TypedFunctionBlock) -> Result<()> {
         self.current_body.clear();
         self.current_locals.clear();
+        self.record_sfc_steps(&fb.name, &fb.sfc_steps, &fb.symbols);
 
         for stmt in &fb.body {
             self.lower_statement(stmt)?;
@@ -376,8 +675,13 @@ impl IrLowerer {
         let func = IrFunction {
             name: fb.name.clone(),
             is_step: false,
+            pou: fb.name.clone(),
+            pou_kind: PouKind::FunctionBlock,
+            line: fb.line as u32,
+            file: String::new(),
             locals: std::mem::take(&mut self.current_locals),
             body: std::mem::take(&mut self.current_body),
+            temp_slots: temp_slots(&fb.symbols),
         };
 
         self.functions.push(func);
@@ -395,21 +699,32 @@ impl IrLowerer {
         let ir_func = IrFunction {
             name: func.name.clone(),
             is_step: false,
+            pou: func.name.clone(),
+            pou_kind: PouKind::Function,
+            line: func.line as u32,
+            file: String::new(),
             locals: std::mem::take(&mut self.current_locals),
             body: std::mem::take(&mut self.current_body),
+            temp_slots: temp_slots(&func.symbols),
         };
 
         self.functions.push(ir_func);
         Ok(())
     }
 
-    fn lower_statement(&mut self, stmt: &TypedStatement) -> Result<()> {
-        match stmt {
+    fn lower_statement(&mut self, stmt: &Spanned<TypedStatement>) -> Result<()> {
+        // Generated statements (e.g. from SFC lowering) have no source line.
+        if stmt.span.line > 0 {
+            self.current_body
+                .push(Instruction::SourceLine(stmt.span.line as u32));
+        }
+        match &stmt.node {
             TypedStatement::Assignment { target, value } => {
                 // Push address for store
                 self.push_address(target)?;
                 // Push value
                 self.lower_expr(value)?;
+                self.emit_convert(&value.ty, &target.ty);
                 // Store based on type
                 self.emit_store(&target.ty)?;
             }
@@ -428,14 +743,19 @@ impl IrLowerer {
                 to,
                 by,
                 body,
+                bound,
             } => {
-                self.lower_for(*var_offset, from, to, by.as_ref(), body)?;
+                self.lower_for(*var_offset, from, to, by.as_ref(), body, *bound)?;
             }
-            TypedStatement::While { condition, body } => {
-                self.lower_while(condition, body)?;
+            TypedStatement::While {
+                condition,
+                body,
+                bound,
+            } => {
+                self.lower_while(condition, body, *bound)?;
             }
-            TypedStatement::Repeat { body, until } => {
-                self.lower_repeat(body, until)?;
+            TypedStatement::Repeat { body, until, bound } => {
+                self.lower_repeat(body, until, *bound)?;
             }
             TypedStatement::Case {
                 selector,
@@ -462,18 +782,14 @@ impl IrLowerer {
             TypedStatement::Call {
                 name,
                 arguments,
+                params,
                 is_user_defined,
             } => {
-                for arg in arguments {
-                    self.lower_expr(arg)?;
-                }
                 if *is_user_defined {
-                    self.current_body.push(Instruction::CallUser(name.clone()));
-                } else {
-                    self.current_body.push(Instruction::CallHost(name.clone()));
+                    self.lower_user_call(name, arguments, params)?;
+                } else if self.lower_host_call(name, arguments)?.result.is_some() {
+                    self.current_body.push(Instruction::Drop);
                 }
-                // Drop result if any
-                self.current_body.push(Instruction::Drop);
             }
             TypedStatement::Empty => {}
         }
@@ -483,9 +799,9 @@ impl IrLowerer {
     fn lower_if(
         &mut self,
         condition: &TypedExpr,
-        then_branch: &[TypedStatement],
-        elsif_branches: &[(TypedExpr, Vec<TypedStatement>)],
-        else_branch: &Option<Vec<TypedStatement>>,
+        then_branch: &[Spanned<TypedStatement>],
+        elsif_branches: &[(TypedExpr, Vec<Spanned<TypedStatement>>)],
+        else_branch: &Option<Vec<Spanned<TypedStatement>>>,
     ) -> Result<()> {
         // Evaluate condition
         self.lower_expr(condition)?;
@@ -530,7 +846,8 @@ impl IrLowerer {
         from: &TypedExpr,
         to: &TypedExpr,
         by: Option<&TypedExpr>,
-        body: &[TypedStatement],
+        body: &[Spanned<TypedStatement>],
+        bound: Option<u64>,
     ) -> Result<()> {
         // Initialize loop variable: var := from
         self.current_body.push(Instruction::I32Const(0)); // Base address
@@ -542,7 +859,7 @@ impl IrLowerer {
         // Block for break
         self.current_body.push(Instruction::Block);
         // Loop for iteration
-        self.current_body.push(Instruction::Loop);
+        self.push_loop(bound);
         self.loop_depth += 1;
 
         // Check condition: var <= to
@@ -585,9 +902,22 @@ impl IrLowerer {
         Ok(())
     }
 
-    fn lower_while(&mut self, condition: &TypedExpr, body: &[TypedStatement]) -> Result<()> {
-        self.current_body.push(Instruction::Block);
+    /// Open a loop, annotated with its iteration bound if known.
+    fn push_loop(&mut self, bound: Option<u64>) {
+        if let Some(bound) = bound {
+            self.current_body.push(Instruction::LoopBound(bound));
+        }
         self.current_body.push(Instruction::Loop);
+    }
+
+    fn lower_while(
+        &mut self,
+        condition: &TypedExpr,
+        body: &[Spanned<TypedStatement>],
+        bound: Option<u64>,
+    ) -> Result<()> {
+        self.current_body.push(Instruction::Block);
+        self.push_loop(bound);
         self.loop_depth += 1;
 
         // Check condition
@@ -610,9 +940,14 @@ impl IrLowerer {
         Ok(())
     }
 
-    fn lower_repeat(&mut self, body: &[TypedStatement], until: &TypedExpr) -> Result<()> {
+    fn lower_repeat(
+        &mut self,
+        body: &[Spanned<TypedStatement>],
+        until: &TypedExpr,
+        bound: Option<u64>,
+    ) -> Result<()> {
         self.current_body.push(Instruction::Block);
-        self.current_body.push(Instruction::Loop);
+        self.push_loop(bound);
         self.loop_depth += 1;
 
         // Body first
@@ -637,8 +972,8 @@ impl IrLowerer {
     fn lower_case(
         &mut self,
         selector: &TypedExpr,
-        branches: &[(Vec<i64>, Vec<TypedStatement>)],
-        else_branch: &Option<Vec<TypedStatement>>,
+        branches: &[(Vec<i64>, Vec<Spanned<TypedStatement>>)],
+        else_branch: &Option<Vec<Spanned<TypedStatement>>>,
     ) -> Result<()> {
         // Allocate a temp local for the selector value
         let selector_local = self.alloc_temp_local(WasmType::I32);
@@ -730,9 +1065,12 @@ impl IrLowerer {
                 self.emit_load(&expr.ty, *field_offset as u32)?;
             }
             TypedExprKind::Binary { left, op, right } => {
+                let operand_ty = operand_type(*op, &expr.ty, &left.ty, &right.ty);
                 self.lower_expr(left)?;
+                self.emit_convert(&left.ty, &operand_ty);
                 self.lower_expr(right)?;
-                self.emit_binary_op(*op, &left.ty)?;
+                self.emit_convert(&right.ty, &operand_ty);
+                self.emit_binary_op(*op, &operand_ty)?;
             }
             TypedExprKind::Unary { op, operand } => {
                 self.lower_expr(operand)?;
@@ -741,21 +1079,72 @@ impl IrLowerer {
             TypedExprKind::Call {
                 name,
                 arguments,
+                params,
                 is_user_defined,
             } => {
-                for arg in arguments {
-                    self.lower_expr(arg)?;
-                }
                 if *is_user_defined {
-                    self.current_body.push(Instruction::CallUser(name.clone()));
+                    self.lower_user_call(name, arguments, params)?;
+                    let (offset, ty) = self
+                        .frames
+                        .get(name)
+                        .and_then(|frame| frame.result.clone())
+                        .ok_or_else(|| anyhow!("{} does not return a value", name))?;
+                    self.current_body.push(Instruction::I32Const(0));
+                    self.emit_load(&ty, offset as u32)?;
                 } else {
-                    self.current_body.push(Instruction::CallHost(name.clone()));
+                    self.lower_host_call(name, arguments)?;
                 }
             }
         }
         Ok(())
     }
 
+    /// Push the arguments, converted to the parameter types, and call a
+    /// host function.
+    fn lower_host_call(
+        &mut self,
+        name: &str,
+        arguments: &[TypedExpr],
+    ) -> Result<&'static HostFunction> {
+        let host =
+            host_api::find(name).ok_or_else(|| anyhow!("Unknown host function: {}", name))?;
+        for (arg, (_, ty)) in arguments.iter().zip(host.params) {
+            self.lower_expr(arg)?;
+            self.emit_convert(&arg.ty, &host_data_type(*ty));
+        }
+        self.current_body
+            .push(Instruction::CallHost(name.to_string()));
+        Ok(host)
+    }
+
+    /// Store the arguments into the callee's input slots, then call it.
+    fn lower_user_call(
+        &mut self,
+        name: &str,
+        arguments: &[TypedExpr],
+        params: &[usize],
+    ) -> Result<()> {
+        let frame = self
+            .frames
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown function: {}", name))?;
+        for (arg, &param) in arguments.iter().zip(params) {
+            let (offset, ty) = frame
+                .inputs
+                .get(param)
+                .ok_or_else(|| anyhow!("Too many arguments for {}", name))?;
+            self.current_body
+                .push(Instruction::I32Const(*offset as i32));
+            self.lower_expr(arg)?;
+            self.emit_convert(&arg.ty, ty);
+            self.emit_store(ty)?;
+        }
+        self.current_body
+            .push(Instruction::CallUser(name.to_string()));
+        Ok(())
+    }
+
     fn lower_literal(&mut self, lit: &TypedLiteral) {
         match lit {
             TypedLiteral::Bool(v) => {
@@ -861,6 +1250,27 @@ impl IrLowerer {
         Ok(())
     }
 
+    /// Convert the value on top of the stack between Wasm representations.
+    fn emit_convert(&mut self, from: &DataType, to: &DataType) {
+        use WasmType::*;
+        let conversion = match (WasmType::from_data_type(from), WasmType::from_data_type(to)) {
+            (I32, I64) => Instruction::I64ExtendI32S,
+            (I32, F32) => Instruction::F32ConvertI32S,
+            (I32, F64) => Instruction::F64ConvertI32S,
+            (I64, I32) => Instruction::I32WrapI64,
+            (I64, F32) => Instruction::F32ConvertI64S,
+            (I64, F64) => Instruction::F64ConvertI64S,
+            (F32, I32) => Instruction::I32TruncF32S,
+            (F32, I64) => Instruction::I64TruncF32S,
+            (F32, F64) => Instruction::F64PromoteF32,
+            (F64, I32) => Instruction::I32TruncF64S,
+            (F64, I64) => Instruction::I64TruncF64S,
+            (F64, F32) => Instruction::F32DemoteF64,
+            _ => return,
+        };
+        self.current_body.push(conversion);
+    }
+
     fn emit_binary_op(&mut self, op: BinaryOp, ty: &DataType) -> Result<()> {
         let is_float = matches!(ty, DataType::Real | DataType::Lreal);
         let is_64 = matches!(
@@ -924,39 +1334,57 @@ impl IrLowerer {
             BinaryOp::Mod => {
                 self.current_body.push(Instruction::I32RemS);
             }
-            BinaryOp::Eq => {
-                if is_float {
-                    if is_64 {
-                        self.current_body.push(Instruction::F64Eq);
-                    } else {
-                        self.current_body.push(Instruction::F32Eq);
-                    }
-                } else {
-                    self.current_body.push(Instruction::I32Eq);
-                }
-            }
-            BinaryOp::Ne => {
-                self.current_body.push(Instruction::I32Ne);
-            }
-            BinaryOp::Lt => {
-                if is_float {
-                    self.current_body.push(Instruction::F32Lt);
-                } else {
-                    self.current_body.push(Instruction::I32LtS);
-                }
-            }
-            BinaryOp::Le => {
-                self.current_body.push(Instruction::I32LeS);
-            }
-            BinaryOp::Gt => {
-                if is_float {
-                    self.current_body.push(Instruction::F32Gt);
-                } else {
-                    self.current_body.push(Instruction::I32GtS);
-                }
-            }
-            BinaryOp::Ge => {
-                self.current_body.push(Instruction::I32GeS);
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => {
+                // Columns: i32, i64, f32, f64
+                let [i32_op, i64_op, f32_op, f64_op] = match op {
+                    BinaryOp::Eq => [
+                        Instruction::I32Eq,
+                        Instruction::I64Eq,
+                        Instruction::F32Eq,
+                        Instruction::F64Eq,
+                    ],
+                    BinaryOp::Ne => [
+                        Instruction::I32Ne,
+                        Instruction::I64Ne,
+                        Instruction::F32Ne,
+                        Instruction::F64Ne,
+                    ],
+                    BinaryOp::Lt => [
+                        Instruction::I32LtS,
+                        Instruction::I64LtS,
+                        Instruction::F32Lt,
+                        Instruction::F64Lt,
+                    ],
+                    BinaryOp::Le => [
+                        Instruction::I32LeS,
+                        Instruction::I64LeS,
+                        Instruction::F32Le,
+                        Instruction::F64Le,
+                    ],
+                    BinaryOp::Gt => [
+                        Instruction::I32GtS,
+                        Instruction::I64GtS,
+                        Instruction::F32Gt,
+                        Instruction::F64Gt,
+                    ],
+                    _ => [
+                        Instruction::I32GeS,
+                        Instruction::I64GeS,
+                        Instruction::F32Ge,
+                        Instruction::F64Ge,
+                    ],
+                };
+                self.current_body.push(match WasmType::from_data_type(ty) {
+                    WasmType::I32 => i32_op,
+                    WasmType::I64 => i64_op,
+                    WasmType::F32 => f32_op,
+                    WasmType::F64 => f64_op,
+                });
             }
             BinaryOp::And | BinaryOp::BitAnd => {
                 self.current_body.push(Instruction::I32And);
@@ -1038,4 +1466,59 @@ mod tests {
         assert_eq!(module.functions.len(), 1);
         assert!(module.functions[0].is_step);
     }
+
+    #[test]
+    fn test_symbols() {
+        let source = r#"
+            FUNCTION_BLOCK Conveyor
+            VAR_INPUT
+                speed : INT;
+            END_VAR
+            VAR_OUTPUT
+                running : BOOL;
+            END_VAR
+            VAR_EXTERNAL
+                load : REAL;
+            END_VAR
+                running := speed > 0;
+            END_FUNCTION_BLOCK
+
+            PROGRAM Main
+            VAR_GLOBAL
+                load : REAL;
+            END_VAR
+            VAR RETAIN
+                total : DINT;
+            END_VAR
+            VAR
+                values : ARRAY[0..3] OF INT;
+            END_VAR
+                total := total + 1;
+            END_PROGRAM
+        "#;
+
+        let typed = check(&parse(source).unwrap()).unwrap();
+        let module = lower(&typed).unwrap();
+        let names: Vec<_> = module.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "load",
+                "Conveyor.speed",
+                "Conveyor.running",
+                "Main.total",
+                "Main.values"
+            ]
+        );
+
+        let total = &module.symbols[3];
+        assert_eq!(total.data_type, "DINT");
+        assert_eq!(total.size, 4);
+        assert_eq!(total.direction, Direction::Local);
+        assert!(total.retain);
+        assert_eq!(module.symbols[0].direction, Direction::Global);
+        assert_eq!(module.symbols[2].direction, Direction::Output);
+        assert_eq!(module.symbols[4].data_type, "ARRAY[0..3] OF INT");
+        assert_eq!(module.symbols[4].size, 8);
+    }
 }
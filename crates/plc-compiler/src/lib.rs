@@ -1,10 +1,15 @@
 //! IEC 61131-3 compiler pipeline targeting WebAssembly.
 //!
 //! This crate provides:
-//! - [`frontend`] - ST lexer, parser, and AST
+//! - [`frontend`] - ST and IL parsers, and the shared AST
+//! - [`cache`] - Incremental compilation
 //! - [`typechecker`] - Type checking and semantic analysis
 //! - [`ir`] - Intermediate representation
+//! - [`optimize`] - IR optimisation passes
 //! - [`codegen`] - WebAssembly code generation
+//! - [`formatter`] - Canonical ST source formatter
+//! - [`plcopen`] - PLCopen TC6 XML project import and export
+//! - [`wcet`] - Static worst-case execution (fuel) estimate
 //!
 //! # Example
 //!
@@ -24,12 +29,25 @@
 //! assert!(!wasm.is_empty());
 //! ```
 
+pub mod cache;
 pub mod codegen;
+pub mod formatter;
 pub mod frontend;
 pub mod ir;
+pub mod optimize;
+pub mod plcopen;
 pub mod typechecker;
+pub mod wcet;
 
-use frontend::CompilationUnit;
+use anyhow::{anyhow, Context};
+use cache::BuildCache;
+use frontend::{CompilationUnit, ProgramUnit};
+use plc_common::abi::ProcessImageLayout;
+use plc_common::debug_map::PouKind;
+use plc_common::unit_test;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
 
 /// Compile Structured Text source to WebAssembly.
 ///
@@ -47,11 +65,92 @@ pub fn compile(source: &str) -> anyhow::Result<Vec<u8>> {
     Compiler::new().compile_st_to_wasm(source)
 }
 
+/// Compile Instruction List source to WebAssembly.
+pub fn compile_il(source: &str) -> anyhow::Result<Vec<u8>> {
+    Compiler::new().compile_sources(&[SourceFile::new("<il>", Language::InstructionList, source)])
+}
+
+/// Source language of a compilation input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// IEC 61131-3 Structured Text.
+    StructuredText,
+    /// IEC 61131-3 Instruction List.
+    InstructionList,
+    /// PLCopen TC6 XML project, possibly containing LD and FBD networks.
+    PlcOpenXml,
+}
+
+impl Language {
+    /// Pick the language from a file extension: `.il` is Instruction List,
+    /// `.xml` is PLCopen XML, anything else is Structured Text.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("il") => Language::InstructionList,
+            Some(ext) if ext.eq_ignore_ascii_case("xml") => Language::PlcOpenXml,
+            _ => Language::StructuredText,
+        }
+    }
+}
+
+/// One source file of a project.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Display name used in error messages (usually the path).
+    pub name: String,
+    /// Source language.
+    pub language: Language,
+    /// Source text.
+    pub source: String,
+}
+
+impl SourceFile {
+    /// Create a source file with an explicit language.
+    pub fn new(name: impl Into<String>, language: Language, source: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            language,
+            source: source.into(),
+        }
+    }
+
+    /// Read a source file from disk, picking the language from its extension.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self::new(
+            path.display().to_string(),
+            Language::from_path(path),
+            source,
+        ))
+    }
+}
+
+/// Compile a project made of several ST and/or IL source files into one
+/// WebAssembly module. POUs from all files share one namespace, so an IL
+/// function block can be called from an ST program and vice versa.
+pub fn compile_project(sources: &[SourceFile]) -> anyhow::Result<Vec<u8>> {
+    Compiler::new().compile_sources(sources)
+}
+
+/// A `TEST_` function block compiled into its own module.
+#[derive(Debug, Clone)]
+pub struct TestModule {
+    /// Test function block name.
+    pub name: String,
+    /// WebAssembly module whose `step` export runs the test body.
+    pub wasm: Vec<u8>,
+}
+
 /// The main compiler driver.
 #[derive(Debug, Default)]
 pub struct Compiler {
     /// Enable debug output.
     pub debug: bool,
+    /// Optimisation level applied to the IR before Wasm emission.
+    pub opt_level: optimize::OptLevel,
+    /// Process image the module is compiled for; must fit in the runtime's.
+    pub process_image: ProcessImageLayout,
 }
 
 impl Compiler {
@@ -73,11 +172,156 @@ impl Compiler {
         // 1. Parse source into AST
         let ast = self.parse(source)?;
 
-        // 2. Type check the AST
+        self.compile_ast(&ast)
+    }
+
+    /// Compile a multi-file, mixed-language project to WebAssembly.
+    ///
+    /// Each file is parsed with the frontend for its language and the POUs
+    /// are merged into a single compilation unit before type checking.
+    pub fn compile_sources(&self, sources: &[SourceFile]) -> anyhow::Result<Vec<u8>> {
+        let (ast, files) = self.parse_sources_with_files(sources)?;
+        self.compile_unit(&ast, &files, None)
+    }
+
+    /// Like [`compile_sources`](Self::compile_sources), reusing what earlier
+    /// builds with the same cache produced.
+    ///
+    /// An unchanged project is taken from the cache's artifact store without
+    /// compiling; otherwise only the POUs that changed, or whose callees'
+    /// interfaces changed, are type checked and lowered again.
+    pub fn compile_sources_cached(
+        &self,
+        sources: &[SourceFile],
+        cache: &mut BuildCache,
+    ) -> anyhow::Result<Vec<u8>> {
+        cache.start_build();
+        let key = cache::module_key(sources, self.opt_level, &self.process_image);
+        if let Some(wasm) = cache.module(&key) {
+            return Ok(wasm);
+        }
+
+        let (ast, files) = self.parse_sources_with_files(sources)?;
+        let wasm = self.compile_unit(&ast, &files, Some(cache))?;
+        cache.finish_build();
+        cache.insert_module(&key, &wasm);
+        Ok(wasm)
+    }
+
+    /// Parse and merge several source files, rejecting duplicate POU names.
+    pub fn parse_sources(&self, sources: &[SourceFile]) -> anyhow::Result<CompilationUnit> {
+        self.parse_sources_with_files(sources).map(|(ast, _)| ast)
+    }
+
+    /// Like [`parse_sources`](Self::parse_sources), also returning the file
+    /// each POU came from, keyed by upper-cased POU name.
+    fn parse_sources_with_files(
+        &self,
+        sources: &[SourceFile],
+    ) -> anyhow::Result<(CompilationUnit, HashMap<String, String>)> {
+        let mut units = Vec::new();
+        let mut globals = Vec::new();
+        let mut defined_in: HashMap<String, String> = HashMap::new();
+
+        for file in sources {
+            let ast = match file.language {
+                Language::StructuredText => frontend::parse(&file.source),
+                Language::InstructionList => frontend::parse_il(&file.source),
+                Language::PlcOpenXml => plcopen::import(&file.source).map(|project| project.unit),
+            }
+            .with_context(|| format!("in {}", file.name))?;
+
+            for unit in ast.units {
+                let name = match &unit.node {
+                    ProgramUnit::Program(p) => &p.name,
+                    ProgramUnit::FunctionBlock(fb) => &fb.name,
+                    ProgramUnit::Function(f) => &f.name,
+                };
+                if let Some(previous) = defined_in.insert(name.to_uppercase(), file.name.clone()) {
+                    return Err(anyhow!(
+                        "POU {} is defined in both {} and {}",
+                        name,
+                        previous,
+                        file.name
+                    ));
+                }
+                units.push(unit);
+            }
+            globals.extend(ast.globals);
+        }
+
+        Ok((CompilationUnit { units, globals }, defined_in))
+    }
+
+    /// Compile an already parsed compilation unit, e.g. one imported from
+    /// PLCopen XML.
+    pub fn compile_ast(&self, ast: &CompilationUnit) -> anyhow::Result<Vec<u8>> {
+        self.compile_unit(ast, &HashMap::new(), None)
+    }
+
+    /// Compile every `TEST_` function block of a project into its own
+    /// module, for the test runner.
+    ///
+    /// Each module contains the whole project, with the test body as the
+    /// `step` function, so tests are isolated from each other.
+    pub fn compile_tests(&self, sources: &[SourceFile]) -> anyhow::Result<Vec<TestModule>> {
+        let (ast, files) = self.parse_sources_with_files(sources)?;
         let typed_ast = self.type_check(&ast)?;
+        let mut ir_module = self.generate_ir(&typed_ast)?;
+        ir_module.layout = self.process_image;
+        set_files(&mut ir_module, &files);
 
-        // 3. Generate IR
-        let ir_module = self.generate_ir(&typed_ast)?;
+        let tests = typed_ast.units.iter().filter_map(|pou| match pou {
+            typechecker::TypedPou::FunctionBlock(fb) => fb.test.clone(),
+            _ => None,
+        });
+        tests
+            .map(|mut test| {
+                if let Some(file) = files.get(&test.name.to_uppercase()) {
+                    test.file = file.clone();
+                }
+                let mut module = ir_module.clone();
+                for func in &mut module.functions {
+                    func.is_step = func.pou_kind == PouKind::FunctionBlock && func.pou == test.name;
+                }
+                let name = test.name.clone();
+                module.test = Some(test);
+                optimize::optimize(&mut module, self.opt_level);
+                let wasm = self
+                    .generate_wasm(&module)
+                    .with_context(|| format!("in test {name}"))?;
+                Ok(TestModule { name, wasm })
+            })
+            .collect()
+    }
+
+    /// Compile a parsed unit; `files` names the source file of each POU for
+    /// the debug line map.
+    fn compile_unit(
+        &self,
+        ast: &CompilationUnit,
+        files: &HashMap<String, String>,
+        cache: Option<&mut BuildCache>,
+    ) -> anyhow::Result<Vec<u8>> {
+        // Tests only run under the test runner; keep them out of deployed
+        // modules.
+        let ast = without_tests(ast);
+
+        // 2. Type check the AST and 3. generate IR, reusing unchanged POUs
+        let mut ir_module = match cache {
+            Some(cache) => {
+                let typed_ast = typechecker::check_cached(&ast, cache, &self.process_image)?;
+                ir::lower_cached(&typed_ast, cache)?
+            }
+            None => {
+                let typed_ast = self.type_check(&ast)?;
+                self.generate_ir(&typed_ast)?
+            }
+        };
+
+        ir_module.layout = self.process_image;
+        set_files(&mut ir_module, files);
+        optimize::optimize(&mut ir_module, self.opt_level);
 
         // 4. Generate Wasm
         let wasm = self.generate_wasm(&ir_module)?;
@@ -92,7 +336,7 @@ impl Compiler {
 
     /// Type check the AST.
     fn type_check(&self, ast: &CompilationUnit) -> anyhow::Result<typechecker::TypedUnit> {
-        typechecker::check(ast)
+        typechecker::check_for(ast, &self.process_image)
     }
 
     /// Generate IR from typed AST.
@@ -105,3 +349,29 @@ impl Compiler {
         codegen::emit(ir_module)
     }
 }
+
+/// Record the source file of every function for the debug line map.
+fn set_files(ir_module: &mut ir::Module, files: &HashMap<String, String>) {
+    for func in &mut ir_module.functions {
+        if let Some(file) = files.get(&func.pou.to_uppercase()) {
+            func.file = file.clone();
+        }
+    }
+}
+
+/// The unit without its `TEST_` function blocks.
+fn without_tests(ast: &CompilationUnit) -> Cow<'_, CompilationUnit> {
+    if !ast.units.iter().any(|unit| is_test(&unit.node)) {
+        return Cow::Borrowed(ast);
+    }
+    let mut stripped = ast.clone();
+    stripped.units.retain(|unit| !is_test(&unit.node));
+    Cow::Owned(stripped)
+}
+
+fn is_test(unit: &ProgramUnit) -> bool {
+    match unit {
+        ProgramUnit::FunctionBlock(fb) => unit_test::is_test(&fb.name),
+        _ => false,
+    }
+}
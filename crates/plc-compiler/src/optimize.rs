@@ -0,0 +1,1097 @@
+//! IR optimisation passes.
+//!
+//! [`optimize`] rewrites an [`ir::Module`](crate::ir::Module) in place
+//! between lowering and Wasm emission. The passes work directly on the
+//! structured stack IR and never change what a program does: process image
+//! writes, host calls and every variable that outlives a call see the same
+//! values as in the unoptimised module.
+//!
+//! - Constant folding of integer, float, comparison and conversion
+//!   operations with Wasm semantics (wrapping integers; anything that would
+//!   trap is left for run time), including `if`/`br_if` on constants.
+//! - Peephole simplification of address arithmetic and algebraic
+//!   identities: `x + 0`, `(x + a) + b`, `x * 2^k` and friends.
+//! - Dead-code elimination after unconditional branches and of empty or
+//!   constant-condition blocks.
+//! - Promotion of `VAR_TEMP` memory slots into Wasm locals, followed by
+//!   copy propagation (`local.set x; local.get x` becomes `local.tee x`).
+//! - Dead-store elimination of memory stores that are overwritten before
+//!   anything can read them.
+//!
+//! Out-of-bounds array accesses are assumed not to alias `VAR_TEMP`
+//! variables; all other memory accesses keep their exact addresses.
+
+use crate::ir::{Instruction, IrFunction, LocalVar, Module, WasmType};
+use plc_common::host_api;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// How much optimisation to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    /// No optimisation; the IR is emitted as lowered.
+    O0,
+    /// Constant folding, peephole simplification and dead-code elimination.
+    #[default]
+    O1,
+    /// Everything in `O1`, plus `VAR_TEMP` promotion into Wasm locals and
+    /// dead-store elimination.
+    O2,
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches(['O', 'o']) {
+            "0" => Ok(OptLevel::O0),
+            "1" => Ok(OptLevel::O1),
+            "2" => Ok(OptLevel::O2),
+            _ => Err(format!(
+                "invalid optimisation level '{s}' (expected 0, 1 or 2)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OptLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            OptLevel::O0 => 0,
+            OptLevel::O1 => 1,
+            OptLevel::O2 => 2,
+        };
+        write!(f, "O{level}")
+    }
+}
+
+/// Upper bound on rounds of the pass pipeline per function; each round
+/// usually exposes fewer new opportunities than the one before.
+const MAX_ROUNDS: usize = 8;
+
+/// Optimise every function of a module at the given level.
+pub fn optimize(module: &mut Module, level: OptLevel) {
+    if level == OptLevel::O0 {
+        return;
+    }
+    for func in &mut module.functions {
+        optimize_function(func, level);
+    }
+}
+
+fn optimize_function(func: &mut IrFunction, level: OptLevel) {
+    if level >= OptLevel::O2 {
+        promote_temps(func);
+    }
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = fold_constants(&mut func.body);
+        changed |= simplify(&mut func.body);
+        changed |= remove_dead_code(&mut func.body);
+        if level >= OptLevel::O2 {
+            changed |= eliminate_dead_stores(&mut func.body);
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Instruction properties
+// ---------------------------------------------------------------------------
+
+/// Number of values an instruction pops and pushes, or `None` for control
+/// flow.
+fn stack_effect(instr: &Instruction) -> Option<(usize, usize)> {
+    use Instruction::*;
+    let effect = match instr {
+        CallHost(name) => {
+            let host = host_api::find(name)?;
+            (host.params.len(), host.result.iter().count())
+        }
+        CallUser(_) => (0, 0),
+        I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_) | LocalGet(_) => (0, 1),
+        I32Load { .. }
+        | I64Load { .. }
+        | F32Load { .. }
+        | F64Load { .. }
+        | I32Load8S { .. }
+        | I32Load16S { .. } => (1, 1),
+        I32Store { .. }
+        | I64Store { .. }
+        | F32Store { .. }
+        | F64Store { .. }
+        | I32Store8 { .. }
+        | I32Store16 { .. } => (2, 0),
+        LocalSet(_) | Drop => (1, 0),
+        LocalTee(_) | I32Eqz | I32WrapI64 | I64ExtendI32S | F32ConvertI32S | F64ConvertI32S
+        | I32TruncF32S | I32TruncF64S | F64PromoteF32 | F32DemoteF64 | F32ConvertI64S
+        | F64ConvertI64S | I64TruncF32S | I64TruncF64S => (1, 1),
+        I32Add | I32Sub | I32Mul | I32DivS | I32RemS | I64Add | I64Sub | I64Mul | I64DivS
+        | F32Add | F32Sub | F32Mul | F32Div | F64Add | F64Sub | F64Mul | F64Div | I32Eq | I32Ne
+        | I32LtS | I32LeS | I32GtS | I32GeS | I64Eq | I64Ne | I64LtS | I64LeS | I64GtS | I64GeS
+        | F32Eq | F32Lt | F32Gt | F32Ne | F32Le | F32Ge | F64Eq | F64Lt | F64Gt | F64Ne | F64Le
+        | F64Ge | I32And | I32Or | I32Xor | I32Shl | I32ShrS => (2, 1),
+        Select => (3, 1),
+        Nop | SourceLine(_) | LoopBound(_) => (0, 0),
+        Br(_) | BrIf(_) | Block | Loop | End | If | Else | Return | Call(_) => return None,
+    };
+    Some(effect)
+}
+
+fn is_call(instr: &Instruction) -> bool {
+    matches!(
+        instr,
+        Instruction::Call(_) | Instruction::CallHost(_) | Instruction::CallUser(_)
+    )
+}
+
+/// Whether an instruction can trap for some operands.
+fn may_trap(instr: &Instruction) -> bool {
+    use Instruction::*;
+    matches!(
+        instr,
+        I32DivS | I32RemS | I64DivS | I32TruncF32S | I32TruncF64S | I64TruncF32S | I64TruncF64S
+    )
+}
+
+/// Whether an instruction always produces 0 or 1.
+fn is_boolean(instr: &Instruction) -> bool {
+    use Instruction::*;
+    matches!(
+        instr,
+        I32Eqz
+            | I32Eq
+            | I32Ne
+            | I32LtS
+            | I32LeS
+            | I32GtS
+            | I32GeS
+            | I64Eq
+            | I64Ne
+            | I64LtS
+            | I64LeS
+            | I64GtS
+            | I64GeS
+            | F32Eq
+            | F32Lt
+            | F32Gt
+            | F32Ne
+            | F32Le
+            | F32Ge
+            | F64Eq
+            | F64Lt
+            | F64Gt
+            | F64Ne
+            | F64Le
+            | F64Ge
+    )
+}
+
+/// A memory load or store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Access {
+    store: bool,
+    offset: u32,
+    width: u32,
+    ty: WasmType,
+}
+
+fn memory_access(instr: &Instruction) -> Option<Access> {
+    use Instruction::*;
+    let (store, offset, width, ty) = match *instr {
+        I32Load { offset } => (false, offset, 4, WasmType::I32),
+        I64Load { offset } => (false, offset, 8, WasmType::I64),
+        F32Load { offset } => (false, offset, 4, WasmType::F32),
+        F64Load { offset } => (false, offset, 8, WasmType::F64),
+        I32Load8S { offset } => (false, offset, 1, WasmType::I32),
+        I32Load16S { offset } => (false, offset, 2, WasmType::I32),
+        I32Store { offset } => (true, offset, 4, WasmType::I32),
+        I64Store { offset } => (true, offset, 8, WasmType::I64),
+        F32Store { offset } => (true, offset, 4, WasmType::F32),
+        F64Store { offset } => (true, offset, 8, WasmType::F64),
+        I32Store8 { offset } => (true, offset, 1, WasmType::I32),
+        I32Store16 { offset } => (true, offset, 2, WasmType::I32),
+        _ => return None,
+    };
+    Some(Access {
+        store,
+        offset,
+        width,
+        ty,
+    })
+}
+
+/// Index of the first instruction of the code computing the operands
+/// `n..=0` (counted from the top of the stack) of the instruction at `pos`.
+///
+/// Returns `None` if that code is not straight-line.
+fn operand_start(body: &[Instruction], pos: usize, n: usize) -> Option<usize> {
+    let mut needed = n + 1;
+    for i in (0..pos).rev() {
+        let (pops, pushes) = stack_effect(&body[i])?;
+        needed = needed.checked_sub(pushes)? + pops;
+        if needed == 0 {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// The constant address of the memory access at `pos`, together with the
+/// index of the `i32.const` pushing its base.
+fn static_address(body: &[Instruction], pos: usize, access: Access) -> Option<(u32, usize)> {
+    let base = if access.store {
+        let base = operand_start(body, pos, 1)?;
+        (operand_start(body, pos, 0)? == base + 1).then_some(base)?
+    } else {
+        pos.checked_sub(1)?
+    };
+    match body[base] {
+        Instruction::I32Const(address) if address >= 0 => {
+            Some(((address as u32).checked_add(access.offset)?, base))
+        }
+        _ => None,
+    }
+}
+
+/// Whether the straight-line code in `code` can be removed without changing
+/// behaviour: no side effects, no traps, no loads from computed addresses.
+fn is_removable(code: &[Instruction]) -> bool {
+    code.iter().enumerate().all(|(i, instr)| {
+        if let Some(access) = memory_access(instr) {
+            !access.store && i > 0 && matches!(code[i - 1], Instruction::I32Const(a) if a >= 0)
+        } else {
+            stack_effect(instr).is_some()
+                && !is_call(instr)
+                && !may_trap(instr)
+                && !matches!(instr, Instruction::LocalSet(_) | Instruction::LocalTee(_))
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Constant folding
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy)]
+enum Const {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl Const {
+    fn of(instr: &Instruction) -> Option<Self> {
+        match *instr {
+            Instruction::I32Const(v) => Some(Const::I32(v)),
+            Instruction::I64Const(v) => Some(Const::I64(v)),
+            Instruction::F32Const(v) => Some(Const::F32(v)),
+            Instruction::F64Const(v) => Some(Const::F64(v)),
+            _ => None,
+        }
+    }
+
+    /// The instruction pushing this constant; NaN results are not folded
+    /// because Wasm leaves their bit pattern to the engine.
+    fn instruction(self) -> Option<Instruction> {
+        match self {
+            Const::I32(v) => Some(Instruction::I32Const(v)),
+            Const::I64(v) => Some(Instruction::I64Const(v)),
+            Const::F32(v) if !v.is_nan() => Some(Instruction::F32Const(v)),
+            Const::F64(v) if !v.is_nan() => Some(Instruction::F64Const(v)),
+            _ => None,
+        }
+    }
+}
+
+fn flag(b: bool) -> Const {
+    Const::I32(i32::from(b))
+}
+
+fn fold_binary(op: &Instruction, a: Const, b: Const) -> Option<Const> {
+    use Instruction::*;
+    let result = match (a, b) {
+        (Const::I32(a), Const::I32(b)) => match op {
+            I32Add => Const::I32(a.wrapping_add(b)),
+            I32Sub => Const::I32(a.wrapping_sub(b)),
+            I32Mul => Const::I32(a.wrapping_mul(b)),
+            I32DivS => Const::I32(a.checked_div(b)?),
+            I32RemS if b != 0 => Const::I32(a.wrapping_rem(b)),
+            I32And => Const::I32(a & b),
+            I32Or => Const::I32(a | b),
+            I32Xor => Const::I32(a ^ b),
+            I32Shl => Const::I32(a.wrapping_shl(b as u32)),
+            I32ShrS => Const::I32(a.wrapping_shr(b as u32)),
+            I32Eq => flag(a == b),
+            I32Ne => flag(a != b),
+            I32LtS => flag(a < b),
+            I32LeS => flag(a <= b),
+            I32GtS => flag(a > b),
+            I32GeS => flag(a >= b),
+            _ => return None,
+        },
+        (Const::I64(a), Const::I64(b)) => match op {
+            I64Add => Const::I64(a.wrapping_add(b)),
+            I64Sub => Const::I64(a.wrapping_sub(b)),
+            I64Mul => Const::I64(a.wrapping_mul(b)),
+            I64DivS => Const::I64(a.checked_div(b)?),
+            I64Eq => flag(a == b),
+            I64Ne => flag(a != b),
+            I64LtS => flag(a < b),
+            I64LeS => flag(a <= b),
+            I64GtS => flag(a > b),
+            I64GeS => flag(a >= b),
+            _ => return None,
+        },
+        (Const::F32(a), Const::F32(b)) => match op {
+            F32Add => Const::F32(a + b),
+            F32Sub => Const::F32(a - b),
+            F32Mul => Const::F32(a * b),
+            F32Div => Const::F32(a / b),
+            F32Eq => flag(a == b),
+            F32Ne => flag(a != b),
+            F32Lt => flag(a < b),
+            F32Le => flag(a <= b),
+            F32Gt => flag(a > b),
+            F32Ge => flag(a >= b),
+            _ => return None,
+        },
+        (Const::F64(a), Const::F64(b)) => match op {
+            F64Add => Const::F64(a + b),
+            F64Sub => Const::F64(a - b),
+            F64Mul => Const::F64(a * b),
+            F64Div => Const::F64(a / b),
+            F64Eq => flag(a == b),
+            F64Ne => flag(a != b),
+            F64Lt => flag(a < b),
+            F64Le => flag(a <= b),
+            F64Gt => flag(a > b),
+            F64Ge => flag(a >= b),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Truncate toward zero, or `None` where Wasm would trap.
+fn truncate(v: f64, min: f64, max_exclusive: f64) -> Option<f64> {
+    let t = v.trunc();
+    (t >= min && t < max_exclusive).then_some(t)
+}
+
+fn fold_unary(op: &Instruction, a: Const) -> Option<Const> {
+    use Instruction::*;
+    const I32_RANGE: (f64, f64) = (-2_147_483_648.0, 2_147_483_648.0);
+    const I64_RANGE: (f64, f64) = (-9_223_372_036_854_775_808.0, 9_223_372_036_854_775_808.0);
+    let result = match (op, a) {
+        (I32Eqz, Const::I32(v)) => flag(v == 0),
+        (I32WrapI64, Const::I64(v)) => Const::I32(v as i32),
+        (I64ExtendI32S, Const::I32(v)) => Const::I64(i64::from(v)),
+        (F32ConvertI32S, Const::I32(v)) => Const::F32(v as f32),
+        (F64ConvertI32S, Const::I32(v)) => Const::F64(f64::from(v)),
+        (F32ConvertI64S, Const::I64(v)) => Const::F32(v as f32),
+        (F64ConvertI64S, Const::I64(v)) => Const::F64(v as f64),
+        (F64PromoteF32, Const::F32(v)) => Const::F64(f64::from(v)),
+        (F32DemoteF64, Const::F64(v)) => Const::F32(v as f32),
+        (I32TruncF32S, Const::F32(v)) => {
+            Const::I32(truncate(f64::from(v), I32_RANGE.0, I32_RANGE.1)? as i32)
+        }
+        (I32TruncF64S, Const::F64(v)) => Const::I32(truncate(v, I32_RANGE.0, I32_RANGE.1)? as i32),
+        (I64TruncF32S, Const::F32(v)) => {
+            Const::I64(truncate(f64::from(v), I64_RANGE.0, I64_RANGE.1)? as i64)
+        }
+        (I64TruncF64S, Const::F64(v)) => Const::I64(truncate(v, I64_RANGE.0, I64_RANGE.1)? as i64),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Evaluate operations whose operands are all constants.
+fn fold_constants(body: &mut Vec<Instruction>) -> bool {
+    let mut out: Vec<Instruction> = Vec::with_capacity(body.len());
+    let mut changed = false;
+    for instr in body.drain(..) {
+        out.push(instr);
+        loop {
+            let n = out.len();
+            let folded = match out.as_slice() {
+                [.., a, b, op] => match (Const::of(a), Const::of(b)) {
+                    (Some(a), Some(b)) => fold_binary(op, a, b)
+                        .and_then(Const::instruction)
+                        .map(|c| (3, vec![c])),
+                    _ => None,
+                },
+                _ => None,
+            }
+            .or_else(|| match out.as_slice() {
+                [.., Instruction::I32Const(c), Instruction::BrIf(depth)] => Some((
+                    2,
+                    if *c != 0 {
+                        vec![Instruction::Br(*depth)]
+                    } else {
+                        Vec::new()
+                    },
+                )),
+                [.., a, op] => Const::of(a)
+                    .and_then(|a| fold_unary(op, a))
+                    .and_then(Const::instruction)
+                    .map(|c| (2, vec![c])),
+                _ => None,
+            })
+            .or_else(|| match out.as_slice() {
+                [.., a, b, Instruction::I32Const(c), Instruction::Select]
+                    if Const::of(a).is_some() && Const::of(b).is_some() =>
+                {
+                    Some((4, vec![if *c != 0 { a.clone() } else { b.clone() }]))
+                }
+                _ => None,
+            });
+            match folded {
+                Some((len, replacement)) => {
+                    out.truncate(n - len);
+                    out.extend(replacement);
+                    changed = true;
+                }
+                None => break,
+            }
+        }
+    }
+    *body = out;
+    changed
+}
+
+// ---------------------------------------------------------------------------
+// Peephole simplification
+// ---------------------------------------------------------------------------
+
+/// Rewrite a short window at the end of `out`, returning how many
+/// instructions to replace and with what.
+fn peephole(out: &[Instruction]) -> Option<(usize, Vec<Instruction>)> {
+    use Instruction::*;
+    let rewrite = match out {
+        // Address arithmetic: merge constant offsets, `(x + a) + b`.
+        [.., I32Const(a), op1 @ (I32Add | I32Sub), I32Const(b), op2 @ (I32Add | I32Sub)] => {
+            let a = if *op1 == I32Sub { a.wrapping_neg() } else { *a };
+            let b = if *op2 == I32Sub { b.wrapping_neg() } else { *b };
+            (4, vec![I32Const(a.wrapping_add(b)), I32Add])
+        }
+        // `0 + x` for a single-instruction `x`.
+        [.., I32Const(0), x @ (I32Const(_) | LocalGet(_)), I32Add] => (3, vec![x.clone()]),
+        // Identities with a constant right operand.
+        [.., I32Const(0), I32Add | I32Sub | I32Or | I32Xor | I32Shl | I32ShrS] => (2, vec![]),
+        [.., I32Const(1), I32Mul | I32DivS] => (2, vec![]),
+        [.., I64Const(0), I64Add | I64Sub] => (2, vec![]),
+        [.., I64Const(1), I64Mul | I64DivS] => (2, vec![]),
+        // Scaling by element sizes: `x * 2^k` is `x << k`.
+        [.., I32Const(k), I32Mul] if *k > 1 && (*k as u32).is_power_of_two() => {
+            (2, vec![I32Const(k.trailing_zeros() as i32), I32Shl])
+        }
+        // Repeated NOT.
+        [.., I32Const(a), I32Xor, I32Const(b), I32Xor] => (4, vec![I32Const(a ^ b), I32Xor]),
+        [.., I32Eqz, I32Eqz, I32Eqz] => (3, vec![I32Eqz]),
+        [.., I32Eqz, I32Eqz, op @ (If | BrIf(_))] => (3, vec![op.clone()]),
+        // Copy propagation through locals.
+        [.., LocalSet(a), LocalGet(b)] if a == b => (2, vec![LocalTee(*a)]),
+        [.., LocalTee(a), Drop] => (2, vec![LocalSet(*a)]),
+        [.., I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_) | LocalGet(_), Drop] => {
+            (2, vec![])
+        }
+        _ => return None,
+    };
+    Some(rewrite)
+}
+
+fn simplify(body: &mut Vec<Instruction>) -> bool {
+    let mut out: Vec<Instruction> = Vec::with_capacity(body.len());
+    let mut changed = false;
+    for instr in body.drain(..) {
+        out.push(instr);
+        while let Some((len, replacement)) = peephole(&out) {
+            let n = out.len();
+            out.truncate(n - len);
+            out.extend(replacement);
+            changed = true;
+        }
+    }
+    *body = out;
+    changed
+}
+
+// ---------------------------------------------------------------------------
+// Dead-code elimination
+// ---------------------------------------------------------------------------
+
+/// Index of the `else` (if any) and `end` closing the block opened at
+/// `open`.
+fn block_end(body: &[Instruction], open: usize) -> (Option<usize>, usize) {
+    let mut depth = 0usize;
+    let mut else_at = None;
+    for (i, instr) in body.iter().enumerate().skip(open + 1) {
+        match instr {
+            Instruction::Block | Instruction::Loop | Instruction::If => depth += 1,
+            Instruction::Else if depth == 0 => else_at = Some(i),
+            Instruction::End if depth == 0 => return (else_at, i),
+            Instruction::End => depth -= 1,
+            _ => {}
+        }
+    }
+    // The function body itself is closed by the emitter.
+    (else_at, body.len())
+}
+
+fn remove_dead_code(body: &mut Vec<Instruction>) -> bool {
+    let mut changed = remove_unreachable(body);
+    changed |= remove_trivial_blocks(body);
+    changed
+}
+
+/// Drop everything between an unconditional branch and the end of its block.
+fn remove_unreachable(body: &mut Vec<Instruction>) -> bool {
+    let mut out = Vec::with_capacity(body.len());
+    let mut changed = false;
+    let mut i = 0;
+    while i < body.len() {
+        let instr = body[i].clone();
+        i += 1;
+        let diverges = matches!(instr, Instruction::Br(_) | Instruction::Return);
+        out.push(instr);
+        if diverges {
+            let mut depth = 0usize;
+            while i < body.len() {
+                match body[i] {
+                    Instruction::Block | Instruction::Loop | Instruction::If => depth += 1,
+                    Instruction::Else | Instruction::End if depth == 0 => break,
+                    Instruction::End => depth -= 1,
+                    _ => {}
+                }
+                i += 1;
+                changed = true;
+            }
+        }
+    }
+    *body = out;
+    changed
+}
+
+/// Resolve `if` on constant conditions and remove blocks with no effect.
+fn remove_trivial_blocks(body: &mut Vec<Instruction>) -> bool {
+    use Instruction::*;
+    for i in 0..body.len() {
+        match (&body[i], body.get(i + 1)) {
+            (I32Const(c), Some(If)) => {
+                let taken = *c != 0;
+                let (else_at, end) = block_end(body, i + 1);
+                // The surviving arm becomes a plain block so branch depths
+                // inside it stay valid.
+                let arm: Vec<Instruction> = match (taken, else_at) {
+                    (true, Some(e)) => body[i + 2..e].to_vec(),
+                    (true, None) => body[i + 2..end].to_vec(),
+                    (false, Some(e)) => body[e + 1..end].to_vec(),
+                    (false, None) => Vec::new(),
+                };
+                let replacement = if arm.is_empty() {
+                    Vec::new()
+                } else {
+                    std::iter::once(Block)
+                        .chain(arm)
+                        .chain(std::iter::once(End))
+                        .collect()
+                };
+                body.splice(i..=end, replacement);
+                return true;
+            }
+            (Block | Loop, Some(End)) => {
+                body.drain(i..i + 2);
+                return true;
+            }
+            (If, Some(End)) => {
+                body.splice(i..i + 2, [Drop]);
+                return true;
+            }
+            (If, Some(Else)) => {
+                let (_, end) = block_end(body, i);
+                if end == i + 2 {
+                    // Nothing in either arm.
+                    body.splice(i..=end, [Drop]);
+                } else {
+                    body.splice(i..i + 2, [I32Eqz, If]);
+                }
+                return true;
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+// ---------------------------------------------------------------------------
+// Dead-store elimination
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy)]
+struct PendingStore {
+    /// First instruction of the store's address and value code.
+    start: usize,
+    /// The store instruction.
+    end: usize,
+    address: u32,
+    width: u32,
+}
+
+impl PendingStore {
+    fn overlaps(&self, address: u32, width: u32) -> bool {
+        self.address < address + width && address < self.address + self.width
+    }
+}
+
+/// Remove stores to a constant address that are overwritten by a later
+/// store in the same straight-line code before any load could observe them.
+fn eliminate_dead_stores(body: &mut Vec<Instruction>) -> bool {
+    let mut pending: Vec<PendingStore> = Vec::new();
+    let mut dead: Vec<(usize, usize)> = Vec::new();
+
+    for pos in 0..body.len() {
+        let Some(access) = memory_access(&body[pos]) else {
+            // Callees may read any variable.
+            if stack_effect(&body[pos]).is_none() || is_call(&body[pos]) {
+                pending.clear();
+            }
+            continue;
+        };
+        let address = static_address(body, pos, access);
+        if !access.store {
+            match address {
+                Some((address, _)) => pending.retain(|s| !s.overlaps(address, access.width)),
+                None => pending.clear(),
+            }
+            continue;
+        }
+        let Some((address, start)) = address else {
+            // A store through a computed address kills nothing for certain.
+            continue;
+        };
+        if let Some(i) = pending
+            .iter()
+            .position(|s| s.address == address && s.width <= access.width)
+        {
+            let killed = pending.remove(i);
+            if is_removable(&body[killed.start + 1..killed.end]) {
+                dead.push((killed.start, killed.end));
+            }
+        }
+        pending.push(PendingStore {
+            start,
+            end: pos,
+            address,
+            width: access.width,
+        });
+    }
+
+    if dead.is_empty() {
+        return false;
+    }
+    let removed: HashSet<usize> = dead.iter().flat_map(|&(start, end)| start..=end).collect();
+    let mut i = 0;
+    body.retain(|_| {
+        let keep = !removed.contains(&i);
+        i += 1;
+        keep
+    });
+    true
+}
+
+// ---------------------------------------------------------------------------
+// VAR_TEMP promotion
+// ---------------------------------------------------------------------------
+
+/// Keep `VAR_TEMP` variables in Wasm locals instead of linear memory.
+///
+/// A slot is promoted only if every access uses its constant address with
+/// the same width and type, and every load is preceded by a store on all
+/// paths through the function, so the value left in memory by an earlier
+/// call can never be observed.
+fn promote_temps(func: &mut IrFunction) {
+    if func.temp_slots.is_empty() {
+        return;
+    }
+
+    // Classify the accesses to each slot.
+    let mut accesses: HashMap<usize, (usize, Access, usize)> = HashMap::new();
+    let mut shape: HashMap<usize, Access> = HashMap::new();
+    let mut rejected: HashSet<usize> = HashSet::new();
+    for pos in 0..func.body.len() {
+        let Some(access) = memory_access(&func.body[pos]) else {
+            continue;
+        };
+        let Some((address, base)) = static_address(&func.body, pos, access) else {
+            continue;
+        };
+        let Some(slot) = func
+            .temp_slots
+            .iter()
+            .position(|s| address < s.offset + s.size && s.offset < address + access.width)
+        else {
+            continue;
+        };
+        let temp = func.temp_slots[slot];
+        let kind = Access {
+            store: false,
+            offset: 0,
+            ..access
+        };
+        if address != temp.offset
+            || access.width != temp.size
+            || *shape.entry(slot).or_insert(kind) != kind
+        {
+            rejected.insert(slot);
+        }
+        accesses.insert(pos, (slot, access, base));
+    }
+
+    rejected.extend(read_before_write(&func.body, &accesses));
+    let promoted: Vec<usize> = (0..func.temp_slots.len())
+        .filter(|slot| shape.contains_key(slot) && !rejected.contains(slot))
+        .collect();
+    if promoted.is_empty() {
+        return;
+    }
+
+    let mut locals = HashMap::new();
+    for &slot in &promoted {
+        locals.insert(slot, func.locals.len() as u32);
+        func.locals.push(LocalVar {
+            name: format!("__temp_{}", func.temp_slots[slot].offset),
+            wasm_type: shape[&slot].ty,
+        });
+    }
+
+    let mut removed = HashSet::new();
+    let mut replaced: HashMap<usize, Vec<Instruction>> = HashMap::new();
+    for (&pos, &(slot, access, base)) in &accesses {
+        let Some(&local) = locals.get(&slot) else {
+            continue;
+        };
+        removed.insert(base);
+        let mut code = Vec::new();
+        if access.store {
+            // Memory would truncate narrow values; loads sign-extend them.
+            let shift = 32 - 8 * access.width as i32;
+            if shift > 0 && !is_boolean(&func.body[pos - 1]) {
+                code.extend([
+                    Instruction::I32Const(shift),
+                    Instruction::I32Shl,
+                    Instruction::I32Const(shift),
+                    Instruction::I32ShrS,
+                ]);
+            }
+            code.push(Instruction::LocalSet(local));
+        } else {
+            code.push(Instruction::LocalGet(local));
+        }
+        replaced.insert(pos, code);
+    }
+
+    let body = std::mem::take(&mut func.body);
+    for (pos, instr) in body.into_iter().enumerate() {
+        if removed.contains(&pos) {
+            continue;
+        }
+        match replaced.remove(&pos) {
+            Some(code) => func.body.extend(code),
+            None => func.body.push(instr),
+        }
+    }
+}
+
+/// Slots that may be loaded before being stored on some path.
+///
+/// Stores inside an `if` count after it only if both arms make them;
+/// stores inside a `block` or `loop` do not count after it, since a branch
+/// may leave it early.
+fn read_before_write(
+    body: &[Instruction],
+    accesses: &HashMap<usize, (usize, Access, usize)>,
+) -> HashSet<usize> {
+    struct Frame {
+        is_if: bool,
+        entry: HashSet<usize>,
+        then_exit: Option<HashSet<usize>>,
+    }
+
+    let mut unsafe_slots = HashSet::new();
+    let mut assigned: HashSet<usize> = HashSet::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    for (pos, instr) in body.iter().enumerate() {
+        if let Some(&(slot, access, _)) = accesses.get(&pos) {
+            if access.store {
+                assigned.insert(slot);
+            } else if !assigned.contains(&slot) {
+                unsafe_slots.insert(slot);
+            }
+            continue;
+        }
+        match instr {
+            Instruction::Block | Instruction::Loop | Instruction::If => frames.push(Frame {
+                is_if: matches!(instr, Instruction::If),
+                entry: assigned.clone(),
+                then_exit: None,
+            }),
+            Instruction::Else => {
+                if let Some(frame) = frames.last_mut() {
+                    frame.then_exit = Some(std::mem::replace(&mut assigned, frame.entry.clone()));
+                }
+            }
+            Instruction::End => {
+                if let Some(frame) = frames.pop() {
+                    assigned = match (frame.is_if, frame.then_exit) {
+                        (true, Some(then_exit)) => {
+                            assigned.intersection(&then_exit).copied().collect()
+                        }
+                        _ => frame.entry,
+                    };
+                }
+            }
+            _ => {}
+        }
+    }
+    unsafe_slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::TempSlot;
+    use plc_common::debug_map::PouKind;
+    use Instruction::*;
+
+    fn function(body: Vec<Instruction>, temp_slots: Vec<TempSlot>) -> IrFunction {
+        IrFunction {
+            name: "step".to_string(),
+            is_step: true,
+            locals: Vec::new(),
+            body,
+            temp_slots,
+            pou: "Main".to_string(),
+            pou_kind: PouKind::Program,
+            line: 1,
+            file: String::new(),
+        }
+    }
+
+    fn run(body: Vec<Instruction>, level: OptLevel) -> Vec<Instruction> {
+        let mut func = function(body, Vec::new());
+        optimize_function(&mut func, level);
+        func.body
+    }
+
+    #[test]
+    fn test_opt_level_parse() {
+        assert_eq!("2".parse::<OptLevel>().unwrap(), OptLevel::O2);
+        assert_eq!("O0".parse::<OptLevel>().unwrap(), OptLevel::O0);
+        assert!("3".parse::<OptLevel>().is_err());
+        assert_eq!(OptLevel::default().to_string(), "O1");
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        // x := (2 + 3) * 4
+        let body = vec![
+            I32Const(8),
+            I32Const(2),
+            I32Const(3),
+            I32Add,
+            I32Const(4),
+            I32Mul,
+            I32Store16 { offset: 0 },
+        ];
+        assert_eq!(
+            run(body, OptLevel::O1),
+            vec![I32Const(8), I32Const(20), I32Store16 { offset: 0 }]
+        );
+    }
+
+    #[test]
+    fn test_fold_keeps_traps() {
+        let body = vec![I32Const(1), I32Const(0), I32DivS, Drop];
+        assert_eq!(run(body.clone(), OptLevel::O1), body);
+        let body = vec![F64Const(1e300), I32TruncF64S, Drop];
+        assert_eq!(run(body.clone(), OptLevel::O1), body);
+        let body = vec![I32Const(i32::MIN), I32Const(-1), I32DivS, Drop];
+        assert_eq!(run(body.clone(), OptLevel::O1), body);
+    }
+
+    #[test]
+    fn test_peephole_address_arithmetic() {
+        // arr[i] with a constant base, element size 4 and nested offsets.
+        let body = vec![
+            LocalGet(0),
+            I32Const(4),
+            I32Mul,
+            I32Const(16),
+            I32Add,
+            I32Const(8),
+            I32Add,
+            I32Const(0),
+            I32Add,
+            I32Load { offset: 0 },
+            Drop,
+        ];
+        assert_eq!(
+            run(body, OptLevel::O1),
+            vec![
+                LocalGet(0),
+                I32Const(2),
+                I32Shl,
+                I32Const(24),
+                I32Add,
+                I32Load { offset: 0 },
+                Drop,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_if_and_unreachable_code() {
+        let body = vec![
+            I32Const(1),
+            If,
+            I32Const(0),
+            I32Const(7),
+            I32Store { offset: 4 },
+            Br(0),
+            I32Const(0),
+            I32Const(9),
+            I32Store { offset: 4 },
+            Else,
+            I32Const(0),
+            I32Const(5),
+            I32Store { offset: 4 },
+            End,
+            I32Const(0),
+            If,
+            I32Const(0),
+            I32Const(3),
+            I32Store { offset: 8 },
+            End,
+        ];
+        assert_eq!(
+            run(body, OptLevel::O1),
+            vec![
+                Block,
+                I32Const(0),
+                I32Const(7),
+                I32Store { offset: 4 },
+                Br(0),
+                End,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dead_store_elimination() {
+        let body = vec![
+            // x := 1; x := 2;  (first store is dead)
+            I32Const(0),
+            I32Const(1),
+            I32Store { offset: 4 },
+            I32Const(0),
+            I32Const(2),
+            I32Store { offset: 4 },
+            // y := 1; z := y; y := 2;  (y is read in between)
+            I32Const(8),
+            I32Const(1),
+            I32Store { offset: 0 },
+            I32Const(12),
+            I32Const(0),
+            I32Load { offset: 8 },
+            I32Store { offset: 0 },
+            I32Const(8),
+            I32Const(2),
+            I32Store { offset: 0 },
+        ];
+        let optimized = run(body.clone(), OptLevel::O2);
+        assert_eq!(
+            optimized[..3],
+            [I32Const(0), I32Const(2), I32Store { offset: 4 }]
+        );
+        assert_eq!(optimized[3..], body[6..]);
+        // O1 keeps every store.
+        assert_eq!(run(body.clone(), OptLevel::O1), body);
+    }
+
+    #[test]
+    fn test_promote_temps() {
+        let slot = TempSlot {
+            offset: 16,
+            size: 2,
+        };
+        let body = vec![
+            // tmp := x + 1; y := tmp * tmp;
+            I32Const(16),
+            I32Const(0),
+            I32Load16S { offset: 0 },
+            I32Const(1),
+            I32Add,
+            I32Store16 { offset: 0 },
+            I32Const(2),
+            I32Const(0),
+            I32Load16S { offset: 16 },
+            I32Const(0),
+            I32Load16S { offset: 16 },
+            I32Mul,
+            I32Store16 { offset: 0 },
+        ];
+        let mut func = function(body, vec![slot]);
+        optimize_function(&mut func, OptLevel::O2);
+        assert_eq!(func.locals.len(), 1);
+        assert_eq!(
+            func.body,
+            vec![
+                I32Const(0),
+                I32Load16S { offset: 0 },
+                I32Const(1),
+                I32Add,
+                I32Const(16),
+                I32Shl,
+                I32Const(16),
+                I32ShrS,
+                LocalSet(0),
+                I32Const(2),
+                LocalGet(0),
+                LocalGet(0),
+                I32Mul,
+                I32Store16 { offset: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_temp_read_before_write_stays_in_memory() {
+        let slot = TempSlot {
+            offset: 16,
+            size: 4,
+        };
+        let body = vec![
+            // IF c THEN tmp := 1; END_IF; y := tmp;
+            I32Const(0),
+            I32Load8S { offset: 0 },
+            If,
+            I32Const(16),
+            I32Const(1),
+            I32Store { offset: 0 },
+            End,
+            I32Const(4),
+            I32Const(0),
+            I32Load { offset: 16 },
+            I32Store { offset: 0 },
+        ];
+        let mut func = function(body.clone(), vec![slot]);
+        optimize_function(&mut func, OptLevel::O2);
+        assert!(func.locals.is_empty());
+        assert_eq!(func.body, body);
+    }
+}
@@ -0,0 +1,396 @@
+//! Writing a project as PLCopen TC6 XML.
+
+use super::{PlcOpenProject, TypeDeclaration, TypeKind};
+use crate::formatter::{format_expression, format_statements, FormatOptions};
+use crate::frontend::{DataType, ProgramUnit, Spanned, Statement, VarBlock, VarBlockKind, VarDecl};
+use anyhow::{bail, Result};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Namespace of the TC6 schema version written by [`export`].
+const NAMESPACE: &str = "http://www.plcopen.org/xml/tc6_0201";
+
+/// Export a project as PLCopen TC6 XML (`tc6_0201`).
+///
+/// Every POU is written with an ST body; POUs that were imported from IL,
+/// LD or FBD are therefore exported as their ST translation.
+pub fn export(project: &PlcOpenProject) -> Result<String> {
+    let mut xml = Xml::default();
+    xml.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.open(&format!(
+        r#"project xmlns="{NAMESPACE}" xmlns:xhtml="http://www.w3.org/1999/xhtml""#
+    ));
+    xml.empty(&format!(
+        r#"fileHeader companyName="virtual-plc" productName="plc-compiler" productVersion="{}" creationDateTime="{}""#,
+        env!("CARGO_PKG_VERSION"),
+        timestamp()
+    ));
+    xml.open(&format!(
+        r#"contentHeader name="{}""#,
+        escape(&project.name)
+    ));
+    xml.open("coordinateInfo");
+    for language in ["fbd", "ld", "sfc"] {
+        xml.line(&format!(
+            r#"<{language}><scaling x="1" y="1"/></{language}>"#
+        ));
+    }
+    xml.close("coordinateInfo");
+    xml.close("contentHeader");
+
+    xml.open("types");
+    if project.data_types.is_empty() {
+        xml.empty("dataTypes");
+    } else {
+        xml.open("dataTypes");
+        for decl in &project.data_types {
+            data_type_decl(&mut xml, decl);
+        }
+        xml.close("dataTypes");
+    }
+    xml.open("pous");
+    for pou in &project.unit.units {
+        self::pou(&mut xml, &pou.node)?;
+    }
+    xml.close("pous");
+    xml.close("types");
+
+    xml.open("instances");
+    xml.open("configurations");
+    if !project.tasks.is_empty() || !project.unit.globals.is_empty() {
+        xml.open(r#"configuration name="Config0""#);
+        if !project.tasks.is_empty() {
+            xml.open(r#"resource name="Res0""#);
+            for task in &project.tasks {
+                let mut attrs = format!(r#"task name="{}""#, escape(&task.name));
+                if let Some(interval) = &task.interval {
+                    attrs.push_str(&format!(r#" interval="{}""#, escape(interval)));
+                }
+                attrs.push_str(&format!(r#" priority="{}""#, task.priority));
+                xml.open(&attrs);
+                for program in &task.programs {
+                    xml.empty(&format!(
+                        r#"pouInstance name="{}" typeName="{}""#,
+                        escape(&program.name),
+                        escape(&program.type_name)
+                    ));
+                }
+                xml.close("task");
+            }
+            xml.close("resource");
+        }
+        for block in &project.unit.globals {
+            var_list(&mut xml, &block.node);
+        }
+        xml.close("configuration");
+    }
+    xml.close("configurations");
+    xml.close("instances");
+    xml.close("project");
+    Ok(xml.finish())
+}
+
+fn pou(xml: &mut Xml, pou: &ProgramUnit) -> Result<()> {
+    let (name, pou_type, variables, body, return_type) = match pou {
+        ProgramUnit::Program(p) => {
+            if p.sfc.is_some() {
+                bail!("POU {} is an SFC, which cannot be exported yet", p.name);
+            }
+            (&p.name, "program", &p.variables, &p.body, None)
+        }
+        ProgramUnit::FunctionBlock(fb) => {
+            if fb.sfc.is_some() {
+                bail!("POU {} is an SFC, which cannot be exported yet", fb.name);
+            }
+            (&fb.name, "functionBlock", &fb.variables, &fb.body, None)
+        }
+        ProgramUnit::Function(f) => (
+            &f.name,
+            "function",
+            &f.variables,
+            &f.body,
+            Some(&f.return_type),
+        ),
+    };
+
+    xml.open(&format!(
+        r#"pou name="{}" pouType="{pou_type}""#,
+        escape(name)
+    ));
+    xml.open("interface");
+    if let Some(return_type) = return_type {
+        xml.open("returnType");
+        type_element(xml, return_type);
+        xml.close("returnType");
+    }
+    for block in variables {
+        var_list(xml, &block.node);
+    }
+    xml.close("interface");
+    xml.open("body");
+    xml.open("ST");
+    st_text(xml, body);
+    xml.close("ST");
+    xml.close("body");
+    xml.close("pou");
+    Ok(())
+}
+
+/// Write an ST body as a CDATA section.
+fn st_text(xml: &mut Xml, body: &[Spanned<Statement>]) {
+    let source = format_statements(body, &FormatOptions::default());
+    // `]]>` cannot appear inside CDATA; split it across two sections.
+    let source = source.replace("]]>", "]]]]><![CDATA[>");
+    xml.line(&format!("<xhtml:p><![CDATA[\n{source}]]></xhtml:p>"));
+}
+
+fn var_list(xml: &mut Xml, block: &VarBlock) {
+    let tag = match block.kind {
+        VarBlockKind::Input => "inputVars",
+        VarBlockKind::Output => "outputVars",
+        VarBlockKind::InOut => "inOutVars",
+        VarBlockKind::Var => "localVars",
+        VarBlockKind::Temp => "tempVars",
+        VarBlockKind::External => "externalVars",
+        VarBlockKind::Global => "globalVars",
+    };
+    let mut attrs = tag.to_string();
+    if block.retain {
+        attrs.push_str(r#" retain="true""#);
+    }
+    if block.constant {
+        attrs.push_str(r#" constant="true""#);
+    }
+    xml.open(&attrs);
+    for decl in &block.declarations {
+        variable(xml, &decl.node);
+    }
+    xml.close(tag);
+}
+
+fn variable(xml: &mut Xml, decl: &VarDecl) {
+    xml.open(&format!(r#"variable name="{}""#, escape(&decl.name)));
+    xml.open("type");
+    type_element(xml, &decl.data_type);
+    xml.close("type");
+    if let Some(init) = &decl.initial_value {
+        xml.open("initialValue");
+        xml.empty(&format!(
+            r#"simpleValue value="{}""#,
+            escape(&format_expression(&init.node))
+        ));
+        xml.close("initialValue");
+    }
+    xml.close("variable");
+}
+
+fn data_type_decl(xml: &mut Xml, decl: &TypeDeclaration) {
+    xml.open(&format!(r#"dataType name="{}""#, escape(&decl.name)));
+    xml.open("baseType");
+    match &decl.kind {
+        TypeKind::Alias(ty) => type_element(xml, ty),
+        TypeKind::Enum(members) => {
+            xml.open("enum");
+            xml.open("values");
+            for (name, value) in members {
+                xml.empty(&format!(r#"value name="{}" value="{value}""#, escape(name)));
+            }
+            xml.close("values");
+            xml.close("enum");
+        }
+        TypeKind::Struct(fields) => {
+            xml.open("struct");
+            for field in fields {
+                variable(xml, field);
+            }
+            xml.close("struct");
+        }
+        TypeKind::Subrange { base, lower, upper } => {
+            let tag = if matches!(
+                base,
+                DataType::Usint | DataType::Uint | DataType::Udint | DataType::Ulint
+            ) {
+                "subrangeUnsigned"
+            } else {
+                "subrangeSigned"
+            };
+            xml.open(tag);
+            xml.empty(&format!(r#"range lower="{lower}" upper="{upper}""#));
+            xml.open("baseType");
+            type_element(xml, base);
+            xml.close("baseType");
+            xml.close(tag);
+        }
+    }
+    xml.close("baseType");
+    xml.close("dataType");
+}
+
+fn type_element(xml: &mut Xml, ty: &DataType) {
+    match ty {
+        DataType::TimeOfDay => xml.empty("TOD"),
+        DataType::DateTime => xml.empty("DT"),
+        DataType::String(length) | DataType::WString(length) => {
+            let tag = if matches!(ty, DataType::String(_)) {
+                "string"
+            } else {
+                "wstring"
+            };
+            match length {
+                Some(length) => xml.empty(&format!(r#"{tag} length="{length}""#)),
+                None => xml.empty(tag),
+            }
+        }
+        DataType::Array {
+            lower,
+            upper,
+            element_type,
+        } => {
+            xml.open("array");
+            xml.empty(&format!(r#"dimension lower="{lower}" upper="{upper}""#));
+            xml.open("baseType");
+            type_element(xml, element_type);
+            xml.close("baseType");
+            xml.close("array");
+        }
+        DataType::Named(name) => xml.empty(&format!(r#"derived name="{}""#, escape(name))),
+        // Elementary types use their ST name as the element name.
+        other => xml.empty(&other.to_string()),
+    }
+}
+
+/// Escape text for use in an attribute value.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Current UTC time as `YYYY-MM-DDThh:mm:ss`.
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, time) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Minimal indenting XML writer.
+#[derive(Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Start tag; `tag` may include attributes.
+    fn open(&mut self, tag: &str) {
+        self.line(&format!("<{tag}>"));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth = self.depth.saturating_sub(1);
+        self.line(&format!("</{name}>"));
+    }
+
+    fn empty(&mut self, tag: &str) {
+        self.line(&format!("<{tag}/>"));
+    }
+
+    fn finish(self) -> String {
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::format_unit;
+    use crate::frontend::parse;
+    use crate::plcopen::import;
+
+    #[test]
+    fn test_round_trip() {
+        let source = "\
+FUNCTION Clamp : INT
+VAR_INPUT
+    value : INT;
+END_VAR
+IF value > 100 THEN
+    Clamp := 100;
+ELSE
+    Clamp := value;
+END_IF;
+END_FUNCTION
+
+PROGRAM Main
+VAR RETAIN
+    count : DINT := 0;
+    label : STRING := 'a<b & \"c\"';
+    table : ARRAY[0..3] OF LREAL;
+END_VAR
+VAR_EXTERNAL
+    limit : INT;
+END_VAR
+count := count + Clamp(limit);
+END_PROGRAM
+";
+        let unit = parse(source).unwrap();
+        let mut project = PlcOpenProject::from_unit("Demo", unit);
+        project.data_types.push(TypeDeclaration {
+            name: "Level".to_string(),
+            kind: TypeKind::Subrange {
+                base: DataType::Uint,
+                lower: 0,
+                upper: 10,
+            },
+        });
+
+        let xml = export(&project).unwrap();
+        assert!(xml.contains(r#"<pou name="Main" pouType="program">"#));
+        assert!(xml.contains("<subrangeUnsigned>"));
+
+        let imported = import(&xml).unwrap();
+        assert_eq!(imported.name, "Demo");
+        assert_eq!(imported.data_types, project.data_types);
+        let options = FormatOptions::default();
+        assert_eq!(
+            format_unit(&imported.unit, &options),
+            format_unit(&project.unit, &options)
+        );
+    }
+
+    #[test]
+    fn test_timestamp_format() {
+        let stamp = timestamp();
+        assert_eq!(stamp.len(), 19);
+        assert_eq!(&stamp[10..11], "T");
+    }
+}
@@ -0,0 +1,750 @@
+//! Reading PLCopen TC6 XML into the compiler's AST.
+
+use super::{
+    child, children, network, text, PlcOpenProject, ProgramInstance, Task, TypeDeclaration,
+    TypeKind,
+};
+use crate::frontend::{
+    parse_expr, parse_il_body, parse_statements, CallArgument, CaseValue, CompilationUnit,
+    DataType, Expression, Function, FunctionBlock, Literal, Program, ProgramUnit, Span, Spanned,
+    Statement, VarBlock, VarBlockKind, VarDecl,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use roxmltree::{Document, Node};
+use std::collections::{HashMap, HashSet};
+
+/// Interface variable lists and the ST block each one maps to.
+const VAR_LISTS: [(&str, VarBlockKind); 7] = [
+    ("inputVars", VarBlockKind::Input),
+    ("outputVars", VarBlockKind::Output),
+    ("inOutVars", VarBlockKind::InOut),
+    ("localVars", VarBlockKind::Var),
+    ("tempVars", VarBlockKind::Temp),
+    ("externalVars", VarBlockKind::External),
+    ("globalVars", VarBlockKind::Global),
+];
+
+/// Type resolution is cut off at this depth to reject recursive aliases.
+const MAX_TYPE_DEPTH: usize = 32;
+
+/// Import a PLCopen TC6 XML project.
+///
+/// Element names are matched without regard to their namespace, so both the
+/// `tc6_0200` and `tc6_0201` schemas (and files without a namespace) are
+/// accepted.
+pub fn import(xml: &str) -> Result<PlcOpenProject> {
+    let doc = Document::parse(xml).context("Invalid PLCopen XML")?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "project" {
+        bail!(
+            "Expected a PLCopen <project> element, found <{}>",
+            root.tag_name().name()
+        );
+    }
+
+    let name = child(root, "contentHeader")
+        .and_then(|header| header.attribute("name"))
+        .unwrap_or("project")
+        .to_string();
+
+    let types = child(root, "types");
+    let data_types = types
+        .and_then(|t| child(t, "dataTypes"))
+        .into_iter()
+        .flat_map(|list| children(list, "dataType"))
+        .map(|node| {
+            let name = node.attribute("name").unwrap_or("?");
+            type_declaration(node).with_context(|| format!("in data type {name}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let resolver = TypeResolver::new(&data_types)?;
+
+    let mut units = Vec::new();
+    for pou in types
+        .and_then(|t| child(t, "pous"))
+        .into_iter()
+        .flat_map(|list| children(list, "pou"))
+    {
+        let name = pou.attribute("name").unwrap_or("?");
+        let unit = import_pou(pou, &resolver).with_context(|| format!("in POU {name}"))?;
+        units.push(spanned(unit));
+    }
+
+    let mut globals = Vec::new();
+    let mut tasks = Vec::new();
+    let configurations = child(root, "instances")
+        .and_then(|i| child(i, "configurations"))
+        .into_iter()
+        .flat_map(|list| children(list, "configuration"));
+    for configuration in configurations {
+        for resource in children(configuration, "resource") {
+            for list in children(resource, "globalVars") {
+                globals.push(spanned(var_list(list, VarBlockKind::Global, &resolver)?));
+            }
+            for task in children(resource, "task") {
+                tasks.push(import_task(task)?);
+            }
+        }
+        for list in children(configuration, "globalVars") {
+            globals.push(spanned(var_list(list, VarBlockKind::Global, &resolver)?));
+        }
+    }
+
+    let mut unit = CompilationUnit { units, globals };
+    resolver.rewrite_enum_values(&mut unit);
+
+    Ok(PlcOpenProject {
+        name,
+        data_types,
+        unit,
+        tasks,
+    })
+}
+
+fn import_pou(pou: Node, types: &TypeResolver) -> Result<ProgramUnit> {
+    let name = pou
+        .attribute("name")
+        .ok_or_else(|| anyhow!("POU without a name"))?
+        .to_string();
+    let pou_type = pou.attribute("pouType").unwrap_or("program");
+
+    let mut variables = Vec::new();
+    let mut return_type = None;
+    if let Some(interface) = child(pou, "interface") {
+        for element in interface.children().filter(Node::is_element) {
+            let tag = element.tag_name().name();
+            if tag == "returnType" {
+                return_type = Some(types.resolve(&data_type(element)?)?);
+            } else if let Some((_, kind)) = VAR_LISTS.iter().find(|(list, _)| *list == tag) {
+                variables.push(spanned(var_list(element, *kind, types)?));
+            }
+        }
+    }
+
+    let body = match child(pou, "body") {
+        Some(body) => {
+            let (statements, hidden) = import_body(&name, body)?;
+            variables.extend(hidden.map(spanned));
+            statements
+        }
+        None => Vec::new(),
+    };
+
+    Ok(match pou_type {
+        "program" => ProgramUnit::Program(Program {
+            name,
+            variables,
+            body,
+            sfc: None,
+        }),
+        "functionBlock" => ProgramUnit::FunctionBlock(FunctionBlock {
+            name,
+            variables,
+            body,
+            sfc: None,
+        }),
+        "function" => ProgramUnit::Function(Function {
+            return_type: return_type
+                .ok_or_else(|| anyhow!("function {} has no returnType", name))?,
+            name,
+            variables,
+            body,
+        }),
+        other => bail!("unknown pouType {:?}", other),
+    })
+}
+
+/// Translate a POU body into ST statements, plus any hidden variables the
+/// translation needs.
+fn import_body(pou: &str, body: Node) -> Result<(Vec<Spanned<Statement>>, Option<VarBlock>)> {
+    let Some(language) = body
+        .children()
+        .filter(Node::is_element)
+        .find(|n| !matches!(n.tag_name().name(), "documentation" | "addData"))
+    else {
+        return Ok((Vec::new(), None));
+    };
+
+    match language.tag_name().name() {
+        "ST" => Ok((parse_statements(&text(language))?, None)),
+        "IL" => {
+            let il = parse_il_body(pou, &text(language))?;
+            let temps = (!il.temps.is_empty()).then_some(VarBlock {
+                kind: VarBlockKind::Temp,
+                retain: false,
+                constant: false,
+                declarations: il.temps,
+            });
+            Ok((il.body, temps))
+        }
+        "LD" | "FBD" => {
+            let network = network::translate(pou, language)?;
+            Ok((network.body, network.variables))
+        }
+        "SFC" => bail!("graphical SFC bodies are not supported"),
+        other => bail!("unsupported body language <{}>", other),
+    }
+}
+
+fn var_list(list: Node, kind: VarBlockKind, types: &TypeResolver) -> Result<VarBlock> {
+    let declarations = children(list, "variable")
+        .map(|variable| {
+            let decl = var_decl(variable)?;
+            Ok(spanned(VarDecl {
+                data_type: types.resolve(&decl.data_type)?,
+                ..decl
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(VarBlock {
+        kind,
+        retain: list.attribute("retain") == Some("true"),
+        constant: list.attribute("constant") == Some("true"),
+        declarations,
+    })
+}
+
+/// A `<variable>` with its declared (unresolved) type.
+fn var_decl(variable: Node) -> Result<VarDecl> {
+    let name = variable
+        .attribute("name")
+        .ok_or_else(|| anyhow!("variable without a name"))?;
+    let context = || format!("in variable {name}");
+    let ty = child(variable, "type").ok_or_else(|| anyhow!("variable {} has no type", name))?;
+    // Array and struct initial values are dropped; see the module docs.
+    let initial_value = child(variable, "initialValue")
+        .and_then(|init| child(init, "simpleValue"))
+        .and_then(|value| value.attribute("value"))
+        .map(|value| parse_expr(value.trim()).map(spanned))
+        .transpose()
+        .with_context(context)?;
+    Ok(VarDecl {
+        name: name.to_string(),
+        data_type: data_type(ty).with_context(context)?,
+        initial_value,
+        address: None,
+    })
+}
+
+/// Parse the type held by a `<type>`, `<baseType>` or `<returnType>`
+/// element.
+fn data_type(holder: Node) -> Result<DataType> {
+    let element = holder
+        .children()
+        .find(Node::is_element)
+        .ok_or_else(|| anyhow!("<{}> is empty", holder.tag_name().name()))?;
+    type_element(element)
+}
+
+fn type_element(element: Node) -> Result<DataType> {
+    let length = || {
+        element
+            .attribute("length")
+            .map(|len| len.trim().parse::<usize>())
+            .transpose()
+            .map_err(|_| anyhow!("invalid string length"))
+    };
+    Ok(match element.tag_name().name() {
+        "BOOL" => DataType::Bool,
+        "SINT" => DataType::Sint,
+        "INT" => DataType::Int,
+        "DINT" => DataType::Dint,
+        "LINT" => DataType::Lint,
+        "USINT" => DataType::Usint,
+        "UINT" => DataType::Uint,
+        "UDINT" => DataType::Udint,
+        "ULINT" => DataType::Ulint,
+        "REAL" => DataType::Real,
+        "LREAL" => DataType::Lreal,
+        "TIME" => DataType::Time,
+        "DATE" => DataType::Date,
+        "TOD" | "TIME_OF_DAY" => DataType::TimeOfDay,
+        "DT" | "DATE_AND_TIME" => DataType::DateTime,
+        "BYTE" => DataType::Byte,
+        "WORD" => DataType::Word,
+        "DWORD" => DataType::Dword,
+        "LWORD" => DataType::Lword,
+        "string" => DataType::String(length()?),
+        "wstring" => DataType::WString(length()?),
+        "derived" => DataType::Named(
+            element
+                .attribute("name")
+                .ok_or_else(|| anyhow!("<derived> type without a name"))?
+                .to_string(),
+        ),
+        "array" => {
+            let element_type = data_type(
+                child(element, "baseType").ok_or_else(|| anyhow!("array has no baseType"))?,
+            )?;
+            let dimensions = children(element, "dimension")
+                .map(|dim| Ok((bound(dim, "lower")?, bound(dim, "upper")?)))
+                .collect::<Result<Vec<_>>>()?;
+            if dimensions.is_empty() {
+                bail!("array has no dimension");
+            }
+            // ARRAY[a..b, c..d] OF T is ARRAY[a..b] OF ARRAY[c..d] OF T.
+            dimensions
+                .into_iter()
+                .rev()
+                .fold(element_type, |inner, (lower, upper)| DataType::Array {
+                    lower,
+                    upper,
+                    element_type: Box::new(inner),
+                })
+        }
+        other => bail!("unsupported type <{}>", other),
+    })
+}
+
+fn bound(node: Node, name: &str) -> Result<i64> {
+    node.attribute(name)
+        .ok_or_else(|| anyhow!("missing {} bound", name))?
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid {} bound", name))
+}
+
+fn type_declaration(node: Node) -> Result<TypeDeclaration> {
+    let name = node
+        .attribute("name")
+        .ok_or_else(|| anyhow!("data type without a name"))?
+        .to_string();
+    let base = child(node, "baseType").ok_or_else(|| anyhow!("data type has no baseType"))?;
+    let element = base
+        .children()
+        .find(Node::is_element)
+        .ok_or_else(|| anyhow!("<baseType> is empty"))?;
+
+    let kind = match element.tag_name().name() {
+        "enum" => {
+            let mut members = Vec::new();
+            let mut next = 0;
+            let values = child(element, "values")
+                .into_iter()
+                .flat_map(|list| children(list, "value"));
+            for value in values {
+                let member = value
+                    .attribute("name")
+                    .ok_or_else(|| anyhow!("enumeration value without a name"))?;
+                if let Some(explicit) = value.attribute("value") {
+                    next = explicit
+                        .trim()
+                        .parse()
+                        .map_err(|_| anyhow!("invalid value of {}", member))?;
+                }
+                members.push((member.to_string(), next));
+                next += 1;
+            }
+            TypeKind::Enum(members)
+        }
+        "struct" => TypeKind::Struct(
+            children(element, "variable")
+                .map(var_decl)
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        "subrangeSigned" | "subrangeUnsigned" => {
+            let range = child(element, "range").ok_or_else(|| anyhow!("subrange has no range"))?;
+            TypeKind::Subrange {
+                base: data_type(
+                    child(element, "baseType")
+                        .ok_or_else(|| anyhow!("subrange has no baseType"))?,
+                )?,
+                lower: bound(range, "lower")?,
+                upper: bound(range, "upper")?,
+            }
+        }
+        _ => TypeKind::Alias(type_element(element)?),
+    };
+    Ok(TypeDeclaration { name, kind })
+}
+
+fn import_task(task: Node) -> Result<Task> {
+    let name = task
+        .attribute("name")
+        .ok_or_else(|| anyhow!("task without a name"))?
+        .to_string();
+    let priority = task
+        .attribute("priority")
+        .map(|p| p.trim().parse())
+        .transpose()
+        .map_err(|_| anyhow!("invalid priority of task {}", name))?
+        .unwrap_or(0);
+    let programs = children(task, "pouInstance")
+        .map(|instance| ProgramInstance {
+            name: instance.attribute("name").unwrap_or_default().to_string(),
+            type_name: instance
+                .attribute("typeName")
+                .unwrap_or_default()
+                .to_string(),
+        })
+        .collect();
+    Ok(Task {
+        interval: task.attribute("interval").map(str::to_string),
+        name,
+        priority,
+        programs,
+    })
+}
+
+/// Maps user data types onto types the compiler understands.
+///
+/// Aliases, subranges and array types are replaced by their definition and
+/// enumerations by `INT`; structures stay named types.
+struct TypeResolver<'a> {
+    /// Declarations by upper-case name.
+    types: HashMap<String, &'a TypeDeclaration>,
+    /// Enumeration members by upper-case name.
+    enum_values: HashMap<String, i64>,
+}
+
+impl<'a> TypeResolver<'a> {
+    fn new(declarations: &'a [TypeDeclaration]) -> Result<Self> {
+        let mut types = HashMap::new();
+        let mut enum_values = HashMap::new();
+        for decl in declarations {
+            if types.insert(decl.name.to_uppercase(), decl).is_some() {
+                bail!("data type {} is declared twice", decl.name);
+            }
+            if let TypeKind::Enum(members) = &decl.kind {
+                for (member, value) in members {
+                    let previous = enum_values.insert(member.to_uppercase(), *value);
+                    if previous.is_some_and(|previous| previous != *value) {
+                        bail!(
+                            "enumeration value {} has different values in different types",
+                            member
+                        );
+                    }
+                }
+            }
+        }
+        Ok(Self { types, enum_values })
+    }
+
+    fn resolve(&self, ty: &DataType) -> Result<DataType> {
+        self.resolve_at(ty, 0)
+    }
+
+    fn resolve_at(&self, ty: &DataType, depth: usize) -> Result<DataType> {
+        if depth > MAX_TYPE_DEPTH {
+            bail!("data type {} is defined recursively", ty);
+        }
+        Ok(match ty {
+            DataType::Named(name) => match self.types.get(&name.to_uppercase()).map(|d| &d.kind) {
+                Some(TypeKind::Alias(target)) => self.resolve_at(target, depth + 1)?,
+                Some(TypeKind::Subrange { base, .. }) => self.resolve_at(base, depth + 1)?,
+                Some(TypeKind::Enum(_)) => DataType::Int,
+                Some(TypeKind::Struct(_)) | None => ty.clone(),
+            },
+            DataType::Array {
+                lower,
+                upper,
+                element_type,
+            } => DataType::Array {
+                lower: *lower,
+                upper: *upper,
+                element_type: Box::new(self.resolve_at(element_type, depth + 1)?),
+            },
+            other => other.clone(),
+        })
+    }
+
+    /// Replace references to enumeration members with their integer values,
+    /// unless a POU declares a variable of the same name.
+    fn rewrite_enum_values(&self, unit: &mut CompilationUnit) {
+        if self.enum_values.is_empty() {
+            return;
+        }
+        let global_names = declared_names(&unit.globals);
+        for block in &mut unit.globals {
+            self.rewrite_decls(&mut block.node, &global_names);
+        }
+        for pou in &mut unit.units {
+            let (variables, body) = match &mut pou.node {
+                ProgramUnit::Program(p) => (&mut p.variables, &mut p.body),
+                ProgramUnit::FunctionBlock(fb) => (&mut fb.variables, &mut fb.body),
+                ProgramUnit::Function(f) => (&mut f.variables, &mut f.body),
+            };
+            let mut shadowed = declared_names(variables);
+            shadowed.extend(global_names.iter().cloned());
+            for block in variables.iter_mut() {
+                self.rewrite_decls(&mut block.node, &shadowed);
+            }
+            let rewrite = |expr: &mut Expression| self.rewrite_expr(expr, &shadowed);
+            walk_statements(body, &rewrite);
+        }
+    }
+
+    fn rewrite_decls(&self, block: &mut VarBlock, shadowed: &HashSet<String>) {
+        for decl in &mut block.declarations {
+            if let Some(init) = &mut decl.node.initial_value {
+                walk_expr(&mut init.node, &|e: &mut Expression| {
+                    self.rewrite_expr(e, shadowed);
+                });
+            }
+        }
+    }
+
+    fn rewrite_expr(&self, expr: &mut Expression, shadowed: &HashSet<String>) {
+        if let Expression::Variable(name) = expr {
+            let key = name.to_uppercase();
+            if !shadowed.contains(&key) {
+                if let Some(value) = self.enum_values.get(&key) {
+                    *expr = Expression::Literal(Literal::Integer(*value));
+                }
+            }
+        }
+    }
+}
+
+fn declared_names(blocks: &[Spanned<VarBlock>]) -> HashSet<String> {
+    blocks
+        .iter()
+        .flat_map(|block| &block.node.declarations)
+        .map(|decl| decl.node.name.to_uppercase())
+        .collect()
+}
+
+/// Apply `f` to every expression in a statement list, innermost first.
+fn walk_statements(statements: &mut [Spanned<Statement>], f: &dyn Fn(&mut Expression)) {
+    for stmt in statements {
+        match &mut stmt.node {
+            Statement::Assignment(assign) => {
+                walk_expr(&mut assign.target.node, f);
+                walk_expr(&mut assign.value.node, f);
+            }
+            Statement::If(if_stmt) => {
+                walk_expr(&mut if_stmt.condition.node, f);
+                walk_statements(&mut if_stmt.then_branch, f);
+                for branch in &mut if_stmt.elsif_branches {
+                    walk_expr(&mut branch.condition.node, f);
+                    walk_statements(&mut branch.statements, f);
+                }
+                if let Some(else_branch) = &mut if_stmt.else_branch {
+                    walk_statements(else_branch, f);
+                }
+            }
+            Statement::Case(case) => {
+                walk_expr(&mut case.selector.node, f);
+                for branch in &mut case.branches {
+                    for value in &mut branch.values {
+                        match value {
+                            CaseValue::Single(v) => walk_expr(&mut v.node, f),
+                            CaseValue::Range(lo, hi) => {
+                                walk_expr(&mut lo.node, f);
+                                walk_expr(&mut hi.node, f);
+                            }
+                        }
+                    }
+                    walk_statements(&mut branch.statements, f);
+                }
+                if let Some(else_branch) = &mut case.else_branch {
+                    walk_statements(else_branch, f);
+                }
+            }
+            Statement::For(for_stmt) => {
+                walk_expr(&mut for_stmt.from.node, f);
+                walk_expr(&mut for_stmt.to.node, f);
+                if let Some(by) = &mut for_stmt.by {
+                    walk_expr(&mut by.node, f);
+                }
+                walk_statements(&mut for_stmt.body, f);
+            }
+            Statement::While(while_stmt) => {
+                walk_expr(&mut while_stmt.condition.node, f);
+                walk_statements(&mut while_stmt.body, f);
+            }
+            Statement::Repeat(repeat) => {
+                walk_statements(&mut repeat.body, f);
+                walk_expr(&mut repeat.until.node, f);
+            }
+            Statement::Return(Some(value)) => walk_expr(&mut value.node, f),
+            Statement::Call(call) => walk_arguments(&mut call.arguments, f),
+            Statement::Return(None) | Statement::Exit | Statement::Continue | Statement::Empty => {}
+        }
+    }
+}
+
+fn walk_expr(expr: &mut Expression, f: &dyn Fn(&mut Expression)) {
+    match expr {
+        Expression::ArrayAccess { array, index } => {
+            walk_expr(&mut array.node, f);
+            walk_expr(&mut index.node, f);
+        }
+        // Field names are not variables; only the object is visited.
+        Expression::FieldAccess { object, .. } => walk_expr(&mut object.node, f),
+        Expression::Binary { left, right, .. } => {
+            walk_expr(&mut left.node, f);
+            walk_expr(&mut right.node, f);
+        }
+        Expression::Unary { operand, .. } => walk_expr(&mut operand.node, f),
+        Expression::Call { arguments, .. } => walk_arguments(arguments, f),
+        Expression::Paren(inner) => walk_expr(&mut inner.node, f),
+        Expression::Literal(_) | Expression::Variable(_) => {}
+    }
+    f(expr);
+}
+
+fn walk_arguments(arguments: &mut [CallArgument], f: &dyn Fn(&mut Expression)) {
+    for arg in arguments {
+        walk_expr(&mut arg.value.node, f);
+    }
+}
+
+fn spanned<T>(node: T) -> Spanned<T> {
+    Spanned::new(node, Span::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::{format_unit, FormatOptions};
+
+    const PROJECT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://www.plcopen.org/xml/tc6_0200" xmlns:xhtml="http://www.w3.org/1999/xhtml">
+  <fileHeader companyName="Acme" productName="Editor" productVersion="1" creationDateTime="2024-01-01T00:00:00"/>
+  <contentHeader name="Mixer">
+    <coordinateInfo><fbd><scaling x="1" y="1"/></fbd><ld><scaling x="1" y="1"/></ld><sfc><scaling x="1" y="1"/></sfc></coordinateInfo>
+  </contentHeader>
+  <types>
+    <dataTypes>
+      <dataType name="Mode">
+        <baseType><enum><values><value name="Off"/><value name="Auto" value="5"/><value name="Manual"/></values></enum></baseType>
+      </dataType>
+      <dataType name="Percent">
+        <baseType><subrangeSigned><range lower="0" upper="100"/><baseType><INT/></baseType></subrangeSigned></baseType>
+      </dataType>
+      <dataType name="Recipe">
+        <baseType><array><dimension lower="1" upper="4"/><baseType><derived name="Percent"/></baseType></array></baseType>
+      </dataType>
+    </dataTypes>
+    <pous>
+      <pou name="Scale" pouType="function">
+        <interface>
+          <returnType><REAL/></returnType>
+          <inputVars><variable name="raw"><type><INT/></type></variable></inputVars>
+        </interface>
+        <body><ST><xhtml:p><![CDATA[Scale := raw / 10;]]></xhtml:p></ST></body>
+      </pou>
+      <pou name="Main" pouType="program">
+        <interface>
+          <localVars retain="true">
+            <variable name="mode"><type><derived name="Mode"/></type>
+              <initialValue><simpleValue value="Auto"/></initialValue></variable>
+            <variable name="recipe"><type><derived name="Recipe"/></type></variable>
+            <variable name="name"><type><string length="20"/></type></variable>
+          </localVars>
+          <externalVars><variable name="speed"><type><REAL/></type></variable></externalVars>
+        </interface>
+        <body>
+          <IL><xhtml:p><![CDATA[
+            LD mode
+            EQ Manual
+            JMPC skip
+            LD 3
+            ST recipe[1]
+          skip:
+          ]]></xhtml:p></IL>
+        </body>
+      </pou>
+    </pous>
+  </types>
+  <instances>
+    <configurations>
+      <configuration name="Config0">
+        <resource name="Res0">
+          <task name="Fast" interval="T#10ms" priority="1">
+            <pouInstance name="main1" typeName="Main"/>
+          </task>
+        </resource>
+        <globalVars><variable name="speed"><type><REAL/></type></variable></globalVars>
+      </configuration>
+    </configurations>
+  </instances>
+</project>"#;
+
+    #[test]
+    fn test_import_project() {
+        let project = import(PROJECT).unwrap();
+        assert_eq!(project.name, "Mixer");
+        assert_eq!(project.data_types.len(), 3);
+        assert_eq!(
+            project.data_types[0].kind,
+            TypeKind::Enum(vec![
+                ("Off".to_string(), 0),
+                ("Auto".to_string(), 5),
+                ("Manual".to_string(), 6)
+            ])
+        );
+        assert_eq!(
+            project.tasks,
+            vec![Task {
+                name: "Fast".to_string(),
+                interval: Some("T#10ms".to_string()),
+                priority: 1,
+                programs: vec![ProgramInstance {
+                    name: "main1".to_string(),
+                    type_name: "Main".to_string(),
+                }],
+            }]
+        );
+        assert_eq!(project.unit.globals.len(), 1);
+
+        let ProgramUnit::Program(main) = &project.unit.units[1].node else {
+            panic!("expected a program");
+        };
+        let locals = &main.variables[0].node;
+        assert!(locals.retain);
+        assert_eq!(locals.declarations[0].node.data_type, DataType::Int);
+        assert_eq!(
+            locals.declarations[0]
+                .node
+                .initial_value
+                .as_ref()
+                .unwrap()
+                .node,
+            Expression::Literal(Literal::Integer(5))
+        );
+        assert_eq!(
+            locals.declarations[1].node.data_type,
+            DataType::Array {
+                lower: 1,
+                upper: 4,
+                element_type: Box::new(DataType::Int),
+            }
+        );
+        assert_eq!(
+            locals.declarations[2].node.data_type,
+            DataType::String(Some(20))
+        );
+        assert_eq!(main.variables[1].node.kind, VarBlockKind::External);
+        // The IL accumulator is declared as a hidden VAR_TEMP.
+        assert_eq!(main.variables[2].node.kind, VarBlockKind::Temp);
+
+        let st = format_unit(&project.unit, &FormatOptions::default());
+        assert!(st.contains("= 6"), "enum value not substituted:\n{st}");
+    }
+
+    #[test]
+    fn test_import_rejects_graphical_sfc() {
+        let xml = r#"<project><types><pous>
+            <pou name="Seq" pouType="program"><body><SFC/></body></pou>
+        </pous></types></project>"#;
+        let err = import(xml).unwrap_err();
+        assert!(format!("{err:#}").contains("in POU Seq"), "{err:#}");
+        assert!(format!("{err:#}").contains("SFC"), "{err:#}");
+    }
+
+    #[test]
+    fn test_import_rejects_recursive_alias() {
+        let xml = r#"<project><types><dataTypes>
+            <dataType name="A"><baseType><derived name="B"/></baseType></dataType>
+            <dataType name="B"><baseType><derived name="A"/></baseType></dataType>
+        </dataTypes><pous>
+            <pou name="Main" pouType="program"><interface><localVars>
+                <variable name="x"><type><derived name="A"/></type></variable>
+            </localVars></interface><body><ST>x := x;</ST></body></pou>
+        </pous></types></project>"#;
+        let err = import(xml).unwrap_err();
+        assert!(format!("{err:#}").contains("recursively"), "{err:#}");
+    }
+}
@@ -0,0 +1,162 @@
+//! PLCopen TC6 XML project exchange.
+//!
+//! [PLCopen XML](https://plcopen.org/technical-activities/xml-exchange) is the
+//! vendor-neutral project format written by most IEC 61131-3 IDEs. This
+//! module reads such a `project.xml` into the compiler's AST and writes a
+//! parsed project back out:
+//!
+//! - [`import`] accepts the `tc6_0200` and `tc6_0201` schemas. POU
+//!   interfaces, ST and IL bodies, user data types and configuration-level
+//!   global variables are imported. Ladder Diagram and Function Block Diagram
+//!   networks are translated into equivalent ST statements, so every POU
+//!   comes out as a regular ST POU.
+//! - [`export`] writes a [`PlcOpenProject`] with ST bodies. Importing the
+//!   exported file yields an equivalent project again.
+//!
+//! Graphical SFC bodies, jumps in LD/FBD networks and array or struct
+//! initial values are not supported; the importer reports an error for the
+//! former two and drops the latter, since the compiler does not apply
+//! initial values yet.
+//!
+//! # Example
+//!
+//! ```
+//! use plc_compiler::plcopen;
+//!
+//! let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+//! <project xmlns="http://www.plcopen.org/xml/tc6_0201"
+//!          xmlns:xhtml="http://www.w3.org/1999/xhtml">
+//!   <types>
+//!     <dataTypes/>
+//!     <pous>
+//!       <pou name="Main" pouType="program">
+//!         <interface>
+//!           <localVars>
+//!             <variable name="x"><type><INT/></type></variable>
+//!           </localVars>
+//!         </interface>
+//!         <body><ST><xhtml:p>x := x + 1;</xhtml:p></ST></body>
+//!       </pou>
+//!     </pous>
+//!   </types>
+//! </project>"#;
+//!
+//! let project = plcopen::import(xml).unwrap();
+//! assert_eq!(project.unit.units.len(), 1);
+//! let wasm = plc_compiler::Compiler::new().compile_ast(&project.unit).unwrap();
+//! assert!(!wasm.is_empty());
+//! ```
+
+mod export;
+mod import;
+mod network;
+
+pub use export::export;
+pub use import::import;
+
+use crate::frontend::{CompilationUnit, DataType, VarDecl};
+use roxmltree::Node;
+
+/// A PLCopen project: the compilation unit plus the parts of the XML model
+/// that have no place in the AST.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlcOpenProject {
+    /// Project name from the content header.
+    pub name: String,
+    /// User data types declared under `<dataTypes>`.
+    ///
+    /// Variable declarations in [`unit`](Self::unit) already refer to the
+    /// resolved types; these are kept for export.
+    pub data_types: Vec<TypeDeclaration>,
+    /// POUs and configuration-level global variables.
+    pub unit: CompilationUnit,
+    /// Tasks of the first configuration resource.
+    pub tasks: Vec<Task>,
+}
+
+impl PlcOpenProject {
+    /// Wrap a parsed compilation unit, e.g. for export.
+    pub fn from_unit(name: impl Into<String>, unit: CompilationUnit) -> Self {
+        Self {
+            name: name.into(),
+            data_types: Vec::new(),
+            unit,
+            tasks: Vec::new(),
+        }
+    }
+}
+
+/// A user data type declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDeclaration {
+    /// Type name.
+    pub name: String,
+    /// Type definition.
+    pub kind: TypeKind,
+}
+
+/// The definition of a user data type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    /// Another name for an existing type (including arrays).
+    Alias(DataType),
+    /// Enumeration with the integer value of each member.
+    Enum(Vec<(String, i64)>),
+    /// Structure.
+    Struct(Vec<VarDecl>),
+    /// Integer subrange of a base type.
+    Subrange {
+        /// Base integer type.
+        base: DataType,
+        /// Lower bound.
+        lower: i64,
+        /// Upper bound.
+        upper: i64,
+    },
+}
+
+/// A cyclic task of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// Task name.
+    pub name: String,
+    /// Cycle interval as written in the XML (e.g. `T#10ms`).
+    pub interval: Option<String>,
+    /// Task priority (0 is highest).
+    pub priority: u32,
+    /// Program instances run by this task.
+    pub programs: Vec<ProgramInstance>,
+}
+
+/// A program instance assigned to a task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramInstance {
+    /// Instance name.
+    pub name: String,
+    /// Name of the PROGRAM POU.
+    pub type_name: String,
+}
+
+/// First child element with the given local name, in any namespace.
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// All child elements with the given local name, in any namespace.
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// Concatenated text content of an element, including CDATA sections and
+/// the text of nested XHTML elements.
+fn text(node: Node) -> String {
+    node.descendants()
+        .filter(Node::is_text)
+        .filter_map(|n| n.text())
+        .collect()
+}
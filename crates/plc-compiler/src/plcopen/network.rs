@@ -0,0 +1,766 @@
+//! Translation of Ladder Diagram and Function Block Diagram bodies into ST.
+//!
+//! Both graphical languages describe a data-flow graph: every element has a
+//! `localId`, and each input connection point names the element (and, for
+//! blocks, the output pin) it is wired to. The translator walks the graph
+//! backwards from its sinks and builds one ST expression per wire:
+//!
+//! | Element                         | ST                                           |
+//! |---------------------------------|----------------------------------------------|
+//! | left power rail                 | `TRUE`                                       |
+//! | contact `a`                     | `power AND a` (`NOT a` when negated)         |
+//! | rising / falling contact        | `power AND (a AND NOT prev)`                 |
+//! | coil `q`                        | `q := power;`                                |
+//! | set / reset coil                | `IF power THEN q := TRUE; END_IF;` / `FALSE` |
+//! | in/out variables                | the expression / `target := value;`          |
+//! | `AND`, `ADD`, `GT`, `MOVE`, ... | the equivalent ST operator                   |
+//! | other functions                 | a function call with positional arguments    |
+//! | function block instance         | `inst(IN := ...);`, outputs read as `inst.Q` |
+//! | connector / continuation        | the wire feeding the connector               |
+//! | return                          | `IF cond THEN RETURN; END_IF;`               |
+//!
+//! Several connections into one input (parallel branches in LD) are ORed.
+//! Sinks (coils, output variables, instances and returns) are emitted in
+//! `executionOrderId` order where the editor assigned one, and otherwise top
+//! to bottom, left to right. Edge memories are hidden `VAR` variables named
+//! `__net_<localId>_prev` that are updated at the end of the body.
+//!
+//! A function block instance reading its own output is a legal feedback path
+//! and sees the previous cycle's value; any other loop is an error, as are
+//! jumps, labels and functions whose `EN` input is not tied to the rail.
+
+use super::{child, children, text};
+use crate::frontend::{
+    parse_expr, Assignment, BinaryOp, CallArgument, CallStatement, DataType, Expression,
+    IfStatement, Literal, Span, Spanned, Statement, UnaryOp, VarBlock, VarBlockKind, VarDecl,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use roxmltree::Node;
+use std::collections::{HashMap, HashSet};
+
+/// Prefix of the hidden edge-detection memories.
+const EDGE_PREFIX: &str = "__net_";
+
+/// An LD or FBD body translated into ST.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct TranslatedNetwork {
+    /// Body statements.
+    pub body: Vec<Spanned<Statement>>,
+    /// Hidden edge memories, if the network needs any.
+    pub variables: Option<VarBlock>,
+}
+
+/// Translate an `<LD>` or `<FBD>` element; `pou` is used in error messages.
+pub(super) fn translate(pou: &str, network: Node) -> Result<TranslatedNetwork> {
+    let mut elements = HashMap::new();
+    let mut connectors = HashMap::new();
+    for node in network.children().filter(Node::is_element) {
+        let Some(id) = node.attribute("localId") else {
+            continue;
+        };
+        let id = parse_id(id)?;
+        if elements.insert(id, node).is_some() {
+            bail!("POU {}: localId {} is used twice", pou, id);
+        }
+        if node.tag_name().name() == "connector" {
+            connectors.insert(connector_name(node)?, id);
+        }
+    }
+
+    let mut sinks: Vec<Node> = elements
+        .values()
+        .copied()
+        .filter(|node| is_sink(*node))
+        .collect();
+    sinks.sort_by(|a, b| {
+        sink_order(*a)
+            .partial_cmp(&sink_order(*b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut translator = Translator {
+        elements,
+        connectors,
+        statements: Vec::new(),
+        called: HashSet::new(),
+        visiting: HashSet::new(),
+        memories: Vec::new(),
+        updates: Vec::new(),
+    };
+    for sink in sinks {
+        translator
+            .sink(sink)
+            .with_context(|| format!("POU {}: {}", pou, describe(sink)))?;
+    }
+
+    let Translator {
+        mut statements,
+        memories,
+        updates,
+        ..
+    } = translator;
+    statements.extend(updates);
+    let variables = (!memories.is_empty()).then_some(VarBlock {
+        kind: VarBlockKind::Var,
+        retain: false,
+        constant: false,
+        declarations: memories,
+    });
+    Ok(TranslatedNetwork {
+        body: statements,
+        variables,
+    })
+}
+
+/// Walks the network graph, accumulating statements.
+struct Translator<'a, 'input> {
+    elements: HashMap<u64, Node<'a, 'input>>,
+    /// Connector name (upper case) to its localId.
+    connectors: HashMap<String, u64>,
+    statements: Vec<Spanned<Statement>>,
+    /// Function block instances whose call has already been emitted.
+    called: HashSet<u64>,
+    /// Elements on the current evaluation path, for loop detection.
+    visiting: HashSet<u64>,
+    /// Edge memory declarations.
+    memories: Vec<Spanned<VarDecl>>,
+    /// Edge memory updates, run after all sinks.
+    updates: Vec<Spanned<Statement>>,
+}
+
+impl<'a, 'input> Translator<'a, 'input> {
+    /// Emit the statement for one sink element.
+    fn sink(&mut self, node: Node<'a, 'input>) -> Result<()> {
+        match node.tag_name().name() {
+            "coil" => self.coil(node),
+            "outVariable" | "inOutVariable" => {
+                let value = self.required(node)?;
+                let negated = flag(node, "negated") || flag(node, "negatedIn");
+                let target = expression(node, "expression")?;
+                self.push(assign(target, negate_if(value, negated)));
+                Ok(())
+            }
+            "block" => self.call_instance(node),
+            "return" => {
+                let ret = Statement::Return(None);
+                let stmt = match self.wire(child(node, "connectionPointIn"))? {
+                    Some(condition) => if_then(condition, ret),
+                    None => ret,
+                };
+                self.push(stmt);
+                Ok(())
+            }
+            "jump" | "label" => bail!("jumps and labels are not supported in LD/FBD networks"),
+            other => bail!("unexpected sink element <{}>", other),
+        }
+    }
+
+    fn coil(&mut self, node: Node<'a, 'input>) -> Result<()> {
+        let power = self.required(node)?;
+        let target = expression(node, "variable")?;
+        let condition = match node.attribute("edge") {
+            Some("rising") => self.edge(node, "", power, true)?,
+            Some("falling") => self.edge(node, "", power, false)?,
+            _ => power,
+        };
+        let stmt = match node.attribute("storage") {
+            Some("set") => if_then(condition, assign(target, bool_literal(true))),
+            Some("reset") => if_then(condition, assign(target, bool_literal(false))),
+            _ => assign(target, negate_if(condition, flag(node, "negated"))),
+        };
+        self.push(stmt);
+        Ok(())
+    }
+
+    /// The value of an output pin of an element.
+    fn output(&mut self, id: u64, pin: Option<&str>) -> Result<Expression> {
+        let node = *self
+            .elements
+            .get(&id)
+            .ok_or_else(|| anyhow!("connection to unknown localId {}", id))?;
+        if self.called.contains(&id) {
+            // Feedback into a function block that has already been called.
+            return Ok(instance_output(node, pin));
+        }
+        if !self.visiting.insert(id) {
+            bail!("feedback loop through {}", describe(node));
+        }
+        let value = self.evaluate(node, pin);
+        self.visiting.remove(&id);
+        value
+    }
+
+    fn evaluate(&mut self, node: Node<'a, 'input>, pin: Option<&str>) -> Result<Expression> {
+        match node.tag_name().name() {
+            "leftPowerRail" => Ok(bool_literal(true)),
+            "inVariable" | "inOutVariable" => {
+                let negated = flag(node, "negated") || flag(node, "negatedOut");
+                Ok(negate_if(expression(node, "expression")?, negated))
+            }
+            "contact" => {
+                let power = self.required(node)?;
+                let variable = expression(node, "variable")?;
+                let term = match node.attribute("edge") {
+                    Some("rising") => self.edge(node, "", variable, true)?,
+                    Some("falling") => self.edge(node, "", variable, false)?,
+                    _ => negate_if(variable, flag(node, "negated")),
+                };
+                Ok(and(power, term))
+            }
+            "coil" | "connector" => self.required(node),
+            "continuation" => {
+                let name = connector_name(node)?;
+                let id = *self
+                    .connectors
+                    .get(&name)
+                    .ok_or_else(|| anyhow!("continuation {} has no connector", name))?;
+                self.output(id, None)
+            }
+            "block" => self.block_output(node, pin),
+            other => bail!("<{}> has no output to connect to", other),
+        }
+    }
+
+    /// The value of a function or operator block, or of a function block
+    /// instance output (emitting the instance call first).
+    fn block_output(&mut self, node: Node<'a, 'input>, pin: Option<&str>) -> Result<Expression> {
+        if instance_name(node).is_some() {
+            self.call_instance(node)?;
+            return Ok(instance_output(node, pin));
+        }
+
+        let type_name = node
+            .attribute("typeName")
+            .ok_or_else(|| anyhow!("{} has no typeName", describe(node)))?;
+        let mut arguments = Vec::new();
+        for (formal, value) in self.block_inputs(node)? {
+            if formal.eq_ignore_ascii_case("EN") {
+                // An open EN input means the block always runs.
+                if value.is_some_and(|en| !is_true(&en)) {
+                    bail!(
+                        "EN of {} {} must be tied to the power rail; \
+                         conditional function calls are not supported",
+                        type_name,
+                        describe(node)
+                    );
+                }
+                continue;
+            }
+            arguments.push(value.ok_or_else(|| {
+                anyhow!(
+                    "input {} of {} {} is not connected",
+                    formal,
+                    type_name,
+                    describe(node)
+                )
+            })?);
+        }
+
+        if pin.is_some_and(|p| p.eq_ignore_ascii_case("ENO")) {
+            return Ok(bool_literal(true));
+        }
+        Ok(negate_if(
+            operator(type_name, arguments),
+            output_negated(node, pin),
+        ))
+    }
+
+    /// Emit the call of a function block instance, once per network.
+    fn call_instance(&mut self, node: Node<'a, 'input>) -> Result<()> {
+        let id = local_id(node)?;
+        // Mark before evaluating the inputs so that feedback from the
+        // instance's own outputs reads the previous cycle's values.
+        if !self.called.insert(id) {
+            return Ok(());
+        }
+        let instance = instance_name(node)
+            .ok_or_else(|| anyhow!("{} is not a function block instance", describe(node)))?;
+
+        let mut arguments = Vec::new();
+        let mut enable = None;
+        for (formal, value) in self.block_inputs(node)? {
+            let Some(value) = value else { continue };
+            if formal.eq_ignore_ascii_case("EN") {
+                enable = Some(value);
+            } else {
+                arguments.push(CallArgument {
+                    name: Some(formal),
+                    value: spanned(value),
+                });
+            }
+        }
+
+        let call = Statement::Call(CallStatement {
+            name: instance.to_string(),
+            arguments,
+        });
+        let stmt = match enable {
+            Some(enable) if !is_true(&enable) => if_then(enable, call),
+            _ => call,
+        };
+        self.push(stmt);
+        Ok(())
+    }
+
+    /// Formal parameter name and connected value of each block input, in
+    /// declaration order, with input negation and edge detection applied.
+    fn block_inputs(
+        &mut self,
+        node: Node<'a, 'input>,
+    ) -> Result<Vec<(String, Option<Expression>)>> {
+        let mut inputs = Vec::new();
+        let pins = child(node, "inputVariables")
+            .into_iter()
+            .flat_map(|list| children(list, "variable"))
+            .chain(
+                child(node, "inOutVariables")
+                    .into_iter()
+                    .flat_map(|list| children(list, "variable")),
+            );
+        for pin in pins {
+            let formal = pin.attribute("formalParameter").unwrap_or("").to_string();
+            let value = match self.wire(child(pin, "connectionPointIn"))? {
+                Some(value) => Some(match pin.attribute("edge") {
+                    Some("rising") => self.edge(node, &formal, value, true)?,
+                    Some("falling") => self.edge(node, &formal, value, false)?,
+                    _ => negate_if(value, flag(pin, "negated")),
+                }),
+                None => None,
+            };
+            inputs.push((formal, value));
+        }
+        Ok(inputs)
+    }
+
+    /// The value of the wire(s) connected to an input connection point;
+    /// parallel connections are ORed.
+    fn wire(&mut self, point: Option<Node<'a, 'input>>) -> Result<Option<Expression>> {
+        let Some(point) = point else {
+            return Ok(None);
+        };
+        let mut result = None;
+        for connection in children(point, "connection") {
+            let id = parse_id(
+                connection
+                    .attribute("refLocalId")
+                    .ok_or_else(|| anyhow!("connection without refLocalId"))?,
+            )?;
+            let value = self.output(id, connection.attribute("formalParameter"))?;
+            result = Some(match result {
+                Some(previous) => binary(previous, BinaryOp::Or, value),
+                None => value,
+            });
+        }
+        Ok(result)
+    }
+
+    /// The value connected to an element's single input.
+    fn required(&mut self, node: Node<'a, 'input>) -> Result<Expression> {
+        self.wire(child(node, "connectionPointIn"))?
+            .ok_or_else(|| anyhow!("{} is not connected", describe(node)))
+    }
+
+    /// Rising or falling edge of `signal`, using a hidden memory of its
+    /// previous value that is updated at the end of the body.
+    fn edge(
+        &mut self,
+        node: Node<'a, 'input>,
+        pin: &str,
+        signal: Expression,
+        rising: bool,
+    ) -> Result<Expression> {
+        let id = local_id(node)?;
+        let name = if pin.is_empty() {
+            format!("{EDGE_PREFIX}{id}_prev")
+        } else {
+            format!("{EDGE_PREFIX}{id}_{pin}_prev")
+        };
+        if !self.memories.iter().any(|decl| decl.node.name == name) {
+            self.memories.push(spanned(VarDecl {
+                name: name.clone(),
+                data_type: DataType::Bool,
+                initial_value: None,
+                address: None,
+            }));
+            self.updates.push(spanned(assign(
+                Expression::Variable(name.clone()),
+                signal.clone(),
+            )));
+        }
+        let previous = Expression::Variable(name);
+        Ok(if rising {
+            and(signal, not(previous))
+        } else {
+            and(not(signal), previous)
+        })
+    }
+
+    fn push(&mut self, stmt: Statement) {
+        self.statements.push(spanned(stmt));
+    }
+}
+
+/// The ST equivalent of a function or operator block.
+fn operator(type_name: &str, mut args: Vec<Expression>) -> Expression {
+    let upper = type_name.to_uppercase();
+    let fold = |op, args: Vec<Expression>| {
+        args.into_iter()
+            .reduce(|left, right| binary(left, op, right))
+            .unwrap_or_else(|| bool_literal(false))
+    };
+    let comparison = match upper.as_str() {
+        "GT" => Some(BinaryOp::Gt),
+        "GE" => Some(BinaryOp::Ge),
+        "EQ" => Some(BinaryOp::Eq),
+        "NE" => Some(BinaryOp::Ne),
+        "LE" => Some(BinaryOp::Le),
+        "LT" => Some(BinaryOp::Lt),
+        _ => None,
+    };
+
+    match (upper.as_str(), args.len()) {
+        ("AND", n) if n >= 2 => fold(BinaryOp::And, args),
+        ("OR", n) if n >= 2 => fold(BinaryOp::Or, args),
+        ("XOR", n) if n >= 2 => fold(BinaryOp::Xor, args),
+        ("ADD", n) if n >= 2 => fold(BinaryOp::Add, args),
+        ("MUL", n) if n >= 2 => fold(BinaryOp::Mul, args),
+        ("SUB", 2) => fold(BinaryOp::Sub, args),
+        ("DIV", 2) => fold(BinaryOp::Div, args),
+        ("MOD", 2) => fold(BinaryOp::Mod, args),
+        ("EXPT", 2) => fold(BinaryOp::Pow, args),
+        ("SHL", 2) => fold(BinaryOp::Shl, args),
+        ("SHR", 2) => fold(BinaryOp::Shr, args),
+        ("NOT", 1) => not(args.remove(0)),
+        ("MOVE", 1) => args.remove(0),
+        // GT(a, b, c) means a > b AND b > c.
+        (_, n) if n >= 2 && comparison.is_some() => {
+            let op = comparison.unwrap_or(BinaryOp::Eq);
+            let pairs = args
+                .windows(2)
+                .map(|pair| binary(pair[0].clone(), op, pair[1].clone()))
+                .collect();
+            fold(BinaryOp::And, pairs)
+        }
+        _ => Expression::Call {
+            name: type_name.to_string(),
+            arguments: args
+                .into_iter()
+                .map(|value| CallArgument {
+                    name: None,
+                    value: spanned(value),
+                })
+                .collect(),
+        },
+    }
+}
+
+/// Read an output of a function block instance as `inst.PIN`.
+fn instance_output(node: Node, pin: Option<&str>) -> Expression {
+    let instance = instance_name(node).unwrap_or_default();
+    let pin = pin
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .or_else(|| output_pins(node).next().map(str::to_string))
+        .unwrap_or_default();
+    let negated = output_negated(node, Some(&pin));
+    let value = Expression::FieldAccess {
+        object: Box::new(spanned(Expression::Variable(instance.to_string()))),
+        field: pin,
+    };
+    negate_if(value, negated)
+}
+
+fn output_pins<'a>(node: Node<'a, '_>) -> impl Iterator<Item = &'a str> {
+    child(node, "outputVariables")
+        .into_iter()
+        .flat_map(|list| children(list, "variable"))
+        .filter_map(|pin| pin.attribute("formalParameter"))
+}
+
+/// Whether the given output pin of a block (the first one if unnamed) is
+/// negated.
+fn output_negated(node: Node, pin: Option<&str>) -> bool {
+    child(node, "outputVariables")
+        .into_iter()
+        .flat_map(|list| children(list, "variable"))
+        .find(|var| match pin.filter(|p| !p.is_empty()) {
+            Some(pin) => var
+                .attribute("formalParameter")
+                .is_some_and(|f| f.eq_ignore_ascii_case(pin)),
+            None => true,
+        })
+        .is_some_and(|var| flag(var, "negated"))
+}
+
+fn instance_name<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.attribute("instanceName")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Elements whose effect must be emitted as a statement.
+fn is_sink(node: Node) -> bool {
+    match node.tag_name().name() {
+        "coil" | "outVariable" | "return" | "jump" | "label" => true,
+        "inOutVariable" => child(node, "connectionPointIn")
+            .is_some_and(|point| children(point, "connection").next().is_some()),
+        "block" => instance_name(node).is_some(),
+        _ => false,
+    }
+}
+
+/// Sort key for sinks: explicit execution order first, then position.
+fn sink_order(node: Node) -> (f64, f64, f64) {
+    let order = node
+        .attribute("executionOrderId")
+        .and_then(|id| id.parse::<f64>().ok())
+        .filter(|&id| id > 0.0)
+        .unwrap_or(f64::MAX);
+    let position = child(node, "position");
+    let coordinate = |axis| {
+        position
+            .and_then(|p| p.attribute(axis))
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+    (order, coordinate("y"), coordinate("x"))
+}
+
+fn connector_name(node: Node) -> Result<String> {
+    node.attribute("name")
+        .map(str::to_uppercase)
+        .ok_or_else(|| anyhow!("{} has no name", describe(node)))
+}
+
+fn local_id(node: Node) -> Result<u64> {
+    parse_id(
+        node.attribute("localId")
+            .ok_or_else(|| anyhow!("<{}> has no localId", node.tag_name().name()))?,
+    )
+}
+
+fn parse_id(id: &str) -> Result<u64> {
+    id.trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid localId {:?}", id))
+}
+
+/// Human-readable element reference for error messages.
+fn describe(node: Node) -> String {
+    format!(
+        "<{}> {}",
+        node.tag_name().name(),
+        node.attribute("localId").unwrap_or("?")
+    )
+}
+
+fn flag(node: Node, name: &str) -> bool {
+    node.attribute(name) == Some("true")
+}
+
+/// Parse the ST expression held in a child element (`<variable>` of
+/// contacts and coils, `<expression>` of variables).
+fn expression(node: Node, element: &str) -> Result<Expression> {
+    let source = child(node, element)
+        .map(text)
+        .ok_or_else(|| anyhow!("{} has no <{}>", describe(node), element))?;
+    parse_expr(source.trim()).with_context(|| format!("in {}", describe(node)))
+}
+
+fn spanned<T>(node: T) -> Spanned<T> {
+    Spanned::new(node, Span::default())
+}
+
+fn bool_literal(value: bool) -> Expression {
+    Expression::Literal(Literal::Bool(value))
+}
+
+fn is_true(expr: &Expression) -> bool {
+    matches!(expr, Expression::Literal(Literal::Bool(true)))
+}
+
+fn binary(left: Expression, op: BinaryOp, right: Expression) -> Expression {
+    Expression::Binary {
+        left: Box::new(spanned(left)),
+        op,
+        right: Box::new(spanned(right)),
+    }
+}
+
+/// Series connection; a contact on the rail is just its own term.
+fn and(power: Expression, term: Expression) -> Expression {
+    if is_true(&power) {
+        term
+    } else {
+        binary(power, BinaryOp::And, term)
+    }
+}
+
+fn not(operand: Expression) -> Expression {
+    Expression::Unary {
+        op: UnaryOp::Not,
+        operand: Box::new(spanned(operand)),
+    }
+}
+
+fn negate_if(value: Expression, negated: bool) -> Expression {
+    if negated {
+        not(value)
+    } else {
+        value
+    }
+}
+
+fn assign(target: Expression, value: Expression) -> Statement {
+    Statement::Assignment(Assignment {
+        target: spanned(target),
+        value: spanned(value),
+    })
+}
+
+fn if_then(condition: Expression, stmt: Statement) -> Statement {
+    Statement::If(IfStatement {
+        condition: spanned(condition),
+        then_branch: vec![spanned(stmt)],
+        elsif_branches: Vec::new(),
+        else_branch: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::{format_statements, FormatOptions};
+
+    fn translate_xml(xml: &str) -> Result<TranslatedNetwork> {
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        translate("Test", doc.root_element())
+    }
+
+    fn st(xml: &str) -> String {
+        let network = translate_xml(xml).unwrap();
+        format_statements(&network.body, &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_ladder_rungs() {
+        let xml = r#"<LD>
+            <leftPowerRail localId="1"><position x="0" y="0"/><connectionPointOut/></leftPowerRail>
+            <contact localId="2" negated="false"><position x="20" y="10"/>
+              <connectionPointIn><connection refLocalId="1"/></connectionPointIn>
+              <variable>start</variable></contact>
+            <contact localId="3"><position x="20" y="40"/>
+              <connectionPointIn><connection refLocalId="1"/></connectionPointIn>
+              <variable>motor</variable></contact>
+            <contact localId="4" negated="true"><position x="60" y="10"/>
+              <connectionPointIn><connection refLocalId="2"/><connection refLocalId="3"/></connectionPointIn>
+              <variable>stop</variable></contact>
+            <coil localId="5"><position x="100" y="10"/>
+              <connectionPointIn><connection refLocalId="4"/></connectionPointIn>
+              <variable>motor</variable></coil>
+            <coil localId="6" storage="set"><position x="100" y="80"/>
+              <connectionPointIn><connection refLocalId="7"/></connectionPointIn>
+              <variable>alarm</variable></coil>
+            <contact localId="7" edge="rising"><position x="20" y="80"/>
+              <connectionPointIn><connection refLocalId="1"/></connectionPointIn>
+              <variable>fault</variable></contact>
+            <rightPowerRail localId="8"><position x="120" y="0"/>
+              <connectionPointIn><connection refLocalId="5"/></connectionPointIn></rightPowerRail>
+        </LD>"#;
+        let network = translate_xml(xml).unwrap();
+        assert_eq!(
+            format_statements(&network.body, &FormatOptions::default()),
+            "motor := (start OR motor) AND NOT stop;\n\
+             IF fault AND NOT __net_7_prev THEN\n    alarm := TRUE;\nEND_IF;\n\
+             __net_7_prev := fault;\n"
+        );
+        let variables = network.variables.unwrap();
+        assert_eq!(variables.declarations.len(), 1);
+        assert_eq!(variables.declarations[0].node.data_type, DataType::Bool);
+    }
+
+    #[test]
+    fn test_fbd_operators_and_instances() {
+        let xml = r#"<FBD>
+            <inVariable localId="1"><position x="0" y="0"/><connectionPointOut/>
+              <expression>level</expression></inVariable>
+            <inVariable localId="2"><position x="0" y="20"/><connectionPointOut/>
+              <expression>80</expression></inVariable>
+            <block localId="3" typeName="GT"><position x="40" y="0"/>
+              <inputVariables>
+                <variable formalParameter="IN1"><connectionPointIn><connection refLocalId="1"/></connectionPointIn></variable>
+                <variable formalParameter="IN2"><connectionPointIn><connection refLocalId="2"/></connectionPointIn></variable>
+              </inputVariables>
+              <outputVariables><variable formalParameter="OUT"><connectionPointOut/></variable></outputVariables>
+            </block>
+            <block localId="4" typeName="TON" instanceName="delay" executionOrderId="1"><position x="80" y="0"/>
+              <inputVariables>
+                <variable formalParameter="IN"><connectionPointIn><connection refLocalId="3" formalParameter="OUT"/></connectionPointIn></variable>
+                <variable formalParameter="PT"><connectionPointIn><connection refLocalId="5"/></connectionPointIn></variable>
+              </inputVariables>
+              <outputVariables>
+                <variable formalParameter="Q"><connectionPointOut/></variable>
+                <variable formalParameter="ET"><connectionPointOut/></variable>
+              </outputVariables>
+            </block>
+            <inVariable localId="5"><position x="0" y="40"/><connectionPointOut/>
+              <expression>T#5s</expression></inVariable>
+            <outVariable localId="6" executionOrderId="2" negated="true"><position x="140" y="0"/>
+              <connectionPointIn><connection refLocalId="4" formalParameter="Q"/></connectionPointIn>
+              <expression>pump</expression></outVariable>
+        </FBD>"#;
+        assert_eq!(
+            st(xml),
+            "delay(IN := level > 80, PT := T#5s);\npump := NOT delay.Q;\n"
+        );
+    }
+
+    #[test]
+    fn test_connectors_and_function_calls() {
+        let xml = r#"<FBD>
+            <inVariable localId="1"><connectionPointOut/><expression>a</expression></inVariable>
+            <inVariable localId="2"><connectionPointOut/><expression>b</expression></inVariable>
+            <block localId="3" typeName="LIMIT">
+              <inputVariables>
+                <variable formalParameter="EN"><connectionPointIn><connection refLocalId="1"/></connectionPointIn></variable>
+                <variable formalParameter="MN"><connectionPointIn><connection refLocalId="1"/></connectionPointIn></variable>
+                <variable formalParameter="IN"><connectionPointIn><connection refLocalId="2"/></connectionPointIn></variable>
+                <variable formalParameter="MX"><connectionPointIn><connection refLocalId="1"/></connectionPointIn></variable>
+              </inputVariables>
+              <outputVariables><variable formalParameter="OUT"><connectionPointOut/></variable></outputVariables>
+            </block>
+            <connector localId="4" name="limited"><connectionPointIn><connection refLocalId="3"/></connectionPointIn></connector>
+            <continuation localId="5" name="limited"><connectionPointOut/></continuation>
+            <outVariable localId="6"><connectionPointIn><connection refLocalId="5"/></connectionPointIn>
+              <expression>c</expression></outVariable>
+        </FBD>"#;
+        let err = translate_xml(xml).unwrap_err();
+        assert!(format!("{err:#}").contains("EN of LIMIT"), "{err:#}");
+
+        let xml = xml.replace(
+            r#"<connectionPointIn><connection refLocalId="1"/></connectionPointIn></variable>
+                <variable formalParameter="MN">"#,
+            r#"<connectionPointIn/></variable>
+                <variable formalParameter="MN">"#,
+        );
+        assert_eq!(st(&xml), "c := LIMIT(a, b, a);\n");
+    }
+
+    #[test]
+    fn test_feedback_loop_is_rejected() {
+        let xml = r#"<FBD>
+            <block localId="1" typeName="AND">
+              <inputVariables>
+                <variable formalParameter="IN1"><connectionPointIn><connection refLocalId="2"/></connectionPointIn></variable>
+                <variable formalParameter="IN2"><connectionPointIn><connection refLocalId="1"/></connectionPointIn></variable>
+              </inputVariables>
+              <outputVariables><variable formalParameter="OUT"><connectionPointOut/></variable></outputVariables>
+            </block>
+            <inVariable localId="2"><connectionPointOut/><expression>a</expression></inVariable>
+            <outVariable localId="3"><connectionPointIn><connection refLocalId="1"/></connectionPointIn>
+              <expression>b</expression></outVariable>
+        </FBD>"#;
+        let err = translate_xml(xml).unwrap_err();
+        assert!(format!("{err:#}").contains("feedback loop"), "{err:#}");
+    }
+}
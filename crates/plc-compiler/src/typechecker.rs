@@ -5,12 +5,19 @@
 //! - Scope analysis and variable resolution
 //! - Type coercion for numeric operations
 
+use crate::cache::{BuildCache, Dependency, TypedEntry};
+use crate::formatter::format_expression;
 use crate::frontend::{
-    BinaryOp, CaseStatement, CompilationUnit, DataType, Expression, ForStatement, Function,
-    FunctionBlock, IfStatement, Literal, Program, ProgramUnit, RepeatStatement, Spanned, Statement,
-    UnaryOp, VarBlock, VarBlockKind, VarDecl, WhileStatement,
+    sfc, BinaryOp, CallArgument, CallStatement, CaseStatement, CompilationUnit, DataType,
+    Expression, ForStatement, Function, FunctionBlock, IfStatement, Literal, Program, ProgramUnit,
+    RepeatStatement, SfcNetwork, Span, Spanned, Statement, UnaryOp, VarBlock, VarBlockKind,
+    VarDecl, WhileStatement,
 };
 use anyhow::{anyhow, Result};
+use plc_common::abi::ProcessImageLayout;
+use plc_common::build_cache::CacheKey;
+use plc_common::host_api::{self, HostType};
+use plc_common::unit_test::{self, Assertion, TestCase};
 use std::collections::HashMap;
 
 /// Information about a function signature.
@@ -18,10 +25,12 @@ use std::collections::HashMap;
 pub struct FunctionSignature {
     /// Function name.
     pub name: String,
-    /// Return type.
-    pub return_type: DataType,
+    /// Return type; `None` for `VOID` (host functions and function blocks).
+    pub return_type: Option<DataType>,
     /// Parameter types.
     pub params: Vec<DataType>,
+    /// Parameter names.
+    pub param_names: Vec<String>,
     /// Whether this is a user-defined function (vs host import).
     pub is_user_defined: bool,
 }
@@ -33,6 +42,10 @@ pub struct TypedUnit {
     pub units: Vec<TypedPou>,
     /// Function signatures for all defined functions.
     pub functions: HashMap<String, FunctionSignature>,
+    /// Global variables by name.
+    pub globals: HashMap<String, SymbolInfo>,
+    /// Build-cache key of each unit, if checked with a cache.
+    pub cache_keys: Vec<String>,
 }
 
 /// A typed Program Organization Unit.
@@ -51,10 +64,14 @@ pub enum TypedPou {
 pub struct TypedProgram {
     /// Program name.
     pub name: String,
+    /// Source line of the POU declaration.
+    pub line: usize,
     /// Symbol table for this program.
     pub symbols: SymbolTable,
     /// Typed statements.
-    pub body: Vec<TypedStatement>,
+    pub body: Vec<Spanned<TypedStatement>>,
+    /// SFC step names, if the body is a chart.
+    pub sfc_steps: Vec<String>,
 }
 
 /// A typed function block.
@@ -62,10 +79,16 @@ pub struct TypedProgram {
 pub struct TypedFunctionBlock {
     /// Function block name.
     pub name: String,
+    /// Source line of the POU declaration.
+    pub line: usize,
     /// Symbol table.
     pub symbols: SymbolTable,
     /// Typed statements.
-    pub body: Vec<TypedStatement>,
+    pub body: Vec<Spanned<TypedStatement>>,
+    /// SFC step names, if the body is a chart.
+    pub sfc_steps: Vec<String>,
+    /// Test metadata, if this is a `TEST_` function block.
+    pub test: Option<TestCase>,
 }
 
 /// A typed function.
@@ -73,12 +96,14 @@ pub struct TypedFunctionBlock {
 pub struct TypedFunction {
     /// Function name.
     pub name: String,
+    /// Source line of the POU declaration.
+    pub line: usize,
     /// Return type.
     pub return_type: DataType,
     /// Symbol table.
     pub symbols: SymbolTable,
     /// Typed statements.
-    pub body: Vec<TypedStatement>,
+    pub body: Vec<Spanned<TypedStatement>>,
 }
 
 /// Symbol table for a scope.
@@ -105,6 +130,8 @@ pub struct SymbolInfo {
     pub size: usize,
     /// Whether it's a constant.
     pub constant: bool,
+    /// Whether it's declared RETAIN.
+    pub retain: bool,
 }
 
 /// Layout information for a variable.
@@ -133,11 +160,11 @@ pub enum TypedStatement {
         /// Condition (must be BOOL).
         condition: TypedExpr,
         /// Then branch.
-        then_branch: Vec<TypedStatement>,
+        then_branch: Vec<Spanned<TypedStatement>>,
         /// Elsif branches.
-        elsif_branches: Vec<(TypedExpr, Vec<TypedStatement>)>,
+        elsif_branches: Vec<(TypedExpr, Vec<Spanned<TypedStatement>>)>,
         /// Else branch.
-        else_branch: Option<Vec<TypedStatement>>,
+        else_branch: Option<Vec<Spanned<TypedStatement>>>,
     },
     /// For loop.
     For {
@@ -152,30 +179,36 @@ pub enum TypedStatement {
         /// Step value.
         by: Option<TypedExpr>,
         /// Loop body.
-        body: Vec<TypedStatement>,
+        body: Vec<Spanned<TypedStatement>>,
+        /// Maximum number of iterations, from a pragma or constant limits.
+        bound: Option<u64>,
     },
     /// While loop.
     While {
         /// Condition.
         condition: TypedExpr,
         /// Body.
-        body: Vec<TypedStatement>,
+        body: Vec<Spanned<TypedStatement>>,
+        /// Maximum number of iterations, from a pragma.
+        bound: Option<u64>,
     },
     /// Repeat loop.
     Repeat {
         /// Body.
-        body: Vec<TypedStatement>,
+        body: Vec<Spanned<TypedStatement>>,
         /// Until condition.
         until: TypedExpr,
+        /// Maximum number of iterations, from a pragma.
+        bound: Option<u64>,
     },
     /// Case statement.
     Case {
         /// Selector expression.
         selector: TypedExpr,
         /// Branches: (values, statements).
-        branches: Vec<(Vec<i64>, Vec<TypedStatement>)>,
+        branches: Vec<(Vec<i64>, Vec<Spanned<TypedStatement>>)>,
         /// Else branch.
-        else_branch: Option<Vec<TypedStatement>>,
+        else_branch: Option<Vec<Spanned<TypedStatement>>>,
     },
     /// Exit loop.
     Exit,
@@ -189,6 +222,8 @@ pub enum TypedStatement {
         name: String,
         /// Arguments.
         arguments: Vec<TypedExpr>,
+        /// Callee parameter index of each argument, parallel to `arguments`.
+        params: Vec<usize>,
         /// Whether this is a user-defined function (vs host import).
         is_user_defined: bool,
     },
@@ -257,6 +292,8 @@ pub enum TypedExprKind {
         name: String,
         /// Arguments.
         arguments: Vec<TypedExpr>,
+        /// Callee parameter index of each argument, parallel to `arguments`.
+        params: Vec<usize>,
         /// Whether this is a user-defined function (vs host import).
         is_user_defined: bool,
     },
@@ -277,10 +314,130 @@ pub enum TypedLiteral {
     Time(i64),
 }
 
-/// Type check a compilation unit.
+/// ST type of a host function parameter or result.
+pub(crate) fn host_data_type(ty: HostType) -> DataType {
+    match ty {
+        HostType::Bool => DataType::Bool,
+        HostType::Int => DataType::Int,
+        HostType::Dint => DataType::Dint,
+        HostType::Lint => DataType::Lint,
+        HostType::Usint => DataType::Usint,
+        HostType::Uint => DataType::Uint,
+        HostType::Udint => DataType::Udint,
+        HostType::Real => DataType::Real,
+    }
+}
+
+/// Value of an integer constant expression (a literal, possibly negated).
+fn const_int(expr: &TypedExpr) -> Option<i64> {
+    match &expr.kind {
+        TypedExprKind::Literal(TypedLiteral::Integer(value, _)) => Some(*value),
+        TypedExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => const_int(operand)?.checked_neg(),
+        _ => None,
+    }
+}
+
+/// Number of iterations of `FOR i := from TO to BY step`; `None` if the loop
+/// never terminates.
+fn for_iterations(from: i64, to: i64, step: i64) -> Option<u64> {
+    let (span, step) = match step {
+        0 => return None,
+        s if s > 0 => (i128::from(to) - i128::from(from), i128::from(s)),
+        s => (i128::from(from) - i128::from(to), -i128::from(s)),
+    };
+    Some(if span < 0 {
+        0
+    } else {
+        (span / step + 1) as u64
+    })
+}
+
+/// Whether any statement, including nested ones, assigns the variable at
+/// `offset`.
+fn assigns(statements: &[Spanned<TypedStatement>], offset: usize) -> bool {
+    statements.iter().any(|stmt| match &stmt.node {
+        TypedStatement::Assignment { target, .. } => {
+            matches!(target.kind, TypedExprKind::Variable { offset: o, .. } if o == offset)
+        }
+        TypedStatement::If {
+            then_branch,
+            elsif_branches,
+            else_branch,
+            ..
+        } => {
+            assigns(then_branch, offset)
+                || elsif_branches.iter().any(|(_, b)| assigns(b, offset))
+                || else_branch.as_deref().is_some_and(|b| assigns(b, offset))
+        }
+        TypedStatement::For {
+            var_offset, body, ..
+        } => *var_offset == offset || assigns(body, offset),
+        TypedStatement::While { body, .. } | TypedStatement::Repeat { body, .. } => {
+            assigns(body, offset)
+        }
+        TypedStatement::Case {
+            branches,
+            else_branch,
+            ..
+        } => {
+            branches.iter().any(|(_, b)| assigns(b, offset))
+                || else_branch.as_deref().is_some_and(|b| assigns(b, offset))
+        }
+        _ => false,
+    })
+}
+
+/// Whether a called name is one of the test assertion built-ins.
+fn is_assertion(name: &str) -> bool {
+    name.eq_ignore_ascii_case("ASSERT_TRUE") || name.eq_ignore_ascii_case("ASSERT_EQ")
+}
+
+/// Collect the names and types of a POU's VAR_INPUT parameters, in order.
+fn input_params(variables: &[Spanned<VarBlock>]) -> (Vec<String>, Vec<DataType>) {
+    variables
+        .iter()
+        .filter(|vb| vb.node.kind == VarBlockKind::Input)
+        .flat_map(|vb| vb.node.declarations.iter())
+        .map(|d| (d.node.name.clone(), d.node.data_type.clone()))
+        .unzip()
+}
+
+/// Type check a compilation unit for the default process image.
 pub fn check(ast: &CompilationUnit) -> Result<TypedUnit> {
-    let mut checker = TypeChecker::new();
-    checker.check_unit(ast)
+    check_for(ast, &ProcessImageLayout::CURRENT)
+}
+
+/// Type check a compilation unit, allocating variables after the process
+/// image described by `layout`.
+pub fn check_for(ast: &CompilationUnit, layout: &ProcessImageLayout) -> Result<TypedUnit> {
+    let mut checker = TypeChecker::new(data_offset(layout));
+    checker.check_unit(ast, None)
+}
+
+/// Type check a compilation unit, reusing POUs checked by earlier builds.
+pub fn check_cached(
+    ast: &CompilationUnit,
+    cache: &mut BuildCache,
+    layout: &ProcessImageLayout,
+) -> Result<TypedUnit> {
+    let mut checker = TypeChecker::new(data_offset(layout));
+    checker.check_unit(ast, Some(cache))
+}
+
+/// First byte available for variables. Modules have always kept the first
+/// 0x100 bytes free, so a layout that fits keeps variable offsets stable.
+fn data_offset(layout: &ProcessImageLayout) -> usize {
+    (layout.user_data_offset as usize).max(0x100)
+}
+
+/// Fingerprint of a symbol table, independent of hash map order.
+fn symbols_fingerprint<'a>(variables: impl Iterator<Item = &'a SymbolInfo>) -> String {
+    let mut entries: Vec<_> = variables.map(|info| format!("{info:?}")).collect();
+    entries.sort();
+    entries.join("\n")
 }
 
 /// Type checker implementation.
@@ -291,98 +448,100 @@ struct TypeChecker {
     next_offset: usize,
     /// Known function signatures (both user-defined and host imports).
     functions: HashMap<String, FunctionSignature>,
+    /// Global variables, shared by every POU that declares them.
+    globals: HashMap<String, SymbolInfo>,
+    /// The test being checked, collecting its assertions.
+    test: Option<TestCase>,
+    /// Symbols of the function blocks checked so far, by upper-cased name,
+    /// for tests accessing `Fb.var`.
+    fb_symbols: HashMap<String, SymbolTable>,
+    /// Interfaces used by the POU being checked, for the build cache.
+    deps: Vec<Dependency>,
 }
 
 impl TypeChecker {
-    fn new() -> Self {
+    fn new(data_offset: usize) -> Self {
         let mut functions = HashMap::new();
 
-        // Register built-in host functions
-        functions.insert(
-            "read_di".to_string(),
-            FunctionSignature {
-                name: "read_di".to_string(),
-                return_type: DataType::Int,
-                params: vec![DataType::Int],
-                is_user_defined: false,
-            },
-        );
-        functions.insert(
-            "write_do".to_string(),
-            FunctionSignature {
-                name: "write_do".to_string(),
-                return_type: DataType::Bool, // void, but we use Bool
-                params: vec![DataType::Int, DataType::Int],
-                is_user_defined: false,
-            },
-        );
-        functions.insert(
-            "read_ai".to_string(),
-            FunctionSignature {
-                name: "read_ai".to_string(),
-                return_type: DataType::Int,
-                params: vec![DataType::Int],
-                is_user_defined: false,
-            },
-        );
-        functions.insert(
-            "write_ao".to_string(),
-            FunctionSignature {
-                name: "write_ao".to_string(),
-                return_type: DataType::Bool,
-                params: vec![DataType::Int, DataType::Int],
-                is_user_defined: false,
-            },
-        );
-        functions.insert(
-            "get_cycle_time".to_string(),
-            FunctionSignature {
-                name: "get_cycle_time".to_string(),
-                return_type: DataType::Int,
-                params: vec![],
-                is_user_defined: false,
-            },
-        );
+        for host in host_api::HOST_FUNCTIONS {
+            functions.insert(
+                host.name.to_string(),
+                FunctionSignature {
+                    name: host.name.to_string(),
+                    return_type: host.result.map(host_data_type),
+                    params: host
+                        .params
+                        .iter()
+                        .map(|(_, ty)| host_data_type(*ty))
+                        .collect(),
+                    param_names: host
+                        .params
+                        .iter()
+                        .map(|(name, _)| name.to_string())
+                        .collect(),
+                    is_user_defined: false,
+                },
+            );
+        }
 
         Self {
             symbols: SymbolTable::default(),
-            // Start after process image area (0x100 bytes reserved)
-            next_offset: 0x100,
+            // Start after the process image area
+            next_offset: data_offset,
             functions,
+            globals: HashMap::new(),
+            test: None,
+            fb_symbols: HashMap::new(),
+            deps: Vec::new(),
         }
     }
 
-    fn check_unit(&mut self, ast: &CompilationUnit) -> Result<TypedUnit> {
+    fn check_unit(
+        &mut self,
+        ast: &CompilationUnit,
+        mut cache: Option<&mut BuildCache>,
+    ) -> Result<TypedUnit> {
+        // Globals are allocated once, before any POU, so that every VAR_GLOBAL
+        // and VAR_EXTERNAL declaration of a name resolves to the same storage.
+        let pou_blocks = ast.units.iter().flat_map(|unit| match &unit.node {
+            ProgramUnit::Program(p) => p.variables.iter(),
+            ProgramUnit::FunctionBlock(fb) => fb.variables.iter(),
+            ProgramUnit::Function(f) => f.variables.iter(),
+        });
+        for block in ast.globals.iter().chain(pou_blocks) {
+            if block.node.kind == VarBlockKind::Global {
+                for decl in &block.node.declarations {
+                    self.register_global(&decl.node, block.node.constant, block.node.retain)?;
+                }
+            }
+        }
+
         // First pass: collect all function signatures
         for spanned_unit in &ast.units {
             match &spanned_unit.node {
                 ProgramUnit::Function(f) => {
-                    let params: Vec<DataType> = f
-                        .variables
-                        .iter()
-                        .filter(|vb| vb.node.kind == VarBlockKind::Input)
-                        .flat_map(|vb| vb.node.declarations.iter())
-                        .map(|d| d.node.data_type.clone())
-                        .collect();
-
+                    let (param_names, params) = input_params(&f.variables);
                     self.functions.insert(
                         f.name.clone(),
                         FunctionSignature {
                             name: f.name.clone(),
-                            return_type: f.return_type.clone(),
+                            return_type: Some(f.return_type.clone()),
                             params,
+                            param_names,
                             is_user_defined: true,
                         },
                     );
                 }
                 ProgramUnit::FunctionBlock(fb) => {
                     // Function blocks are also callable
+                    let (param_names, params) = input_params(&fb.variables);
                     self.functions.insert(
                         fb.name.clone(),
                         FunctionSignature {
                             name: fb.name.clone(),
-                            return_type: DataType::Bool, // FBs don't return values directly
-                            params: vec![],
+                            return_type: None, // FBs don't return values directly
+                            params,
+                            param_names,
                             is_user_defined: true,
                         },
                     );
@@ -391,28 +550,122 @@ impl TypeChecker {
             }
         }
 
-        // Second pass: type check all units
+        // Second pass: type check all units. Tests go last, so that they can
+        // access the variables of every function block.
+        let (tests, pous): (Vec<_>, Vec<_>) = ast.units.iter().partition(|unit| {
+            matches!(&unit.node, ProgramUnit::FunctionBlock(fb) if unit_test::is_test(&fb.name))
+        });
         let mut units = Vec::new();
-        for spanned_unit in &ast.units {
+        let mut cache_keys = Vec::new();
+        for spanned_unit in pous.into_iter().chain(tests) {
+            let key = cache.is_some().then(|| self.cache_key(spanned_unit));
+            if let (Some(cache), Some(key)) = (cache.as_deref_mut(), &key) {
+                if let Some(pou) = self.reuse(cache, key) {
+                    units.push(pou);
+                    cache_keys.push(key.clone());
+                    continue;
+                }
+            }
+
+            self.deps.clear();
             let typed = match &spanned_unit.node {
-                ProgramUnit::Program(p) => TypedPou::Program(self.check_program(p)?),
+                ProgramUnit::Program(p) => {
+                    TypedPou::Program(self.check_program(p, spanned_unit.span.line)?)
+                }
                 ProgramUnit::FunctionBlock(fb) => {
-                    TypedPou::FunctionBlock(self.check_function_block(fb)?)
+                    TypedPou::FunctionBlock(self.check_function_block(fb, spanned_unit.span.line)?)
+                }
+                ProgramUnit::Function(f) => {
+                    TypedPou::Function(self.check_function(f, spanned_unit.span.line)?)
                 }
-                ProgramUnit::Function(f) => TypedPou::Function(self.check_function(f)?),
             };
+            if let (Some(cache), Some(key)) = (cache.as_deref_mut(), key) {
+                let mut deps = std::mem::take(&mut self.deps);
+                deps.sort();
+                deps.dedup();
+                let deps = deps
+                    .into_iter()
+                    .filter_map(|dep| Some((dep.clone(), self.fingerprint(&dep)?)))
+                    .collect();
+                let entry = TypedEntry {
+                    pou: typed.clone(),
+                    end_offset: self.next_offset,
+                    deps,
+                };
+                cache.insert_typed(key.clone(), entry);
+                cache_keys.push(key);
+            }
             units.push(typed);
         }
 
         Ok(TypedUnit {
             units,
             functions: self.functions.clone(),
+            globals: self.globals.clone(),
+            cache_keys,
         })
     }
 
-    fn check_program(&mut self, program: &Program) -> Result<TypedProgram> {
+    /// Cache key of a POU: its source, where its variables start and the
+    /// globals. Spans are part of the source, so moving a POU to another
+    /// line invalidates its debug line map entry as well.
+    fn cache_key(&self, unit: &Spanned<ProgramUnit>) -> String {
+        let mut key = CacheKey::new("typed");
+        key.add(format!("{unit:?}"))
+            .add(self.next_offset.to_le_bytes())
+            .add(symbols_fingerprint(self.globals.values()));
+        key.finish()
+    }
+
+    /// Take a POU from the cache if the interfaces it uses are unchanged.
+    fn reuse(&mut self, cache: &mut BuildCache, key: &str) -> Option<TypedPou> {
+        let entry = cache.typed(key)?;
+        if !entry
+            .deps
+            .iter()
+            .all(|(dep, fingerprint)| self.fingerprint(dep).as_ref() == Some(fingerprint))
+        {
+            return None;
+        }
+        let TypedEntry {
+            pou, end_offset, ..
+        } = entry.clone();
+        cache.reused_typed();
+
+        self.next_offset = end_offset;
+        if let TypedPou::FunctionBlock(fb) = &pou {
+            self.fb_symbols
+                .insert(fb.name.to_uppercase(), fb.symbols.clone());
+        }
+        Some(pou)
+    }
+
+    /// Current fingerprint of an interface; `None` if it no longer exists.
+    fn fingerprint(&self, dep: &Dependency) -> Option<String> {
+        match dep {
+            Dependency::Function(name) => self.functions.get(name).map(|sig| format!("{sig:?}")),
+            Dependency::FunctionBlock(name) => self
+                .fb_symbols
+                .get(name)
+                .map(|table| symbols_fingerprint(table.variables.values())),
+        }
+    }
+
+    /// Look up a function signature, recording the dependency.
+    fn signature(&mut self, name: &str) -> Result<FunctionSignature> {
+        let sig = self
+            .functions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown function: {}", name))?;
+        self.deps.push(Dependency::Function(name.to_string()));
+        Ok(sig)
+    }
+
+    fn check_program(&mut self, program: &Program, line: usize) -> Result<TypedProgram> {
+        // Offsets keep growing across POUs: every POU owns its memory region,
+        // so a call cannot clobber the caller's variables.
         self.symbols = SymbolTable::default();
-        self.next_offset = 0x100;
 
         // Register variables
         for var_block in &program.variables {
@@ -420,35 +673,82 @@ impl TypeChecker {
         }
 
         // Type check body
-        let body = self.check_statements(&program.body)?;
+        let (body, sfc_steps) = self.check_body(&program.name, &program.body, &program.sfc)?;
 
         Ok(TypedProgram {
             name: program.name.clone(),
+            line,
             symbols: self.symbols.clone(),
             body,
+            sfc_steps,
         })
     }
 
-    fn check_function_block(&mut self, fb: &FunctionBlock) -> Result<TypedFunctionBlock> {
+    fn check_function_block(
+        &mut self,
+        fb: &FunctionBlock,
+        line: usize,
+    ) -> Result<TypedFunctionBlock> {
         self.symbols = SymbolTable::default();
-        self.next_offset = 0x100;
 
         for var_block in &fb.variables {
             self.register_var_block(&var_block.node)?;
         }
 
-        let body = self.check_statements(&fb.body)?;
+        if unit_test::is_test(&fb.name) {
+            self.test = Some(TestCase {
+                name: fb.name.clone(),
+                line: line as u32,
+                ..TestCase::default()
+            });
+            for (name, data_type) in [
+                (unit_test::FAILED, DataType::Udint),
+                (unit_test::ACTUAL, DataType::Lreal),
+                (unit_test::EXPECTED, DataType::Lreal),
+            ] {
+                let decl = VarDecl {
+                    name: name.to_string(),
+                    data_type,
+                    initial_value: None,
+                    address: None,
+                };
+                self.register_variable(&decl, VarBlockKind::Var, false, false)?;
+            }
+        }
+
+        let (body, sfc_steps) = self.check_body(&fb.name, &fb.body, &fb.sfc)?;
+        self.fb_symbols
+            .insert(fb.name.to_uppercase(), self.symbols.clone());
 
         Ok(TypedFunctionBlock {
             name: fb.name.clone(),
+            line,
             symbols: self.symbols.clone(),
             body,
+            sfc_steps,
+            test: self.test.take(),
         })
     }
 
-    fn check_function(&mut self, func: &Function) -> Result<TypedFunction> {
+    /// Check a program or FB body, lowering an SFC network to ST first.
+    fn check_body(
+        &mut self,
+        pou: &str,
+        body: &[Spanned<Statement>],
+        sfc: &Option<SfcNetwork>,
+    ) -> Result<(Vec<Spanned<TypedStatement>>, Vec<String>)> {
+        let Some(network) = sfc else {
+            return Ok((self.check_statements(body)?, Vec::new()));
+        };
+
+        let lowered = sfc::lower(pou, network)?;
+        self.register_var_block(&lowered.variables)?;
+        let body = self.check_statements(&lowered.body)?;
+        Ok((body, lowered.steps))
+    }
+
+    fn check_function(&mut self, func: &Function, line: usize) -> Result<TypedFunction> {
         self.symbols = SymbolTable::default();
-        self.next_offset = 0x100;
 
         // Register return value as a variable
         let ret_size = func.return_type.size_bytes().unwrap_or(4);
@@ -461,6 +761,7 @@ impl TypeChecker {
                 offset: self.next_offset,
                 size: ret_size,
                 constant: false,
+                retain: false,
             },
         );
         self.next_offset += ret_size;
@@ -473,6 +774,7 @@ impl TypeChecker {
 
         Ok(TypedFunction {
             name: func.name.clone(),
+            line,
             return_type: func.return_type.clone(),
             symbols: self.symbols.clone(),
             body,
@@ -481,17 +783,74 @@ impl TypeChecker {
 
     fn register_var_block(&mut self, block: &VarBlock) -> Result<()> {
         for decl in &block.declarations {
-            self.register_variable(&decl.node, block.kind, block.constant)?;
+            self.register_variable(&decl.node, block.kind, block.constant, block.retain)?;
         }
         Ok(())
     }
 
+    fn register_global(&mut self, decl: &VarDecl, constant: bool, retain: bool) -> Result<()> {
+        if let Some(existing) = self.globals.get(&decl.name) {
+            if existing.data_type != decl.data_type {
+                return Err(anyhow!(
+                    "Global {} is declared as both {} and {}",
+                    decl.name,
+                    existing.data_type,
+                    decl.data_type
+                ));
+            }
+            return Ok(());
+        }
+
+        let size = decl.data_type.size_bytes().unwrap_or(4);
+        let alignment = size.min(8);
+        self.next_offset = (self.next_offset + alignment - 1) & !(alignment - 1);
+        self.globals.insert(
+            decl.name.clone(),
+            SymbolInfo {
+                name: decl.name.clone(),
+                data_type: decl.data_type.clone(),
+                kind: VarBlockKind::Global,
+                offset: self.next_offset,
+                size,
+                constant,
+                retain,
+            },
+        );
+        self.next_offset += size;
+        Ok(())
+    }
+
     fn register_variable(
         &mut self,
         decl: &VarDecl,
         kind: VarBlockKind,
         constant: bool,
+        retain: bool,
     ) -> Result<()> {
+        // Globals live outside the POU's own layout; see `register_global`.
+        if matches!(kind, VarBlockKind::Global | VarBlockKind::External) {
+            let global = self
+                .globals
+                .get(&decl.name)
+                .ok_or_else(|| anyhow!("VAR_EXTERNAL {} has no matching VAR_GLOBAL", decl.name))?;
+            if global.data_type != decl.data_type {
+                return Err(anyhow!(
+                    "VAR_EXTERNAL {} : {} does not match VAR_GLOBAL {} : {}",
+                    decl.name,
+                    decl.data_type,
+                    decl.name,
+                    global.data_type
+                ));
+            }
+            let info = SymbolInfo {
+                kind,
+                constant: constant || global.constant,
+                ..global.clone()
+            };
+            self.symbols.variables.insert(decl.name.clone(), info);
+            return Ok(());
+        }
+
         let size = decl.data_type.size_bytes().unwrap_or(4);
 
         // Align offset
@@ -505,6 +864,7 @@ impl TypeChecker {
             offset: self.next_offset,
             size,
             constant,
+            retain,
         };
 
         self.symbols.layout.push(VarLayout {
@@ -522,16 +882,128 @@ impl TypeChecker {
     fn check_statements(
         &mut self,
         statements: &[Spanned<Statement>],
-    ) -> Result<Vec<TypedStatement>> {
+    ) -> Result<Vec<Spanned<TypedStatement>>> {
         let mut typed = Vec::new();
         for stmt in statements {
-            if let Some(t) = self.check_statement(&stmt.node)? {
-                typed.push(t);
+            let checked = match &stmt.node {
+                Statement::Call(call) if is_assertion(&call.name) => {
+                    Some(self.check_assertion(call, stmt.span.line)?)
+                }
+                other => self.check_statement(other)?,
+            };
+            if let Some(t) = checked {
+                typed.push(Spanned::new(t, stmt.span));
             }
         }
         Ok(typed)
     }
 
+    /// Lower `ASSERT_TRUE(cond)` or `ASSERT_EQ(actual, expected)` in a test.
+    ///
+    /// The assertion becomes an IF that records its id in the test's
+    /// `FAILED` variable, unless an earlier assertion already failed. For
+    /// `ASSERT_EQ` of elementary values, both values are also stored as
+    /// LREAL so the runner can report them; they are evaluated again for
+    /// this, on the failure path only.
+    fn check_assertion(&mut self, call: &CallStatement, line: usize) -> Result<TypedStatement> {
+        let name = call.name.to_uppercase();
+        let Some(test) = &self.test else {
+            return Err(anyhow!(
+                "{} is only allowed in test function blocks ({}*)",
+                name,
+                unit_test::PREFIX
+            ));
+        };
+        let id = test.assertions.len() as u32 + 1;
+
+        let arity = if name == "ASSERT_EQ" { 2 } else { 1 };
+        if call.arguments.len() != arity || call.arguments.iter().any(|a| a.name.is_some()) {
+            return Err(anyhow!("{} takes {} positional arguments", name, arity));
+        }
+        let args: Vec<&Expression> = call.arguments.iter().map(|a| &a.value.node).collect();
+        let message = format!(
+            "{}({})",
+            name,
+            args.iter()
+                .map(|a| format_expression(a))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let spanned = |e: Expression| Box::new(Spanned::new(e, Span::default()));
+        let paren = |e: &Expression| spanned(Expression::Paren(spanned(e.clone())));
+        let failed = match args[..] {
+            [condition] => Expression::Unary {
+                op: UnaryOp::Not,
+                operand: paren(condition),
+            },
+            [actual, expected] => Expression::Binary {
+                left: paren(actual),
+                op: BinaryOp::Ne,
+                right: paren(expected),
+            },
+            _ => unreachable!("arity checked above"),
+        };
+        let first_failure = Expression::Binary {
+            left: spanned(Expression::Variable(unit_test::FAILED.into())),
+            op: BinaryOp::Eq,
+            right: spanned(Expression::Literal(Literal::Integer(0))),
+        };
+        let condition = self.check_expr(&Expression::Binary {
+            left: spanned(failed),
+            op: BinaryOp::And,
+            right: spanned(first_failure),
+        })?;
+        self.expect_bool(&condition.ty)?;
+
+        let mut then_branch = vec![TypedStatement::Assignment {
+            target: self.check_expr(&Expression::Variable(unit_test::FAILED.into()))?,
+            value: TypedExpr {
+                kind: TypedExprKind::Literal(TypedLiteral::Integer(i64::from(id), 32)),
+                ty: DataType::Udint,
+            },
+        }];
+        let mut value_type = None;
+        if let [actual, expected] = args[..] {
+            let actual = self.check_expr(actual)?;
+            let expected = self.check_expr(expected)?;
+            let recordable =
+                |ty: &DataType| ty.is_numeric() || matches!(ty, DataType::Bool | DataType::Time);
+            if recordable(&actual.ty) && recordable(&expected.ty) {
+                value_type = Some(actual.ty.to_string());
+                for (slot, value) in [(unit_test::ACTUAL, actual), (unit_test::EXPECTED, expected)]
+                {
+                    then_branch.push(TypedStatement::Assignment {
+                        target: self.check_expr(&Expression::Variable(slot.into()))?,
+                        value,
+                    });
+                }
+            }
+        }
+
+        if let Some(test) = &mut self.test {
+            test.assertions.push(Assertion {
+                id,
+                line: line as u32,
+                value_type,
+                message,
+            });
+        }
+        let span = Span {
+            line,
+            ..Span::default()
+        };
+        Ok(TypedStatement::If {
+            condition,
+            then_branch: then_branch
+                .into_iter()
+                .map(|stmt| Spanned::new(stmt, span))
+                .collect(),
+            elsif_branches: Vec::new(),
+            else_branch: None,
+        })
+    }
+
     fn check_statement(&mut self, stmt: &Statement) -> Result<Option<TypedStatement>> {
         match stmt {
             Statement::Assignment(assign) => {
@@ -558,21 +1030,14 @@ impl TypeChecker {
                 Ok(Some(TypedStatement::Return(typed_expr)))
             }
             Statement::Call(call) => {
-                let args: Result<Vec<_>> = call
-                    .arguments
-                    .iter()
-                    .map(|a| self.check_expr(&a.value.node))
-                    .collect();
-
                 // Look up function - error if not found (mirrors Expression::Call behavior)
-                let func_sig = self
-                    .functions
-                    .get(&call.name)
-                    .ok_or_else(|| anyhow!("Unknown function: {}", call.name))?;
+                let func_sig = self.signature(&call.name)?;
+                let (arguments, params) = self.check_call_args(&func_sig, &call.arguments)?;
 
                 Ok(Some(TypedStatement::Call {
                     name: call.name.clone(),
-                    arguments: args?,
+                    arguments,
+                    params,
                     is_user_defined: func_sig.is_user_defined,
                 }))
             }
@@ -628,6 +1093,13 @@ impl TypeChecker {
             .transpose()?;
 
         let body = self.check_statements(&for_stmt.body)?;
+        let bound = for_stmt.bound.or_else(|| {
+            if assigns(&body, var_info.offset) {
+                return None;
+            }
+            let step = by.as_ref().map_or(Some(1), const_int)?;
+            for_iterations(const_int(&from)?, const_int(&to)?, step)
+        });
 
         Ok(Some(TypedStatement::For {
             variable: for_stmt.variable.clone(),
@@ -636,6 +1108,7 @@ impl TypeChecker {
             to,
             by,
             body,
+            bound,
         }))
     }
 
@@ -645,7 +1118,11 @@ impl TypeChecker {
 
         let body = self.check_statements(&while_stmt.body)?;
 
-        Ok(Some(TypedStatement::While { condition, body }))
+        Ok(Some(TypedStatement::While {
+            condition,
+            body,
+            bound: while_stmt.bound,
+        }))
     }
 
     fn check_repeat(&mut self, repeat_stmt: &RepeatStatement) -> Result<Option<TypedStatement>> {
@@ -654,7 +1131,11 @@ impl TypeChecker {
         let until = self.check_expr(&repeat_stmt.until.node)?;
         self.expect_bool(&until.ty)?;
 
-        Ok(Some(TypedStatement::Repeat { body, until }))
+        Ok(Some(TypedStatement::Repeat {
+            body,
+            until,
+            bound: repeat_stmt.bound,
+        }))
     }
 
     fn check_case(&mut self, case_stmt: &CaseStatement) -> Result<Option<TypedStatement>> {
@@ -726,6 +1207,9 @@ impl TypeChecker {
                 })
             }
             Expression::FieldAccess { object, field } => {
+                if let Some(var) = self.fb_variable(&object.node, field) {
+                    return Ok(var);
+                }
                 let obj = self.check_expr(&object.node)?;
                 // Simplified: assume INT field type
                 Ok(TypedExpr {
@@ -774,24 +1258,21 @@ impl TypeChecker {
                 })
             }
             Expression::Call { name, arguments } => {
-                let args: Result<Vec<_>> = arguments
-                    .iter()
-                    .map(|a| self.check_expr(&a.value.node))
-                    .collect();
-
                 // Look up function signature - error if not found
-                let func_sig = self
-                    .functions
-                    .get(name)
-                    .ok_or_else(|| anyhow!("Unknown function: {}", name))?;
+                let func_sig = self.signature(name)?;
+                let (arguments, params) = self.check_call_args(&func_sig, arguments)?;
+                let ty = func_sig
+                    .return_type
+                    .ok_or_else(|| anyhow!("{} does not return a value", name))?;
 
                 Ok(TypedExpr {
                     kind: TypedExprKind::Call {
                         name: name.clone(),
-                        arguments: args?,
+                        arguments,
+                        params,
                         is_user_defined: func_sig.is_user_defined,
                     },
-                    ty: func_sig.return_type.clone(),
+                    ty,
                 })
             }
             Expression::Paren(inner) => self.check_expr(&inner.node),
@@ -842,6 +1323,35 @@ impl TypeChecker {
         }
     }
 
+    /// Resolve `Fb.var` in a test to the variable of the function block.
+    ///
+    /// Function blocks are singletons, so tests can inspect and set their
+    /// variables directly.
+    fn fb_variable(&mut self, object: &Expression, field: &str) -> Option<TypedExpr> {
+        let Expression::Variable(pou) = object else {
+            return None;
+        };
+        if self.test.is_none() || self.symbols.variables.contains_key(pou) {
+            return None;
+        }
+        let fb = pou.to_uppercase();
+        let info = self
+            .fb_symbols
+            .get(&fb)?
+            .variables
+            .values()
+            .find(|info| info.name.eq_ignore_ascii_case(field))?
+            .clone();
+        self.deps.push(Dependency::FunctionBlock(fb));
+        Some(TypedExpr {
+            kind: TypedExprKind::Variable {
+                name: format!("{pou}.{}", info.name),
+                offset: info.offset,
+            },
+            ty: info.data_type.clone(),
+        })
+    }
+
     fn check_variable(&self, name: &str) -> Result<TypedExpr> {
         let info = self
             .symbols
@@ -898,6 +1408,10 @@ impl TypeChecker {
 
     fn numeric_promotion(&self, left: &DataType, right: &DataType) -> Result<DataType> {
         // Simplified promotion rules
+        if *left == DataType::Time || *right == DataType::Time {
+            // Durations may be added together or scaled by integers.
+            return Ok(DataType::Time);
+        }
         if *left == DataType::Lreal || *right == DataType::Lreal {
             return Ok(DataType::Lreal);
         }
@@ -913,6 +1427,55 @@ impl TypeChecker {
         Ok(DataType::Int)
     }
 
+    /// Type check call arguments and resolve each one to a parameter index.
+    ///
+    /// Positional arguments bind in declaration order; named arguments
+    /// (`name := value`) bind by parameter name. Host imports take positional
+    /// arguments only.
+    fn check_call_args(
+        &mut self,
+        sig: &FunctionSignature,
+        arguments: &[CallArgument],
+    ) -> Result<(Vec<TypedExpr>, Vec<usize>)> {
+        let mut typed = Vec::with_capacity(arguments.len());
+        let mut params = Vec::with_capacity(arguments.len());
+
+        for (position, arg) in arguments.iter().enumerate() {
+            let value = self.check_expr(&arg.value.node)?;
+            let index = match &arg.name {
+                Some(param) if sig.is_user_defined => sig
+                    .param_names
+                    .iter()
+                    .position(|p| p.eq_ignore_ascii_case(param))
+                    .ok_or_else(|| anyhow!("{} has no input named {}", sig.name, param))?,
+                Some(_) => return Err(anyhow!("{} takes positional arguments only", sig.name)),
+                None => position,
+            };
+            let param_ty = sig.params.get(index).ok_or_else(|| {
+                anyhow!(
+                    "Too many arguments for {}: expected at most {}",
+                    sig.name,
+                    sig.params.len()
+                )
+            })?;
+            self.check_assignment_types(param_ty, &value.ty)?;
+            typed.push(value);
+            params.push(index);
+        }
+
+        // Host functions have no defaults for missing arguments.
+        if !sig.is_user_defined && typed.len() != sig.params.len() {
+            return Err(anyhow!(
+                "{} expects {} arguments, got {}",
+                sig.name,
+                sig.params.len(),
+                typed.len()
+            ));
+        }
+
+        Ok((typed, params))
+    }
+
     fn check_assignment_types(&self, target: &DataType, source: &DataType) -> Result<()> {
         // Simplified: allow same types or numeric promotion
         if target == source {
@@ -1019,6 +1582,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_globals_are_shared_between_pous() {
+        let source = r#"
+            FUNCTION_BLOCK Counter
+            VAR_EXTERNAL
+                total : DINT;
+            END_VAR
+                total := total + 1;
+            END_FUNCTION_BLOCK
+
+            PROGRAM Main
+            VAR_GLOBAL
+                total : DINT;
+            END_VAR
+                Counter();
+            END_PROGRAM
+        "#;
+
+        let typed = check(&parse(source).unwrap()).unwrap();
+        let offset = |pou: &TypedPou| {
+            let symbols = match pou {
+                TypedPou::Program(p) => &p.symbols,
+                TypedPou::FunctionBlock(fb) => &fb.symbols,
+                TypedPou::Function(f) => &f.symbols,
+            };
+            symbols.variables["total"].offset
+        };
+        assert_eq!(offset(&typed.units[0]), offset(&typed.units[1]));
+
+        let missing = source.replace("VAR_GLOBAL", "VAR");
+        let err = check(&parse(&missing).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("no matching VAR_GLOBAL"), "{err}");
+
+        let mismatch = source.replace(
+            "total : DINT;\n            END_VAR\n                total := total",
+            "total : INT;\n            END_VAR\n                total := total",
+        );
+        let err = check(&parse(&mismatch).unwrap()).unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{err}");
+    }
+
     #[test]
     fn test_unknown_function_error() {
         let source = r#"
@@ -1061,6 +1665,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_host_function_signatures() {
+        let check_body = |body: &str| {
+            let source =
+                format!("PROGRAM Test\nVAR x : INT; b : BOOL; END_VAR\n{body}\nEND_PROGRAM");
+            check(&parse(&source).unwrap()).map(|_| ())
+        };
+
+        assert!(check_body("write_do(0, b); b := is_first_cycle();").is_ok());
+        let err = check_body("x := write_do(0, b);").unwrap_err();
+        assert!(err.to_string().contains("does not return a value"), "{err}");
+        let err = check_body("write_do(0);").unwrap_err();
+        assert!(err.to_string().contains("expects 2 arguments"), "{err}");
+        let err = check_body("write_do(0, b, 1);").unwrap_err();
+        assert!(err.to_string().contains("Too many arguments"), "{err}");
+        let err = check_body("write_do(0, x);").unwrap_err();
+        assert!(
+            err.to_string().contains("Cannot assign INT to BOOL"),
+            "{err}"
+        );
+        let err = check_body("write_ao(channel := 0, value := x);").unwrap_err();
+        assert!(err.to_string().contains("positional"), "{err}");
+    }
+
     #[test]
     fn test_unknown_function_statement_error() {
         // Test unknown function called as statement (not in expression context)
@@ -1081,4 +1709,59 @@ mod tests {
             err_msg
         );
     }
+
+    #[test]
+    fn test_assertion_outside_test_error() {
+        let source = r#"
+            PROGRAM Test
+            VAR
+                x : INT;
+            END_VAR
+                ASSERT_EQ(x, 1);
+            END_PROGRAM
+        "#;
+
+        let ast = parse(source).unwrap();
+        let err_msg = check(&ast).unwrap_err().to_string();
+        assert!(err_msg.contains("ASSERT_EQ"), "got: {}", err_msg);
+    }
+
+    #[test]
+    fn test_assertions_in_test() {
+        let source = r#"
+            FUNCTION_BLOCK Counter
+            VAR_OUTPUT
+                count : INT;
+            END_VAR
+                count := count + 1;
+            END_FUNCTION_BLOCK
+
+            FUNCTION_BLOCK TEST_Counter
+                Counter();
+                ASSERT_TRUE(Counter.count > 0);
+                ASSERT_EQ(Counter.count, 1);
+            END_FUNCTION_BLOCK
+        "#;
+
+        let ast = parse(source).unwrap();
+        let typed = check(&ast).unwrap();
+        let test = typed
+            .units
+            .iter()
+            .find_map(|unit| match unit {
+                TypedPou::FunctionBlock(fb) => fb.test.as_ref(),
+                _ => None,
+            })
+            .expect("no test metadata");
+        assert_eq!(test.name, "TEST_Counter");
+        let messages: Vec<_> = test.assertions.iter().map(|a| a.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "ASSERT_TRUE(Counter.count > 0)",
+                "ASSERT_EQ(Counter.count, 1)"
+            ]
+        );
+        assert_eq!(test.assertions[1].value_type.as_deref(), Some("INT"));
+    }
 }
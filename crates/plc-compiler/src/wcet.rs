@@ -0,0 +1,372 @@
+//! Static worst-case execution estimate.
+//!
+//! Computes an upper bound on the fuel one `step` call consumes, using
+//! Wasmtime's fuel cost model: every operator costs one unit, except `nop`,
+//! `drop`, `return` and the structural operators `block`, `loop`, `else` and
+//! `end`, which are free. Time spent inside host functions is not metered.
+//!
+//! The analysis runs on the final IR, where every instruction becomes exactly
+//! one Wasm operator:
+//!
+//! - `IF` costs the condition plus the more expensive branch.
+//! - Loops cost `bound + 1` times their body, the extra pass covering the
+//!   final exit test. Bounds come from constant `FOR` limits and
+//!   `{bound N}` pragmas ([`Instruction::LoopBound`]).
+//! - Calls to user POUs add the callee's bound.
+//!
+//! Loops without a bound and recursive calls make the estimate unbounded;
+//! each one is reported with its source location.
+
+use crate::ir::{Instruction, IrFunction, Module};
+use plc_common::debug_map::SourceLocation;
+use plc_common::wcet::{FuelEstimate, Unbounded};
+use std::collections::HashMap;
+
+/// Estimate the fuel of one `step` call.
+pub fn analyze(module: &Module) -> FuelEstimate {
+    let mut analyzer = Analyzer {
+        functions: module
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), f))
+            .collect(),
+        costs: HashMap::new(),
+        active: Vec::new(),
+        unbounded: Vec::new(),
+    };
+    let step_fuel = match module.functions.iter().find(|f| f.is_step) {
+        Some(step) => analyzer.function(&step.name),
+        None => Some(0),
+    };
+    FuelEstimate {
+        step_fuel,
+        unbounded: analyzer.unbounded,
+    }
+}
+
+struct Analyzer<'a> {
+    functions: HashMap<&'a str, &'a IrFunction>,
+    /// Bound of every function analysed so far.
+    costs: HashMap<&'a str, Option<u64>>,
+    /// Call stack, for detecting recursion.
+    active: Vec<&'a str>,
+    unbounded: Vec<Unbounded>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn function(&mut self, name: &str) -> Option<u64> {
+        let Some(&func) = self.functions.get(name) else {
+            // Not part of this module; only the call itself is metered.
+            return Some(0);
+        };
+        if let Some(&cost) = self.costs.get(name) {
+            return cost;
+        }
+        if self.active.contains(&func.name.as_str()) {
+            let caller = self.functions[self.active.last().unwrap()];
+            self.report(
+                caller,
+                caller.line,
+                format!("recursive call to {}", func.pou),
+            );
+            return None;
+        }
+
+        self.active.push(&func.name);
+        let mut pos = 0;
+        let mut line = func.line;
+        let cost = self.sequence(func, &mut pos, &mut line);
+        self.active.pop();
+        self.costs.insert(&func.name, cost);
+        cost
+    }
+
+    /// Cost of the instructions from `pos` up to the `Else` or `End` that
+    /// closes the enclosing block, which is left unconsumed.
+    ///
+    /// Keeps walking past unbounded constructs so that all of them are
+    /// reported.
+    fn sequence(&mut self, func: &'a IrFunction, pos: &mut usize, line: &mut u32) -> Option<u64> {
+        let mut total = Some(0u64);
+        let mut bound = None;
+        while let Some(instr) = func.body.get(*pos) {
+            let cost = match instr {
+                Instruction::End | Instruction::Else => break,
+                Instruction::SourceLine(l) => {
+                    *line = *l;
+                    *pos += 1;
+                    continue;
+                }
+                Instruction::LoopBound(n) => {
+                    bound = Some(*n);
+                    *pos += 1;
+                    continue;
+                }
+                Instruction::Block => {
+                    *pos += 1;
+                    let body = self.sequence(func, pos, line);
+                    *pos += 1;
+                    body
+                }
+                Instruction::Loop => {
+                    let loop_line = *line;
+                    *pos += 1;
+                    let body = self.sequence(func, pos, line);
+                    *pos += 1;
+                    match bound {
+                        Some(n) => body.and_then(|c| c.checked_mul(n.checked_add(1)?)),
+                        None => {
+                            self.report(func, loop_line, "loop has no iteration bound".into());
+                            None
+                        }
+                    }
+                }
+                Instruction::If => {
+                    *pos += 1;
+                    let then_cost = self.sequence(func, pos, line);
+                    let else_cost = if func.body.get(*pos) == Some(&Instruction::Else) {
+                        *pos += 1;
+                        self.sequence(func, pos, line)
+                    } else {
+                        Some(0)
+                    };
+                    *pos += 1;
+                    then_cost
+                        .zip(else_cost)
+                        .and_then(|(t, e)| t.max(e).checked_add(1))
+                }
+                Instruction::CallUser(name) => {
+                    *pos += 1;
+                    self.function(name).and_then(|c| c.checked_add(1))
+                }
+                Instruction::Nop | Instruction::Drop | Instruction::Return => {
+                    *pos += 1;
+                    Some(0)
+                }
+                _ => {
+                    *pos += 1;
+                    Some(1)
+                }
+            };
+            bound = None;
+            total = total.zip(cost).and_then(|(a, b)| a.checked_add(b));
+        }
+        total
+    }
+
+    fn report(&mut self, func: &IrFunction, line: u32, reason: String) {
+        self.unbounded.push(Unbounded {
+            location: SourceLocation {
+                file: func.file.clone(),
+                line,
+                pou_kind: func.pou_kind,
+                pou: func.pou.clone(),
+            },
+            reason,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{parse, parse_il};
+    use crate::{ir, typechecker};
+
+    fn estimate(source: &str) -> FuelEstimate {
+        let typed = typechecker::check(&parse(source).unwrap()).unwrap();
+        analyze(&ir::lower(&typed).unwrap())
+    }
+
+    #[test]
+    fn test_straight_line_and_branches() {
+        let base = estimate(
+            r#"
+            PROGRAM Main
+            VAR x : DINT; END_VAR
+                x := x + 1;
+            END_PROGRAM
+        "#,
+        );
+        // i32.const 0, i32.const 0, i32.load, i32.const 1, i32.add, i32.store
+        assert_eq!(base.step_fuel, Some(6));
+
+        let branch = estimate(
+            r#"
+            PROGRAM Main
+            VAR x : DINT; END_VAR
+                IF x > 10 THEN
+                    x := 0;
+                ELSE
+                    x := x + 1;
+                END_IF;
+            END_PROGRAM
+        "#,
+        );
+        // Condition (4) + if (1) + the longer branch (6).
+        assert_eq!(branch.step_fuel, Some(11));
+        assert!(branch.unbounded.is_empty());
+    }
+
+    #[test]
+    fn test_loop_bounds() {
+        let for_loop = |limit: &str| {
+            estimate(&format!(
+                r#"
+                PROGRAM Main
+                VAR i : DINT; x : DINT; END_VAR
+                    FOR i := 1 TO {limit} DO
+                        x := x + i;
+                    END_FOR;
+                END_PROGRAM
+            "#
+            ))
+            .step_fuel
+            .unwrap()
+        };
+        // Ten more iterations cost ten more passes over the loop body.
+        let ten = for_loop("10");
+        let twenty = for_loop("20");
+        assert_eq!((twenty - ten) % 10, 0);
+        let body = (twenty - ten) / 10;
+        assert!(ten > 11 * body);
+
+        let unbounded = estimate(
+            r#"
+            PROGRAM Main
+            VAR x : DINT; END_VAR
+                WHILE x < 100 DO
+                    x := x + 1;
+                END_WHILE;
+            END_PROGRAM
+        "#,
+        );
+        assert_eq!(unbounded.step_fuel, None);
+        assert_eq!(unbounded.unbounded.len(), 1);
+        assert_eq!(unbounded.unbounded[0].location.line, 4);
+
+        let pragma = estimate(
+            r#"
+            PROGRAM Main
+            VAR x : DINT; END_VAR
+                {bound 100}
+                WHILE x < 100 DO
+                    x := x + 1;
+                END_WHILE;
+            END_PROGRAM
+        "#,
+        );
+        assert!(pragma.step_fuel.is_some());
+    }
+
+    #[test]
+    fn test_loop_variable_assigned_in_body() {
+        let result = estimate(
+            r#"
+            PROGRAM Main
+            VAR i : DINT; END_VAR
+                FOR i := 1 TO 10 DO
+                    i := i - 1;
+                END_FOR;
+            END_PROGRAM
+        "#,
+        );
+        assert_eq!(result.step_fuel, None);
+    }
+
+    #[test]
+    fn test_calls_are_expanded() {
+        let result = estimate(
+            r#"
+            FUNCTION Twice : DINT
+            VAR_INPUT v : DINT; END_VAR
+                Twice := v * 2;
+            END_FUNCTION
+
+            PROGRAM Main
+            VAR x : DINT; END_VAR
+                x := Twice(x);
+                x := Twice(x);
+            END_PROGRAM
+        "#,
+        );
+        let single = estimate(
+            r#"
+            FUNCTION Twice : DINT
+            VAR_INPUT v : DINT; END_VAR
+                Twice := v * 2;
+            END_FUNCTION
+
+            PROGRAM Main
+            VAR x : DINT; END_VAR
+                x := Twice(x);
+            END_PROGRAM
+        "#,
+        );
+        let per_call = result.step_fuel.unwrap() - single.step_fuel.unwrap();
+        // The callee's body is part of every call.
+        assert!(per_call > 6, "{per_call}");
+    }
+
+    #[test]
+    fn test_recursion_is_unbounded() {
+        let result = estimate(
+            r#"
+            FUNCTION Fact : DINT
+            VAR_INPUT n : DINT; END_VAR
+                IF n <= 1 THEN
+                    Fact := 1;
+                ELSE
+                    Fact := n * Fact(n - 1);
+                END_IF;
+            END_FUNCTION
+
+            PROGRAM Main
+            VAR x : DINT; END_VAR
+                x := Fact(5);
+            END_PROGRAM
+        "#,
+        );
+        assert_eq!(result.step_fuel, None);
+        assert_eq!(result.unbounded[0].reason, "recursive call to Fact");
+    }
+
+    #[test]
+    fn test_il_forward_jumps_are_bounded() {
+        let forward = parse_il(
+            r#"
+            PROGRAM Main
+            VAR x : INT; END_VAR
+                LD x
+                GT 10
+                JMPC skip
+                LD x
+                ADD 1
+                ST x
+            skip:
+                RET
+            END_PROGRAM
+        "#,
+        )
+        .unwrap();
+        let typed = typechecker::check(&forward).unwrap();
+        assert!(analyze(&ir::lower(&typed).unwrap()).step_fuel.is_some());
+
+        let backward = parse_il(
+            r#"
+            PROGRAM Main
+            VAR x : INT; END_VAR
+            again:
+                LD x
+                ADD 1
+                ST x
+                LT 10
+                JMPC again
+            END_PROGRAM
+        "#,
+        )
+        .unwrap();
+        let typed = typechecker::check(&backward).unwrap();
+        assert_eq!(analyze(&ir::lower(&typed).unwrap()).step_fuel, None);
+    }
+}
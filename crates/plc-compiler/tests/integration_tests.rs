@@ -1,8 +1,14 @@
 //! Integration tests for the IEC 61131-3 compiler.
 //!
 //! These tests verify the complete compilation pipeline from ST source to Wasm.
+//! Every compiled program is also run on both logic engines, Wasmtime and
+//! the interpreter, which must agree cycle by cycle.
 
-use plc_compiler::compile;
+use plc_compiler::{compile, compile_il, compile_project, plcopen, Language, SourceFile};
+use plc_runtime::io_image::ProcessData;
+use plc_runtime::wasm_host::{LogicEngine, WasmtimeConfig, WasmtimeHost};
+use plc_runtime::wasm_interp::InterpreterHost;
+use std::time::Duration;
 
 /// Test compiling a simple blink program.
 #[test]
@@ -36,6 +42,7 @@ fn test_compile_blink_program() {
         &[0x01, 0x00, 0x00, 0x00],
         "Invalid Wasm version"
     );
+    assert_engines_agree(&wasm);
 }
 
 /// Test compiling a program with arithmetic expressions.
@@ -62,6 +69,7 @@ fn test_compile_arithmetic() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling a program with boolean logic.
@@ -86,6 +94,7 @@ fn test_compile_boolean_logic() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling a program with FOR loop.
@@ -105,6 +114,7 @@ fn test_compile_for_loop() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling a program with WHILE loop.
@@ -124,6 +134,7 @@ fn test_compile_while_loop() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling a program with nested IF statements.
@@ -151,6 +162,7 @@ fn test_compile_nested_if() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling a FUNCTION.
@@ -168,6 +180,22 @@ fn test_compile_function() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+
+    // A module of functions alone has no `step` to run, so compare the
+    // engines on a program that calls it
+    let program = format!(
+        r#"{source}
+        PROGRAM Main
+        VAR
+            total : INT;
+        END_VAR
+            total := Add(total, 3);
+        END_PROGRAM
+    "#
+    );
+    let result = compile(&program);
+    assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling a program with comparison operators.
@@ -196,6 +224,7 @@ fn test_compile_comparisons() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling with different integer types.
@@ -218,6 +247,7 @@ fn test_compile_integer_types() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test compiling with REAL types.
@@ -236,6 +266,7 @@ fn test_compile_real_types() {
 
     let result = compile(source);
     assert!(result.is_ok(), "Compile failed: {:?}", result.err());
+    assert_engines_agree(&result.unwrap());
 }
 
 /// Test that generated Wasm can be validated by wasmtime.
@@ -268,4 +299,839 @@ fn test_wasm_validates() {
             }
         }
     }
+    assert_engines_agree(&wasm);
+}
+
+fn assert_valid_wasm(wasm: &[u8]) {
+    wasmparser::Validator::new()
+        .validate_all(wasm)
+        .unwrap_or_else(|e| panic!("Wasm validation failed: {}", e));
+}
+
+/// Cycles each module runs for in [`assert_engines_agree`].
+const DIFFERENTIAL_CYCLES: u32 = 20;
+
+/// Run a module on the Wasmtime and interpreter engines with the same
+/// inputs and check that both produce the same outputs, memory and SFC
+/// steps, or trap in the same cycle.
+fn assert_engines_agree(wasm: &[u8]) {
+    assert_engines_agree_with(wasm, WasmtimeConfig::default());
+}
+
+/// [`assert_engines_agree`] with a host configuration.
+fn assert_engines_agree_with(wasm: &[u8], config: WasmtimeConfig) {
+    let cycle_time = Duration::from_millis(1);
+    let mut jit = WasmtimeHost::with_config(cycle_time, config.clone()).unwrap();
+    let mut interp = InterpreterHost::with_config(cycle_time, config.clone()).unwrap();
+    jit.load_module(wasm).expect("Wasmtime refuses module");
+    interp
+        .load_module(wasm)
+        .expect("interpreter refuses module");
+    jit.init().expect("Wasmtime init failed");
+    interp.init().expect("interpreter init failed");
+
+    let mut inputs = ProcessData::new(&config.process_image);
+    for cycle in 0..DIFFERENTIAL_CYCLES {
+        for (i, word) in inputs.digital_inputs_mut().iter_mut().enumerate() {
+            *word = (cycle ^ i as u32).wrapping_mul(0x9E37_79B9);
+        }
+        for (i, value) in inputs.analog_inputs_mut().iter_mut().enumerate() {
+            *value = (cycle as i16).wrapping_mul(977).wrapping_add(i as i16);
+        }
+        match (jit.step(&inputs).cloned(), interp.step(&inputs).cloned()) {
+            (Ok(expected), Ok(actual)) => {
+                assert_eq!(actual, expected, "outputs differ in cycle {cycle}");
+            }
+            // Engines need not agree on memory after a trap.
+            (Err(_), Err(_)) => return,
+            (expected, actual) => {
+                panic!("cycle {cycle}: Wasmtime {expected:?}, interpreter {actual:?}")
+            }
+        }
+        assert_eq!(
+            interp.active_steps(),
+            jit.active_steps(),
+            "active steps differ in cycle {cycle}"
+        );
+    }
+    assert!(
+        interp.memory_snapshot() == jit.memory_snapshot(),
+        "memory differs after {DIFFERENTIAL_CYCLES} cycles"
+    );
+}
+
+/// Test compiling an Instruction List program with jumps.
+#[test]
+fn test_compile_il_program() {
+    let source = r#"
+        PROGRAM Counter
+        VAR
+            count : INT;
+            limit_hit : BOOL;
+        END_VAR
+            LD count
+            ADD 1
+            ST count
+            GE 100
+            JMPCN done
+            LD 0
+            ST count
+            LD TRUE
+            S limit_hit
+        done:
+            RET
+        END_PROGRAM
+    "#;
+
+    let wasm = compile_il(source).expect("Compile failed");
+    assert_valid_wasm(&wasm);
+    assert_engines_agree(&wasm);
+}
+
+/// Test that ST and IL POUs in one project can call each other.
+#[test]
+fn test_compile_mixed_st_il_project() {
+    let il = r#"
+        FUNCTION Scale : INT
+        VAR_INPUT
+            raw : INT;
+            gain : INT;
+        END_VAR
+            LD raw
+            MUL gain
+            ST Scale
+        END_FUNCTION
+
+        FUNCTION_BLOCK Latch
+        VAR_INPUT
+            set : BOOL;
+            reset : BOOL;
+        END_VAR
+        VAR
+            q : BOOL;
+            changes : INT;
+        END_VAR
+            LD set
+            S q
+            LD reset
+            R q
+            LD changes
+            ADD 1
+            Clamp 0, 10
+            ST changes
+        END_FUNCTION_BLOCK
+    "#;
+    let st = r#"
+        FUNCTION Clamp : INT
+        VAR_INPUT
+            value : INT;
+            lo : INT;
+            hi : INT;
+        END_VAR
+            Clamp := value;
+            IF value < lo THEN
+                Clamp := lo;
+            ELSIF value > hi THEN
+                Clamp := hi;
+            END_IF;
+        END_FUNCTION
+
+        PROGRAM Main
+        VAR
+            level : INT;
+            alarm : BOOL;
+        END_VAR
+            level := Scale(level, 2);
+            Latch(reset := FALSE, set := level > 50);
+        END_PROGRAM
+    "#;
+
+    let sources = [
+        SourceFile::new("logic.il", Language::InstructionList, il),
+        SourceFile::new("main.st", Language::StructuredText, st),
+    ];
+    let wasm = compile_project(&sources).expect("Compile failed");
+    assert_valid_wasm(&wasm);
+    assert_engines_agree(&wasm);
+}
+
+/// Test that a POU defined in two files is rejected.
+#[test]
+fn test_compile_project_rejects_duplicate_pou() {
+    let sources = [
+        SourceFile::new(
+            "a.st",
+            Language::StructuredText,
+            "FUNCTION F : INT F := 1; END_FUNCTION",
+        ),
+        SourceFile::new(
+            "b.il",
+            Language::InstructionList,
+            "FUNCTION F : INT LD 2 ST F END_FUNCTION",
+        ),
+    ];
+    let err = compile_project(&sources).unwrap_err().to_string();
+    assert!(err.contains("a.st") && err.contains("b.il"), "{}", err);
+}
+
+/// Test compiling a textual SFC with timed actions and step flags.
+#[test]
+fn test_compile_sfc_program() {
+    let source = r#"
+        PROGRAM Tank
+        VAR
+            start : BOOL;
+            full : BOOL;
+            valve : BOOL;
+            alarm : BOOL;
+            fills : INT;
+        END_VAR
+            INITIAL_STEP Idle:
+            END_STEP
+
+            STEP Fill:
+                OpenValve(N);
+                Count(P);
+                alarm(D, T#30s);
+            END_STEP
+
+            STEP Drain:
+                Lamp(S);
+            END_STEP
+
+            TRANSITION FROM Idle TO Fill := start;
+            END_TRANSITION
+
+            TRANSITION (PRIORITY := 1) FROM Fill TO Drain := full OR Fill.T >= T#1m;
+            END_TRANSITION
+
+            TRANSITION FROM Drain TO Idle := NOT full;
+            END_TRANSITION
+
+            ACTION OpenValve:
+                valve := Fill.X;
+            END_ACTION
+
+            ACTION Count:
+                fills := fills + 1;
+            END_ACTION
+
+            ACTION Lamp:
+            END_ACTION
+        END_PROGRAM
+    "#;
+
+    let wasm = compile(source).expect("Compile failed");
+    assert_valid_wasm(&wasm);
+    assert_engines_agree(&wasm);
+
+    let steps = plc_common::wasm_meta::custom_section(&wasm, plc_common::sfc::SECTION_NAME)
+        .map(plc_common::sfc::decode_steps)
+        .expect("missing step table");
+    let names: Vec<_> = steps.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["Tank.Idle", "Tank.Fill", "Tank.Drain"]);
+}
+
+/// Test compiling a PLCopen XML project with LD and FBD POUs alongside an
+/// ST file, and that its export compiles again.
+#[test]
+fn test_compile_plcopen_project() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://www.plcopen.org/xml/tc6_0201" xmlns:xhtml="http://www.w3.org/1999/xhtml">
+  <contentHeader name="Conveyor"/>
+  <types>
+    <dataTypes/>
+    <pous>
+      <pou name="InBand" pouType="function">
+        <interface>
+          <returnType><BOOL/></returnType>
+          <inputVars>
+            <variable name="value"><type><INT/></type></variable>
+            <variable name="limit"><type><INT/></type></variable>
+          </inputVars>
+        </interface>
+        <body>
+          <FBD>
+            <inVariable localId="1"><position x="0" y="0"/><connectionPointOut/><expression>value</expression></inVariable>
+            <inVariable localId="2"><position x="0" y="20"/><connectionPointOut/><expression>limit</expression></inVariable>
+            <block localId="3" typeName="LE"><position x="40" y="0"/>
+              <inputVariables>
+                <variable formalParameter="IN1"><connectionPointIn><connection refLocalId="1"/></connectionPointIn></variable>
+                <variable formalParameter="IN2"><connectionPointIn><connection refLocalId="2"/></connectionPointIn></variable>
+              </inputVariables>
+              <outputVariables><variable formalParameter="OUT"><connectionPointOut/></variable></outputVariables>
+            </block>
+            <outVariable localId="4"><position x="80" y="0"/>
+              <connectionPointIn><connection refLocalId="3" formalParameter="OUT"/></connectionPointIn>
+              <expression>InBand</expression></outVariable>
+          </FBD>
+        </body>
+      </pou>
+      <pou name="Belt" pouType="program">
+        <interface>
+          <localVars>
+            <variable name="start"><type><BOOL/></type></variable>
+            <variable name="stop"><type><BOOL/></type></variable>
+            <variable name="motor"><type><BOOL/></type></variable>
+            <variable name="count"><type><INT/></type></variable>
+          </localVars>
+          <externalVars><variable name="load"><type><INT/></type></variable></externalVars>
+        </interface>
+        <body>
+          <LD>
+            <leftPowerRail localId="1"><position x="0" y="0"/><connectionPointOut/></leftPowerRail>
+            <contact localId="2"><position x="20" y="10"/>
+              <connectionPointIn><connection refLocalId="1"/></connectionPointIn><variable>start</variable></contact>
+            <contact localId="3"><position x="20" y="30"/>
+              <connectionPointIn><connection refLocalId="1"/></connectionPointIn><variable>motor</variable></contact>
+            <contact localId="4" negated="true"><position x="60" y="10"/>
+              <connectionPointIn><connection refLocalId="2"/><connection refLocalId="3"/></connectionPointIn>
+              <variable>stop</variable></contact>
+            <contact localId="5"><position x="80" y="10"/>
+              <connectionPointIn><connection refLocalId="4"/></connectionPointIn>
+              <variable>InBand(load, Limit(load))</variable></contact>
+            <coil localId="6"><position x="120" y="10"/>
+              <connectionPointIn><connection refLocalId="5"/></connectionPointIn><variable>motor</variable></coil>
+            <contact localId="7" edge="rising"><position x="20" y="60"/>
+              <connectionPointIn><connection refLocalId="1"/></connectionPointIn><variable>motor</variable></contact>
+            <coil localId="8" storage="set"><position x="120" y="60"/>
+              <connectionPointIn><connection refLocalId="7"/></connectionPointIn><variable>stop</variable></coil>
+          </LD>
+        </body>
+      </pou>
+    </pous>
+  </types>
+  <instances>
+    <configurations>
+      <configuration name="Config0">
+        <globalVars><variable name="load"><type><INT/></type></variable></globalVars>
+      </configuration>
+    </configurations>
+  </instances>
+</project>"#;
+    let st = r#"
+        FUNCTION Limit : INT
+        VAR_INPUT
+            value : INT;
+        END_VAR
+            Limit := 100;
+        END_FUNCTION
+    "#;
+
+    let sources = [
+        SourceFile::new("project.xml", Language::PlcOpenXml, xml),
+        SourceFile::new("main.st", Language::StructuredText, st),
+    ];
+    let wasm = compile_project(&sources).expect("Compile failed");
+    assert_valid_wasm(&wasm);
+    assert_engines_agree(&wasm);
+
+    let project = plcopen::import(xml).expect("Import failed");
+    let exported = plcopen::export(&project).expect("Export failed");
+    let sources = [
+        SourceFile::new("exported.xml", Language::PlcOpenXml, exported),
+        SourceFile::new("main.st", Language::StructuredText, st),
+    ];
+    let wasm = compile_project(&sources).expect("Exported project does not compile");
+    assert_valid_wasm(&wasm);
+    assert_engines_agree(&wasm);
+}
+
+/// Test that the debug line map resolves a trap to its source line.
+#[test]
+fn test_debug_map_resolves_trap() {
+    use plc_common::{debug_map, wasm_meta};
+    use wasmtime::{Engine, Instance, Linker, Module, Store, WasmBacktrace};
+
+    let conveyor = "\
+FUNCTION_BLOCK Conveyor
+VAR_INPUT
+    speed : DINT;
+END_VAR
+VAR
+    period : DINT;
+END_VAR
+    period := 0;
+    period := 1000 / speed;
+END_FUNCTION_BLOCK
+";
+    let main = "\
+PROGRAM Main
+VAR
+    speed : DINT;
+END_VAR
+    Conveyor(speed := speed);
+END_PROGRAM
+";
+    let sources = [
+        SourceFile::new("conveyor.st", Language::StructuredText, conveyor),
+        SourceFile::new("main.st", Language::StructuredText, main),
+    ];
+    let wasm = compile_project(&sources).expect("Compile failed");
+    assert_valid_wasm(&wasm);
+    assert_engines_agree(&wasm);
+    let payload = wasm_meta::custom_section(&wasm, debug_map::SECTION_NAME)
+        .expect("debug line section missing");
+    let map = debug_map::decode_lines(payload);
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm).unwrap();
+    let mut linker = Linker::new(&engine);
+    linker
+        .func_wrap("plc", "read_di", |_: i32| 0)
+        .unwrap()
+        .func_wrap("plc", "write_do", |_: i32, _: i32| {})
+        .unwrap()
+        .func_wrap("plc", "read_ai", |_: i32| 0)
+        .unwrap()
+        .func_wrap("plc", "write_ao", |_: i32, _: i32| {})
+        .unwrap()
+        .func_wrap("plc", "get_cycle_time", || 10)
+        .unwrap();
+    let mut store = Store::new(&engine, ());
+    let instance: Instance = linker.instantiate(&mut store, &module).unwrap();
+    let step = instance
+        .get_typed_func::<(), ()>(&mut store, "step")
+        .unwrap();
+    let err = step
+        .call(&mut store, ())
+        .expect_err("division by zero must trap");
+
+    let trace = err.downcast_ref::<WasmBacktrace>().expect("no backtrace");
+    let location = trace
+        .frames()
+        .iter()
+        .find_map(|frame| map.lookup(frame.module_offset()? as u32))
+        .expect("trap not resolved");
+    assert_eq!(location.to_string(), "conveyor.st:9 in FB Conveyor");
+
+    let caller = trace.frames()[1].module_offset().unwrap() as u32;
+    assert_eq!(
+        map.lookup(caller).unwrap().to_string(),
+        "main.st:5 in PROGRAM Main"
+    );
+}
+
+/// Test that the symbol table section describes the program's variables.
+#[test]
+fn test_symbol_table_section() {
+    use plc_common::{symbols, wasm_meta};
+
+    let source = r#"
+        PROGRAM Main
+        VAR RETAIN
+            total : DINT;
+        END_VAR
+        VAR_OUTPUT
+            speed : INT;
+        END_VAR
+            total := total + speed;
+        END_PROGRAM
+    "#;
+    let wasm = compile(source).expect("Compile failed");
+    let payload =
+        wasm_meta::custom_section(&wasm, symbols::SECTION_NAME).expect("symbol section missing");
+    let table = symbols::decode_symbols(payload).expect("unsupported version");
+    let total = table.iter().find(|s| s.name == "Main.total").unwrap();
+    assert!(total.retain);
+    assert_eq!(total.data_type, "DINT");
+    let speed = table.iter().find(|s| s.name == "Main.speed").unwrap();
+    assert_eq!(speed.direction, symbols::Direction::Output);
+    assert_eq!(speed.size, 2);
+    assert_engines_agree(&wasm);
+}
+
+/// Test that the embedded fuel estimate bounds the fuel Wasmtime measures.
+#[test]
+fn test_fuel_estimate_bounds_execution() {
+    use plc_common::{wasm_meta, wcet};
+    use wasmtime::{Config, Engine, Linker, Module, Store};
+
+    let source = r#"
+        FUNCTION Scale : DINT
+        VAR_INPUT
+            raw : DINT;
+        END_VAR
+            IF raw > 100 THEN
+                Scale := raw / 2;
+            ELSE
+                Scale := raw * 3;
+            END_IF;
+        END_FUNCTION
+
+        PROGRAM Main
+        VAR
+            i : DINT;
+            acc : DINT;
+            level : DINT;
+        END_VAR
+            FOR i := 0 TO 9 DO
+                acc := acc + Scale(i * level);
+            END_FOR;
+            {bound 20}
+            WHILE level < 200 DO
+                level := level + 10;
+            END_WHILE;
+        END_PROGRAM
+    "#;
+    let wasm = compile(source).expect("Compile failed");
+    let payload = wasm_meta::custom_section(&wasm, wcet::SECTION_NAME).expect("no estimate");
+    let estimate = wcet::decode_estimate(payload).unwrap();
+    let bound = estimate.step_fuel.expect("program should be bounded");
+    assert_engines_agree(&wasm);
+
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config).unwrap();
+    let module = Module::new(&engine, &wasm).unwrap();
+    let mut linker = Linker::new(&engine);
+    linker
+        .func_wrap("plc", "read_di", |_: i32| 0)
+        .unwrap()
+        .func_wrap("plc", "write_do", |_: i32, _: i32| {})
+        .unwrap()
+        .func_wrap("plc", "read_ai", |_: i32| 0)
+        .unwrap()
+        .func_wrap("plc", "write_ao", |_: i32, _: i32| {})
+        .unwrap()
+        .func_wrap("plc", "get_cycle_time", || 10)
+        .unwrap();
+    let mut store = Store::new(&engine, ());
+    store.set_fuel(u64::MAX).unwrap();
+    let instance = linker.instantiate(&mut store, &module).unwrap();
+    let step = instance
+        .get_typed_func::<(), ()>(&mut store, "step")
+        .unwrap();
+
+    // The first cycle runs the WHILE loop to completion.
+    let mut worst = 0;
+    for _ in 0..5 {
+        let before = store.get_fuel().unwrap();
+        step.call(&mut store, ()).unwrap();
+        worst = worst.max(before - store.get_fuel().unwrap());
+    }
+    assert!(worst <= bound, "measured {worst} > estimate {bound}");
+    assert!(
+        bound < worst * 2,
+        "estimate {bound} is too loose for {worst}"
+    );
+}
+
+/// Test that compiled modules declare the process-image ABI they target.
+#[test]
+fn test_abi_section() {
+    use plc_common::abi::{self, ProcessImageLayout};
+    use plc_common::wasm_meta;
+
+    let wasm = compile("PROGRAM Main VAR x : INT; END_VAR x := 1; END_PROGRAM").unwrap();
+    let payload = wasm_meta::custom_section(&wasm, abi::SECTION_NAME).expect("no ABI section");
+    let layout = abi::decode_layout(payload).unwrap();
+    assert_eq!(layout, ProcessImageLayout::CURRENT);
+    assert_engines_agree(&wasm);
+}
+
+/// Test that a larger process image moves variables past it.
+#[test]
+fn test_configured_process_image() {
+    use plc_common::abi::{self, ProcessImageLayout};
+    use plc_common::config::ProcessImageConfig;
+    use plc_common::symbols;
+    use plc_common::wasm_meta;
+    use plc_compiler::Compiler;
+
+    let layout = ProcessImageLayout::with_dimensions(256, 128, 64, 32);
+    let compiler = Compiler {
+        process_image: layout,
+        ..Compiler::default()
+    };
+    let source = SourceFile::new(
+        "main.st",
+        Language::StructuredText,
+        "PROGRAM Main VAR x : INT; END_VAR x := 1; END_PROGRAM",
+    );
+    let wasm = compiler.compile_sources(&[source]).unwrap();
+
+    let payload = wasm_meta::custom_section(&wasm, abi::SECTION_NAME).unwrap();
+    assert_eq!(abi::decode_layout(payload).unwrap(), layout);
+    let payload = wasm_meta::custom_section(&wasm, symbols::SECTION_NAME).unwrap();
+    let symbols = symbols::decode_symbols(payload).unwrap();
+    assert!(symbols
+        .iter()
+        .all(|symbol| symbol.offset >= layout.user_data_offset));
+
+    let config = WasmtimeConfig {
+        process_image: ProcessImageConfig {
+            digital_inputs: 256,
+            digital_outputs: 128,
+            analog_inputs: 64,
+            analog_outputs: 32,
+        },
+        ..WasmtimeConfig::default()
+    };
+    assert_engines_agree_with(&wasm, config);
+}
+
+/// Test that every host function is callable from ST and imported with the
+/// signature the runtime registers.
+#[test]
+fn test_host_functions() {
+    use plc_common::host_api::{self, WasmValType};
+    use std::sync::{Arc, Mutex};
+    use wasmtime::{Engine, ExternType, Linker, Module, Store, ValType};
+
+    let source = r#"
+        PROGRAM Main
+        VAR
+            first : BOOL;
+            cycles : LINT;
+            dt : DINT;
+            level : INT;
+            raw : UDINT;
+            temp : REAL;
+        END_VAR
+            first := is_first_cycle();
+            cycles := get_cycle_count();
+            dt := get_cycle_time();
+            level := read_ai(0) + read_di(1);
+            write_do(0, first);
+            write_ao(1, level);
+            log_message(0, 0);
+            raw := read_dword(0) + read_word(8) + read_byte(10);
+            temp := read_real(12);
+            write_dword(40, raw);
+            write_word(44, raw);
+            write_byte(46, raw);
+            write_real(48, temp);
+            write_bit(4, 1, read_bit(0, 1));
+        END_PROGRAM
+    "#;
+    let wasm = compile(source).expect("Compile failed");
+    assert_engines_agree(&wasm);
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm).unwrap();
+    let wasm_type = |ty: WasmValType| match ty {
+        WasmValType::I32 => ValType::I32,
+        WasmValType::I64 => ValType::I64,
+        WasmValType::F32 => ValType::F32,
+    };
+    let mut imported = 0;
+    for import in module.imports() {
+        assert_eq!(import.module(), host_api::MODULE);
+        let host = host_api::find(import.name()).expect("unknown import");
+        let ExternType::Func(ty) = import.ty() else {
+            panic!("{} is not a function", import.name());
+        };
+        let params: Vec<_> = ty.params().collect();
+        let results: Vec<_> = ty.results().collect();
+        assert_eq!(params.len(), host.params.len(), "{host}");
+        assert_eq!(results.len(), usize::from(host.result.is_some()), "{host}");
+        for (actual, expected) in params.iter().zip(host.wasm_params()) {
+            assert!(ValType::eq(actual, &wasm_type(expected)), "{host}");
+        }
+        for (actual, expected) in results.iter().zip(host.wasm_results()) {
+            assert!(ValType::eq(actual, &wasm_type(expected)), "{host}");
+        }
+        imported += 1;
+    }
+    assert_eq!(imported, host_api::HOST_FUNCTIONS.len());
+
+    // Modules only import the host functions they call.
+    let minimal = compile("PROGRAM Main VAR x : INT; END_VAR x := 1; END_PROGRAM").unwrap();
+    assert_eq!(Module::new(&engine, &minimal).unwrap().imports().len(), 0);
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let mut linker = Linker::new(&engine);
+    let log = calls.clone();
+    linker
+        .func_wrap("plc", "read_di", |_: i32| 1)
+        .unwrap()
+        .func_wrap("plc", "write_do", move |bit: i32, value: i32| {
+            log.lock().unwrap().push(("write_do", bit, value))
+        })
+        .unwrap()
+        .func_wrap("plc", "read_ai", |_: i32| 41)
+        .unwrap()
+        .func_wrap("plc", "write_ao", {
+            let log = calls.clone();
+            move |channel: i32, value: i32| log.lock().unwrap().push(("write_ao", channel, value))
+        })
+        .unwrap()
+        .func_wrap("plc", "get_cycle_time", || 10)
+        .unwrap()
+        .func_wrap("plc", "get_cycle_count", || 7i64)
+        .unwrap()
+        .func_wrap("plc", "is_first_cycle", || 1)
+        .unwrap()
+        .func_wrap("plc", "log_message", |_: i32, _: i32| {})
+        .unwrap()
+        .define_unknown_imports_as_default_values(&module)
+        .unwrap();
+    let mut store = Store::new(&engine, ());
+    let instance = linker.instantiate(&mut store, &module).unwrap();
+    let step = instance
+        .get_typed_func::<(), ()>(&mut store, "step")
+        .unwrap();
+    step.call(&mut store, ()).unwrap();
+    assert_eq!(
+        *calls.lock().unwrap(),
+        [("write_do", 0, 1), ("write_ao", 1, 42)]
+    );
+}
+
+/// Test that each test function block gets its own module, and that tests are
+/// left out of production builds.
+#[test]
+fn test_compile_tests() {
+    use plc_common::unit_test;
+    use plc_common::wasm_meta;
+    use plc_compiler::Compiler;
+
+    let st = r#"
+        FUNCTION_BLOCK Counter
+        VAR_OUTPUT
+            count : INT;
+        END_VAR
+            count := count + 1;
+        END_FUNCTION_BLOCK
+
+        FUNCTION_BLOCK TEST_Counts
+            Counter();
+            ASSERT_EQ(Counter.count, 1);
+        END_FUNCTION_BLOCK
+
+        PROGRAM Main
+            Counter();
+        END_PROGRAM
+    "#;
+    let sources = [SourceFile::new("counter.st", Language::StructuredText, st)];
+
+    let tests = Compiler::new().compile_tests(&sources).unwrap();
+    assert_eq!(tests.len(), 1);
+    assert_eq!(tests[0].name, "TEST_Counts");
+    assert_valid_wasm(&tests[0].wasm);
+    let payload = wasm_meta::custom_section(&tests[0].wasm, unit_test::SECTION_NAME)
+        .expect("no test section");
+    let test = unit_test::decode_test(payload).unwrap();
+    assert_eq!(test.file, "counter.st");
+    assert_eq!(test.assertions.len(), 1);
+    assert_eq!(test.assertions[0].line, 11);
+
+    let wasm = compile_project(&sources).unwrap();
+    assert_valid_wasm(&wasm);
+    assert_engines_agree(&wasm);
+    assert!(wasm_meta::custom_section(&wasm, unit_test::SECTION_NAME).is_none());
+    assert!(!String::from_utf8_lossy(&wasm).contains("TEST_Counts"));
+}
+
+/// Test that a cached rebuild only checks and lowers the POUs that changed,
+/// and produces the same module as a full build.
+#[test]
+fn test_incremental_compile() {
+    use plc_compiler::cache::BuildCache;
+    use plc_compiler::Compiler;
+
+    let lib = |param: &str| {
+        format!(
+            r#"
+        FUNCTION_BLOCK Counter
+        VAR_OUTPUT
+            count : INT;
+        END_VAR
+            count := count + 1;
+        END_FUNCTION_BLOCK
+
+        FUNCTION Scale : INT
+        VAR_INPUT
+            {param} : INT;
+        END_VAR
+            Scale := {param} * 2;
+        END_FUNCTION
+    "#
+        )
+    };
+    let main = |factor: i32| {
+        format!(
+            r#"
+        PROGRAM Main
+        VAR
+            y : INT;
+        END_VAR
+            Counter();
+            y := Scale(3) + {factor};
+        END_PROGRAM
+    "#
+        )
+    };
+    let project = |param: &str, factor: i32| {
+        [
+            SourceFile::new("lib.st", Language::StructuredText, lib(param)),
+            SourceFile::new("main.st", Language::StructuredText, main(factor)),
+        ]
+    };
+
+    let compiler = Compiler::new();
+    let mut cache = BuildCache::new();
+    let build = |cache: &mut BuildCache, sources: &[SourceFile]| {
+        let wasm = compiler.compile_sources_cached(sources, cache).unwrap();
+        assert_eq!(wasm, compile_project(sources).unwrap());
+        let stats = cache.stats();
+        assert_eq!(stats.checked, stats.lowered);
+        (stats.checked, stats.checked_reused)
+    };
+
+    assert_eq!(build(&mut cache, &project("x", 1)), (3, 0));
+    assert_eq!(build(&mut cache, &project("x", 1)), (0, 3));
+    // A body edit only rebuilds that POU.
+    assert_eq!(build(&mut cache, &project("x", 2)), (1, 2));
+    // Renaming a parameter changes Scale's interface, so its caller is
+    // checked again too.
+    assert_eq!(build(&mut cache, &project("v", 2)), (2, 1));
+
+    // With a directory, an unchanged project is not compiled at all, even
+    // with a fresh cache.
+    let dir = std::env::temp_dir().join(format!("plc-compile-cache-{}", std::process::id()));
+    let sources = project("x", 1);
+    let first = compiler
+        .compile_sources_cached(&sources, &mut BuildCache::open(&dir))
+        .unwrap();
+    let mut cache = BuildCache::open(&dir);
+    let second = compiler
+        .compile_sources_cached(&sources, &mut cache)
+        .unwrap();
+    assert!(cache.stats().module_reused);
+    assert_eq!(cache.stats().checked, 0);
+    assert_eq!(first, second);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Test that each program is exported as a task entry point, and the first
+/// one also as `step`.
+#[test]
+fn test_programs_exported_as_tasks() {
+    use wasmtime::{Engine, Module};
+
+    let source = r#"
+        PROGRAM Fast
+        VAR x : INT; END_VAR
+            x := x + 1;
+        END_PROGRAM
+
+        PROGRAM Slow
+        VAR y : INT; END_VAR
+            y := y + 1;
+        END_PROGRAM
+    "#;
+    let wasm = compile(source).expect("Compile failed");
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm).unwrap();
+    let exports: Vec<&str> = module.exports().map(|export| export.name()).collect();
+    assert_eq!(exports, ["step", "Fast", "Slow", "memory"]);
+
+    let config = WasmtimeConfig {
+        entry_points: vec!["Fast".into(), "Slow".into()],
+        ..WasmtimeConfig::default()
+    };
+    assert_engines_agree_with(&wasm, config);
 }
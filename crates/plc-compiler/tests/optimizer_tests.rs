@@ -0,0 +1,268 @@
+//! Equivalence tests for the IR optimiser.
+//!
+//! Every program is compiled at each optimisation level and run for a number
+//! of cycles against the same simulated inputs; the variables in linear
+//! memory must match the unoptimised build after every cycle.
+
+use plc_compiler::frontend::{parse, parse_il, CompilationUnit};
+use plc_compiler::ir::{self, TempSlot};
+use plc_compiler::optimize::{optimize, OptLevel};
+use plc_compiler::{codegen, typechecker};
+use wasmtime::{Caller, Engine, Instance, Linker, Module, Store};
+
+const CYCLES: u32 = 40;
+
+/// Compile to Wasm at the given level, also returning the `VAR_TEMP` slots
+/// (whose memory contents are allowed to differ) and the instruction count.
+fn build(unit: &CompilationUnit, level: OptLevel) -> (Vec<u8>, Vec<TempSlot>, usize) {
+    let typed = typechecker::check(unit).expect("type check failed");
+    let mut module = ir::lower(&typed).expect("lowering failed");
+    let temps = module
+        .functions
+        .iter()
+        .flat_map(|f| f.temp_slots.iter().copied())
+        .collect();
+    optimize(&mut module, level);
+    let size = module.functions.iter().map(|f| f.body.len()).sum();
+    let wasm = codegen::emit(&module).expect("emission failed");
+    wasmparser::validate(&wasm).expect("optimised module is invalid");
+    (wasm, temps, size)
+}
+
+/// Simulated digital input: a pseudo-random pattern per cycle and bit.
+fn input(cycle: u32, channel: i32) -> i32 {
+    let x = cycle
+        .wrapping_mul(2_654_435_761)
+        .wrapping_add(channel as u32 * 40_503);
+    ((x >> 7) % 1000) as i32
+}
+
+/// Run the module and return a snapshot of memory after every cycle.
+fn run(wasm: &[u8], temps: &[TempSlot]) -> Vec<Vec<u8>> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, wasm).unwrap();
+    let mut linker = Linker::new(&engine);
+    linker
+        .func_wrap("plc", "read_di", |c: Caller<'_, u32>, bit: i32| {
+            input(*c.data(), bit) & 1
+        })
+        .unwrap()
+        .func_wrap("plc", "write_do", |_: i32, _: i32| {})
+        .unwrap()
+        .func_wrap("plc", "read_ai", |c: Caller<'_, u32>, channel: i32| {
+            input(*c.data(), channel) - 500
+        })
+        .unwrap()
+        .func_wrap("plc", "write_ao", |_: i32, _: i32| {})
+        .unwrap()
+        .func_wrap("plc", "get_cycle_time", || 10)
+        .unwrap();
+    let mut store = Store::new(&engine, 0u32);
+    let instance: Instance = linker.instantiate(&mut store, &module).unwrap();
+    let step = instance
+        .get_typed_func::<(), ()>(&mut store, "step")
+        .unwrap();
+    let memory = instance.get_memory(&mut store, "memory").unwrap();
+
+    let mut snapshots = Vec::new();
+    for cycle in 0..CYCLES {
+        *store.data_mut() = cycle;
+        step.call(&mut store, ()).unwrap();
+        let mut image = memory.data(&store).to_vec();
+        for slot in temps {
+            let range = slot.offset as usize..(slot.offset + slot.size) as usize;
+            image[range].fill(0);
+        }
+        snapshots.push(image);
+    }
+    snapshots
+}
+
+/// Assert that all levels behave like `O0`; returns the instruction counts
+/// at `O0` and `O2`.
+fn assert_equivalent(unit: &CompilationUnit) -> (usize, usize) {
+    let (reference, temps, baseline) = build(unit, OptLevel::O0);
+    let expected = run(&reference, &temps);
+    let mut optimised = baseline;
+    for level in [OptLevel::O1, OptLevel::O2] {
+        let (wasm, _, size) = build(unit, level);
+        let actual = run(&wasm, &temps);
+        for (cycle, (a, e)) in actual.iter().zip(&expected).enumerate() {
+            assert!(a == e, "memory differs at {level} after cycle {cycle}");
+        }
+        optimised = size;
+    }
+    (baseline, optimised)
+}
+
+fn assert_st_equivalent(source: &str) -> (usize, usize) {
+    assert_equivalent(&parse(source).expect("parse failed"))
+}
+
+#[test]
+fn test_arithmetic_and_constants() {
+    let (before, after) = assert_st_equivalent(
+        r#"
+        PROGRAM Main
+        VAR
+            a : INT;
+            b : DINT;
+            c : LINT;
+            r : REAL;
+            l : LREAL;
+            t : TIME;
+            flag : BOOL;
+        END_VAR
+            a := a + (2 + 3) * 4 - 20;
+            a := a * 1 + 0 + read_ai(1) / 7;
+            b := b + 100000 * 30000 + a MOD 3;
+            c := c + 3000000000;
+            r := r + 1.5 * 2.0;
+            l := l * 0.5 + 2.0 / 4.0;
+            t := t + T#10ms;
+            flag := NOT flag;
+            flag := flag XOR (a > 10) AND (3 < 4);
+        END_PROGRAM
+    "#,
+    );
+    assert!(after < before, "{after} >= {before}");
+}
+
+#[test]
+fn test_arrays() {
+    let (before, after) = assert_st_equivalent(
+        r#"
+        PROGRAM Main
+        VAR
+            values : ARRAY[0..7] OF DINT;
+            small : ARRAY[0..3] OF INT;
+            i : INT;
+            sum : DINT;
+        END_VAR
+            i := (i + 1) MOD 8;
+            values[i] := values[i] + i;
+            values[2] := values[1 + 1] + 1;
+            small[i MOD 4] := small[3] + 7;
+            sum := values[0] + values[2] + values[i];
+        END_PROGRAM
+    "#,
+    );
+    assert!(after < before, "{after} >= {before}");
+}
+
+#[test]
+fn test_control_flow() {
+    assert_st_equivalent(
+        r#"
+        PROGRAM Main
+        VAR
+            mode : INT;
+            count : DINT;
+            acc : DINT;
+            k : DINT;
+            done : BOOL;
+        END_VAR
+            mode := read_ai(0) MOD 4;
+            IF TRUE THEN
+                count := count + 1;
+            ELSE
+                count := 0;
+            END_IF;
+            IF FALSE THEN
+                count := -1;
+            END_IF;
+            CASE mode OF
+                0: acc := acc + 1;
+                1, 2: acc := acc - 1;
+            ELSE
+                acc := 0;
+            END_CASE;
+            FOR k := 1 TO 5 DO
+                acc := acc + k;
+            END_FOR;
+            WHILE acc > 100 DO
+                acc := acc - 50;
+            END_WHILE;
+            REPEAT
+                acc := acc + 3;
+            UNTIL acc > 10
+            END_REPEAT;
+            done := acc > 50 OR read_di(3) = 1;
+        END_PROGRAM
+    "#,
+    );
+}
+
+#[test]
+fn test_temps_and_functions() {
+    let (before, after) = assert_st_equivalent(
+        r#"
+        FUNCTION Scale : DINT
+        VAR_INPUT
+            raw : DINT;
+        END_VAR
+        VAR_TEMP
+            doubled : DINT;
+            offset : DINT;
+        END_VAR
+            doubled := raw * 2;
+            offset := 100;
+            Scale := doubled + offset;
+        END_FUNCTION
+
+        PROGRAM Main
+        VAR
+            x : DINT;
+            y : INT;
+            stale : DINT;
+        END_VAR
+        VAR_TEMP
+            t : INT;
+            u : DINT;
+            maybe : DINT;
+            i : DINT;
+        END_VAR
+            t := 32767;
+            t := t + 1;
+            y := t;
+            u := read_ai(2);
+            IF u > 0 THEN
+                maybe := u;
+            END_IF;
+            stale := maybe;
+            FOR i := 0 TO 3 DO
+                u := u + i;
+            END_FOR;
+            x := Scale(u) + Scale(x MOD 1000);
+        END_PROGRAM
+    "#,
+    );
+    assert!(after < before, "{after} >= {before}");
+}
+
+#[test]
+fn test_instruction_list() {
+    let unit = parse_il(
+        r#"
+        PROGRAM Counter
+        VAR
+            count : INT;
+            limit_hit : BOOL;
+        END_VAR
+            LD count
+            ADD 1
+            ST count
+            GE 10
+            JMPCN done
+            LD 0
+            ST count
+            LD TRUE
+            S limit_hit
+        done:
+            RET
+        END_PROGRAM
+    "#,
+    )
+    .expect("parse failed");
+    assert_equivalent(&unit);
+}
@@ -3,9 +3,11 @@
 //! Provides runtime health monitoring, metrics export, and diagnostic
 //! information for external monitoring systems (e.g., Prometheus).
 
+use crate::redundancy::Role;
 use plc_common::metrics::CycleMetrics;
 use plc_common::state::RuntimeState;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use plc_runtime::scheduler::Task;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -59,6 +61,57 @@ pub struct DiagnosticsSnapshot {
     pub fieldbus_connected: bool,
     /// Whether Wasm module is loaded.
     pub wasm_loaded: bool,
+    /// Number of forced I/O points and variables.
+    pub forces_active: u64,
+    /// Redundancy status, if redundancy is enabled.
+    pub redundancy: Option<RedundancyStatus>,
+    /// Metrics of each task.
+    pub tasks: Vec<TaskStatus>,
+}
+
+/// Metrics of one scheduler task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskStatus {
+    /// Task name.
+    pub name: String,
+    /// Task period.
+    pub period: Duration,
+    /// Times the task ran.
+    pub cycles: u64,
+    /// Number of overruns.
+    pub overruns: u64,
+    /// Average execution time.
+    pub avg_time: Option<Duration>,
+    /// Maximum execution time.
+    pub max_time: Option<Duration>,
+}
+
+impl TaskStatus {
+    /// Take the current metrics of a task.
+    pub fn of(task: &Task) -> Self {
+        let metrics = task.metrics();
+        Self {
+            name: task.name().to_string(),
+            period: task.period(),
+            cycles: task.cycle_count(),
+            overruns: metrics.overrun_count(),
+            avg_time: metrics.mean(),
+            max_time: metrics.max(),
+        }
+    }
+}
+
+/// State of the redundant pair as seen by this node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedundancyStatus {
+    /// Role of this node.
+    pub role: Role,
+    /// Whether the peer is connected.
+    pub peer_connected: bool,
+    /// Whether the standby holds the current state of the primary.
+    pub in_sync: bool,
+    /// Number of times this node took over from its peer.
+    pub takeovers: u64,
 }
 
 /// Shared diagnostics state updated by the runtime.
@@ -74,6 +127,16 @@ pub struct DiagnosticsState {
     wasm_loaded: AtomicBool,
     /// Last cycle time in nanoseconds.
     last_cycle_ns: AtomicU64,
+    /// Number of active forces.
+    forces_active: AtomicU64,
+    /// Redundancy role code, 0 if redundancy is disabled.
+    redundancy_role: AtomicU8,
+    /// Whether the redundant peer is connected.
+    peer_connected: AtomicBool,
+    /// Whether the standby is in sync with the primary.
+    peer_in_sync: AtomicBool,
+    /// Number of takeovers by this node.
+    takeovers: AtomicU64,
     /// Daemon start time.
     start_time: Instant,
 }
@@ -93,6 +156,11 @@ impl DiagnosticsState {
             fieldbus_connected: AtomicBool::new(false),
             wasm_loaded: AtomicBool::new(false),
             last_cycle_ns: AtomicU64::new(0),
+            forces_active: AtomicU64::new(0),
+            redundancy_role: AtomicU8::new(0),
+            peer_connected: AtomicBool::new(false),
+            peer_in_sync: AtomicBool::new(false),
+            takeovers: AtomicU64::new(0),
             start_time: Instant::now(),
         }
     }
@@ -117,6 +185,38 @@ impl DiagnosticsState {
         self.wasm_loaded.store(loaded, Ordering::Relaxed);
     }
 
+    /// Set the number of active forces.
+    pub fn set_forces_active(&self, count: usize) {
+        self.forces_active.store(count as u64, Ordering::Relaxed);
+    }
+
+    /// Set the redundancy role and link status.
+    pub fn set_redundancy(&self, role: Role, peer_connected: bool, in_sync: bool) {
+        self.redundancy_role.store(role.code(), Ordering::Relaxed);
+        self.peer_connected.store(peer_connected, Ordering::Relaxed);
+        self.peer_in_sync.store(in_sync, Ordering::Relaxed);
+    }
+
+    /// Record a takeover from the redundant peer.
+    pub fn record_takeover(&self) {
+        self.takeovers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get the redundancy status, `None` if redundancy is disabled.
+    pub fn redundancy(&self) -> Option<RedundancyStatus> {
+        Some(RedundancyStatus {
+            role: Role::from_code(self.redundancy_role.load(Ordering::Relaxed))?,
+            peer_connected: self.peer_connected.load(Ordering::Relaxed),
+            in_sync: self.peer_in_sync.load(Ordering::Relaxed),
+            takeovers: self.takeovers.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Get the number of active forces.
+    pub fn forces_active(&self) -> u64 {
+        self.forces_active.load(Ordering::Relaxed)
+    }
+
     /// Get total cycle count.
     pub fn cycle_count(&self) -> u64 {
         self.cycle_count.load(Ordering::Relaxed)
@@ -176,13 +276,15 @@ impl DiagnosticsCollector {
                     0.0
                 };
 
-                if overrun_rate > 0.01 {
-                    // More than 1% overruns
+                if overrun_rate > 0.01 || self.state.forces_active() > 0 {
+                    // More than 1% overruns, or running with forced values
                     HealthStatus::Degraded
                 } else {
                     HealthStatus::Healthy
                 }
             }
+            // Logic is halted on purpose, outputs are held or safe
+            RuntimeState::Debug => HealthStatus::Degraded,
             RuntimeState::SafeStop => HealthStatus::ShuttingDown,
             RuntimeState::Fault => HealthStatus::Unhealthy,
         }
@@ -193,6 +295,7 @@ impl DiagnosticsCollector {
         &self,
         runtime_state: RuntimeState,
         metrics: &CycleMetrics,
+        tasks: &[Task],
     ) -> DiagnosticsSnapshot {
         DiagnosticsSnapshot {
             health: self.health_from_state(runtime_state),
@@ -205,6 +308,9 @@ impl DiagnosticsCollector {
             max_cycle_time: metrics.max(),
             fieldbus_connected: self.state.is_fieldbus_connected(),
             wasm_loaded: self.state.is_wasm_loaded(),
+            forces_active: self.state.forces_active(),
+            redundancy: self.state.redundancy(),
+            tasks: tasks.iter().map(TaskStatus::of).collect(),
         }
     }
 
@@ -305,6 +411,95 @@ pub fn format_prometheus_metrics(snapshot: &DiagnosticsSnapshot, target_cycle_ns
         if snapshot.wasm_loaded { 1 } else { 0 }
     ));
 
+    output.push_str("# HELP plc_forces_active Number of forced I/O points and variables\n");
+    output.push_str("# TYPE plc_forces_active gauge\n");
+    output.push_str(&format!("plc_forces_active {}\n", snapshot.forces_active));
+
+    if let Some(redundancy) = snapshot.redundancy {
+        output.push_str("# HELP plc_redundancy_role Role of this node in the redundant pair\n");
+        output.push_str("# TYPE plc_redundancy_role gauge\n");
+        output.push_str(&format!(
+            "plc_redundancy_role {{role=\"{}\"}} 1\n",
+            redundancy.role
+        ));
+
+        output.push_str("# HELP plc_redundancy_peer_connected Redundant peer connection status\n");
+        output.push_str("# TYPE plc_redundancy_peer_connected gauge\n");
+        output.push_str(&format!(
+            "plc_redundancy_peer_connected {}\n",
+            if redundancy.peer_connected { 1 } else { 0 }
+        ));
+
+        output.push_str("# HELP plc_redundancy_in_sync Standby holds the state of the primary\n");
+        output.push_str("# TYPE plc_redundancy_in_sync gauge\n");
+        output.push_str(&format!(
+            "plc_redundancy_in_sync {}\n",
+            if redundancy.in_sync { 1 } else { 0 }
+        ));
+
+        output
+            .push_str("# HELP plc_redundancy_takeovers_total Takeovers from the redundant peer\n");
+        output.push_str("# TYPE plc_redundancy_takeovers_total counter\n");
+        output.push_str(&format!(
+            "plc_redundancy_takeovers_total {}\n",
+            redundancy.takeovers
+        ));
+    }
+
+    if !snapshot.tasks.is_empty() {
+        output.push_str("# HELP plc_task_period_seconds Configured task period\n");
+        output.push_str("# TYPE plc_task_period_seconds gauge\n");
+        for task in &snapshot.tasks {
+            output.push_str(&format!(
+                "plc_task_period_seconds {{task=\"{}\"}} {:.9}\n",
+                task.name,
+                task.period.as_secs_f64()
+            ));
+        }
+
+        output.push_str("# HELP plc_task_cycles_total Times the task ran\n");
+        output.push_str("# TYPE plc_task_cycles_total counter\n");
+        for task in &snapshot.tasks {
+            output.push_str(&format!(
+                "plc_task_cycles_total {{task=\"{}\"}} {}\n",
+                task.name, task.cycles
+            ));
+        }
+
+        output.push_str("# HELP plc_task_overruns_total Task overruns\n");
+        output.push_str("# TYPE plc_task_overruns_total counter\n");
+        for task in &snapshot.tasks {
+            output.push_str(&format!(
+                "plc_task_overruns_total {{task=\"{}\"}} {}\n",
+                task.name, task.overruns
+            ));
+        }
+
+        output.push_str("# HELP plc_task_time_avg_seconds Average task execution time\n");
+        output.push_str("# TYPE plc_task_time_avg_seconds gauge\n");
+        for task in &snapshot.tasks {
+            if let Some(avg) = task.avg_time {
+                output.push_str(&format!(
+                    "plc_task_time_avg_seconds {{task=\"{}\"}} {:.9}\n",
+                    task.name,
+                    avg.as_secs_f64()
+                ));
+            }
+        }
+
+        output.push_str("# HELP plc_task_time_max_seconds Maximum task execution time\n");
+        output.push_str("# TYPE plc_task_time_max_seconds gauge\n");
+        for task in &snapshot.tasks {
+            if let Some(max) = task.max_time {
+                output.push_str(&format!(
+                    "plc_task_time_max_seconds {{task=\"{}\"}} {:.9}\n",
+                    task.name,
+                    max.as_secs_f64()
+                ));
+            }
+        }
+    }
+
     output
 }
 
@@ -381,6 +576,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_degraded_health_while_forced() {
+        let state = Arc::new(DiagnosticsState::new());
+        let collector = DiagnosticsCollector::new(Arc::clone(&state));
+
+        state.set_forces_active(1);
+        assert_eq!(
+            collector.health_from_state(RuntimeState::Run),
+            HealthStatus::Degraded
+        );
+        state.set_forces_active(0);
+        assert_eq!(
+            collector.health_from_state(RuntimeState::Run),
+            HealthStatus::Healthy
+        );
+    }
+
     #[test]
     fn test_prometheus_metrics_format() {
         let snapshot = DiagnosticsSnapshot {
@@ -394,6 +606,21 @@ mod tests {
             max_cycle_time: Some(Duration::from_micros(1200)),
             fieldbus_connected: true,
             wasm_loaded: true,
+            forces_active: 2,
+            redundancy: Some(RedundancyStatus {
+                role: Role::Standby,
+                peer_connected: true,
+                in_sync: true,
+                takeovers: 0,
+            }),
+            tasks: vec![TaskStatus {
+                name: "Motion".into(),
+                period: Duration::from_millis(1),
+                cycles: 900,
+                overruns: 3,
+                avg_time: Some(Duration::from_micros(200)),
+                max_time: Some(Duration::from_micros(1100)),
+            }],
         };
 
         let output = format_prometheus_metrics(&snapshot, 1_000_000);
@@ -403,5 +630,19 @@ mod tests {
         assert!(output.contains("plc_overruns_total 5"));
         assert!(output.contains("plc_fieldbus_connected 1"));
         assert!(output.contains("plc_wasm_loaded 1"));
+        assert!(output.contains("plc_forces_active 2"));
+        assert!(output.contains("plc_redundancy_role {role=\"standby\"} 1"));
+        assert!(output.contains("plc_redundancy_in_sync 1"));
+        assert!(output.contains("plc_task_cycles_total {task=\"Motion\"} 900"));
+        assert!(output.contains("plc_task_overruns_total {task=\"Motion\"} 3"));
+        assert!(output.contains("plc_task_time_max_seconds {task=\"Motion\"} 0.001100000"));
+        assert!(!format_prometheus_metrics(
+            &DiagnosticsSnapshot {
+                redundancy: None,
+                ..snapshot
+            },
+            1_000_000
+        )
+        .contains("plc_redundancy"));
     }
 }
@@ -4,24 +4,59 @@
 //! into a complete runtime with signal handling and diagnostics.
 
 mod diagnostics;
+mod module_source;
+mod postmortem;
+mod redundancy;
 mod signals;
+mod sync_link;
+mod test_runner;
+mod witness;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use plc_common::config::{FieldbusDriver as FieldbusDriverType, RuntimeConfig};
+use plc_common::abi::{self, ProcessImageLayout};
+use plc_common::config::{
+    FieldbusDriver as FieldbusDriverType, RuntimeConfig, TraceConfig, WasmEngine,
+};
+use plc_common::error::PlcError;
 use plc_common::state::RuntimeState;
-use plc_fieldbus::{FieldbusDriver, ModbusTcpConfig, ModbusTcpDriver, SimulatedDriver};
-use plc_runtime::scheduler::{Scheduler, SchedulerBuilder};
-use plc_runtime::wasm_host::{LogicEngine, NullEngine, WasmtimeHost};
-use plc_web_ui::{StateUpdater, WebUiConfig, WebUiServer};
+use plc_common::wasm_meta;
+use plc_common::wcet::{self, FuelEstimate};
+use plc_fieldbus::{
+    FieldbusDriver, FieldbusInputs, FieldbusOutputs, ModbusTcpConfig, ModbusTcpDriver,
+    SimulatedDriver,
+};
+use plc_runtime::debug::DebugCommand;
+use plc_runtime::fault_recorder::FaultReason;
+use plc_runtime::forcing::ForceTarget;
+use plc_runtime::io_image::ProcessData;
+use plc_runtime::online_change::MigrationPlan;
+use plc_runtime::precompiled;
+use plc_runtime::scheduler::{Scheduler, SchedulerBuilder, Task};
+use plc_runtime::signing::{self, SigningKey};
+use plc_runtime::symbols::VarValue;
+use plc_runtime::trace::{TraceHandle, TraceSpec, TraceState};
+use plc_runtime::wasm_host::{LogicEngine, NullEngine, WasmtimeConfig, WasmtimeHost};
+use plc_runtime::wasm_interp::InterpreterHost;
+use plc_web_ui::{
+    ControlReceiver, ControlRequest, ControlValue, DebugRequest, DebugSnapshot, ForceEntry,
+    RawMetrics, StateUpdater, TaskMetrics, TraceExport, TraceRequest, TraceSnapshot, VariableEntry,
+    WebUiConfig, WebUiServer,
+};
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{error, info, warn};
 
-use crate::diagnostics::{format_prometheus_metrics, DiagnosticsCollector, DiagnosticsState};
+use crate::diagnostics::{
+    format_prometheus_metrics, DiagnosticsCollector, DiagnosticsState, TaskStatus,
+};
+use crate::module_source::ModuleSource;
+use crate::postmortem::{Decoder, PostMortem, PostMortemWriter, RecentLogs};
+use crate::redundancy::Redundancy;
 use crate::signals::{wait_for_shutdown, SignalHandler};
+use crate::sync_link::Endpoint;
 
 /// PLC daemon command-line interface.
 #[derive(Parser, Debug)]
@@ -45,17 +80,38 @@ enum Commands {
     /// Run the PLC daemon with full runtime.
     Run(RunArgs),
 
-    /// Compile Structured Text to WebAssembly.
+    /// Compile Structured Text, Instruction List and/or PLCopen XML to WebAssembly.
     Compile(CompileArgs),
 
+    /// Format Structured Text source files in canonical style.
+    Fmt(FmtArgs),
+
+    /// Export a project as PLCopen TC6 XML.
+    Export(ExportArgs),
+
     /// Validate a WebAssembly module for PLC compatibility.
     Validate(ValidateArgs),
 
+    /// Sign a module or precompiled artifact for hosts with trusted keys.
+    Sign(SignArgs),
+
+    /// Generate a module signing key.
+    Keygen(KeygenArgs),
+
+    /// Run the TEST_ function blocks of a project.
+    Test(TestArgs),
+
     /// Run in simulation mode (simplified, no RT requirements).
     Simulate(SimulateArgs),
 
     /// Diagnose system real-time capabilities.
     Diagnose(DiagnoseArgs),
+
+    /// Inspect post-mortem files written on faults.
+    Postmortem(PostmortemArgs),
+
+    /// Lend the lease of a redundant pair to one node at a time.
+    Witness(WitnessArgs),
 }
 
 /// Arguments for the 'run' subcommand.
@@ -69,6 +125,12 @@ struct RunArgs {
     #[arg(long, short = 'w', value_name = "FILE")]
     wasm_module: Option<PathBuf>,
 
+    /// Compile these project sources instead of loading a Wasm module.
+    /// They are compiled again on every hot-reload, rebuilding only what
+    /// changed. Repeat for several files.
+    #[arg(long = "source", value_name = "FILE", conflicts_with = "wasm_module")]
+    sources: Vec<PathBuf>,
+
     /// Run in simulated fieldbus mode.
     #[arg(long, short = 's')]
     simulated: bool,
@@ -76,15 +138,25 @@ struct RunArgs {
     /// Maximum cycles to run (0 = infinite).
     #[arg(long, default_value = "0")]
     max_cycles: u64,
+
+    /// On hot-reload, only report how state would migrate to the new
+    /// module and keep running the current one.
+    #[arg(long)]
+    reload_dry_run: bool,
 }
 
 /// Arguments for the 'compile' subcommand.
 #[derive(Parser, Debug)]
 struct CompileArgs {
-    /// Input Structured Text file (.st).
+    /// Input source file (.st for Structured Text, .il for Instruction List,
+    /// .xml for a PLCopen project).
     #[arg(value_name = "INPUT")]
     input: PathBuf,
 
+    /// Additional source files compiled into the same module.
+    #[arg(value_name = "SOURCES")]
+    sources: Vec<PathBuf>,
+
     /// Output WebAssembly file (.wasm).
     #[arg(short = 'o', long, value_name = "OUTPUT")]
     output: Option<PathBuf>,
@@ -93,11 +165,72 @@ struct CompileArgs {
     #[arg(long)]
     wat: bool,
 
+    /// Optimisation level: 0 (none), 1 (folding, peephole, dead code) or
+    /// 2 (also VAR_TEMP promotion and dead-store elimination).
+    #[arg(short = 'O', long, value_name = "LEVEL", default_value = "1")]
+    opt_level: plc_compiler::optimize::OptLevel,
+
+    /// Build cache directory. An unchanged project is taken from the cache
+    /// instead of being compiled.
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Runtime configuration whose process image the module is built for
+    /// (TOML). Defaults to the built-in process image.
+    #[arg(long, short = 'c', value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Also write a precompiled artifact (.cwasm) with native code for the
+    /// engine settings of `--config`, so the runtime skips Cranelift.
+    #[arg(long, value_name = "FILE")]
+    precompile: Option<PathBuf>,
+
+    /// Target triple of the precompiled artifact (defaults to this machine).
+    #[arg(long, value_name = "TRIPLE", requires = "precompile")]
+    target: Option<String>,
+
+    /// Sign the module, and the precompiled artifact, with this key file
+    /// (written by 'keygen').
+    #[arg(long, value_name = "FILE", conflicts_with = "wat")]
+    sign_key: Option<PathBuf>,
+
     /// Enable verbose compiler output.
     #[arg(short, long)]
     verbose: bool,
 }
 
+/// Arguments for the 'export' subcommand.
+#[derive(Parser, Debug)]
+struct ExportArgs {
+    /// Project source files (.st, .il or PLCopen .xml).
+    #[arg(value_name = "SOURCES", required = true)]
+    sources: Vec<PathBuf>,
+
+    /// Output PLCopen XML file.
+    #[arg(short = 'o', long, value_name = "OUTPUT")]
+    output: PathBuf,
+
+    /// Project name (defaults to the output file name).
+    #[arg(long)]
+    name: Option<String>,
+}
+
+/// Arguments for the 'fmt' subcommand.
+#[derive(Parser, Debug)]
+struct FmtArgs {
+    /// Structured Text files to format (.st).
+    #[arg(value_name = "FILE", required = true)]
+    files: Vec<PathBuf>,
+
+    /// Check formatting only; exit non-zero if any file would change.
+    #[arg(long)]
+    check: bool,
+
+    /// Maximum line width before call arguments are wrapped.
+    #[arg(long, default_value = "100")]
+    max_width: usize,
+}
+
 /// Arguments for the 'validate' subcommand.
 #[derive(Parser, Debug)]
 struct ValidateArgs {
@@ -112,15 +245,97 @@ struct ValidateArgs {
     /// Show detailed module information.
     #[arg(short, long)]
     verbose: bool,
+
+    /// Runtime configuration to check the module against (TOML).
+    /// Defaults to the built-in limits.
+    #[arg(long, short = 'c', value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Assumed worst-case nanoseconds per fuel unit, used to compare the
+    /// module's fuel estimate with the cycle time.
+    #[arg(long, default_value = "10", value_name = "NS")]
+    ns_per_fuel: u64,
+
+    /// Previous version of the module (.wasm or .wat). Reports how its
+    /// state would carry over in an online change, without running either.
+    #[arg(long, value_name = "MODULE")]
+    previous: Option<PathBuf>,
 }
 
-/// Arguments for the 'simulate' subcommand.
+/// Arguments for the 'sign' subcommand.
 #[derive(Parser, Debug)]
-struct SimulateArgs {
-    /// WebAssembly module to run (.wasm or .wat).
+struct SignArgs {
+    /// Module (.wasm) or precompiled artifact (.cwasm) to sign.
     #[arg(value_name = "MODULE")]
     module: PathBuf,
 
+    /// Signing key file (written by 'keygen').
+    #[arg(long, short = 'k', value_name = "FILE")]
+    key: PathBuf,
+
+    /// Output file; the module is signed in place if omitted.
+    #[arg(short = 'o', long, value_name = "OUTPUT")]
+    output: Option<PathBuf>,
+}
+
+/// Arguments for the 'keygen' subcommand.
+#[derive(Parser, Debug)]
+struct KeygenArgs {
+    /// Secret key file to write. The public key goes to the same path with
+    /// a `.pub` extension.
+    #[arg(value_name = "FILE")]
+    output: PathBuf,
+
+    /// Overwrite an existing key file.
+    #[arg(long)]
+    force: bool,
+}
+
+/// Arguments for the 'test' subcommand.
+#[derive(Parser, Debug)]
+struct TestArgs {
+    /// Project source files (.st, .il or PLCopen .xml).
+    #[arg(value_name = "SOURCES", required = true)]
+    sources: Vec<PathBuf>,
+
+    /// Runtime configuration for the test hosts (TOML).
+    /// Defaults to the built-in limits.
+    #[arg(long, short = 'c', value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Scripted process inputs (TOML).
+    #[arg(long, value_name = "FILE")]
+    inputs: Option<PathBuf>,
+
+    /// Write a JUnit XML report.
+    #[arg(long, value_name = "FILE")]
+    junit: Option<PathBuf>,
+
+    /// Only run tests whose name contains this text (case-insensitive).
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Cycles after which a test that has not set DONE fails.
+    #[arg(long, default_value = "1000")]
+    max_cycles: u64,
+
+    /// Optimisation level, as for 'compile'.
+    #[arg(short = 'O', long, value_name = "LEVEL", default_value = "1")]
+    opt_level: plc_compiler::optimize::OptLevel,
+}
+
+/// Arguments for the 'simulate' subcommand.
+#[derive(Parser, Debug)]
+struct SimulateArgs {
+    /// WebAssembly module to run (.wasm or .wat). With --replay, the module
+    /// to replay instead of the recorded one.
+    #[arg(value_name = "MODULE", required_unless_present = "replay")]
+    module: Option<PathBuf>,
+
+    /// Replay a cycle recording directory and compare the outputs.
+    #[arg(long, value_name = "DIR")]
+    replay: Option<PathBuf>,
+
     /// Number of cycles to simulate (default: 100).
     #[arg(short = 'n', long, default_value = "100")]
     cycles: u64,
@@ -158,43 +373,149 @@ struct DiagnoseArgs {
     skip_timing_test: bool,
 }
 
+/// Arguments for the 'postmortem' subcommand.
+#[derive(Parser, Debug)]
+struct PostmortemArgs {
+    #[command(subcommand)]
+    command: PostmortemCommand,
+}
+
+/// What to do with post-mortem files.
+#[derive(Subcommand, Debug)]
+enum PostmortemCommand {
+    /// List the post-mortem files in a directory, oldest first.
+    List {
+        /// Directory the daemon writes post-mortem files to.
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+    },
+
+    /// Print a post-mortem file.
+    Show {
+        /// Post-mortem file (.json).
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Module that was running (.wasm or .wat), to decode the variables
+        /// it places in the process image.
+        #[arg(long, short = 'w', value_name = "MODULE")]
+        module: Option<PathBuf>,
+
+        /// Number of frames, counted back from the fault, whose I/O is printed.
+        #[arg(long, default_value = "1")]
+        frames: usize,
+    },
+
+    /// Compare two post-mortem files.
+    Diff {
+        /// Earlier post-mortem file.
+        #[arg(value_name = "A")]
+        a: PathBuf,
+
+        /// Later post-mortem file.
+        #[arg(value_name = "B")]
+        b: PathBuf,
+
+        /// Module to decode both files with (.wasm or .wat).
+        #[arg(long, short = 'w', value_name = "MODULE")]
+        module: Option<PathBuf>,
+    },
+
+    /// Export the frames of a post-mortem file with their decoded I/O.
+    Export {
+        /// Post-mortem file (.json).
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Output format: csv or json.
+        #[arg(long, default_value = "csv")]
+        format: ExportFormat,
+
+        /// Output file; standard output if omitted.
+        #[arg(short = 'o', long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+
+        /// Module that was running (.wasm or .wat).
+        #[arg(long, short = 'w', value_name = "MODULE")]
+        module: Option<PathBuf>,
+    },
+}
+
+/// Arguments for the 'witness' subcommand.
+#[derive(Parser, Debug)]
+struct WitnessArgs {
+    /// Configuration of the pair (TOML). The witness uses its
+    /// `redundancy.secret` and listens on `redundancy.witness`.
+    #[arg(long, short = 'c', value_name = "FILE")]
+    config: PathBuf,
+
+    /// Listen on this address instead: `host:port` or `unix:<path>`.
+    #[arg(long, value_name = "ADDRESS")]
+    listen: Option<String>,
+}
+
+/// Format of 'postmortem export'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     // Initialize logging
-    init_logging(&cli.log_level);
+    let recent_logs = init_logging(&cli.log_level);
 
     match cli.command {
-        Commands::Run(args) => cmd_run(args),
+        Commands::Run(args) => cmd_run(args, recent_logs),
         Commands::Compile(args) => cmd_compile(args),
+        Commands::Fmt(args) => cmd_fmt(args),
+        Commands::Export(args) => cmd_export(args),
         Commands::Validate(args) => cmd_validate(args),
+        Commands::Sign(args) => cmd_sign(args),
+        Commands::Keygen(args) => cmd_keygen(args),
+        Commands::Test(args) => cmd_test(args),
         Commands::Simulate(args) => cmd_simulate(args),
         Commands::Diagnose(args) => cmd_diagnose(args),
+        Commands::Postmortem(args) => cmd_postmortem(args),
+        Commands::Witness(args) => cmd_witness(args),
     }
 }
 
 /// Initialize logging with the specified log level.
-fn init_logging(level: &str) {
+///
+/// Returns the recent log lines, which go into post-mortem files.
+fn init_logging(level: &str) -> RecentLogs {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
     let filter = format!(
         "plc_daemon={},plc_runtime={},plc_fieldbus={},plc_common={},plc_compiler={}",
         level, level, level, level, level
     );
 
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    let recent_logs = RecentLogs::default();
+    tracing_subscriber::registry()
+        .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(&filter)),
         )
-        .with_target(true)
-        .with_thread_ids(true)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(true)
+                .with_thread_ids(true),
+        )
+        .with(recent_logs.clone())
         .init();
+    recent_logs
 }
 
 // =============================================================================
 // SUBCOMMAND: run
 // =============================================================================
 
-fn cmd_run(args: RunArgs) -> Result<()> {
+fn cmd_run(args: RunArgs, recent_logs: RecentLogs) -> Result<()> {
     info!(version = env!("CARGO_PKG_VERSION"), "Starting PLC daemon");
 
     // Load configuration
@@ -207,6 +528,9 @@ fn cmd_run(args: RunArgs) -> Result<()> {
     if args.simulated {
         config.fieldbus.driver = FieldbusDriverType::Simulated;
     }
+    if args.reload_dry_run {
+        config.wasm.reload_dry_run = true;
+    }
 
     info!(?config.cycle_time, ?config.fieldbus.driver, "Configuration loaded");
 
@@ -218,7 +542,16 @@ fn cmd_run(args: RunArgs) -> Result<()> {
     let diagnostics = DiagnosticsCollector::new(Arc::clone(&diag_state));
 
     // Run the daemon
-    run_daemon(&config, &signal_handler, &diagnostics, args.max_cycles)
+    let module_source = ModuleSource::new(&config, args.sources);
+    let postmortem = PostMortemWriter::new(&config, recent_logs);
+    run_daemon(
+        &config,
+        module_source,
+        &signal_handler,
+        &diagnostics,
+        args.max_cycles,
+        postmortem,
+    )
 }
 
 /// Load configuration from file or use defaults.
@@ -262,18 +595,55 @@ fn load_config(args: &RunArgs) -> Result<RuntimeConfig> {
 // =============================================================================
 
 fn cmd_compile(args: CompileArgs) -> Result<()> {
-    info!(input = ?args.input, "Compiling Structured Text");
+    info!(input = ?args.input, extra = args.sources.len(), "Compiling");
 
-    // Read source file
-    let source = std::fs::read_to_string(&args.input)
-        .with_context(|| format!("Failed to read source file: {:?}", args.input))?;
+    // Read source files; the language follows the file extension
+    let sources = std::iter::once(&args.input)
+        .chain(&args.sources)
+        .map(|path| plc_compiler::SourceFile::read(path))
+        .collect::<Result<Vec<_>>>()?;
 
     if args.verbose {
-        info!(lines = source.lines().count(), "Source loaded");
+        for file in &sources {
+            info!(
+                file = %file.name,
+                language = ?file.language,
+                lines = file.source.lines().count(),
+                "Source loaded"
+            );
+        }
     }
 
+    let config = match &args.config {
+        Some(path) => RuntimeConfig::from_file(path)
+            .with_context(|| format!("Failed to load config from {:?}", path))?,
+        None => RuntimeConfig::default(),
+    };
+
     // Compile to Wasm
-    let wasm_bytes = plc_compiler::compile(&source).with_context(|| "Compilation failed")?;
+    let compiler = plc_compiler::Compiler {
+        opt_level: args.opt_level,
+        process_image: ProcessImageLayout::for_config(&config.process_image),
+        ..Default::default()
+    };
+    let wasm_bytes = match &args.cache_dir {
+        Some(dir) => {
+            let mut cache = plc_compiler::cache::BuildCache::open(dir);
+            let wasm = compiler.compile_sources_cached(&sources, &mut cache);
+            if cache.stats().module_reused {
+                info!(cache_dir = ?dir, "Module unchanged, taken from build cache");
+            }
+            wasm
+        }
+        None => compiler.compile_sources(&sources),
+    }
+    .with_context(|| "Compilation failed")?;
+
+    let signing_key = args.sign_key.as_deref().map(read_signing_key).transpose()?;
+    let wasm_bytes = match &signing_key {
+        Some(key) => key.sign(&wasm_bytes)?,
+        None => wasm_bytes,
+    };
 
     // Determine output path
     let output_path = args.output.unwrap_or_else(|| {
@@ -293,13 +663,136 @@ fn cmd_compile(args: CompileArgs) -> Result<()> {
             .with_context(|| format!("Failed to write Wasm file: {:?}", output_path))?;
     }
 
+    let inputs: Vec<_> = sources.iter().map(|file| file.name.as_str()).collect();
     println!(
         "Compiled {} -> {} ({} bytes)",
-        args.input.display(),
+        inputs.join(", "),
         output_path.display(),
         wasm_bytes.len()
     );
 
+    if let Some(artifact_path) = &args.precompile {
+        let artifact = precompiled::precompile(
+            &wasm_bytes,
+            &WasmtimeConfig::from_runtime_config(&config),
+            args.target.as_deref(),
+        )?;
+        let artifact = match &signing_key {
+            Some(key) => key.sign(&artifact)?,
+            None => artifact,
+        };
+        std::fs::write(artifact_path, &artifact)
+            .with_context(|| format!("Failed to write artifact: {:?}", artifact_path))?;
+        println!(
+            "Precompiled for {} -> {} ({} bytes)",
+            args.target.as_deref().unwrap_or("this machine"),
+            artifact_path.display(),
+            artifact.len()
+        );
+    }
+
+    if let Some(key) = &signing_key {
+        println!("Signed with key {}", key.public_key_hex());
+    }
+
+    Ok(())
+}
+
+/// Read a key file written by 'keygen'.
+fn read_signing_key(path: &Path) -> Result<SigningKey> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read signing key: {:?}", path))?;
+    SigningKey::from_hex(&text).with_context(|| format!("Invalid signing key in {:?}", path))
+}
+
+// =============================================================================
+// SUBCOMMAND: fmt
+// =============================================================================
+
+fn cmd_fmt(args: FmtArgs) -> Result<()> {
+    let options = plc_compiler::formatter::FormatOptions {
+        max_width: args.max_width,
+        ..Default::default()
+    };
+
+    let mut unformatted = Vec::new();
+    for path in &args.files {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read source file: {:?}", path))?;
+        let formatted = plc_compiler::formatter::format_source(&source, &options)
+            .with_context(|| format!("Failed to format {:?}", path))?;
+
+        if formatted == source {
+            continue;
+        }
+
+        if args.check {
+            println!("Would reformat: {}", path.display());
+        } else {
+            std::fs::write(path, &formatted)
+                .with_context(|| format!("Failed to write formatted file: {:?}", path))?;
+            println!("Formatted {}", path.display());
+        }
+        unformatted.push(path);
+    }
+
+    if args.check && !unformatted.is_empty() {
+        anyhow::bail!(
+            "{} of {} file(s) are not formatted",
+            unformatted.len(),
+            args.files.len()
+        );
+    }
+
+    Ok(())
+}
+
+// =============================================================================
+// SUBCOMMAND: export
+// =============================================================================
+
+fn cmd_export(args: ExportArgs) -> Result<()> {
+    use plc_compiler::plcopen::{self, PlcOpenProject};
+    use plc_compiler::{Compiler, Language, SourceFile};
+
+    let sources = args
+        .sources
+        .iter()
+        .map(|path| SourceFile::read(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Only export projects that compile.
+    let compiler = Compiler::new();
+    let unit = compiler.parse_sources(&sources)?;
+    compiler
+        .compile_ast(&unit)
+        .with_context(|| "Compilation failed")?;
+
+    let name = args.name.clone().unwrap_or_else(|| {
+        args.output
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string())
+    });
+    let mut project = PlcOpenProject::from_unit(name, unit);
+    // Carry data types and tasks over from imported PLCopen files.
+    for file in sources
+        .iter()
+        .filter(|f| f.language == Language::PlcOpenXml)
+    {
+        let imported = plcopen::import(&file.source)?;
+        project.data_types.extend(imported.data_types);
+        project.tasks.extend(imported.tasks);
+    }
+
+    let xml = plcopen::export(&project)?;
+    std::fs::write(&args.output, xml)
+        .with_context(|| format!("Failed to write {:?}", args.output))?;
+    println!(
+        "Exported {} POU(s) -> {}",
+        project.unit.units.len(),
+        args.output.display()
+    );
     Ok(())
 }
 
@@ -314,23 +807,26 @@ fn build_validation_host(config: &RuntimeConfig) -> Result<WasmtimeHost> {
 fn cmd_validate(args: ValidateArgs) -> Result<()> {
     info!(module = ?args.module, "Validating WebAssembly module");
 
-    // Read module
-    let module_bytes = std::fs::read(&args.module)
-        .with_context(|| format!("Failed to read module: {:?}", args.module))?;
+    let wasm_bytes = read_module_file(&args.module)?;
+    // Metadata of precompiled artifacts lives in their embedded Wasm module
+    let wasm = precompiled::wasm_module(&wasm_bytes)?;
 
-    // Check if it's WAT and convert if needed
-    let wasm_bytes = if args.module.extension().map_or(false, |e| e == "wat") {
-        wat::parse_bytes(&module_bytes)
-            .with_context(|| "Failed to parse WAT")?
-            .into_owned()
-    } else {
-        module_bytes
+    // Use the deployment config, or runtime defaults, so validation matches
+    // daemon limits
+    let config = match &args.config {
+        Some(path) => RuntimeConfig::from_file(path)
+            .with_context(|| format!("Failed to load config from {:?}", path))?,
+        None => RuntimeConfig::default(),
     };
-
-    // Use runtime defaults so validation matches daemon limits
-    let config = RuntimeConfig::default();
     let mut host = build_validation_host(&config)?;
 
+    // Check the process-image ABI first so layout mismatches get a clear error
+    if let Some(payload) = wasm_meta::custom_section(wasm, abi::SECTION_NAME) {
+        abi::decode_layout(payload)
+            .and_then(|layout| layout.check_compatible(host.host_abi()))
+            .with_context(|| "Module was built for an incompatible process image")?;
+    }
+
     // load_module validates the module and checks for required exports (step, memory)
     host.load_module(&wasm_bytes)
         .with_context(|| "Module failed validation - check for required exports: step, memory")?;
@@ -383,6 +879,32 @@ fn cmd_validate(args: ValidateArgs) -> Result<()> {
         println!("  Has init: {}", init_ok);
         println!("  Ready: {}", host.is_ready());
         println!("  Supports hot-reload: {}", host.supports_hot_reload());
+        println!("  Precompiled: {}", host.module_precompiled());
+        match signing::signer(&wasm_bytes)? {
+            Some(key) => println!("  Signed by: {}", key),
+            None => println!("  Signed by: unsigned"),
+        }
+        match host.module_abi() {
+            Some(layout) => println!("  Process-image ABI: {}", layout.version),
+            None => println!(
+                "  Process-image ABI: not declared (assuming {})",
+                host.host_abi().version
+            ),
+        }
+    }
+
+    // Compare the compiler's fuel estimate against the configured budget
+    match wasm_meta::custom_section(wasm, wcet::SECTION_NAME).and_then(wcet::decode_estimate) {
+        Some(estimate) => check_fuel_estimate(&estimate, &config, args.ns_per_fuel)?,
+        None => {
+            if args.verbose {
+                println!("  Fuel estimate: not available");
+            }
+        }
+    }
+
+    if let Some(previous) = &args.previous {
+        report_online_change(previous, &wasm_bytes, &config)?;
     }
 
     println!("Module is valid and compatible with Virtual PLC runtime");
@@ -390,32 +912,245 @@ fn cmd_validate(args: ValidateArgs) -> Result<()> {
     Ok(())
 }
 
+// =============================================================================
+// SUBCOMMANDS: sign, keygen
+// =============================================================================
+
+fn cmd_sign(args: SignArgs) -> Result<()> {
+    let key = read_signing_key(&args.key)?;
+    let module = std::fs::read(&args.module)
+        .with_context(|| format!("Failed to read module: {:?}", args.module))?;
+    let signed = key
+        .sign(&module)
+        .with_context(|| format!("Failed to sign {:?}", args.module))?;
+
+    let output = args.output.as_ref().unwrap_or(&args.module);
+    std::fs::write(output, &signed)
+        .with_context(|| format!("Failed to write signed module: {:?}", output))?;
+    println!(
+        "Signed {} -> {} with key {}",
+        args.module.display(),
+        output.display(),
+        key.public_key_hex()
+    );
+    Ok(())
+}
+
+fn cmd_keygen(args: KeygenArgs) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let key = SigningKey::generate()?;
+    let public_path = args.output.with_extension("pub");
+
+    // The secret key is only readable by its owner; the mode only applies
+    // to a new file, so an overwritten one is restricted before the write
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).mode(0o600);
+    if args.force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    options
+        .open(&args.output)
+        .and_then(|mut file| {
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+            writeln!(file, "{}", key.to_hex())
+        })
+        .with_context(|| format!("Failed to write signing key: {:?}", args.output))?;
+    std::fs::write(&public_path, format!("{}\n", key.public_key_hex()))
+        .with_context(|| format!("Failed to write public key: {:?}", public_path))?;
+
+    println!("Signing key: {}", args.output.display());
+    println!("Public key:  {}", public_path.display());
+    println!();
+    println!("Trust modules signed with it by adding to the runtime configuration:");
+    println!("  [wasm]");
+    println!("  trusted_keys = [\"{}\"]", key.public_key_hex());
+    Ok(())
+}
+
+/// Read a module file, converting WAT to binary if needed.
+fn read_module_file(path: &Path) -> Result<Vec<u8>> {
+    let module_bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read module: {:?}", path))?;
+
+    // Check if it's WAT and convert if needed
+    if path.extension().map_or(false, |e| e == "wat") {
+        Ok(wat::parse_bytes(&module_bytes)
+            .with_context(|| "Failed to parse WAT")?
+            .into_owned())
+    } else {
+        Ok(module_bytes)
+    }
+}
+
+/// Print how the state of the module at `previous` would carry over to
+/// `wasm_bytes` in an online change.
+fn report_online_change(previous: &Path, wasm_bytes: &[u8], config: &RuntimeConfig) -> Result<()> {
+    let mut host = build_validation_host(config)?;
+    host.load_module(&read_module_file(previous)?)
+        .with_context(|| format!("Previous module {:?} failed to load", previous))?;
+    host.init()
+        .with_context(|| format!("Previous module {:?} failed to initialize", previous))?;
+    let plan = host
+        .plan_reload(wasm_bytes)
+        .with_context(|| "Online change would be refused")?;
+
+    println!("Online change from {}: {}", previous.display(), plan);
+    for change in plan.converted() {
+        println!("  converted: {change}");
+    }
+    for name in plan.added() {
+        println!("  added: {name}");
+    }
+    for name in plan.removed() {
+        println!("  removed: {name}");
+    }
+    for change in plan.incompatible() {
+        println!("  incompatible: {change}");
+    }
+    Ok(())
+}
+
+/// Check a module's static fuel estimate against the fuel budget and the
+/// cycle time, assuming `ns_per_fuel` nanoseconds per fuel unit.
+///
+/// Unbounded constructs are reported as warnings, since the runtime still
+/// enforces the budget at run time.
+fn check_fuel_estimate(
+    estimate: &FuelEstimate,
+    config: &RuntimeConfig,
+    ns_per_fuel: u64,
+) -> Result<()> {
+    let Some(fuel) = estimate.step_fuel else {
+        println!("Fuel estimate: unbounded");
+        for entry in &estimate.unbounded {
+            println!("  warning: {}: {}", entry.location, entry.reason);
+        }
+        return Ok(());
+    };
+
+    let time = Duration::from_nanos(fuel.saturating_mul(ns_per_fuel));
+    println!("Fuel estimate: {} units per cycle (~{:?})", fuel, time);
+
+    if config.wasm.use_fuel && fuel > config.wasm.fuel_per_cycle {
+        anyhow::bail!(
+            "Worst-case fuel {} exceeds the configured fuel_per_cycle {}",
+            fuel,
+            config.wasm.fuel_per_cycle
+        );
+    }
+    if time > config.cycle_time {
+        anyhow::bail!(
+            "Worst-case execution time {:?} exceeds the cycle time {:?}",
+            time,
+            config.cycle_time
+        );
+    }
+    Ok(())
+}
+
+// =============================================================================
+// SUBCOMMAND: test
+// =============================================================================
+
+fn cmd_test(args: TestArgs) -> Result<()> {
+    let sources = args
+        .sources
+        .iter()
+        .map(|path| plc_compiler::SourceFile::read(path))
+        .collect::<Result<Vec<_>>>()?;
+    let config = match &args.config {
+        Some(path) => RuntimeConfig::from_file(path)
+            .with_context(|| format!("Failed to load config from {:?}", path))?,
+        None => RuntimeConfig::default(),
+    };
+    let script = match &args.inputs {
+        Some(path) => test_runner::InputScript::from_file(path)?,
+        None => test_runner::InputScript::default(),
+    };
+
+    let compiler = plc_compiler::Compiler {
+        opt_level: args.opt_level,
+        process_image: ProcessImageLayout::for_config(&config.process_image),
+        ..Default::default()
+    };
+    let mut tests = compiler
+        .compile_tests(&sources)
+        .with_context(|| "Compilation failed")?;
+    if let Some(filter) = &args.filter {
+        let filter = filter.to_uppercase();
+        tests.retain(|t| t.name.to_uppercase().contains(&filter));
+    }
+
+    println!("running {} tests", tests.len());
+    let mut reports = Vec::with_capacity(tests.len());
+    for test in &tests {
+        let report = test_runner::run_test(test, &config, &script, args.max_cycles);
+        let status = if report.failure.is_some() {
+            "FAILED"
+        } else {
+            "ok"
+        };
+        println!(
+            "test {} ... {} ({} cycles)",
+            report.name, status, report.cycles
+        );
+        reports.push(report);
+    }
+
+    let failed: Vec<_> = reports.iter().filter(|r| r.failure.is_some()).collect();
+    if !failed.is_empty() {
+        println!("\nfailures:");
+        for report in &failed {
+            if let Some(failure) = &report.failure {
+                println!("    {}: {}", report.name, failure);
+            }
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        reports.len() - failed.len(),
+        failed.len()
+    );
+
+    if let Some(path) = &args.junit {
+        let suite = args.sources[0]
+            .file_stem()
+            .map_or_else(|| "plc".into(), |s| s.to_string_lossy());
+        std::fs::write(path, test_runner::junit_xml(&suite, &reports))
+            .with_context(|| format!("Failed to write JUnit report: {:?}", path))?;
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!("{} of {} tests failed", failed.len(), reports.len());
+    }
+    Ok(())
+}
+
 // =============================================================================
 // SUBCOMMAND: simulate
 // =============================================================================
 
 fn cmd_simulate(args: SimulateArgs) -> Result<()> {
-    use plc_runtime::io_image::ProcessData;
+    if let Some(dir) = &args.replay {
+        return cmd_replay(dir, args.module.as_deref());
+    }
+    let Some(module) = &args.module else {
+        anyhow::bail!("No module given");
+    };
 
     // Validate arguments
     if args.cycles == 0 {
         anyhow::bail!("Number of cycles must be at least 1");
     }
 
-    info!(module = ?args.module, cycles = args.cycles, "Starting simulation");
-
-    // Read module
-    let module_bytes = std::fs::read(&args.module)
-        .with_context(|| format!("Failed to read module: {:?}", args.module))?;
+    info!(module = ?module, cycles = args.cycles, "Starting simulation");
 
-    // Check if it's WAT and convert if needed
-    let wasm_bytes = if args.module.extension().map_or(false, |e| e == "wat") {
-        wat::parse_bytes(&module_bytes)
-            .with_context(|| "Failed to parse WAT")?
-            .into_owned()
-    } else {
-        module_bytes
-    };
+    let wasm_bytes = read_module_file(module)?;
 
     // Create Wasm host
     let cycle_time = Duration::from_millis(args.cycle_time_ms);
@@ -440,7 +1175,7 @@ fn cmd_simulate(args: SimulateArgs) -> Result<()> {
 
     // Create process inputs using ProcessData
     let mut inputs = ProcessData::default();
-    inputs.digital_inputs[0] = initial_di;
+    inputs.digital_inputs_mut()[0] = initial_di;
 
     println!(
         "Simulating {} cycles with {}ms cycle time...\n",
@@ -468,9 +1203,9 @@ fn cmd_simulate(args: SimulateArgs) -> Result<()> {
             println!(
                 "{:>8} {:>12} {:>12} {:>12}",
                 cycle,
-                format!("0x{:08X}", inputs.digital_inputs[0]),
-                format!("0x{:08X}", outputs.digital_outputs[0]),
-                outputs.analog_outputs[0]
+                format!("0x{:08X}", inputs.digital_inputs()[0]),
+                format!("0x{:08X}", outputs.digital_outputs()[0]),
+                outputs.analog_outputs()[0]
             );
         }
 
@@ -490,6 +1225,46 @@ fn cmd_simulate(args: SimulateArgs) -> Result<()> {
     Ok(())
 }
 
+/// Replay a cycle recording, optionally against another module.
+fn cmd_replay(dir: &Path, module: Option<&Path>) -> Result<()> {
+    let segments = plc_runtime::replay::read_segments(dir)
+        .with_context(|| format!("Failed to read recording {}", dir.display()))?;
+    if segments.is_empty() {
+        anyhow::bail!("No cycle recording in {}", dir.display());
+    }
+    for segment in segments.iter().filter(|segment| !segment.complete) {
+        warn!(file = %segment.path.display(), "Recording ends in a partly written step, replaying the rest");
+    }
+    let module = module.map(read_module_file).transpose()?;
+
+    let steps: usize = segments.iter().map(|segment| segment.records.len()).sum();
+    println!(
+        "Replaying {} steps from {} segments{}...",
+        steps,
+        segments.len(),
+        if module.is_some() {
+            " against the given module"
+        } else {
+            ""
+        }
+    );
+    let report = plc_runtime::replay::replay(&segments, module.as_deref())?;
+    match report.divergence {
+        None => {
+            println!(
+                "Replay matched the recording: {} steps in {} segments",
+                report.steps, report.segments
+            );
+            Ok(())
+        }
+        Some(divergence) => {
+            println!("First divergence, at step {}:", report.steps);
+            println!("  {divergence}");
+            anyhow::bail!("Replay diverged from the recording");
+        }
+    }
+}
+
 // =============================================================================
 // SUBCOMMAND: diagnose
 // =============================================================================
@@ -876,16 +1651,109 @@ fn cmd_diagnose(args: DiagnoseArgs) -> Result<()> {
     Ok(())
 }
 
-// Diagnostic data structures
-#[derive(Default)]
-struct DiagnosticReport {
-    os: OsInfo,
-    cpu: CpuInfo,
-    capabilities: Capabilities,
-    network_interfaces: Vec<NetworkInterface>,
-    timing: TimingTest,
-    recommendations: Vec<String>,
-}
+// =============================================================================
+// SUBCOMMAND: postmortem
+// =============================================================================
+
+fn cmd_postmortem(args: PostmortemArgs) -> Result<()> {
+    match args.command {
+        PostmortemCommand::List { dir } => {
+            let files = postmortem::list_files(&dir)?;
+            if files.is_empty() {
+                println!("No post-mortem files in {}", dir.display());
+            }
+            for path in files {
+                match PostMortem::read(&path) {
+                    Ok(postmortem) => println!("{}", postmortem::describe(&path, &postmortem)),
+                    Err(e) => println!("{}  unreadable: {e:#}", path.display()),
+                }
+            }
+        }
+        PostmortemCommand::Show {
+            file,
+            module,
+            frames,
+        } => {
+            let postmortem = PostMortem::read(&file)?;
+            let decoder = postmortem_decoder(&postmortem, module.as_deref())?;
+            print!("{}", postmortem::render(&postmortem, &decoder, frames)?);
+        }
+        PostmortemCommand::Diff { a, b, module } => {
+            let a = PostMortem::read(&a)?;
+            let b = PostMortem::read(&b)?;
+            let decoder_a = postmortem_decoder(&a, module.as_deref())?;
+            let decoder_b = postmortem_decoder(&b, module.as_deref())?;
+            print!("{}", postmortem::diff((&a, &decoder_a), (&b, &decoder_b))?);
+        }
+        PostmortemCommand::Export {
+            file,
+            format,
+            output,
+            module,
+        } => {
+            let postmortem = PostMortem::read(&file)?;
+            let decoder = postmortem_decoder(&postmortem, module.as_deref())?;
+            let text = match format {
+                ExportFormat::Csv => postmortem::export_csv(&postmortem, &decoder)?,
+                ExportFormat::Json => postmortem::export_json(&postmortem, &decoder)?,
+            };
+            match output {
+                Some(path) => std::fs::write(&path, text)
+                    .with_context(|| format!("Failed to write {}", path.display()))?,
+                None => print!("{text}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decoder for a post-mortem file, using the module if one is given.
+fn postmortem_decoder(postmortem: &PostMortem, module: Option<&Path>) -> Result<Decoder> {
+    let Some(path) = module else {
+        return Ok(Decoder::new(postmortem));
+    };
+    let wasm = read_module_file(path)?;
+    if postmortem.module_matches(&wasm) == Some(false) {
+        warn!(
+            module = %path.display(),
+            "Module differs from the one that was running; decoded variables may be wrong"
+        );
+    }
+    Decoder::with_module(postmortem, &wasm)
+        .with_context(|| format!("Failed to read symbols from {}", path.display()))
+}
+
+// =============================================================================
+// SUBCOMMAND: witness
+// =============================================================================
+
+fn cmd_witness(args: WitnessArgs) -> Result<()> {
+    let config = RuntimeConfig::from_file(&args.config)
+        .with_context(|| format!("Failed to load config from {:?}", args.config))?;
+    let redundancy = &config.redundancy;
+    let Some(secret) = redundancy
+        .secret
+        .as_deref()
+        .filter(|secret| secret.len() >= 16)
+    else {
+        anyhow::bail!("redundancy.secret must be set, with at least 16 characters");
+    };
+    let Some(address) = args.listen.as_deref().or(redundancy.witness.as_deref()) else {
+        anyhow::bail!("Nothing to listen on: set redundancy.witness or pass --listen");
+    };
+    witness::run(&Endpoint::parse(address), secret.as_bytes())
+}
+
+// Diagnostic data structures
+#[derive(Default)]
+struct DiagnosticReport {
+    os: OsInfo,
+    cpu: CpuInfo,
+    capabilities: Capabilities,
+    network_interfaces: Vec<NetworkInterface>,
+    timing: TimingTest,
+    recommendations: Vec<String>,
+}
 
 #[derive(Default)]
 struct OsInfo {
@@ -938,16 +1806,17 @@ struct TimingTest {
 /// Main daemon run loop.
 fn run_daemon(
     config: &RuntimeConfig,
+    module_source: Option<ModuleSource>,
     signal_handler: &SignalHandler,
     diagnostics: &DiagnosticsCollector,
     max_cycles: u64,
+    mut postmortem: PostMortemWriter,
 ) -> Result<()> {
     let metrics_http_export = config.metrics.http_export;
-    let target_cycle_ns = u64::try_from(config.cycle_time.as_nanos()).unwrap_or(u64::MAX);
 
     // Start web UI server if HTTP export is enabled
     // We keep the runtime alive for the daemon's lifetime and drop it during shutdown.
-    let (state_updater, _web_runtime) = if metrics_http_export {
+    let (state_updater, control_commands, _web_runtime) = if metrics_http_export {
         let bind_addr: SocketAddr = format!("0.0.0.0:{}", config.metrics.http_port)
             .parse()
             .context("Invalid HTTP bind address")?;
@@ -959,8 +1828,9 @@ fn run_daemon(
             ws_channel_capacity: 256,
         };
 
-        let server = WebUiServer::new(web_config);
+        let mut server = WebUiServer::new(web_config);
         let updater = server.state_updater();
+        let commands = server.control_receiver();
 
         // Create tokio runtime for async web server
         let rt = tokio::runtime::Builder::new_multi_thread()
@@ -977,9 +1847,9 @@ fn run_daemon(
         });
 
         info!(addr = %bind_addr, "Web UI server started");
-        (Some(updater), Some(rt))
+        (Some(updater), commands, Some(rt))
     } else {
-        (None, None)
+        (None, None, None)
     };
 
     // Initialize fieldbus driver
@@ -988,43 +1858,82 @@ fn run_daemon(
     diagnostics.state().set_fieldbus_connected(true);
     info!("Fieldbus driver initialized");
 
-    // Create scheduler with appropriate engine
-    let has_wasm = config.wasm_module.is_some();
-
-    if has_wasm {
-        let wasm_path = config
-            .wasm_module
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("wasm_module required but not configured"))?;
-        info!(?wasm_path, "Loading Wasm module");
-
-        let wasm_bytes = std::fs::read(wasm_path)
-            .with_context(|| format!("Failed to read Wasm module: {:?}", wasm_path))?;
-
-        let engine = WasmtimeHost::from_runtime_config(config)
-            .with_context(|| "Failed to create Wasmtime host")?;
-
-        let mut scheduler = create_scheduler(engine, config);
-
-        scheduler
-            .engine
-            .load_module(&wasm_bytes)
-            .with_context(|| "Failed to load Wasm module")?;
-
-        diagnostics.state().set_wasm_loaded(true);
-
-        run_scheduler_loop(
-            &mut scheduler,
-            &mut fieldbus,
-            signal_handler,
-            diagnostics,
-            max_cycles,
-            metrics_http_export,
-            target_cycle_ns,
-            &config.fault_policy.fieldbus_failure,
-            config.wasm_module.as_deref(),
-            state_updater,
+    let mut redundancy = if config.redundancy.enabled {
+        Some(
+            Redundancy::start(
+                &config.redundancy,
+                &config.process_image,
+                Arc::clone(diagnostics.state()),
+            )
+            .context("Failed to start redundancy")?,
         )
+    } else {
+        None
+    };
+
+    // Create scheduler with appropriate engine
+    if let Some(mut module_source) = module_source {
+        info!(source = %module_source, "Loading Wasm module");
+
+        let wasm_bytes = module_source.load()?;
+
+        match config.wasm.engine {
+            WasmEngine::Wasmtime => {
+                let mut engine = WasmtimeHost::from_runtime_config(config)
+                    .with_context(|| "Failed to create Wasmtime host")?;
+                let loaded = engine.load_module(&wasm_bytes);
+                record_signature_audit(
+                    config,
+                    state_updater.as_ref(),
+                    &wasm_bytes,
+                    loaded.as_ref().err().and_then(anyhow::Error::downcast_ref),
+                    0,
+                );
+                loaded.with_context(|| "Failed to load Wasm module")?;
+                run_module(
+                    engine,
+                    &wasm_bytes,
+                    &mut module_source,
+                    &mut fieldbus,
+                    signal_handler,
+                    diagnostics,
+                    max_cycles,
+                    config,
+                    state_updater,
+                    control_commands,
+                    &mut postmortem,
+                    redundancy.as_mut(),
+                )
+            }
+            WasmEngine::Interpreter => {
+                info!("Using the interpreter engine");
+                let mut engine = InterpreterHost::from_runtime_config(config)
+                    .with_context(|| "Failed to create interpreter host")?;
+                let loaded = engine.load_module(&wasm_bytes);
+                record_signature_audit(
+                    config,
+                    state_updater.as_ref(),
+                    &wasm_bytes,
+                    loaded.as_ref().err().and_then(anyhow::Error::downcast_ref),
+                    0,
+                );
+                loaded.with_context(|| "Failed to load Wasm module")?;
+                run_module(
+                    engine,
+                    &wasm_bytes,
+                    &mut module_source,
+                    &mut fieldbus,
+                    signal_handler,
+                    diagnostics,
+                    max_cycles,
+                    config,
+                    state_updater,
+                    control_commands,
+                    &mut postmortem,
+                    redundancy.as_mut(),
+                )
+            }
+        }
     } else {
         info!("No Wasm module configured, using NullEngine");
         let engine = NullEngine::default();
@@ -1038,14 +1947,112 @@ fn run_daemon(
             diagnostics,
             max_cycles,
             metrics_http_export,
-            target_cycle_ns,
-            &config.fault_policy.fieldbus_failure,
+            config,
             None, // NullEngine doesn't support hot-reload
             state_updater,
+            control_commands,
+            &mut postmortem,
+            redundancy.as_mut(),
         )
     }
 }
 
+/// Run the scheduler with `engine`, which has the module `wasm_bytes` from
+/// `module_source` loaded.
+fn run_module<E: LogicEngine>(
+    engine: E,
+    wasm_bytes: &[u8],
+    module_source: &mut ModuleSource,
+    fieldbus: &mut Box<dyn FieldbusDriver>,
+    signal_handler: &SignalHandler,
+    diagnostics: &DiagnosticsCollector,
+    max_cycles: u64,
+    config: &RuntimeConfig,
+    state_updater: Option<StateUpdater>,
+    control_commands: Option<ControlReceiver>,
+    postmortem: &mut PostMortemWriter,
+    mut redundancy: Option<&mut Redundancy>,
+) -> Result<()> {
+    let mut scheduler = create_scheduler(engine, config);
+
+    diagnostics.state().set_wasm_loaded(true);
+    postmortem.set_module(module_source, wasm_bytes);
+    if let Some(redundancy) = redundancy.as_deref_mut() {
+        redundancy.set_module(wasm_bytes);
+    }
+    if config.record.dir.is_some() {
+        scheduler
+            .start_recording(wasm_bytes)
+            .with_context(|| "Failed to start cycle recording")?;
+    }
+
+    run_scheduler_loop(
+        &mut scheduler,
+        fieldbus,
+        signal_handler,
+        diagnostics,
+        max_cycles,
+        config.metrics.http_export,
+        config,
+        Some(module_source),
+        state_updater,
+        control_commands,
+        postmortem,
+        redundancy,
+    )
+}
+
+/// The audit trail entry for checking the signature of `wasm_bytes`, in a
+/// load that ended with `error`: what was decided and whether the module
+/// was accepted. `None` while signatures are not checked.
+fn signature_audit(
+    config: &RuntimeConfig,
+    wasm_bytes: &[u8],
+    error: Option<&PlcError>,
+) -> Option<(String, bool)> {
+    if config.wasm.trusted_keys.is_empty() {
+        return None;
+    }
+    let module_sha256 = postmortem::sha256_hex(wasm_bytes);
+    Some(match error {
+        Some(PlcError::UntrustedModule(reason)) => (
+            format!("Logic module {module_sha256} refused: {reason}"),
+            false,
+        ),
+        // The signature is checked first, so any other outcome passed it
+        _ => {
+            let key = signing::signer(wasm_bytes)
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+            (
+                format!("Logic module {module_sha256} accepted: signed by {key}"),
+                true,
+            )
+        }
+    })
+}
+
+/// Record a signature decision in the web UI's audit trail, at start-up and
+/// on every reload; a refusal is a security event and also a fault.
+fn record_signature_audit(
+    config: &RuntimeConfig,
+    state_updater: Option<&StateUpdater>,
+    wasm_bytes: &[u8],
+    error: Option<&PlcError>,
+    cycle: u64,
+) {
+    let (Some(updater), Some((event, accepted))) =
+        (state_updater, signature_audit(config, wasm_bytes, error))
+    else {
+        return;
+    };
+    if !accepted {
+        updater.record_fault(event.clone(), cycle);
+    }
+    updater.record_audit(event, accepted, cycle);
+}
+
 /// Create the appropriate fieldbus driver based on configuration.
 fn create_fieldbus_driver(config: &RuntimeConfig) -> Result<Box<dyn FieldbusDriver>> {
     match config.fieldbus.driver {
@@ -1084,6 +2091,261 @@ fn create_fieldbus_driver(config: &RuntimeConfig) -> Result<Box<dyn FieldbusDriv
 }
 
 /// Create scheduler with the given logic engine.
+/// Fieldbus outputs from the process image. The drivers exchange one
+/// digital word and 16 analog channels; the rest of a larger image stays
+/// local.
+fn fieldbus_outputs(outputs: &ProcessData) -> FieldbusOutputs {
+    let mut fb_outputs = FieldbusOutputs {
+        digital: outputs.digital_outputs().first().copied().unwrap_or(0),
+        ..FieldbusOutputs::default()
+    };
+    for (slot, &value) in fb_outputs.analog.iter_mut().zip(outputs.analog_outputs()) {
+        *slot = value;
+    }
+    fb_outputs
+}
+
+/// Copy fieldbus inputs into the process image, as far as it has room.
+fn apply_fieldbus_inputs(data: &mut ProcessData, inputs: &FieldbusInputs) {
+    if let Some(word) = data.digital_inputs_mut().first_mut() {
+        *word = inputs.digital;
+    }
+    for (slot, &value) in data.analog_inputs_mut().iter_mut().zip(&inputs.analog) {
+        *slot = value;
+    }
+}
+
+/// Log a dry-run online change, one line per variable that does not carry
+/// over unchanged.
+fn log_migration_plan(plan: &MigrationPlan) {
+    info!(%plan, lossless = plan.is_lossless(), "Online change dry run, module not replaced");
+    for change in plan.converted() {
+        info!(%change, "Would convert");
+    }
+    for name in plan.added() {
+        info!(variable = %name, "Would add with initial value");
+    }
+    for name in plan.removed() {
+        warn!(variable = %name, "Would drop removed variable");
+    }
+    for change in plan.incompatible() {
+        warn!(%change, "Would reset variable with incompatible type");
+    }
+}
+
+/// Apply a control-plane request at a cycle boundary.
+///
+/// Trace settings left out of an arm request come from `trace_config`.
+fn apply_control_request<E: LogicEngine>(
+    scheduler: &mut Scheduler<E>,
+    request: &ControlRequest,
+    trace_config: &TraceConfig,
+) -> plc_common::error::PlcResult<()> {
+    match request {
+        ControlRequest::Force { target, value } => {
+            let value = match *value {
+                ControlValue::Bool(v) => VarValue::Bool(v),
+                ControlValue::Int(v) => VarValue::Int(v),
+                ControlValue::UInt(v) => VarValue::UInt(v),
+                ControlValue::Real(v) => VarValue::Real(v),
+            };
+            scheduler.force(target.parse()?, value)
+        }
+        ControlRequest::Unforce { target } => {
+            if scheduler.unforce(&target.parse::<ForceTarget>()?) {
+                Ok(())
+            } else {
+                Err(plc_common::error::PlcError::Config(format!(
+                    "{target} is not forced"
+                )))
+            }
+        }
+        ControlRequest::ClearForces => {
+            scheduler.clear_forces();
+            Ok(())
+        }
+        ControlRequest::Debug(request) => match request {
+            DebugRequest::Enter => scheduler.enter_debug(),
+            DebugRequest::Exit => scheduler.exit_debug(),
+            DebugRequest::Pause => scheduler.debug(DebugCommand::Pause),
+            DebugRequest::Step { cycles } => scheduler.debug(DebugCommand::Step(*cycles)),
+            DebugRequest::RunUntil { condition } => {
+                scheduler.debug(DebugCommand::RunUntil(condition.parse()?))
+            }
+        },
+        ControlRequest::Trace(TraceRequest::Arm {
+            signals,
+            trigger,
+            pre_trigger,
+            post_trigger,
+            divider,
+        }) => {
+            let settings = TraceConfig {
+                signals: if signals.is_empty() {
+                    trace_config.signals.clone()
+                } else {
+                    signals.clone()
+                },
+                trigger: trigger
+                    .clone()
+                    .unwrap_or_else(|| trace_config.trigger.clone()),
+                pre_trigger: pre_trigger.unwrap_or(trace_config.pre_trigger),
+                post_trigger: post_trigger.unwrap_or(trace_config.post_trigger),
+                divider: divider.unwrap_or(trace_config.divider),
+                output_dir: trace_config.output_dir.clone(),
+            };
+            scheduler.arm_trace(TraceSpec::from_config(&settings)?)?;
+            Ok(())
+        }
+        ControlRequest::Trace(TraceRequest::Stop) => {
+            scheduler.stop_trace();
+            Ok(())
+        }
+    }
+}
+
+/// Report the trace status to the web UI when it changes, and export the
+/// capture once the trace has finished.
+fn publish_trace<E: LogicEngine>(
+    scheduler: &Scheduler<E>,
+    published: &mut Option<(TraceHandle, TraceState)>,
+    output_dir: Option<&Path>,
+    state_updater: Option<&StateUpdater>,
+) {
+    let Some(trace) = scheduler.trace() else {
+        return;
+    };
+    let state = trace.state();
+    if published
+        .as_ref()
+        .is_some_and(|(last, last_state)| last.is_same(&trace) && *last_state == state)
+    {
+        return;
+    }
+    *published = Some((trace.clone(), state));
+
+    let capture = (!state.is_recording()).then(|| trace.capture());
+    if let Some(updater) = state_updater {
+        updater.update_trace(Some(TraceSnapshot {
+            state: state.to_string(),
+            signals: trace
+                .channels()
+                .iter()
+                .map(|channel| channel.name.clone())
+                .collect(),
+            trigger: trace.trigger().to_string(),
+            samples: capture.as_ref().map_or(0, |capture| capture.samples.len()),
+            trigger_cycle: capture
+                .as_ref()
+                .and_then(|capture| capture.trigger_sample())
+                .map(|sample| sample.cycle),
+        }));
+    }
+    let Some(capture) = capture else {
+        return;
+    };
+    info!(%state, samples = capture.samples.len(), "Trace finished");
+
+    // Writing into memory cannot fail
+    let (mut csv, mut vcd) = (Vec::new(), Vec::new());
+    let _ = capture.write_csv(&mut csv);
+    let _ = capture.write_vcd(&mut vcd);
+    if let Some(dir) = output_dir {
+        if let Err(e) = write_trace_files(dir, &csv, &vcd) {
+            warn!(error = %format!("{e:#}"), dir = %dir.display(), "Failed to write trace");
+        }
+    }
+    if let Some(updater) = state_updater {
+        updater.set_trace_export(TraceExport {
+            csv: String::from_utf8_lossy(&csv).into_owned(),
+            vcd: String::from_utf8_lossy(&vcd).into_owned(),
+        });
+    }
+}
+
+/// Write a finished trace to `trace-<unix time>.csv` and `.vcd` in `dir`.
+fn write_trace_files(dir: &Path, csv: &[u8], vcd: &[u8]) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    for (extension, contents) in [("csv", csv), ("vcd", vcd)] {
+        let path = dir.join(format!("trace-{stamp}.{extension}"));
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        info!(path = %path.display(), "Trace written");
+    }
+    Ok(())
+}
+
+/// Report the runtime state and debug status to the web UI.
+fn publish_debug<E: LogicEngine>(scheduler: &Scheduler<E>, state_updater: Option<&StateUpdater>) {
+    let Some(updater) = state_updater else {
+        return;
+    };
+    let debug = (scheduler.state() == RuntimeState::Debug).then(|| {
+        let stop = scheduler.debug_stop();
+        DebugSnapshot {
+            paused: scheduler.is_paused(),
+            cycle: stop.map_or(0, |stop| stop.cycle),
+            reason: stop.map(|stop| stop.reason.to_string()).unwrap_or_default(),
+            variables: stop
+                .map(|stop| {
+                    stop.variables
+                        .iter()
+                        .map(|(name, value)| VariableEntry {
+                            name: name.clone(),
+                            value: value.to_string(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    });
+    updater.update_debug(debug);
+    updater.set_runtime_state(scheduler.state());
+}
+
+/// Report the active forces to diagnostics and the web UI.
+fn publish_forces<E: LogicEngine>(
+    scheduler: &Scheduler<E>,
+    diagnostics: &DiagnosticsCollector,
+    state_updater: Option<&StateUpdater>,
+) {
+    let forces = scheduler.forces();
+    diagnostics.state().set_forces_active(forces.len());
+    if let Some(updater) = state_updater {
+        updater.update_forces(
+            forces
+                .iter()
+                .map(|force| ForceEntry {
+                    target: force.target.to_string(),
+                    value: force.value.to_string(),
+                })
+                .collect(),
+        );
+    }
+}
+
+/// Refresh the per-task metrics for the web UI in place, so the scan loop
+/// reuses one buffer between publishes.
+fn update_task_metrics(metrics: &mut Vec<TaskMetrics>, tasks: &[Task]) {
+    let micros = |time: Option<Duration>| time.map_or(0, |time| time.as_micros() as u64);
+    metrics.resize_with(tasks.len(), TaskMetrics::default);
+    for (entry, task) in metrics.iter_mut().zip(tasks) {
+        let task_metrics = task.metrics();
+        if entry.name != task.name() {
+            entry.name.clear();
+            entry.name.push_str(task.name());
+        }
+        entry.period_us = task.period().as_micros() as u64;
+        entry.cycles = task.cycle_count();
+        entry.overrun_count = task_metrics.overrun_count();
+        entry.avg_us = micros(task_metrics.mean());
+        entry.max_us = micros(task_metrics.max());
+    }
+}
+
 fn create_scheduler<E: LogicEngine>(engine: E, config: &RuntimeConfig) -> Scheduler<E> {
     SchedulerBuilder::new(engine)
         .config(config.clone())
@@ -1099,11 +2361,16 @@ fn run_scheduler_loop<E: LogicEngine>(
     diagnostics: &DiagnosticsCollector,
     max_cycles: u64,
     metrics_http_export: bool,
-    target_cycle_ns: u64,
-    failure_policy: &plc_common::config::FieldbusFailurePolicy,
-    wasm_module_path: Option<&std::path::Path>,
+    config: &RuntimeConfig,
+    mut module_source: Option<&mut ModuleSource>,
     state_updater: Option<StateUpdater>,
+    mut control_commands: Option<ControlReceiver>,
+    postmortem: &mut PostMortemWriter,
+    mut redundancy: Option<&mut Redundancy>,
 ) -> Result<()> {
+    let target_cycle_ns = u64::try_from(scheduler.cycle_period().as_nanos()).unwrap_or(u64::MAX);
+    let failure_policy = &config.fault_policy.fieldbus_failure;
+
     scheduler
         .initialize()
         .context("Failed to initialize scheduler")?;
@@ -1135,6 +2402,11 @@ fn run_scheduler_loop<E: LogicEngine>(
     let mut consecutive_fb_failures = 0u32;
     let mut in_failure_streak = false;
     let mut recovery_cycles_remaining = 0u32;
+    let mut published_trace = None;
+    let mut web_metrics = RawMetrics {
+        target_ns: target_cycle_ns,
+        ..RawMetrics::default()
+    };
 
     // Web UI update interval (every N cycles to avoid overhead)
     const WEB_UI_UPDATE_INTERVAL: u64 = 100;
@@ -1145,23 +2417,52 @@ fn run_scheduler_loop<E: LogicEngine>(
     }
 
     if !shutdown_requested {
-        while scheduler.state() == RuntimeState::Run {
+        while scheduler.state().is_scanning() {
             if signal_handler.shutdown_requested() {
                 info!("Shutdown signal received, stopping scheduler");
                 break;
             }
 
             if signal_handler.take_reload_request() {
-                if let Some(wasm_path) = wasm_module_path {
+                if let Some(source) = module_source.as_deref_mut() {
                     if scheduler.engine.supports_hot_reload() {
-                        info!(?wasm_path, "Hot-reload requested, loading module");
-                        match std::fs::read(wasm_path) {
+                        info!(%source, "Hot-reload requested, loading module");
+                        match source.load() {
+                            Ok(wasm_bytes) if config.wasm.reload_dry_run => {
+                                let planned = scheduler.engine.plan_reload(&wasm_bytes);
+                                record_signature_audit(
+                                    config,
+                                    state_updater.as_ref(),
+                                    &wasm_bytes,
+                                    planned.as_ref().err(),
+                                    cycles_run,
+                                );
+                                match planned {
+                                    Ok(plan) => log_migration_plan(&plan),
+                                    Err(e) => {
+                                        error!(error = %e, "Online change dry run failed");
+                                    }
+                                }
+                            }
                             Ok(wasm_bytes) => {
                                 // Reload with memory preservation to maintain state
-                                match scheduler.engine.reload_module(&wasm_bytes, true) {
+                                let reloaded = scheduler.engine.reload_module(&wasm_bytes, true);
+                                record_signature_audit(
+                                    config,
+                                    state_updater.as_ref(),
+                                    &wasm_bytes,
+                                    reloaded.as_ref().err(),
+                                    cycles_run,
+                                );
+                                match reloaded {
                                     Ok(()) => {
                                         info!("Hot-reload successful, module updated");
                                         diagnostics.state().set_wasm_loaded(true);
+                                        postmortem.set_module(source, &wasm_bytes);
+                                        scheduler.set_recorded_module(&wasm_bytes);
+                                        if let Some(redundancy) = redundancy.as_deref_mut() {
+                                            redundancy.set_module(&wasm_bytes);
+                                        }
                                     }
                                     Err(e) => {
                                         error!(error = %e, "Hot-reload failed, keeping previous module");
@@ -1169,7 +2470,7 @@ fn run_scheduler_loop<E: LogicEngine>(
                                 }
                             }
                             Err(e) => {
-                                error!(error = %e, ?wasm_path, "Failed to read Wasm module for hot-reload");
+                                error!(error = %format!("{e:#}"), %source, "Failed to load Wasm module for hot-reload");
                             }
                         }
                     } else {
@@ -1180,13 +2481,47 @@ fn run_scheduler_loop<E: LogicEngine>(
                 }
             }
 
+            if let Some(commands) = control_commands.as_mut() {
+                let mut changed = false;
+                while let Some(command) = commands.try_recv() {
+                    let result = apply_control_request(scheduler, &command.request, &config.trace);
+                    if let Err(ref e) = result {
+                        warn!(request = ?command.request, error = %e, "Control request rejected");
+                    }
+                    changed |= result.is_ok();
+                    command.reply(result.map_err(|e| e.to_string()));
+                }
+                if changed {
+                    publish_forces(scheduler, diagnostics, state_updater.as_ref());
+                    publish_debug(scheduler, state_updater.as_ref());
+                    publish_trace(
+                        scheduler,
+                        &mut published_trace,
+                        config.trace.output_dir.as_deref(),
+                        state_updater.as_ref(),
+                    );
+                }
+            }
+
+            if let Some(redundancy) = redundancy.as_deref_mut() {
+                redundancy.poll(scheduler);
+                if !redundancy.role().drives_outputs() {
+                    // Standby or still negotiating: keep the cadence, leave
+                    // the logic and the fieldbus to the primary
+                    scheduler.idle_cycle();
+                    cycles_run += 1;
+                    if max_cycles > 0 && cycles_run >= max_cycles {
+                        info!(cycles = cycles_run, "Maximum cycle count reached");
+                        signal_handler.request_shutdown();
+                        break;
+                    }
+                    continue;
+                }
+            }
+
             if !in_failure_streak {
                 let outputs = scheduler.io.read_outputs();
-                let fb_outputs = plc_fieldbus::FieldbusOutputs {
-                    digital: outputs.digital_outputs[0],
-                    analog: outputs.analog_outputs,
-                };
-                fieldbus.set_outputs(&fb_outputs);
+                fieldbus.set_outputs(&fieldbus_outputs(&outputs));
             }
 
             if let Err(e) = fieldbus.exchange() {
@@ -1198,7 +2533,9 @@ fn run_scheduler_loop<E: LogicEngine>(
                         updater.record_fault("WKC threshold exceeded".to_string(), cycles_run);
                         updater.set_runtime_state(RuntimeState::Fault);
                     }
-                    if let Err(fe) = scheduler.enter_fault("WKC threshold exceeded") {
+                    if let Err(fe) = scheduler
+                        .enter_external_fault(FaultReason::WkcError, "WKC threshold exceeded")
+                    {
                         warn!("Failed to enter fault state: {}", fe);
                     }
                     signal_handler.request_shutdown();
@@ -1237,7 +2574,10 @@ fn run_scheduler_loop<E: LogicEngine>(
                         );
                         updater.set_runtime_state(RuntimeState::Fault);
                     }
-                    if let Err(e) = scheduler.enter_fault("Fieldbus failure limit exceeded") {
+                    if let Err(e) = scheduler.enter_external_fault(
+                        FaultReason::FieldbusError,
+                        "Fieldbus failure limit exceeded",
+                    ) {
                         warn!("Failed to enter fault state: {}", e);
                     }
                     signal_handler.request_shutdown();
@@ -1263,11 +2603,7 @@ fn run_scheduler_loop<E: LogicEngine>(
                         consecutive_fb_failures = 0;
 
                         let outputs = scheduler.io.read_outputs();
-                        let fb_outputs = plc_fieldbus::FieldbusOutputs {
-                            digital: outputs.digital_outputs[0],
-                            analog: outputs.analog_outputs,
-                        };
-                        fieldbus.set_outputs(&fb_outputs);
+                        fieldbus.set_outputs(&fieldbus_outputs(&outputs));
                         let _ = fieldbus.exchange();
                     }
                 }
@@ -1276,17 +2612,28 @@ fn run_scheduler_loop<E: LogicEngine>(
 
             {
                 let fb_inputs = fieldbus.get_inputs();
-                scheduler.io.write_inputs(|data| {
-                    data.digital_inputs[0] = fb_inputs.digital;
-                    data.analog_inputs = fb_inputs.analog;
-                });
+                scheduler
+                    .io
+                    .write_inputs(|data| apply_fieldbus_inputs(data, &fb_inputs));
             }
 
             match scheduler.run_cycle() {
+                Ok(result) if result.paused => {
+                    // Logic paused in DEBUG; the scan only kept its cadence
+                }
                 Ok(result) => {
                     diagnostics
                         .state()
                         .record_cycle(result.execution_time, result.overrun);
+                    if scheduler.take_debug_stop().is_some() {
+                        publish_debug(scheduler, state_updater.as_ref());
+                    }
+                    publish_trace(
+                        scheduler,
+                        &mut published_trace,
+                        config.trace.output_dir.as_deref(),
+                        state_updater.as_ref(),
+                    );
 
                     if result.overrun {
                         warn!(
@@ -1298,10 +2645,18 @@ fn run_scheduler_loop<E: LogicEngine>(
                 }
                 Err(e) => {
                     error!("Cycle execution failed: {}", e);
+                    // Record fault in web UI
+                    if let Some(ref updater) = state_updater {
+                        updater.record_fault(e.to_string(), cycles_run);
+                        updater.set_runtime_state(RuntimeState::Fault);
+                    }
                     signal_handler.request_shutdown();
                     break;
                 }
             }
+            if let Some(redundancy) = redundancy.as_deref_mut() {
+                redundancy.publish(scheduler);
+            }
 
             cycles_run += 1;
             if max_cycles > 0 && cycles_run >= max_cycles {
@@ -1328,44 +2683,79 @@ fn run_scheduler_loop<E: LogicEngine>(
                     let inputs = scheduler.io.read_inputs();
                     let outputs = scheduler.io.read_outputs();
                     updater.update_io_raw(
-                        inputs.digital_inputs[0],
-                        outputs.digital_outputs[0],
-                        &inputs.analog_inputs,
-                        &outputs.analog_outputs,
+                        inputs.digital_inputs(),
+                        outputs.digital_outputs(),
+                        inputs.analog_inputs(),
+                        outputs.analog_outputs(),
                     );
 
                     // Update metrics
                     let metrics = scheduler.metrics();
-                    updater.update_metrics_raw(
-                        cycles_run,
-                        metrics.min().map(|d| d.as_nanos() as u64).unwrap_or(0),
-                        metrics.max().map(|d| d.as_nanos() as u64).unwrap_or(0),
-                        metrics.mean().map(|d| d.as_nanos() as u64).unwrap_or(0),
-                        target_cycle_ns,
-                        diagnostics.state().overrun_count() as u64,
-                    );
+                    web_metrics.total_cycles = cycles_run;
+                    web_metrics.min_ns = metrics.min().map(|d| d.as_nanos() as u64).unwrap_or(0);
+                    web_metrics.max_ns = metrics.max().map(|d| d.as_nanos() as u64).unwrap_or(0);
+                    web_metrics.avg_ns = metrics.mean().map(|d| d.as_nanos() as u64).unwrap_or(0);
+                    web_metrics.overrun_count = diagnostics.state().overrun_count() as u64;
+                    update_task_metrics(&mut web_metrics.tasks, scheduler.tasks());
+                    updater.update_metrics_raw(&web_metrics);
+
+                    // Update active SFC steps
+                    updater.update_active_steps(scheduler.engine.active_steps());
                 }
             }
+            if scheduler.forces().len() as u64 != diagnostics.state().forces_active() {
+                // A forced variable was removed by an online change
+                publish_forces(scheduler, diagnostics, state_updater.as_ref());
+            }
         }
     }
 
     info!("Shutting down...");
 
+    match postmortem.persist(scheduler) {
+        Ok(Some(path)) => info!(path = %path.display(), "Post-mortem written"),
+        Ok(None) => {}
+        Err(e) => error!(error = %format!("{e:#}"), "Failed to write post-mortem"),
+    }
+
     // Stop scheduler first, then update UI with final state
     if let Err(e) = scheduler.stop() {
         warn!("Scheduler stop failed: {}", e);
     }
 
-    // Notify web UI of shutdown state AFTER stop completes
-    if let Some(ref updater) = state_updater {
-        updater.set_runtime_state(scheduler.state());
+    // Notify web UI of shutdown state AFTER stop completes; a fault or stop
+    // may have released the forces and left DEBUG
+    publish_forces(scheduler, diagnostics, state_updater.as_ref());
+    publish_debug(scheduler, state_updater.as_ref());
+    publish_trace(
+        scheduler,
+        &mut published_trace,
+        config.trace.output_dir.as_deref(),
+        state_updater.as_ref(),
+    );
+    if !scheduler.forces().is_empty() {
+        warn!(
+            forces = scheduler.forces().len(),
+            "Forces still active at shutdown"
+        );
     }
 
     if let Err(e) = fieldbus.shutdown() {
         warn!("Fieldbus shutdown failed: {}", e);
     }
 
-    let snapshot = diagnostics.snapshot(scheduler.state(), scheduler.metrics());
+    for task in scheduler.tasks().iter().map(TaskStatus::of) {
+        info!(
+            task = %task.name,
+            period_us = task.period.as_micros(),
+            cycles = task.cycles,
+            overruns = task.overruns,
+            max_us = task.max_time.map_or(0, |max| max.as_micros()),
+            "Task summary"
+        );
+    }
+
+    let snapshot = diagnostics.snapshot(scheduler.state(), scheduler.metrics(), scheduler.tasks());
     if metrics_http_export {
         let _ = format_prometheus_metrics(&snapshot, target_cycle_ns);
     }
@@ -1394,18 +2784,310 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_run_reload_dry_run() {
+        let cli = Cli::parse_from(["plc-daemon", "run", "--reload-dry-run"]);
+        match cli.command {
+            Commands::Run(args) => assert!(args.reload_dry_run),
+            _ => panic!("Expected Run command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_run_sources() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "run",
+            "--source",
+            "main.st",
+            "--source",
+            "io.il",
+        ]);
+        match cli.command {
+            Commands::Run(args) => assert_eq!(
+                args.sources,
+                vec![PathBuf::from("main.st"), PathBuf::from("io.il")]
+            ),
+            _ => panic!("Expected Run command"),
+        }
+
+        // A project is either compiled or loaded, not both.
+        assert!(Cli::try_parse_from([
+            "plc-daemon",
+            "run",
+            "--source",
+            "main.st",
+            "-w",
+            "main.wasm",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_fieldbus_mapping_with_configured_image() {
+        use plc_common::config::ProcessImageConfig;
+
+        let small = ProcessImageConfig {
+            digital_inputs: 0,
+            digital_outputs: 8,
+            analog_inputs: 4,
+            analog_outputs: 2,
+        };
+        let mut data = ProcessData::new(&small);
+        let inputs = FieldbusInputs {
+            digital: 0xFF,
+            analog: [7; 16],
+        };
+        apply_fieldbus_inputs(&mut data, &inputs);
+        assert!(data.digital_inputs().is_empty());
+        assert_eq!(data.analog_inputs(), &[7; 4]);
+
+        data.digital_outputs_mut()[0] = 0x5A;
+        data.analog_outputs_mut().copy_from_slice(&[1, 2]);
+        let outputs = fieldbus_outputs(&data);
+        assert_eq!(outputs.digital, 0x5A);
+        assert_eq!(&outputs.analog[..3], &[1, 2, 0]);
+    }
+
+    #[test]
+    fn test_trace_arm_request_uses_configured_defaults() {
+        let config = RuntimeConfig {
+            trace: TraceConfig {
+                signals: vec!["%IX0.0".into()],
+                trigger: "rising %IX0.0".into(),
+                ..TraceConfig::default()
+            },
+            ..RuntimeConfig::default()
+        };
+        let mut scheduler = create_scheduler(NullEngine::default(), &config);
+        scheduler.initialize().unwrap();
+
+        let arm = |signals: &[&str], trigger: Option<&str>| {
+            ControlRequest::Trace(TraceRequest::Arm {
+                signals: signals.iter().map(|s| s.to_string()).collect(),
+                trigger: trigger.map(str::to_string),
+                pre_trigger: None,
+                post_trigger: Some(5),
+                divider: None,
+            })
+        };
+        apply_control_request(&mut scheduler, &arm(&[], None), &config.trace).unwrap();
+        let trace = scheduler.trace().unwrap();
+        assert_eq!(trace.channels()[0].name, "%IX0.0");
+        assert_eq!(trace.trigger().to_string(), "rising %IX0.0");
+
+        apply_control_request(
+            &mut scheduler,
+            &arm(&["%QW1"], Some("fault")),
+            &config.trace,
+        )
+        .unwrap();
+        assert_eq!(scheduler.trace().unwrap().trigger().to_string(), "fault");
+        assert!(
+            apply_control_request(&mut scheduler, &arm(&["%QW99"], None), &config.trace).is_err()
+        );
+
+        let stop = ControlRequest::Trace(TraceRequest::Stop);
+        apply_control_request(&mut scheduler, &stop, &config.trace).unwrap();
+        assert_eq!(scheduler.trace().unwrap().state(), TraceState::Stopped);
+    }
+
+    #[test]
+    fn test_cli_compile_cache_dir() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "compile",
+            "test.st",
+            "--cache-dir",
+            ".plc-cache",
+        ]);
+        match cli.command {
+            Commands::Compile(args) => {
+                assert_eq!(args.cache_dir, Some(PathBuf::from(".plc-cache")));
+                assert_eq!(args.config, None);
+            }
+            _ => panic!("Expected Compile command"),
+        }
+    }
+
     #[test]
     fn test_cli_compile_subcommand() {
         let cli = Cli::parse_from(["plc-daemon", "compile", "test.st", "-o", "test.wasm"]);
         match cli.command {
             Commands::Compile(args) => {
                 assert_eq!(args.input, PathBuf::from("test.st"));
+                assert!(args.sources.is_empty());
                 assert_eq!(args.output, Some(PathBuf::from("test.wasm")));
             }
             _ => panic!("Expected Compile command"),
         }
     }
 
+    #[test]
+    fn test_cli_sign_and_keygen() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "compile",
+            "test.st",
+            "--precompile",
+            "test.cwasm",
+            "--sign-key",
+            "plc.key",
+        ]);
+        match cli.command {
+            Commands::Compile(args) => assert_eq!(args.sign_key, Some(PathBuf::from("plc.key"))),
+            _ => panic!("Expected Compile command"),
+        }
+        assert!(Cli::try_parse_from([
+            "plc-daemon",
+            "compile",
+            "test.st",
+            "--wat",
+            "--sign-key",
+            "plc.key"
+        ])
+        .is_err());
+
+        let cli = Cli::parse_from(["plc-daemon", "sign", "test.wasm", "-k", "plc.key"]);
+        match cli.command {
+            Commands::Sign(args) => {
+                assert_eq!(args.module, PathBuf::from("test.wasm"));
+                assert_eq!(args.key, PathBuf::from("plc.key"));
+                assert_eq!(args.output, None);
+            }
+            _ => panic!("Expected Sign command"),
+        }
+        assert!(Cli::try_parse_from(["plc-daemon", "sign", "test.wasm"]).is_err());
+
+        let cli = Cli::parse_from(["plc-daemon", "keygen", "plc.key"]);
+        match cli.command {
+            Commands::Keygen(args) => {
+                assert_eq!(args.output, PathBuf::from("plc.key"));
+                assert!(!args.force);
+            }
+            _ => panic!("Expected Keygen command"),
+        }
+    }
+
+    #[test]
+    fn test_keygen_force_restricts_an_existing_key_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("plc-keygen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("plc.key");
+        std::fs::write(&output, "old key\n").unwrap();
+        std::fs::set_permissions(&output, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        cmd_keygen(KeygenArgs {
+            output: output.clone(),
+            force: true,
+        })
+        .unwrap();
+        let mode = std::fs::metadata(&output).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(read_signing_key(&output).is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cli_compile_opt_level() {
+        use plc_compiler::optimize::OptLevel;
+
+        let cli = Cli::parse_from(["plc-daemon", "compile", "test.st"]);
+        match cli.command {
+            Commands::Compile(args) => assert_eq!(args.opt_level, OptLevel::O1),
+            _ => panic!("Expected Compile command"),
+        }
+
+        let cli = Cli::parse_from(["plc-daemon", "compile", "test.st", "-O2"]);
+        match cli.command {
+            Commands::Compile(args) => assert_eq!(args.opt_level, OptLevel::O2),
+            _ => panic!("Expected Compile command"),
+        }
+
+        assert!(Cli::try_parse_from(["plc-daemon", "compile", "test.st", "-O", "3"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_compile_multiple_sources() {
+        let cli = Cli::parse_from(["plc-daemon", "compile", "main.st", "logic.il", "io.st"]);
+        match cli.command {
+            Commands::Compile(args) => {
+                assert_eq!(args.input, PathBuf::from("main.st"));
+                assert_eq!(
+                    args.sources,
+                    vec![PathBuf::from("logic.il"), PathBuf::from("io.st")]
+                );
+            }
+            _ => panic!("Expected Compile command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_fmt_subcommand() {
+        let cli = Cli::parse_from(["plc-daemon", "fmt", "a.st", "b.st", "--check"]);
+        match cli.command {
+            Commands::Fmt(args) => {
+                assert_eq!(
+                    args.files,
+                    vec![PathBuf::from("a.st"), PathBuf::from("b.st")]
+                );
+                assert!(args.check);
+                assert_eq!(args.max_width, 100);
+            }
+            _ => panic!("Expected Fmt command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_export_subcommand() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "export",
+            "main.st",
+            "logic.il",
+            "-o",
+            "project.xml",
+        ]);
+        match cli.command {
+            Commands::Export(args) => {
+                assert_eq!(
+                    args.sources,
+                    vec![PathBuf::from("main.st"), PathBuf::from("logic.il")]
+                );
+                assert_eq!(args.output, PathBuf::from("project.xml"));
+                assert_eq!(args.name, None);
+            }
+            _ => panic!("Expected Export command"),
+        }
+    }
+
+    #[test]
+    fn test_fmt_check_reports_unformatted_file() {
+        let dir = std::env::temp_dir().join(format!("plc-fmt-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.st");
+        std::fs::write(&path, "program Main\nx:=1;\nend_program\n").unwrap();
+
+        let check = |check| FmtArgs {
+            files: vec![path.clone()],
+            check,
+            max_width: 100,
+        };
+
+        assert!(cmd_fmt(check(true)).is_err());
+        cmd_fmt(check(false)).unwrap();
+        assert!(cmd_fmt(check(true)).is_ok());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "PROGRAM Main\nx := 1;\nEND_PROGRAM\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_cli_validate_subcommand() {
         let cli = Cli::parse_from(["plc-daemon", "validate", "module.wasm", "--verbose"]);
@@ -1418,16 +3100,195 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_validate_config() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "validate",
+            "module.wasm",
+            "-c",
+            "plc.toml",
+            "--ns-per-fuel",
+            "5",
+        ]);
+        match cli.command {
+            Commands::Validate(args) => {
+                assert_eq!(args.config, Some(PathBuf::from("plc.toml")));
+                assert_eq!(args.ns_per_fuel, 5);
+            }
+            _ => panic!("Expected Validate command"),
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_online_change() {
+        let dir =
+            std::env::temp_dir().join(format!("plc-online-change-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let old = plc_compiler::compile(
+            "PROGRAM Main VAR a : INT; b : INT; END_VAR a := a + 1; END_PROGRAM",
+        )
+        .unwrap();
+        let new = plc_compiler::compile(
+            "PROGRAM Main VAR a : INT; x : INT := 5; b : DINT; END_VAR a := a + 1; END_PROGRAM",
+        )
+        .unwrap();
+        let old_path = dir.join("old.wasm");
+        let new_path = dir.join("new.wasm");
+        std::fs::write(&old_path, &old).unwrap();
+        std::fs::write(&new_path, &new).unwrap();
+
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "validate",
+            new_path.to_str().unwrap(),
+            "--exports",
+            "step,memory",
+            "--previous",
+            old_path.to_str().unwrap(),
+        ]);
+        let Commands::Validate(args) = cli.command else {
+            panic!("Expected Validate command");
+        };
+        assert_eq!(args.previous, Some(old_path.clone()));
+        cmd_validate(args).unwrap();
+
+        // The dry run itself reports what changed
+        let mut host = build_validation_host(&RuntimeConfig::default()).unwrap();
+        host.load_module(&old).unwrap();
+        host.init().unwrap();
+        let plan = host.plan_reload(&new).unwrap();
+        assert_eq!(plan.added(), ["Main.x"]);
+        assert_eq!(plan.converted()[0].to_string(), "Main.b : INT -> DINT");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fuel_estimate_checked_against_config() {
+        let mut config = RuntimeConfig {
+            cycle_time: Duration::from_millis(1),
+            ..Default::default()
+        };
+        config.wasm.fuel_per_cycle = 50_000;
+        let estimate = |fuel| FuelEstimate {
+            step_fuel: fuel,
+            unbounded: Vec::new(),
+        };
+
+        check_fuel_estimate(&estimate(Some(40_000)), &config, 10).unwrap();
+        // Over the fuel budget.
+        assert!(check_fuel_estimate(&estimate(Some(60_000)), &config, 10).is_err());
+        // Within the budget, but 40k units at 50ns take 2ms.
+        assert!(check_fuel_estimate(&estimate(Some(40_000)), &config, 50).is_err());
+        // Without fuel metering only the cycle time matters.
+        config.wasm.use_fuel = false;
+        check_fuel_estimate(&estimate(Some(60_000)), &config, 10).unwrap();
+        // Unbounded code is only a warning.
+        check_fuel_estimate(&estimate(None), &config, 10).unwrap();
+    }
+
+    #[test]
+    fn test_cli_test_subcommand() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "test",
+            "main.st",
+            "tests.st",
+            "--junit",
+            "report.xml",
+            "--filter",
+            "valve",
+        ]);
+        match cli.command {
+            Commands::Test(args) => {
+                assert_eq!(args.sources.len(), 2);
+                assert_eq!(args.junit, Some(PathBuf::from("report.xml")));
+                assert_eq!(args.filter.as_deref(), Some("valve"));
+                assert_eq!(args.max_cycles, 1000);
+            }
+            _ => panic!("Expected Test command"),
+        }
+    }
+
     #[test]
     fn test_cli_simulate_subcommand() {
         let cli = Cli::parse_from(["plc-daemon", "simulate", "test.wasm", "-n", "50"]);
         match cli.command {
             Commands::Simulate(args) => {
-                assert_eq!(args.module, PathBuf::from("test.wasm"));
+                assert_eq!(args.module, Some(PathBuf::from("test.wasm")));
                 assert_eq!(args.cycles, 50);
             }
             _ => panic!("Expected Simulate command"),
         }
+
+        let cli = Cli::parse_from(["plc-daemon", "simulate", "--replay", "rec"]);
+        match cli.command {
+            Commands::Simulate(args) => {
+                assert_eq!(args.replay, Some(PathBuf::from("rec")));
+                assert_eq!(args.module, None);
+            }
+            _ => panic!("Expected Simulate command"),
+        }
+        assert!(Cli::try_parse_from(["plc-daemon", "simulate"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_postmortem_subcommand() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "postmortem",
+            "export",
+            "postmortem-1.json",
+            "--format",
+            "json",
+            "-w",
+            "plc.wasm",
+        ]);
+        match cli.command {
+            Commands::Postmortem(PostmortemArgs {
+                command:
+                    PostmortemCommand::Export {
+                        file,
+                        format,
+                        output,
+                        module,
+                    },
+            }) => {
+                assert_eq!(file, PathBuf::from("postmortem-1.json"));
+                assert_eq!(format, ExportFormat::Json);
+                assert_eq!(output, None);
+                assert_eq!(module, Some(PathBuf::from("plc.wasm")));
+            }
+            _ => panic!("Expected Postmortem export command"),
+        }
+
+        let cli = Cli::parse_from(["plc-daemon", "postmortem", "diff", "a.json", "b.json"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Postmortem(PostmortemArgs {
+                command: PostmortemCommand::Diff { module: None, .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn test_cli_witness_subcommand() {
+        let cli = Cli::parse_from([
+            "plc-daemon",
+            "witness",
+            "-c",
+            "pair.toml",
+            "--listen",
+            "0.0.0.0:7701",
+        ]);
+        match cli.command {
+            Commands::Witness(args) => {
+                assert_eq!(args.config, PathBuf::from("pair.toml"));
+                assert_eq!(args.listen.as_deref(), Some("0.0.0.0:7701"));
+            }
+            _ => panic!("Expected Witness command"),
+        }
     }
 
     #[test]
@@ -1459,4 +3320,24 @@ mod tests {
         let result = host.init();
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_signature_audit_records_accepts_and_refusals() {
+        let key = SigningKey::generate().unwrap();
+        let module = wat::parse_str(r#"(module (func (export "step")))"#).unwrap();
+        let signed = key.sign(&module).unwrap();
+        let mut config = RuntimeConfig::default();
+        assert_eq!(signature_audit(&config, &signed, None), None);
+
+        config.wasm.trusted_keys = vec![key.public_key_hex()];
+        let (event, accepted) = signature_audit(&config, &signed, None).unwrap();
+        assert!(accepted);
+        assert!(event.contains(&postmortem::sha256_hex(&signed)));
+        assert!(event.contains(&key.public_key_hex()));
+
+        let refusal = PlcError::UntrustedModule("module is not signed".into());
+        let (event, accepted) = signature_audit(&config, &module, Some(&refusal)).unwrap();
+        assert!(!accepted);
+        assert!(event.contains("refused: module is not signed"));
+    }
 }
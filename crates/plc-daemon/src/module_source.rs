@@ -0,0 +1,134 @@
+//! Where the daemon gets its logic module from.
+//!
+//! The module is either a compiled `.wasm` file or a set of project sources
+//! that the daemon compiles itself. Sources are compiled through a
+//! [`BuildCache`] that lives as long as the daemon, so a hot-reload after an
+//! edit only rebuilds the POUs that changed, and an unchanged project is not
+//! compiled at all.
+
+use anyhow::{Context, Result};
+use plc_common::abi::ProcessImageLayout;
+use plc_common::config::RuntimeConfig;
+use plc_compiler::cache::BuildCache;
+use plc_compiler::{Compiler, SourceFile};
+use std::fmt;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Source of the logic module, read again on every hot-reload.
+#[derive(Debug)]
+pub enum ModuleSource {
+    /// A compiled WebAssembly module.
+    Wasm(PathBuf),
+    /// Project sources compiled by the daemon.
+    Project {
+        /// Source files (.st, .il or PLCopen .xml).
+        sources: Vec<PathBuf>,
+        /// Compiler settings.
+        compiler: Compiler,
+        /// Results of earlier builds.
+        cache: Box<BuildCache>,
+    },
+}
+
+impl ModuleSource {
+    /// Pick the module source: project sources if given, otherwise the
+    /// configured Wasm module. The build cache directory is shared with the
+    /// Wasmtime host's precompiled modules.
+    pub fn new(config: &RuntimeConfig, sources: Vec<PathBuf>) -> Option<Self> {
+        if sources.is_empty() {
+            return config.wasm_module.clone().map(ModuleSource::Wasm);
+        }
+        let cache = match &config.wasm.cache_dir {
+            Some(dir) => BuildCache::open(dir),
+            None => BuildCache::new(),
+        };
+        Some(ModuleSource::Project {
+            sources,
+            compiler: Compiler {
+                process_image: ProcessImageLayout::for_config(&config.process_image),
+                ..Compiler::default()
+            },
+            cache: Box::new(cache),
+        })
+    }
+
+    /// Read or build the module.
+    pub fn load(&mut self) -> Result<Vec<u8>> {
+        match self {
+            ModuleSource::Wasm(path) => std::fs::read(&*path)
+                .with_context(|| format!("Failed to read Wasm module: {path:?}")),
+            ModuleSource::Project {
+                sources,
+                compiler,
+                cache,
+            } => {
+                let files = sources
+                    .iter()
+                    .map(|path| SourceFile::read(path))
+                    .collect::<Result<Vec<_>>>()?;
+                let wasm = compiler
+                    .compile_sources_cached(&files, cache)
+                    .context("Compilation failed")?;
+                let stats = cache.stats();
+                info!(
+                    bytes = wasm.len(),
+                    cached_module = stats.module_reused,
+                    checked = stats.checked,
+                    reused = stats.checked_reused,
+                    "Project compiled"
+                );
+                Ok(wasm)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ModuleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleSource::Wasm(path) => write!(f, "{}", path.display()),
+            ModuleSource::Project { sources, .. } => {
+                let names: Vec<_> = sources.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "{}", names.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_source_rebuilds_incrementally() {
+        let dir = std::env::temp_dir().join(format!("plc-module-source-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.st");
+        let write = |factor: i32| {
+            let source = format!(
+                "FUNCTION_BLOCK Counter\nVAR_OUTPUT count : INT; END_VAR\n    count := count + 1;\nEND_FUNCTION_BLOCK\n\
+                 PROGRAM Main\nVAR y : INT; END_VAR\n    Counter();\n    y := {factor};\nEND_PROGRAM\n"
+            );
+            std::fs::write(&path, source).unwrap();
+        };
+
+        let config = RuntimeConfig::default();
+        assert!(ModuleSource::new(&config, Vec::new()).is_none());
+
+        let mut source = ModuleSource::new(&config, vec![path.clone()]).unwrap();
+        write(1);
+        let first = source.load().unwrap();
+        write(2);
+        let second = source.load().unwrap();
+        assert_ne!(first, second);
+
+        let ModuleSource::Project { cache, .. } = &source else {
+            panic!("expected a project source");
+        };
+        assert_eq!(cache.stats().checked, 1);
+        assert_eq!(cache.stats().checked_reused, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
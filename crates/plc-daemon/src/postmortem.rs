@@ -0,0 +1,970 @@
+//! Post-mortem files for faults.
+//!
+//! When a fault freezes the [`FaultRecorder`](plc_runtime::FaultRecorder),
+//! the daemon writes what it recorded to `postmortem-<time>.json` in
+//! `fault_policy.postmortem_dir`: the frames and fault summary, the values
+//! of the program variables, SHA-256 hashes of the runtime configuration and
+//! of the module, and the most recent log lines. The file is written under a
+//! temporary name and renamed into place, so a crash while writing never
+//! leaves a partial file behind.
+//!
+//! The format is JSON with a `format_version`; files from a newer version
+//! are rejected. I/O snapshots are stored as hex in the fault recorder's
+//! layout: digital words, then analog channels, little-endian. A
+//! [`Decoder`] turns them into process-image points (`%ID0`, `%IW3`) and,
+//! given the module, into the variables its symbol table places in the
+//! process image.
+
+use anyhow::{bail, Context as _, Result};
+use plc_common::abi::{self, ProcessImageLayout};
+use plc_common::config::{ProcessImageConfig, RuntimeConfig};
+use plc_common::wasm_meta;
+use plc_runtime::fault_recorder::FaultFrame;
+use plc_runtime::scheduler::Scheduler;
+use plc_runtime::symbols::SymbolTable;
+use plc_runtime::wasm_host::LogicEngine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{self, Write as _};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Version of the file format written by this daemon.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Number of log lines kept for post-mortem files.
+const RECENT_LOG_LINES: usize = 200;
+
+/// File name prefix of post-mortem files.
+const FILE_PREFIX: &str = "postmortem-";
+
+/// Everything recorded about one fault.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostMortem {
+    /// File format version.
+    pub format_version: u32,
+    /// When the file was written, RFC 3339 in UTC.
+    pub created: String,
+    /// Version of the daemon that wrote the file.
+    pub daemon_version: String,
+    /// SHA-256 of the effective runtime configuration, as JSON.
+    pub config_sha256: String,
+    /// Module that was running, if any.
+    pub module: Option<ModuleInfo>,
+    /// Process image the snapshots were taken from.
+    pub process_image: ProcessImageConfig,
+    /// The fault.
+    pub summary: Summary,
+    /// Recorded frames, oldest first; the last one is the fault.
+    pub frames: Vec<Frame>,
+    /// Elementary program variables when the file was written.
+    pub variables: Vec<Variable>,
+    /// Most recent log lines, oldest first.
+    pub logs: Vec<String>,
+}
+
+/// The module that was running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleInfo {
+    /// Where it was loaded from.
+    pub source: String,
+    /// SHA-256 of the Wasm bytes.
+    pub sha256: String,
+}
+
+/// The fault, as summarized by the fault recorder.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// Scan in which the fault occurred.
+    pub cycle: u64,
+    /// Index of the faulting task.
+    pub task: usize,
+    /// Name of the faulting task.
+    pub task_name: String,
+    /// Fault reason (`WASM_TRAP`, `CYCLE_OVERRUN`, ...).
+    pub reason: String,
+    /// Execution time of the faulting scan.
+    pub execution_ns: u64,
+    /// Time spent reading inputs.
+    pub io_read_ns: u64,
+    /// Time spent in the logic.
+    pub logic_exec_ns: u64,
+    /// Time spent writing outputs.
+    pub io_write_ns: u64,
+    /// Whether the fieldbus working counter did not match.
+    pub wkc_mismatch: bool,
+    /// Number of frames the recorder held.
+    pub frames_available: usize,
+    /// ST source position of a trap.
+    pub source_location: Option<String>,
+}
+
+/// One recorded scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    /// Scan number.
+    pub cycle: u64,
+    /// Index of the task.
+    pub task: usize,
+    /// Time since the recorder started.
+    pub timestamp_ns: u64,
+    /// Fault reason, `NONE` for normal scans.
+    pub reason: String,
+    /// Time spent reading inputs.
+    pub io_read_ns: u64,
+    /// Time spent in the logic.
+    pub logic_exec_ns: u64,
+    /// Time spent writing outputs.
+    pub io_write_ns: u64,
+    /// Total execution time.
+    pub total_ns: u64,
+    /// Fieldbus working counter.
+    pub wkc: Option<u16>,
+    /// Expected working counter.
+    pub expected_wkc: Option<u16>,
+    /// ST source position of a trap.
+    pub source_location: Option<String>,
+    /// Input snapshot, hex.
+    pub inputs: String,
+    /// Output snapshot, hex.
+    pub outputs: String,
+}
+
+/// A program variable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variable {
+    /// Qualified name (`Main.speed`).
+    pub name: String,
+    /// Value in ST syntax.
+    pub value: String,
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+impl From<&FaultFrame> for Frame {
+    fn from(frame: &FaultFrame) -> Self {
+        let timings = &frame.phase_timings;
+        Self {
+            cycle: frame.cycle,
+            task: frame.task,
+            timestamp_ns: frame.timestamp_ns,
+            reason: frame.fault_reason.to_string(),
+            io_read_ns: nanos(timings.io_read),
+            logic_exec_ns: nanos(timings.logic_exec),
+            io_write_ns: nanos(timings.io_write),
+            total_ns: nanos(timings.total),
+            wkc: frame.wkc,
+            expected_wkc: frame.expected_wkc,
+            source_location: frame.source_location.as_ref().map(ToString::to_string),
+            inputs: to_hex(&frame.inputs),
+            outputs: to_hex(&frame.outputs),
+        }
+    }
+}
+
+impl PostMortem {
+    /// Read a post-mortem file.
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let value: serde_json::Value = serde_json::from_str(&text)
+            .with_context(|| format!("{} is not a post-mortem file", path.display()))?;
+        let version = value
+            .get("format_version")
+            .and_then(serde_json::Value::as_u64)
+            .with_context(|| format!("{} has no format version", path.display()))?;
+        if version > u64::from(FORMAT_VERSION) {
+            bail!(
+                "{} has format version {version}, newer than supported version {FORMAT_VERSION}",
+                path.display()
+            );
+        }
+        serde_json::from_value(value)
+            .with_context(|| format!("{} is not a valid post-mortem file", path.display()))
+    }
+
+    /// Write the file to `dir`, creating it if needed, and return its path.
+    ///
+    /// The contents are written and synced under a temporary name first,
+    /// then renamed into place.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let stamp: String = self
+            .created
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect();
+        let mut path = dir.join(format!("{FILE_PREFIX}{stamp}.json"));
+        let mut n = 1;
+        while path.exists() {
+            n += 1;
+            path = dir.join(format!("{FILE_PREFIX}{stamp}-{n}.json"));
+        }
+
+        let json = serde_json::to_vec_pretty(self)?;
+        let partial = dir.join(format!(
+            ".{}.partial-{}",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            std::process::id()
+        ));
+        let write = || -> std::io::Result<()> {
+            let mut file = std::fs::File::create(&partial)?;
+            file.write_all(&json)?;
+            file.sync_all()?;
+            std::fs::rename(&partial, &path)
+        };
+        if let Err(e) = write() {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+        }
+        // Make the rename itself durable; not every platform can sync a
+        // directory, so this is best effort
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+        Ok(path)
+    }
+
+    /// The frame the fault was recorded in.
+    pub fn fault_frame(&self) -> Option<&Frame> {
+        self.frames
+            .iter()
+            .rev()
+            .find(|frame| frame.reason != "NONE")
+    }
+
+    /// Whether `wasm` is the module that was running; `None` if no module
+    /// was recorded.
+    pub fn module_matches(&self, wasm: &[u8]) -> Option<bool> {
+        let module = self.module.as_ref()?;
+        Some(module.sha256 == sha256_hex(wasm))
+    }
+}
+
+/// The post-mortem files in `dir`, oldest first.
+pub fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(".json"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Lower-case hex SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        bail!("snapshot has an odd number of hex digits");
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|at| {
+            hex.get(at..at + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .context("snapshot is not valid hex")
+        })
+        .collect()
+}
+
+/// Writes the post-mortem file when the daemon faults.
+#[derive(Debug)]
+pub struct PostMortemWriter {
+    dir: Option<PathBuf>,
+    config_sha256: String,
+    process_image: ProcessImageConfig,
+    module: Option<ModuleInfo>,
+    logs: RecentLogs,
+    written: bool,
+}
+
+impl PostMortemWriter {
+    /// A writer for the daemon running `config`, taking log lines from
+    /// `logs`.
+    pub fn new(config: &RuntimeConfig, logs: RecentLogs) -> Self {
+        let config_json = serde_json::to_vec(config).unwrap_or_default();
+        Self {
+            dir: config.fault_policy.postmortem_dir.clone(),
+            config_sha256: sha256_hex(&config_json),
+            process_image: config.process_image,
+            module: None,
+            logs,
+            written: false,
+        }
+    }
+
+    /// Record the module that is now running.
+    pub fn set_module(&mut self, source: &dyn fmt::Display, wasm: &[u8]) {
+        self.module = Some(ModuleInfo {
+            source: source.to_string(),
+            sha256: sha256_hex(wasm),
+        });
+    }
+
+    /// Collect the post-mortem of the scheduler's recorded fault, if there
+    /// is one.
+    pub fn capture<E: LogicEngine>(&self, scheduler: &Scheduler<E>) -> Option<PostMortem> {
+        let recorder = scheduler.fault_recorder();
+        let summary = recorder.fault_summary()?;
+        let variables = scheduler
+            .engine
+            .symbols()
+            .map(|symbols| {
+                symbols
+                    .iter()
+                    .filter_map(|symbol| {
+                        let value = scheduler.engine.read_variable(&symbol.name).ok()?;
+                        Some(Variable {
+                            name: symbol.name.clone(),
+                            value: value.to_string(),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(PostMortem {
+            format_version: FORMAT_VERSION,
+            created: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_sha256: self.config_sha256.clone(),
+            module: self.module.clone(),
+            process_image: self.process_image,
+            summary: Summary {
+                cycle: summary.cycle,
+                task: summary.task,
+                task_name: scheduler
+                    .tasks()
+                    .get(summary.task)
+                    .map(|task| task.name().to_string())
+                    .unwrap_or_default(),
+                reason: summary.reason.to_string(),
+                execution_ns: nanos(summary.execution_time),
+                io_read_ns: nanos(summary.io_read_time),
+                logic_exec_ns: nanos(summary.logic_exec_time),
+                io_write_ns: nanos(summary.io_write_time),
+                wkc_mismatch: summary.wkc_mismatch,
+                frames_available: summary.frames_available,
+                source_location: summary.source_location.map(|location| location.to_string()),
+            },
+            frames: recorder.frames_chronological().map(Frame::from).collect(),
+            variables,
+            logs: self.logs.lines(),
+        })
+    }
+
+    /// Write the post-mortem of a recorded fault, once.
+    ///
+    /// Returns the path written, or `None` if there is no fault, no
+    /// directory is configured, or the file was already written.
+    pub fn persist<E: LogicEngine>(&mut self, scheduler: &Scheduler<E>) -> Result<Option<PathBuf>> {
+        let Some(dir) = self.dir.as_deref() else {
+            return Ok(None);
+        };
+        if self.written {
+            return Ok(None);
+        }
+        let Some(postmortem) = self.capture(scheduler) else {
+            return Ok(None);
+        };
+        self.written = true;
+        postmortem.write(dir).map(Some)
+    }
+}
+
+/// The most recent log lines, kept by a [`Layer`] of the log subscriber.
+#[derive(Debug, Clone, Default)]
+pub struct RecentLogs(Arc<Mutex<VecDeque<String>>>);
+
+impl RecentLogs {
+    /// The kept lines, oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        let lines = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        lines.iter().cloned().collect()
+    }
+
+    fn push(&self, line: String) {
+        let mut lines = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if lines.len() == RECENT_LOG_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+impl<S: Subscriber> Layer<S> for RecentLogs {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut line = LogLine::default();
+        event.record(&mut line);
+        let metadata = event.metadata();
+        self.push(format!(
+            "{} {} {}: {}{}",
+            humantime::format_rfc3339_millis(SystemTime::now()),
+            metadata.level(),
+            metadata.target(),
+            line.message,
+            line.fields
+        ));
+    }
+}
+
+/// Message and `key=value` fields of a log event.
+#[derive(Default)]
+struct LogLine {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LogLine {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+/// Decodes I/O snapshots into named values.
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    image: ProcessImageConfig,
+    layout: ProcessImageLayout,
+    symbols: SymbolTable,
+}
+
+impl Decoder {
+    /// Decode into process-image points only.
+    pub fn new(postmortem: &PostMortem) -> Self {
+        Self {
+            image: postmortem.process_image,
+            layout: ProcessImageLayout::for_config(&postmortem.process_image),
+            symbols: SymbolTable::default(),
+        }
+    }
+
+    /// Also decode the variables `wasm` places in the process image, using
+    /// its ABI layout and symbol table.
+    pub fn with_module(postmortem: &PostMortem, wasm: &[u8]) -> Result<Self> {
+        let mut decoder = Self::new(postmortem);
+        if let Some(payload) = wasm_meta::custom_section(wasm, abi::SECTION_NAME) {
+            decoder.layout = abi::decode_layout(payload)?;
+        }
+        let image_end = decoder.layout.sysinfo_offset();
+        let symbols = SymbolTable::from_wasm(wasm)?;
+        decoder.symbols = SymbolTable::new(
+            symbols
+                .iter()
+                .filter(|symbol| symbol.offset.saturating_add(symbol.size) <= image_end)
+                .cloned()
+                .collect(),
+        );
+        Ok(decoder)
+    }
+
+    /// Values in a frame: the process-image points (`%ID0`, `%IW0`, `%QD0`,
+    /// `%QW0`), then the variables placed in the process image.
+    pub fn decode(&self, frame: &Frame) -> Result<Vec<(String, String)>> {
+        let inputs = from_hex(&frame.inputs)?;
+        let outputs = from_hex(&frame.outputs)?;
+        let (di, ai) = split_snapshot(&inputs, self.image.di_words(), self.image.ai_channels());
+        let (dq, aq) = split_snapshot(&outputs, self.image.do_words(), self.image.ao_channels());
+
+        let mut values = Vec::new();
+        for (area, words, channels) in [("I", &di, &ai), ("Q", &dq, &aq)] {
+            for (index, word) in words.iter().enumerate() {
+                values.push((format!("%{area}D{index}"), format!("16#{word:08X}")));
+            }
+            for (index, channel) in channels.iter().enumerate() {
+                values.push((format!("%{area}W{index}"), channel.to_string()));
+            }
+        }
+
+        if !self.symbols.is_empty() {
+            let layout = &self.layout;
+            let mut memory = vec![0u8; layout.sysinfo_offset() as usize];
+            let mut place = |offset: u32, bytes: &mut dyn Iterator<Item = u8>| {
+                for (target, byte) in memory[offset as usize..].iter_mut().zip(bytes) {
+                    *target = byte;
+                }
+            };
+            let di_len = (layout.di_words() as usize).min(di.len());
+            let do_len = (layout.do_words() as usize).min(dq.len());
+            let ai_len = (layout.ai_channels as usize).min(ai.len());
+            let ao_len = (layout.ao_channels as usize).min(aq.len());
+            place(
+                layout.di_offset(),
+                &mut di[..di_len].iter().flat_map(|word| word.to_le_bytes()),
+            );
+            place(
+                layout.do_offset(),
+                &mut dq[..do_len].iter().flat_map(|word| word.to_le_bytes()),
+            );
+            place(
+                layout.ai_offset(),
+                &mut ai[..ai_len].iter().flat_map(|value| value.to_le_bytes()),
+            );
+            place(
+                layout.ao_offset(),
+                &mut aq[..ao_len].iter().flat_map(|value| value.to_le_bytes()),
+            );
+            for symbol in self.symbols.iter() {
+                // Aggregates have no single value
+                if let Ok(value) = self.symbols.read(&memory, &symbol.name) {
+                    values.push((symbol.name.clone(), value.to_string()));
+                }
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Digital words and analog channels of a snapshot; missing bytes read as
+/// zero.
+fn split_snapshot(snapshot: &[u8], words: usize, channels: usize) -> (Vec<u32>, Vec<i16>) {
+    let byte = |at: usize| snapshot.get(at).copied().unwrap_or(0);
+    let digital = (0..words)
+        .map(|i| u32::from_le_bytes([0, 1, 2, 3].map(|b| byte(i * 4 + b))))
+        .collect();
+    let analog = (0..channels)
+        .map(|i| i16::from_le_bytes([0, 1].map(|b| byte(words * 4 + i * 2 + b))))
+        .collect();
+    (digital, analog)
+}
+
+/// One line describing a file, for listings.
+pub fn describe(path: &Path, postmortem: &PostMortem) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut line = format!(
+        "{name}  {}  {}  cycle {}",
+        postmortem.created, postmortem.summary.reason, postmortem.summary.cycle
+    );
+    if !postmortem.summary.task_name.is_empty() {
+        let _ = write!(line, "  task {}", postmortem.summary.task_name);
+    }
+    if let Some(location) = &postmortem.summary.source_location {
+        let _ = write!(line, "  at {location}");
+    }
+    line
+}
+
+/// Full report of a file, with the last `frames` frames decoded.
+pub fn render(postmortem: &PostMortem, decoder: &Decoder, frames: usize) -> Result<String> {
+    let summary = &postmortem.summary;
+    let mut out = String::new();
+    let _ = writeln!(out, "Fault:       {}", summary.reason);
+    let _ = writeln!(out, "Created:     {}", postmortem.created);
+    let _ = writeln!(out, "Daemon:      {}", postmortem.daemon_version);
+    let _ = writeln!(out, "Cycle:       {}", summary.cycle);
+    let _ = writeln!(out, "Task:        {} ({})", summary.task, summary.task_name);
+    if let Some(location) = &summary.source_location {
+        let _ = writeln!(out, "Location:    {location}");
+    }
+    let _ = writeln!(
+        out,
+        "Timing:      {} us total, {} us read, {} us logic, {} us write",
+        summary.execution_ns / 1000,
+        summary.io_read_ns / 1000,
+        summary.logic_exec_ns / 1000,
+        summary.io_write_ns / 1000
+    );
+    if summary.wkc_mismatch {
+        let _ = writeln!(out, "WKC:         mismatch");
+    }
+    let _ = writeln!(out, "Config:      {}", postmortem.config_sha256);
+    match &postmortem.module {
+        Some(module) => {
+            let _ = writeln!(out, "Module:      {} ({})", module.sha256, module.source);
+        }
+        None => {
+            let _ = writeln!(out, "Module:      none");
+        }
+    }
+
+    let _ = writeln!(out, "\nFrames ({}):", postmortem.frames.len());
+    let _ = writeln!(
+        out,
+        "  {:>10} {:>4} {:>10} {:>10}  reason",
+        "cycle", "task", "total_us", "logic_us"
+    );
+    for frame in &postmortem.frames {
+        let _ = writeln!(
+            out,
+            "  {:>10} {:>4} {:>10} {:>10}  {}",
+            frame.cycle,
+            frame.task,
+            frame.total_ns / 1000,
+            frame.logic_exec_ns / 1000,
+            frame.reason
+        );
+    }
+
+    let skip = postmortem.frames.len().saturating_sub(frames);
+    for frame in &postmortem.frames[skip..] {
+        let _ = writeln!(out, "\nI/O at cycle {}:", frame.cycle);
+        for (name, value) in decoder.decode(frame)? {
+            let _ = writeln!(out, "  {name} = {value}");
+        }
+    }
+
+    if !postmortem.variables.is_empty() {
+        let _ = writeln!(out, "\nVariables:");
+        for variable in &postmortem.variables {
+            let _ = writeln!(out, "  {} = {}", variable.name, variable.value);
+        }
+    }
+    if !postmortem.logs.is_empty() {
+        let _ = writeln!(out, "\nLog:");
+        for line in &postmortem.logs {
+            let _ = writeln!(out, "  {line}");
+        }
+    }
+    Ok(out)
+}
+
+/// Differences between two files: header fields, variables, and the I/O
+/// of the fault frames.
+pub fn diff(
+    (a, decoder_a): (&PostMortem, &Decoder),
+    (b, decoder_b): (&PostMortem, &Decoder),
+) -> Result<String> {
+    let mut out = String::new();
+    let mut field = |name: &str, left: &str, right: &str| {
+        if left != right {
+            let _ = writeln!(out, "{name}: {left} -> {right}");
+        }
+    };
+    let module = |postmortem: &PostMortem| {
+        postmortem
+            .module
+            .as_ref()
+            .map_or_else(|| "none".to_string(), |module| module.sha256.clone())
+    };
+    let location = |postmortem: &PostMortem| {
+        postmortem
+            .summary
+            .source_location
+            .clone()
+            .unwrap_or_else(|| "-".to_string())
+    };
+    field("reason", &a.summary.reason, &b.summary.reason);
+    field(
+        "cycle",
+        &a.summary.cycle.to_string(),
+        &b.summary.cycle.to_string(),
+    );
+    field("task", &a.summary.task_name, &b.summary.task_name);
+    field("location", &location(a), &location(b));
+    field("config", &a.config_sha256, &b.config_sha256);
+    field("module", &module(a), &module(b));
+
+    let variables = |postmortem: &PostMortem| -> BTreeMap<String, String> {
+        postmortem
+            .variables
+            .iter()
+            .map(|variable| (variable.name.clone(), variable.value.clone()))
+            .collect()
+    };
+    diff_values(&mut out, "", &variables(a), &variables(b));
+
+    let fault_io = |postmortem: &PostMortem, decoder: &Decoder| -> Result<BTreeMap<_, _>> {
+        match postmortem.fault_frame() {
+            Some(frame) => Ok(decoder.decode(frame)?.into_iter().collect()),
+            None => Ok(BTreeMap::new()),
+        }
+    };
+    diff_values(
+        &mut out,
+        "fault I/O ",
+        &fault_io(a, decoder_a)?,
+        &fault_io(b, decoder_b)?,
+    );
+
+    if out.is_empty() {
+        out.push_str("No differences\n");
+    }
+    Ok(out)
+}
+
+fn diff_values(
+    out: &mut String,
+    prefix: &str,
+    a: &BTreeMap<String, String>,
+    b: &BTreeMap<String, String>,
+) {
+    for (name, left) in a {
+        match b.get(name) {
+            Some(right) if right != left => {
+                let _ = writeln!(out, "{prefix}{name}: {left} -> {right}");
+            }
+            Some(_) => {}
+            None => {
+                let _ = writeln!(out, "{prefix}{name}: {left} -> (absent)");
+            }
+        }
+    }
+    for (name, right) in b {
+        if !a.contains_key(name) {
+            let _ = writeln!(out, "{prefix}{name}: (absent) -> {right}");
+        }
+    }
+}
+
+/// The frames as CSV, one row per frame with its decoded I/O.
+pub fn export_csv(postmortem: &PostMortem, decoder: &Decoder) -> Result<String> {
+    let rows = postmortem
+        .frames
+        .iter()
+        .map(|frame| Ok((frame, decoder.decode(frame)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut out = String::from(
+        "cycle,task,timestamp_ns,reason,io_read_ns,logic_exec_ns,io_write_ns,total_ns,wkc,expected_wkc",
+    );
+    if let Some((_, values)) = rows.first() {
+        for (name, _) in values {
+            let _ = write!(out, ",{name}");
+        }
+    }
+    out.push('\n');
+    let optional = |value: Option<u16>| value.map(|v| v.to_string()).unwrap_or_default();
+    for (frame, values) in rows {
+        let _ = write!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{}",
+            frame.cycle,
+            frame.task,
+            frame.timestamp_ns,
+            frame.reason,
+            frame.io_read_ns,
+            frame.logic_exec_ns,
+            frame.io_write_ns,
+            frame.total_ns,
+            optional(frame.wkc),
+            optional(frame.expected_wkc)
+        );
+        for (_, value) in values {
+            let _ = write!(out, ",{value}");
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// The file as JSON, with every frame's I/O decoded.
+pub fn export_json(postmortem: &PostMortem, decoder: &Decoder) -> Result<String> {
+    let mut value = serde_json::to_value(postmortem)?;
+    let decoded = postmortem
+        .frames
+        .iter()
+        .map(|frame| {
+            let values: serde_json::Map<_, _> = decoder
+                .decode(frame)?
+                .into_iter()
+                .map(|(name, value)| (name, serde_json::Value::String(value)))
+                .collect();
+            Ok(serde_json::Value::Object(values))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(frames) = value.get_mut("frames").and_then(|f| f.as_array_mut()) {
+        for (frame, values) in frames.iter_mut().zip(decoded) {
+            frame["values"] = values;
+        }
+    }
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plc_common::symbols::{encode_symbols, Direction, Symbol};
+    use plc_runtime::fault_recorder::FaultReason;
+    use plc_runtime::wasm_host::NullEngine;
+
+    fn faulted_scheduler(config: &RuntimeConfig) -> Scheduler<NullEngine> {
+        let mut scheduler = Scheduler::new(NullEngine::default(), config);
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+        scheduler.run_cycle().unwrap();
+        scheduler.io.write_inputs(|data| {
+            data.digital_inputs_mut()[0] = 0x0000_0005;
+            data.analog_inputs_mut()[1] = -42;
+        });
+        scheduler
+            .enter_external_fault(FaultReason::FieldbusError, "Fieldbus down")
+            .unwrap();
+        scheduler
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("plc-postmortem-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_capture_and_round_trip() {
+        let dir = temp_dir("round-trip");
+        let config = RuntimeConfig {
+            fault_policy: plc_common::config::FaultPolicyConfig {
+                postmortem_dir: Some(dir.clone()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let logs = RecentLogs::default();
+        logs.push("INFO plc_daemon: started".into());
+        let mut writer = PostMortemWriter::new(&config, logs);
+        writer.set_module(&"module.wasm", b"\0asm");
+
+        let scheduler = Scheduler::new(NullEngine::default(), &config);
+        assert_eq!(writer.persist(&scheduler).unwrap(), None);
+
+        let scheduler = faulted_scheduler(&config);
+        let path = writer.persist(&scheduler).unwrap().unwrap();
+        assert_eq!(writer.persist(&scheduler).unwrap(), None, "written once");
+        assert_eq!(list_files(&dir).unwrap(), vec![path.clone()]);
+
+        let postmortem = PostMortem::read(&path).unwrap();
+        assert_eq!(postmortem.format_version, FORMAT_VERSION);
+        assert_eq!(postmortem.summary.reason, "FIELDBUS_ERROR");
+        assert_eq!(postmortem.summary.cycle, 1);
+        assert_eq!(postmortem.logs, ["INFO plc_daemon: started"]);
+        assert_eq!(postmortem.module_matches(b"\0asm"), Some(true));
+        assert_eq!(postmortem.module_matches(b"other"), Some(false));
+        assert_eq!(postmortem.fault_frame(), postmortem.frames.last());
+
+        let values = Decoder::new(&postmortem)
+            .decode(postmortem.fault_frame().unwrap())
+            .unwrap();
+        assert!(values.contains(&("%ID0".into(), "16#00000005".into())));
+        assert!(values.contains(&("%IW1".into(), "-42".into())));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_newer_format_rejected() {
+        let dir = temp_dir("version");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("postmortem-x.json");
+        std::fs::write(&path, r#"{"format_version": 99}"#).unwrap();
+        let error = PostMortem::read(&path).unwrap_err();
+        assert!(error.to_string().contains("newer"), "{error}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_decode_with_module_symbols() {
+        let config = RuntimeConfig::default();
+        let writer = PostMortemWriter::new(&config, RecentLogs::default());
+        let postmortem = writer.capture(&faulted_scheduler(&config)).unwrap();
+
+        let layout = ProcessImageLayout::for_config(&config.process_image);
+        let symbol = |name: &str, data_type: &str, offset: u32, size: u32| Symbol {
+            name: name.into(),
+            data_type: data_type.into(),
+            offset,
+            size,
+            direction: Direction::Global,
+            retain: false,
+        };
+        let symbols = encode_symbols(&[
+            symbol("InputBits", "DWORD", layout.di_offset(), 4),
+            symbol("Level", "INT", layout.ai_offset() + 2, 2),
+            symbol("Main.count", "INT", layout.user_data_offset, 2),
+        ]);
+        let wat = format!(
+            r#"(module (memory (export "memory") 1) (func (export "step"))
+                (@custom "plc.symbols" "{}"))"#,
+            String::from_utf8(symbols).unwrap().replace('\n', "\\n")
+        );
+        let wasm = wat::parse_str(wat).unwrap();
+
+        let decoder = Decoder::with_module(&postmortem, &wasm).unwrap();
+        let values = decoder.decode(postmortem.fault_frame().unwrap()).unwrap();
+        assert!(values.contains(&("InputBits".into(), "5".into())));
+        assert!(values.contains(&("Level".into(), "-42".into())));
+        assert!(
+            !values.iter().any(|(name, _)| name == "Main.count"),
+            "variables outside the process image are not decoded"
+        );
+
+        let csv = export_csv(&postmortem, &decoder).unwrap();
+        let header = csv.lines().next().unwrap();
+        assert!(header.starts_with("cycle,task,timestamp_ns,reason"));
+        assert!(header.ends_with("InputBits,Level"));
+        assert!(csv.lines().last().unwrap().contains("FIELDBUS_ERROR"));
+    }
+
+    #[test]
+    fn test_diff_reports_changes() {
+        let config = RuntimeConfig::default();
+        let writer = PostMortemWriter::new(&config, RecentLogs::default());
+        let a = writer.capture(&faulted_scheduler(&config)).unwrap();
+        let mut b = a.clone();
+        b.summary.reason = "WASM_TRAP".into();
+        b.variables.push(Variable {
+            name: "Main.x".into(),
+            value: "3".into(),
+        });
+        let decoder = Decoder::new(&a);
+
+        assert_eq!(
+            diff((&a, &decoder), (&a, &decoder)).unwrap(),
+            "No differences\n"
+        );
+        let report = diff((&a, &decoder), (&b, &decoder)).unwrap();
+        assert!(
+            report.contains("reason: FIELDBUS_ERROR -> WASM_TRAP"),
+            "{report}"
+        );
+        assert!(report.contains("Main.x: (absent) -> 3"), "{report}");
+    }
+
+    #[test]
+    fn test_recent_logs_bounded() {
+        let logs = RecentLogs::default();
+        for i in 0..RECENT_LOG_LINES + 5 {
+            logs.push(i.to_string());
+        }
+        let lines = logs.lines();
+        assert_eq!(lines.len(), RECENT_LOG_LINES);
+        assert_eq!(lines[0], "5");
+    }
+}
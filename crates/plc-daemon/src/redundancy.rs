@@ -0,0 +1,1350 @@
+//! Hot-standby redundancy between two runtimes.
+//!
+//! Two daemons with the same module form a pair over a [`SyncLink`]. One is
+//! primary: it runs the logic and drives the fieldbus. The other is
+//! standby: it keeps its scan cadence without running the logic and mirrors
+//! the primary's state, so that it can take over without a bump.
+//!
+//! # Roles
+//!
+//! A starting node is negotiating. When both nodes are connected they
+//! exchange `Hello`s and settle the roles the same way on both sides: a
+//! node that is already primary stays primary; otherwise a standby holding
+//! a primary's state leads, then the stronger `role` preference (primary,
+//! auto, standby), then the lower node id. A node that finds no peer within
+//! `startup_timeout` becomes primary on its own, unless it prefers standby.
+//!
+//! If two primaries meet, after a partition or two lonely start-ups, the
+//! one with the higher term wins and the other demotes itself to standby
+//! and takes the winner's state. Every node that becomes primary starts a
+//! new term, one above any it has seen.
+//!
+//! # State
+//!
+//! After every scan the primary sends a `State` frame with its scan and
+//! logic cycle counts, the latched inputs and committed outputs, and the
+//! 64-byte blocks of the logic's linear memory that changed since the last
+//! frame. The first frame after a (re)connection, a dropped frame or a
+//! resync request carries the whole memory. The standby applies frames
+//! only on top of a complete state and asks for a resync on a gap; it
+//! acknowledges each frame it applied.
+//!
+//! # Takeover
+//!
+//! A standby that hears nothing from the primary for `takeover_cycles`
+//! scans takes over: it loads the mirrored state into its scheduler, with
+//! [`Scheduler::take_over`], and runs the logic and drives the outputs in
+//! that same scan, so the scan after the primary's last one is not lost. A
+//! standby without a complete state of the same module still takes
+//! over, from its own state, and says so.
+//!
+//! Without a witness, a node cannot tell a dead peer from a broken link, and
+//! a link failure leaves two primaries until it heals; a pair only runs so
+//! with `allow_split_brain`. With a `witness` configured, driving the outputs takes a lease from it (see
+//! [`crate::witness`]): the standby only takes over, and a lonely node only
+//! starts as primary, once the witness lends it the lease. The primary
+//! renews its lease while it runs, and stops driving the outputs, falling
+//! back to standby, once it has neither the lease nor news from the standby
+//! for half the takeover time. The lease is asked for on a background
+//! thread; the scan only reads whether it is held.
+//!
+//! # Authentication
+//!
+//! Every message is sealed with an HMAC-SHA256 under the pair's shared
+//! `secret`. On each connection both nodes send a fresh nonce, and the MAC
+//! of a message covers the receiver's nonce and a counter that grows with
+//! every message, so frames cannot be forged, replayed or reflected. Frames
+//! that fail the check are dropped without touching the role.
+
+use crate::diagnostics::DiagnosticsState;
+use crate::postmortem::sha256_hex;
+use crate::sync_link::{Endpoint, LinkEvent, SyncLink};
+use crate::witness::Lease;
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use plc_common::codec::{put_bytes, Reader};
+use plc_common::config::{ProcessImageConfig, RedundancyConfig, RedundancyRole};
+use plc_runtime::io_image::ProcessData;
+use plc_runtime::scheduler::Scheduler;
+use plc_runtime::wasm_host::LogicEngine;
+use sha2::Sha256;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+/// Version of the messages between the nodes.
+const PROTOCOL_VERSION: u16 = 2;
+
+/// Granularity of memory deltas.
+const BLOCK_SIZE: usize = 64;
+
+/// Length of a connection nonce.
+pub(crate) const NONCE_LEN: usize = 16;
+
+/// Length of a MAC.
+pub(crate) const MAC_LEN: usize = 32;
+
+/// Frame with the sender's nonce for this connection, in the clear.
+const FRAME_NONCE: u8 = 0x4e;
+/// Frame with a sealed message.
+const FRAME_SEALED: u8 = 0x53;
+
+const TAG_HELLO: u8 = 1;
+const TAG_STATE: u8 = 2;
+const TAG_HEARTBEAT: u8 = 3;
+const TAG_ACK: u8 = 4;
+const TAG_RESYNC: u8 = 5;
+
+/// Role of a node in the redundant pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Waiting for the peer or for the start-up timeout.
+    Negotiating,
+    /// Runs the logic and drives the outputs.
+    Primary,
+    /// Follows the primary, ready to take over.
+    Standby,
+}
+
+impl Role {
+    /// Whether a node in this role runs the logic and drives the outputs.
+    pub fn drives_outputs(self) -> bool {
+        self == Role::Primary
+    }
+
+    /// Non-zero code, for atomics and the wire.
+    pub fn code(self) -> u8 {
+        match self {
+            Role::Negotiating => 1,
+            Role::Primary => 2,
+            Role::Standby => 3,
+        }
+    }
+
+    /// Role with `code`; `None` for 0 or an unknown code.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Role::Negotiating),
+            2 => Some(Role::Primary),
+            3 => Some(Role::Standby),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Negotiating => write!(f, "negotiating"),
+            Role::Primary => write!(f, "primary"),
+            Role::Standby => write!(f, "standby"),
+        }
+    }
+}
+
+/// What a node tells its peer about itself.
+#[derive(Debug, Clone, PartialEq)]
+struct Hello {
+    node_id: u32,
+    preferred: RedundancyRole,
+    role: Role,
+    /// A standby with the complete state of a primary.
+    holds_state: bool,
+    term: u64,
+    /// SHA-256 of the loaded module, hex.
+    module: String,
+    process_image: ProcessImageConfig,
+}
+
+/// The primary's state after a scan.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct StateFrame {
+    term: u64,
+    seq: u64,
+    scan: u64,
+    logic_cycles: u64,
+    /// The chunks cover the whole memory.
+    full: bool,
+    memory_len: u32,
+    /// Changed memory: offset and length of each chunk.
+    chunks: Vec<(u32, u32)>,
+    /// The chunks' bytes, one after another.
+    data: Vec<u8>,
+    /// Inputs and outputs, see [`pack_image`].
+    image: Vec<u8>,
+}
+
+impl StateFrame {
+    /// Offset and bytes of each chunk.
+    fn chunks(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        let mut data = self.data.as_slice();
+        self.chunks.iter().map(move |&(offset, len)| {
+            let (chunk, rest) = data.split_at(len as usize);
+            data = rest;
+            (offset, chunk)
+        })
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(TAG_STATE);
+        for value in [self.term, self.seq, self.scan, self.logic_cycles] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.push(u8::from(self.full));
+        out.extend_from_slice(&self.memory_len.to_le_bytes());
+        out.extend_from_slice(&(self.chunks.len() as u32).to_le_bytes());
+        for (offset, data) in self.chunks() {
+            out.extend_from_slice(&offset.to_le_bytes());
+            put_bytes(out, data);
+        }
+        put_bytes(out, &self.image);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Message {
+    Hello(Hello),
+    State(StateFrame),
+    /// The primary is alive but ran no scan.
+    Heartbeat {
+        term: u64,
+    },
+    /// The standby applied the frame `seq`.
+    Ack {
+        seq: u64,
+    },
+    /// The standby needs a full frame.
+    Resync,
+}
+
+impl Message {
+    /// Append the message to `out`.
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Message::Hello(hello) => {
+                out.push(TAG_HELLO);
+                out.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+                out.extend_from_slice(&hello.node_id.to_le_bytes());
+                out.push(preference_code(hello.preferred));
+                out.push(hello.role.code());
+                out.push(u8::from(hello.holds_state));
+                out.extend_from_slice(&hello.term.to_le_bytes());
+                put_bytes(out, hello.module.as_bytes());
+                let image = &hello.process_image;
+                for count in [
+                    image.digital_inputs,
+                    image.digital_outputs,
+                    image.analog_inputs,
+                    image.analog_outputs,
+                ] {
+                    out.extend_from_slice(&count.to_le_bytes());
+                }
+            }
+            Message::State(frame) => frame.encode(out),
+            Message::Heartbeat { term } => {
+                out.push(TAG_HEARTBEAT);
+                out.extend_from_slice(&term.to_le_bytes());
+            }
+            Message::Ack { seq } => {
+                out.push(TAG_ACK);
+                out.extend_from_slice(&seq.to_le_bytes());
+            }
+            Message::Resync => out.push(TAG_RESYNC),
+        }
+    }
+
+    /// Decode a message; `None` if it is malformed or of another protocol
+    /// version.
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(bytes);
+        let message = match reader.u8()? {
+            TAG_HELLO => {
+                if reader.u16()? != PROTOCOL_VERSION {
+                    return None;
+                }
+                Message::Hello(Hello {
+                    node_id: reader.u32()?,
+                    preferred: preference_from_code(reader.u8()?)?,
+                    role: Role::from_code(reader.u8()?)?,
+                    holds_state: reader.u8()? != 0,
+                    term: reader.u64()?,
+                    module: String::from_utf8(reader.bytes()?.to_vec()).ok()?,
+                    process_image: ProcessImageConfig {
+                        digital_inputs: reader.u32()?,
+                        digital_outputs: reader.u32()?,
+                        analog_inputs: reader.u32()?,
+                        analog_outputs: reader.u32()?,
+                    },
+                })
+            }
+            TAG_STATE => {
+                let term = reader.u64()?;
+                let seq = reader.u64()?;
+                let scan = reader.u64()?;
+                let logic_cycles = reader.u64()?;
+                let full = reader.u8()? != 0;
+                let memory_len = reader.u32()?;
+                let count = reader.u32()?;
+                let mut chunks = Vec::new();
+                let mut data = Vec::new();
+                for _ in 0..count {
+                    let offset = reader.u32()?;
+                    let bytes = reader.bytes()?;
+                    chunks.push((offset, bytes.len() as u32));
+                    data.extend_from_slice(bytes);
+                }
+                Message::State(StateFrame {
+                    term,
+                    seq,
+                    scan,
+                    logic_cycles,
+                    full,
+                    memory_len,
+                    chunks,
+                    data,
+                    image: reader.bytes()?.to_vec(),
+                })
+            }
+            TAG_HEARTBEAT => Message::Heartbeat {
+                term: reader.u64()?,
+            },
+            TAG_ACK => Message::Ack { seq: reader.u64()? },
+            TAG_RESYNC => Message::Resync,
+            _ => return None,
+        };
+        reader.is_empty().then_some(message)
+    }
+}
+
+/// The standby's copy of the primary's state.
+#[derive(Debug)]
+struct Mirror {
+    memory: Vec<u8>,
+    image: ProcessData,
+    scan: u64,
+    logic_cycles: u64,
+    seq: u64,
+    /// `memory` and `image` are a complete state of the primary.
+    synced: bool,
+}
+
+impl Mirror {
+    fn new(process_image: &ProcessImageConfig) -> Self {
+        Self {
+            memory: Vec::new(),
+            image: ProcessData::new(process_image),
+            scan: 0,
+            logic_cycles: 0,
+            seq: 0,
+            synced: false,
+        }
+    }
+
+    /// Apply a frame; `false` if it does not follow on the mirrored state,
+    /// which is then incomplete until the next full frame.
+    fn apply(&mut self, frame: &StateFrame) -> bool {
+        let memory_len = frame.memory_len as usize;
+        if frame.full {
+            self.memory.clear();
+            self.memory.resize(memory_len, 0);
+        } else if !self.synced || frame.seq != self.seq + 1 || self.memory.len() != memory_len {
+            self.synced = false;
+            return false;
+        }
+        for (offset, data) in frame.chunks() {
+            let start = offset as usize;
+            let Some(target) = self.memory.get_mut(start..start + data.len()) else {
+                self.synced = false;
+                return false;
+            };
+            target.copy_from_slice(data);
+        }
+        if unpack_image(&frame.image, &mut self.image).is_none() {
+            self.synced = false;
+            return false;
+        }
+        self.seq = frame.seq;
+        self.scan = frame.scan;
+        self.logic_cycles = frame.logic_cycles;
+        self.synced = true;
+        true
+    }
+}
+
+/// This node's side of a redundant pair.
+pub struct Redundancy {
+    config: RedundancyConfig,
+    process_image: ProcessImageConfig,
+    link: SyncLink,
+    status: Arc<DiagnosticsState>,
+    role: Role,
+    term: u64,
+    /// SHA-256 of the loaded module, hex.
+    module: String,
+    started: Instant,
+    connected: bool,
+    /// The connected peer's last `Hello`.
+    peer: Option<Hello>,
+    /// When the peer was last heard from.
+    last_heard: Instant,
+    /// Primary: the memory as last sent.
+    shadow: Vec<u8>,
+    /// Primary: the next frame must be full.
+    resync: bool,
+    seq: u64,
+    published_scan: Option<u64>,
+    last_ack: Option<Instant>,
+    inputs: ProcessData,
+    outputs: ProcessData,
+    /// Standby: the primary's state.
+    mirror: Mirror,
+    resync_requested: bool,
+    /// The peer runs another module or process image.
+    peer_mismatch: bool,
+    /// Shared secret the messages are sealed with.
+    secret: Vec<u8>,
+    /// Our nonce on the current connection; the peer seals for it. No
+    /// message is accepted without one.
+    nonce: Option<[u8; NONCE_LEN]>,
+    /// The peer's nonce on the current connection; nothing is sent before
+    /// it arrives.
+    peer_nonce: Option<[u8; NONCE_LEN]>,
+    /// Counter of the last message sent.
+    sent: u64,
+    /// Primary: the frame sent after the last scan; its buffers are reused.
+    frame: StateFrame,
+    /// The message being sent, encoded.
+    body: Vec<u8>,
+    /// Counter of the last message accepted.
+    received: u64,
+    /// A frame that failed authentication was reported.
+    forged_warned: bool,
+    witness: Option<Lease>,
+    /// Waiting for the witness' lease was reported.
+    witness_warned: bool,
+}
+
+impl fmt::Debug for Redundancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redundancy")
+            .field("node_id", &self.config.node_id)
+            .field("role", &self.role)
+            .field("term", &self.term)
+            .field("connected", &self.connected)
+            .finish()
+    }
+}
+
+impl Redundancy {
+    /// Open the link to the peer and start negotiating.
+    pub fn start(
+        config: &RedundancyConfig,
+        process_image: &ProcessImageConfig,
+        status: Arc<DiagnosticsState>,
+    ) -> Result<Self> {
+        let link = match (&config.listen, &config.connect) {
+            (Some(listen), None) => SyncLink::listen(&Endpoint::parse(listen))
+                .with_context(|| format!("Failed to listen for the redundant peer on {listen}"))?,
+            (None, Some(connect)) => SyncLink::connect(&Endpoint::parse(connect)),
+            _ => bail!("Redundancy needs exactly one of 'listen' and 'connect'"),
+        };
+        let Some(secret) = config.secret.as_deref() else {
+            bail!("Redundancy needs a shared 'secret'");
+        };
+        let secret = secret.as_bytes().to_vec();
+        nonce().context("No random source for the redundant link's nonces")?;
+        if config.witness.is_none() {
+            if !config.allow_split_brain {
+                bail!("Redundancy needs a 'witness', or 'allow_split_brain' to run without one");
+            }
+            warn!(
+                "REDUNDANCY WITHOUT A WITNESS: a broken link between the nodes leaves both \
+                 driving the outputs (allow_split_brain = true)"
+            );
+        }
+        info!(
+            node_id = config.node_id,
+            preferred = ?config.role,
+            "Redundancy enabled, negotiating the role"
+        );
+        let now = Instant::now();
+        let redundancy = Self {
+            config: config.clone(),
+            process_image: *process_image,
+            link,
+            status,
+            role: Role::Negotiating,
+            term: 0,
+            module: String::new(),
+            started: now,
+            connected: false,
+            peer: None,
+            last_heard: now,
+            shadow: Vec::new(),
+            resync: true,
+            seq: 0,
+            published_scan: None,
+            last_ack: None,
+            inputs: ProcessData::new(process_image),
+            outputs: ProcessData::new(process_image),
+            mirror: Mirror::new(process_image),
+            resync_requested: false,
+            peer_mismatch: false,
+            witness: config
+                .witness
+                .as_deref()
+                .map(|witness| Lease::start(witness, config.node_id, &secret)),
+            secret,
+            nonce: None,
+            peer_nonce: None,
+            sent: 0,
+            frame: StateFrame::default(),
+            body: Vec::new(),
+            received: 0,
+            forged_warned: false,
+            witness_warned: false,
+        };
+        redundancy.update_status();
+        Ok(redundancy)
+    }
+
+    /// Role of this node.
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Note the module now loaded, at start-up and after an online change.
+    ///
+    /// The standby only mirrors a primary running the same module.
+    pub fn set_module(&mut self, wasm_bytes: &[u8]) {
+        self.module = sha256_hex(wasm_bytes);
+        self.mirror.synced = false;
+        self.resync = true;
+        if let Some(peer) = self.peer.take() {
+            self.check_peer(&peer);
+            self.peer = Some(peer);
+        }
+        self.send_hello();
+    }
+
+    /// Handle what the peer sent and settle the role; call once per scan,
+    /// before deciding whether to run the logic.
+    ///
+    /// A standby that missed the primary for too long takes over here,
+    /// loading the mirrored state into `scheduler`; it is then primary and
+    /// runs the logic in this scan.
+    pub fn poll<E: LogicEngine>(&mut self, scheduler: &mut Scheduler<E>) {
+        while let Some(event) = self.link.try_event() {
+            match event {
+                LinkEvent::Connected => {
+                    info!("Redundant peer connected");
+                    self.connected = true;
+                    self.peer_nonce = None;
+                    self.sent = 0;
+                    self.received = 0;
+                    self.nonce = match nonce() {
+                        Ok(nonce) => Some(nonce),
+                        Err(e) => {
+                            error!(error = %e, "No random nonce for the redundant link, ignoring the peer");
+                            None
+                        }
+                    };
+                    if let Some(nonce) = &self.nonce {
+                        let mut frame = vec![FRAME_NONCE];
+                        frame.extend_from_slice(nonce);
+                        self.link.send(frame);
+                    }
+                }
+                LinkEvent::Disconnected => {
+                    warn!(role = %self.role, "Redundant peer disconnected");
+                    self.connected = false;
+                    self.peer = None;
+                    self.peer_nonce = None;
+                    self.last_ack = None;
+                }
+                LinkEvent::Frame(bytes) => self.on_frame(&bytes, scheduler),
+            }
+        }
+        if self.role == Role::Standby {
+            // Without the witness' lease, the primary runs on news from us
+            self.send(&Message::Heartbeat { term: self.term });
+        }
+
+        let takeover_after = scheduler.cycle_period() * self.config.takeover_cycles;
+        let mut wants_lease = false;
+        match self.role {
+            Role::Negotiating
+                if self.peer.is_none()
+                    && self.config.role != RedundancyRole::Standby
+                    && self.started.elapsed() >= self.config.startup_timeout =>
+            {
+                wants_lease = true;
+                if self.lease_held() {
+                    self.become_primary("no peer within the start-up timeout");
+                } else {
+                    self.warn_no_lease("No peer and no lease from the witness, not starting");
+                }
+            }
+            Role::Standby if self.last_heard.elapsed() >= takeover_after => {
+                wants_lease = true;
+                if self.lease_held() {
+                    self.take_over(scheduler, "primary silent");
+                } else {
+                    self.warn_no_lease(
+                        "Lost the primary and no lease from the witness, staying standby",
+                    );
+                }
+            }
+            Role::Primary
+                if !self.lease_held() && self.last_heard.elapsed() >= takeover_after / 2 =>
+            {
+                error!(
+                    "Lost the standby and the witness' lease, stopping the outputs to leave them to one node"
+                );
+                self.become_standby();
+            }
+            _ => {}
+        }
+        if let Some(witness) = &self.witness {
+            witness.set_wanted(wants_lease || self.role == Role::Primary);
+        }
+        if self.role == Role::Primary
+            && self
+                .last_ack
+                .is_some_and(|ack| ack.elapsed() >= takeover_after)
+        {
+            self.last_ack = None;
+        }
+        self.update_status();
+    }
+
+    /// Send the state after a scan to the standby; call once per scan on
+    /// the primary, after the logic ran.
+    pub fn publish<E: LogicEngine>(&mut self, scheduler: &Scheduler<E>) {
+        if self.role != Role::Primary || !self.connected {
+            return;
+        }
+        let scan = scheduler.cycle_count();
+        if self.published_scan == Some(scan) && !self.resync {
+            // Paused in DEBUG: nothing new, only show we are alive
+            self.send(&Message::Heartbeat { term: self.term });
+            return;
+        }
+
+        let memory = scheduler.engine.memory().unwrap_or(&[]);
+        let frame = &mut self.frame;
+        diff(&mut self.shadow, memory, self.resync, frame);
+        scheduler.io.read_inputs_into(&mut self.inputs);
+        scheduler.io.read_outputs_into(&mut self.outputs);
+        frame.image.clear();
+        pack_image(&self.inputs, &self.outputs, &mut frame.image);
+
+        self.seq += 1;
+        frame.term = self.term;
+        frame.seq = self.seq;
+        frame.scan = scan;
+        frame.logic_cycles = scheduler
+            .engine
+            .system_info()
+            .map_or(0, |info| info.cycle_count);
+        self.body.clear();
+        frame.encode(&mut self.body);
+        // A dropped frame leaves a gap only a full frame closes
+        self.resync = !self.send_body();
+        self.published_scan = Some(scan);
+    }
+
+    fn on_frame<E: LogicEngine>(&mut self, bytes: &[u8], scheduler: &mut Scheduler<E>) {
+        if let Some((&FRAME_NONCE, nonce)) = bytes.split_first() {
+            if let Ok(nonce) = <[u8; NONCE_LEN]>::try_from(nonce) {
+                self.peer_nonce = Some(nonce);
+                self.resync = true;
+                self.send_hello();
+                return;
+            }
+        }
+        let Some(nonce) = &self.nonce else {
+            return;
+        };
+        match open(&self.secret, nonce, bytes) {
+            Some((counter, body)) if counter > self.received => match Message::decode(body) {
+                Some(message) => {
+                    self.received = counter;
+                    self.last_heard = Instant::now();
+                    self.forged_warned = false;
+                    self.witness_warned = false;
+                    self.handle(message, scheduler);
+                }
+                None => warn!(
+                    bytes = bytes.len(),
+                    "Unreadable message from the redundant peer"
+                ),
+            },
+            _ => {
+                if !self.forged_warned {
+                    warn!(
+                        bytes = bytes.len(),
+                        "Dropped a message that failed authentication; do both nodes have the same secret?"
+                    );
+                    self.forged_warned = true;
+                }
+            }
+        }
+    }
+
+    fn handle<E: LogicEngine>(&mut self, message: Message, scheduler: &mut Scheduler<E>) {
+        match message {
+            Message::Hello(hello) => self.on_hello(hello, scheduler),
+            Message::State(frame) => self.on_state(&frame),
+            Message::Heartbeat { .. } => {}
+            Message::Ack { .. } => self.last_ack = Some(Instant::now()),
+            Message::Resync => self.resync = true,
+        }
+    }
+
+    fn on_hello<E: LogicEngine>(&mut self, hello: Hello, scheduler: &mut Scheduler<E>) {
+        if hello.node_id == self.config.node_id {
+            error!(
+                node_id = hello.node_id,
+                "Redundant peer has the same node id, ignoring it"
+            );
+            self.peer = None;
+            return;
+        }
+        self.check_peer(&hello);
+
+        let mine = self.hello();
+        match (self.role, hello.role) {
+            (Role::Primary, Role::Primary) => {
+                if wins_conflict(&mine, &hello) {
+                    warn!(
+                        peer = hello.node_id,
+                        term = self.term,
+                        "Two primaries, the peer yields"
+                    );
+                } else {
+                    error!(
+                        peer = hello.node_id,
+                        peer_term = hello.term,
+                        term = self.term,
+                        "Two primaries, yielding to the peer"
+                    );
+                    self.term = hello.term;
+                    self.become_standby();
+                }
+            }
+            // A new follower gets the whole state
+            (Role::Primary, _) => {
+                if !(hello.holds_state && hello.term == self.term) {
+                    self.resync = true;
+                }
+            }
+            (role, Role::Primary) => {
+                self.term = self.term.max(hello.term);
+                if role != Role::Standby {
+                    self.become_standby();
+                }
+            }
+            (role, _) => {
+                if leads(&mine, &hello) {
+                    self.term = self.term.max(hello.term);
+                    if role == Role::Standby {
+                        self.take_over(scheduler, "peer is not primary");
+                    } else {
+                        self.become_primary("negotiated with the peer");
+                    }
+                } else if role == Role::Negotiating {
+                    self.become_standby();
+                }
+            }
+        }
+        self.peer = Some(hello);
+    }
+
+    /// Whether the standby can mirror `peer`; the mirrored state is
+    /// dropped if not.
+    fn check_peer(&mut self, peer: &Hello) {
+        let mismatch = peer.module != self.module || peer.process_image != self.process_image;
+        if mismatch {
+            if !self.peer_mismatch {
+                warn!(
+                    peer = peer.node_id,
+                    "Redundant peer runs another module or process image, a takeover will not be bumpless"
+                );
+            }
+            self.mirror.synced = false;
+        }
+        self.peer_mismatch = mismatch;
+    }
+
+    fn on_state(&mut self, frame: &StateFrame) {
+        if self.role != Role::Standby || self.peer_mismatch {
+            return;
+        }
+        let was_synced = self.mirror.synced;
+        if self.mirror.apply(frame) {
+            self.resync_requested = false;
+            self.send(&Message::Ack { seq: frame.seq });
+        } else if !self.resync_requested {
+            self.resync_requested = self.send(&Message::Resync);
+        }
+        if self.mirror.synced != was_synced {
+            // The peer weighs whether we hold a state when the roles are open
+            self.send_hello();
+        }
+    }
+
+    fn take_over<E: LogicEngine>(&mut self, scheduler: &mut Scheduler<E>, reason: &str) {
+        if self.mirror.synced {
+            let mirror = &self.mirror;
+            if let Err(e) = scheduler.take_over(
+                &mirror.memory,
+                mirror.scan,
+                mirror.logic_cycles,
+                &mirror.image,
+            ) {
+                error!(error = %e, "Failed to load the primary's state, continuing from own state");
+            }
+        } else {
+            warn!("No complete state of the primary, taking over from own state; this is not bumpless");
+        }
+        self.status.record_takeover();
+        self.become_primary(reason);
+    }
+
+    fn become_primary(&mut self, reason: &str) {
+        let peer_term = self.peer.as_ref().map_or(0, |peer| peer.term);
+        self.term = self.term.max(peer_term) + 1;
+        self.role = Role::Primary;
+        self.resync = true;
+        self.published_scan = None;
+        self.last_ack = None;
+        self.mirror.synced = false;
+        self.witness_warned = false;
+        info!(term = self.term, reason, "Redundancy role: primary");
+        self.send_hello();
+        self.update_status();
+    }
+
+    fn become_standby(&mut self) {
+        self.role = Role::Standby;
+        self.mirror.synced = false;
+        self.resync_requested = false;
+        self.last_heard = Instant::now();
+        info!(term = self.term, "Redundancy role: standby");
+        self.send_hello();
+        self.update_status();
+    }
+
+    fn hello(&self) -> Hello {
+        Hello {
+            node_id: self.config.node_id,
+            preferred: self.config.role,
+            role: self.role,
+            holds_state: self.role == Role::Standby && self.mirror.synced,
+            term: self.term,
+            module: self.module.clone(),
+            process_image: self.process_image,
+        }
+    }
+
+    fn send_hello(&mut self) {
+        if self.connected {
+            self.send(&Message::Hello(self.hello()));
+        }
+    }
+
+    fn send(&mut self, message: &Message) -> bool {
+        self.body.clear();
+        message.encode(&mut self.body);
+        self.send_body()
+    }
+
+    /// Seal the encoded message in `body` for the peer and queue it.
+    fn send_body(&mut self) -> bool {
+        let Some(peer_nonce) = &self.peer_nonce else {
+            return false;
+        };
+        self.sent += 1;
+        let mut frame = self.link.buffer();
+        seal(&self.secret, peer_nonce, self.sent, &self.body, &mut frame);
+        self.link.send(frame)
+    }
+
+    /// Whether the witness lends this node the lease; always when the pair
+    /// runs without one (`allow_split_brain`).
+    fn lease_held(&self) -> bool {
+        self.witness.as_ref().is_none_or(Lease::held)
+    }
+
+    fn warn_no_lease(&mut self, message: &str) {
+        if !self.witness_warned {
+            let witness = self.witness.as_ref().map_or("", Lease::address);
+            warn!(witness, "{message}");
+            self.witness_warned = true;
+        }
+    }
+
+    fn update_status(&self) {
+        let in_sync = match self.role {
+            Role::Primary => self.last_ack.is_some(),
+            Role::Standby => self.mirror.synced,
+            Role::Negotiating => false,
+        };
+        self.status
+            .set_redundancy(self.role, self.connected, in_sync);
+    }
+}
+
+/// Whether `me` leads a pair in which neither node is primary.
+fn leads(me: &Hello, peer: &Hello) -> bool {
+    if me.holds_state != peer.holds_state {
+        return me.holds_state;
+    }
+    let rank = |role: RedundancyRole| match role {
+        RedundancyRole::Primary => 2,
+        RedundancyRole::Auto => 1,
+        RedundancyRole::Standby => 0,
+    };
+    match rank(me.preferred).cmp(&rank(peer.preferred)) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => me.node_id < peer.node_id,
+    }
+}
+
+/// Whether `me` stays primary when it meets another primary.
+fn wins_conflict(me: &Hello, peer: &Hello) -> bool {
+    match me.term.cmp(&peer.term) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => leads(me, peer),
+    }
+}
+
+/// Seal `message` for the peer into `frame`, replacing what was there: a
+/// counter, the message and a MAC bound to the peer's nonce.
+fn seal(secret: &[u8], peer_nonce: &[u8], counter: u64, message: &[u8], frame: &mut Vec<u8>) {
+    frame.clear();
+    frame.reserve(1 + 8 + message.len() + MAC_LEN);
+    frame.push(FRAME_SEALED);
+    frame.extend_from_slice(&counter.to_le_bytes());
+    frame.extend_from_slice(message);
+    let tag = mac(secret, &[b"plc-sync", peer_nonce, frame]).finalize();
+    frame.extend_from_slice(&tag.into_bytes());
+}
+
+/// Counter and message of a frame sealed for `nonce`; `None` if it was not
+/// sealed with `secret` for this connection.
+fn open<'a>(secret: &[u8], nonce: &[u8], frame: &'a [u8]) -> Option<(u64, &'a [u8])> {
+    let (sealed, tag) = frame.split_at(frame.len().checked_sub(MAC_LEN)?);
+    mac(secret, &[b"plc-sync", nonce, sealed])
+        .verify_slice(tag)
+        .ok()?;
+    let mut reader = Reader::new(sealed);
+    if reader.u8()? != FRAME_SEALED {
+        return None;
+    }
+    let counter = reader.u64()?;
+    Some((counter, reader.rest()))
+}
+
+/// HMAC-SHA256 of the concatenated `parts` under `secret`; finalize it for
+/// the tag, or check a tag with `verify_slice`, which takes constant time.
+pub(crate) fn mac(secret: &[u8], parts: &[&[u8]]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+/// A fresh nonce from the operating system's random source.
+pub(crate) fn nonce() -> Result<[u8; NONCE_LEN], getrandom::Error> {
+    let mut nonce = [0; NONCE_LEN];
+    getrandom::getrandom(&mut nonce)?;
+    Ok(nonce)
+}
+
+/// Put the changes of `memory` against `shadow`, which is brought up to
+/// date, in the memory fields of `frame`, replacing what was there.
+///
+/// The frame is full, one chunk of the whole memory, if `full` or the size
+/// changed.
+fn diff(shadow: &mut Vec<u8>, memory: &[u8], full: bool, frame: &mut StateFrame) {
+    frame.chunks.clear();
+    frame.data.clear();
+    frame.memory_len = memory.len() as u32;
+    frame.full = full || shadow.len() != memory.len();
+    if frame.full {
+        shadow.clear();
+        shadow.extend_from_slice(memory);
+        frame.chunks.push((0, memory.len() as u32));
+        frame.data.extend_from_slice(memory);
+        return;
+    }
+    let mut offset = 0;
+    for (new, old) in memory.chunks(BLOCK_SIZE).zip(shadow.chunks_mut(BLOCK_SIZE)) {
+        if new != old {
+            old.copy_from_slice(new);
+            match frame.chunks.last_mut() {
+                // Adjacent blocks go in one chunk
+                Some((start, len)) if (*start + *len) as usize == offset => {
+                    *len += new.len() as u32;
+                }
+                _ => frame.chunks.push((offset as u32, new.len() as u32)),
+            }
+            frame.data.extend_from_slice(new);
+        }
+        offset += new.len();
+    }
+}
+
+/// Pack the inputs of `inputs` and outputs of `outputs`: digital inputs,
+/// analog inputs, digital outputs, analog outputs, little-endian.
+fn pack_image(inputs: &ProcessData, outputs: &ProcessData, out: &mut Vec<u8>) {
+    for word in inputs.digital_inputs() {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    for channel in inputs.analog_inputs() {
+        out.extend_from_slice(&channel.to_le_bytes());
+    }
+    for word in outputs.digital_outputs() {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    for channel in outputs.analog_outputs() {
+        out.extend_from_slice(&channel.to_le_bytes());
+    }
+}
+
+/// Unpack [`pack_image`] output into `image`; `None` if the sizes differ.
+fn unpack_image(bytes: &[u8], image: &mut ProcessData) -> Option<()> {
+    let mut reader = Reader::new(bytes);
+    for word in image.digital_inputs_mut() {
+        *word = reader.u32()?;
+    }
+    for channel in image.analog_inputs_mut() {
+        *channel = reader.u16()? as i16;
+    }
+    for word in image.digital_outputs_mut() {
+        *word = reader.u32()?;
+    }
+    for channel in image.analog_outputs_mut() {
+        *channel = reader.u16()? as i16;
+    }
+    reader.is_empty().then_some(())
+}
+
+fn preference_code(role: RedundancyRole) -> u8 {
+    match role {
+        RedundancyRole::Auto => 0,
+        RedundancyRole::Primary => 1,
+        RedundancyRole::Standby => 2,
+    }
+}
+
+fn preference_from_code(code: u8) -> Option<RedundancyRole> {
+    match code {
+        0 => Some(RedundancyRole::Auto),
+        1 => Some(RedundancyRole::Primary),
+        2 => Some(RedundancyRole::Standby),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plc_common::config::RuntimeConfig;
+    use plc_runtime::scheduler::SchedulerBuilder;
+    use plc_runtime::signing::encode_hex;
+    use plc_runtime::wasm_host::WasmtimeHost;
+    use std::time::Duration;
+
+    /// Counts scans in memory at `COUNTER`.
+    const COUNTER: i32 = 60_000;
+
+    fn counter_module() -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "step")
+                    (i32.store (i32.const {COUNTER})
+                        (i32.add (i32.load (i32.const {COUNTER})) (i32.const 1)))))"#
+        ))
+        .unwrap()
+    }
+
+    fn hello(node_id: u32, preferred: RedundancyRole, role: Role, term: u64) -> Hello {
+        Hello {
+            node_id,
+            preferred,
+            role,
+            holds_state: false,
+            term,
+            module: "abc".into(),
+            process_image: ProcessImageConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_message_roundtrip() {
+        let messages = [
+            Message::Hello(hello(2, RedundancyRole::Standby, Role::Negotiating, 7)),
+            Message::State(StateFrame {
+                term: 3,
+                seq: 9,
+                scan: 100,
+                logic_cycles: 99,
+                full: false,
+                memory_len: 256,
+                chunks: vec![(0, 64), (192, 64)],
+                data: [[1; 64], [2; 64]].concat(),
+                image: vec![5; 12],
+            }),
+            Message::Heartbeat { term: 3 },
+            Message::Ack { seq: 9 },
+            Message::Resync,
+        ];
+        for message in messages {
+            let mut bytes = Vec::new();
+            message.encode(&mut bytes);
+            assert_eq!(Message::decode(&bytes), Some(message));
+        }
+        assert_eq!(Message::decode(&[]), None);
+        assert_eq!(Message::decode(&[TAG_ACK, 1]), None);
+        assert_eq!(Message::decode(&[0xff]), None);
+    }
+
+    #[test]
+    fn test_memory_delta_applies_on_mirror() {
+        let config = ProcessImageConfig::default();
+        let mut memory = vec![0u8; 1024];
+        let mut shadow = Vec::new();
+        let mut mirror = Mirror::new(&config);
+        let mut image = ProcessData::new(&config);
+        image.write_do(3, true);
+        image.write_ao(1, -5);
+
+        let frame = |seq: u64, shadow: &mut Vec<u8>, memory: &[u8], full: bool| {
+            let mut frame = StateFrame {
+                term: 1,
+                seq,
+                scan: seq,
+                logic_cycles: seq,
+                ..StateFrame::default()
+            };
+            diff(shadow, memory, full, &mut frame);
+            pack_image(&image, &image, &mut frame.image);
+            frame
+        };
+
+        // A delta without a complete state is refused
+        memory[10] = 1;
+        let delta = frame(1, &mut shadow, &memory, false);
+        assert!(delta.full, "the first frame is full");
+        assert!(!mirror.apply(&StateFrame {
+            full: false,
+            ..delta.clone()
+        }));
+        assert!(mirror.apply(&delta));
+        assert_eq!(mirror.memory, memory);
+        assert_eq!(mirror.image.digital_outputs(), image.digital_outputs());
+        assert_eq!(mirror.image.analog_outputs()[1], -5);
+
+        // Only changed blocks travel, adjacent ones merged
+        memory[70] = 2;
+        memory[130] = 3;
+        memory[900] = 4;
+        let delta = frame(2, &mut shadow, &memory, false);
+        assert!(!delta.full);
+        assert_eq!(delta.chunks, [(64, 128), (896, 64)]);
+        assert_eq!(delta.data.len(), 192);
+        assert!(mirror.apply(&delta));
+        assert_eq!(mirror.memory, memory);
+
+        // A gap needs a full frame
+        memory[0] = 5;
+        let skipped = frame(4, &mut shadow, &memory, false);
+        assert!(!mirror.apply(&skipped));
+        assert!(!mirror.synced);
+        let full = frame(5, &mut shadow, &memory, true);
+        assert!(mirror.apply(&full));
+        assert_eq!(mirror.memory, memory);
+    }
+
+    #[test]
+    fn test_role_decisions() {
+        use RedundancyRole::{Auto, Primary, Standby};
+
+        // Preference, then the lower node id
+        let a = hello(1, Auto, Role::Negotiating, 0);
+        let b = hello(2, Primary, Role::Negotiating, 0);
+        assert!(!leads(&a, &b) && leads(&b, &a));
+        let b = hello(2, Auto, Role::Negotiating, 0);
+        assert!(leads(&a, &b) && !leads(&b, &a));
+        let b = hello(2, Standby, Role::Negotiating, 0);
+        assert!(leads(&a, &b));
+
+        // A standby with the primary's state leads regardless
+        let b = Hello {
+            holds_state: true,
+            ..hello(2, Standby, Role::Standby, 3)
+        };
+        assert!(!leads(&a, &b) && leads(&b, &a));
+
+        // Two primaries: the newer term stays
+        let a = hello(1, Primary, Role::Primary, 2);
+        let b = hello(2, Auto, Role::Primary, 3);
+        assert!(!wins_conflict(&a, &b) && wins_conflict(&b, &a));
+        let b = hello(2, Auto, Role::Primary, 2);
+        assert!(wins_conflict(&a, &b) && !wins_conflict(&b, &a));
+    }
+
+    #[test]
+    fn test_mac_matches_rfc_4231() {
+        let tag = mac(b"Jefe", &[b"what do ya want ", b"for nothing?"])
+            .finalize()
+            .into_bytes();
+        assert_eq!(
+            encode_hex(&tag),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let long_key = [0xaa; 131];
+        let tag = mac(
+            &long_key,
+            &[b"Test Using Larger Than Block-Size Key - Hash Key First"],
+        )
+        .finalize()
+        .into_bytes();
+        assert_eq!(
+            encode_hex(&tag),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_sealed_frames_are_authenticated() {
+        let secret = b"0123456789abcdef";
+        let ours = nonce().unwrap();
+        let mut message = Vec::new();
+        Message::Hello(hello(2, RedundancyRole::Primary, Role::Primary, 99)).encode(&mut message);
+        let mut frame = Vec::new();
+        seal(secret, &ours, 7, &message, &mut frame);
+        assert_eq!(open(secret, &ours, &frame), Some((7, message.as_slice())));
+
+        // Another secret, another connection or a changed byte: refused
+        assert_eq!(open(b"another secret!!", &ours, &frame), None);
+        assert_eq!(open(secret, &nonce().unwrap(), &frame), None);
+        let mut altered = frame.clone();
+        altered[12] ^= 1;
+        assert_eq!(open(secret, &ours, &altered), None);
+        assert_eq!(open(secret, &ours, &frame[..MAC_LEN - 1]), None);
+    }
+
+    #[test]
+    fn test_standby_takes_over_where_primary_stopped() {
+        let path = std::env::temp_dir().join(format!("plc-redundancy-{}.sock", std::process::id()));
+        let address = format!("unix:{}", path.display());
+        let config = RuntimeConfig {
+            cycle_time: Duration::from_millis(2),
+            ..Default::default()
+        };
+        let node = |node_id: u32, listen: bool| RedundancyConfig {
+            enabled: true,
+            node_id,
+            role: if listen {
+                RedundancyRole::Primary
+            } else {
+                RedundancyRole::Auto
+            },
+            listen: listen.then(|| address.clone()),
+            connect: (!listen).then(|| address.clone()),
+            takeover_cycles: 5,
+            startup_timeout: Duration::from_secs(5),
+            secret: Some("0123456789abcdef".into()),
+            witness: None,
+            allow_split_brain: true,
+        };
+        let wasm = counter_module();
+        let scheduler = || {
+            let mut host = WasmtimeHost::from_runtime_config(&config).unwrap();
+            host.load_module(&wasm).unwrap();
+            let mut scheduler = SchedulerBuilder::new(host).config(config.clone()).build();
+            scheduler.initialize().unwrap();
+            scheduler.start().unwrap();
+            scheduler
+        };
+        let status_a = Arc::new(DiagnosticsState::new());
+        let status_b = Arc::new(DiagnosticsState::new());
+        let mut a = Redundancy::start(&node(1, true), &config.process_image, Arc::clone(&status_a))
+            .unwrap();
+        let mut b = Redundancy::start(
+            &node(2, false),
+            &config.process_image,
+            Arc::clone(&status_b),
+        )
+        .unwrap();
+        a.set_module(&wasm);
+        b.set_module(&wasm);
+        let mut scheduler_a = scheduler();
+        let mut scheduler_b = scheduler();
+
+        let scan = |redundancy: &mut Redundancy, scheduler: &mut Scheduler<WasmtimeHost>| {
+            redundancy.poll(scheduler);
+            if redundancy.role().drives_outputs() {
+                scheduler.run_cycle().unwrap();
+                redundancy.publish(scheduler);
+            } else {
+                scheduler.idle_cycle();
+            }
+        };
+        let counter = |scheduler: &Scheduler<WasmtimeHost>| {
+            let memory = scheduler.engine.memory().unwrap();
+            let at = COUNTER as usize;
+            u32::from_le_bytes(memory[at..at + 4].try_into().unwrap())
+        };
+
+        // Node 1 prefers primary; run until node 2 mirrors it
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !(b.role() == Role::Standby && b.mirror.synced && counter(&scheduler_a) >= 20) {
+            assert!(Instant::now() < deadline, "pair did not sync: {a:?} {b:?}");
+            scan(&mut a, &mut scheduler_a);
+            scan(&mut b, &mut scheduler_b);
+        }
+        assert_eq!(a.role(), Role::Primary);
+        assert_eq!(
+            counter(&scheduler_b),
+            0,
+            "the standby does not run the logic"
+        );
+        assert_eq!(status_b.redundancy().unwrap().role, Role::Standby);
+
+        // The primary stops; the standby runs the next scan where it stopped,
+        // in the scan it takes over in
+        let last = counter(&scheduler_a);
+        let last_scan = scheduler_a.cycle_count();
+        while b.role() != Role::Primary {
+            assert!(Instant::now() < deadline, "standby did not take over");
+            scan(&mut b, &mut scheduler_b);
+        }
+        assert_eq!(status_b.redundancy().unwrap().takeovers, 1);
+        assert_eq!(counter(&scheduler_b), last + 1);
+        assert_eq!(scheduler_b.cycle_count(), last_scan + 1);
+        scan(&mut b, &mut scheduler_b);
+        assert_eq!(counter(&scheduler_b), last + 2);
+        assert_eq!(scheduler_b.cycle_count(), last_scan + 2);
+
+        // The stalled primary yields to the newer term when it comes back
+        a.poll(&mut scheduler_a);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while a.role() != Role::Standby {
+            assert!(Instant::now() < deadline, "old primary did not yield");
+            scan(&mut b, &mut scheduler_b);
+            a.poll(&mut scheduler_a);
+        }
+        assert_eq!(b.role(), Role::Primary);
+    }
+}
@@ -0,0 +1,446 @@
+//! Connection between the two nodes of a redundant pair.
+//!
+//! A [`SyncLink`] keeps one stream to the peer, over TCP or a Unix socket:
+//! the listening node accepts it, the connecting node dials and redials
+//! until it gets through. Messages are opaque, length-prefixed frames.
+//!
+//! The link runs on its own thread so the scan never waits for the network.
+//! Outgoing frames are queued on a bounded channel and dropped when it is
+//! full or no peer is connected; incoming frames and connection changes are
+//! delivered as [`LinkEvent`]s. The link thread hands the buffers of sent
+//! frames back through [`SyncLink::buffer`], so a steady stream of frames
+//! does not allocate.
+
+use std::fmt;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Frames queued for sending.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Largest frame accepted from the peer.
+const MAX_FRAME_BYTES: usize = 64 * 1024 * 1024;
+
+/// How often the link thread checks for shutdown while idle.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Delay between connection attempts.
+const RETRY_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Longest a write may block before the peer is considered gone.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Address of a node: `host:port` for TCP or `unix:<path>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// TCP address.
+    Tcp(String),
+    /// Unix domain socket path.
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    /// Parse an address as written in the configuration.
+    pub fn parse(address: &str) -> Self {
+        match address.strip_prefix("unix:") {
+            Some(path) => Endpoint::Unix(PathBuf::from(path)),
+            None => Endpoint::Tcp(address.trim_start_matches("tcp://").to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(address) => write!(f, "{address}"),
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// What happened on the link.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// A peer connected; frames queued before were discarded.
+    Connected,
+    /// A frame from the peer.
+    Frame(Vec<u8>),
+    /// The peer went away.
+    Disconnected,
+}
+
+/// Stream to the peer.
+enum Stream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+        }
+    }
+
+    fn prepare(&self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => {
+                stream.set_nonblocking(false)?;
+                stream.set_nodelay(true)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))
+            }
+            Stream::Unix(stream) => {
+                stream.set_nonblocking(false)?;
+                stream.set_write_timeout(Some(WRITE_TIMEOUT))
+            }
+        }
+    }
+
+    fn shutdown(&self) {
+        let _ = match self {
+            Stream::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            Stream::Unix(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// How the link finds its peer.
+enum Dialer {
+    Tcp(TcpListener),
+    Unix(UnixListener, PathBuf),
+    Connect(Endpoint),
+}
+
+impl Dialer {
+    /// Wait for the next connection; `None` once `stop` is set.
+    fn establish(&self, stop: &AtomicBool) -> Option<Stream> {
+        while !stop.load(Ordering::Relaxed) {
+            let attempt = match self {
+                Dialer::Tcp(listener) => listener.accept().map(|(stream, _)| Stream::Tcp(stream)),
+                Dialer::Unix(listener, _) => {
+                    listener.accept().map(|(stream, _)| Stream::Unix(stream))
+                }
+                Dialer::Connect(Endpoint::Tcp(address)) => {
+                    TcpStream::connect(address.as_str()).map(Stream::Tcp)
+                }
+                Dialer::Connect(Endpoint::Unix(path)) => {
+                    UnixStream::connect(path).map(Stream::Unix)
+                }
+            };
+            match attempt.and_then(|stream| stream.prepare().map(|()| stream)) {
+                Ok(stream) => return Some(stream),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => {
+                    debug!(error = %e, "Peer not reachable yet");
+                    thread::sleep(RETRY_INTERVAL);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Drop for Dialer {
+    fn drop(&mut self) {
+        if let Dialer::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Link to the peer node.
+pub struct SyncLink {
+    outgoing: SyncSender<Vec<u8>>,
+    /// Buffers of sent frames, cleared.
+    spare: Receiver<Vec<u8>>,
+    events: Receiver<LinkEvent>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for SyncLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncLink")
+            .field("running", &self.thread.is_some())
+            .finish()
+    }
+}
+
+impl SyncLink {
+    /// Accept the peer's connections on `endpoint`.
+    ///
+    /// The address is bound before returning, so a taken port is reported
+    /// at start-up. A stale Unix socket file is replaced.
+    pub fn listen(endpoint: &Endpoint) -> io::Result<Self> {
+        let dialer = match endpoint {
+            Endpoint::Tcp(address) => {
+                let listener = TcpListener::bind(address.as_str())?;
+                listener.set_nonblocking(true)?;
+                Dialer::Tcp(listener)
+            }
+            Endpoint::Unix(path) => {
+                let _ = std::fs::remove_file(path);
+                let listener = UnixListener::bind(path)?;
+                listener.set_nonblocking(true)?;
+                Dialer::Unix(listener, path.clone())
+            }
+        };
+        info!(%endpoint, "Waiting for the redundant peer");
+        Ok(Self::start(dialer))
+    }
+
+    /// Connect to the peer at `endpoint`, retrying until it answers.
+    pub fn connect(endpoint: &Endpoint) -> Self {
+        info!(%endpoint, "Connecting to the redundant peer");
+        Self::start(Dialer::Connect(endpoint.clone()))
+    }
+
+    fn start(dialer: Dialer) -> Self {
+        let (outgoing, queued) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let (spare_tx, spare) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let (events_tx, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            thread::Builder::new()
+                .name("plc-sync-link".into())
+                .spawn(move || run_link(&dialer, &queued, &spare_tx, &events_tx, &stop))
+                .ok()
+        };
+        if thread.is_none() {
+            warn!("Failed to start the redundancy link thread");
+        }
+        Self {
+            outgoing,
+            spare,
+            events,
+            stop,
+            thread,
+        }
+    }
+
+    /// Queue a frame for the peer. Returns `false` if it was dropped
+    /// because the queue is full or the link is down.
+    pub fn send(&self, frame: Vec<u8>) -> bool {
+        match self.outgoing.try_send(frame) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    /// An empty buffer for the next frame, one already sent if the link
+    /// thread handed one back.
+    pub fn buffer(&self) -> Vec<u8> {
+        self.spare.try_recv().unwrap_or_default()
+    }
+
+    /// Next event, if one is waiting.
+    pub fn try_event(&self) -> Option<LinkEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Drop for SyncLink {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Link thread: connect, pass frames both ways until the peer goes away,
+/// and start over.
+fn run_link(
+    dialer: &Dialer,
+    queued: &Receiver<Vec<u8>>,
+    spare: &SyncSender<Vec<u8>>,
+    events: &Sender<LinkEvent>,
+    stop: &AtomicBool,
+) {
+    while let Some(mut stream) = dialer.establish(stop) {
+        let reader = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(e) => {
+                warn!(error = %e, "Failed to set up the redundancy link");
+                continue;
+            }
+        };
+        // Frames queued while nobody listened are stale
+        while queued.try_recv().is_ok() {}
+        let _ = events.send(LinkEvent::Connected);
+
+        let reader = {
+            let events = events.clone();
+            thread::spawn(move || read_frames(reader, &events))
+        };
+        loop {
+            if stop.load(Ordering::Relaxed) || reader.is_finished() {
+                break;
+            }
+            match queued.recv_timeout(POLL_INTERVAL) {
+                Ok(mut frame) => {
+                    if let Err(e) = write_frame(&mut stream, &frame) {
+                        warn!(error = %e, "Redundancy link write failed");
+                        break;
+                    }
+                    frame.clear();
+                    let _ = spare.try_send(frame);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+
+        stream.shutdown();
+        let _ = reader.join();
+        let _ = events.send(LinkEvent::Disconnected);
+    }
+}
+
+/// Reader thread: forward frames until the stream ends.
+fn read_frames(stream: Stream, events: &Sender<LinkEvent>) {
+    let mut stream = BufReader::new(stream);
+    loop {
+        match read_frame(&mut stream) {
+            Ok(frame) => {
+                if events.send(LinkEvent::Frame(frame)).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                debug!(error = %e, "Redundancy link closed");
+                return;
+            }
+        }
+    }
+}
+
+fn write_frame(stream: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    stream.write_all(&(frame.len() as u32).to_le_bytes())?;
+    stream.write_all(frame)?;
+    stream.flush()
+}
+
+fn read_frame(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes"),
+        ));
+    }
+    let mut frame = vec![0; len];
+    stream.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn wait_for(link: &SyncLink, expected: &LinkEvent) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            match link.try_event() {
+                Some(event) if &event == expected => return,
+                Some(_) => {}
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+        panic!("no {expected:?}");
+    }
+
+    #[test]
+    fn test_endpoint_parse() {
+        assert_eq!(
+            Endpoint::parse("unix:/run/plc/sync.sock"),
+            Endpoint::Unix(PathBuf::from("/run/plc/sync.sock"))
+        );
+        assert_eq!(
+            Endpoint::parse("10.0.0.2:7700"),
+            Endpoint::Tcp("10.0.0.2:7700".into())
+        );
+        assert_eq!(
+            Endpoint::parse("tcp://10.0.0.2:7700"),
+            Endpoint::Tcp("10.0.0.2:7700".into())
+        );
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"hello").unwrap();
+        write_frame(&mut buffer, b"").unwrap();
+        let mut reader = &buffer[..];
+        assert_eq!(read_frame(&mut reader).unwrap(), b"hello");
+        assert_eq!(read_frame(&mut reader).unwrap(), b"");
+        assert!(read_frame(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_link_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!("plc-sync-{}.sock", std::process::id()));
+        let endpoint = Endpoint::Unix(path.clone());
+        let server = SyncLink::listen(&endpoint).unwrap();
+        let client = SyncLink::connect(&endpoint);
+
+        wait_for(&server, &LinkEvent::Connected);
+        wait_for(&client, &LinkEvent::Connected);
+        assert!(client.send(b"state".to_vec()));
+        wait_for(&server, &LinkEvent::Frame(b"state".to_vec()));
+        assert!(server.send(b"ack".to_vec()));
+        wait_for(&client, &LinkEvent::Frame(b"ack".to_vec()));
+
+        // The sent frame's buffer comes back for the next one
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while client.buffer().capacity() == 0 {
+            assert!(Instant::now() < deadline, "no buffer handed back");
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        drop(client);
+        wait_for(&server, &LinkEvent::Disconnected);
+        drop(server);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,446 @@
+//! Runner for unit tests written in Structured Text.
+//!
+//! Every `TEST_` function block is compiled into its own module (see
+//! [`plc_compiler::Compiler::compile_tests`]) and run in a fresh
+//! [`WasmtimeHost`], so tests cannot affect each other. Cycles run back to
+//! back on a virtual clock: the program sees the configured cycle time, but
+//! the runner never waits for it. Process inputs come from an optional
+//! [`InputScript`].
+//!
+//! Results are printed in the style of `cargo test` and can be written as
+//! JUnit XML for CI systems.
+
+use anyhow::{Context, Result};
+use plc_common::config::RuntimeConfig;
+use plc_common::debug_map::SourceLocation;
+use plc_common::unit_test::{self, TestCase};
+use plc_common::wasm_meta;
+use plc_compiler::TestModule;
+use plc_runtime::io_image::ProcessData;
+use plc_runtime::wasm_host::{LogicEngine, WasmtimeHost};
+use plc_runtime::VarValue;
+use serde::Deserialize;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Process inputs applied at given cycles.
+///
+/// ```toml
+/// [[input]]
+/// cycle = 0
+/// di = 0x01
+///
+/// [[input]]
+/// test = "TEST_Overfill"   # only for this test
+/// cycle = 5
+/// ai = [1200, 0, -40]      # channels 0, 1, 2
+/// ```
+///
+/// Inputs keep their value until a later entry changes them.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputScript {
+    /// Entries, in any order.
+    #[serde(default)]
+    pub input: Vec<InputStep>,
+}
+
+/// Inputs applied from one cycle on.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InputStep {
+    /// Cycle (0-based) at which the inputs are applied.
+    pub cycle: u64,
+    /// Test the entry applies to; all tests if omitted.
+    pub test: Option<String>,
+    /// Digital input word.
+    pub di: Option<u32>,
+    /// Analog input values, from channel 0.
+    pub ai: Option<Vec<i16>>,
+}
+
+impl InputScript {
+    /// Load a script from a TOML file.
+    pub fn from_file(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read input script {:?}", path))?;
+        toml::from_str(&text).with_context(|| format!("Invalid input script {:?}", path))
+    }
+
+    /// Apply the entries for `test` at `cycle` to `inputs`.
+    fn apply(&self, test: &str, cycle: u64, inputs: &mut ProcessData) {
+        let entries = self.input.iter().filter(|step| {
+            step.cycle == cycle
+                && step
+                    .test
+                    .as_deref()
+                    .map_or(true, |t| t.eq_ignore_ascii_case(test))
+        });
+        for step in entries {
+            if let (Some(di), Some(word)) = (step.di, inputs.digital_inputs_mut().first_mut()) {
+                *word = di;
+            }
+            for (channel, &value) in step.ai.iter().flatten().enumerate() {
+                if let Some(slot) = inputs.analog_inputs_mut().get_mut(channel) {
+                    *slot = value;
+                }
+            }
+        }
+    }
+}
+
+/// Why a test failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// Description.
+    pub message: String,
+    /// Where it failed, if known.
+    pub location: Option<SourceLocation>,
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Result of one test.
+#[derive(Debug, Clone)]
+pub struct TestReport {
+    /// Test function block name.
+    pub name: String,
+    /// Source file of the test, if known.
+    pub file: String,
+    /// Cycles executed.
+    pub cycles: u64,
+    /// Wall-clock time taken.
+    pub elapsed: Duration,
+    /// `None` if the test passed.
+    pub failure: Option<Failure>,
+}
+
+/// Run one compiled test.
+///
+/// Tests without a `DONE` output run for one cycle; others run until they
+/// set `DONE`, failing after `max_cycles`.
+pub fn run_test(
+    test: &TestModule,
+    config: &RuntimeConfig,
+    script: &InputScript,
+    max_cycles: u64,
+) -> TestReport {
+    let started = Instant::now();
+    let case = wasm_meta::custom_section(&test.wasm, unit_test::SECTION_NAME)
+        .and_then(unit_test::decode_test)
+        .unwrap_or_else(|| TestCase {
+            name: test.name.clone(),
+            ..TestCase::default()
+        });
+
+    let mut cycles = 0;
+    let failure = execute(&case, test, config, script, max_cycles, &mut cycles).err();
+    TestReport {
+        name: test.name.clone(),
+        file: case.file,
+        cycles,
+        elapsed: started.elapsed(),
+        failure,
+    }
+}
+
+fn execute(
+    case: &TestCase,
+    test: &TestModule,
+    config: &RuntimeConfig,
+    script: &InputScript,
+    max_cycles: u64,
+    cycles: &mut u64,
+) -> std::result::Result<(), Failure> {
+    let error = |message: String| Failure {
+        message,
+        location: Some(case.location(case.line)),
+    };
+
+    let mut host = WasmtimeHost::from_runtime_config(config)
+        .and_then(|mut host| host.load_module(&test.wasm).map(|()| host))
+        .map_err(|e| error(format!("failed to load test module: {e:#}")))?;
+    host.init()
+        .map_err(|e| error(format!("init failed: {e}")))?;
+
+    let done = format!("{}.{}", case.name, unit_test::DONE);
+    let failed = format!("{}.{}", case.name, unit_test::FAILED);
+    let has_done = host.symbols().is_some_and(|s| s.get(&done).is_some());
+
+    let mut inputs = ProcessData::new(&config.process_image);
+    for cycle in 0..max_cycles {
+        script.apply(&case.name, cycle, &mut inputs);
+        *cycles = cycle + 1;
+        if let Err(e) = host.step(&inputs) {
+            return Err(Failure {
+                message: e.to_string(),
+                location: host.trap_location(),
+            });
+        }
+
+        match host.read_variable(&failed) {
+            Ok(VarValue::UInt(0)) => {}
+            Ok(VarValue::UInt(id)) => return Err(assertion_failure(case, &host, id as u32)),
+            other => return Err(error(format!("cannot read assertion state: {other:?}"))),
+        }
+
+        if !has_done || host.read_variable(&done) == Ok(VarValue::Bool(true)) {
+            return Ok(());
+        }
+    }
+    Err(error(format!(
+        "{} not set within {} cycles",
+        unit_test::DONE,
+        max_cycles
+    )))
+}
+
+fn assertion_failure(case: &TestCase, host: &WasmtimeHost, id: u32) -> Failure {
+    let Some(assertion) = case.assertion(id) else {
+        return Failure {
+            message: format!("assertion {id} failed"),
+            location: Some(case.location(case.line)),
+        };
+    };
+
+    let mut message = format!("{} failed", assertion.message);
+    if let Some(ty) = &assertion.value_type {
+        let value = |slot: &str| match host.read_variable(&format!("{}.{slot}", case.name)) {
+            Ok(VarValue::Real(v)) => format_value(v, ty),
+            _ => "?".to_string(),
+        };
+        let _ = write!(
+            message,
+            ": expected {}, got {}",
+            value(unit_test::EXPECTED),
+            value(unit_test::ACTUAL)
+        );
+    }
+    Failure {
+        message,
+        location: Some(case.location(assertion.line)),
+    }
+}
+
+/// Format a value recorded as LREAL in the notation of its type.
+fn format_value(value: f64, ty: &str) -> String {
+    match ty {
+        "BOOL" => if value != 0.0 { "TRUE" } else { "FALSE" }.to_string(),
+        "REAL" | "LREAL" => value.to_string(),
+        "TIME" => format!("T#{}ms", value / 1e6),
+        _ => format!("{}", value as i64),
+    }
+}
+
+/// Render reports as a JUnit XML document.
+pub fn junit_xml(suite: &str, reports: &[TestReport]) -> String {
+    let failures = reports.iter().filter(|r| r.failure.is_some()).count();
+    let time: f64 = reports.iter().map(|r| r.elapsed.as_secs_f64()).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{}\" failures=\"{failures}\" time=\"{time:.6}\">",
+        reports.len()
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" time=\"{time:.6}\">",
+        escape(suite),
+        reports.len()
+    );
+    for report in reports {
+        let _ = write!(
+            xml,
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.6}\"",
+            escape(&report.name),
+            escape(if report.file.is_empty() {
+                suite
+            } else {
+                &report.file
+            }),
+            report.elapsed.as_secs_f64()
+        );
+        match &report.failure {
+            None => xml.push_str("/>\n"),
+            Some(failure) => {
+                let _ = writeln!(
+                    xml,
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    escape(&failure.message),
+                    escape(&failure.to_string())
+                );
+            }
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plc_compiler::{Compiler, Language, SourceFile};
+
+    const SOURCE: &str = r#"
+FUNCTION_BLOCK Counter
+VAR_INPUT
+    enable : BOOL;
+END_VAR
+VAR_OUTPUT
+    count : INT;
+END_VAR
+    IF enable THEN
+        count := count + 1;
+    END_IF;
+END_FUNCTION_BLOCK
+
+FUNCTION_BLOCK TEST_CountsEnabledCycles
+VAR_OUTPUT
+    DONE : BOOL;
+END_VAR
+VAR
+    cycles : INT;
+END_VAR
+    Counter(enable := read_di(0) = 1);
+    cycles := cycles + 1;
+    IF cycles = 3 THEN
+        ASSERT_EQ(Counter.count, 2);
+        DONE := TRUE;
+    END_IF;
+END_FUNCTION_BLOCK
+
+FUNCTION_BLOCK TEST_Fails
+    Counter(enable := TRUE);
+    ASSERT_TRUE(Counter.count > 0);
+    ASSERT_EQ(Counter.count, 5);
+END_FUNCTION_BLOCK
+
+FUNCTION_BLOCK TEST_NeverDone
+VAR_OUTPUT
+    DONE : BOOL;
+END_VAR
+    DONE := FALSE;
+END_FUNCTION_BLOCK
+"#;
+
+    fn compile() -> Vec<TestModule> {
+        let source = SourceFile::new("counter.st", Language::StructuredText, SOURCE);
+        Compiler::new().compile_tests(&[source]).unwrap()
+    }
+
+    #[test]
+    fn test_run_tests_with_scripted_inputs() {
+        let tests = compile();
+        assert_eq!(tests.len(), 3);
+        let script: InputScript = toml::from_str(
+            r#"
+            [[input]]
+            cycle = 1
+            di = 1
+            "#,
+        )
+        .unwrap();
+        let config = RuntimeConfig::default();
+        let reports: Vec<_> = tests
+            .iter()
+            .map(|t| run_test(t, &config, &script, 10))
+            .collect();
+
+        // DI 0 is set from cycle 1, so the counter runs in cycles 1 and 2.
+        assert_eq!(reports[0].failure, None);
+        assert_eq!(reports[0].cycles, 3);
+
+        let failure = reports[1].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.to_string(),
+            "counter.st:32 in FB TEST_Fails: ASSERT_EQ(Counter.count, 5) failed: expected 5, got 1"
+        );
+
+        let failure = reports[2].failure.as_ref().unwrap();
+        assert_eq!(failure.message, "DONE not set within 10 cycles");
+        assert_eq!(reports[2].cycles, 10);
+    }
+
+    #[test]
+    fn test_typed_image_access() {
+        let source = r#"
+FUNCTION_BLOCK TEST_ImageAccess
+VAR
+    level : REAL;
+END_VAR
+    write_dword(40, 305419896);
+    ASSERT_EQ(read_dword(40), 305419896);
+    ASSERT_EQ(read_word(42), 4660);
+    write_real(44, 1.5);
+    level := read_real(44);
+    ASSERT_TRUE(level = 1.5);
+    write_bit(4, 3, TRUE);
+    ASSERT_TRUE(read_bit(4, 3));
+    ASSERT_EQ(read_byte(4), 8);
+    write_byte(0, 255);
+    ASSERT_EQ(read_byte(0), 0);
+    ASSERT_EQ(read_dword(104), 0);
+END_FUNCTION_BLOCK
+"#;
+        let source = SourceFile::new("image.st", Language::StructuredText, source);
+        let tests = Compiler::new().compile_tests(&[source]).unwrap();
+        let report = run_test(
+            &tests[0],
+            &RuntimeConfig::default(),
+            &InputScript::default(),
+            1,
+        );
+        assert_eq!(report.failure, None);
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let reports = vec![
+            TestReport {
+                name: "TEST_Ok".into(),
+                file: "a.st".into(),
+                cycles: 1,
+                elapsed: Duration::from_millis(2),
+                failure: None,
+            },
+            TestReport {
+                name: "TEST_Bad".into(),
+                file: String::new(),
+                cycles: 1,
+                elapsed: Duration::ZERO,
+                failure: Some(Failure {
+                    message: "ASSERT_TRUE(a < b) failed".into(),
+                    location: None,
+                }),
+            },
+        ];
+        let xml = junit_xml("plc", &reports);
+        assert!(xml.contains(r#"<testsuites tests="2" failures="1""#));
+        assert!(xml.contains(r#"<testcase name="TEST_Ok" classname="a.st" time="0.002000"/>"#));
+        assert!(xml.contains(r#"<failure message="ASSERT_TRUE(a &lt; b) failed">"#));
+        assert!(xml.contains(r#"classname="plc""#));
+    }
+}
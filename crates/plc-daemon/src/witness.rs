@@ -0,0 +1,423 @@
+//! Witness deciding which node of a redundant pair may drive the outputs.
+//!
+//! The witness lends a lease to one node at a time. The primary renews its
+//! lease while it runs; a standby that lost the primary only takes over once
+//! the witness lends it the lease, which happens after the primary's lease
+//! ran out. Two nodes that reach the witness but not each other therefore
+//! never drive the outputs together. Run it on a third machine with
+//! `plc-daemon witness`.
+//!
+//! # Protocol
+//!
+//! One request per connection, over TCP or a Unix socket:
+//!
+//! 1. the witness sends a fresh nonce;
+//! 2. the node sends its own nonce, its node id, the length of the lease in
+//!    milliseconds and a MAC of both nonces and the request;
+//! 3. the witness answers whether it lent the lease, which node holds it,
+//!    and a MAC of both nonces and the answer.
+//!
+//! The MACs use the pair's shared secret, so neither side can be forged,
+//! and the nonces keep a recorded exchange from being replayed.
+
+use crate::redundancy::{mac, nonce, MAC_LEN, NONCE_LEN};
+use crate::sync_link::Endpoint;
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// How long a lease lasts once lent.
+pub const LEASE: Duration = Duration::from_secs(1);
+
+/// Pause between two requests of a node wanting the lease.
+const RENEW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long either side waits for the other during a request.
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Longest lease the witness lends.
+const MAX_LEASE: Duration = Duration::from_secs(60);
+
+/// How often the witness checks for shutdown while idle.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+const REQUEST_LEN: usize = NONCE_LEN + 4 + 4 + MAC_LEN;
+const ANSWER_LEN: usize = 1 + 4 + MAC_LEN;
+
+fn request_mac(
+    secret: &[u8],
+    nonces: &[u8; 2 * NONCE_LEN],
+    node_id: u32,
+    lease_ms: u32,
+) -> Hmac<Sha256> {
+    mac(
+        secret,
+        &[
+            b"plc-witness-request",
+            nonces,
+            &node_id.to_le_bytes(),
+            &lease_ms.to_le_bytes(),
+        ],
+    )
+}
+
+fn answer_mac(
+    secret: &[u8],
+    nonces: &[u8; 2 * NONCE_LEN],
+    granted: bool,
+    holder: u32,
+) -> Hmac<Sha256> {
+    mac(
+        secret,
+        &[
+            b"plc-witness-answer",
+            nonces,
+            &[u8::from(granted)],
+            &holder.to_le_bytes(),
+        ],
+    )
+}
+
+/// The witness' nonce followed by the node's.
+fn nonces(witness: &[u8], node: &[u8]) -> [u8; 2 * NONCE_LEN] {
+    let mut both = [0; 2 * NONCE_LEN];
+    both[..NONCE_LEN].copy_from_slice(witness);
+    both[NONCE_LEN..].copy_from_slice(node);
+    both
+}
+
+/// Lease held on behalf of a node.
+#[derive(Debug, Clone, Copy)]
+struct Holder {
+    node_id: u32,
+    until: Instant,
+}
+
+/// The witness: lends the lease to the nodes asking for it.
+#[derive(Debug)]
+pub struct WitnessServer {
+    listener: Listener,
+    secret: Vec<u8>,
+    holder: Option<Holder>,
+}
+
+#[derive(Debug)]
+enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+impl WitnessServer {
+    /// Listen on `endpoint`. A stale Unix socket file is replaced.
+    pub fn bind(endpoint: &Endpoint, secret: &[u8]) -> io::Result<Self> {
+        let listener = match endpoint {
+            Endpoint::Tcp(address) => {
+                let listener = TcpListener::bind(address.as_str())?;
+                listener.set_nonblocking(true)?;
+                Listener::Tcp(listener)
+            }
+            Endpoint::Unix(path) => {
+                let _ = std::fs::remove_file(path);
+                let listener = UnixListener::bind(path)?;
+                listener.set_nonblocking(true)?;
+                Listener::Unix(listener)
+            }
+        };
+        info!(%endpoint, "Witness listening");
+        Ok(Self {
+            listener,
+            secret: secret.to_vec(),
+            holder: None,
+        })
+    }
+
+    /// Answer requests until `stop` is set.
+    pub fn serve(&mut self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            let (secret, holder) = (&self.secret, &mut self.holder);
+            let accepted = match &self.listener {
+                Listener::Tcp(listener) => listener.accept().and_then(|(stream, _)| {
+                    stream.set_nonblocking(false)?;
+                    stream.set_read_timeout(Some(TIMEOUT))?;
+                    stream.set_write_timeout(Some(TIMEOUT))?;
+                    answer(stream, secret, holder)
+                }),
+                Listener::Unix(listener) => listener.accept().and_then(|(stream, _)| {
+                    stream.set_nonblocking(false)?;
+                    stream.set_read_timeout(Some(TIMEOUT))?;
+                    stream.set_write_timeout(Some(TIMEOUT))?;
+                    answer(stream, secret, holder)
+                }),
+            };
+            match accepted {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => debug!(error = %e, "Witness request failed"),
+            }
+        }
+    }
+}
+
+/// Answer one request, lending the lease if it is free.
+fn answer(
+    mut stream: impl Read + Write,
+    secret: &[u8],
+    holder: &mut Option<Holder>,
+) -> io::Result<()> {
+    let own = nonce()?;
+    stream.write_all(&own)?;
+    let mut request = [0; REQUEST_LEN];
+    stream.read_exact(&mut request)?;
+
+    let (node_nonce, rest) = request.split_at(NONCE_LEN);
+    let node_id = u32::from_le_bytes(rest[..4].try_into().expect("4 bytes"));
+    let lease_ms = u32::from_le_bytes(rest[4..8].try_into().expect("4 bytes"));
+    let both = nonces(&own, node_nonce);
+    if request_mac(secret, &both, node_id, lease_ms)
+        .verify_slice(&rest[8..])
+        .is_err()
+    {
+        warn!(node_id, "Witness request with a bad MAC, ignored");
+        return Ok(());
+    }
+
+    let now = Instant::now();
+    let lease = Duration::from_millis(u64::from(lease_ms)).min(MAX_LEASE);
+    let granted = match *holder {
+        Some(current) if current.node_id != node_id && current.until > now => false,
+        previous => {
+            if previous.map(|current| current.node_id) != Some(node_id) {
+                info!(node_id, "Witness lends the lease");
+            }
+            *holder = Some(Holder {
+                node_id,
+                until: now + lease,
+            });
+            true
+        }
+    };
+    let holder = holder.map_or(0, |current| current.node_id);
+    let mut answer = [0; ANSWER_LEN];
+    answer[0] = u8::from(granted);
+    answer[1..5].copy_from_slice(&holder.to_le_bytes());
+    answer[5..].copy_from_slice(
+        &answer_mac(secret, &both, granted, holder)
+            .finalize()
+            .into_bytes(),
+    );
+    stream.write_all(&answer)
+}
+
+/// A node's side of the witness: asks for the lease on a background thread
+/// while the node wants it, so the scan only reads atomics.
+#[derive(Debug)]
+pub struct Lease {
+    address: String,
+    wanted: Arc<AtomicBool>,
+    /// End of the lease, in milliseconds since `epoch` plus one; zero if
+    /// not held.
+    until: Arc<AtomicU64>,
+    epoch: Instant,
+    stop: Arc<AtomicBool>,
+}
+
+impl Lease {
+    /// Start asking the witness at `address` for leases of `node_id`.
+    pub fn start(address: &str, node_id: u32, secret: &[u8]) -> Self {
+        let lease = Self {
+            address: address.to_string(),
+            wanted: Arc::new(AtomicBool::new(false)),
+            until: Arc::new(AtomicU64::new(0)),
+            epoch: Instant::now(),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        let spawned = {
+            let endpoint = Endpoint::parse(address);
+            let secret = secret.to_vec();
+            let wanted = Arc::clone(&lease.wanted);
+            let until = Arc::clone(&lease.until);
+            let epoch = lease.epoch;
+            let stop = Arc::clone(&lease.stop);
+            thread::Builder::new()
+                .name("plc-witness".into())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        if wanted.load(Ordering::Relaxed) {
+                            // The lease counts from before the request left,
+                            // so it ends here no later than on the witness
+                            let asked = millis_since(epoch);
+                            match request(&endpoint, &secret, node_id) {
+                                Ok(true) => until
+                                    .store(asked + LEASE.as_millis() as u64 + 1, Ordering::Relaxed),
+                                Ok(false) => until.store(0, Ordering::Relaxed),
+                                Err(e) => debug!(error = %e, "Witness not reachable"),
+                            }
+                        }
+                        thread::sleep(RENEW_INTERVAL);
+                    }
+                })
+        };
+        if let Err(e) = spawned {
+            warn!(error = %e, "Failed to start the witness client, the lease is never held");
+        }
+        lease
+    }
+
+    /// Address of the witness.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Keep asking for the lease, or stop asking.
+    pub fn set_wanted(&self, wanted: bool) {
+        self.wanted.store(wanted, Ordering::Relaxed);
+    }
+
+    /// Whether this node holds the lease.
+    pub fn held(&self) -> bool {
+        millis_since(self.epoch) + 1 < self.until.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        // Not joined: a request may be stuck in name resolution
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn millis_since(epoch: Instant) -> u64 {
+    u64::try_from(epoch.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Ask the witness for the lease; `Ok(true)` if it lent it.
+fn request(endpoint: &Endpoint, secret: &[u8], node_id: u32) -> io::Result<bool> {
+    match endpoint {
+        Endpoint::Tcp(address) => {
+            let mut last = io::Error::new(io::ErrorKind::NotFound, "no address");
+            for address in address.to_socket_addrs()? {
+                match TcpStream::connect_timeout(&address, TIMEOUT) {
+                    Ok(stream) => {
+                        stream.set_read_timeout(Some(TIMEOUT))?;
+                        stream.set_write_timeout(Some(TIMEOUT))?;
+                        return exchange(stream, secret, node_id);
+                    }
+                    Err(e) => last = e,
+                }
+            }
+            Err(last)
+        }
+        Endpoint::Unix(path) => {
+            let stream = UnixStream::connect(path)?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            exchange(stream, secret, node_id)
+        }
+    }
+}
+
+fn exchange(mut stream: impl Read + Write, secret: &[u8], node_id: u32) -> io::Result<bool> {
+    let mut witness_nonce = [0; NONCE_LEN];
+    stream.read_exact(&mut witness_nonce)?;
+    let own = nonce()?;
+    let both = nonces(&witness_nonce, &own);
+    let lease_ms = LEASE.as_millis() as u32;
+
+    let mut request = Vec::with_capacity(REQUEST_LEN);
+    request.extend_from_slice(&own);
+    request.extend_from_slice(&node_id.to_le_bytes());
+    request.extend_from_slice(&lease_ms.to_le_bytes());
+    request.extend_from_slice(
+        &request_mac(secret, &both, node_id, lease_ms)
+            .finalize()
+            .into_bytes(),
+    );
+    stream.write_all(&request)?;
+
+    let mut answer = [0; ANSWER_LEN];
+    stream.read_exact(&mut answer)?;
+    let granted = answer[0] == 1;
+    let holder = u32::from_le_bytes(answer[1..5].try_into().expect("4 bytes"));
+    if answer_mac(secret, &both, granted, holder)
+        .verify_slice(&answer[5..])
+        .is_err()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "witness answer with a bad MAC",
+        ));
+    }
+    if !granted {
+        debug!(holder, "The witness lent the lease to another node");
+    }
+    Ok(granted)
+}
+
+/// Run a witness on `endpoint` until the process is stopped.
+pub fn run(endpoint: &Endpoint, secret: &[u8]) -> Result<()> {
+    let mut server = WitnessServer::bind(endpoint, secret)
+        .with_context(|| format!("Failed to listen on {endpoint}"))?;
+    server.serve(&AtomicBool::new(false));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_until(what: &str, condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out waiting until {what}");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_lease_goes_to_one_node_at_a_time() {
+        let path = std::env::temp_dir().join(format!("plc-witness-{}.sock", std::process::id()));
+        let endpoint = Endpoint::parse(&format!("unix:{}", path.display()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut server = WitnessServer::bind(&endpoint, b"0123456789abcdef").unwrap();
+        let witness = {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || server.serve(&stop))
+        };
+        let address = endpoint.to_string();
+
+        let a = Lease::start(&address, 1, b"0123456789abcdef");
+        let b = Lease::start(&address, 2, b"0123456789abcdef");
+        a.set_wanted(true);
+        wait_until("a holds the lease", || a.held());
+
+        // b waits while a renews
+        b.set_wanted(true);
+        thread::sleep(LEASE + RENEW_INTERVAL * 2);
+        assert!(a.held());
+        assert!(!b.held());
+
+        // Once a lets go, b gets the lease, but only after a's ran out
+        a.set_wanted(false);
+        wait_until("a's lease runs out", || !a.held());
+        wait_until("b holds the lease", || b.held());
+
+        // A node without the secret gets nothing
+        let rogue = Lease::start(&address, 3, b"not the secret!!");
+        rogue.set_wanted(true);
+        b.set_wanted(false);
+        thread::sleep(LEASE * 2 + RENEW_INTERVAL * 2);
+        assert!(!rogue.held());
+
+        stop.store(true, Ordering::Relaxed);
+        witness.join().unwrap();
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,155 @@
+//! Hot-standby redundancy between separate `plc-daemon` processes, with a
+//! witness process lending the lease.
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SECRET: &str = "redundancy-test-secret";
+
+/// A running `plc-daemon` whose output is collected; killed when dropped.
+struct Daemon {
+    child: Child,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Daemon {
+    fn start(args: &[&str]) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_plc-daemon"))
+            .args(args)
+            .env("NO_COLOR", "1")
+            .env_remove("RUST_LOG")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to start plc-daemon");
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        for output in [
+            Box::new(child.stdout.take().unwrap()) as Box<dyn std::io::Read + Send>,
+            Box::new(child.stderr.take().unwrap()),
+        ] {
+            let lines = Arc::clone(&lines);
+            thread::spawn(move || {
+                for line in BufReader::new(output).lines().map_while(Result::ok) {
+                    lines.lock().unwrap().push(line);
+                }
+            });
+        }
+        Self { child, lines }
+    }
+
+    fn logged(&self, text: &str) -> bool {
+        self.lines
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains(text))
+    }
+
+    fn wait_for(&self, text: &str) {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while !self.logged(text) {
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for {text:?}; output:\n{}",
+                self.lines.lock().unwrap().join("\n")
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+fn write_node_config(dir: &Path, node_id: u32, side: &str) -> PathBuf {
+    let config = format!(
+        r#"cycle_time = "10ms"
+watchdog_timeout = "500ms"
+max_overrun = "100ms"
+wasm_module = "{module}"
+
+[fieldbus]
+driver = "simulated"
+
+[redundancy]
+enabled = true
+node_id = {node_id}
+role = "{role}"
+{side} = "unix:{sync}"
+takeover_cycles = 10
+startup_timeout = "500ms"
+secret = "{SECRET}"
+witness = "unix:{witness}"
+"#,
+        module = dir.join("counter.wasm").display(),
+        role = if node_id == 1 { "primary" } else { "auto" },
+        sync = dir.join("sync.sock").display(),
+        witness = dir.join("witness.sock").display(),
+    );
+    let path = dir.join(format!("node{node_id}.toml"));
+    std::fs::write(&path, config).unwrap();
+    path
+}
+
+#[test]
+fn test_standby_process_takes_over_from_killed_primary() {
+    let dir = std::env::temp_dir().join(format!("plc-redundancy-pair-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let module = wat::parse_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (func (export "step")
+                (i32.store (i32.const 1024)
+                    (i32.add (i32.load (i32.const 1024)) (i32.const 1)))))"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("counter.wasm"), module).unwrap();
+
+    let witness_config = dir.join("witness.toml");
+    std::fs::write(
+        &witness_config,
+        format!(
+            "[redundancy]\nsecret = \"{SECRET}\"\nwitness = \"unix:{}\"\n",
+            dir.join("witness.sock").display()
+        ),
+    )
+    .unwrap();
+    let witness = Daemon::start(&["witness", "-c", witness_config.to_str().unwrap()]);
+    witness.wait_for("Witness listening");
+
+    let a_config = write_node_config(&dir, 1, "listen");
+    let b_config = write_node_config(&dir, 2, "connect");
+    let mut a = Daemon::start(&["run", "-c", a_config.to_str().unwrap()]);
+    a.wait_for("Redundancy role: primary");
+    let b = Daemon::start(&["run", "-c", b_config.to_str().unwrap()]);
+    b.wait_for("Redundancy role: standby");
+    assert!(!b.logged("Redundancy role: primary"));
+    assert!(!a.logged("failed authentication"));
+    assert!(!b.logged("failed authentication"));
+
+    // The standby takes over once the dead primary's lease ran out
+    a.kill();
+    b.wait_for("Redundancy role: primary");
+    assert!(b.logged("primary silent"));
+
+    // A restarted node finds the lease taken and follows
+    let a = Daemon::start(&["run", "-c", a_config.to_str().unwrap()]);
+    a.wait_for("Redundancy role: standby");
+    thread::sleep(Duration::from_secs(2));
+    assert!(!a.logged("Redundancy role: primary"));
+
+    drop((a, b, witness));
+    let _ = std::fs::remove_dir_all(&dir);
+}
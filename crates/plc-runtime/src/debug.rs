@@ -0,0 +1,318 @@
+//! Debug execution: pause, single-cycle step and cycle breakpoints.
+//!
+//! In DEBUG the scan keeps running, so the fieldbus keeps exchanging and the
+//! watchdogs stay fed, but the logic only executes on command:
+//!
+//! - [`DebugCommand::Step`] runs a number of scans, then pauses,
+//! - [`DebugCommand::RunUntil`] runs until a [`Breakpoint`] condition on a
+//!   variable holds at the end of a scan,
+//! - [`DebugCommand::Pause`] stops a running command.
+//!
+//! Every pause records a [`DebugStop`] with the values of all variables, so
+//! they can be inspected while the logic is frozen.
+
+use crate::symbols::VarValue;
+use plc_common::error::{PlcError, PlcResult};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Comparison in a breakpoint condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "<>",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// A condition on a named variable, checked after each scan.
+///
+/// Written in ST syntax: `Main.count >= 10`, `Main.state <> 3`, or just
+/// `Main.done` for a `BOOL` that becomes `TRUE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breakpoint {
+    variable: String,
+    op: CompareOp,
+    value: VarValue,
+}
+
+impl Breakpoint {
+    /// Create a breakpoint on `variable op value`.
+    pub fn new(variable: impl Into<String>, op: CompareOp, value: VarValue) -> Self {
+        Self {
+            variable: variable.into(),
+            op,
+            value,
+        }
+    }
+
+    /// The variable the condition reads.
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    /// Whether the condition holds for the variable's current value.
+    ///
+    /// Integers compare exactly; other values compare as `LREAL`, with
+    /// `TRUE` as 1. `NaN` never compares equal.
+    pub fn is_hit(&self, current: VarValue) -> bool {
+        compare(current, self.value).is_some_and(|ordering| self.op.holds(ordering))
+    }
+}
+
+fn compare(a: VarValue, b: VarValue) -> Option<Ordering> {
+    fn integer(v: VarValue) -> Option<i128> {
+        match v {
+            VarValue::Bool(b) => Some(i128::from(b)),
+            VarValue::Int(v) => Some(i128::from(v)),
+            VarValue::UInt(v) => Some(i128::from(v)),
+            VarValue::Real(_) => None,
+        }
+    }
+    fn real(v: VarValue) -> f64 {
+        match v {
+            VarValue::Bool(b) => f64::from(u8::from(b)),
+            VarValue::Int(v) => v as f64,
+            VarValue::UInt(v) => v as f64,
+            VarValue::Real(v) => v,
+        }
+    }
+    match (integer(a), integer(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => real(a).partial_cmp(&real(b)),
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.variable, self.op.symbol(), self.value)
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = PlcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PlcError::Config(format!("invalid breakpoint condition: {s}"));
+        // The first operator in the text, the longest at that position so
+        // `<=` is not read as `<`
+        const OPERATORS: [(&str, CompareOp); 8] = [
+            ("<>", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("=", CompareOp::Eq),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+
+        let found = OPERATORS
+            .iter()
+            .filter_map(|&(token, op)| s.find(token).map(|at| (at, token, op)))
+            .min_by_key(|&(at, token, _)| (at, std::cmp::Reverse(token.len())));
+        let Some((at, token, op)) = found else {
+            let variable = s.trim();
+            if variable.is_empty() || variable.contains(char::is_whitespace) {
+                return Err(invalid());
+            }
+            return Ok(Breakpoint::new(
+                variable,
+                CompareOp::Eq,
+                VarValue::Bool(true),
+            ));
+        };
+
+        let variable = s[..at].trim();
+        let value = s[at + token.len()..].trim();
+        if variable.is_empty() || variable.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+        let value = match value.to_ascii_uppercase().as_str() {
+            "TRUE" => VarValue::Bool(true),
+            "FALSE" => VarValue::Bool(false),
+            _ => value
+                .parse::<i64>()
+                .map(VarValue::Int)
+                .or_else(|_| value.parse::<u64>().map(VarValue::UInt))
+                .or_else(|_| value.parse::<f64>().map(VarValue::Real))
+                .map_err(|_| invalid())?,
+        };
+        Ok(Breakpoint::new(variable, op, value))
+    }
+}
+
+/// A command for the logic while the runtime is in DEBUG.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugCommand {
+    /// Stop executing the logic after the current scan.
+    Pause,
+    /// Execute this many scans, then pause.
+    Step(u64),
+    /// Execute scans until the breakpoint is hit, then pause.
+    RunUntil(Breakpoint),
+}
+
+/// Why the logic paused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// DEBUG was entered.
+    Entered,
+    /// A pause was requested.
+    Paused,
+    /// The requested number of scans ran.
+    Stepped,
+    /// A breakpoint was hit.
+    Breakpoint(Breakpoint),
+    /// The breakpoint variable could not be read.
+    BreakpointError(String),
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopReason::Entered => f.write_str("entered debug"),
+            StopReason::Paused => f.write_str("paused"),
+            StopReason::Stepped => f.write_str("step complete"),
+            StopReason::Breakpoint(breakpoint) => write!(f, "breakpoint hit: {breakpoint}"),
+            StopReason::BreakpointError(error) => write!(f, "breakpoint failed: {error}"),
+        }
+    }
+}
+
+/// Where the logic paused, with the variables as they were.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugStop {
+    /// Scan the logic paused after.
+    pub cycle: u64,
+    /// Why it paused.
+    pub reason: StopReason,
+    /// Elementary variables by qualified name.
+    pub variables: Vec<(String, VarValue)>,
+}
+
+/// What the logic does in DEBUG.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DebugRun {
+    /// Not executing.
+    Paused,
+    /// Executing the given number of remaining scans.
+    Cycles(u64),
+    /// Executing until the breakpoint is hit.
+    Until(Breakpoint),
+}
+
+impl DebugRun {
+    /// Account for one executed scan, returning why to pause, if at all.
+    pub(crate) fn after_scan(
+        &mut self,
+        read: impl FnOnce(&str) -> PlcResult<VarValue>,
+    ) -> Option<StopReason> {
+        match self {
+            DebugRun::Paused => None,
+            DebugRun::Cycles(remaining) => {
+                *remaining = remaining.saturating_sub(1);
+                (*remaining == 0).then_some(StopReason::Stepped)
+            }
+            DebugRun::Until(breakpoint) => match read(breakpoint.variable()) {
+                Ok(value) if breakpoint.is_hit(value) => {
+                    Some(StopReason::Breakpoint(breakpoint.clone()))
+                }
+                Ok(_) => None,
+                Err(e) => Some(StopReason::BreakpointError(e.to_string())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_breakpoints() {
+        let parse = |s: &str| s.parse::<Breakpoint>().unwrap();
+        assert_eq!(
+            parse("Main.count >= 10"),
+            Breakpoint::new("Main.count", CompareOp::Ge, VarValue::Int(10))
+        );
+        assert_eq!(
+            parse("Main.level<-1.5"),
+            Breakpoint::new("Main.level", CompareOp::Lt, VarValue::Real(-1.5))
+        );
+        assert_eq!(
+            parse("Main.state <> 3"),
+            Breakpoint::new("Main.state", CompareOp::Ne, VarValue::Int(3))
+        );
+        assert_eq!(
+            parse("Main.done"),
+            Breakpoint::new("Main.done", CompareOp::Eq, VarValue::Bool(true))
+        );
+        assert_eq!(parse("Main.run = false").to_string(), "Main.run = FALSE");
+        for bad in ["", "Main.x >=", ">= 3", "Main x", "Main.x = abc"] {
+            assert!(bad.parse::<Breakpoint>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_breakpoint_comparisons() {
+        let bp = |s: &str| s.parse::<Breakpoint>().unwrap();
+        assert!(bp("x >= 10").is_hit(VarValue::Int(10)));
+        assert!(!bp("x >= 10").is_hit(VarValue::Int(9)));
+        assert!(bp("x > 1").is_hit(VarValue::UInt(u64::MAX)));
+        assert!(bp("x < 0.5").is_hit(VarValue::Real(0.25)));
+        assert!(bp("x = 2").is_hit(VarValue::Real(2.0)));
+        assert!(!bp("x = 2").is_hit(VarValue::Real(f64::NAN)));
+        assert!(bp("x").is_hit(VarValue::Bool(true)));
+        assert!(!bp("x").is_hit(VarValue::Bool(false)));
+        assert!(bp("x <> TRUE").is_hit(VarValue::Bool(false)));
+    }
+
+    #[test]
+    fn test_run_accounting() {
+        let mut run = DebugRun::Cycles(2);
+        assert_eq!(run.after_scan(|_| unreachable!()), None);
+        assert_eq!(
+            run.after_scan(|_| unreachable!()),
+            Some(StopReason::Stepped)
+        );
+
+        let mut run = DebugRun::Until("Main.n >= 3".parse().unwrap());
+        assert_eq!(run.after_scan(|_| Ok(VarValue::Int(2))), None);
+        assert!(matches!(
+            run.after_scan(|_| Ok(VarValue::Int(3))),
+            Some(StopReason::Breakpoint(_))
+        ));
+        assert!(matches!(
+            run.after_scan(|_| Err(PlcError::Config("gone".into()))),
+            Some(StopReason::BreakpointError(_))
+        ));
+    }
+}
@@ -7,27 +7,41 @@
 //! # Design
 //!
 //! - **Pre-allocated**: All memory is allocated upfront to avoid heap activity in RT path.
-//! - **Fixed-size buffers**: Input/output snapshots use fixed arrays, not Vec.
+//! - **Fixed-size buffers**: Input/output snapshots are sized for the configured
+//!   process image when the recorder is created and reused for every frame.
 //! - **Lock-free recording**: Single-threaded recording path has no synchronization overhead.
 //! - **Configurable depth**: Default 64 frames, adjustable for memory vs. history tradeoff.
 
 use crate::io_image::ProcessData;
 use crate::scheduler::CyclePhaseTimings;
-use static_assertions::const_assert;
+use plc_common::config::ProcessImageConfig;
+use plc_common::debug_map::SourceLocation;
 use std::time::Duration;
 
 /// Default number of fault frames to retain.
 pub const DEFAULT_FAULT_FRAME_COUNT: usize = 64;
 
-/// Maximum size of I/O snapshot in bytes.
-/// Sized to hold ProcessData's digital and analog I/O.
-pub const IO_SNAPSHOT_SIZE: usize = 256;
+/// Size in bytes of an input snapshot: digital input words, then analog
+/// input channels, little-endian.
+pub fn input_snapshot_size(config: &ProcessImageConfig) -> usize {
+    config.di_words() * 4 + config.ai_channels() * 2
+}
 
-// Compile-time check that IO_SNAPSHOT_SIZE can hold ProcessData's I/O fields.
-// ProcessData has: digital_inputs[1] + digital_outputs[1] (8 bytes) +
-//                  analog_inputs[16] + analog_outputs[16] (64 bytes) = 72 bytes minimum
-// We use 256 to allow for future expansion.
-const_assert!(IO_SNAPSHOT_SIZE >= std::mem::size_of::<ProcessData>());
+/// Size in bytes of an output snapshot: digital output words, then analog
+/// output channels, little-endian.
+pub fn output_snapshot_size(config: &ProcessImageConfig) -> usize {
+    config.do_words() * 4 + config.ao_channels() * 2
+}
+
+/// Pack digital words followed by analog channels into `snapshot`,
+/// truncating whatever does not fit.
+pub(crate) fn pack_snapshot(snapshot: &mut [u8], digital: &[u32], analog: &[i16]) {
+    let digital_bytes = digital.iter().flat_map(|word| word.to_le_bytes());
+    let analog_bytes = analog.iter().flat_map(|value| value.to_le_bytes());
+    for (target, byte) in snapshot.iter_mut().zip(digital_bytes.chain(analog_bytes)) {
+        *target = byte;
+    }
+}
 
 /// Reason for entering fault state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -78,12 +92,14 @@ impl std::fmt::Display for FaultReason {
 pub struct FaultFrame {
     /// Cycle number when this frame was captured.
     pub cycle: u64,
+    /// Index of the scheduler task that ran in this frame.
+    pub task: usize,
     /// Timestamp in nanoseconds since recorder start.
     pub timestamp_ns: u64,
-    /// Snapshot of input data.
-    pub inputs: [u8; IO_SNAPSHOT_SIZE],
-    /// Snapshot of output data.
-    pub outputs: [u8; IO_SNAPSHOT_SIZE],
+    /// Snapshot of input data (see [`input_snapshot_size`]).
+    pub inputs: Box<[u8]>,
+    /// Snapshot of output data (see [`output_snapshot_size`]).
+    pub outputs: Box<[u8]>,
     /// Per-phase timing breakdown.
     pub phase_timings: CyclePhaseTimings,
     /// Working counter from fieldbus (if applicable).
@@ -92,62 +108,83 @@ pub struct FaultFrame {
     pub expected_wkc: Option<u16>,
     /// Fault reason (None for normal frames).
     pub fault_reason: FaultReason,
+    /// ST source location of the fault, if the logic trapped.
+    pub source_location: Option<SourceLocation>,
     /// Whether this frame has valid data.
     pub valid: bool,
 }
 
 impl Default for FaultFrame {
     fn default() -> Self {
+        Self::for_process_image(&ProcessImageConfig::default())
+    }
+}
+
+impl FaultFrame {
+    /// Create an empty frame with snapshots sized for `config`.
+    pub fn for_process_image(config: &ProcessImageConfig) -> Self {
         Self {
             cycle: 0,
+            task: 0,
             timestamp_ns: 0,
-            inputs: [0; IO_SNAPSHOT_SIZE],
-            outputs: [0; IO_SNAPSHOT_SIZE],
+            inputs: vec![0; input_snapshot_size(config)].into_boxed_slice(),
+            outputs: vec![0; output_snapshot_size(config)].into_boxed_slice(),
             phase_timings: CyclePhaseTimings::default(),
             wkc: None,
             expected_wkc: None,
             fault_reason: FaultReason::None,
+            source_location: None,
             valid: false,
         }
     }
-}
 
-impl FaultFrame {
     /// Create a new fault frame with cycle data.
     pub fn new(cycle: u64, timestamp_ns: u64, phase_timings: CyclePhaseTimings) -> Self {
-        Self {
-            cycle,
-            timestamp_ns,
-            phase_timings,
-            valid: true,
-            ..Default::default()
-        }
+        let mut frame = Self::default();
+        frame.start(cycle, timestamp_ns, phase_timings);
+        frame
+    }
+
+    /// Reuse this frame for a new cycle, keeping the snapshot buffers.
+    fn start(&mut self, cycle: u64, timestamp_ns: u64, phase_timings: CyclePhaseTimings) {
+        self.clear();
+        self.cycle = cycle;
+        self.timestamp_ns = timestamp_ns;
+        self.phase_timings = phase_timings;
+        self.valid = true;
+    }
+
+    /// Reset to an empty frame, keeping the snapshot buffers.
+    fn clear(&mut self) {
+        self.cycle = 0;
+        self.task = 0;
+        self.timestamp_ns = 0;
+        self.inputs.fill(0);
+        self.outputs.fill(0);
+        self.phase_timings = CyclePhaseTimings::default();
+        self.wkc = None;
+        self.expected_wkc = None;
+        self.fault_reason = FaultReason::None;
+        self.source_location = None;
+        self.valid = false;
     }
 
     /// Set input snapshot from ProcessData.
     pub fn set_inputs(&mut self, data: &ProcessData) {
-        // Pack digital inputs (4 bytes)
-        self.inputs[0..4].copy_from_slice(&data.digital_inputs[0].to_le_bytes());
-        // Pack analog inputs (32 bytes = 16 x 2 bytes)
-        for (i, &val) in data.analog_inputs.iter().enumerate() {
-            let offset = 4 + i * 2;
-            if offset + 2 <= self.inputs.len() {
-                self.inputs[offset..offset + 2].copy_from_slice(&val.to_le_bytes());
-            }
-        }
+        pack_snapshot(
+            &mut self.inputs,
+            data.digital_inputs(),
+            data.analog_inputs(),
+        );
     }
 
     /// Set output snapshot from ProcessData.
     pub fn set_outputs(&mut self, data: &ProcessData) {
-        // Pack digital outputs (4 bytes)
-        self.outputs[0..4].copy_from_slice(&data.digital_outputs[0].to_le_bytes());
-        // Pack analog outputs (32 bytes = 16 x 2 bytes)
-        for (i, &val) in data.analog_outputs.iter().enumerate() {
-            let offset = 4 + i * 2;
-            if offset + 2 <= self.outputs.len() {
-                self.outputs[offset..offset + 2].copy_from_slice(&val.to_le_bytes());
-            }
-        }
+        pack_snapshot(
+            &mut self.outputs,
+            data.digital_outputs(),
+            data.analog_outputs(),
+        );
     }
 
     /// Set WKC values.
@@ -160,6 +197,16 @@ impl FaultFrame {
     pub fn set_fault(&mut self, reason: FaultReason) {
         self.fault_reason = reason;
     }
+
+    /// Set the index of the scheduler task that ran in this frame.
+    pub fn set_task(&mut self, task: usize) {
+        self.task = task;
+    }
+
+    /// Set the ST source location of the fault.
+    pub fn set_source_location(&mut self, location: SourceLocation) {
+        self.source_location = Some(location);
+    }
 }
 
 /// Pre-allocated ring buffer for fault frame recording.
@@ -184,10 +231,19 @@ pub struct FaultRecorder {
 }
 
 impl FaultRecorder {
-    /// Create a new fault recorder with the specified capacity.
+    /// Create a new fault recorder with the specified capacity, for the
+    /// default process image.
     pub fn new(capacity: usize) -> Self {
+        Self::for_process_image(capacity, &ProcessImageConfig::default())
+    }
+
+    /// Create a new fault recorder with the specified capacity, whose frames
+    /// capture a process image of the configured size.
+    pub fn for_process_image(capacity: usize, config: &ProcessImageConfig) -> Self {
         let capacity = capacity.max(1);
-        let frames: Vec<FaultFrame> = (0..capacity).map(|_| FaultFrame::default()).collect();
+        let frames: Vec<FaultFrame> = (0..capacity)
+            .map(|_| FaultFrame::for_process_image(config))
+            .collect();
 
         Self {
             frames: frames.into_boxed_slice(),
@@ -238,7 +294,7 @@ impl FaultRecorder {
         let timestamp_ns = self.start_time.elapsed().as_nanos() as u64;
         let idx = self.write_pos;
 
-        self.frames[idx] = FaultFrame::new(cycle, timestamp_ns, phase_timings);
+        self.frames[idx].start(cycle, timestamp_ns, phase_timings);
 
         self.write_pos = (self.write_pos + 1) % self.frames.len();
         self.frame_count = self.frame_count.saturating_add(1);
@@ -266,7 +322,7 @@ impl FaultRecorder {
         let timestamp_ns = self.start_time.elapsed().as_nanos() as u64;
         let idx = self.write_pos;
 
-        self.frames[idx] = FaultFrame::new(cycle, timestamp_ns, phase_timings);
+        self.frames[idx].start(cycle, timestamp_ns, phase_timings);
         self.frames[idx].set_fault(reason);
         self.fault_frame_index = Some(idx);
 
@@ -295,7 +351,7 @@ impl FaultRecorder {
         let timestamp_ns = self.start_time.elapsed().as_nanos() as u64;
         let idx = self.write_pos;
 
-        self.frames[idx] = FaultFrame::new(cycle, timestamp_ns, phase_timings);
+        self.frames[idx].start(cycle, timestamp_ns, phase_timings);
         self.frames[idx].set_inputs(inputs);
         self.frames[idx].set_outputs(outputs);
         self.frames[idx].set_fault(reason);
@@ -306,6 +362,24 @@ impl FaultRecorder {
         self.frozen = true;
     }
 
+    /// Attach the ST source location to the recorded fault frame.
+    ///
+    /// Does nothing if no fault has been recorded.
+    pub fn set_fault_location(&mut self, location: SourceLocation) {
+        if let Some(idx) = self.fault_frame_index {
+            self.frames[idx].set_source_location(location);
+        }
+    }
+
+    /// Attach the index of the faulting task to the recorded fault frame.
+    ///
+    /// Does nothing if no fault has been recorded.
+    pub fn set_fault_task(&mut self, task: usize) {
+        if let Some(idx) = self.fault_frame_index {
+            self.frames[idx].set_task(task);
+        }
+    }
+
     /// Freeze the recorder without recording a new fault frame.
     ///
     /// Use this when the fault was already recorded via `record_cycle`.
@@ -362,7 +436,7 @@ impl FaultRecorder {
     /// Reset the recorder, clearing all frames and unfreezing.
     pub fn reset(&mut self) {
         for frame in self.frames.iter_mut() {
-            *frame = FaultFrame::default();
+            frame.clear();
         }
         self.write_pos = 0;
         self.frame_count = 0;
@@ -378,6 +452,7 @@ impl FaultRecorder {
 
         Some(FaultSummary {
             cycle: fault_frame.cycle,
+            task: fault_frame.task,
             reason: fault_frame.fault_reason,
             execution_time: fault_frame.phase_timings.total,
             io_read_time: fault_frame.phase_timings.io_read,
@@ -389,6 +464,7 @@ impl FaultRecorder {
                 .map(|(actual, expected)| actual != expected)
                 .unwrap_or(false),
             frames_available: self.frame_count(),
+            source_location: fault_frame.source_location.clone(),
         })
     }
 }
@@ -398,6 +474,8 @@ impl FaultRecorder {
 pub struct FaultSummary {
     /// Cycle at which the fault occurred.
     pub cycle: u64,
+    /// Index of the scheduler task that faulted.
+    pub task: usize,
     /// Reason for the fault.
     pub reason: FaultReason,
     /// Total execution time of the faulting cycle.
@@ -412,6 +490,8 @@ pub struct FaultSummary {
     pub wkc_mismatch: bool,
     /// Number of frames available for analysis.
     pub frames_available: usize,
+    /// ST source location of the fault, if known.
+    pub source_location: Option<SourceLocation>,
 }
 
 #[cfg(test)]
@@ -486,6 +566,41 @@ mod tests {
         assert!(recorder.record_cycle(6, timings).is_none());
     }
 
+    #[test]
+    fn test_fault_source_location() {
+        use plc_common::debug_map::PouKind;
+
+        let location = SourceLocation {
+            file: "conveyor.st".into(),
+            line: 42,
+            pou_kind: PouKind::FunctionBlock,
+            pou: "Conveyor".into(),
+        };
+        let mut recorder = FaultRecorder::new(4);
+        recorder.set_fault_location(location.clone());
+        assert!(recorder.fault_frame().is_none());
+
+        recorder.record_fault(3, FaultReason::LogicError, CyclePhaseTimings::default());
+        recorder.set_fault_location(location.clone());
+        let summary = recorder.fault_summary().unwrap();
+        assert_eq!(summary.source_location, Some(location));
+    }
+
+    #[test]
+    fn test_fault_task() {
+        let mut recorder = FaultRecorder::new(4);
+        recorder
+            .record_cycle(1, CyclePhaseTimings::default())
+            .unwrap()
+            .set_task(1);
+        assert_eq!(recorder.frames_chronological().next().unwrap().task, 1);
+
+        recorder.record_fault(2, FaultReason::LogicError, CyclePhaseTimings::default());
+        assert_eq!(recorder.fault_summary().unwrap().task, 0);
+        recorder.set_fault_task(2);
+        assert_eq!(recorder.fault_summary().unwrap().task, 2);
+    }
+
     #[test]
     fn test_fault_summary() {
         let mut recorder = FaultRecorder::new(10);
@@ -544,9 +659,9 @@ mod tests {
         let mut frame = FaultFrame::default();
 
         let mut data = ProcessData::default();
-        data.digital_inputs[0] = 0xDEADBEEF;
-        data.analog_inputs[0] = 1234;
-        data.analog_inputs[1] = -5678;
+        data.digital_inputs_mut()[0] = 0xDEADBEEF;
+        data.analog_inputs_mut()[0] = 1234;
+        data.analog_inputs_mut()[1] = -5678;
 
         frame.set_inputs(&data);
 
@@ -568,4 +683,38 @@ mod tests {
             -5678
         );
     }
+
+    #[test]
+    fn test_configured_snapshot_size() {
+        let config = ProcessImageConfig {
+            digital_inputs: 400,
+            digital_outputs: 64,
+            analog_inputs: 0,
+            analog_outputs: 2,
+        };
+        let mut recorder = FaultRecorder::for_process_image(2, &config);
+        let mut data = ProcessData::new(&config);
+        data.digital_inputs_mut()[12] = 0x0102_0304;
+        data.digital_outputs_mut()[1] = 7;
+        data.analog_outputs_mut()[1] = -2;
+
+        let frame = recorder
+            .record_cycle(1, CyclePhaseTimings::default())
+            .unwrap();
+        frame.set_inputs(&data);
+        frame.set_outputs(&data);
+        assert_eq!(frame.inputs.len(), 52);
+        assert_eq!(&frame.inputs[48..], &[4, 3, 2, 1]);
+        assert_eq!(frame.outputs.len(), 12);
+        assert_eq!(&frame.outputs[4..8], &[7, 0, 0, 0]);
+        assert_eq!(&frame.outputs[10..], &(-2i16).to_le_bytes());
+
+        // Reused frames start out empty
+        recorder.record_cycle(2, CyclePhaseTimings::default());
+        let frame = recorder
+            .record_cycle(3, CyclePhaseTimings::default())
+            .unwrap();
+        assert!(frame.inputs.iter().all(|&b| b == 0));
+        assert_eq!(frame.inputs.len(), 52);
+    }
 }
@@ -0,0 +1,518 @@
+//! Forcing of process-image points and variables.
+//!
+//! During commissioning an input can be forced on, or an output pinned,
+//! while the logic keeps running. Forces are addressed like IEC direct
+//! variables, using the byte layout of the process image ABI:
+//!
+//! | Address          | Target                                    | Value  |
+//! |------------------|-------------------------------------------|--------|
+//! | `%IX<byte>.<bit>`| digital input `byte * 8 + bit`            | `BOOL` |
+//! | `%ID<word>`      | digital inputs `32 * word ..` as a `DWORD`| `DWORD`|
+//! | `%IW<channel>`   | analog input channel                      | `INT`  |
+//! | `%QX`, `%QD`, `%QW` | the same for outputs                   |        |
+//!
+//! Anything else names a program variable (`Main.speed`).
+//!
+//! Input forces are applied after the inputs are latched and output forces
+//! before the outputs are committed, so the logic sees forced inputs and the
+//! fieldbus sees forced outputs. Variables are written before and after each
+//! step, so the program reads the forced value even if it assigns the
+//! variable itself.
+
+use crate::io_image::ProcessData;
+use crate::symbols::VarValue;
+use plc_common::config::ProcessImageConfig;
+use plc_common::error::{PlcError, PlcResult};
+use std::fmt;
+use std::str::FromStr;
+
+/// Process image area a force applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Area {
+    /// Inputs (`%I`), written by the fieldbus.
+    Input,
+    /// Outputs (`%Q`), written by the logic.
+    Output,
+}
+
+impl Area {
+    fn prefix(self) -> char {
+        match self {
+            Area::Input => 'I',
+            Area::Output => 'Q',
+        }
+    }
+}
+
+/// What a force overrides.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ForceTarget {
+    /// A single digital point, numbered from 0.
+    Bit { area: Area, point: usize },
+    /// A packed word of 32 digital points.
+    Word { area: Area, word: usize },
+    /// An analog channel.
+    Analog { area: Area, channel: usize },
+    /// A program variable by qualified name.
+    Variable(String),
+}
+
+impl ForceTarget {
+    /// Whether the target is a variable rather than a process image point.
+    pub fn is_variable(&self) -> bool {
+        matches!(self, ForceTarget::Variable(_))
+    }
+
+    /// Check that a process image point lies inside the configured image.
+    pub(crate) fn check_range(&self, image: &ProcessImageConfig) -> PlcResult<()> {
+        let (index, count) = match *self {
+            ForceTarget::Bit { area, point } => (point, digital_points(area, image)),
+            ForceTarget::Word { area, word } => (word, digital_points(area, image).div_ceil(32)),
+            ForceTarget::Analog { area, channel } => (channel, analog_channels(area, image)),
+            // Variables are checked against the symbol table when accessed.
+            ForceTarget::Variable(_) => return Ok(()),
+        };
+        if index < count as usize {
+            Ok(())
+        } else {
+            Err(PlcError::Config(format!(
+                "{self} is outside the process image ({count} configured)"
+            )))
+        }
+    }
+
+    /// Check the target against the size of the process image and convert
+    /// the value to the form it is applied in.
+    fn normalize(&self, value: VarValue, image: &ProcessImageConfig) -> PlcResult<VarValue> {
+        let mismatch = || PlcError::Config(format!("cannot force {self} to {value}"));
+        self.check_range(image)?;
+
+        match *self {
+            ForceTarget::Bit { .. } => match value {
+                VarValue::Bool(_) => Ok(value),
+                VarValue::Int(v @ 0..=1) => Ok(VarValue::Bool(v == 1)),
+                VarValue::UInt(v @ 0..=1) => Ok(VarValue::Bool(v == 1)),
+                _ => Err(mismatch()),
+            },
+            ForceTarget::Word { .. } => {
+                let v = match value {
+                    VarValue::UInt(v) => v,
+                    VarValue::Int(v) => u64::try_from(v).map_err(|_| mismatch())?,
+                    _ => return Err(mismatch()),
+                };
+                u32::try_from(v)
+                    .map(|v| VarValue::UInt(u64::from(v)))
+                    .map_err(|_| mismatch())
+            }
+            ForceTarget::Analog { .. } => {
+                let v = match value {
+                    VarValue::Int(v) => v,
+                    VarValue::UInt(v) => i64::try_from(v).map_err(|_| mismatch())?,
+                    _ => return Err(mismatch()),
+                };
+                i16::try_from(v)
+                    .map(|v| VarValue::Int(i64::from(v)))
+                    .map_err(|_| mismatch())
+            }
+            ForceTarget::Variable(_) => Ok(value),
+        }
+    }
+
+    /// Read a process image point: inputs from `inputs`, outputs from
+    /// `outputs`. Returns `None` for variables and points outside the image.
+    pub fn read(&self, inputs: &ProcessData, outputs: &ProcessData) -> Option<VarValue> {
+        let digital = |area| match area {
+            Area::Input => inputs.digital_inputs(),
+            Area::Output => outputs.digital_outputs(),
+        };
+        match *self {
+            ForceTarget::Bit { area, point } => digital(area)
+                .get(point / 32)
+                .map(|word| VarValue::Bool(word & (1 << (point % 32)) != 0)),
+            ForceTarget::Word { area, word } => digital(area)
+                .get(word)
+                .map(|&word| VarValue::UInt(u64::from(word))),
+            ForceTarget::Analog { area, channel } => {
+                let analog = match area {
+                    Area::Input => inputs.analog_inputs(),
+                    Area::Output => outputs.analog_outputs(),
+                };
+                analog
+                    .get(channel)
+                    .map(|&value| VarValue::Int(i64::from(value)))
+            }
+            ForceTarget::Variable(_) => None,
+        }
+    }
+
+    fn area(&self) -> Option<Area> {
+        match *self {
+            ForceTarget::Bit { area, .. }
+            | ForceTarget::Word { area, .. }
+            | ForceTarget::Analog { area, .. } => Some(area),
+            ForceTarget::Variable(_) => None,
+        }
+    }
+}
+
+fn digital_points(area: Area, image: &ProcessImageConfig) -> u32 {
+    match area {
+        Area::Input => image.digital_inputs,
+        Area::Output => image.digital_outputs,
+    }
+}
+
+fn analog_channels(area: Area, image: &ProcessImageConfig) -> u32 {
+    match area {
+        Area::Input => image.analog_inputs,
+        Area::Output => image.analog_outputs,
+    }
+}
+
+impl fmt::Display for ForceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForceTarget::Bit { area, point } => {
+                write!(f, "%{}X{}.{}", area.prefix(), point / 8, point % 8)
+            }
+            ForceTarget::Word { area, word } => write!(f, "%{}D{word}", area.prefix()),
+            ForceTarget::Analog { area, channel } => write!(f, "%{}W{channel}", area.prefix()),
+            ForceTarget::Variable(name) => f.write_str(name),
+        }
+    }
+}
+
+impl FromStr for ForceTarget {
+    type Err = PlcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let Some(address) = s.strip_prefix('%') else {
+            if s.is_empty() {
+                return Err(PlcError::Config("empty force target".into()));
+            }
+            return Ok(ForceTarget::Variable(s.to_string()));
+        };
+
+        let invalid = || PlcError::Config(format!("invalid address: {s}"));
+        let number = |digits: &str| digits.parse::<usize>().map_err(|_| invalid());
+        let mut chars = address.chars();
+        let area = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            Some('I') => Area::Input,
+            Some('Q') => Area::Output,
+            _ => return Err(invalid()),
+        };
+        let size = chars.next().map(|c| c.to_ascii_uppercase());
+        let rest = chars.as_str();
+
+        match size {
+            Some('X') => {
+                let (byte, bit) = rest.split_once('.').ok_or_else(invalid)?;
+                let (byte, bit) = (number(byte)?, number(bit)?);
+                if bit >= 8 {
+                    return Err(invalid());
+                }
+                let point = byte.checked_mul(8).ok_or_else(invalid)? + bit;
+                Ok(ForceTarget::Bit { area, point })
+            }
+            Some('D') => Ok(ForceTarget::Word {
+                area,
+                word: number(rest)?,
+            }),
+            Some('W') => Ok(ForceTarget::Analog {
+                area,
+                channel: number(rest)?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// An active force.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Force {
+    /// What is forced.
+    pub target: ForceTarget,
+    /// The forced value.
+    pub value: VarValue,
+}
+
+impl fmt::Display for Force {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} := {}", self.target, self.value)
+    }
+}
+
+/// The active forces, in the order they were set.
+#[derive(Debug, Clone, Default)]
+pub struct ForceTable {
+    forces: Vec<Force>,
+}
+
+impl ForceTable {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Force a target, replacing any earlier force on it.
+    ///
+    /// Process image targets are checked against `image`; variable forces
+    /// are checked when they are first written.
+    pub fn set(
+        &mut self,
+        target: ForceTarget,
+        value: VarValue,
+        image: &ProcessImageConfig,
+    ) -> PlcResult<()> {
+        let value = target.normalize(value, image)?;
+        match self.forces.iter_mut().find(|force| force.target == target) {
+            Some(force) => force.value = value,
+            None => self.forces.push(Force { target, value }),
+        }
+        Ok(())
+    }
+
+    /// Release the force on a target. Returns whether it was forced.
+    pub fn remove(&mut self, target: &ForceTarget) -> bool {
+        let before = self.forces.len();
+        self.forces.retain(|force| &force.target != target);
+        self.forces.len() != before
+    }
+
+    /// Release all forces. Returns how many were active.
+    pub fn clear(&mut self) -> usize {
+        let count = self.forces.len();
+        self.forces.clear();
+        count
+    }
+
+    /// Get the force on a target, if any.
+    pub fn get(&self, target: &ForceTarget) -> Option<VarValue> {
+        self.forces
+            .iter()
+            .find(|force| &force.target == target)
+            .map(|force| force.value)
+    }
+
+    /// Number of active forces.
+    pub fn len(&self) -> usize {
+        self.forces.len()
+    }
+
+    /// Whether no force is active.
+    pub fn is_empty(&self) -> bool {
+        self.forces.is_empty()
+    }
+
+    /// Iterate over the active forces.
+    pub fn iter(&self) -> impl Iterator<Item = &Force> {
+        self.forces.iter()
+    }
+
+    /// Iterate over the forced variables.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, VarValue)> {
+        self.forces.iter().filter_map(|force| match &force.target {
+            ForceTarget::Variable(name) => Some((name.as_str(), force.value)),
+            _ => None,
+        })
+    }
+
+    /// Override latched inputs with the input forces.
+    pub fn apply_inputs(&self, data: &mut ProcessData) {
+        self.apply(Area::Input, data);
+    }
+
+    /// Override outputs with the output forces.
+    pub fn apply_outputs(&self, data: &mut ProcessData) {
+        self.apply(Area::Output, data);
+    }
+
+    fn apply(&self, area: Area, data: &mut ProcessData) {
+        for force in self.forces.iter().filter(|f| f.target.area() == Some(area)) {
+            match (&force.target, force.value) {
+                (ForceTarget::Bit { point, .. }, VarValue::Bool(on)) => {
+                    if let Some(word) = digital_mut(area, data).get_mut(point / 32) {
+                        let mask = 1 << (point % 32);
+                        if on {
+                            *word |= mask;
+                        } else {
+                            *word &= !mask;
+                        }
+                    }
+                }
+                (ForceTarget::Word { word, .. }, VarValue::UInt(v)) => {
+                    if let Some(slot) = digital_mut(area, data).get_mut(*word) {
+                        *slot = v as u32;
+                    }
+                }
+                (ForceTarget::Analog { channel, .. }, VarValue::Int(v)) => {
+                    let analog = match area {
+                        Area::Input => data.analog_inputs_mut(),
+                        Area::Output => data.analog_outputs_mut(),
+                    };
+                    if let Some(slot) = analog.get_mut(*channel) {
+                        *slot = v as i16;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn digital_mut(area: Area, data: &mut ProcessData) -> &mut [u32] {
+    match area {
+        Area::Input => data.digital_inputs_mut(),
+        Area::Output => data.digital_outputs_mut(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> ProcessImageConfig {
+        ProcessImageConfig {
+            digital_inputs: 64,
+            digital_outputs: 32,
+            analog_inputs: 4,
+            analog_outputs: 2,
+        }
+    }
+
+    #[test]
+    fn test_parse_and_display_addresses() {
+        let cases = [
+            (
+                "%IX0.0",
+                ForceTarget::Bit {
+                    area: Area::Input,
+                    point: 0,
+                },
+            ),
+            (
+                "%IX2.3",
+                ForceTarget::Bit {
+                    area: Area::Input,
+                    point: 19,
+                },
+            ),
+            (
+                "%QX1.7",
+                ForceTarget::Bit {
+                    area: Area::Output,
+                    point: 15,
+                },
+            ),
+            (
+                "%ID1",
+                ForceTarget::Word {
+                    area: Area::Input,
+                    word: 1,
+                },
+            ),
+            (
+                "%QW1",
+                ForceTarget::Analog {
+                    area: Area::Output,
+                    channel: 1,
+                },
+            ),
+            ("Main.speed", ForceTarget::Variable("Main.speed".into())),
+        ];
+        for (text, target) in cases {
+            assert_eq!(text.parse::<ForceTarget>().unwrap(), target);
+            assert_eq!(target.to_string(), text);
+        }
+        assert_eq!(
+            "%qx0.1".parse::<ForceTarget>().unwrap(),
+            ForceTarget::Bit {
+                area: Area::Output,
+                point: 1
+            }
+        );
+        for bad in ["%IX0.8", "%IX0", "%MW0", "%QB0", "%IWx", ""] {
+            assert!(bad.parse::<ForceTarget>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_set_validates_target_and_value() {
+        let mut table = ForceTable::new();
+        let image = image();
+        let target = |s: &str| s.parse::<ForceTarget>().unwrap();
+
+        table
+            .set(target("%IX7.7"), VarValue::Int(1), &image)
+            .unwrap();
+        assert_eq!(table.get(&target("%IX7.7")), Some(VarValue::Bool(true)));
+        assert!(table
+            .set(target("%QX4.0"), VarValue::Bool(true), &image)
+            .is_err());
+        assert!(table
+            .set(target("%IX0.0"), VarValue::Int(2), &image)
+            .is_err());
+        assert!(table.set(target("%QW2"), VarValue::Int(0), &image).is_err());
+        assert!(table
+            .set(target("%QW1"), VarValue::Int(40_000), &image)
+            .is_err());
+        assert!(table
+            .set(target("%ID0"), VarValue::Real(1.0), &image)
+            .is_err());
+
+        // Forcing the same target again replaces the value
+        table
+            .set(target("%IX7.7"), VarValue::Bool(false), &image)
+            .unwrap();
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(&target("%IX7.7")), Some(VarValue::Bool(false)));
+
+        assert!(table.remove(&target("%IX7.7")));
+        assert!(!table.remove(&target("%IX7.7")));
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_apply_overrides_only_its_area() {
+        let image = image();
+        let mut table = ForceTable::new();
+        let target = |s: &str| s.parse::<ForceTarget>().unwrap();
+        table
+            .set(target("%IX4.1"), VarValue::Bool(true), &image)
+            .unwrap();
+        table
+            .set(target("%IX0.0"), VarValue::Bool(false), &image)
+            .unwrap();
+        table
+            .set(target("%IW3"), VarValue::Int(-5), &image)
+            .unwrap();
+        table
+            .set(target("%QD0"), VarValue::UInt(0xF0), &image)
+            .unwrap();
+        table
+            .set(target("%QW0"), VarValue::Int(123), &image)
+            .unwrap();
+        table
+            .set(target("Main.x"), VarValue::Int(1), &image)
+            .unwrap();
+
+        let mut data = ProcessData::new(&image);
+        data.digital_inputs_mut()[0] = 1;
+        data.digital_outputs_mut()[0] = 0x0F;
+        table.apply_inputs(&mut data);
+        assert_eq!(data.digital_inputs(), &[0, 1 << 1]);
+        assert_eq!(data.analog_inputs()[3], -5);
+        assert_eq!(data.digital_outputs()[0], 0x0F);
+
+        table.apply_outputs(&mut data);
+        assert_eq!(data.digital_outputs()[0], 0xF0);
+        assert_eq!(data.analog_outputs(), &[123, 0]);
+
+        assert_eq!(
+            table.variables().collect::<Vec<_>>(),
+            vec![("Main.x", VarValue::Int(1))]
+        );
+        assert_eq!(table.clear(), 6);
+    }
+}
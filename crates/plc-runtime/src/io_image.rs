@@ -27,69 +27,155 @@
 //! │ └──────────────────────┘  └──────────────────────┘             │
 //! └─────────────────────────────────────────────────────────────────┘
 //! ```
+//!
+//! The dimensions come from the `[process_image]` section of the runtime
+//! configuration (see [`IoImage::with_config`]). All buffers are allocated
+//! when the image is created; reads and writes only copy values.
 
 use crossbeam_utils::CachePadded;
+use plc_common::config::ProcessImageConfig;
 use std::cell::UnsafeCell;
 use std::sync::atomic::{AtomicU64, Ordering};
 
-/// Cache line size for alignment (common x86_64 value).
-const CACHE_LINE_SIZE: usize = 64;
-
-/// Number of digital input/output words (32 bits each).
-const DI_WORDS: usize = 1;
-const DO_WORDS: usize = 1;
-
-/// Number of analog input/output channels.
-const AI_CHANNELS: usize = 16;
-const AO_CHANNELS: usize = 16;
-
-/// Raw process data structure, cache-line aligned.
+/// Raw process data: digital and analog inputs and outputs.
 ///
-/// This struct holds the actual I/O values. It's designed to fit
-/// within cache lines efficiently and avoid false sharing.
-#[repr(C, align(64))]
-#[derive(Debug, Clone, Copy)]
+/// The region sizes are fixed when the data is created from a
+/// [`ProcessImageConfig`]; the accessors hand out slices, so values can be
+/// changed but the regions never grow or shrink. Copying between images of
+/// the same size with [`copy_from`](Self::copy_from) does not allocate.
+#[derive(Debug, PartialEq, Eq)]
 pub struct ProcessData {
-    /// Digital inputs (32 bits = 32 discrete inputs).
-    pub digital_inputs: [u32; DI_WORDS],
-    /// Digital outputs (32 bits = 32 discrete outputs).
-    pub digital_outputs: [u32; DO_WORDS],
+    /// Digital inputs, 32 points per word.
+    digital_inputs: Box<[u32]>,
+    /// Digital outputs, 32 points per word.
+    digital_outputs: Box<[u32]>,
     /// Analog inputs (16-bit signed values).
-    pub analog_inputs: [i16; AI_CHANNELS],
+    analog_inputs: Box<[i16]>,
     /// Analog outputs (16-bit signed values).
-    pub analog_outputs: [i16; AO_CHANNELS],
-    /// Padding to ensure cache line alignment.
-    _pad: [u8; CACHE_LINE_SIZE
-        - ((DI_WORDS + DO_WORDS) * 4 + (AI_CHANNELS + AO_CHANNELS) * 2) % CACHE_LINE_SIZE],
+    analog_outputs: Box<[i16]>,
 }
 
-impl Default for ProcessData {
-    fn default() -> Self {
+impl Clone for ProcessData {
+    fn clone(&self) -> Self {
         Self {
-            digital_inputs: [0; DI_WORDS],
-            digital_outputs: [0; DO_WORDS],
-            analog_inputs: [0; AI_CHANNELS],
-            analog_outputs: [0; AO_CHANNELS],
-            _pad: [0; CACHE_LINE_SIZE
-                - ((DI_WORDS + DO_WORDS) * 4 + (AI_CHANNELS + AO_CHANNELS) * 2) % CACHE_LINE_SIZE],
+            digital_inputs: self.digital_inputs.clone(),
+            digital_outputs: self.digital_outputs.clone(),
+            analog_inputs: self.analog_inputs.clone(),
+            analog_outputs: self.analog_outputs.clone(),
         }
     }
+
+    /// Reuses the existing regions if the dimensions match.
+    fn clone_from(&mut self, source: &Self) {
+        self.digital_inputs.clone_from(&source.digital_inputs);
+        self.digital_outputs.clone_from(&source.digital_outputs);
+        self.analog_inputs.clone_from(&source.analog_inputs);
+        self.analog_outputs.clone_from(&source.analog_outputs);
+    }
+}
+
+impl Default for ProcessData {
+    fn default() -> Self {
+        Self::new(&ProcessImageConfig::default())
+    }
+}
+
+/// Copy the common prefix of two regions.
+#[inline]
+fn copy_prefix<T: Copy>(target: &mut [T], source: &[T]) {
+    let len = target.len().min(source.len());
+    target[..len].copy_from_slice(&source[..len]);
 }
 
 impl ProcessData {
+    /// Create zeroed process data with the configured dimensions.
+    pub fn new(config: &ProcessImageConfig) -> Self {
+        Self {
+            digital_inputs: vec![0; config.di_words()].into_boxed_slice(),
+            digital_outputs: vec![0; config.do_words()].into_boxed_slice(),
+            analog_inputs: vec![0; config.ai_channels()].into_boxed_slice(),
+            analog_outputs: vec![0; config.ao_channels()].into_boxed_slice(),
+        }
+    }
+
+    /// Digital input words.
+    #[inline]
+    pub fn digital_inputs(&self) -> &[u32] {
+        &self.digital_inputs
+    }
+
+    /// Mutable digital input words.
+    #[inline]
+    pub fn digital_inputs_mut(&mut self) -> &mut [u32] {
+        &mut self.digital_inputs
+    }
+
+    /// Digital output words.
+    #[inline]
+    pub fn digital_outputs(&self) -> &[u32] {
+        &self.digital_outputs
+    }
+
+    /// Mutable digital output words.
+    #[inline]
+    pub fn digital_outputs_mut(&mut self) -> &mut [u32] {
+        &mut self.digital_outputs
+    }
+
+    /// Analog input channels.
+    #[inline]
+    pub fn analog_inputs(&self) -> &[i16] {
+        &self.analog_inputs
+    }
+
+    /// Mutable analog input channels.
+    #[inline]
+    pub fn analog_inputs_mut(&mut self) -> &mut [i16] {
+        &mut self.analog_inputs
+    }
+
+    /// Analog output channels.
+    #[inline]
+    pub fn analog_outputs(&self) -> &[i16] {
+        &self.analog_outputs
+    }
+
+    /// Mutable analog output channels.
+    #[inline]
+    pub fn analog_outputs_mut(&mut self) -> &mut [i16] {
+        &mut self.analog_outputs
+    }
+
+    /// Copy the inputs of `other`. If the sizes differ, the common prefix of
+    /// each region is copied and the rest is left unchanged.
+    #[inline]
+    pub fn copy_inputs_from(&mut self, other: &ProcessData) {
+        copy_prefix(&mut self.digital_inputs, &other.digital_inputs);
+        copy_prefix(&mut self.analog_inputs, &other.analog_inputs);
+    }
+
+    /// Copy the outputs of `other`, like
+    /// [`copy_inputs_from`](Self::copy_inputs_from).
+    #[inline]
+    pub fn copy_outputs_from(&mut self, other: &ProcessData) {
+        copy_prefix(&mut self.digital_outputs, &other.digital_outputs);
+        copy_prefix(&mut self.analog_outputs, &other.analog_outputs);
+    }
+
+    /// Copy inputs and outputs of `other`.
+    #[inline]
+    pub fn copy_from(&mut self, other: &ProcessData) {
+        self.copy_inputs_from(other);
+        self.copy_outputs_from(other);
+    }
+
     /// Read a digital input bit.
     #[inline]
     pub fn read_di(&self, bit: usize) -> bool {
-        let word = bit / 32;
-        let offset = bit % 32;
-        if word < DI_WORDS {
-            (self.digital_inputs[word] >> offset) & 1 != 0
-        } else {
-            false
-        }
+        self.read_di_word(bit / 32) >> (bit % 32) & 1 != 0
     }
 
-    /// Read all digital inputs as a u32.
+    /// Read a word of 32 digital inputs.
     #[inline]
     pub fn read_di_word(&self, word: usize) -> u32 {
         self.digital_inputs.get(word).copied().unwrap_or(0)
@@ -98,18 +184,16 @@ impl ProcessData {
     /// Write a digital output bit.
     #[inline]
     pub fn write_do(&mut self, bit: usize, value: bool) {
-        let word = bit / 32;
-        let offset = bit % 32;
-        if word < DO_WORDS {
+        if let Some(word) = self.digital_outputs.get_mut(bit / 32) {
             if value {
-                self.digital_outputs[word] |= 1 << offset;
+                *word |= 1 << (bit % 32);
             } else {
-                self.digital_outputs[word] &= !(1 << offset);
+                *word &= !(1 << (bit % 32));
             }
         }
     }
 
-    /// Write all digital outputs as a u32.
+    /// Write a word of 32 digital outputs.
     #[inline]
     pub fn write_do_word(&mut self, word: usize, value: u32) {
         if let Some(w) = self.digital_outputs.get_mut(word) {
@@ -134,8 +218,11 @@ impl ProcessData {
 
 /// A seqlock-protected double buffer for one-way data transfer.
 ///
-/// One thread writes to the back buffer, then commits to swap it.
-/// Another thread reads from the front buffer with seqlock protection.
+/// The writer fills a private staging buffer, then commits it: the staging
+/// data is copied into the back buffer, which becomes the front. Readers copy
+/// the front buffer with seqlock protection. Because the shared buffers are
+/// only ever written by copying into their existing regions, a reader that
+/// races a writer sees torn values (and retries), never freed memory.
 struct SeqlockBuffer {
     /// Sequence number (odd = write in progress).
     sequence: CachePadded<AtomicU64>,
@@ -145,21 +232,29 @@ struct SeqlockBuffer {
     buf1: CachePadded<UnsafeCell<ProcessData>>,
     /// Which buffer is currently the "published" front (0 or 1).
     front_idx: CachePadded<AtomicU64>,
+    /// Data being written; only accessed by the writer.
+    staging: CachePadded<UnsafeCell<ProcessData>>,
+    /// Dimensions of the buffers.
+    config: ProcessImageConfig,
 }
 
 impl SeqlockBuffer {
-    fn new() -> Self {
+    fn new(config: &ProcessImageConfig) -> Self {
+        let data = ProcessData::new(config);
         Self {
             sequence: CachePadded::new(AtomicU64::new(0)),
-            buf0: CachePadded::new(UnsafeCell::new(ProcessData::default())),
-            buf1: CachePadded::new(UnsafeCell::new(ProcessData::default())),
+            buf0: CachePadded::new(UnsafeCell::new(data.clone())),
+            buf1: CachePadded::new(UnsafeCell::new(data.clone())),
             front_idx: CachePadded::new(AtomicU64::new(0)),
+            staging: CachePadded::new(UnsafeCell::new(data)),
+            config: *config,
         }
     }
 
-    /// Read data with seqlock protection.
+    /// Read data with seqlock protection into `target`, which should have
+    /// the buffer's dimensions.
     /// Spins if a write is in progress, ensuring a consistent snapshot.
-    fn read(&self) -> ProcessData {
+    fn read_into(&self, target: &mut ProcessData) {
         loop {
             let seq1 = self.sequence.load(Ordering::Acquire);
 
@@ -172,17 +267,19 @@ impl SeqlockBuffer {
             // Read from the front buffer
             let front = self.front_idx.load(Ordering::Acquire);
             // SAFETY: We check sequence before and after to ensure consistency.
-            // The writer only touches the back buffer, never the front.
+            // The writer only copies into the back buffer, never the front,
+            // and never reallocates either buffer.
             let data = if front == 0 {
-                unsafe { *self.buf0.get() }
+                unsafe { &*self.buf0.get() }
             } else {
-                unsafe { *self.buf1.get() }
+                unsafe { &*self.buf1.get() }
             };
+            target.copy_from(data);
 
             // Verify sequence hasn't changed
             let seq2 = self.sequence.load(Ordering::Acquire);
             if seq1 == seq2 {
-                return data;
+                return;
             }
 
             // Sequence changed during read - retry
@@ -190,31 +287,42 @@ impl SeqlockBuffer {
         }
     }
 
-    /// Begin writing to the back buffer.
-    /// Returns a mutable reference to the back buffer.
+    /// Read a copy of the data.
+    fn read(&self) -> ProcessData {
+        let mut data = ProcessData::new(&self.config);
+        self.read_into(&mut data);
+        data
+    }
+
+    /// Begin writing.
+    /// Returns a mutable reference to the staging buffer, which holds the
+    /// values of the last write.
     ///
     /// # Safety
     /// Only one thread should call this at a time.
     /// The seqlock protocol ensures readers see consistent data.
     #[allow(clippy::mut_from_ref)] // Interior mutability via UnsafeCell is intentional
     fn begin_write(&self) -> &mut ProcessData {
+        // SAFETY: Single writer assumed; readers never touch the staging buffer
+        unsafe { &mut *self.staging.get() }
+    }
+
+    /// Commit the write and swap buffers.
+    fn commit(&self) {
         // Increment sequence to odd (write in progress)
         self.sequence.fetch_add(1, Ordering::Release);
 
-        // Write to the back buffer (opposite of front)
-        let front = self.front_idx.load(Ordering::Acquire);
-        if front == 0 {
-            // SAFETY: Single writer assumed, and we hold the seqlock
+        // Copy staging into the back buffer (opposite of front)
+        let old_front = self.front_idx.load(Ordering::Acquire);
+        // SAFETY: Single writer assumed, and we hold the seqlock
+        let back = if old_front == 0 {
             unsafe { &mut *self.buf1.get() }
         } else {
             unsafe { &mut *self.buf0.get() }
-        }
-    }
+        };
+        back.copy_from(unsafe { &*self.staging.get() });
 
-    /// Commit the write and swap buffers.
-    fn commit(&self) {
         // Swap front buffer index
-        let old_front = self.front_idx.load(Ordering::Acquire);
         self.front_idx.store(1 - old_front, Ordering::Release);
 
         // Increment sequence to even (write complete)
@@ -268,14 +376,24 @@ impl Default for IoImage {
 }
 
 impl IoImage {
-    /// Create a new double-buffered I/O image.
+    /// Create a new double-buffered I/O image with the default dimensions.
     pub fn new() -> Self {
+        Self::with_config(&ProcessImageConfig::default())
+    }
+
+    /// Create a new double-buffered I/O image with the configured dimensions.
+    pub fn with_config(config: &ProcessImageConfig) -> Self {
         Self {
-            inputs: SeqlockBuffer::new(),
-            outputs: SeqlockBuffer::new(),
+            inputs: SeqlockBuffer::new(config),
+            outputs: SeqlockBuffer::new(config),
         }
     }
 
+    /// Dimensions of the image.
+    pub fn config(&self) -> &ProcessImageConfig {
+        &self.inputs.config
+    }
+
     // =========================================================================
     // INPUT PATH: Fieldbus (writer) → Logic (reader)
     // =========================================================================
@@ -291,12 +409,22 @@ impl IoImage {
         self.inputs.read()
     }
 
-    /// Begin writing to the input back buffer.
+    /// Like [`read_inputs`](Self::read_inputs), copying into existing
+    /// process data instead of allocating.
+    ///
+    /// **Called by: Logic thread**
+    #[inline]
+    pub fn read_inputs_into(&self, target: &mut ProcessData) {
+        self.inputs.read_into(target);
+    }
+
+    /// Begin writing inputs.
     ///
     /// **Called by: Fieldbus thread**
     ///
-    /// Returns a mutable reference to the back buffer. Call `commit_inputs()`
-    /// when done to make the changes visible.
+    /// Returns a mutable reference to the staging buffer, which holds the
+    /// last written values. Call `commit_inputs()` when done to make the
+    /// changes visible.
     #[inline]
     pub fn begin_write_inputs(&self) -> &mut ProcessData {
         self.inputs.begin_write()
@@ -338,12 +466,22 @@ impl IoImage {
         self.outputs.read()
     }
 
-    /// Begin writing to the output back buffer.
+    /// Like [`read_outputs`](Self::read_outputs), copying into existing
+    /// process data instead of allocating.
+    ///
+    /// **Called by: Fieldbus thread**
+    #[inline]
+    pub fn read_outputs_into(&self, target: &mut ProcessData) {
+        self.outputs.read_into(target);
+    }
+
+    /// Begin writing outputs.
     ///
     /// **Called by: Logic thread**
     ///
-    /// Returns a mutable reference to the back buffer. Call `commit_outputs()`
-    /// when done to make the changes visible.
+    /// Returns a mutable reference to the staging buffer, which holds the
+    /// last written values. Call `commit_outputs()` when done to make the
+    /// changes visible.
     #[inline]
     pub fn begin_write_outputs(&self) -> &mut ProcessData {
         self.outputs.begin_write()
@@ -399,7 +537,7 @@ impl IoImage {
     /// Read digital inputs word (0-31).
     #[inline]
     pub fn read_di(&self) -> u32 {
-        self.read_inputs().digital_inputs[0]
+        self.read_inputs().read_di_word(0)
     }
 
     /// Write digital outputs word (0-31).
@@ -410,7 +548,7 @@ impl IoImage {
     )]
     #[allow(deprecated)]
     pub fn write_do(&mut self, value: u32) {
-        self.outputs_mut().digital_outputs[0] = value;
+        self.outputs_mut().write_do_word(0, value);
     }
 
     /// Read a single digital input bit.
@@ -461,8 +599,37 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_process_data_alignment() {
-        assert_eq!(std::mem::align_of::<ProcessData>(), CACHE_LINE_SIZE);
+    fn test_process_data_dimensions() {
+        let data = ProcessData::default();
+        assert_eq!(data.digital_inputs().len(), 1);
+        assert_eq!(data.analog_outputs().len(), 16);
+
+        let config = ProcessImageConfig {
+            digital_inputs: 400,
+            digital_outputs: 33,
+            analog_inputs: 4,
+            analog_outputs: 0,
+        };
+        let mut large = ProcessData::new(&config);
+        assert_eq!(large.digital_inputs().len(), 13);
+        assert_eq!(large.digital_outputs().len(), 2);
+        assert_eq!(large.analog_inputs().len(), 4);
+        assert!(large.analog_outputs().is_empty());
+
+        large.digital_inputs_mut()[12] = 1 << 15;
+        assert!(large.read_di(399));
+        large.write_do(40, true);
+        assert_eq!(large.digital_outputs()[1], 1 << 8);
+        large.write_ao(0, 5); // No channels: ignored
+
+        // Copies between different sizes copy the common prefix
+        let mut small = ProcessData::default();
+        small.digital_inputs_mut()[0] = 7;
+        large.copy_inputs_from(&small);
+        assert_eq!(large.digital_inputs()[0], 7);
+        assert_eq!(large.digital_inputs()[12], 1 << 15);
+        small.copy_outputs_from(&large);
+        assert_eq!(small.digital_outputs(), &[0]);
     }
 
     #[test]
@@ -471,7 +638,7 @@ mod tests {
 
         // Test bit operations
         assert!(!data.read_di(0));
-        data.digital_inputs[0] = 0b1010;
+        data.digital_inputs_mut()[0] = 0b1010;
         assert!(!data.read_di(0));
         assert!(data.read_di(1));
         assert!(!data.read_di(2));
@@ -479,24 +646,24 @@ mod tests {
 
         // Test output bit operations
         data.write_do(5, true);
-        assert_eq!(data.digital_outputs[0], 0b100000);
+        assert_eq!(data.digital_outputs()[0], 0b100000);
         data.write_do(5, false);
-        assert_eq!(data.digital_outputs[0], 0);
+        assert_eq!(data.digital_outputs()[0], 0);
     }
 
     #[test]
     fn test_analog_io() {
         let mut data = ProcessData::default();
 
-        data.analog_inputs[0] = 1000;
-        data.analog_inputs[15] = -1000;
+        data.analog_inputs_mut()[0] = 1000;
+        data.analog_inputs_mut()[15] = -1000;
 
         assert_eq!(data.read_ai(0), 1000);
         assert_eq!(data.read_ai(15), -1000);
         assert_eq!(data.read_ai(100), 0); // Out of bounds returns 0
 
         data.write_ao(0, 2000);
-        assert_eq!(data.analog_outputs[0], 2000);
+        assert_eq!(data.analog_outputs()[0], 2000);
     }
 
     #[test]
@@ -505,14 +672,14 @@ mod tests {
 
         // Write inputs (simulating fieldbus)
         io.write_inputs(|data| {
-            data.digital_inputs[0] = 0xFF;
-            data.analog_inputs[0] = 100;
+            data.digital_inputs_mut()[0] = 0xFF;
+            data.analog_inputs_mut()[0] = 100;
         });
 
         // Read inputs (simulating logic engine)
         let inputs = io.read_inputs();
-        assert_eq!(inputs.digital_inputs[0], 0xFF);
-        assert_eq!(inputs.analog_inputs[0], 100);
+        assert_eq!(inputs.digital_inputs()[0], 0xFF);
+        assert_eq!(inputs.analog_inputs()[0], 100);
 
         // Write outputs (simulating logic engine)
         io.write_do(0xAA);
@@ -520,8 +687,28 @@ mod tests {
 
         // Read outputs (simulating fieldbus)
         let outputs = io.read_outputs();
-        assert_eq!(outputs.digital_outputs[0], 0xAA);
-        assert_eq!(outputs.analog_outputs[0], 500);
+        assert_eq!(outputs.digital_outputs()[0], 0xAA);
+        assert_eq!(outputs.analog_outputs()[0], 500);
+    }
+
+    #[test]
+    fn test_io_image_with_config() {
+        let config = ProcessImageConfig {
+            digital_inputs: 400,
+            ..Default::default()
+        };
+        let io = IoImage::with_config(&config);
+        assert_eq!(io.config().di_words(), 13);
+
+        io.write_inputs(|data| data.digital_inputs_mut()[12] = 0xAB);
+        // Writes build on the previously written values
+        io.write_inputs(|data| data.analog_inputs_mut()[3] = -3);
+
+        let mut inputs = ProcessData::new(&config);
+        io.read_inputs_into(&mut inputs);
+        assert_eq!(inputs.digital_inputs()[12], 0xAB);
+        assert_eq!(inputs.analog_inputs()[3], -3);
+        assert_eq!(io.read_inputs(), inputs);
     }
 
     #[test]
@@ -530,19 +717,19 @@ mod tests {
 
         // First input write
         io.write_inputs(|data| {
-            data.digital_inputs[0] = 1;
+            data.digital_inputs_mut()[0] = 1;
         });
 
         let read1 = io.read_inputs();
-        assert_eq!(read1.digital_inputs[0], 1);
+        assert_eq!(read1.digital_inputs()[0], 1);
 
         // Second input write should go to other buffer
         io.write_inputs(|data| {
-            data.digital_inputs[0] = 2;
+            data.digital_inputs_mut()[0] = 2;
         });
 
         let read2 = io.read_inputs();
-        assert_eq!(read2.digital_inputs[0], 2);
+        assert_eq!(read2.digital_inputs()[0], 2);
     }
 
     #[test]
@@ -551,19 +738,19 @@ mod tests {
 
         // First output write
         io.write_outputs(|data| {
-            data.digital_outputs[0] = 0xAA;
+            data.digital_outputs_mut()[0] = 0xAA;
         });
 
         let read1 = io.read_outputs();
-        assert_eq!(read1.digital_outputs[0], 0xAA);
+        assert_eq!(read1.digital_outputs()[0], 0xAA);
 
         // Second output write
         io.write_outputs(|data| {
-            data.digital_outputs[0] = 0xBB;
+            data.digital_outputs_mut()[0] = 0xBB;
         });
 
         let read2 = io.read_outputs();
-        assert_eq!(read2.digital_outputs[0], 0xBB);
+        assert_eq!(read2.digital_outputs()[0], 0xBB);
     }
 
     #[test]
@@ -572,23 +759,23 @@ mod tests {
 
         // Write to inputs
         io.write_inputs(|data| {
-            data.digital_inputs[0] = 0xFF;
-            data.digital_outputs[0] = 0x11; // This should not affect output buffer
+            data.digital_inputs_mut()[0] = 0xFF;
+            data.digital_outputs_mut()[0] = 0x11; // This should not affect output buffer
         });
 
         // Write to outputs
         io.write_outputs(|data| {
-            data.digital_outputs[0] = 0xAA;
-            data.digital_inputs[0] = 0x22; // This should not affect input buffer
+            data.digital_outputs_mut()[0] = 0xAA;
+            data.digital_inputs_mut()[0] = 0x22; // This should not affect input buffer
         });
 
         // Verify isolation
         let inputs = io.read_inputs();
-        assert_eq!(inputs.digital_inputs[0], 0xFF);
+        assert_eq!(inputs.digital_inputs()[0], 0xFF);
         // Input buffer's digital_outputs field is not the same as output buffer
 
         let outputs = io.read_outputs();
-        assert_eq!(outputs.digital_outputs[0], 0xAA);
+        assert_eq!(outputs.digital_outputs()[0], 0xAA);
         // Output buffer's digital_inputs field is not the same as input buffer
     }
 
@@ -625,7 +812,7 @@ mod tests {
         let writer = thread::spawn(move || {
             for i in 0..1000u32 {
                 io_writer.write_inputs(|data| {
-                    data.digital_inputs[0] = i;
+                    data.digital_inputs_mut()[0] = i;
                 });
             }
         });
@@ -638,12 +825,12 @@ mod tests {
                 // Values should be monotonically increasing (or same)
                 // and should never be torn (partial writes)
                 assert!(
-                    data.digital_inputs[0] >= last_seen,
+                    data.digital_inputs()[0] >= last_seen,
                     "Value went backwards: {} -> {}",
                     last_seen,
-                    data.digital_inputs[0]
+                    data.digital_inputs()[0]
                 );
-                last_seen = data.digital_inputs[0];
+                last_seen = data.digital_inputs()[0];
             }
         });
 
@@ -1,20 +1,35 @@
 #![doc = "Real-time execution engine for the virtual PLC."]
 #![allow(unsafe_code)]
 
+pub mod debug;
 pub mod fault_recorder;
+pub mod forcing;
 pub mod io_image;
+pub mod online_change;
+pub mod precompiled;
 pub mod realtime;
+pub mod replay;
 pub mod scheduler;
+pub mod signing;
+pub mod symbols;
+pub mod trace;
 pub mod wasm_host;
 pub mod wasm_imports;
+pub mod wasm_interp;
 pub mod wasm_memory;
 pub mod watchdog;
 
+pub use debug::{Breakpoint, DebugCommand, DebugStop, StopReason};
 pub use fault_recorder::*;
+pub use forcing::{ForceTable, ForceTarget};
 pub use io_image::*;
+pub use online_change::{MigrationPlan, TypeChange};
 pub use realtime::*;
 pub use scheduler::*;
+pub use symbols::{SymbolTable, VarValue};
+pub use trace::{TraceCapture, TraceHandle, TraceSpec, TraceState, Tracer, Trigger};
 pub use wasm_host::*;
 pub use wasm_imports::HostState;
+pub use wasm_interp::{InterpreterHost, InterpreterState};
 pub use wasm_memory::*;
 pub use watchdog::*;
@@ -0,0 +1,421 @@
+//! State migration for online change.
+//!
+//! When a module is replaced while running, the variables of the old module
+//! are carried over to the new one by qualified name rather than by address,
+//! so adding a variable in the middle of a `VAR` block does not shift every
+//! later value. A [`MigrationPlan`] compares the two symbol tables:
+//!
+//! - variables with the same type are copied,
+//! - variables whose type was widened (`INT` to `DINT`, `INT` to `REAL`, ...)
+//!   are converted,
+//! - new variables keep the initializers of the new module,
+//! - removed variables are dropped, and variables whose type changed in any
+//!   other way are reset to their new initializer.
+//!
+//! SFC step flags are carried over by step name, and the process image is
+//! copied as is. Modules without a symbol table fall back to copying linear
+//! memory byte for byte.
+
+use crate::symbols::SymbolTable;
+use plc_common::error::{PlcError, PlcResult};
+use plc_common::sfc::StepFlag;
+use plc_common::symbols::{Direction, Symbol};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A variable whose type differs between the old and new module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeChange {
+    /// Qualified name in the new module.
+    pub name: String,
+    /// Type in the old module.
+    pub from: String,
+    /// Type in the new module.
+    pub to: String,
+}
+
+impl fmt::Display for TypeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} : {} -> {}", self.name, self.from, self.to)
+    }
+}
+
+/// How one piece of state moves from old to new memory.
+#[derive(Debug, Clone)]
+enum Action {
+    /// Copy `len` bytes.
+    Copy { from: usize, to: usize, len: usize },
+    /// Read the old variable and write it, converted, to the new one.
+    Convert { from: String, to: String },
+}
+
+/// How the state of a running module is carried over to its replacement.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationPlan {
+    /// Whether variables are matched by name; otherwise memory is copied
+    /// byte for byte.
+    symbolic: bool,
+    actions: Vec<Action>,
+    /// Bytes of new memory the actions write.
+    required_len: usize,
+    copied: usize,
+    converted: Vec<TypeChange>,
+    added: Vec<String>,
+    removed: Vec<String>,
+    incompatible: Vec<TypeChange>,
+    steps: usize,
+}
+
+impl MigrationPlan {
+    /// Plan a migration by name.
+    ///
+    /// `image_len` bytes at the start of memory (the process image and
+    /// system info) are copied unchanged. `VAR_TEMP` variables are not
+    /// carried over.
+    pub fn new(
+        old: &SymbolTable,
+        old_steps: &[StepFlag],
+        new: &SymbolTable,
+        new_steps: &[StepFlag],
+        image_len: usize,
+    ) -> Self {
+        let mut plan = Self {
+            symbolic: true,
+            ..Self::default()
+        };
+        plan.push(Action::Copy {
+            from: 0,
+            to: 0,
+            len: image_len,
+        });
+
+        for symbol in new.iter().filter(|s| s.direction != Direction::Temp) {
+            let Some(previous) = old.get(&symbol.name) else {
+                plan.added.push(symbol.name.clone());
+                continue;
+            };
+            let change = || TypeChange {
+                name: symbol.name.clone(),
+                from: previous.data_type.clone(),
+                to: symbol.data_type.clone(),
+            };
+            if same_type(previous, symbol) {
+                plan.copied += 1;
+                plan.push(Action::Copy {
+                    from: previous.offset as usize,
+                    to: symbol.offset as usize,
+                    len: symbol.size as usize,
+                });
+            } else if widens(&previous.data_type, &symbol.data_type) {
+                plan.converted.push(change());
+                plan.push(Action::Convert {
+                    from: previous.name.clone(),
+                    to: symbol.name.clone(),
+                });
+                plan.required_len = plan.required_len.max(end(symbol));
+            } else {
+                plan.incompatible.push(change());
+            }
+        }
+        plan.removed = old
+            .iter()
+            .filter(|s| s.direction != Direction::Temp && new.get(&s.name).is_none())
+            .map(|s| s.name.clone())
+            .collect();
+
+        let old_steps: HashMap<&str, u32> = old_steps
+            .iter()
+            .map(|step| (step.name.as_str(), step.offset))
+            .collect();
+        for step in new_steps {
+            if let Some(&from) = old_steps.get(step.name.as_str()) {
+                plan.steps += 1;
+                plan.push(Action::Copy {
+                    from: from as usize,
+                    to: step.offset as usize,
+                    len: 1,
+                });
+            }
+        }
+
+        plan
+    }
+
+    /// Plan a byte-for-byte copy of `len` bytes, for modules without a
+    /// symbol table.
+    pub fn raw(len: usize) -> Self {
+        let mut plan = Self::default();
+        plan.push(Action::Copy {
+            from: 0,
+            to: 0,
+            len,
+        });
+        plan
+    }
+
+    fn push(&mut self, action: Action) {
+        if let Action::Copy { to, len, .. } = action {
+            self.required_len = self.required_len.max(to + len);
+        }
+        self.actions.push(action);
+    }
+
+    /// Whether variables are matched by name.
+    pub fn is_symbolic(&self) -> bool {
+        self.symbolic
+    }
+
+    /// Number of variables copied unchanged.
+    pub fn copied(&self) -> usize {
+        self.copied
+    }
+
+    /// Variables converted to a wider type.
+    pub fn converted(&self) -> &[TypeChange] {
+        &self.converted
+    }
+
+    /// Variables only in the new module; they start from their initializers.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// Variables only in the old module; their values are dropped.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Variables whose type changed in a way that cannot be converted; they
+    /// are reset to their initializers.
+    pub fn incompatible(&self) -> &[TypeChange] {
+        &self.incompatible
+    }
+
+    /// Number of SFC step flags carried over.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Whether every old value survives the migration.
+    pub fn is_lossless(&self) -> bool {
+        self.removed.is_empty() && self.incompatible.is_empty()
+    }
+
+    /// Size of new memory needed to carry out the plan.
+    pub fn required_len(&self) -> usize {
+        self.required_len
+    }
+
+    /// Carry out the plan, from the old module's memory into the new one's.
+    ///
+    /// `new_memory` should hold the new module's initial memory, so added
+    /// and incompatible variables keep their initializers.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the new memory is too small or a variable lies outside
+    /// either memory; `new_memory` may then be partly written.
+    pub fn apply(
+        &self,
+        old: &SymbolTable,
+        old_memory: &[u8],
+        new: &SymbolTable,
+        new_memory: &mut [u8],
+    ) -> PlcResult<()> {
+        if new_memory.len() < self.required_len {
+            return Err(PlcError::Config(format!(
+                "Cannot preserve memory: new module has smaller memory ({} bytes) than needed ({} bytes)",
+                new_memory.len(),
+                self.required_len
+            )));
+        }
+        for action in &self.actions {
+            match action {
+                Action::Copy { from, to, len } => {
+                    let source = old_memory.get(*from..from + len).ok_or_else(|| {
+                        PlcError::Config(format!(
+                            "Cannot preserve memory: {len} bytes at {from:#x} lie outside the old memory"
+                        ))
+                    })?;
+                    new_memory[*to..to + len].copy_from_slice(source);
+                }
+                Action::Convert { from, to } => {
+                    let value = old.read(old_memory, from)?;
+                    new.write(new_memory, to, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.symbolic {
+            return write!(f, "copy {} bytes of memory", self.required_len);
+        }
+        write!(
+            f,
+            "{} copied, {} converted, {} added, {} removed, {} incompatible, {} steps",
+            self.copied,
+            self.converted.len(),
+            self.added.len(),
+            self.removed.len(),
+            self.incompatible.len(),
+            self.steps
+        )
+    }
+}
+
+fn end(symbol: &Symbol) -> usize {
+    symbol.offset as usize + symbol.size as usize
+}
+
+fn same_type(a: &Symbol, b: &Symbol) -> bool {
+    a.size == b.size && a.data_type.eq_ignore_ascii_case(&b.data_type)
+}
+
+const SIGNED: [&str; 4] = ["SINT", "INT", "DINT", "LINT"];
+const UNSIGNED: [&str; 4] = ["USINT", "UINT", "UDINT", "ULINT"];
+const BITS: [&str; 4] = ["BYTE", "WORD", "DWORD", "LWORD"];
+
+/// Whether IEC 61131-3 converts `from` to `to` implicitly, i.e. without
+/// losing any value.
+fn widens(from: &str, to: &str) -> bool {
+    let rank = |types: &[&str], name: &str| types.iter().position(|t| t.eq_ignore_ascii_case(name));
+    let from_signed = rank(&SIGNED, from);
+    let from_unsigned = rank(&UNSIGNED, from);
+
+    if let Some(to_rank) = rank(&SIGNED, to) {
+        return from_signed.is_some_and(|r| r < to_rank)
+            || from_unsigned.is_some_and(|r| r < to_rank);
+    }
+    if let Some(to_rank) = rank(&UNSIGNED, to) {
+        return from_unsigned.is_some_and(|r| r < to_rank);
+    }
+    if let Some(to_rank) = rank(&BITS, to) {
+        return rank(&BITS, from).is_some_and(|r| r < to_rank);
+    }
+    // REAL holds 16-bit integers exactly, LREAL 32-bit ones.
+    let int_rank = from_signed.or(from_unsigned);
+    if to.eq_ignore_ascii_case("REAL") {
+        return int_rank.is_some_and(|r| r <= 1);
+    }
+    if to.eq_ignore_ascii_case("LREAL") {
+        return int_rank.is_some_and(|r| r <= 2) || from.eq_ignore_ascii_case("REAL");
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symbols::VarValue;
+
+    fn symbol(name: &str, data_type: &str, offset: u32, size: u32) -> Symbol {
+        Symbol {
+            name: name.into(),
+            data_type: data_type.into(),
+            offset,
+            size,
+            direction: Direction::Local,
+            retain: false,
+        }
+    }
+
+    #[test]
+    fn test_widening_conversions() {
+        assert!(widens("INT", "DINT"));
+        assert!(widens("USINT", "INT"));
+        assert!(widens("UINT", "UDINT"));
+        assert!(widens("BYTE", "WORD"));
+        assert!(widens("INT", "REAL"));
+        assert!(widens("DINT", "LREAL"));
+        assert!(widens("REAL", "LREAL"));
+
+        assert!(!widens("DINT", "INT"));
+        assert!(!widens("INT", "UDINT"));
+        assert!(!widens("UINT", "INT"));
+        assert!(!widens("DINT", "REAL"));
+        assert!(!widens("BOOL", "INT"));
+        assert!(!widens("BYTE", "UINT"));
+        assert!(!widens("TIME", "LINT"));
+    }
+
+    #[test]
+    fn test_migrate_by_name() {
+        let old = SymbolTable::new(vec![
+            symbol("Main.a", "INT", 0x100, 2),
+            symbol("Main.b", "INT", 0x102, 2),
+            symbol("Main.c", "BOOL", 0x104, 1),
+            symbol("Main.gone", "DINT", 0x108, 4),
+        ]);
+        // `x` was inserted before `b`, which was widened; `c` became an INT.
+        let new = SymbolTable::new(vec![
+            symbol("Main.a", "INT", 0x100, 2),
+            symbol("Main.x", "INT", 0x102, 2),
+            symbol("Main.b", "DINT", 0x104, 4),
+            symbol("Main.c", "INT", 0x108, 2),
+        ]);
+        let old_steps = [StepFlag {
+            name: "Main.Fill".into(),
+            offset: 0x10c,
+        }];
+        let new_steps = [StepFlag {
+            name: "Main.Fill".into(),
+            offset: 0x10a,
+        }];
+        let plan = MigrationPlan::new(&old, &old_steps, &new, &new_steps, 0x10);
+
+        assert!(plan.is_symbolic());
+        assert_eq!(plan.copied(), 1);
+        assert_eq!(plan.converted()[0].to_string(), "Main.b : INT -> DINT");
+        assert_eq!(plan.added(), ["Main.x"]);
+        assert_eq!(plan.removed(), ["Main.gone"]);
+        assert_eq!(plan.incompatible()[0].name, "Main.c");
+        assert_eq!(plan.steps(), 1);
+        assert!(!plan.is_lossless());
+
+        let mut old_memory = vec![0u8; 0x110];
+        old_memory[0x04] = 0xAA;
+        old.write(&mut old_memory, "Main.a", VarValue::Int(11))
+            .unwrap();
+        old.write(&mut old_memory, "Main.b", VarValue::Int(-22))
+            .unwrap();
+        old.write(&mut old_memory, "Main.c", VarValue::Bool(true))
+            .unwrap();
+        old_memory[0x10c] = 1;
+
+        // The new module initializes `x` to 5 and `c` to 7.
+        let mut new_memory = vec![0u8; 0x110];
+        new.write(&mut new_memory, "Main.x", VarValue::Int(5))
+            .unwrap();
+        new.write(&mut new_memory, "Main.c", VarValue::Int(7))
+            .unwrap();
+        plan.apply(&old, &old_memory, &new, &mut new_memory)
+            .unwrap();
+
+        assert_eq!(new_memory[0x04], 0xAA);
+        assert_eq!(new.read(&new_memory, "Main.a").unwrap(), VarValue::Int(11));
+        assert_eq!(new.read(&new_memory, "Main.x").unwrap(), VarValue::Int(5));
+        assert_eq!(new.read(&new_memory, "Main.b").unwrap(), VarValue::Int(-22));
+        assert_eq!(new.read(&new_memory, "Main.c").unwrap(), VarValue::Int(7));
+        assert_eq!(new_memory[0x10a], 1);
+    }
+
+    #[test]
+    fn test_raw_plan_needs_room() {
+        let plan = MigrationPlan::raw(8);
+        assert!(!plan.is_symbolic());
+        assert_eq!(plan.to_string(), "copy 8 bytes of memory");
+
+        let table = SymbolTable::default();
+        let old_memory = [1u8; 8];
+        let mut small = [0u8; 4];
+        assert!(plan.apply(&table, &old_memory, &table, &mut small).is_err());
+        let mut new_memory = [0u8; 16];
+        plan.apply(&table, &old_memory, &table, &mut new_memory)
+            .unwrap();
+        assert_eq!(new_memory[..8], old_memory);
+    }
+}
@@ -0,0 +1,284 @@
+//! Ahead-of-time precompiled logic modules.
+//!
+//! `plc-daemon compile --precompile` packs a Wasm module together with the
+//! native code Wasmtime generates for it, for a given target and `[wasm]`
+//! engine configuration. [`WasmtimeHost`](crate::WasmtimeHost) accepts such
+//! an artifact wherever it accepts a Wasm module and skips Cranelift when
+//! the code was built for an engine like its own; otherwise it warns and
+//! compiles the embedded Wasm module instead. The interpreter only uses the
+//! embedded module.
+//!
+//! # Format
+//!
+//! All integers are little-endian; byte strings carry a `u32` length prefix.
+//!
+//! | Field              | Contents                                        |
+//! |--------------------|-------------------------------------------------|
+//! | magic              | [`MAGIC`]                                       |
+//! | version            | `u32`, [`FORMAT_VERSION`]                       |
+//! | target             | target triple, UTF-8                            |
+//! | engine fingerprint | 32 bytes, see [`engine_fingerprint`]            |
+//! | module             | the Wasm module                                 |
+//! | code               | output of `Engine::precompile_module`           |
+//! | signature          | see [`signing`](crate::signing), empty if none  |
+//! | digest             | hex SHA-256 over all preceding bytes (64 bytes) |
+//!
+//! The digest is checked before anything else is read, so a truncated or
+//! altered artifact is refused rather than handed to
+//! `Module::deserialize`, which trusts its input. The digest only catches
+//! damage; a signature, over every field before it, also tells who built
+//! the artifact.
+
+use crate::wasm_host::{engine_config, WasmtimeConfig};
+use anyhow::{Context, Result};
+use plc_common::build_cache::CacheKey;
+use plc_common::codec::{put_bytes, Reader};
+use plc_common::error::{PlcError, PlcResult};
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
+use wasmtime::Engine;
+
+/// First bytes of every artifact. The leading NUL keeps it from being
+/// mistaken for text, like the `\0asm` of Wasm modules.
+pub const MAGIC: [u8; 8] = *b"\0plcaot\x01";
+
+/// Version of the artifact layout.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Domain of the artifact digest.
+const DIGEST_DOMAIN: &str = "plc.precompiled";
+
+/// Length of the hex digest trailer.
+const DIGEST_LEN: usize = 64;
+
+/// Length of an engine fingerprint, a SHA-256 digest.
+pub const FINGERPRINT_LEN: usize = 32;
+
+/// A parsed, integrity-checked artifact borrowing from its bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Artifact<'a> {
+    /// Target triple the code was built for.
+    pub target: &'a str,
+    /// Fingerprint of the engine configuration the code was built with.
+    pub engine_fingerprint: [u8; FINGERPRINT_LEN],
+    /// The Wasm module the code was built from.
+    pub wasm: &'a [u8],
+    /// Serialized native code.
+    pub code: &'a [u8],
+    /// Signature over the preceding fields; empty if unsigned.
+    pub signature: &'a [u8],
+}
+
+impl<'a> Artifact<'a> {
+    /// Whether `bytes` start like an artifact rather than a Wasm module.
+    pub fn is_artifact(bytes: &[u8]) -> bool {
+        bytes.starts_with(&MAGIC)
+    }
+
+    /// Parse an artifact; `Ok(None)` if `bytes` are not one at all.
+    ///
+    /// Fails if the artifact is truncated, of an unknown version, or does
+    /// not match its digest.
+    pub fn parse(bytes: &'a [u8]) -> PlcResult<Option<Self>> {
+        if !Self::is_artifact(bytes) {
+            return Ok(None);
+        }
+        let corrupt = |what: &str| PlcError::Config(format!("Precompiled artifact {what}"));
+
+        let body_len = bytes
+            .len()
+            .checked_sub(DIGEST_LEN)
+            .ok_or_else(|| corrupt("is truncated"))?;
+        let (body, digest) = bytes.split_at(body_len);
+        if digest != digest_of(body).as_bytes() {
+            return Err(corrupt("does not match its digest; refusing to load it"));
+        }
+
+        let mut reader = Reader::new(&body[MAGIC.len()..]);
+        let version = reader.u32().ok_or_else(|| corrupt("is truncated"))?;
+        if version != FORMAT_VERSION {
+            return Err(corrupt(&format!(
+                "has format version {version}, expected {FORMAT_VERSION}"
+            )));
+        }
+        let target = reader
+            .bytes()
+            .map(std::str::from_utf8)
+            .ok_or_else(|| corrupt("is truncated"))?
+            .map_err(|_| corrupt("has a non-UTF-8 target"))?;
+        let engine_fingerprint = reader
+            .take(FINGERPRINT_LEN)
+            .and_then(|fingerprint| fingerprint.try_into().ok())
+            .ok_or_else(|| corrupt("is truncated"))?;
+        let wasm = reader.bytes().ok_or_else(|| corrupt("is truncated"))?;
+        let code = reader.bytes().ok_or_else(|| corrupt("is truncated"))?;
+        let signature = reader.bytes().ok_or_else(|| corrupt("is truncated"))?;
+        if !reader.is_empty() {
+            return Err(corrupt("has trailing data"));
+        }
+
+        Ok(Some(Self {
+            target,
+            engine_fingerprint,
+            wasm,
+            code,
+            signature,
+        }))
+    }
+
+    /// Encode the artifact, appending its digest.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.signed_bytes();
+        put_bytes(&mut out, self.signature);
+        let digest = digest_of(&out);
+        out.extend_from_slice(digest.as_bytes());
+        out
+    }
+
+    /// The encoded fields a signature covers: all before the signature.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            MAGIC.len()
+                + 24
+                + FINGERPRINT_LEN
+                + self.target.len()
+                + self.wasm.len()
+                + self.code.len()
+                + self.signature.len()
+                + DIGEST_LEN,
+        );
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        put_bytes(&mut out, self.target.as_bytes());
+        out.extend_from_slice(&self.engine_fingerprint);
+        put_bytes(&mut out, self.wasm);
+        put_bytes(&mut out, self.code);
+        out
+    }
+}
+
+/// The Wasm module in `bytes`: the embedded one for an artifact, otherwise
+/// `bytes` themselves.
+pub fn wasm_module(bytes: &[u8]) -> PlcResult<&[u8]> {
+    Ok(Artifact::parse(bytes)?.map_or(bytes, |artifact| artifact.wasm))
+}
+
+/// Fingerprint of an engine's code generation settings.
+///
+/// Covers the Wasmtime version, the target and every setting that affects
+/// the generated code, so equal fingerprints mean the code is usable. The
+/// settings are hashed with SHA-256 rather than `DefaultHasher`, whose
+/// output may change with the Rust release the runtime is built with.
+pub fn engine_fingerprint(engine: &Engine) -> [u8; FINGERPRINT_LEN] {
+    let mut hasher = Sha256Hasher(Sha256::new());
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    hasher.0.finalize().into()
+}
+
+/// A [`Hasher`] feeding everything it is given into SHA-256.
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 has 32 bytes"))
+    }
+}
+
+/// Target triple of the machine the runtime was built for, as far as the
+/// standard library tells; used for artifacts built without `--target`.
+pub fn host_target() -> String {
+    format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+/// Precompile `wasm_bytes` for `target` (the host if `None`) with the engine
+/// settings of `wasm_config`, and pack the result as an artifact.
+pub fn precompile(
+    wasm_bytes: &[u8],
+    wasm_config: &WasmtimeConfig,
+    target: Option<&str>,
+) -> Result<Vec<u8>> {
+    let mut config = engine_config(wasm_config);
+    if let Some(target) = target {
+        config
+            .target(target)
+            .with_context(|| format!("Unsupported target '{target}'"))?;
+    }
+    let engine = Engine::new(&config).context("Failed to create Wasmtime engine")?;
+    let code = engine
+        .precompile_module(wasm_bytes)
+        .context("Failed to precompile Wasm module")?;
+
+    let target = target.map_or_else(host_target, str::to_string);
+    Ok(Artifact {
+        target: &target,
+        engine_fingerprint: engine_fingerprint(&engine),
+        wasm: wasm_bytes,
+        code: &code,
+        signature: &[],
+    }
+    .encode())
+}
+
+fn digest_of(body: &[u8]) -> String {
+    let mut key = CacheKey::new(DIGEST_DOMAIN);
+    key.add(body);
+    key.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAT: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "step"))
+        )
+    "#;
+
+    #[test]
+    fn test_artifact_roundtrip() {
+        let wasm = wat::parse_str(WAT).unwrap();
+        let bytes = precompile(&wasm, &WasmtimeConfig::default(), None).unwrap();
+
+        let artifact = Artifact::parse(&bytes).unwrap().unwrap();
+        assert_eq!(artifact.target, host_target());
+        assert_eq!(artifact.wasm, &wasm[..]);
+        assert!(!artifact.code.is_empty());
+        assert!(artifact.signature.is_empty());
+        let engine = Engine::new(&engine_config(&WasmtimeConfig::default())).unwrap();
+        assert_eq!(artifact.engine_fingerprint, engine_fingerprint(&engine));
+        assert_eq!(wasm_module(&bytes).unwrap(), &wasm[..]);
+
+        // Plain Wasm modules are not artifacts
+        assert!(Artifact::parse(&wasm).unwrap().is_none());
+        assert_eq!(wasm_module(&wasm).unwrap(), &wasm[..]);
+    }
+
+    #[test]
+    fn test_artifact_integrity() {
+        let wasm = wat::parse_str(WAT).unwrap();
+        let bytes = precompile(&wasm, &WasmtimeConfig::default(), None).unwrap();
+
+        // Any altered byte is caught by the digest
+        let mut tampered = bytes.clone();
+        let last_code_byte = tampered.len() - DIGEST_LEN - 1;
+        tampered[last_code_byte] ^= 0xff;
+        assert!(Artifact::parse(&tampered).is_err());
+
+        assert!(Artifact::parse(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Artifact::parse(&MAGIC).is_err());
+    }
+
+    #[test]
+    fn test_precompile_rejects_unknown_target() {
+        let wasm = wat::parse_str(WAT).unwrap();
+        let result = precompile(&wasm, &WasmtimeConfig::default(), Some("not-a-target"));
+        assert!(result.is_err());
+    }
+}
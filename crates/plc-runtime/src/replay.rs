@@ -0,0 +1,1034 @@
+//! Recording of scan cycles and their deterministic replay.
+//!
+//! A [`CycleRecorder`] records every task step: the latched inputs the
+//! logic saw, the system info it was presented (cycle count, first-cycle
+//! flag), a wall-clock timestamp and the outputs it produced. Records go
+//! to a writer thread over a bounded channel, so the scan never waits for
+//! the disk; when the channel is full the record is dropped and the next
+//! step starts a new segment.
+//!
+//! Records are stored in segment files, `cycles-<sequence>.plcrec` in the
+//! configured directory. Each segment starts with what is needed to replay
+//! it on its own: the module, the task entry points, the process image
+//! size, the cycle time and a checkpoint of the logic's linear memory taken
+//! before its first step. Only the newest `max_segments` files are kept.
+//! Inputs and outputs are only stored when they differ from the previous
+//! step's, which keeps the files small while a machine idles.
+//!
+//! [`replay`] restores each segment's checkpoint in a [`WasmtimeHost`] in
+//! deterministic mode, feeds it the recorded inputs and compares the
+//! outputs step by step. With another module, the checkpoint is migrated
+//! to it by variable name, as in an online change, so new logic can be
+//! tested against recorded production traffic. Forced variables are not
+//! recorded; a replay of steps taken while variables were forced may
+//! diverge.
+
+use crate::fault_recorder::{input_snapshot_size, output_snapshot_size, pack_snapshot};
+use crate::io_image::ProcessData;
+use crate::wasm_host::{LogicEngine, WasmtimeConfig, WasmtimeHost};
+use crate::wasm_memory::WasmSystemInfo;
+use plc_common::config::{ProcessImageConfig, RecordConfig};
+use plc_common::error::{PlcError, PlcResult};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+/// Version of the segment file format.
+pub const FORMAT_VERSION: u16 = 1;
+
+const MAGIC: &[u8; 6] = b"PLCREC";
+
+/// Messages queued for the writer thread.
+const CHANNEL_CAPACITY: usize = 1024;
+
+const FILE_PREFIX: &str = "cycles-";
+const FILE_EXTENSION: &str = "plcrec";
+
+const INPUTS_FOLLOW: u8 = 0x01;
+const OUTPUTS_FOLLOW: u8 = 0x02;
+const TRAPPED: u8 = 0x04;
+
+/// What a segment's replay starts from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentHeader {
+    /// Position of the segment in the recording.
+    pub sequence: u64,
+    /// Cycle time presented to the logic.
+    pub cycle_time_ns: u64,
+    /// Process image the snapshots were taken from.
+    pub process_image: ProcessImageConfig,
+    /// Entry point of each task, by task index.
+    pub entries: Vec<String>,
+    /// The module that was running.
+    pub module: Vec<u8>,
+    /// The logic's linear memory before the first step.
+    pub memory: Vec<u8>,
+}
+
+/// One recorded task step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleRecord {
+    /// Scan the step ran in.
+    pub scan: u64,
+    /// Index of the task.
+    pub task: u16,
+    /// Wall-clock time of the step, nanoseconds since the Unix epoch.
+    pub timestamp_ns: u64,
+    /// Cycle count presented to the logic.
+    pub cycle_count: u64,
+    /// System info flags presented to the logic.
+    pub flags: u32,
+    /// Whether the step trapped instead of producing outputs.
+    pub trapped: bool,
+    /// Latched inputs, packed like fault recorder snapshots.
+    pub inputs: Vec<u8>,
+    /// Outputs of the step, packed; empty if it trapped.
+    pub outputs: Vec<u8>,
+}
+
+impl SegmentHeader {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.sequence.to_le_bytes());
+        out.extend_from_slice(&self.cycle_time_ns.to_le_bytes());
+        let image = &self.process_image;
+        for count in [
+            image.digital_inputs,
+            image.digital_outputs,
+            image.analog_inputs,
+            image.analog_outputs,
+        ] {
+            out.extend_from_slice(&count.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        for entry in &self.entries {
+            out.extend_from_slice(&(entry.len() as u16).to_le_bytes());
+            out.extend_from_slice(entry.as_bytes());
+        }
+        for blob in [&self.module, &self.memory] {
+            out.extend_from_slice(&(blob.len() as u32).to_le_bytes());
+            out.extend_from_slice(blob);
+        }
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> Option<Self> {
+        if reader.take(MAGIC.len())? != MAGIC {
+            return None;
+        }
+        if reader.u16()? > FORMAT_VERSION {
+            return None;
+        }
+        let sequence = reader.u64()?;
+        let cycle_time_ns = reader.u64()?;
+        let process_image = ProcessImageConfig {
+            digital_inputs: reader.u32()?,
+            digital_outputs: reader.u32()?,
+            analog_inputs: reader.u32()?,
+            analog_outputs: reader.u32()?,
+        };
+        let entries = (0..reader.u16()?)
+            .map(|_| {
+                let len = reader.u16()? as usize;
+                String::from_utf8(reader.take(len)?.to_vec()).ok()
+            })
+            .collect::<Option<_>>()?;
+        let module_len = reader.u32()? as usize;
+        let module = reader.take(module_len)?.to_vec();
+        let memory_len = reader.u32()? as usize;
+        let memory = reader.take(memory_len)?.to_vec();
+        Some(Self {
+            sequence,
+            cycle_time_ns,
+            process_image,
+            entries,
+            module,
+            memory,
+        })
+    }
+}
+
+impl CycleRecord {
+    fn empty() -> Self {
+        Self {
+            scan: 0,
+            task: 0,
+            timestamp_ns: 0,
+            cycle_count: 0,
+            flags: 0,
+            trapped: false,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Encode the record, leaving out inputs and outputs that are the same
+    /// as in `previous`.
+    fn encode(&self, previous: Option<&CycleRecord>, out: &mut Vec<u8>) {
+        let write_inputs = previous.map_or(true, |p| p.inputs != self.inputs);
+        let write_outputs = !self.trapped && previous.map_or(true, |p| p.outputs != self.outputs);
+        let mut kind = 0;
+        if write_inputs {
+            kind |= INPUTS_FOLLOW;
+        }
+        if write_outputs {
+            kind |= OUTPUTS_FOLLOW;
+        }
+        if self.trapped {
+            kind |= TRAPPED;
+        }
+        out.push(kind);
+        out.extend_from_slice(&self.scan.to_le_bytes());
+        out.extend_from_slice(&self.task.to_le_bytes());
+        out.extend_from_slice(&self.timestamp_ns.to_le_bytes());
+        out.extend_from_slice(&self.cycle_count.to_le_bytes());
+        out.extend_from_slice(&self.flags.to_le_bytes());
+        if write_inputs {
+            out.extend_from_slice(&self.inputs);
+        }
+        if write_outputs {
+            out.extend_from_slice(&self.outputs);
+        }
+    }
+
+    fn decode(
+        reader: &mut Reader<'_>,
+        previous: Option<&CycleRecord>,
+        sizes: (usize, usize),
+    ) -> Option<Self> {
+        let kind = reader.u8()?;
+        let mut record = Self {
+            scan: reader.u64()?,
+            task: reader.u16()?,
+            timestamp_ns: reader.u64()?,
+            cycle_count: reader.u64()?,
+            flags: reader.u32()?,
+            trapped: kind & TRAPPED != 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+        record.inputs = if kind & INPUTS_FOLLOW != 0 {
+            reader.take(sizes.0)?.to_vec()
+        } else {
+            previous?.inputs.clone()
+        };
+        record.outputs = if kind & OUTPUTS_FOLLOW != 0 {
+            reader.take(sizes.1)?.to_vec()
+        } else if record.trapped {
+            Vec::new()
+        } else {
+            previous?.outputs.clone()
+        };
+        Some(record)
+    }
+}
+
+/// Little-endian reader over a segment file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+/// A segment file read back.
+#[derive(Debug, Clone)]
+pub struct RecordedSegment {
+    /// Where it was read from.
+    pub path: PathBuf,
+    /// What its replay starts from.
+    pub header: SegmentHeader,
+    /// The recorded steps, in order.
+    pub records: Vec<CycleRecord>,
+    /// False if the file ends in a partly written record, as when the
+    /// process stopped while writing it.
+    pub complete: bool,
+}
+
+/// Read one segment file.
+pub fn read_segment(path: &Path) -> PlcResult<RecordedSegment> {
+    let bytes =
+        std::fs::read(path).map_err(|e| PlcError::IoError(format!("{}: {e}", path.display())))?;
+    let mut reader = Reader { bytes: &bytes };
+    let header = SegmentHeader::decode(&mut reader).ok_or_else(|| {
+        PlcError::Config(format!(
+            "{} is not a cycle recording of format version {FORMAT_VERSION} or older",
+            path.display()
+        ))
+    })?;
+    let sizes = (
+        input_snapshot_size(&header.process_image),
+        output_snapshot_size(&header.process_image),
+    );
+
+    let mut records: Vec<CycleRecord> = Vec::new();
+    let complete = loop {
+        if reader.bytes.is_empty() {
+            break true;
+        }
+        match CycleRecord::decode(&mut reader, records.last(), sizes) {
+            Some(record) => records.push(record),
+            None => break false,
+        }
+    };
+    Ok(RecordedSegment {
+        path: path.to_path_buf(),
+        header,
+        records,
+        complete,
+    })
+}
+
+/// The segment files in `dir`, oldest first.
+pub fn segment_files(dir: &Path) -> PlcResult<Vec<PathBuf>> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| PlcError::IoError(format!("{}: {e}", dir.display())))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| segment_sequence(path).is_some())
+        .collect();
+    files.sort_by_key(|path| segment_sequence(path));
+    Ok(files)
+}
+
+/// Read every segment in `dir`, oldest first.
+pub fn read_segments(dir: &Path) -> PlcResult<Vec<RecordedSegment>> {
+    segment_files(dir)?
+        .iter()
+        .map(|path| read_segment(path))
+        .collect()
+}
+
+fn segment_sequence(path: &Path) -> Option<u64> {
+    if path.extension()? != FILE_EXTENSION {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix(FILE_PREFIX)?
+        .parse()
+        .ok()
+}
+
+fn segment_path(dir: &Path, sequence: u64) -> PathBuf {
+    dir.join(format!("{FILE_PREFIX}{sequence:08}.{FILE_EXTENSION}"))
+}
+
+enum Message {
+    Segment(Box<SegmentHeader>),
+    Record(CycleRecord),
+}
+
+/// Records task steps to segment files.
+///
+/// The scheduler calls it around every step; the files are written by a
+/// background thread. Record buffers are handed back by that thread and
+/// reused, so steady-state recording does not allocate.
+pub struct CycleRecorder {
+    sender: Option<SyncSender<Message>>,
+    recycled: Receiver<CycleRecord>,
+    writer: Option<JoinHandle<()>>,
+    module: Arc<[u8]>,
+    entries: Vec<String>,
+    process_image: ProcessImageConfig,
+    segment_steps: u64,
+    steps_in_segment: u64,
+    segment_open: bool,
+    next_sequence: u64,
+    system_info: WasmSystemInfo,
+    dropped: u64,
+}
+
+impl fmt::Debug for CycleRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CycleRecorder")
+            .field("recording", &self.sender.is_some())
+            .field("next_sequence", &self.next_sequence)
+            .field("dropped", &self.dropped)
+            .finish()
+    }
+}
+
+impl CycleRecorder {
+    /// Start recording steps of `module` into `config.dir`.
+    ///
+    /// `entries` are the task entry points by task index. Numbering
+    /// continues after the segments already in the directory.
+    pub fn start(
+        config: &RecordConfig,
+        module: &[u8],
+        entries: Vec<String>,
+        process_image: ProcessImageConfig,
+    ) -> PlcResult<Self> {
+        let dir = config
+            .dir
+            .clone()
+            .ok_or_else(|| PlcError::Config("no recording directory configured".into()))?;
+        if config.segment_steps == 0 || config.max_segments == 0 {
+            return Err(PlcError::Config(
+                "record.segment_steps and record.max_segments must be at least 1".into(),
+            ));
+        }
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| PlcError::IoError(format!("{}: {e}", dir.display())))?;
+        let next_sequence = segment_files(&dir)?
+            .last()
+            .and_then(|path| segment_sequence(path))
+            .map_or(0, |last| last + 1);
+
+        let (sender, messages) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let (recycle, recycled) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let max_segments = config.max_segments;
+        let writer_dir = dir.clone();
+        let writer = std::thread::Builder::new()
+            .name("plc-recorder".into())
+            .spawn(move || {
+                if let Err(e) = write_segments(&writer_dir, max_segments, &messages, &recycle) {
+                    error!(error = %e, "Cycle recording failed");
+                }
+            })
+            .map_err(|e| PlcError::Fault(format!("Cannot start recorder thread: {e}")))?;
+        info!(dir = %dir.display(), first_segment = next_sequence, "Cycle recording started");
+
+        Ok(Self {
+            sender: Some(sender),
+            recycled,
+            writer: Some(writer),
+            module: module.into(),
+            entries,
+            process_image,
+            segment_steps: config.segment_steps,
+            steps_in_segment: 0,
+            segment_open: false,
+            next_sequence,
+            system_info: WasmSystemInfo::default(),
+            dropped: 0,
+        })
+    }
+
+    /// The module was replaced; the next step starts a new segment.
+    pub fn set_module(&mut self, module: &[u8]) {
+        self.module = module.into();
+        self.segment_open = false;
+    }
+
+    /// Steps that could not be recorded because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Whether steps are still being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.sender.is_some()
+    }
+
+    /// Capture what the engine presents to the next step, and start a new
+    /// segment with a memory checkpoint if one is due.
+    pub(crate) fn before_step<E: LogicEngine>(&mut self, engine: &E) {
+        if self.sender.is_none() {
+            return;
+        }
+        self.system_info = engine.system_info().unwrap_or_default();
+        if self.segment_open && self.steps_in_segment < self.segment_steps {
+            return;
+        }
+        let header = SegmentHeader {
+            sequence: self.next_sequence,
+            cycle_time_ns: self.system_info.cycle_time_ns,
+            process_image: self.process_image,
+            entries: self.entries.clone(),
+            module: self.module.to_vec(),
+            memory: engine.memory_snapshot().unwrap_or_default(),
+        };
+        self.segment_open = self.send(Message::Segment(Box::new(header)));
+        if self.segment_open {
+            self.next_sequence += 1;
+            self.steps_in_segment = 0;
+        }
+    }
+
+    /// Record a step; `outputs` is `None` if it trapped.
+    pub(crate) fn after_step(
+        &mut self,
+        scan: u64,
+        task: usize,
+        inputs: &ProcessData,
+        outputs: Option<&ProcessData>,
+    ) {
+        if !self.segment_open {
+            return;
+        }
+        let mut record = self
+            .recycled
+            .try_recv()
+            .unwrap_or_else(|_| CycleRecord::empty());
+        record.scan = scan;
+        record.task = task as u16;
+        record.timestamp_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_nanos() as u64);
+        record.cycle_count = self.system_info.cycle_count;
+        record.flags = self.system_info.flags;
+        record.trapped = outputs.is_none();
+        record
+            .inputs
+            .resize(input_snapshot_size(&self.process_image), 0);
+        pack_snapshot(
+            &mut record.inputs,
+            inputs.digital_inputs(),
+            inputs.analog_inputs(),
+        );
+        match outputs {
+            Some(outputs) => {
+                record
+                    .outputs
+                    .resize(output_snapshot_size(&self.process_image), 0);
+                pack_snapshot(
+                    &mut record.outputs,
+                    outputs.digital_outputs(),
+                    outputs.analog_outputs(),
+                );
+            }
+            None => record.outputs.clear(),
+        }
+
+        if self.send(Message::Record(record)) {
+            self.steps_in_segment += 1;
+        } else {
+            // The replay of this segment would miss a step; continue in a
+            // new one with a fresh checkpoint
+            self.segment_open = false;
+        }
+    }
+
+    fn send(&mut self, message: Message) -> bool {
+        let Some(sender) = self.sender.as_ref() else {
+            return false;
+        };
+        match sender.try_send(message) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                warn!("Cycle recording stopped, the writer has exited");
+                self.sender = None;
+                false
+            }
+        }
+    }
+
+    /// Stop recording and wait until everything queued is written.
+    pub fn stop(&mut self) {
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+            if self.dropped > 0 {
+                warn!(
+                    dropped = self.dropped,
+                    "Cycle recording dropped steps, the writer fell behind"
+                );
+            }
+            info!("Cycle recording stopped");
+        }
+    }
+}
+
+impl Drop for CycleRecorder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Writer thread: write queued segments and records until the recorder
+/// hangs up, flushing whenever the queue runs empty.
+fn write_segments(
+    dir: &Path,
+    max_segments: usize,
+    messages: &Receiver<Message>,
+    recycle: &SyncSender<CycleRecord>,
+) -> PlcResult<()> {
+    let io_error = |path: &Path, e: std::io::Error| {
+        PlcError::IoError(format!("cycle recording {}: {e}", path.display()))
+    };
+    let mut file: Option<(PathBuf, BufWriter<File>)> = None;
+    let mut previous: Option<CycleRecord> = None;
+    let mut buffer = Vec::new();
+
+    loop {
+        let message = match messages.try_recv() {
+            Ok(message) => message,
+            Err(TryRecvError::Empty) => {
+                if let Some((path, out)) = file.as_mut() {
+                    out.flush().map_err(|e| io_error(path, e))?;
+                }
+                match messages.recv() {
+                    Ok(message) => message,
+                    Err(_) => break,
+                }
+            }
+            Err(TryRecvError::Disconnected) => break,
+        };
+
+        match message {
+            Message::Segment(header) => {
+                if let Some((path, mut out)) = file.take() {
+                    out.flush().map_err(|e| io_error(&path, e))?;
+                }
+                let path = segment_path(dir, header.sequence);
+                let out = File::create(&path).map_err(|e| io_error(&path, e))?;
+                let mut out = BufWriter::new(out);
+                buffer.clear();
+                header.encode(&mut buffer);
+                out.write_all(&buffer).map_err(|e| io_error(&path, e))?;
+                file = Some((path, out));
+                previous = None;
+
+                let files = segment_files(dir)?;
+                for old in &files[..files.len().saturating_sub(max_segments)] {
+                    std::fs::remove_file(old).map_err(|e| io_error(old, e))?;
+                }
+            }
+            Message::Record(record) => {
+                let Some((path, out)) = file.as_mut() else {
+                    continue;
+                };
+                buffer.clear();
+                record.encode(previous.as_ref(), &mut buffer);
+                out.write_all(&buffer).map_err(|e| io_error(path, e))?;
+                if let Some(used) = previous.replace(record) {
+                    let _ = recycle.try_send(used);
+                }
+            }
+        }
+    }
+
+    if let Some((path, mut out)) = file {
+        out.flush().map_err(|e| io_error(&path, e))?;
+    }
+    Ok(())
+}
+
+/// An output point that differs between recording and replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputDifference {
+    /// Process image address (`%QD0`, `%QW3`).
+    pub point: String,
+    /// Recorded value.
+    pub recorded: String,
+    /// Value produced by the replay.
+    pub replayed: String,
+}
+
+/// The first step whose replay did not match the recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Segment file of the step.
+    pub segment: PathBuf,
+    /// Scan the step ran in.
+    pub scan: u64,
+    /// Entry point of the task.
+    pub task: String,
+    /// Cycle count presented to the logic.
+    pub cycle_count: u64,
+    /// What differed.
+    pub detail: String,
+    /// Differing outputs, if the outputs differed.
+    pub differences: Vec<OutputDifference>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "scan {} task '{}' (cycle count {}, {}): {}",
+            self.scan,
+            self.task,
+            self.cycle_count,
+            self.segment.display(),
+            self.detail
+        )?;
+        for difference in &self.differences {
+            write!(
+                f,
+                "\n  {}: recorded {}, replayed {}",
+                difference.point, difference.recorded, difference.replayed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a replay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayReport {
+    /// Segments replayed.
+    pub segments: usize,
+    /// Steps replayed, including the diverging one.
+    pub steps: u64,
+    /// The first divergence; replay stops there.
+    pub divergence: Option<Divergence>,
+}
+
+/// Replay recorded segments, in order, and compare the outputs with the
+/// recording.
+///
+/// Each segment starts from its own checkpoint in the module it recorded.
+/// With `module`, the checkpoint is migrated to that module first and its
+/// outputs are compared instead.
+pub fn replay(segments: &[RecordedSegment], module: Option<&[u8]>) -> PlcResult<ReplayReport> {
+    let mut report = ReplayReport {
+        segments: 0,
+        steps: 0,
+        divergence: None,
+    };
+    for segment in segments {
+        report.segments += 1;
+        let divergence = replay_segment(segment, module, &mut report.steps)?;
+        if divergence.is_some() {
+            report.divergence = divergence;
+            break;
+        }
+    }
+    Ok(report)
+}
+
+fn replay_segment(
+    segment: &RecordedSegment,
+    module: Option<&[u8]>,
+    steps: &mut u64,
+) -> PlcResult<Option<Divergence>> {
+    let header = &segment.header;
+    let config = WasmtimeConfig {
+        deterministic: true,
+        max_memory_bytes: WasmtimeConfig::default()
+            .max_memory_bytes
+            .max(header.memory.len()),
+        process_image: header.process_image,
+        entry_points: header
+            .entries
+            .iter()
+            .filter(|entry| *entry != "step")
+            .cloned()
+            .collect(),
+        ..WasmtimeConfig::default()
+    };
+    let mut host = WasmtimeHost::with_config(Duration::from_nanos(header.cycle_time_ns), config)
+        .map_err(|e| PlcError::Config(format!("Cannot create replay host: {e}")))?;
+    host.load_module(&header.module)
+        .map_err(|e| PlcError::Config(format!("Cannot load recorded module: {e}")))?;
+    host.init()?;
+    host.restore_memory(&header.memory)?;
+    if let Some(module) = module.filter(|module| *module != header.module.as_slice()) {
+        host.reload_module(module, true)?;
+    }
+
+    let mut inputs = ProcessData::new(&header.process_image);
+    let mut replayed = vec![0u8; output_snapshot_size(&header.process_image)];
+    for record in &segment.records {
+        *steps += 1;
+        let entry = header
+            .entries
+            .get(usize::from(record.task))
+            .map_or("step", String::as_str);
+        let divergence = |detail: String, differences| Divergence {
+            segment: segment.path.clone(),
+            scan: record.scan,
+            task: entry.to_string(),
+            cycle_count: record.cycle_count,
+            detail,
+            differences,
+        };
+
+        unpack_inputs(&record.inputs, &mut inputs);
+        host.set_cycle_state(
+            record.cycle_count,
+            record.flags & WasmSystemInfo::FLAG_FIRST_CYCLE != 0,
+        );
+        match (host.step_entry(entry, &inputs), record.trapped) {
+            (Ok(outputs), false) => {
+                pack_snapshot(
+                    &mut replayed,
+                    outputs.digital_outputs(),
+                    outputs.analog_outputs(),
+                );
+                if replayed != record.outputs {
+                    let differences =
+                        output_differences(&header.process_image, &record.outputs, &replayed);
+                    return Ok(Some(divergence("outputs differ".into(), differences)));
+                }
+            }
+            (Err(_), true) => {}
+            (Ok(_), true) => {
+                return Ok(Some(divergence(
+                    "the recorded step trapped, the replay did not".into(),
+                    Vec::new(),
+                )));
+            }
+            (Err(e), false) => {
+                return Ok(Some(divergence(
+                    format!("the replay trapped: {e}"),
+                    Vec::new(),
+                )));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Copy a packed input snapshot into the process data.
+fn unpack_inputs(snapshot: &[u8], inputs: &mut ProcessData) {
+    let (digital, analog) = split_snapshot(
+        snapshot,
+        inputs.digital_inputs().len(),
+        inputs.analog_inputs().len(),
+    );
+    inputs.digital_inputs_mut().copy_from_slice(&digital);
+    inputs.analog_inputs_mut().copy_from_slice(&analog);
+}
+
+/// Digital words and analog channels of a packed snapshot; missing bytes
+/// read as zero.
+fn split_snapshot(snapshot: &[u8], words: usize, channels: usize) -> (Vec<u32>, Vec<i16>) {
+    let byte = |at: usize| snapshot.get(at).copied().unwrap_or(0);
+    let digital = (0..words)
+        .map(|i| u32::from_le_bytes([0, 1, 2, 3].map(|b| byte(i * 4 + b))))
+        .collect();
+    let analog = (0..channels)
+        .map(|i| i16::from_le_bytes([0, 1].map(|b| byte(words * 4 + i * 2 + b))))
+        .collect();
+    (digital, analog)
+}
+
+fn output_differences(
+    image: &ProcessImageConfig,
+    recorded: &[u8],
+    replayed: &[u8],
+) -> Vec<OutputDifference> {
+    let (words, channels) = (image.do_words(), image.ao_channels());
+    let (recorded_digital, recorded_analog) = split_snapshot(recorded, words, channels);
+    let (replayed_digital, replayed_analog) = split_snapshot(replayed, words, channels);
+
+    let digital = recorded_digital
+        .iter()
+        .zip(&replayed_digital)
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(index, (a, b))| OutputDifference {
+            point: format!("%QD{index}"),
+            recorded: format!("16#{a:08X}"),
+            replayed: format!("16#{b:08X}"),
+        });
+    let analog = recorded_analog
+        .iter()
+        .zip(&replayed_analog)
+        .enumerate()
+        .filter(|(_, (a, b))| a != b)
+        .map(|(index, (a, b))| OutputDifference {
+            point: format!("%QW{index}"),
+            recorded: a.to_string(),
+            replayed: b.to_string(),
+        });
+    digital.chain(analog).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(scan: u64, inputs: &[u8], outputs: &[u8]) -> CycleRecord {
+        CycleRecord {
+            scan,
+            task: 0,
+            timestamp_ns: 1_000 + scan,
+            cycle_count: scan - 1,
+            flags: if scan == 1 {
+                WasmSystemInfo::FLAG_FIRST_CYCLE
+            } else {
+                0
+            },
+            trapped: false,
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_segment_round_trip_skips_unchanged_snapshots() {
+        let image = ProcessImageConfig {
+            digital_inputs: 32,
+            digital_outputs: 32,
+            analog_inputs: 1,
+            analog_outputs: 1,
+        };
+        let header = SegmentHeader {
+            sequence: 7,
+            cycle_time_ns: 10_000_000,
+            process_image: image,
+            entries: vec!["step".into(), "fast".into()],
+            module: b"\0asm".to_vec(),
+            memory: vec![1, 2, 3],
+        };
+        let first = record(1, &[1, 0, 0, 0, 5, 0], &[0; 6]);
+        let same = record(2, &[1, 0, 0, 0, 5, 0], &[0; 6]);
+        let trapped = CycleRecord {
+            trapped: true,
+            outputs: Vec::new(),
+            ..record(3, &[2, 0, 0, 0, 5, 0], &[])
+        };
+        let after = record(4, &[2, 0, 0, 0, 5, 0], &[9, 0, 0, 0, 0, 0]);
+
+        let mut bytes = Vec::new();
+        header.encode(&mut bytes);
+        let mut previous: Option<&CycleRecord> = None;
+        let mut sizes = Vec::new();
+        for r in [&first, &same, &trapped, &after] {
+            let start = bytes.len();
+            r.encode(previous, &mut bytes);
+            sizes.push(bytes.len() - start);
+            previous = Some(r);
+        }
+        assert_eq!(sizes, [43, 31, 37, 37]);
+
+        let dir = std::env::temp_dir().join(format!("plc-replay-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = segment_path(&dir, 7);
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        let segment = read_segment(&path).unwrap();
+        assert_eq!(segment.header, header);
+        assert_eq!(segment.records, [first, same, trapped]);
+        assert!(!segment.complete);
+        assert_eq!(segment_files(&dir).unwrap(), [path]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Counts its steps in memory and sets `%QX0.1` on every third one.
+    const COUNTER_WAT: &str = r#"
+        (module
+            (import "plc" "read_di" (func $read_di (param i32) (result i32)))
+            (import "plc" "write_do" (func $write_do (param i32 i32)))
+
+            (memory (export "memory") 1)
+
+            (func (export "step")
+                (local $count i32)
+                (local.set $count (i32.add (i32.load (i32.const 0x100)) (i32.const 1)))
+                (i32.store (i32.const 0x100) (local.get $count))
+                (call $write_do (i32.const 0) (call $read_di (i32.const 0)))
+                (call $write_do
+                    (i32.const 1)
+                    (i32.eqz (i32.rem_u (local.get $count) (i32.const 3)))
+                )
+            )
+        )
+    "#;
+
+    #[test]
+    fn test_replay_matches_recording_and_finds_divergence() {
+        let dir = std::env::temp_dir().join(format!("plc-replay-{}", std::process::id()));
+        let module = wat::parse_str(COUNTER_WAT).unwrap();
+        let config = RecordConfig {
+            dir: Some(dir.clone()),
+            segment_steps: 10,
+            max_segments: 2,
+        };
+        let image = ProcessImageConfig::default();
+        let mut recorder =
+            CycleRecorder::start(&config, &module, vec!["step".into()], image).unwrap();
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        host.load_module(&module).unwrap();
+        host.init().unwrap();
+        let mut inputs = ProcessData::new(&image);
+        for scan in 1..=25 {
+            inputs.digital_inputs_mut()[0] = u32::from(scan % 4 == 0);
+            recorder.before_step(&host);
+            let outputs = host.step(&inputs).unwrap();
+            recorder.after_step(scan, 0, &inputs, Some(outputs));
+        }
+        recorder.stop();
+        assert_eq!(recorder.dropped(), 0);
+
+        // The oldest segment was pruned; the rest replays from its checkpoint
+        let segments = read_segments(&dir).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].header.sequence, 1);
+        assert!(segments.iter().all(|segment| segment.complete));
+        let report = replay(&segments, None).unwrap();
+        assert_eq!(report.segments, 2);
+        assert_eq!(report.steps, 15);
+        assert_eq!(report.divergence, None);
+
+        // Logic setting the output on every fourth step instead diverges on
+        // scan 15, the first after the checkpoint where the two disagree
+        let changed =
+            wat::parse_str(COUNTER_WAT.replace("(i32.const 3)", "(i32.const 4)")).unwrap();
+        let report = replay(&segments, Some(&changed)).unwrap();
+        let divergence = report.divergence.unwrap();
+        assert_eq!(divergence.scan, 15);
+        assert_eq!(divergence.task, "step");
+        assert_eq!(divergence.differences[0].point, "%QD0");
+        assert_eq!(report.steps, 5);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_differences() {
+        let image = ProcessImageConfig {
+            digital_inputs: 32,
+            digital_outputs: 32,
+            analog_inputs: 0,
+            analog_outputs: 2,
+        };
+        let recorded = [1, 0, 0, 0, 10, 0, 20, 0];
+        let replayed = [3, 0, 0, 0, 10, 0, 21, 0];
+        let differences = output_differences(&image, &recorded, &replayed);
+        assert_eq!(
+            differences,
+            [
+                OutputDifference {
+                    point: "%QD0".into(),
+                    recorded: "16#00000001".into(),
+                    replayed: "16#00000003".into(),
+                },
+                OutputDifference {
+                    point: "%QW1".into(),
+                    recorded: "20".into(),
+                    replayed: "21".into(),
+                },
+            ]
+        );
+    }
+}
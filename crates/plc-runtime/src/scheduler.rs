@@ -6,13 +6,28 @@
 //! 3. Write outputs to fieldbus
 //! 4. Wait for next cycle deadline
 //!
+//! Logic is split into [`Task`]s, each with its own period, priority,
+//! overrun policy, watchdog and metrics. Without configured tasks, a single
+//! task calls `step` every `cycle_time`.
+//!
+//! In DEBUG the scan keeps its cadence but the logic only runs on command;
+//! see [`crate::debug`].
+//!
 //! Uses `clock_nanosleep` with `TIMER_ABSTIME` for jitter-free timing.
 
+use crate::debug::{DebugCommand, DebugRun, DebugStop, StopReason};
 use crate::fault_recorder::{FaultReason, FaultRecorder};
+use crate::forcing::{ForceTable, ForceTarget};
 use crate::io_image::{IoImage, ProcessData};
+use crate::replay::CycleRecorder;
+use crate::symbols::VarValue;
+use crate::trace::{TraceHandle, TraceSpec, Tracer};
 use crate::wasm_host::LogicEngine;
 use crate::watchdog::Watchdog;
-use plc_common::config::{FaultPolicyConfig, OverrunPolicy, RuntimeConfig, SafeOutputPolicy};
+use plc_common::config::{
+    DebugConfig, DebugOutputPolicy, FaultPolicyConfig, ForcingConfig, OverrunPolicy, RecordConfig,
+    RuntimeConfig, SafeOutputPolicy, TaskConfig, TraceConfig,
+};
 use plc_common::error::{PlcError, PlcResult};
 use plc_common::metrics::CycleMetrics;
 use plc_common::state::{RuntimeState, StateMachine};
@@ -56,22 +71,133 @@ impl CyclePhaseTimings {
     }
 }
 
+/// A cyclic task: one exported entry point released at its own period.
+///
+/// Each task latches its own copy of the inputs when it starts and commits
+/// its outputs to the shared I/O image when it finishes.
+pub struct Task {
+    /// Task name, for diagnostics.
+    name: String,
+    /// Exported function called each period.
+    entry: String,
+    /// Release period.
+    period: Duration,
+    /// Priority; lower values run first when several tasks are due.
+    priority: u32,
+    /// Maximum allowed overrun before the overrun policy applies.
+    max_overrun: Duration,
+    /// What to do on a critical overrun.
+    on_overrun: OverrunPolicy,
+    /// Next release time (absolute).
+    next_release: Option<Instant>,
+    /// Number of times this task has run.
+    cycle_count: u64,
+    /// Response time metrics, measured from the start of the scan.
+    metrics: CycleMetrics,
+    /// Watchdog timeout.
+    watchdog_timeout: Duration,
+    /// Watchdog timer, if enabled.
+    watchdog: Option<Watchdog>,
+    /// Inputs latched at the start of this task's last run.
+    inputs: ProcessData,
+}
+
+impl Task {
+    fn new(task: &TaskConfig, config: &RuntimeConfig) -> Self {
+        Self {
+            name: task.name.clone(),
+            entry: task.entry().to_string(),
+            period: task.period,
+            priority: task.priority,
+            max_overrun: task.max_overrun.unwrap_or(config.max_overrun),
+            on_overrun: task.on_overrun.unwrap_or(config.fault_policy.on_overrun),
+            next_release: None,
+            cycle_count: 0,
+            metrics: CycleMetrics::new(config.metrics.histogram_size, task.period),
+            watchdog_timeout: task.watchdog_timeout.unwrap_or(task.period * 3),
+            watchdog: None,
+            inputs: ProcessData::new(&config.process_image),
+        }
+    }
+
+    /// Task name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Exported function called each period.
+    pub fn entry(&self) -> &str {
+        &self.entry
+    }
+
+    /// Release period.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Priority; lower values run first.
+    pub fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    /// Maximum allowed overrun before the overrun policy applies.
+    pub fn max_overrun(&self) -> Duration {
+        self.max_overrun
+    }
+
+    /// Overrun policy.
+    pub fn on_overrun(&self) -> OverrunPolicy {
+        self.on_overrun
+    }
+
+    /// Number of times this task has run.
+    pub fn cycle_count(&self) -> u64 {
+        self.cycle_count
+    }
+
+    /// Response time metrics for this task.
+    pub fn metrics(&self) -> &CycleMetrics {
+        &self.metrics
+    }
+
+    /// Watchdog timeout.
+    pub fn watchdog_timeout(&self) -> Duration {
+        self.watchdog_timeout
+    }
+
+    /// Whether the watchdog is enabled.
+    pub fn has_watchdog(&self) -> bool {
+        self.watchdog.is_some()
+    }
+
+    /// Whether this task is due at `now`.
+    fn is_due(&self, now: Instant) -> bool {
+        self.next_release.is_some_and(|release| release <= now)
+    }
+}
+
 /// Result of a single cycle execution.
 #[derive(Debug, Clone)]
 pub struct CycleResult {
     /// Actual execution time of this cycle.
     pub execution_time: Duration,
-    /// Whether the cycle exceeded the deadline.
+    /// Whether any task exceeded its deadline.
     pub overrun: bool,
     /// Current cycle number.
     pub cycle_count: u64,
-    /// Per-phase timing breakdown for diagnostics.
+    /// Whether the logic was paused in DEBUG, so no task ran.
+    pub paused: bool,
+    /// Per-phase timing breakdown for diagnostics, summed over the tasks
+    /// that ran.
     pub phase_timings: CyclePhaseTimings,
 }
 
 /// Deterministic cyclic scheduler.
 ///
 /// Coordinates the scan cycle between I/O image, logic engine, and fieldbus.
+/// The configured tasks run rate-monotonically on the calling thread: each
+/// scan runs every task that is due, in priority order, then sleeps until
+/// the next release.
 pub struct Scheduler<E: LogicEngine> {
     /// Process I/O image.
     pub io: IoImage,
@@ -79,46 +205,81 @@ pub struct Scheduler<E: LogicEngine> {
     pub engine: E,
     /// Runtime state machine.
     state: StateMachine,
-    /// Cycle time configuration.
-    cycle_period: Duration,
-    /// Maximum allowed overrun before fault.
-    max_overrun: Duration,
-    /// Next cycle deadline (absolute time).
-    next_deadline: Option<Instant>,
-    /// Total cycles executed.
+    /// Tasks, in the order they run.
+    tasks: Vec<Task>,
+    /// Total scans executed.
     cycle_count: u64,
-    /// Metrics collection.
+    /// Scan metrics collection.
     metrics: CycleMetrics,
-    /// Watchdog timer.
-    watchdog: Option<Watchdog>,
     /// Fault handling policy.
     fault_policy: FaultPolicyConfig,
     /// Last known output values (for HoldLast safe output policy).
     last_outputs: ProcessData,
     /// Fault frame recorder for postmortem diagnosis.
     fault_recorder: FaultRecorder,
+    /// Active forces.
+    forces: ForceTable,
+    /// Forcing policy.
+    forcing: ForcingConfig,
+    /// Debug mode policy.
+    debug_config: DebugConfig,
+    /// What the logic does in DEBUG; `None` outside of it.
+    debug_run: Option<DebugRun>,
+    /// Where the logic last paused in DEBUG.
+    debug_stop: Option<DebugStop>,
+    /// Whether `debug_stop` has not been taken yet.
+    debug_stop_pending: bool,
+    /// Trace armed when the runtime starts.
+    trace_config: TraceConfig,
+    /// The current signal trace, if one was armed.
+    tracer: Option<Tracer>,
+    /// Cycle recording policy.
+    record_config: RecordConfig,
+    /// The cycle recorder, while recording.
+    recorder: Option<CycleRecorder>,
 }
 
 impl<E: LogicEngine> Scheduler<E> {
     /// Create a new scheduler with the given logic engine and configuration.
     pub fn new(engine: E, config: &RuntimeConfig) -> Self {
-        let metrics = CycleMetrics::new(config.metrics.histogram_size, config.cycle_time);
-        let fault_recorder =
-            FaultRecorder::new(config.fault_policy.fault_frame_count.unwrap_or(64));
+        let mut tasks: Vec<Task> = config
+            .scheduled_tasks()
+            .iter()
+            .map(|task| Task::new(task, config))
+            .collect();
+        tasks.sort_by_key(|task| (task.priority, task.period));
+
+        let shortest = tasks
+            .iter()
+            .map(|task| task.period)
+            .min()
+            .unwrap_or(config.cycle_time);
+        let metrics = CycleMetrics::new(config.metrics.histogram_size, shortest);
+        let fault_recorder = FaultRecorder::for_process_image(
+            config.fault_policy.fault_frame_count.unwrap_or(64),
+            &config.process_image,
+        );
 
         Self {
-            io: IoImage::new(),
+            io: IoImage::with_config(&config.process_image),
             engine,
             state: StateMachine::new(),
-            cycle_period: config.cycle_time,
-            max_overrun: config.max_overrun,
-            next_deadline: None,
+            tasks,
             cycle_count: 0,
             metrics,
-            watchdog: None,
             fault_policy: config.fault_policy.clone(),
-            last_outputs: ProcessData::default(),
+            last_outputs: ProcessData::new(&config.process_image),
             fault_recorder,
+            forces: ForceTable::new(),
+            forcing: config.forcing.clone(),
+            debug_config: config.debug.clone(),
+            debug_run: None,
+            debug_stop: None,
+            debug_stop_pending: false,
+            trace_config: config.trace.clone(),
+            tracer: None,
+            record_config: config.record.clone(),
+            recorder: None,
         }
     }
 
@@ -127,9 +288,11 @@ impl<E: LogicEngine> Scheduler<E> {
         Self::new(engine, &RuntimeConfig::default())
     }
 
-    /// Set the watchdog timer.
-    pub fn set_watchdog(&mut self, watchdog: Watchdog) {
-        self.watchdog = Some(watchdog);
+    /// Give every task a watchdog with its configured timeout.
+    pub fn enable_watchdogs(&mut self) {
+        for task in &mut self.tasks {
+            task.watchdog = Some(Watchdog::new(task.watchdog_timeout));
+        }
     }
 
     /// Get the current runtime state.
@@ -137,12 +300,26 @@ impl<E: LogicEngine> Scheduler<E> {
         self.state.state()
     }
 
-    /// Get cycle metrics.
+    /// Get the tasks, in the order they run.
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    /// Get the scan period: the shortest task period.
+    pub fn cycle_period(&self) -> Duration {
+        self.tasks
+            .iter()
+            .map(|task| task.period)
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Get scan metrics.
     pub fn metrics(&self) -> &CycleMetrics {
         &self.metrics
     }
 
-    /// Get total cycle count.
+    /// Get total scan count.
     pub fn cycle_count(&self) -> u64 {
         self.cycle_count
     }
@@ -152,6 +329,106 @@ impl<E: LogicEngine> Scheduler<E> {
         &self.fault_recorder
     }
 
+    /// Get the active forces.
+    pub fn forces(&self) -> &ForceTable {
+        &self.forces
+    }
+
+    /// Force a process image point or variable.
+    ///
+    /// The force takes effect from the next scan and stays until it is
+    /// released, the runtime faults, or the runtime stops (if configured).
+    /// A variable force is written immediately, so unknown variables and
+    /// out-of-range values are rejected here.
+    pub fn force(&mut self, target: ForceTarget, value: VarValue) -> PlcResult<()> {
+        if !self.forcing.enabled {
+            return Err(PlcError::Config("forcing is disabled".into()));
+        }
+        if let ForceTarget::Variable(name) = &target {
+            self.engine.write_variable(name, value)?;
+        }
+        self.forces.set(target.clone(), value, self.io.config())?;
+        warn!(target = %target, %value, active = self.forces.len(), "Force set");
+        Ok(())
+    }
+
+    /// Release the force on a target. Returns whether it was forced.
+    pub fn unforce(&mut self, target: &ForceTarget) -> bool {
+        let released = self.forces.remove(target);
+        if released {
+            info!(target = %target, active = self.forces.len(), "Force released");
+        }
+        released
+    }
+
+    /// Release all forces. Returns how many were active.
+    pub fn clear_forces(&mut self) -> usize {
+        let count = self.forces.clear();
+        if count > 0 {
+            info!(count, "All forces released");
+        }
+        count
+    }
+
+    /// Arm a signal trace, replacing the current one.
+    ///
+    /// The signals are checked against the process image and the logic's
+    /// variables, so the logic must be initialized.
+    pub fn arm_trace(&mut self, spec: TraceSpec) -> PlcResult<TraceHandle> {
+        let tracer = Tracer::arm(spec, self.io.config(), &self.engine)?;
+        info!(
+            signals = tracer.handle().channels().len(),
+            trigger = %tracer.handle().trigger(),
+            "Trace armed"
+        );
+        let handle = tracer.handle();
+        self.tracer = Some(tracer);
+        Ok(handle)
+    }
+
+    /// Stop the current trace; what it recorded stays readable.
+    pub fn stop_trace(&mut self) {
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.stop();
+        }
+    }
+
+    /// Read access to the current trace.
+    pub fn trace(&self) -> Option<TraceHandle> {
+        self.tracer.as_ref().map(Tracer::handle)
+    }
+
+    /// Start recording every task step of `module` for replay; see
+    /// [`crate::replay`].
+    pub fn start_recording(&mut self, module: &[u8]) -> PlcResult<()> {
+        let entries = self.tasks.iter().map(|task| task.entry.clone()).collect();
+        let recorder =
+            CycleRecorder::start(&self.record_config, module, entries, *self.io.config())?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Tell the recorder the logic was replaced by `module`.
+    pub fn set_recorded_module(&mut self, module: &[u8]) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.set_module(module);
+        }
+    }
+
+    /// Stop recording and wait until the recording is written.
+    pub fn stop_recording(&mut self) {
+        if let Some(mut recorder) = self.recorder.take() {
+            recorder.stop();
+        }
+    }
+
+    /// Whether task steps are being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.recorder
+            .as_ref()
+            .is_some_and(CycleRecorder::is_recording)
+    }
+
     /// Initialize the scheduler and logic engine.
     ///
     /// Transitions from BOOT → INIT → PRE_OP.
@@ -175,7 +452,8 @@ impl<E: LogicEngine> Scheduler<E> {
 
     /// Start cyclic execution.
     ///
-    /// Transitions from PRE_OP → RUN and starts the watchdog if configured.
+    /// Transitions from PRE_OP → RUN, releases every task and starts the
+    /// watchdogs if enabled.
     pub fn start(&mut self) -> PlcResult<()> {
         if self.state.state() != RuntimeState::PreOp {
             return Err(PlcError::InvalidStateTransition {
@@ -185,195 +463,405 @@ impl<E: LogicEngine> Scheduler<E> {
         }
 
         info!(
-            cycle_period_us = self.cycle_period.as_micros(),
+            tasks = self.tasks.len(),
+            cycle_period_us = self.cycle_period().as_micros(),
             "Starting cyclic execution"
         );
 
-        // Start the watchdog timer if configured
-        if let Some(ref mut wd) = self.watchdog {
-            wd.start(|| {
-                // The callback is invoked when watchdog times out.
-                // The triggered flag is already set by the watchdog itself.
-                // The main loop checks watchdog_triggered() and enters fault state.
-                error!("Watchdog triggered - RT loop has stopped responding");
-            })?;
+        for task in &mut self.tasks {
+            debug!(
+                task = %task.name,
+                entry = %task.entry,
+                period_us = task.period.as_micros(),
+                priority = task.priority,
+                "Task configured"
+            );
+
+            // Start the watchdog timer if configured
+            if let Some(ref mut wd) = task.watchdog {
+                let name = task.name.clone();
+                wd.start(move || {
+                    // The callback is invoked when watchdog times out.
+                    // The triggered flag is already set by the watchdog itself.
+                    // The main loop checks watchdog_triggered() and enters fault state.
+                    error!(task = %name, "Watchdog triggered - task has stopped responding");
+                })?;
+            }
         }
 
+        if !self.trace_config.signals.is_empty() {
+            let spec = TraceSpec::from_config(&self.trace_config)?;
+            self.arm_trace(spec)?;
+        }
+
+        self.state.transition(RuntimeState::Run)?;
+        self.release_tasks_now();
+
+        Ok(())
+    }
+
+    /// Release every task now, so the next scan runs all of them.
+    fn release_tasks_now(&mut self) {
+        let now = Instant::now();
+        for task in &mut self.tasks {
+            task.next_release = Some(now);
+        }
+    }
+
+    /// Enter DEBUG: the logic pauses after the current scan.
+    ///
+    /// The scan keeps running, so the fieldbus keeps exchanging and the
+    /// watchdogs stay fed. Outputs are set safe or held according to the
+    /// debug output policy.
+    pub fn enter_debug(&mut self) -> PlcResult<()> {
+        self.state.transition(RuntimeState::Debug)?;
+        info!(outputs = ?self.debug_config.outputs, "Entering DEBUG");
+        if self.debug_config.outputs == DebugOutputPolicy::Safe {
+            self.set_safe_outputs();
+        }
+        self.pause_logic(StopReason::Entered);
+        Ok(())
+    }
+
+    /// Leave DEBUG and resume normal cyclic execution.
+    pub fn exit_debug(&mut self) -> PlcResult<()> {
         self.state.transition(RuntimeState::Run)?;
-        self.next_deadline = Some(Instant::now() + self.cycle_period);
+        info!("Leaving DEBUG, resuming cyclic execution");
+        self.debug_run = None;
+        self.debug_stop_pending = false;
+        self.release_tasks_now();
+        Ok(())
+    }
 
+    /// Run the logic on command while in DEBUG.
+    pub fn debug(&mut self, command: DebugCommand) -> PlcResult<()> {
+        if self.state.state() != RuntimeState::Debug {
+            return Err(PlcError::Config(format!(
+                "debug commands need DEBUG, runtime is in {}",
+                self.state.state()
+            )));
+        }
+        let run = match command {
+            DebugCommand::Pause => {
+                if self.debug_run != Some(DebugRun::Paused) {
+                    self.pause_logic(StopReason::Paused);
+                }
+                return Ok(());
+            }
+            DebugCommand::Step(0) => {
+                return Err(PlcError::Config("step needs at least one cycle".into()));
+            }
+            DebugCommand::Step(cycles) => DebugRun::Cycles(cycles),
+            DebugCommand::RunUntil(breakpoint) => {
+                // Reject unknown variables before running anything
+                self.engine.read_variable(breakpoint.variable())?;
+                DebugRun::Until(breakpoint)
+            }
+        };
+        info!(run = ?run, "Debug command");
+        if self.debug_run == Some(DebugRun::Paused) {
+            self.release_tasks_now();
+        }
+        self.debug_run = Some(run);
+        Ok(())
+    }
+
+    /// Whether the logic is paused in DEBUG.
+    pub fn is_paused(&self) -> bool {
+        self.debug_run == Some(DebugRun::Paused)
+    }
+
+    /// Where the logic last paused in DEBUG.
+    pub fn debug_stop(&self) -> Option<&DebugStop> {
+        self.debug_stop.as_ref()
+    }
+
+    /// Take the stop the logic reached since the last call, if any.
+    pub fn take_debug_stop(&mut self) -> Option<&DebugStop> {
+        if std::mem::take(&mut self.debug_stop_pending) {
+            self.debug_stop.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Pause the logic and record the variables for inspection.
+    fn pause_logic(&mut self, reason: StopReason) {
+        let variables = match self.engine.symbols() {
+            Some(symbols) => symbols
+                .iter()
+                .filter_map(|symbol| {
+                    let value = self.engine.read_variable(&symbol.name).ok()?;
+                    Some((symbol.name.clone(), value))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        info!(cycle = self.cycle_count, %reason, "Logic paused");
+        self.debug_run = Some(DebugRun::Paused);
+        self.debug_stop = Some(DebugStop {
+            cycle: self.cycle_count,
+            reason,
+            variables,
+        });
+        self.debug_stop_pending = true;
+    }
+
+    /// A scan without running any task: keep the cadence and the watchdogs
+    /// alive, as while the logic is paused in DEBUG or the runtime is a hot
+    /// standby.
+    pub fn idle_cycle(&mut self) -> CycleResult {
+        for task in &self.tasks {
+            if let Some(ref wd) = task.watchdog {
+                wd.kick();
+            }
+        }
+        self.wait_until(Instant::now() + self.cycle_period());
+        CycleResult {
+            execution_time: Duration::ZERO,
+            overrun: false,
+            cycle_count: self.cycle_count,
+            paused: true,
+            phase_timings: CyclePhaseTimings::default(),
+        }
+    }
+
+    /// Continue from the state of a redundant peer (a bumpless takeover).
+    ///
+    /// Restores the logic's linear memory and its cycle count, takes over
+    /// the peer's scan count and puts the inputs and outputs of its last
+    /// scan (`image`) in the I/O image, so the fieldbus sees no step in the
+    /// outputs.
+    /// An empty `memory` leaves the logic as it is. Every task is released
+    /// at once.
+    pub fn take_over(
+        &mut self,
+        memory: &[u8],
+        scan: u64,
+        logic_cycles: u64,
+        image: &ProcessData,
+    ) -> PlcResult<()> {
+        if !memory.is_empty() {
+            self.engine.restore_state(memory, logic_cycles)?;
+        }
+        self.cycle_count = scan;
+        self.io.write_inputs(|data| data.copy_inputs_from(image));
+        self.io.write_outputs(|data| data.copy_outputs_from(image));
+        self.last_outputs.copy_outputs_from(image);
+        self.release_tasks_now();
+        info!(
+            scan,
+            logic_cycles, "Took over the state of the redundant peer"
+        );
         Ok(())
     }
 
     /// Execute one scan cycle.
     ///
-    /// This is the core PLC loop iteration:
-    /// 1. Kick watchdog
-    /// 2. Record cycle start time
-    /// 3. Read inputs (already in I/O image from fieldbus)
-    /// 4. Execute logic
-    /// 5. Write outputs (to I/O image for fieldbus)
-    /// 6. Record metrics
-    /// 7. Wait for next deadline
+    /// This is the core PLC loop iteration. For each task that is due, in
+    /// priority order:
+    /// 1. Kick the task's watchdog
+    /// 2. Latch inputs (already in I/O image from fieldbus) and apply
+    ///    input forces
+    /// 3. Execute the task's entry point, with forced variables written
+    ///    before and after it
+    /// 4. Apply output forces and commit outputs (to I/O image for fieldbus)
+    /// 5. Record metrics and check for overrun
+    ///
+    /// It then records a trace sample, if a trace is armed, and waits for the
+    /// next task release. In DEBUG, no task runs while the logic is paused;
+    /// the scan only keeps its cadence.
     ///
     /// # Returns
     ///
     /// Returns `Ok(CycleResult)` on success, or `Err` on fault.
     pub fn run_cycle(&mut self) -> PlcResult<CycleResult> {
-        if self.state.state() != RuntimeState::Run {
+        if !self.state.state().is_scanning() {
             return Err(PlcError::Fault(format!(
                 "Cannot run cycle in state {}",
                 self.state.state()
             )));
         }
 
-        // Check if watchdog has triggered (RT loop was too slow)
-        if self.watchdog_triggered() {
+        // Check if a watchdog has triggered (RT loop was too slow)
+        if let Some(index) = self
+            .tasks
+            .iter()
+            .position(|task| task.watchdog.as_ref().is_some_and(|wd| wd.has_triggered()))
+        {
             self.fault_recorder.record_fault(
                 self.cycle_count,
                 FaultReason::WatchdogTimeout,
                 CyclePhaseTimings::default(),
             );
-            self.enter_fault("Watchdog timeout detected")?;
+            self.fault_recorder.set_fault_task(index);
+            let reason = format!(
+                "Watchdog timeout detected in task '{}'",
+                self.tasks[index].name
+            );
+            self.enter_fault(&reason)?;
             return Err(PlcError::Fault("Watchdog timeout".into()));
         }
+        if self.is_paused() {
+            return Ok(self.idle_cycle());
+        }
 
         let cycle_start = Instant::now();
+        let cycle = self.cycle_count + 1;
+        let mut scan_timings = CyclePhaseTimings::default();
+        let mut overrun = false;
 
-        // 1. Kick watchdog
-        if let Some(ref wd) = self.watchdog {
-            wd.kick();
-        }
+        for index in 0..self.tasks.len() {
+            if !self.tasks[index].is_due(cycle_start) {
+                continue;
+            }
+            if !self.forces.is_empty() {
+                apply_variable_forces(&mut self.engine, &mut self.forces);
+            }
+            let task = &mut self.tasks[index];
 
-        // 2. Read inputs from I/O image (timed)
-        let io_read_start = Instant::now();
-        let inputs = self.io.read_inputs();
-        let io_read_time = io_read_start.elapsed();
-
-        // 3. Execute logic engine with inputs (timed)
-        let logic_start = Instant::now();
-        let outputs = match self.engine.step(&inputs) {
-            Ok(outputs) => outputs,
-            Err(e) => {
-                let logic_exec_time = logic_start.elapsed();
-                let phase_timings = CyclePhaseTimings {
-                    io_read: io_read_time,
-                    logic_exec: logic_exec_time,
-                    io_write: Duration::ZERO,
-                    fieldbus_exchange: Duration::ZERO,
-                    total: cycle_start.elapsed(),
-                };
-                self.fault_recorder.record_fault(
-                    self.cycle_count,
-                    FaultReason::LogicError,
-                    phase_timings,
-                );
-                self.enter_fault(&format!("Logic engine step failed: {e}"))?;
-                return Err(e);
+            // 1. Kick watchdog
+            if let Some(ref wd) = task.watchdog {
+                wd.kick();
             }
-        };
-        let logic_exec_time = logic_start.elapsed();
-
-        // 4. Write outputs to I/O image for fieldbus to read (timed)
-        let io_write_start = Instant::now();
-        // Only copy output fields, not the entire ProcessData
-        self.io.write_outputs(|io_outputs| {
-            io_outputs.digital_outputs = outputs.digital_outputs;
-            io_outputs.analog_outputs = outputs.analog_outputs;
-        });
 
-        // Track last outputs for HoldLast safe output policy
-        self.last_outputs.digital_outputs = outputs.digital_outputs;
-        self.last_outputs.analog_outputs = outputs.analog_outputs;
-        let io_write_time = io_write_start.elapsed();
+            // 2. Latch inputs from I/O image (timed)
+            let io_read_start = Instant::now();
+            self.io.read_inputs_into(&mut task.inputs);
+            self.forces.apply_inputs(&mut task.inputs);
+            let io_read_time = io_read_start.elapsed();
 
-        let execution_time = cycle_start.elapsed();
-        let phase_timings = CyclePhaseTimings {
-            io_read: io_read_time,
-            logic_exec: logic_exec_time,
-            io_write: io_write_time,
-            fieldbus_exchange: Duration::ZERO, // Measured externally by daemon
-            total: execution_time,
-        };
-        self.cycle_count += 1;
+            // 3. Execute the task's entry point with its inputs (timed)
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.before_step(&self.engine);
+            }
+            let logic_start = Instant::now();
+            let outputs = match self.engine.step_entry(&task.entry, &task.inputs) {
+                Ok(outputs) => outputs,
+                Err(e) => {
+                    if let Some(recorder) = self.recorder.as_mut() {
+                        recorder.after_step(cycle, index, &task.inputs, None);
+                    }
+                    let phase_timings = CyclePhaseTimings {
+                        io_read: io_read_time,
+                        logic_exec: logic_start.elapsed(),
+                        io_write: Duration::ZERO,
+                        fieldbus_exchange: Duration::ZERO,
+                        total: cycle_start.elapsed(),
+                    };
+                    let reason = format!("Task '{}' failed: {e}", task.name);
+                    self.fault_recorder.record_fault(
+                        self.cycle_count,
+                        FaultReason::LogicError,
+                        phase_timings,
+                    );
+                    self.fault_recorder.set_fault_task(index);
+                    if let Some(location) = self.engine.trap_location() {
+                        self.fault_recorder.set_fault_location(location);
+                    }
+                    self.enter_fault(&reason)?;
+                    return Err(e);
+                }
+            };
+            let logic_exec_time = logic_start.elapsed();
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.after_step(cycle, index, &task.inputs, Some(outputs));
+            }
 
-        // 5. Record fault frame for this cycle (for postmortem analysis)
-        if let Some(frame) = self
-            .fault_recorder
-            .record_cycle(self.cycle_count, phase_timings)
-        {
-            frame.set_inputs(&inputs);
-            frame.set_outputs(&outputs);
+            // 4. Commit outputs to I/O image for fieldbus to read (timed);
+            // in DEBUG with safe outputs, stepped cycles do not drive them
+            let io_write_start = Instant::now();
+            let commit =
+                self.debug_run.is_none() || self.debug_config.outputs == DebugOutputPolicy::Hold;
+            if commit {
+                // Track last outputs for HoldLast safe output policy; forces
+                // are not held
+                self.last_outputs.copy_outputs_from(outputs);
+            }
+            if !self.forces.is_empty() {
+                apply_variable_forces(&mut self.engine, &mut self.forces);
+            }
+            if commit {
+                // Only copy output fields, not the entire ProcessData
+                let (forces, last) = (&self.forces, &self.last_outputs);
+                self.io.write_outputs(|io_outputs| {
+                    io_outputs.copy_outputs_from(last);
+                    forces.apply_outputs(io_outputs);
+                });
+            }
+            let io_write_time = io_write_start.elapsed();
+
+            // Response time, from the start of the scan
+            let execution_time = cycle_start.elapsed();
+            let phase_timings = CyclePhaseTimings {
+                io_read: io_read_time,
+                logic_exec: logic_exec_time,
+                io_write: io_write_time,
+                fieldbus_exchange: Duration::ZERO, // Measured externally by daemon
+                total: execution_time,
+            };
+            task.cycle_count += 1;
+
+            // 5. Record fault frame for this task (for postmortem analysis)
+            if let Some(frame) = self.fault_recorder.record_cycle(cycle, phase_timings) {
+                frame.set_task(index);
+                frame.set_inputs(&task.inputs);
+                frame.set_outputs(&self.last_outputs);
+            }
+
+            // 6. Record metrics
+            task.metrics.record(execution_time);
+            scan_timings.io_read += io_read_time;
+            scan_timings.logic_exec += logic_exec_time;
+            scan_timings.io_write += io_write_time;
+
+            // 7. Check for overrun and apply the task's fault policy
+            if let Some(release) = task.next_release.as_mut() {
+                *release += task.period;
+            }
+            if execution_time > task.period {
+                overrun = true;
+                if let Err(e) = self.check_overrun(index, cycle, execution_time, phase_timings) {
+                    self.cycle_count = cycle;
+                    return Err(e);
+                }
+            }
         }
 
-        // 6. Record metrics
+        self.cycle_count = cycle;
+        let execution_time = cycle_start.elapsed();
+        scan_timings.total = execution_time;
         self.metrics.record(execution_time);
 
-        // 6. Check for overrun and apply fault policy
-        let overrun = execution_time > self.cycle_period;
-        if overrun {
-            let overrun_amount = execution_time - self.cycle_period;
-            if overrun_amount > self.max_overrun {
-                // Critical overrun - apply overrun policy
-                match self.fault_policy.on_overrun {
-                    OverrunPolicy::Fault => {
-                        error!(
-                            execution_us = execution_time.as_micros(),
-                            deadline_us = self.cycle_period.as_micros(),
-                            overrun_us = overrun_amount.as_micros(),
-                            "Critical cycle overrun - entering fault state"
-                        );
-                        self.fault_recorder.record_fault(
-                            self.cycle_count,
-                            FaultReason::CycleOverrun,
-                            phase_timings,
-                        );
-                        self.enter_fault("Critical cycle overrun")?;
-                        return Err(PlcError::CycleOverrun {
-                            expected_ns: self.cycle_period.as_nanos() as u64,
-                            actual_ns: execution_time.as_nanos() as u64,
-                        });
-                    }
-                    OverrunPolicy::Warn => {
-                        warn!(
-                            cycle = self.cycle_count,
-                            execution_us = execution_time.as_micros(),
-                            deadline_us = self.cycle_period.as_micros(),
-                            overrun_us = overrun_amount.as_micros(),
-                            "Critical cycle overrun (policy: warn)"
-                        );
-                    }
-                    OverrunPolicy::Ignore => {
-                        trace!(
-                            cycle = self.cycle_count,
-                            overrun_us = overrun_amount.as_micros(),
-                            "Critical cycle overrun ignored by policy"
-                        );
-                    }
-                }
-            } else {
-                // Minor overrun within tolerance
-                warn!(
-                    cycle = self.cycle_count,
-                    execution_us = execution_time.as_micros(),
-                    deadline_us = self.cycle_period.as_micros(),
-                    "Cycle overrun (within tolerance)"
-                );
+        if let Some(tracer) = self.tracer.as_mut() {
+            let forces = &self.forces;
+            tracer.sample(
+                self.cycle_count,
+                &self.io,
+                |inputs| forces.apply_inputs(inputs),
+                &self.engine,
+            );
+        }
+
+        // In DEBUG, pause once the command is done
+        if let Some(run) = self.debug_run.as_mut() {
+            let engine = &self.engine;
+            if let Some(reason) = run.after_scan(|name| engine.read_variable(name)) {
+                self.pause_logic(reason);
             }
         }
 
-        // 7. Wait for next deadline
-        if let Some(deadline) = self.next_deadline {
-            self.wait_until(deadline);
-            self.next_deadline = Some(deadline + self.cycle_period);
+        // 8. Wait for the next release
+        if let Some(release) = self.tasks.iter().filter_map(|task| task.next_release).min() {
+            self.wait_until(release);
         }
 
         trace!(
             cycle = self.cycle_count,
             execution_us = execution_time.as_micros(),
-            io_read_us = phase_timings.io_read.as_micros(),
-            logic_exec_us = phase_timings.logic_exec.as_micros(),
-            io_write_us = phase_timings.io_write.as_micros(),
+            io_read_us = scan_timings.io_read.as_micros(),
+            logic_exec_us = scan_timings.logic_exec.as_micros(),
+            io_write_us = scan_timings.io_write.as_micros(),
             "Cycle complete"
         );
 
@@ -381,17 +869,84 @@ impl<E: LogicEngine> Scheduler<E> {
             execution_time,
             overrun,
             cycle_count: self.cycle_count,
-            phase_timings,
+            paused: false,
+            phase_timings: scan_timings,
         })
     }
 
+    /// Apply a task's overrun policy after it missed its deadline.
+    fn check_overrun(
+        &mut self,
+        index: usize,
+        cycle: u64,
+        execution_time: Duration,
+        phase_timings: CyclePhaseTimings,
+    ) -> PlcResult<()> {
+        let task = &self.tasks[index];
+        let overrun_amount = execution_time - task.period;
+        if overrun_amount <= task.max_overrun {
+            // Minor overrun within tolerance
+            warn!(
+                cycle,
+                task = %task.name,
+                execution_us = execution_time.as_micros(),
+                deadline_us = task.period.as_micros(),
+                "Cycle overrun (within tolerance)"
+            );
+            return Ok(());
+        }
+
+        // Critical overrun - apply overrun policy
+        match task.on_overrun {
+            OverrunPolicy::Fault => {
+                error!(
+                    task = %task.name,
+                    execution_us = execution_time.as_micros(),
+                    deadline_us = task.period.as_micros(),
+                    overrun_us = overrun_amount.as_micros(),
+                    "Critical cycle overrun - entering fault state"
+                );
+                let err = PlcError::CycleOverrun {
+                    expected_ns: task.period.as_nanos() as u64,
+                    actual_ns: execution_time.as_nanos() as u64,
+                };
+                let reason = format!("Critical cycle overrun in task '{}'", task.name);
+                self.fault_recorder
+                    .record_fault(cycle, FaultReason::CycleOverrun, phase_timings);
+                self.fault_recorder.set_fault_task(index);
+                self.enter_fault(&reason)?;
+                Err(err)
+            }
+            OverrunPolicy::Warn => {
+                warn!(
+                    cycle,
+                    task = %task.name,
+                    execution_us = execution_time.as_micros(),
+                    deadline_us = task.period.as_micros(),
+                    overrun_us = overrun_amount.as_micros(),
+                    "Critical cycle overrun (policy: warn)"
+                );
+                Ok(())
+            }
+            OverrunPolicy::Ignore => {
+                trace!(
+                    cycle,
+                    task = %task.name,
+                    overrun_us = overrun_amount.as_micros(),
+                    "Critical cycle overrun ignored by policy"
+                );
+                Ok(())
+            }
+        }
+    }
+
     /// Run the scheduler loop until stopped or faulted.
     ///
     /// This blocks the current thread.
     pub fn run(&mut self) -> PlcResult<()> {
         info!("Entering main scheduler loop");
 
-        while self.state.state() == RuntimeState::Run {
+        while self.state.state().is_scanning() {
             self.run_cycle()?;
         }
 
@@ -406,18 +961,28 @@ impl<E: LogicEngine> Scheduler<E> {
 
     /// Stop cyclic execution gracefully.
     ///
-    /// Transitions RUN → SAFE_STOP and stops the watchdog.
+    /// Transitions RUN → SAFE_STOP and stops the watchdogs, the trace and
+    /// the recording.
     pub fn stop(&mut self) -> PlcResult<()> {
         info!("Stopping scheduler");
 
-        // Stop the watchdog first to prevent spurious triggers during shutdown
-        if let Some(ref mut wd) = self.watchdog {
-            wd.stop();
+        // Stop the watchdogs first to prevent spurious triggers during shutdown
+        for task in &mut self.tasks {
+            if let Some(ref mut wd) = task.watchdog {
+                wd.stop();
+            }
         }
 
-        if self.state.state() == RuntimeState::Run {
+        if self.state.state().is_scanning() {
             self.state.transition(RuntimeState::SafeStop)?;
         }
+        self.debug_run = None;
+        self.stop_trace();
+        self.stop_recording();
+
+        if self.forcing.clear_on_stop {
+            self.clear_forces();
+        }
 
         // Set outputs to safe state
         self.set_safe_outputs();
@@ -433,6 +998,16 @@ impl<E: LogicEngine> Scheduler<E> {
         error!(reason, "Entering FAULT state");
 
         self.state.enter_fault();
+        self.debug_run = None;
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.fault();
+        }
+
+        // Forces never survive a fault
+        let released = self.forces.clear();
+        if released > 0 {
+            warn!(count = released, "Forces released on fault");
+        }
 
         // Try to set outputs to safe state
         self.set_safe_outputs();
@@ -445,6 +1020,24 @@ impl<E: LogicEngine> Scheduler<E> {
         Ok(())
     }
 
+    /// Record an external fault with the current I/O, then enter fault state.
+    ///
+    /// Used by the daemon for failures outside the scan, such as the
+    /// fieldbus, so they are captured by the fault recorder like faults
+    /// detected by the scheduler.
+    pub fn enter_external_fault(&mut self, reason: FaultReason, message: &str) -> PlcResult<()> {
+        let inputs = self.io.read_inputs();
+        let outputs = self.io.read_outputs();
+        self.fault_recorder.record_fault_with_io(
+            self.cycle_count,
+            reason,
+            CyclePhaseTimings::default(),
+            &inputs,
+            &outputs,
+        );
+        self.enter_fault(message)
+    }
+
     /// Set outputs to safe values based on the configured safe output policy.
     ///
     /// Uses the seqlock-protected write_outputs() for thread-safe atomic update.
@@ -454,36 +1047,23 @@ impl<E: LogicEngine> Scheduler<E> {
         match &self.fault_policy.safe_outputs {
             SafeOutputPolicy::AllOff => {
                 self.io.write_outputs(|outputs| {
-                    outputs.digital_outputs = [0; 1];
-                    outputs.analog_outputs = [0; 16];
+                    outputs.digital_outputs_mut().fill(0);
+                    outputs.analog_outputs_mut().fill(0);
                 });
             }
             SafeOutputPolicy::HoldLast => {
                 // Keep current outputs - they're already in place via last_outputs tracking
                 // Just ensure the I/O image has the last known values
-                let last = self.last_outputs;
-                self.io.write_outputs(|outputs| {
-                    outputs.digital_outputs = last.digital_outputs;
-                    outputs.analog_outputs = last.analog_outputs;
-                });
+                let last = &self.last_outputs;
+                self.io
+                    .write_outputs(|outputs| outputs.copy_outputs_from(last));
                 debug!("Holding last output values");
             }
             SafeOutputPolicy::UserDefined { digital, analog } => {
-                // Apply user-defined safe values
-                let mut safe_digital = [0u32; 1];
-                let mut safe_analog = [0i16; 16];
-
-                // Copy user-defined values (up to array bounds)
-                for (i, &val) in digital.iter().take(safe_digital.len()).enumerate() {
-                    safe_digital[i] = val;
-                }
-                for (i, &val) in analog.iter().take(safe_analog.len()).enumerate() {
-                    safe_analog[i] = val;
-                }
-
+                // Apply user-defined safe values; outputs without one are off
                 self.io.write_outputs(|outputs| {
-                    outputs.digital_outputs = safe_digital;
-                    outputs.analog_outputs = safe_analog;
+                    fill_safe_values(outputs.digital_outputs_mut(), digital);
+                    fill_safe_values(outputs.analog_outputs_mut(), analog);
                 });
                 debug!("Applied user-defined safe output values");
             }
@@ -561,9 +1141,36 @@ impl<E: LogicEngine> Scheduler<E> {
         &mut self.io
     }
 
-    /// Check if the watchdog has triggered.
+    /// Check if any task's watchdog has triggered.
     pub fn watchdog_triggered(&self) -> bool {
-        self.watchdog.as_ref().is_some_and(|wd| wd.has_triggered())
+        self.tasks
+            .iter()
+            .any(|task| task.watchdog.as_ref().is_some_and(|wd| wd.has_triggered()))
+    }
+}
+
+/// Write the forced variables into the logic engine.
+///
+/// A variable that can no longer be written (for example after an online
+/// change removed it) loses its force.
+fn apply_variable_forces<E: LogicEngine>(engine: &mut E, forces: &mut ForceTable) {
+    let mut stale = Vec::new();
+    for (name, value) in forces.variables() {
+        if let Err(e) = engine.write_variable(name, value) {
+            warn!(variable = name, "Releasing force: {e}");
+            stale.push(ForceTarget::Variable(name.to_string()));
+        }
+    }
+    for target in &stale {
+        forces.remove(target);
+    }
+}
+
+/// Copy configured safe values into an output region, zeroing outputs the
+/// configuration does not cover.
+fn fill_safe_values<T: Copy + Default>(outputs: &mut [T], values: &[T]) {
+    for (i, output) in outputs.iter_mut().enumerate() {
+        *output = values.get(i).copied().unwrap_or_default();
     }
 }
 
@@ -596,7 +1203,8 @@ impl<E: LogicEngine> SchedulerBuilder<E> {
         self
     }
 
-    /// Set the watchdog timeout.
+    /// Enable task watchdogs, with this timeout for the implicit task built
+    /// from `cycle_time`. Configured tasks use their own timeouts.
     pub fn watchdog_timeout(mut self, timeout: Duration) -> Self {
         self.watchdog_timeout = Some(timeout);
         self
@@ -609,11 +1217,14 @@ impl<E: LogicEngine> SchedulerBuilder<E> {
     }
 
     /// Build the scheduler.
-    pub fn build(self) -> Scheduler<E> {
+    pub fn build(mut self) -> Scheduler<E> {
+        if let Some(timeout) = self.watchdog_timeout {
+            self.config.watchdog_timeout = timeout;
+        }
         let mut scheduler = Scheduler::new(self.engine, &self.config);
 
-        if let Some(timeout) = self.watchdog_timeout {
-            scheduler.set_watchdog(Watchdog::new(timeout));
+        if self.watchdog_timeout.is_some() {
+            scheduler.enable_watchdogs();
         }
 
         scheduler
@@ -628,6 +1239,8 @@ mod tests {
     struct MockEngine {
         step_count: u64,
         should_fail: bool,
+        outputs: ProcessData,
+        entries: Vec<String>,
     }
 
     impl MockEngine {
@@ -635,6 +1248,8 @@ mod tests {
             Self {
                 step_count: 0,
                 should_fail: false,
+                outputs: ProcessData::default(),
+                entries: Vec::new(),
             }
         }
     }
@@ -644,16 +1259,19 @@ mod tests {
             Ok(())
         }
 
-        fn step(
-            &mut self,
-            inputs: &crate::io_image::ProcessData,
-        ) -> PlcResult<crate::io_image::ProcessData> {
+        fn step(&mut self, inputs: &ProcessData) -> PlcResult<&ProcessData> {
             if self.should_fail {
                 return Err(PlcError::Fault("Simulated failure".into()));
             }
             self.step_count += 1;
             // Pass-through: copy inputs to outputs
-            Ok(*inputs)
+            self.outputs.clone_from(inputs);
+            Ok(&self.outputs)
+        }
+
+        fn step_entry(&mut self, entry: &str, inputs: &ProcessData) -> PlcResult<&ProcessData> {
+            self.entries.push(entry.to_string());
+            self.step(inputs)
         }
 
         fn fault(&mut self) -> PlcResult<()> {
@@ -663,6 +1281,13 @@ mod tests {
         fn is_ready(&self) -> bool {
             true
         }
+
+        fn read_variable(&self, name: &str) -> PlcResult<VarValue> {
+            match name {
+                "Mock.steps" => Ok(VarValue::UInt(self.step_count)),
+                _ => Err(PlcError::Config(format!("unknown variable: {name}"))),
+            }
+        }
     }
 
     #[test]
@@ -713,9 +1338,332 @@ mod tests {
             .watchdog_timeout(Duration::from_millis(15))
             .build();
 
-        assert_eq!(scheduler.cycle_period, Duration::from_millis(5));
-        assert_eq!(scheduler.max_overrun, Duration::from_micros(100));
-        assert!(scheduler.watchdog.is_some());
+        assert_eq!(scheduler.cycle_period(), Duration::from_millis(5));
+        let task = &scheduler.tasks()[0];
+        assert_eq!(task.entry(), "step");
+        assert_eq!(task.max_overrun(), Duration::from_micros(100));
+        assert_eq!(task.watchdog_timeout(), Duration::from_millis(15));
+        assert!(task.has_watchdog());
+    }
+
+    fn task(name: &str, period_ms: u64, priority: u32) -> TaskConfig {
+        TaskConfig {
+            name: name.into(),
+            entry: None,
+            period: Duration::from_millis(period_ms),
+            priority,
+            max_overrun: None,
+            on_overrun: None,
+            watchdog_timeout: None,
+        }
+    }
+
+    #[test]
+    fn test_task_order_and_defaults() {
+        let urgent = TaskConfig {
+            on_overrun: Some(OverrunPolicy::Ignore),
+            ..task("Urgent", 50, 0)
+        };
+        let config = RuntimeConfig {
+            tasks: vec![task("Slow", 20, 1), task("Fast", 2, 0), urgent],
+            max_overrun: Duration::from_micros(250),
+            ..Default::default()
+        };
+        let scheduler = Scheduler::new(MockEngine::new(), &config);
+
+        let names: Vec<&str> = scheduler.tasks().iter().map(Task::name).collect();
+        assert_eq!(names, ["Fast", "Urgent", "Slow"]);
+        assert_eq!(scheduler.cycle_period(), Duration::from_millis(2));
+
+        let fast = &scheduler.tasks()[0];
+        assert_eq!(fast.entry(), "Fast");
+        assert_eq!(fast.max_overrun(), Duration::from_micros(250));
+        assert_eq!(fast.on_overrun(), OverrunPolicy::Fault);
+        assert_eq!(fast.watchdog_timeout(), Duration::from_millis(6));
+        assert!(!fast.has_watchdog());
+        assert_eq!(scheduler.tasks()[1].on_overrun(), OverrunPolicy::Ignore);
+    }
+
+    #[test]
+    fn test_multi_task_rates() {
+        let config = RuntimeConfig {
+            tasks: vec![task("Fast", 2, 0), task("Slow", 6, 0)],
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+
+        let start = Instant::now();
+        while scheduler.tasks()[0].cycle_count() < 9 {
+            scheduler.run_cycle().unwrap();
+        }
+        let elapsed = start.elapsed();
+
+        let fast = &scheduler.tasks()[0];
+        let slow = &scheduler.tasks()[1];
+        assert_eq!(fast.metrics().total_cycles(), fast.cycle_count());
+        assert!((3..=4).contains(&slow.cycle_count()));
+        assert_eq!(slow.metrics().total_cycles(), slow.cycle_count());
+        assert!(elapsed >= Duration::from_millis(16));
+
+        // The first scan releases both tasks, fast one first
+        let entries = &scheduler.engine.entries;
+        assert_eq!(entries[..2], ["Fast", "Slow"]);
+        assert_eq!(
+            scheduler.engine.step_count,
+            fast.cycle_count() + slow.cycle_count()
+        );
+    }
+
+    #[test]
+    fn test_task_fault_recorded() {
+        let config = RuntimeConfig {
+            tasks: vec![task("Fast", 2, 0), task("Slow", 6, 0)],
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+        scheduler.run_cycle().unwrap();
+
+        scheduler.engine.should_fail = true;
+        assert!(scheduler.run_cycle().is_err());
+        assert_eq!(scheduler.state(), RuntimeState::Fault);
+        let summary = scheduler.fault_recorder().fault_summary().unwrap();
+        assert_eq!(summary.reason, FaultReason::LogicError);
+        assert_eq!(summary.task, 0);
+    }
+
+    #[test]
+    fn test_external_fault_recorded_with_io() {
+        let mut scheduler = Scheduler::new(MockEngine::new(), &RuntimeConfig::default());
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+        scheduler.run_cycle().unwrap();
+        scheduler
+            .io
+            .write_inputs(|data| data.digital_inputs_mut()[0] = 0x0102_0304);
+
+        scheduler
+            .enter_external_fault(FaultReason::FieldbusError, "Fieldbus down")
+            .unwrap();
+        assert_eq!(scheduler.state(), RuntimeState::Fault);
+        let summary = scheduler.fault_recorder().fault_summary().unwrap();
+        assert_eq!(summary.reason, FaultReason::FieldbusError);
+        assert_eq!(summary.cycle, 1);
+        let frame = scheduler.fault_recorder().fault_frame().unwrap();
+        assert_eq!(frame.inputs[..4], [0x04, 0x03, 0x02, 0x01]);
+    }
+
+    #[test]
+    fn test_forces_applied_and_released_on_fault() {
+        let config = RuntimeConfig {
+            cycle_time: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+
+        let target = |s: &str| s.parse::<ForceTarget>().unwrap();
+        scheduler
+            .force(target("%IX0.1"), VarValue::Bool(true))
+            .unwrap();
+        scheduler.force(target("%QW0"), VarValue::Int(7)).unwrap();
+        // The mock engine has no symbols
+        assert!(scheduler.force(target("Main.x"), VarValue::Int(1)).is_err());
+        assert_eq!(scheduler.forces().len(), 2);
+
+        scheduler.run_cycle().unwrap();
+        // The logic saw the forced input; the I/O image has the forced output
+        assert_eq!(scheduler.engine.outputs.digital_inputs()[0], 1 << 1);
+        assert_eq!(scheduler.io.read_outputs().analog_outputs()[0], 7);
+        // Held outputs are the logic's own
+        assert_eq!(scheduler.last_outputs.analog_outputs()[0], 0);
+
+        assert!(scheduler.unforce(&target("%QW0")));
+        scheduler.run_cycle().unwrap();
+        assert_eq!(scheduler.io.read_outputs().analog_outputs()[0], 0);
+
+        scheduler.engine.should_fail = true;
+        assert!(scheduler.run_cycle().is_err());
+        assert!(scheduler.forces().is_empty());
+    }
+
+    #[test]
+    fn test_debug_step_and_breakpoint() {
+        let mut scheduler = Scheduler::new(MockEngine::new(), &RuntimeConfig::default());
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+        assert!(scheduler.debug(DebugCommand::Step(1)).is_err());
+        scheduler.run_cycle().unwrap();
+
+        scheduler.enter_debug().unwrap();
+        assert_eq!(scheduler.state(), RuntimeState::Debug);
+        assert_eq!(
+            scheduler.take_debug_stop().unwrap().reason,
+            StopReason::Entered
+        );
+        assert!(scheduler.take_debug_stop().is_none());
+
+        // Paused: the scan keeps going, the logic does not run
+        let result = scheduler.run_cycle().unwrap();
+        assert!(result.paused);
+        assert_eq!(scheduler.engine.step_count, 1);
+
+        scheduler.debug(DebugCommand::Step(2)).unwrap();
+        assert!(!scheduler.run_cycle().unwrap().paused);
+        assert!(scheduler.take_debug_stop().is_none());
+        scheduler.run_cycle().unwrap();
+        let stop = scheduler.take_debug_stop().unwrap();
+        assert_eq!(stop.reason, StopReason::Stepped);
+        assert_eq!(stop.cycle, 3);
+        assert!(scheduler.run_cycle().unwrap().paused);
+        assert_eq!(scheduler.engine.step_count, 3);
+
+        assert!(scheduler
+            .debug(DebugCommand::RunUntil("Mock.missing".parse().unwrap()))
+            .is_err());
+        let breakpoint = "Mock.steps >= 6".parse().unwrap();
+        scheduler.debug(DebugCommand::RunUntil(breakpoint)).unwrap();
+        while !scheduler.is_paused() {
+            scheduler.run_cycle().unwrap();
+        }
+        assert_eq!(scheduler.engine.step_count, 6);
+        assert!(matches!(
+            scheduler.debug_stop().unwrap().reason,
+            StopReason::Breakpoint(_)
+        ));
+
+        scheduler.exit_debug().unwrap();
+        assert_eq!(scheduler.state(), RuntimeState::Run);
+        assert!(!scheduler.run_cycle().unwrap().paused);
+        assert_eq!(scheduler.engine.step_count, 7);
+    }
+
+    #[test]
+    fn test_trace_armed_at_start_ends_on_fault() {
+        let config = RuntimeConfig {
+            cycle_time: Duration::from_micros(100),
+            trace: TraceConfig {
+                signals: vec!["Mock.steps".into(), "%QD0".into()],
+                trigger: "fault".into(),
+                pre_trigger: 2,
+                ..TraceConfig::default()
+            },
+            ..RuntimeConfig::default()
+        };
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+        let trace = scheduler.trace().unwrap();
+
+        for _ in 0..5 {
+            scheduler.run_cycle().unwrap();
+        }
+        assert_eq!(trace.state(), crate::trace::TraceState::Armed);
+        scheduler.engine.should_fail = true;
+        assert!(scheduler.run_cycle().is_err());
+
+        let capture = trace.capture();
+        assert_eq!(capture.state, crate::trace::TraceState::Complete);
+        let steps: Vec<_> = capture.samples.iter().map(|s| s.values[0]).collect();
+        assert_eq!(steps, [3, 4, 5].map(VarValue::UInt));
+        assert_eq!(capture.trigger_sample().unwrap().cycle, 5);
+
+        // Signals are checked when armed
+        let spec = TraceSpec {
+            signals: vec!["Mock.missing".parse().unwrap()],
+            ..TraceSpec::from_config(&config.trace).unwrap()
+        };
+        assert!(scheduler.arm_trace(spec).is_err());
+    }
+
+    #[test]
+    fn test_debug_safe_outputs_not_driven() {
+        let mut config = RuntimeConfig::default();
+        config.fault_policy.safe_outputs = SafeOutputPolicy::UserDefined {
+            digital: vec![0],
+            analog: vec![100],
+        };
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+        scheduler.run_cycle().unwrap();
+
+        scheduler.enter_debug().unwrap();
+        assert_eq!(scheduler.io.read_outputs().analog_outputs()[0], 100);
+        scheduler.debug(DebugCommand::Step(1)).unwrap();
+        scheduler.run_cycle().unwrap();
+        assert_eq!(scheduler.engine.step_count, 2);
+        assert_eq!(scheduler.io.read_outputs().analog_outputs()[0], 100);
+
+        scheduler.exit_debug().unwrap();
+        scheduler.run_cycle().unwrap();
+        assert_eq!(scheduler.io.read_outputs().analog_outputs()[0], 0);
+    }
+
+    #[test]
+    fn test_steps_recorded() {
+        let dir = std::env::temp_dir().join(format!("plc-record-{}", std::process::id()));
+        let mut config = RuntimeConfig::default();
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        assert!(scheduler.start_recording(b"module").is_err());
+
+        config.record.dir = Some(dir.clone());
+        config.record.segment_steps = 2;
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        scheduler.initialize().unwrap();
+        scheduler.start_recording(b"module").unwrap();
+        scheduler.start().unwrap();
+        for _ in 0..3 {
+            scheduler.run_cycle().unwrap();
+        }
+        scheduler.engine.should_fail = true;
+        assert!(scheduler.run_cycle().is_err());
+        scheduler.stop_recording();
+        assert!(!scheduler.is_recording());
+
+        let segments = crate::replay::read_segments(&dir).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].header.module, b"module");
+        assert_eq!(segments[0].header.entries, ["step"]);
+        let scans: Vec<_> = segments
+            .iter()
+            .flat_map(|segment| segment.records.iter().map(|r| (r.scan, r.trapped)))
+            .collect();
+        assert_eq!(scans, [(1, false), (2, false), (3, false), (4, true)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_take_over_continues_scan() {
+        let mut scheduler = Scheduler::with_defaults(MockEngine::new());
+        scheduler.initialize().unwrap();
+        scheduler.start().unwrap();
+
+        let mut image = ProcessData::default();
+        image.digital_inputs_mut()[0] = 0b10;
+        image.digital_outputs_mut()[0] = 0b01;
+        scheduler.take_over(&[], 41, 41, &image).unwrap();
+        assert_eq!(scheduler.cycle_count(), 41);
+        assert_eq!(scheduler.io.read_inputs().digital_inputs()[0], 0b10);
+        assert_eq!(scheduler.io.read_outputs().digital_outputs()[0], 0b01);
+
+        // The mock engine has no memory to restore
+        assert!(scheduler.take_over(&[0; 4], 41, 41, &image).is_err());
+
+        assert_eq!(scheduler.run_cycle().unwrap().cycle_count, 42);
+    }
+
+    #[test]
+    fn test_forcing_disabled() {
+        let mut config = RuntimeConfig::default();
+        config.forcing.enabled = false;
+        let mut scheduler = Scheduler::new(MockEngine::new(), &config);
+        let target = "%IX0.0".parse::<ForceTarget>().unwrap();
+        assert!(scheduler.force(target, VarValue::Bool(true)).is_err());
     }
 
     #[test]
@@ -0,0 +1,331 @@
+//! Signed logic modules.
+//!
+//! Modules are signed with Ed25519 keys. The signature travels with the
+//! module: a Wasm module carries it in a trailing `plc.signature` custom
+//! section, a precompiled artifact in its signature field. It covers every
+//! byte before it, so any change to the module, or to an artifact's native
+//! code, invalidates it. Both take the same 96-byte payload: the signer's
+//! public key followed by the signature.
+//!
+//! Hosts configured with trusted keys ([`WasmtimeConfig::trusted_keys`])
+//! check the signature before they compile a module, on load and on
+//! hot-reload, and refuse unsigned modules, modules signed by other keys
+//! and tampered ones with [`PlcError::UntrustedModule`]. Each decision is
+//! logged under the [`AUDIT_TARGET`] target.
+//!
+//! [`WasmtimeConfig::trusted_keys`]: crate::wasm_host::WasmtimeConfig::trusted_keys
+
+use crate::precompiled::Artifact;
+use ed25519_dalek::{Signature, Signer, VerifyingKey};
+use plc_common::error::{PlcError, PlcResult};
+use plc_common::wasm_meta;
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Write as _};
+use tracing::{error, info};
+
+/// Custom section holding a Wasm module's signature.
+pub const SIGNATURE_SECTION: &str = "plc.signature";
+
+/// Tracing target of the accept and reject decisions.
+pub const AUDIT_TARGET: &str = "plc::audit";
+
+/// Prefix of every signed message, so that a module signature cannot be
+/// mistaken for a signature over anything else.
+const DOMAIN: &[u8] = b"plc.module-signature.v1\0";
+
+const KEY_LEN: usize = 32;
+const PAYLOAD_LEN: usize = KEY_LEN + 64;
+
+/// A key that signs modules.
+pub struct SigningKey(ed25519_dalek::SigningKey);
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("public_key", &self.public_key_hex())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SigningKey {
+    /// Generate a key from the operating system's random source.
+    pub fn generate() -> PlcResult<Self> {
+        let mut seed = [0; KEY_LEN];
+        getrandom::getrandom(&mut seed)
+            .map_err(|e| PlcError::IoError(format!("Failed to read random bytes: {e}")))?;
+        Ok(Self(ed25519_dalek::SigningKey::from_bytes(&seed)))
+    }
+
+    /// Read a key written by [`to_hex`](Self::to_hex).
+    pub fn from_hex(text: &str) -> PlcResult<Self> {
+        let seed = decode_hex(text.trim())
+            .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+            .ok_or_else(|| PlcError::Config("Signing key is not 64 hex digits".into()))?;
+        Ok(Self(ed25519_dalek::SigningKey::from_bytes(&seed)))
+    }
+
+    /// The secret key as hex. Keep it out of the configuration.
+    pub fn to_hex(&self) -> String {
+        encode_hex(self.0.as_bytes())
+    }
+
+    /// The public key as hex, as listed in `wasm.trusted_keys`.
+    pub fn public_key_hex(&self) -> String {
+        encode_hex(self.0.verifying_key().as_bytes())
+    }
+
+    /// Sign a Wasm module or precompiled artifact, replacing any signature
+    /// it has.
+    pub fn sign(&self, module: &[u8]) -> PlcResult<Vec<u8>> {
+        let signed = signed_content(module)?;
+        let mut payload = Vec::with_capacity(PAYLOAD_LEN);
+        payload.extend_from_slice(self.0.verifying_key().as_bytes());
+        payload.extend_from_slice(&self.0.sign(&message(&signed.content)).to_bytes());
+
+        Ok(match Artifact::parse(module)? {
+            Some(artifact) => Artifact {
+                signature: &payload,
+                ..artifact
+            }
+            .encode(),
+            None => {
+                let mut wasm = signed.content;
+                wasm_meta::append_custom_section(&mut wasm, SIGNATURE_SECTION, &payload);
+                wasm
+            }
+        })
+    }
+}
+
+/// Public keys whose signatures a host accepts.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedKeys(Vec<VerifyingKey>);
+
+impl TrustedKeys {
+    /// Parse hex public keys; none means signatures are not checked.
+    pub fn parse(keys: &[String]) -> PlcResult<Self> {
+        keys.iter()
+            .map(|key| {
+                decode_hex(key)
+                    .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+                    .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+                    .ok_or_else(|| PlcError::Config(format!("Invalid trusted key '{key}'")))
+            })
+            .collect::<PlcResult<_>>()
+            .map(Self)
+    }
+
+    /// Whether signatures are not checked.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check that `module` is signed by a trusted key, logging the decision
+    /// to the audit trail. Always passes if no keys are trusted.
+    pub fn check(&self, module: &[u8]) -> PlcResult<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let module_sha256 = encode_hex(&Sha256::digest(module));
+        match self.verify(module) {
+            Ok(key) => {
+                info!(
+                    target: AUDIT_TARGET,
+                    %module_sha256,
+                    key = %encode_hex(key.as_bytes()),
+                    "Logic module accepted: signed by a trusted key"
+                );
+                Ok(())
+            }
+            Err(reason) => {
+                error!(
+                    target: AUDIT_TARGET,
+                    %module_sha256,
+                    %reason,
+                    "Logic module refused"
+                );
+                Err(PlcError::UntrustedModule(reason))
+            }
+        }
+    }
+
+    fn verify(&self, module: &[u8]) -> Result<&VerifyingKey, String> {
+        let signed = signed_content(module).map_err(|e| e.to_string())?;
+        let payload = signed.signature.ok_or("module is not signed")?;
+        if payload.len() != PAYLOAD_LEN {
+            return Err("malformed signature".into());
+        }
+        let (key, signature) = payload.split_at(KEY_LEN);
+        let trusted = self
+            .0
+            .iter()
+            .find(|trusted| trusted.as_bytes() == key)
+            .ok_or_else(|| format!("signed by untrusted key {}", encode_hex(key)))?;
+        let signature = Signature::from_slice(signature).map_err(|_| "malformed signature")?;
+        trusted
+            .verify_strict(&message(&signed.content), &signature)
+            .map_err(|_| "signature does not match the module; it was altered after signing")?;
+        Ok(trusted)
+    }
+}
+
+/// Public key, as hex, of whoever signed `module`, without verifying the
+/// signature; `None` if it is unsigned.
+pub fn signer(module: &[u8]) -> PlcResult<Option<String>> {
+    Ok(signed_content(module)?
+        .signature
+        .filter(|payload| payload.len() == PAYLOAD_LEN)
+        .map(|payload| encode_hex(&payload[..KEY_LEN])))
+}
+
+/// What a signature covers, and the signature if there is one.
+struct SignedContent<'a> {
+    content: Vec<u8>,
+    signature: Option<&'a [u8]>,
+}
+
+fn signed_content(module: &[u8]) -> PlcResult<SignedContent<'_>> {
+    if let Some(artifact) = Artifact::parse(module)? {
+        return Ok(SignedContent {
+            content: artifact.signed_bytes(),
+            signature: Some(artifact.signature).filter(|signature| !signature.is_empty()),
+        });
+    }
+    Ok(
+        match wasm_meta::trailing_custom_section(module, SIGNATURE_SECTION) {
+            Some((start, payload)) => SignedContent {
+                content: module[..start].to_vec(),
+                signature: Some(payload),
+            },
+            None => SignedContent {
+                content: module.to_vec(),
+                signature: None,
+            },
+        },
+    )
+}
+
+fn message(content: &[u8]) -> Vec<u8> {
+    [DOMAIN, content].concat()
+}
+
+/// Lower-case hex of `bytes`.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precompiled::precompile;
+    use crate::wasm_host::WasmtimeConfig;
+
+    const WAT: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "step"))
+        )
+    "#;
+
+    fn trusting(key: &SigningKey) -> TrustedKeys {
+        TrustedKeys::parse(&[key.public_key_hex()]).unwrap()
+    }
+
+    fn refusal(keys: &TrustedKeys, module: &[u8]) -> String {
+        match keys.check(module) {
+            Err(PlcError::UntrustedModule(reason)) => reason,
+            other => panic!("expected a refusal, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_wasm() {
+        let key = SigningKey::generate().unwrap();
+        let wasm = wat::parse_str(WAT).unwrap();
+        let signed = key.sign(&wasm).unwrap();
+
+        assert!(signed.starts_with(&wasm));
+        assert!(is_valid_wasm(&signed), "signed module is still valid Wasm");
+        assert_eq!(signer(&signed).unwrap(), Some(key.public_key_hex()));
+        assert_eq!(signer(&wasm).unwrap(), None);
+        trusting(&key).check(&signed).unwrap();
+
+        // Re-signing replaces the signature rather than stacking them
+        let other = SigningKey::generate().unwrap();
+        let resigned = other.sign(&signed).unwrap();
+        assert_eq!(resigned.len(), signed.len());
+        trusting(&other).check(&resigned).unwrap();
+
+        // No trusted keys: nothing is checked
+        TrustedKeys::default().check(&wasm).unwrap();
+    }
+
+    #[test]
+    fn test_refuses_unsigned_foreign_and_tampered_modules() {
+        let key = SigningKey::generate().unwrap();
+        let keys = trusting(&key);
+        let wasm = wat::parse_str(WAT).unwrap();
+
+        assert!(refusal(&keys, &wasm).contains("not signed"));
+
+        let foreign = SigningKey::generate().unwrap().sign(&wasm).unwrap();
+        assert!(refusal(&keys, &foreign).contains("untrusted key"));
+
+        let mut tampered = key.sign(&wasm).unwrap();
+        tampered[wasm.len() - 1] ^= 0x01;
+        assert!(refusal(&keys, &tampered).contains("altered"));
+    }
+
+    #[test]
+    fn test_signed_artifact() {
+        let key = SigningKey::generate().unwrap();
+        let keys = trusting(&key);
+        let wasm = wat::parse_str(WAT).unwrap();
+        let artifact = precompile(&wasm, &WasmtimeConfig::default(), None).unwrap();
+
+        assert!(refusal(&keys, &artifact).contains("not signed"));
+        let signed = key.sign(&artifact).unwrap();
+        keys.check(&signed).unwrap();
+        assert_eq!(signer(&signed).unwrap(), Some(key.public_key_hex()));
+
+        // Native code swapped under a valid digest: the signature catches it
+        let parsed = Artifact::parse(&signed).unwrap().unwrap();
+        let mut code = parsed.code.to_vec();
+        code[0] ^= 0xff;
+        let swapped = Artifact {
+            code: &code,
+            ..parsed
+        }
+        .encode();
+        assert!(refusal(&keys, &swapped).contains("altered"));
+    }
+
+    #[test]
+    fn test_key_hex_roundtrip() {
+        let key = SigningKey::generate().unwrap();
+        let restored = SigningKey::from_hex(&format!("{}\n", key.to_hex())).unwrap();
+        assert_eq!(restored.public_key_hex(), key.public_key_hex());
+        assert!(SigningKey::from_hex("abc").is_err());
+        assert!(TrustedKeys::parse(&["zz".repeat(32)]).is_err());
+        assert!(!format!("{key:?}").contains(&key.to_hex()));
+    }
+
+    fn is_valid_wasm(wasm: &[u8]) -> bool {
+        wasmtime::Module::validate(&wasmtime::Engine::default(), wasm).is_ok()
+    }
+}
@@ -0,0 +1,325 @@
+//! Name-based access to PLC variables.
+//!
+//! The compiler embeds a symbol table describing every variable in linear
+//! memory (see [`plc_common::symbols`]). [`SymbolTable`] indexes it by
+//! qualified name so tools can read and write `Main.speed` instead of raw
+//! addresses. Lookups are case-insensitive, like IEC 61131-3 identifiers.
+//!
+//! Only elementary types can be read or written; arrays and strings are
+//! listed but have to be accessed through their address.
+
+use plc_common::error::{PlcError, PlcResult};
+use plc_common::symbols::{self, Symbol};
+use plc_common::wasm_meta;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Value of an elementary PLC variable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VarValue {
+    /// `BOOL`.
+    Bool(bool),
+    /// Signed integers, `TIME` and the date types.
+    Int(i64),
+    /// Unsigned integers and bit strings.
+    UInt(u64),
+    /// `REAL` and `LREAL`.
+    Real(f64),
+}
+
+impl fmt::Display for VarValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VarValue::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+            VarValue::Int(v) => write!(f, "{v}"),
+            VarValue::UInt(v) => write!(f, "{v}"),
+            VarValue::Real(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// How a variable's bytes are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Bool,
+    Signed,
+    Unsigned,
+    Float,
+}
+
+impl Encoding {
+    fn of(symbol: &Symbol) -> Option<Self> {
+        let encoding = match symbol.data_type.as_str() {
+            "BOOL" => Encoding::Bool,
+            "SINT" | "INT" | "DINT" | "LINT" | "TIME" | "DATE" | "TIME_OF_DAY"
+            | "DATE_AND_TIME" => Encoding::Signed,
+            "USINT" | "UINT" | "UDINT" | "ULINT" | "BYTE" | "WORD" | "DWORD" | "LWORD" => {
+                Encoding::Unsigned
+            }
+            "REAL" | "LREAL" => Encoding::Float,
+            _ => return None,
+        };
+        matches!(symbol.size, 1 | 2 | 4 | 8).then_some(encoding)
+    }
+}
+
+/// Symbol table of a loaded module, indexed by name.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    /// Upper-cased name to index in `symbols`.
+    index: HashMap<String, usize>,
+}
+
+impl SymbolTable {
+    /// Build a table from decoded symbols.
+    pub fn new(symbols: Vec<Symbol>) -> Self {
+        let index = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (symbol.name.to_uppercase(), i))
+            .collect();
+        Self { symbols, index }
+    }
+
+    /// Read the symbol table embedded in a Wasm module.
+    ///
+    /// Returns an empty table if the module has none; fails if the section
+    /// uses an unsupported format version.
+    pub fn from_wasm(wasm_bytes: &[u8]) -> PlcResult<Self> {
+        match wasm_meta::custom_section(wasm_bytes, symbols::SECTION_NAME) {
+            Some(payload) => Ok(Self::new(symbols::decode_symbols(payload)?)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Whether the table has no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Number of symbols.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// All symbols, ordered by offset.
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Look up a symbol by qualified name.
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.index
+            .get(&name.to_uppercase())
+            .map(|&i| &self.symbols[i])
+    }
+
+    /// Read a variable from linear memory.
+    pub fn read(&self, memory: &[u8], name: &str) -> PlcResult<VarValue> {
+        let (symbol, encoding) = self.resolve(name)?;
+        let bytes = slot(memory, symbol)?;
+        let mut raw = [0u8; 8];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        let unsigned = u64::from_le_bytes(raw);
+        let bits = bytes.len() as u32 * 8;
+
+        Ok(match encoding {
+            Encoding::Bool => VarValue::Bool(unsigned != 0),
+            Encoding::Unsigned => VarValue::UInt(unsigned),
+            // Sign-extend from the variable's width.
+            Encoding::Signed => VarValue::Int(((unsigned << (64 - bits)) as i64) >> (64 - bits)),
+            Encoding::Float if bits == 32 => {
+                VarValue::Real(f64::from(f32::from_bits(unsigned as u32)))
+            }
+            Encoding::Float => VarValue::Real(f64::from_bits(unsigned)),
+        })
+    }
+
+    /// Write a variable to linear memory.
+    ///
+    /// Integers are range-checked against the variable's type; integer values
+    /// are accepted for floating-point variables.
+    pub fn write(&self, memory: &mut [u8], name: &str, value: VarValue) -> PlcResult<()> {
+        let (symbol, encoding) = self.resolve(name)?;
+        let bits = symbol.size * 8;
+        let mismatch = || {
+            PlcError::Config(format!(
+                "cannot assign {value} to {} : {}",
+                symbol.name, symbol.data_type
+            ))
+        };
+
+        let raw: u64 = match (encoding, value) {
+            (Encoding::Bool, VarValue::Bool(b)) => u64::from(b),
+            (Encoding::Signed, VarValue::Int(_) | VarValue::UInt(_)) => {
+                let v = match value {
+                    VarValue::UInt(v) => i64::try_from(v).map_err(|_| mismatch())?,
+                    VarValue::Int(v) => v,
+                    _ => return Err(mismatch()),
+                };
+                let shift = 64 - bits;
+                // The value must survive truncation to the variable's width.
+                if (v << shift) >> shift != v {
+                    return Err(mismatch());
+                }
+                v as u64
+            }
+            (Encoding::Unsigned, VarValue::Int(_) | VarValue::UInt(_)) => {
+                let v = match value {
+                    VarValue::Int(v) => u64::try_from(v).map_err(|_| mismatch())?,
+                    VarValue::UInt(v) => v,
+                    _ => return Err(mismatch()),
+                };
+                if bits < 64 && v >> bits != 0 {
+                    return Err(mismatch());
+                }
+                v
+            }
+            (Encoding::Float, VarValue::Real(v)) => float_bits(v, bits),
+            (Encoding::Float, VarValue::Int(v)) => float_bits(v as f64, bits),
+            (Encoding::Float, VarValue::UInt(v)) => float_bits(v as f64, bits),
+            _ => return Err(mismatch()),
+        };
+
+        let bytes = slot_mut(memory, symbol)?;
+        let len = bytes.len();
+        bytes.copy_from_slice(&raw.to_le_bytes()[..len]);
+        Ok(())
+    }
+
+    fn resolve(&self, name: &str) -> PlcResult<(&Symbol, Encoding)> {
+        let symbol = self
+            .get(name)
+            .ok_or_else(|| PlcError::Config(format!("unknown variable: {name}")))?;
+        let encoding = Encoding::of(symbol).ok_or_else(|| {
+            PlcError::Config(format!(
+                "{} : {} is not an elementary variable",
+                symbol.name, symbol.data_type
+            ))
+        })?;
+        Ok((symbol, encoding))
+    }
+}
+
+fn float_bits(value: f64, bits: u32) -> u64 {
+    if bits == 32 {
+        u64::from((value as f32).to_bits())
+    } else {
+        value.to_bits()
+    }
+}
+
+fn slot<'a>(memory: &'a [u8], symbol: &Symbol) -> PlcResult<&'a [u8]> {
+    let start = symbol.offset as usize;
+    memory
+        .get(start..start + symbol.size as usize)
+        .ok_or_else(|| out_of_bounds(symbol))
+}
+
+fn slot_mut<'a>(memory: &'a mut [u8], symbol: &Symbol) -> PlcResult<&'a mut [u8]> {
+    let start = symbol.offset as usize;
+    memory
+        .get_mut(start..start + symbol.size as usize)
+        .ok_or_else(|| out_of_bounds(symbol))
+}
+
+fn out_of_bounds(symbol: &Symbol) -> PlcError {
+    PlcError::Fault(format!(
+        "{} at offset {} lies outside linear memory",
+        symbol.name, symbol.offset
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plc_common::symbols::Direction;
+
+    fn symbol(name: &str, data_type: &str, offset: u32, size: u32) -> Symbol {
+        Symbol {
+            name: name.into(),
+            data_type: data_type.into(),
+            offset,
+            size,
+            direction: Direction::Local,
+            retain: false,
+        }
+    }
+
+    fn table() -> SymbolTable {
+        SymbolTable::new(vec![
+            symbol("Main.run", "BOOL", 0, 1),
+            symbol("Main.speed", "INT", 2, 2),
+            symbol("Main.count", "UDINT", 4, 4),
+            symbol("Main.gain", "REAL", 8, 4),
+            symbol("Main.total", "LINT", 16, 8),
+            symbol("Main.values", "ARRAY[0..1] OF INT", 24, 4),
+        ])
+    }
+
+    #[test]
+    fn test_read_write_by_name() {
+        let table = table();
+        let mut memory = vec![0u8; 32];
+
+        table
+            .write(&mut memory, "Main.speed", VarValue::Int(-300))
+            .unwrap();
+        table
+            .write(&mut memory, "main.RUN", VarValue::Bool(true))
+            .unwrap();
+        table
+            .write(&mut memory, "Main.count", VarValue::Int(70_000))
+            .unwrap();
+        table
+            .write(&mut memory, "Main.gain", VarValue::Int(2))
+            .unwrap();
+        table
+            .write(&mut memory, "Main.total", VarValue::Int(i64::MIN))
+            .unwrap();
+
+        assert_eq!(&memory[2..4], &(-300i16).to_le_bytes());
+        assert_eq!(
+            table.read(&memory, "Main.speed").unwrap(),
+            VarValue::Int(-300)
+        );
+        assert_eq!(
+            table.read(&memory, "Main.run").unwrap(),
+            VarValue::Bool(true)
+        );
+        assert_eq!(
+            table.read(&memory, "Main.count").unwrap(),
+            VarValue::UInt(70_000)
+        );
+        assert_eq!(
+            table.read(&memory, "Main.gain").unwrap(),
+            VarValue::Real(2.0)
+        );
+        assert_eq!(
+            table.read(&memory, "Main.total").unwrap(),
+            VarValue::Int(i64::MIN)
+        );
+    }
+
+    #[test]
+    fn test_rejected_accesses() {
+        let table = table();
+        let mut memory = vec![0u8; 32];
+
+        // Out of range for INT, negative for UDINT, wrong kind for BOOL.
+        assert!(table
+            .write(&mut memory, "Main.speed", VarValue::Int(40_000))
+            .is_err());
+        assert!(table
+            .write(&mut memory, "Main.count", VarValue::Int(-1))
+            .is_err());
+        assert!(table
+            .write(&mut memory, "Main.run", VarValue::Int(1))
+            .is_err());
+        assert!(table.read(&memory, "Main.values").is_err());
+        assert!(table.read(&memory, "Main.missing").is_err());
+        assert!(table.read(&memory[..8], "Main.total").is_err());
+        assert_eq!(memory, vec![0u8; 32]);
+    }
+}
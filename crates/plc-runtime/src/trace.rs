@@ -0,0 +1,959 @@
+//! Signal tracing: a scope for process image points and variables.
+//!
+//! A trace records a set of signals once every `divider` scans. It waits for
+//! its [`Trigger`] while keeping the last `pre_trigger` samples, records
+//! `post_trigger` more, then stops. The capture can be exported as CSV or as
+//! a Value Change Dump for GTKWave.
+//!
+//! Signals are addressed like forces (see [`crate::forcing`]): `%IX0.1`,
+//! `%QW2`, or a variable such as `Main.speed`. Inputs are sampled as the
+//! logic saw them, with input forces applied; outputs as the fieldbus sees
+//! them.
+//!
+//! # Design
+//!
+//! - **Pre-allocated**: the ring buffer is sized for `pre_trigger +
+//!   post_trigger + 1` samples when the trace is armed; sampling does not
+//!   allocate.
+//! - **Lock-free**: the scheduler is the only writer. A [`TraceHandle`] can
+//!   copy the samples out from any thread; every slot is stored in atomics,
+//!   and a sample overwritten while it was being copied is dropped from the
+//!   copy, as with the seqlock of the I/O image.
+
+use crate::debug::Breakpoint;
+use crate::forcing::ForceTarget;
+use crate::io_image::{IoImage, ProcessData};
+use crate::symbols::VarValue;
+use crate::wasm_host::LogicEngine;
+use plc_common::config::{ProcessImageConfig, TraceConfig};
+use plc_common::error::{PlcError, PlcResult};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{fence, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// When a trace captures.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trigger {
+    /// On the first sample after arming.
+    Immediate,
+    /// When the runtime faults; the capture ends with the last scan.
+    Fault,
+    /// When a signal goes from zero to non-zero.
+    Rising(ForceTarget),
+    /// When a signal goes from non-zero to zero.
+    Falling(ForceTarget),
+    /// When a condition on a signal starts to hold (`Main.level > 80`).
+    Threshold {
+        /// The signal the condition reads.
+        signal: ForceTarget,
+        /// The condition, in breakpoint syntax.
+        condition: Breakpoint,
+    },
+}
+
+impl Trigger {
+    /// The signal the trigger watches, if any.
+    pub fn signal(&self) -> Option<&ForceTarget> {
+        match self {
+            Trigger::Immediate | Trigger::Fault => None,
+            Trigger::Rising(signal)
+            | Trigger::Falling(signal)
+            | Trigger::Threshold { signal, .. } => Some(signal),
+        }
+    }
+
+    /// Whether the trigger condition holds for the signal's value.
+    fn level(&self, value: VarValue) -> bool {
+        match self {
+            Trigger::Threshold { condition, .. } => condition.is_hit(value),
+            _ => is_set(value),
+        }
+    }
+}
+
+fn is_set(value: VarValue) -> bool {
+    match value {
+        VarValue::Bool(b) => b,
+        VarValue::Int(v) => v != 0,
+        VarValue::UInt(v) => v != 0,
+        VarValue::Real(v) => v != 0.0,
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Immediate => f.write_str("immediate"),
+            Trigger::Fault => f.write_str("fault"),
+            Trigger::Rising(signal) => write!(f, "rising {signal}"),
+            Trigger::Falling(signal) => write!(f, "falling {signal}"),
+            Trigger::Threshold { condition, .. } => write!(f, "{condition}"),
+        }
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = PlcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.to_ascii_lowercase().as_str() {
+            "" | "immediate" => return Ok(Trigger::Immediate),
+            "fault" => return Ok(Trigger::Fault),
+            _ => {}
+        }
+        if let Some((edge, signal)) = s.split_once(char::is_whitespace) {
+            match edge.to_ascii_lowercase().as_str() {
+                "rising" => return Ok(Trigger::Rising(signal.parse()?)),
+                "falling" => return Ok(Trigger::Falling(signal.parse()?)),
+                _ => {}
+            }
+        }
+        let condition: Breakpoint = s
+            .parse()
+            .map_err(|_| PlcError::Config(format!("invalid trigger: {s}")))?;
+        Ok(Trigger::Threshold {
+            signal: condition.variable().parse()?,
+            condition,
+        })
+    }
+}
+
+/// What to record and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceSpec {
+    /// Signals to record.
+    pub signals: Vec<ForceTarget>,
+    /// When to capture.
+    pub trigger: Trigger,
+    /// Samples kept from before the trigger.
+    pub pre_trigger: usize,
+    /// Samples recorded after the trigger.
+    pub post_trigger: usize,
+    /// Record every Nth scan.
+    pub divider: u32,
+}
+
+impl TraceSpec {
+    /// Parse a trace configuration.
+    pub fn from_config(config: &TraceConfig) -> PlcResult<Self> {
+        if config.signals.is_empty() {
+            return Err(PlcError::Config("a trace needs at least one signal".into()));
+        }
+        if config.divider == 0 {
+            return Err(PlcError::Config("trace divider must be at least 1".into()));
+        }
+        Ok(Self {
+            signals: config
+                .signals
+                .iter()
+                .map(|signal| signal.parse())
+                .collect::<PlcResult<_>>()?,
+            trigger: config.trigger.parse()?,
+            pre_trigger: config.pre_trigger,
+            post_trigger: config.post_trigger,
+            divider: config.divider,
+        })
+    }
+}
+
+/// Type of a recorded signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalKind {
+    /// `BOOL` and digital points.
+    Bool,
+    /// Signed integers and analog channels.
+    Int,
+    /// Unsigned integers and digital words.
+    UInt,
+    /// `REAL` and `LREAL`.
+    Real,
+}
+
+impl SignalKind {
+    fn of(value: VarValue) -> Self {
+        match value {
+            VarValue::Bool(_) => SignalKind::Bool,
+            VarValue::Int(_) => SignalKind::Int,
+            VarValue::UInt(_) => SignalKind::UInt,
+            VarValue::Real(_) => SignalKind::Real,
+        }
+    }
+
+    fn decode(self, bits: u64) -> VarValue {
+        match self {
+            SignalKind::Bool => VarValue::Bool(bits != 0),
+            SignalKind::Int => VarValue::Int(bits as i64),
+            SignalKind::UInt => VarValue::UInt(bits),
+            SignalKind::Real => VarValue::Real(f64::from_bits(bits)),
+        }
+    }
+}
+
+fn encode(value: VarValue) -> u64 {
+    match value {
+        VarValue::Bool(b) => u64::from(b),
+        VarValue::Int(v) => v as u64,
+        VarValue::UInt(v) => v,
+        VarValue::Real(v) => v.to_bits(),
+    }
+}
+
+/// A recorded signal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceChannel {
+    /// Address or variable name.
+    pub name: String,
+    /// Value type.
+    pub kind: SignalKind,
+}
+
+/// Progress of a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceState {
+    /// Recording, waiting for the trigger.
+    Armed,
+    /// Triggered, recording the post-trigger samples.
+    Triggered,
+    /// The capture is complete.
+    Complete,
+    /// Stopped before it completed.
+    Stopped,
+}
+
+impl TraceState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => TraceState::Armed,
+            1 => TraceState::Triggered,
+            2 => TraceState::Complete,
+            _ => TraceState::Stopped,
+        }
+    }
+
+    /// Whether samples are still being recorded.
+    pub fn is_recording(self) -> bool {
+        matches!(self, TraceState::Armed | TraceState::Triggered)
+    }
+}
+
+impl fmt::Display for TraceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceState::Armed => f.write_str("ARMED"),
+            TraceState::Triggered => f.write_str("TRIGGERED"),
+            TraceState::Complete => f.write_str("COMPLETE"),
+            TraceState::Stopped => f.write_str("STOPPED"),
+        }
+    }
+}
+
+/// Marks a trace that has not triggered.
+const NO_TRIGGER: u64 = u64::MAX;
+
+/// Sample ring buffer, written by the scheduler and copied by readers.
+#[derive(Debug)]
+struct TraceBuffer {
+    channels: usize,
+    capacity: usize,
+    cycles: Box<[AtomicU64]>,
+    timestamps: Box<[AtomicU64]>,
+    /// `capacity * channels` encoded values.
+    values: Box<[AtomicU64]>,
+    /// Samples whose write has started.
+    started: AtomicU64,
+    /// Samples completely written.
+    written: AtomicU64,
+    /// Index of the trigger sample, or [`NO_TRIGGER`].
+    trigger: AtomicU64,
+    state: AtomicU8,
+}
+
+fn atomics(len: usize) -> Box<[AtomicU64]> {
+    (0..len).map(|_| AtomicU64::new(0)).collect()
+}
+
+impl TraceBuffer {
+    fn new(channels: usize, capacity: usize) -> Self {
+        Self {
+            channels,
+            capacity,
+            cycles: atomics(capacity),
+            timestamps: atomics(capacity),
+            values: atomics(capacity * channels),
+            started: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+            trigger: AtomicU64::new(NO_TRIGGER),
+            state: AtomicU8::new(TraceState::Armed as u8),
+        }
+    }
+
+    fn state(&self) -> TraceState {
+        TraceState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    fn set_state(&self, state: TraceState) {
+        self.state.store(state as u8, Ordering::Release);
+    }
+
+    /// Append a sample, returning its index. Only the tracer calls this.
+    fn push(&self, cycle: u64, timestamp_ns: u64, values: impl Iterator<Item = u64>) -> u64 {
+        let index = self.written.load(Ordering::Relaxed);
+        // Announce the overwrite before touching the slot, so readers that
+        // see any of the new data also see that the old sample is gone
+        self.started.store(index + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        let slot = (index % self.capacity as u64) as usize;
+        self.cycles[slot].store(cycle, Ordering::Relaxed);
+        self.timestamps[slot].store(timestamp_ns, Ordering::Relaxed);
+        let row = &self.values[slot * self.channels..(slot + 1) * self.channels];
+        for (target, value) in row.iter().zip(values) {
+            target.store(value, Ordering::Relaxed);
+        }
+
+        self.written.store(index + 1, Ordering::Release);
+        index
+    }
+
+    /// Copy out the retained samples with their indices, oldest first.
+    fn copy(&self) -> Vec<(u64, u64, u64, Vec<u64>)> {
+        let end = self.written.load(Ordering::Acquire);
+        let begin = end.saturating_sub(self.capacity as u64);
+        let mut samples: Vec<_> = (begin..end)
+            .map(|index| {
+                let slot = (index % self.capacity as u64) as usize;
+                let row = &self.values[slot * self.channels..(slot + 1) * self.channels];
+                (
+                    index,
+                    self.cycles[slot].load(Ordering::Relaxed),
+                    self.timestamps[slot].load(Ordering::Relaxed),
+                    row.iter().map(|v| v.load(Ordering::Relaxed)).collect(),
+                )
+            })
+            .collect();
+
+        // Drop whatever the writer started to overwrite while we copied
+        fence(Ordering::Acquire);
+        let started = self.started.load(Ordering::Relaxed);
+        let valid_from = started.saturating_sub(self.capacity as u64);
+        samples.retain(|(index, ..)| *index >= valid_from);
+        samples
+    }
+}
+
+/// The recording side of a trace, driven by the scheduler.
+#[derive(Debug)]
+pub struct Tracer {
+    spec: TraceSpec,
+    channels: Arc<[TraceChannel]>,
+    buffer: Arc<TraceBuffer>,
+    /// Scratch copies of the process image, for sampling.
+    inputs: ProcessData,
+    outputs: ProcessData,
+    armed_at: Instant,
+    /// Scans left until the next sample.
+    skip: u32,
+    /// Trigger condition at the previous sample, if it could be read.
+    last_level: Option<bool>,
+    /// Samples left to record after the trigger.
+    remaining: usize,
+}
+
+impl Tracer {
+    /// Arm a trace, checking every signal against the process image and
+    /// the logic engine's variables.
+    pub fn arm<E: LogicEngine>(
+        spec: TraceSpec,
+        image: &ProcessImageConfig,
+        engine: &E,
+    ) -> PlcResult<Self> {
+        if spec.signals.is_empty() {
+            return Err(PlcError::Config("a trace needs at least one signal".into()));
+        }
+        if spec.divider == 0 {
+            return Err(PlcError::Config("trace divider must be at least 1".into()));
+        }
+
+        let kind = |signal: &ForceTarget| -> PlcResult<SignalKind> {
+            signal.check_range(image)?;
+            Ok(match signal {
+                ForceTarget::Bit { .. } => SignalKind::Bool,
+                ForceTarget::Word { .. } => SignalKind::UInt,
+                ForceTarget::Analog { .. } => SignalKind::Int,
+                ForceTarget::Variable(name) => SignalKind::of(engine.read_variable(name)?),
+            })
+        };
+        let channels = spec
+            .signals
+            .iter()
+            .map(|signal| {
+                Ok(TraceChannel {
+                    name: signal.to_string(),
+                    kind: kind(signal)?,
+                })
+            })
+            .collect::<PlcResult<Arc<[TraceChannel]>>>()?;
+        if let Some(signal) = spec.trigger.signal() {
+            kind(signal)?;
+        }
+
+        let capacity = spec
+            .pre_trigger
+            .saturating_add(spec.post_trigger)
+            .saturating_add(1);
+        Ok(Self {
+            buffer: Arc::new(TraceBuffer::new(channels.len(), capacity)),
+            channels,
+            inputs: ProcessData::new(image),
+            outputs: ProcessData::new(image),
+            armed_at: Instant::now(),
+            skip: 0,
+            last_level: None,
+            remaining: spec.post_trigger,
+            spec,
+        })
+    }
+
+    /// A handle for reading the trace.
+    pub fn handle(&self) -> TraceHandle {
+        TraceHandle {
+            buffer: Arc::clone(&self.buffer),
+            channels: Arc::clone(&self.channels),
+            trigger: self.spec.trigger.clone(),
+            pre_trigger: self.spec.pre_trigger,
+        }
+    }
+
+    /// Current progress.
+    pub fn state(&self) -> TraceState {
+        self.buffer.state()
+    }
+
+    /// Record a scan, if it is due, and evaluate the trigger.
+    ///
+    /// `apply_forces` applies the input forces to the sampled inputs.
+    pub(crate) fn sample<E: LogicEngine>(
+        &mut self,
+        cycle: u64,
+        io: &IoImage,
+        apply_forces: impl FnOnce(&mut ProcessData),
+        engine: &E,
+    ) {
+        let state = self.buffer.state();
+        if !state.is_recording() {
+            return;
+        }
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+        self.skip = self.spec.divider - 1;
+
+        io.read_inputs_into(&mut self.inputs);
+        apply_forces(&mut self.inputs);
+        io.read_outputs_into(&mut self.outputs);
+
+        let (inputs, outputs) = (&self.inputs, &self.outputs);
+        let read = |signal: &ForceTarget| match signal {
+            ForceTarget::Variable(name) => engine.read_variable(name).ok(),
+            _ => signal.read(inputs, outputs),
+        };
+        let timestamp_ns = self.armed_at.elapsed().as_nanos() as u64;
+        let index = self.buffer.push(
+            cycle,
+            timestamp_ns,
+            self.spec
+                .signals
+                .iter()
+                .map(|signal| read(signal).map_or(0, encode)),
+        );
+
+        if state == TraceState::Triggered {
+            self.remaining -= 1;
+            if self.remaining == 0 {
+                self.buffer.set_state(TraceState::Complete);
+            }
+            return;
+        }
+
+        let level = self
+            .spec
+            .trigger
+            .signal()
+            .and_then(read)
+            .map(|value| self.spec.trigger.level(value));
+        let fired = match self.spec.trigger {
+            Trigger::Immediate => true,
+            Trigger::Fault => false,
+            Trigger::Falling(_) => self.last_level == Some(true) && level == Some(false),
+            Trigger::Rising(_) | Trigger::Threshold { .. } => {
+                self.last_level == Some(false) && level == Some(true)
+            }
+        };
+        self.last_level = level;
+        if fired {
+            self.buffer.trigger.store(index, Ordering::Release);
+            self.buffer.set_state(if self.remaining == 0 {
+                TraceState::Complete
+            } else {
+                TraceState::Triggered
+            });
+        }
+    }
+
+    /// The runtime faulted: end the capture at the last recorded scan.
+    pub(crate) fn fault(&mut self) {
+        if !self.buffer.state().is_recording() {
+            return;
+        }
+        let written = self.buffer.written.load(Ordering::Relaxed);
+        if self.buffer.trigger.load(Ordering::Relaxed) == NO_TRIGGER && written > 0 {
+            self.buffer.trigger.store(written - 1, Ordering::Release);
+        }
+        self.buffer.set_state(TraceState::Complete);
+    }
+
+    /// Stop recording; what was recorded stays readable.
+    pub fn stop(&mut self) {
+        if self.buffer.state().is_recording() {
+            self.buffer.set_state(TraceState::Stopped);
+        }
+    }
+}
+
+/// Read access to a trace, from any thread.
+#[derive(Debug, Clone)]
+pub struct TraceHandle {
+    buffer: Arc<TraceBuffer>,
+    channels: Arc<[TraceChannel]>,
+    trigger: Trigger,
+    pre_trigger: usize,
+}
+
+impl TraceHandle {
+    /// Current progress.
+    pub fn state(&self) -> TraceState {
+        self.buffer.state()
+    }
+
+    /// The recorded signals.
+    pub fn channels(&self) -> &[TraceChannel] {
+        &self.channels
+    }
+
+    /// The trigger condition.
+    pub fn trigger(&self) -> &Trigger {
+        &self.trigger
+    }
+
+    /// Whether both handles read the same trace.
+    pub fn is_same(&self, other: &TraceHandle) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer)
+    }
+
+    /// Copy out the samples recorded so far.
+    ///
+    /// Once triggered, only `pre_trigger` samples before the trigger are
+    /// kept.
+    pub fn capture(&self) -> TraceCapture {
+        let state = self.buffer.state();
+        let trigger = self.buffer.trigger.load(Ordering::Acquire);
+        let trigger = (trigger != NO_TRIGGER).then_some(trigger);
+        let first = trigger.map_or(0, |t| t.saturating_sub(self.pre_trigger as u64));
+
+        let copied: Vec<_> = self
+            .buffer
+            .copy()
+            .into_iter()
+            .filter(|(index, ..)| *index >= first)
+            .collect();
+        let trigger = trigger.and_then(|t| copied.iter().position(|(index, ..)| *index == t));
+        let samples = copied
+            .into_iter()
+            .map(|(_, cycle, timestamp_ns, values)| TraceSample {
+                cycle,
+                timestamp_ns,
+                values: self
+                    .channels
+                    .iter()
+                    .zip(values)
+                    .map(|(channel, bits)| channel.kind.decode(bits))
+                    .collect(),
+            })
+            .collect();
+
+        TraceCapture {
+            channels: self.channels.to_vec(),
+            trigger_condition: self.trigger.to_string(),
+            state,
+            trigger,
+            samples,
+        }
+    }
+}
+
+/// One recorded scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceSample {
+    /// Scan number.
+    pub cycle: u64,
+    /// Time since the trace was armed.
+    pub timestamp_ns: u64,
+    /// Value of each channel.
+    pub values: Vec<VarValue>,
+}
+
+/// A copy of a trace, for export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceCapture {
+    /// The recorded signals.
+    pub channels: Vec<TraceChannel>,
+    /// The trigger condition, as text.
+    pub trigger_condition: String,
+    /// Progress when the copy was taken.
+    pub state: TraceState,
+    /// Index of the trigger sample in `samples`.
+    pub trigger: Option<usize>,
+    /// Samples, oldest first.
+    pub samples: Vec<TraceSample>,
+}
+
+impl TraceCapture {
+    /// The trigger sample, if the trace triggered.
+    pub fn trigger_sample(&self) -> Option<&TraceSample> {
+        self.trigger.map(|t| &self.samples[t])
+    }
+
+    /// Time origin: the trigger sample, or the first sample.
+    fn origin_ns(&self) -> u64 {
+        self.trigger_sample()
+            .or(self.samples.first())
+            .map_or(0, |sample| sample.timestamp_ns)
+    }
+
+    /// Write the capture as CSV: `cycle`, `time_ns` relative to the trigger
+    /// (negative before it), then one column per signal. `BOOL`s are
+    /// written as 0 and 1.
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        write!(out, "cycle,time_ns")?;
+        for channel in &self.channels {
+            write!(out, ",{}", channel.name)?;
+        }
+        writeln!(out)?;
+
+        let origin = i128::from(self.origin_ns());
+        for sample in &self.samples {
+            write!(
+                out,
+                "{},{}",
+                sample.cycle,
+                i128::from(sample.timestamp_ns) - origin
+            )?;
+            for value in &sample.values {
+                match value {
+                    VarValue::Bool(b) => write!(out, ",{}", u8::from(*b))?,
+                    value => write!(out, ",{value}")?,
+                }
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Write the capture as a Value Change Dump (IEEE 1364), with a 1 ns
+    /// timescale starting at the first sample.
+    pub fn write_vcd(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "$version virtual-plc trace $end")?;
+        if let Some(trigger) = self.trigger_sample() {
+            writeln!(
+                out,
+                "$comment trigger {} at cycle {} $end",
+                self.trigger_condition, trigger.cycle
+            )?;
+        }
+        writeln!(out, "$timescale 1 ns $end")?;
+        writeln!(out, "$scope module plc $end")?;
+        let ids: Vec<String> = (0..self.channels.len()).map(vcd_identifier).collect();
+        for (channel, id) in self.channels.iter().zip(&ids) {
+            let (kind, width) = match channel.kind {
+                SignalKind::Bool => ("wire", 1),
+                SignalKind::Int | SignalKind::UInt => ("integer", 64),
+                SignalKind::Real => ("real", 64),
+            };
+            writeln!(out, "$var {kind} {width} {id} {} $end", channel.name)?;
+        }
+        writeln!(out, "$upscope $end")?;
+        writeln!(out, "$enddefinitions $end")?;
+
+        let start = self.samples.first().map_or(0, |sample| sample.timestamp_ns);
+        let mut previous: Option<&[VarValue]> = None;
+        for sample in &self.samples {
+            writeln!(out, "#{}", sample.timestamp_ns.saturating_sub(start))?;
+            if previous.is_none() {
+                writeln!(out, "$dumpvars")?;
+            }
+            for (i, (value, id)) in sample.values.iter().zip(&ids).enumerate() {
+                if previous.is_some_and(|previous| previous[i] == *value) {
+                    continue;
+                }
+                match *value {
+                    VarValue::Bool(b) => writeln!(out, "{}{id}", u8::from(b))?,
+                    VarValue::Int(v) => writeln!(out, "b{:b} {id}", v as u64)?,
+                    VarValue::UInt(v) => writeln!(out, "b{v:b} {id}")?,
+                    VarValue::Real(v) => writeln!(out, "r{v} {id}")?,
+                }
+            }
+            if previous.is_none() {
+                writeln!(out, "$end")?;
+            }
+            previous = Some(&sample.values);
+        }
+        Ok(())
+    }
+}
+
+/// Short VCD identifier for a channel: printable ASCII from `!`, in base 94.
+fn vcd_identifier(mut index: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push(char::from(b'!' + (index % 94) as u8));
+        index /= 94;
+        if index == 0 {
+            return id;
+        }
+        index -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm_host::NullEngine;
+
+    fn spec(signals: &[&str], trigger: &str, pre: usize, post: usize) -> TraceSpec {
+        TraceSpec::from_config(&TraceConfig {
+            signals: signals.iter().map(|s| s.to_string()).collect(),
+            trigger: trigger.into(),
+            pre_trigger: pre,
+            post_trigger: post,
+            ..TraceConfig::default()
+        })
+        .unwrap()
+    }
+
+    /// Run scans with digital input word 0 taking the given values.
+    fn run(tracer: &mut Tracer, io: &IoImage, inputs: impl IntoIterator<Item = u32>) {
+        for (cycle, word) in inputs.into_iter().enumerate() {
+            io.write_inputs(|data| data.digital_inputs_mut()[0] = word);
+            tracer.sample(cycle as u64 + 1, io, |_| {}, &NullEngine::default());
+        }
+    }
+
+    #[test]
+    fn test_parse_triggers() {
+        assert_eq!("".parse::<Trigger>().unwrap(), Trigger::Immediate);
+        assert_eq!("FAULT".parse::<Trigger>().unwrap(), Trigger::Fault);
+        assert_eq!(
+            "rising %IX0.1".parse::<Trigger>().unwrap(),
+            Trigger::Rising(ForceTarget::Bit {
+                area: crate::forcing::Area::Input,
+                point: 1
+            })
+        );
+        let threshold: Trigger = "Main.level > 80".parse().unwrap();
+        assert_eq!(
+            threshold.signal(),
+            Some(&ForceTarget::Variable("Main.level".into()))
+        );
+        assert_eq!(threshold.to_string(), "Main.level > 80");
+        assert!("Main.level > high".parse::<Trigger>().is_err());
+    }
+
+    #[test]
+    fn test_arm_checks_signals() {
+        let image = ProcessImageConfig::default();
+        let engine = NullEngine::default();
+        assert!(Tracer::arm(spec(&["%IX0.0"], "immediate", 1, 1), &image, &engine).is_ok());
+        assert!(Tracer::arm(spec(&["%IW99"], "immediate", 1, 1), &image, &engine).is_err());
+        assert!(Tracer::arm(spec(&["%IX0.0"], "rising %QX9.0", 1, 1), &image, &engine).is_err());
+        // The null engine has no variables
+        assert!(Tracer::arm(spec(&["Main.x"], "immediate", 1, 1), &image, &engine).is_err());
+    }
+
+    #[test]
+    fn test_rising_edge_with_pre_and_post_trigger() {
+        let io = IoImage::new();
+        let mut tracer = Tracer::arm(
+            spec(&["%IX0.0", "%ID0"], "rising %IX0.0", 2, 2),
+            io.config(),
+            &NullEngine::default(),
+        )
+        .unwrap();
+        let handle = tracer.handle();
+
+        // High from the start is not an edge
+        run(&mut tracer, &io, [1, 0, 0, 2, 0]);
+        assert_eq!(handle.state(), TraceState::Armed);
+        run(&mut tracer, &io, [1, 3]);
+        assert_eq!(handle.state(), TraceState::Triggered);
+        run(&mut tracer, &io, [0, 0, 1]);
+        assert_eq!(handle.state(), TraceState::Complete);
+
+        let capture = handle.capture();
+        let words: Vec<_> = capture.samples.iter().map(|s| s.values[1]).collect();
+        assert_eq!(
+            words,
+            [2, 0, 1, 3, 0].map(VarValue::UInt).to_vec(),
+            "two before the trigger, the trigger, two after"
+        );
+        assert_eq!(capture.trigger, Some(2));
+        assert_eq!(capture.samples[2].values[0], VarValue::Bool(true));
+    }
+
+    #[test]
+    fn test_divider_and_threshold() {
+        let io = IoImage::new();
+        let mut spec = spec(&["%ID0"], "%ID0 >= 10", 1, 0);
+        spec.divider = 2;
+        let mut tracer = Tracer::arm(spec, io.config(), &NullEngine::default()).unwrap();
+
+        // Every other scan is sampled: 1, 5, then 12 crosses the threshold
+        run(&mut tracer, &io, [1, 20, 5, 30, 12]);
+        let capture = tracer.handle().capture();
+        assert_eq!(tracer.state(), TraceState::Complete);
+        let cycles: Vec<_> = capture.samples.iter().map(|s| s.cycle).collect();
+        assert_eq!(cycles, [3, 5]);
+        assert_eq!(capture.trigger, Some(1));
+    }
+
+    #[test]
+    fn test_fault_trigger_keeps_history() {
+        let io = IoImage::new();
+        let mut tracer = Tracer::arm(
+            spec(&["%ID0"], "fault", 3, 10),
+            io.config(),
+            &NullEngine::default(),
+        )
+        .unwrap();
+        run(&mut tracer, &io, 1..=20);
+        assert_eq!(tracer.state(), TraceState::Armed);
+        tracer.fault();
+
+        let capture = tracer.handle().capture();
+        assert_eq!(capture.state, TraceState::Complete);
+        let cycles: Vec<_> = capture.samples.iter().map(|s| s.cycle).collect();
+        assert_eq!(cycles, [17, 18, 19, 20]);
+        assert_eq!(capture.trigger_sample().map(|s| s.cycle), Some(20));
+    }
+
+    #[test]
+    fn test_stop_keeps_samples() {
+        let io = IoImage::new();
+        let mut tracer = Tracer::arm(
+            spec(&["%ID0"], "rising %IX0.5", 4, 4),
+            io.config(),
+            &NullEngine::default(),
+        )
+        .unwrap();
+        run(&mut tracer, &io, [1, 2, 3]);
+        tracer.stop();
+        run(&mut tracer, &io, [4]);
+        let capture = tracer.handle().capture();
+        assert_eq!(capture.state, TraceState::Stopped);
+        assert_eq!(capture.samples.len(), 3);
+        assert_eq!(capture.trigger, None);
+    }
+
+    #[test]
+    fn test_csv_and_vcd_export() {
+        let channel = |name: &str, kind| TraceChannel {
+            name: name.into(),
+            kind,
+        };
+        let sample = |cycle, timestamp_ns, values: Vec<VarValue>| TraceSample {
+            cycle,
+            timestamp_ns,
+            values,
+        };
+        let capture = TraceCapture {
+            channels: vec![
+                channel("%IX0.0", SignalKind::Bool),
+                channel("%IW0", SignalKind::Int),
+                channel("Main.level", SignalKind::Real),
+            ],
+            trigger_condition: "rising %IX0.0".into(),
+            state: TraceState::Complete,
+            trigger: Some(1),
+            samples: vec![
+                sample(
+                    7,
+                    1_000,
+                    vec![
+                        VarValue::Bool(false),
+                        VarValue::Int(-2),
+                        VarValue::Real(0.5),
+                    ],
+                ),
+                sample(
+                    8,
+                    2_000,
+                    vec![VarValue::Bool(true), VarValue::Int(-2), VarValue::Real(1.5)],
+                ),
+            ],
+        };
+
+        let mut csv = Vec::new();
+        capture.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "cycle,time_ns,%IX0.0,%IW0,Main.level\n7,-1000,0,-2,0.5\n8,0,1,-2,1.5\n"
+        );
+
+        let mut vcd = Vec::new();
+        capture.write_vcd(&mut vcd).unwrap();
+        let vcd = String::from_utf8(vcd).unwrap();
+        assert!(vcd.contains("$comment trigger rising %IX0.0 at cycle 8 $end"));
+        assert!(vcd.contains("$var wire 1 ! %IX0.0 $end"));
+        assert!(vcd.contains("$var integer 64 \" %IW0 $end"));
+        assert!(vcd.contains("$var real 64 # Main.level $end"));
+        assert!(vcd.contains(&format!(
+            "#0\n$dumpvars\n0!\nb{:b} \"\nr0.5 #\n$end\n",
+            -2i64 as u64
+        )));
+        // Unchanged values are not repeated
+        assert!(vcd.ends_with("#1000\n1!\nr1.5 #\n"));
+    }
+
+    #[test]
+    fn test_vcd_identifiers_are_unique() {
+        let ids: std::collections::HashSet<_> = (0..10_000).map(vcd_identifier).collect();
+        assert_eq!(ids.len(), 10_000);
+        assert_eq!(vcd_identifier(0), "!");
+        assert_eq!(vcd_identifier(93), "~");
+    }
+
+    #[test]
+    fn test_concurrent_reader_sees_consistent_samples() {
+        let buffer = Arc::new(TraceBuffer::new(2, 8));
+        let reader = {
+            let buffer = Arc::clone(&buffer);
+            std::thread::spawn(move || {
+                for _ in 0..2_000 {
+                    for (index, cycle, timestamp, values) in buffer.copy() {
+                        assert_eq!(cycle, index);
+                        assert_eq!(timestamp, index * 10);
+                        assert_eq!(values, [index, index + 1]);
+                    }
+                }
+            })
+        };
+        for i in 0..50_000u64 {
+            buffer.push(i, i * 10, [i, i + 1].into_iter());
+        }
+        reader.join().unwrap();
+    }
+}
@@ -23,12 +23,24 @@
 //! ```
 
 use crate::io_image::ProcessData;
+use crate::online_change::MigrationPlan;
+use crate::precompiled::{self, engine_fingerprint, Artifact};
+use crate::signing::TrustedKeys;
+use crate::symbols::{SymbolTable, VarValue};
 use crate::wasm_imports::{register_host_functions, HostState};
 use crate::wasm_memory::{
-    copy_inputs_to_wasm, copy_outputs_from_wasm, write_system_info, WasmSystemInfo,
+    copy_inputs_to_wasm, copy_outputs_from_wasm, write_system_info_for, WasmSystemInfo,
 };
 use anyhow::{anyhow, Context, Result};
+use plc_common::abi::{self, ProcessImageLayout};
+use plc_common::build_cache::{self, ArtifactStore, CacheKey};
+use plc_common::config::ProcessImageConfig;
+use plc_common::debug_map::{self, DebugMap, SourceLocation};
 use plc_common::error::{PlcError, PlcResult};
+use plc_common::sfc::{self, StepFlag};
+use plc_common::wasm_meta;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
@@ -36,12 +48,15 @@ use std::time::Duration;
 use tracing::{debug, info, trace, warn};
 use wasmtime::{
     Config, Engine, Instance, Linker, Memory, Module, OptLevel, Store, Trap, TypedFunc,
+    WasmBacktrace,
 };
 
 /// Logic engine trait for swappable Wasm runtimes.
 ///
 /// This trait allows the runtime to work with different Wasm engines
-/// (Wasmtime, WAMR, wasm3, etc.) through a common interface.
+/// (Wasmtime, WAMR, wasm3, etc.) through a common interface. The runtime
+/// ships [`WasmtimeHost`] and the interpreter-based
+/// [`InterpreterHost`](crate::wasm_interp::InterpreterHost).
 pub trait LogicEngine: Send {
     /// Initialize the logic engine.
     ///
@@ -53,7 +68,22 @@ pub trait LogicEngine: Send {
     ///
     /// Copies inputs to Wasm memory, calls the step function,
     /// then copies outputs back. Must complete within cycle time.
-    fn step(&mut self, inputs: &ProcessData) -> PlcResult<ProcessData>;
+    /// The outputs are returned by reference to a buffer owned by the
+    /// engine, so a cycle does not allocate.
+    fn step(&mut self, inputs: &ProcessData) -> PlcResult<&ProcessData>;
+
+    /// Execute one cycle of a task: like [`step`](Self::step), but calling
+    /// the exported function `entry`.
+    ///
+    /// The default implementation only supports the `step` entry.
+    fn step_entry(&mut self, entry: &str, inputs: &ProcessData) -> PlcResult<&ProcessData> {
+        if entry == "step" {
+            return self.step(inputs);
+        }
+        Err(PlcError::Config(format!(
+            "Entry point '{entry}' not supported by this engine"
+        )))
+    }
 
     /// Handle a fault condition.
     ///
@@ -73,7 +103,8 @@ pub trait LogicEngine: Send {
     ///
     /// The implementation may attempt to migrate state from the old module to
     /// the new one. The `preserve_memory` flag controls this behavior:
-    /// - `true`: Copy linear memory from old to new module (if sizes are compatible)
+    /// - `true`: Carry variables over by name (see [`MigrationPlan`]), or copy
+    ///   linear memory byte for byte if a module has no symbol table
     /// - `false`: Start with fresh memory (new module's initialization)
     ///
     /// # Errors
@@ -93,6 +124,18 @@ pub trait LogicEngine: Send {
         ))
     }
 
+    /// Check a replacement module and plan the state migration that
+    /// `reload_module(wasm_bytes, true)` would perform, without swapping
+    /// (a dry run).
+    ///
+    /// The default implementation returns `Err(PlcError::Config)` indicating
+    /// hot-reload is not supported.
+    fn plan_reload(&self, _wasm_bytes: &[u8]) -> PlcResult<MigrationPlan> {
+        Err(PlcError::Config(
+            "Hot-reload not supported by this engine".into(),
+        ))
+    }
+
     /// Check if the engine supports hot-reload.
     ///
     /// Returns `true` if `reload_module` is implemented and can be called.
@@ -117,6 +160,79 @@ pub trait LogicEngine: Send {
     fn exports(&self) -> Vec<String> {
         Vec::new()
     }
+
+    /// List the SFC steps that are currently active.
+    ///
+    /// Names are qualified with their POU (`Main.Fill`). Returns an empty vec
+    /// if the module has no SFC or the engine does not track steps.
+    fn active_steps(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Source location of the most recent trap in `step()`.
+    ///
+    /// Returns `None` if no trap occurred, the module carries no debug line
+    /// map, or the engine cannot resolve trap locations.
+    fn trap_location(&self) -> Option<SourceLocation> {
+        None
+    }
+
+    /// Symbol table of the loaded module, if the engine exposes one.
+    fn symbols(&self) -> Option<&SymbolTable> {
+        None
+    }
+
+    /// Read a variable by qualified name (`Main.speed`).
+    ///
+    /// The default implementation returns `Err(PlcError::Config)` indicating
+    /// variable access is not supported.
+    fn read_variable(&self, name: &str) -> PlcResult<VarValue> {
+        Err(PlcError::Config(format!(
+            "Cannot read {name}: variable access not supported by this engine"
+        )))
+    }
+
+    /// Write a variable by qualified name.
+    ///
+    /// Should be called at cycle boundaries; the program sees the new value
+    /// in the next `step()`.
+    fn write_variable(&mut self, name: &str, _value: VarValue) -> PlcResult<()> {
+        Err(PlcError::Config(format!(
+            "Cannot write {name}: variable access not supported by this engine"
+        )))
+    }
+
+    /// System info the next `step()` will present to the logic.
+    ///
+    /// Used to record cycles for replay. Returns `None` if the engine
+    /// presents none.
+    fn system_info(&self) -> Option<WasmSystemInfo> {
+        None
+    }
+
+    /// Copy of the logic's linear memory, the checkpoint a replay starts
+    /// from. Returns `None` if the engine has no memory to copy.
+    fn memory_snapshot(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// The logic's linear memory, read in place. Returns `None` if the
+    /// engine has no memory.
+    fn memory(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Replace the logic's linear memory and the cycle count the next step
+    /// presents, so it continues from state taken on another engine, as
+    /// when a hot standby takes over from its primary.
+    ///
+    /// The default implementation returns `Err(PlcError::Config)` indicating
+    /// state transfer is not supported.
+    fn restore_state(&mut self, _memory: &[u8], _cycle_count: u64) -> PlcResult<()> {
+        Err(PlcError::Config(
+            "State transfer not supported by this engine".into(),
+        ))
+    }
 }
 
 /// Handle for an epoch ticker thread.
@@ -192,6 +308,10 @@ pub struct WasmtimeHost {
     memory: Option<Memory>,
     /// Cached step function.
     step_fn: Option<TypedFunc<(), ()>>,
+    /// Task entry points besides `step` the module must export.
+    entry_points: Vec<String>,
+    /// Cached task entry functions, by export name.
+    entry_fns: HashMap<String, TypedFunc<(), ()>>,
     /// Cached init function (optional).
     init_fn: Option<TypedFunc<(), ()>>,
     /// Cached fault function (optional).
@@ -204,14 +324,36 @@ pub struct WasmtimeHost {
     cycle_time_ns: u64,
     /// Whether the engine has been initialized.
     initialized: bool,
-    /// Local copy of process data for step().
+    /// Outputs of the last step(), sized like the host's process image.
     process_data: ProcessData,
+    /// Process-image layout of the host, from the configured dimensions.
+    host_layout: ProcessImageLayout,
     /// Whether fuel-based execution budgeting is enabled.
     use_fuel: bool,
     /// Fuel units to grant per cycle.
     fuel_per_cycle: u64,
+    /// SFC step flags from the module's step table.
+    sfc_steps: Vec<StepFlag>,
+    /// Source line map from the module's debug section.
+    debug_map: DebugMap,
+    /// Where the last trap in `step()` happened, if known.
+    trap_location: Option<SourceLocation>,
+    /// Variables described by the module's symbol table.
+    symbols: SymbolTable,
+    /// Process-image layout declared by the module, if any.
+    layout: Option<ProcessImageLayout>,
+    /// Store for precompiled modules.
+    artifacts: Option<ArtifactStore>,
+    /// Whether the current module's native code was precompiled, by an
+    /// artifact or the store.
+    precompiled: bool,
+    /// Keys a module must be signed by; empty to accept any module.
+    trusted_keys: TrustedKeys,
 }
 
+/// Size of a Wasm memory page.
+pub(crate) const WASM_PAGE_SIZE: usize = 64 * 1024;
+
 impl std::fmt::Debug for WasmtimeHost {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("WasmtimeHost")
@@ -232,19 +374,9 @@ impl WasmtimeHost {
     ///
     /// Reads Wasm limits from the config's `wasm` section instead of using defaults.
     pub fn from_runtime_config(config: &plc_common::config::RuntimeConfig) -> Result<Self> {
-        let wasm_config = WasmtimeConfig {
-            opt_level: OptLevel::Speed,
-            max_memory_bytes: config.wasm.max_memory_bytes,
-            max_table_elements: config.wasm.max_table_elements,
-            enable_simd: config.wasm.enable_simd,
-            deterministic: config.wasm.deterministic,
-            use_fuel: config.wasm.use_fuel,
-            fuel_per_cycle: config.wasm.fuel_per_cycle,
-        };
-
         Self::with_config_and_epochs(
             config.cycle_time,
-            wasm_config,
+            WasmtimeConfig::from_runtime_config(config),
             config.wasm.max_epochs_per_cycle,
         )
     }
@@ -263,47 +395,20 @@ impl WasmtimeHost {
         wasm_config: WasmtimeConfig,
         max_epochs_per_cycle: u64,
     ) -> Result<Self> {
-        // Configure Wasmtime for real-time PLC execution
-        let mut config = Config::new();
-
-        // Enable epoch interruption for timeout control
-        config.epoch_interruption(true);
-
-        // Set optimization level
-        config.cranelift_opt_level(wasm_config.opt_level);
-
-        // Configure Wasm features for PLC use
-        config.wasm_threads(false);
-        config.wasm_simd(wasm_config.enable_simd);
-        // Relaxed SIMD requires SIMD to be enabled, but must be disabled in
-        // deterministic mode as it allows implementation-specific behavior
-        config.wasm_relaxed_simd(wasm_config.enable_simd && !wasm_config.deterministic);
-
-        // Apply deterministic mode settings if enabled
-        if wasm_config.deterministic {
-            // Disable features that can introduce non-determinism:
-            // - Reference types (externref/funcref) can have implementation-specific behavior
-            config.wasm_reference_types(false);
-            // - Bulk memory ops may have platform-specific edge cases
-            config.wasm_bulk_memory(false);
-            // - Multi-value returns add complexity
-            config.wasm_multi_value(false);
-            // - Function references can introduce non-determinism
-            config.wasm_function_references(false);
-            // - Tail calls can have stack behavior differences
-            config.wasm_tail_call(false);
-
-            debug!("Deterministic mode enabled: restricted Wasm feature set");
-        }
-
-        // Enable fuel-based execution budgeting if configured
-        if wasm_config.use_fuel {
-            config.consume_fuel(true);
-            debug!(
-                fuel_per_cycle = wasm_config.fuel_per_cycle,
-                "Fuel-based execution budgeting enabled"
-            );
-        }
+        let config = engine_config(&wasm_config);
+        let trusted_keys = TrustedKeys::parse(&wasm_config.trusted_keys)?;
+        // Cached native code carries no signature, so with signatures
+        // required only the code of a signed module or artifact may run
+        let artifacts = match wasm_config.cache_dir {
+            Some(dir) if !trusted_keys.is_empty() => {
+                info!(
+                    cache_dir = %dir.display(),
+                    "Trusted keys are configured, precompiled modules are not cached"
+                );
+                None
+            }
+            dir => dir.map(ArtifactStore::new),
+        };
 
         // Create engine
         let engine = Engine::new(&config).context("Failed to create Wasmtime engine")?;
@@ -337,6 +442,7 @@ impl WasmtimeHost {
             deterministic = wasm_config.deterministic,
             use_fuel = wasm_config.use_fuel,
             fuel_per_cycle = wasm_config.fuel_per_cycle,
+            signatures_required = !trusted_keys.is_empty(),
             "WasmtimeHost created"
         );
 
@@ -348,22 +454,40 @@ impl WasmtimeHost {
             instance: None,
             memory: None,
             step_fn: None,
+            entry_points: wasm_config.entry_points,
+            entry_fns: HashMap::new(),
             init_fn: None,
             fault_fn: None,
             epoch_counter: Arc::new(AtomicU64::new(0)),
             max_epochs_per_cycle,
             cycle_time_ns,
             initialized: false,
-            process_data: ProcessData::default(),
+            process_data: ProcessData::new(&wasm_config.process_image),
+            host_layout: ProcessImageLayout::for_config(&wasm_config.process_image),
             use_fuel: wasm_config.use_fuel,
             fuel_per_cycle: wasm_config.fuel_per_cycle,
+            sfc_steps: Vec::new(),
+            debug_map: DebugMap::default(),
+            trap_location: None,
+            symbols: SymbolTable::default(),
+            layout: None,
+            artifacts,
+            precompiled: false,
+            trusted_keys,
         })
     }
 
-    /// Load a Wasm module from bytes.
+    /// Load a Wasm module, or a precompiled artifact, from bytes.
+    ///
+    /// With trusted keys configured, modules without a valid signature by
+    /// one of them are refused.
     pub fn load_module(&mut self, wasm_bytes: &[u8]) -> Result<()> {
-        let module =
-            Module::new(&self.engine, wasm_bytes).context("Failed to compile Wasm module")?;
+        self.trusted_keys.check(wasm_bytes)?;
+        let (module, wasm_bytes, precompiled) = self
+            .prepare_module(wasm_bytes)
+            .context("Failed to compile Wasm module")?;
+        let layout = read_layout(wasm_bytes, &self.host_layout)?;
+        let symbols = SymbolTable::from_wasm(wasm_bytes)?;
 
         info!(
             exports = ?module.exports().map(|e| e.name()).collect::<Vec<_>>(),
@@ -371,9 +495,17 @@ impl WasmtimeHost {
         );
 
         self.module = Some(module);
+        self.precompiled = precompiled;
+        self.sfc_steps = read_step_table(wasm_bytes);
+        self.debug_map = read_debug_map(wasm_bytes);
+        self.trap_location = None;
+        self.symbols = symbols;
+        self.layout = layout;
+        self.store.data_mut().layout = self.process_image_layout();
         self.instance = None;
         self.memory = None;
         self.step_fn = None;
+        self.entry_fns.clear();
         self.init_fn = None;
         self.fault_fn = None;
         self.initialized = false;
@@ -381,12 +513,180 @@ impl WasmtimeHost {
         Ok(())
     }
 
+    /// Get native code for `bytes`, a Wasm module or a precompiled artifact.
+    /// Returns the module, the Wasm bytes it was built from and whether its
+    /// code was precompiled.
+    ///
+    /// An artifact's code is used only if it passes its integrity check and
+    /// was built for this engine's target and configuration; code built for
+    /// another engine is ignored with a warning and the embedded Wasm
+    /// module is compiled instead. A corrupt artifact is refused.
+    fn prepare_module<'a>(&self, bytes: &'a [u8]) -> Result<(Module, &'a [u8], bool)> {
+        let Some(artifact) = Artifact::parse(bytes)? else {
+            let (module, precompiled) = self.compile_module(bytes)?;
+            return Ok((module, bytes, precompiled));
+        };
+
+        let fingerprint = engine_fingerprint(&self.engine);
+        if artifact.engine_fingerprint != fingerprint {
+            warn!(
+                artifact_target = artifact.target,
+                host = %precompiled::host_target(),
+                "Precompiled artifact was built for another target or engine configuration; \
+                 compiling its Wasm module instead"
+            );
+        } else {
+            // SAFETY: the artifact passed its digest check and was produced by
+            // `Engine::precompile_module` for an engine with this fingerprint;
+            // Wasmtime checks the version and configuration again and rejects
+            // a mismatch.
+            match unsafe { Module::deserialize(&self.engine, artifact.code) } {
+                Ok(module) => {
+                    debug!(
+                        artifact_target = artifact.target,
+                        "Using precompiled artifact"
+                    );
+                    return Ok((module, artifact.wasm, true));
+                }
+                Err(e) => warn!(
+                    error = %e,
+                    "Precompiled artifact is unusable; compiling its Wasm module instead"
+                ),
+            }
+        }
+        let (module, precompiled) = self.compile_module(artifact.wasm)?;
+        Ok((module, artifact.wasm, precompiled))
+    }
+
+    /// Compile a module with Cranelift, or reuse its precompiled code from
+    /// the artifact store. Returns whether the code came from the store.
+    ///
+    /// Artifacts are keyed by the Wasm bytes and the engine configuration,
+    /// so a changed module or a changed `[wasm]` section compiles afresh.
+    /// There is no store when trusted keys are configured.
+    fn compile_module(&self, wasm_bytes: &[u8]) -> Result<(Module, bool)> {
+        let Some(store) = &self.artifacts else {
+            return Ok((Module::new(&self.engine, wasm_bytes)?, false));
+        };
+
+        let mut key = CacheKey::new(build_cache::PRECOMPILED);
+        key.add(wasm_bytes).add(engine_fingerprint(&self.engine));
+        let key = key.finish();
+
+        if let Some(code) = store.get(build_cache::PRECOMPILED, &key) {
+            // SAFETY: this runs whatever native code the entry holds. Its
+            // digest proves it is intact, not who wrote it, so the entry is
+            // trusted like the daemon's own files: only its owner can write
+            // the cache directory. That is why there is no store when
+            // signatures are required. Wasmtime checks the version and
+            // configuration again and rejects a mismatch.
+            match unsafe { Module::deserialize(&self.engine, &code) } {
+                Ok(module) => {
+                    debug!(%key, "Reusing precompiled module");
+                    return Ok((module, true));
+                }
+                Err(e) => warn!(error = %e, %key, "Ignoring unusable precompiled module"),
+            }
+        }
+
+        let module = Module::new(&self.engine, wasm_bytes)?;
+        // The cache is an optimisation; failing to fill it must not fail the load.
+        let stored = module
+            .serialize()
+            .map_err(|e| PlcError::IoError(e.to_string()))
+            .and_then(|code| store.put(build_cache::PRECOMPILED, &key, &code));
+        if let Err(e) = stored {
+            warn!(error = %e, "Failed to store precompiled module");
+        }
+        Ok((module, false))
+    }
+
+    /// Whether the loaded module's native code came from a precompiled
+    /// artifact or the build cache instead of being compiled by Cranelift.
+    pub fn module_precompiled(&self) -> bool {
+        self.precompiled
+    }
+
+    /// Translate a failed `step()` call into a `PlcError`.
+    ///
+    /// Traps are resolved to their ST source line through the module's debug
+    /// line map, which is also remembered for [`LogicEngine::trap_location`].
+    fn step_error(&mut self, e: &anyhow::Error) -> PlcError {
+        let trap = e.downcast_ref::<Trap>();
+        // Check for out-of-fuel trap using Wasmtime's Trap enum
+        if matches!(trap, Some(Trap::OutOfFuel)) {
+            return PlcError::CycleOverrun {
+                expected_ns: self.cycle_time_ns,
+                actual_ns: 0, // Unknown - fuel exhausted before completion
+            };
+        }
+
+        self.trap_location = e.downcast_ref::<WasmBacktrace>().and_then(|trace| {
+            trace
+                .frames()
+                .iter()
+                .find_map(|frame| self.debug_map.lookup(frame.module_offset()? as u32))
+                .cloned()
+        });
+        match (&self.trap_location, trap) {
+            (Some(location), Some(trap)) => {
+                let message = format!("step() failed: {} at {location}", describe_trap(trap));
+                warn!("{message}");
+                PlcError::WasmTrap(message)
+            }
+            _ => PlcError::WasmTrap(format!("step() failed: {e}")),
+        }
+    }
+
     /// Load a Wasm module from WAT text format.
     pub fn load_wat(&mut self, wat: &str) -> Result<()> {
         let wasm_bytes = wat::parse_str(wat).context("Failed to parse WAT")?;
         self.load_module(&wasm_bytes)
     }
 
+    /// Replace the logic's linear memory with a snapshot taken by
+    /// [`LogicEngine::memory_snapshot`], growing the memory if needed. Memory
+    /// beyond the snapshot is zeroed.
+    pub fn restore_memory(&mut self, snapshot: &[u8]) -> PlcResult<()> {
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        let missing = snapshot.len().saturating_sub(memory.data_size(&self.store));
+        if missing > 0 {
+            let pages = missing.div_ceil(WASM_PAGE_SIZE) as u64;
+            memory
+                .grow(&mut self.store, pages)
+                .map_err(|e| PlcError::Fault(format!("Cannot grow memory for snapshot: {e}")))?;
+        }
+        let data = memory.data_mut(&mut self.store);
+        data[..snapshot.len()].copy_from_slice(snapshot);
+        data[snapshot.len()..].fill(0);
+        Ok(())
+    }
+
+    /// Set the cycle count and first-cycle flag the next step presents to
+    /// the logic, as when replaying recorded cycles.
+    pub fn set_cycle_state(&mut self, cycle_count: u64, first_cycle: bool) {
+        let state = self.store.data_mut();
+        state.cycle_count = cycle_count;
+        state.first_cycle = first_cycle;
+    }
+
+    /// Process-image layout declared by the loaded module, if it has one.
+    pub fn module_abi(&self) -> Option<&ProcessImageLayout> {
+        self.layout.as_ref()
+    }
+
+    /// Process-image layout of the host, from the configured dimensions.
+    pub fn host_abi(&self) -> &ProcessImageLayout {
+        &self.host_layout
+    }
+
+    /// Layout used when exchanging the process image with the module.
+    fn process_image_layout(&self) -> ProcessImageLayout {
+        self.layout.unwrap_or(ProcessImageLayout::CURRENT)
+    }
+
     /// Instantiate the loaded module.
     fn instantiate(&mut self) -> Result<()> {
         let module = self
@@ -419,6 +719,9 @@ impl WasmtimeHost {
         let step_fn: TypedFunc<(), ()> = instance
             .get_typed_func(&mut self.store, "step")
             .context("Module must export 'step' function")?;
+        let entry_fns = self
+            .resolve_entries(&instance)
+            .map_err(|e| anyhow!("{e}"))?;
 
         // Get optional init function
         let init_fn = instance.get_typed_func(&mut self.store, "init").ok();
@@ -436,12 +739,150 @@ impl WasmtimeHost {
         self.instance = Some(instance);
         self.memory = Some(memory);
         self.step_fn = Some(step_fn);
+        self.entry_fns = entry_fns;
         self.init_fn = init_fn;
         self.fault_fn = fault_fn;
 
         Ok(())
     }
 
+    /// Run one cycle calling `func`: copy inputs in, call, copy outputs out.
+    ///
+    /// The epoch and fuel budgets apply to each call, whichever task makes it.
+    fn run_cycle(
+        &mut self,
+        func: PlcResult<TypedFunc<(), ()>>,
+        inputs: &ProcessData,
+    ) -> PlcResult<&ProcessData> {
+        if !self.initialized {
+            return Err(PlcError::Fault("Engine not initialized".into()));
+        }
+
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        let func = func?;
+
+        // Read values needed before mutable borrow
+        let cycle_time_ns = self.cycle_time_ns;
+        let first_cycle = self.store.data().first_cycle;
+        let cycle_count = self.store.data().cycle_count;
+        let layout = self.process_image_layout();
+
+        // Copy inputs to Wasm memory
+        {
+            let data = memory.data_mut(&mut self.store);
+            copy_inputs_to_wasm(data, inputs, &layout);
+
+            // Update system info
+            let sys_info = WasmSystemInfo {
+                cycle_time_ns,
+                flags: if first_cycle {
+                    WasmSystemInfo::FLAG_FIRST_CYCLE
+                } else {
+                    0
+                },
+                cycle_count,
+                fault_code: 0,
+            };
+            write_system_info_for(data, &sys_info, &layout);
+        }
+
+        // Set epoch deadline for timeout
+        self.set_epoch_deadline();
+
+        // Add fuel if fuel-based budgeting is enabled
+        if self.use_fuel {
+            self.store
+                .set_fuel(self.fuel_per_cycle)
+                .map_err(|e| PlcError::Fault(format!("Failed to set fuel: {e}")))?;
+        }
+
+        // Call the entry function
+        if let Err(e) = func.call(&mut self.store, ()) {
+            return Err(self.step_error(&e));
+        }
+
+        // Copy outputs from Wasm memory
+        copy_outputs_from_wasm(memory.data(&self.store), &mut self.process_data, &layout);
+
+        // Advance cycle
+        self.store.data_mut().advance_cycle();
+
+        trace!(cycle = self.store.data().cycle_count, "Step completed");
+
+        Ok(&self.process_data)
+    }
+
+    /// Compile a replacement module and check its exports and layout.
+    fn check_replacement(&self, wasm_bytes: &[u8]) -> PlcResult<Replacement> {
+        self.trusted_keys.check(wasm_bytes)?;
+        let (module, wasm_bytes, precompiled) = self
+            .prepare_module(wasm_bytes)
+            .map_err(|e| PlcError::Config(format!("Failed to compile new module: {e}")))?;
+        let layout = read_layout(wasm_bytes, &self.host_layout)?;
+        let symbols = SymbolTable::from_wasm(wasm_bytes)?;
+
+        // Verify required exports exist
+        let required = ["step", "memory"]
+            .into_iter()
+            .chain(self.entry_points.iter().map(String::as_str));
+        for name in required {
+            if !module.exports().any(|e| e.name() == name) {
+                return Err(PlcError::Config(format!(
+                    "New module missing required '{name}' export"
+                )));
+            }
+        }
+
+        Ok(Replacement {
+            module,
+            precompiled,
+            layout,
+            symbols,
+            steps: read_step_table(wasm_bytes),
+            debug_map: read_debug_map(wasm_bytes),
+        })
+    }
+
+    /// Plan how the current state carries over to `replacement`.
+    fn migration_plan(&self, replacement: &Replacement) -> PlcResult<MigrationPlan> {
+        let memory_len = self
+            .memory
+            .map_or(0, |memory| memory.data_size(&self.store));
+        plan_migration(
+            StateShape {
+                layout: self.layout,
+                symbols: &self.symbols,
+                steps: &self.sfc_steps,
+            },
+            memory_len,
+            StateShape {
+                layout: replacement.layout,
+                symbols: &replacement.symbols,
+                steps: &replacement.steps,
+            },
+        )
+    }
+
+    /// Look up the configured task entry points in `instance`.
+    fn resolve_entries(
+        &mut self,
+        instance: &Instance,
+    ) -> PlcResult<HashMap<String, TypedFunc<(), ()>>> {
+        self.entry_points
+            .iter()
+            .map(|name| {
+                instance
+                    .get_typed_func(&mut self.store, name)
+                    .map(|func| (name.clone(), func))
+                    .map_err(|e| {
+                        PlcError::Config(format!("Module must export '{name}' function: {e}"))
+                    })
+            })
+            .collect()
+    }
+
     /// Increment the epoch counter (call from timer thread).
     ///
     /// This increments both the Wasmtime engine's epoch and our local counter.
@@ -504,6 +945,7 @@ impl LogicEngine for WasmtimeHost {
 
         // Write initial system info
         let cycle_time_ns = self.cycle_time_ns;
+        let layout = self.process_image_layout();
         if let Some(memory) = self.memory {
             let data = memory.data_mut(&mut self.store);
             let sys_info = WasmSystemInfo {
@@ -512,7 +954,7 @@ impl LogicEngine for WasmtimeHost {
                 cycle_count: 0,
                 fault_code: 0,
             };
-            write_system_info(data, &sys_info);
+            write_system_info_for(data, &sys_info, &layout);
         }
 
         self.initialized = true;
@@ -521,82 +963,24 @@ impl LogicEngine for WasmtimeHost {
         Ok(())
     }
 
-    fn step(&mut self, inputs: &ProcessData) -> PlcResult<ProcessData> {
-        if !self.initialized {
-            return Err(PlcError::Fault("Engine not initialized".into()));
-        }
-
-        let memory = self
-            .memory
-            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
-
-        if self.step_fn.is_none() {
-            return Err(PlcError::Fault("No step function".into()));
-        }
-
-        // Read values needed before mutable borrow
-        let cycle_time_ns = self.cycle_time_ns;
-        let first_cycle = self.store.data().first_cycle;
-        let cycle_count = self.store.data().cycle_count;
-
-        // Copy inputs to Wasm memory
-        {
-            let data = memory.data_mut(&mut self.store);
-            copy_inputs_to_wasm(data, inputs);
-
-            // Update system info
-            let sys_info = WasmSystemInfo {
-                cycle_time_ns,
-                flags: if first_cycle {
-                    WasmSystemInfo::FLAG_FIRST_CYCLE
-                } else {
-                    0
-                },
-                cycle_count,
-                fault_code: 0,
-            };
-            write_system_info(data, &sys_info);
-        }
-
-        // Set epoch deadline for timeout
-        self.set_epoch_deadline();
-
-        // Add fuel if fuel-based budgeting is enabled
-        if self.use_fuel {
-            self.store
-                .set_fuel(self.fuel_per_cycle)
-                .map_err(|e| PlcError::Fault(format!("Failed to set fuel: {e}")))?;
-        }
-
-        // Call step function
-        if let Some(step_fn) = &self.step_fn {
-            step_fn.call(&mut self.store, ()).map_err(|e| {
-                // Check for out-of-fuel trap using Wasmtime's Trap enum
-                if let Some(trap) = e.downcast_ref::<Trap>() {
-                    if matches!(trap, Trap::OutOfFuel) {
-                        return PlcError::CycleOverrun {
-                            expected_ns: self.cycle_time_ns,
-                            actual_ns: 0, // Unknown - fuel exhausted before completion
-                        };
-                    }
-                }
-                PlcError::WasmTrap(format!("step() failed: {e}"))
-            })?;
-        }
+    fn step(&mut self, inputs: &ProcessData) -> PlcResult<&ProcessData> {
+        let step_fn = self
+            .step_fn
+            .clone()
+            .ok_or_else(|| PlcError::Fault("No step function".into()));
+        self.run_cycle(step_fn, inputs)
+    }
 
-        // Copy outputs from Wasm memory
-        let mut outputs = self.process_data;
-        {
-            let data = memory.data(&self.store);
-            copy_outputs_from_wasm(data, &mut outputs);
+    fn step_entry(&mut self, entry: &str, inputs: &ProcessData) -> PlcResult<&ProcessData> {
+        if entry == "step" {
+            return self.step(inputs);
         }
-
-        // Advance cycle
-        self.store.data_mut().advance_cycle();
-
-        trace!(cycle = self.store.data().cycle_count, "Step completed");
-
-        Ok(outputs)
+        let entry_fn = self
+            .entry_fns
+            .get(entry)
+            .cloned()
+            .ok_or_else(|| PlcError::Fault(format!("No entry point '{entry}'")));
+        self.run_cycle(entry_fn, inputs)
     }
 
     fn fault(&mut self) -> PlcResult<()> {
@@ -618,15 +1002,19 @@ impl LogicEngine for WasmtimeHost {
         // Zero all outputs in memory
         let cycle_time_ns = self.cycle_time_ns;
         let cycle_count = self.store.data().cycle_count;
+        let layout = self.process_image_layout();
         if let Some(memory) = self.memory {
             let data = memory.data_mut(&mut self.store);
-            // Zero digital outputs (offset 4, size 4)
-            if data.len() >= 8 {
-                data[4..8].fill(0);
-            }
-            // Zero analog outputs (offset 0x28, size 32)
-            if data.len() >= 0x48 {
-                data[0x28..0x48].fill(0);
+            // Zero digital and analog outputs
+            let regions = [
+                (layout.do_offset(), layout.do_words() * 4),
+                (layout.ao_offset(), layout.ao_channels * 2),
+            ];
+            for (offset, len) in regions {
+                let range = offset as usize..(offset + len) as usize;
+                if let Some(outputs) = data.get_mut(range) {
+                    outputs.fill(0);
+                }
             }
 
             // Set fault flag in system info
@@ -636,7 +1024,7 @@ impl LogicEngine for WasmtimeHost {
                 cycle_count,
                 fault_code: 1, // Generic fault
             };
-            write_system_info(data, &sys_info);
+            write_system_info_for(data, &sys_info, &layout);
         }
 
         Ok(())
@@ -653,7 +1041,7 @@ impl LogicEngine for WasmtimeHost {
 
         // Tick interval: roughly 10µs per epoch as estimated in constructor
         // This allows fine-grained timeout control
-        let tick_interval = Duration::from_micros(10);
+        let tick_interval = plc_common::config::EPOCH_INTERVAL;
 
         info!(
             tick_interval_us = tick_interval.as_micros(),
@@ -681,35 +1069,27 @@ impl LogicEngine for WasmtimeHost {
             "Hot-reloading Wasm module"
         );
 
-        // Compile the new module first (before touching current state)
-        let new_module = Module::new(&self.engine, wasm_bytes)
-            .map_err(|e| PlcError::Config(format!("Failed to compile new module: {e}")))?;
-
-        // Verify required exports exist
-        let has_step = new_module.exports().any(|e| e.name() == "step");
-        let has_memory = new_module.exports().any(|e| e.name() == "memory");
-
-        if !has_step {
-            return Err(PlcError::Config(
-                "New module missing required 'step' export".into(),
-            ));
-        }
-        if !has_memory {
-            return Err(PlcError::Config(
-                "New module missing required 'memory' export".into(),
-            ));
-        }
+        // Check the new module and plan the migration before touching
+        // current state
+        let replacement = self.check_replacement(wasm_bytes)?;
+        let plan = match self.memory {
+            Some(_) if preserve_memory => Some(self.migration_plan(&replacement)?),
+            _ => None,
+        };
+        let Replacement {
+            module: new_module,
+            precompiled,
+            layout: new_layout,
+            symbols: new_symbols,
+            steps: new_steps,
+            debug_map: new_debug_map,
+        } = replacement;
 
         // Save old memory contents if preserving state
-        let old_memory_data = if preserve_memory {
-            self.memory.map(|mem| {
-                let data = mem.data(&self.store);
-                data.to_vec()
-            })
-        } else {
-            None
+        let old_memory_data = match (&plan, self.memory) {
+            (Some(_), Some(mem)) => Some(mem.data(&self.store).to_vec()),
+            _ => None,
         };
-        let old_memory_len = old_memory_data.as_ref().map(Vec::len);
 
         // Save cycle count to preserve continuity
         let saved_cycle_count = self.store.data().cycle_count;
@@ -742,6 +1122,8 @@ impl LogicEngine for WasmtimeHost {
             PlcError::Config(format!("New module must export 'step' function: {e}"))
         })?;
 
+        let new_entry_fns = self.resolve_entries(&new_instance)?;
+
         // Get optional init function from new instance
         let new_init_fn: Option<TypedFunc<(), ()>> =
             new_instance.get_typed_func(&mut self.store, "init").ok();
@@ -750,22 +1132,31 @@ impl LogicEngine for WasmtimeHost {
         let new_fault_fn: Option<TypedFunc<(), ()>> =
             new_instance.get_typed_func(&mut self.store, "fault").ok();
 
-        // Ensure new memory can fit old state BEFORE committing
-        if let Some(old_len) = old_memory_len {
-            let new_len = new_memory.data(&self.store).len();
-            if new_len < old_len {
-                return Err(PlcError::Config(format!(
-                    "Cannot preserve memory: new module has smaller memory ({} bytes) than old ({} bytes)",
-                    new_len, old_len
-                )));
+        // Migrate the old state into a copy of the new memory BEFORE
+        // committing, so a failed migration leaves the old module active
+        let migrated = match (&plan, &old_memory_data) {
+            (Some(plan), Some(old_data)) => {
+                log_migration(plan);
+                let mut data = new_memory.data(&self.store).to_vec();
+                plan.apply(&self.symbols, old_data, &new_symbols, &mut data)?;
+                Some(data)
             }
-        }
+            _ => None,
+        };
 
         // All instantiation succeeded - now commit state atomically
         self.module = Some(new_module);
+        self.precompiled = precompiled;
+        self.sfc_steps = new_steps;
+        self.debug_map = new_debug_map;
+        self.trap_location = None;
+        self.symbols = new_symbols;
+        self.layout = new_layout;
+        self.store.data_mut().layout = self.process_image_layout();
         self.instance = Some(new_instance);
         self.memory = Some(new_memory);
         self.step_fn = Some(new_step_fn);
+        self.entry_fns = new_entry_fns;
         self.init_fn = new_init_fn;
         self.fault_fn = new_fault_fn;
 
@@ -779,20 +1170,10 @@ impl LogicEngine for WasmtimeHost {
             "New module instantiated for hot-reload"
         );
 
-        // Restore memory contents if preserving state
-        if let Some(old_data) = old_memory_data {
-            let new_data = new_memory.data_mut(&mut self.store);
-            let copy_len = old_data.len();
-            if copy_len > 0 {
-                new_data[..copy_len].copy_from_slice(&old_data[..copy_len]);
-                debug!(
-                    copied_bytes = copy_len,
-                    old_size = old_data.len(),
-                    new_size = new_data.len(),
-                    "Memory state migrated"
-                );
-            }
-            debug_assert!(new_data.len() >= old_data.len());
+        // Install the migrated memory contents if preserving state
+        if let Some(data) = migrated {
+            new_memory.data_mut(&mut self.store).copy_from_slice(&data);
+            debug!(new_size = data.len(), "Memory state migrated");
         } else {
             // Call init on new module (either couldn't preserve memory or not requested)
             let has_init = self.init_fn.is_some();
@@ -816,6 +1197,14 @@ impl LogicEngine for WasmtimeHost {
         Ok(())
     }
 
+    fn plan_reload(&self, wasm_bytes: &[u8]) -> PlcResult<MigrationPlan> {
+        if self.memory.is_none() {
+            return Err(PlcError::Config("No module loaded".into()));
+        }
+        let replacement = self.check_replacement(wasm_bytes)?;
+        self.migration_plan(&replacement)
+    }
+
     fn supports_hot_reload(&self) -> bool {
         true
     }
@@ -826,6 +1215,234 @@ impl LogicEngine for WasmtimeHost {
             .map(|m| m.exports().map(|e| e.name().to_string()).collect())
             .unwrap_or_default()
     }
+
+    fn trap_location(&self) -> Option<SourceLocation> {
+        self.trap_location.clone()
+    }
+
+    fn symbols(&self) -> Option<&SymbolTable> {
+        Some(&self.symbols)
+    }
+
+    fn read_variable(&self, name: &str) -> PlcResult<VarValue> {
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        self.symbols.read(memory.data(&self.store), name)
+    }
+
+    fn write_variable(&mut self, name: &str, value: VarValue) -> PlcResult<()> {
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        self.symbols
+            .write(memory.data_mut(&mut self.store), name, value)
+    }
+
+    fn system_info(&self) -> Option<WasmSystemInfo> {
+        let state = self.store.data();
+        Some(WasmSystemInfo {
+            cycle_time_ns: self.cycle_time_ns,
+            flags: if state.first_cycle {
+                WasmSystemInfo::FLAG_FIRST_CYCLE
+            } else {
+                0
+            },
+            cycle_count: state.cycle_count,
+            fault_code: 0,
+        })
+    }
+
+    fn memory_snapshot(&self) -> Option<Vec<u8>> {
+        self.memory.map(|memory| memory.data(&self.store).to_vec())
+    }
+
+    fn memory(&self) -> Option<&[u8]> {
+        self.memory.map(|memory| memory.data(&self.store))
+    }
+
+    fn restore_state(&mut self, memory: &[u8], cycle_count: u64) -> PlcResult<()> {
+        self.restore_memory(memory)?;
+        self.set_cycle_state(cycle_count, false);
+        Ok(())
+    }
+
+    fn active_steps(&self) -> Vec<String> {
+        let Some(memory) = self.memory else {
+            return Vec::new();
+        };
+        let data = memory.data(&self.store);
+        self.sfc_steps
+            .iter()
+            .filter(|step| {
+                data.get(step.offset as usize)
+                    .is_some_and(|&flag| flag != 0)
+            })
+            .map(|step| step.name.clone())
+            .collect()
+    }
+}
+
+/// A compiled replacement module and its metadata, checked but not yet
+/// instantiated.
+struct Replacement {
+    module: Module,
+    precompiled: bool,
+    layout: Option<ProcessImageLayout>,
+    symbols: SymbolTable,
+    steps: Vec<StepFlag>,
+    debug_map: DebugMap,
+}
+
+/// The parts of a module that decide how its state migrates to another.
+pub(crate) struct StateShape<'a> {
+    /// Process-image layout declared by the module, if any.
+    pub layout: Option<ProcessImageLayout>,
+    /// Variables described by the module's symbol table.
+    pub symbols: &'a SymbolTable,
+    /// SFC step flags from the module's step table.
+    pub steps: &'a [StepFlag],
+}
+
+/// Plan how the state of the running module (`memory_len` bytes of linear
+/// memory) carries over to a replacement.
+///
+/// Variables carry over by name; without a symbol table on either side,
+/// memory is copied byte for byte.
+pub(crate) fn plan_migration(
+    current: StateShape<'_>,
+    memory_len: usize,
+    new: StateShape<'_>,
+) -> PlcResult<MigrationPlan> {
+    // Refuse incompatible layouts
+    let current_layout = current.layout.unwrap_or(ProcessImageLayout::CURRENT);
+    let new_layout = new.layout.unwrap_or(ProcessImageLayout::CURRENT);
+    if new_layout.user_data_offset != current_layout.user_data_offset {
+        return Err(PlcError::Config(format!(
+            "Cannot preserve memory: user data moves from {:#x} to {:#x}",
+            current_layout.user_data_offset, new_layout.user_data_offset
+        )));
+    }
+
+    if current.symbols.is_empty() || new.symbols.is_empty() {
+        return Ok(MigrationPlan::raw(memory_len));
+    }
+    Ok(MigrationPlan::new(
+        current.symbols,
+        current.steps,
+        new.symbols,
+        new.steps,
+        current_layout.user_data_offset as usize,
+    ))
+}
+
+/// Log what a migration drops or resets before it is applied.
+pub(crate) fn log_migration(plan: &MigrationPlan) {
+    info!(%plan, "Migrating state to the new module");
+    for name in plan.removed() {
+        warn!(variable = %name, "Variable removed, its value is dropped");
+    }
+    for change in plan.incompatible() {
+        warn!(%change, "Incompatible type change, variable reset to its initializer");
+    }
+}
+
+/// Read the SFC step table embedded by the compiler, if any.
+pub(crate) fn read_step_table(wasm_bytes: &[u8]) -> Vec<StepFlag> {
+    wasm_meta::custom_section(wasm_bytes, sfc::SECTION_NAME)
+        .map(sfc::decode_steps)
+        .unwrap_or_default()
+}
+
+/// Read the module's process-image layout and check it against the host's.
+///
+/// Modules without the section (hand-written WAT, modules from older
+/// compilers) are assumed to use the current layout with the default
+/// dimensions.
+pub(crate) fn read_layout(
+    wasm_bytes: &[u8],
+    host: &ProcessImageLayout,
+) -> PlcResult<Option<ProcessImageLayout>> {
+    let Some(payload) = wasm_meta::custom_section(wasm_bytes, abi::SECTION_NAME) else {
+        return Ok(None);
+    };
+    let layout = abi::decode_layout(payload)?;
+    layout.check_compatible(host)?;
+    if layout.version < host.version {
+        info!(abi = %layout.version, "Adapting process image for older module ABI");
+    }
+    Ok(Some(layout))
+}
+
+/// Read the source line map embedded by the compiler, if any.
+pub(crate) fn read_debug_map(wasm_bytes: &[u8]) -> DebugMap {
+    wasm_meta::custom_section(wasm_bytes, debug_map::SECTION_NAME)
+        .map(debug_map::decode_lines)
+        .unwrap_or_default()
+}
+
+/// Wasmtime settings for `wasm_config`.
+///
+/// Shared by the host and by ahead-of-time precompilation, so artifacts are
+/// built for exactly the engine that loads them.
+pub(crate) fn engine_config(wasm_config: &WasmtimeConfig) -> Config {
+    // Configure Wasmtime for real-time PLC execution
+    let mut config = Config::new();
+
+    // Enable epoch interruption for timeout control
+    config.epoch_interruption(true);
+
+    // Set optimization level
+    config.cranelift_opt_level(wasm_config.opt_level);
+
+    // Configure Wasm features for PLC use
+    config.wasm_threads(false);
+    config.wasm_simd(wasm_config.enable_simd);
+    // Relaxed SIMD requires SIMD to be enabled, but must be disabled in
+    // deterministic mode as it allows implementation-specific behavior
+    config.wasm_relaxed_simd(wasm_config.enable_simd && !wasm_config.deterministic);
+
+    // Apply deterministic mode settings if enabled
+    if wasm_config.deterministic {
+        // Disable features that can introduce non-determinism:
+        // - Reference types (externref/funcref) can have implementation-specific behavior
+        config.wasm_reference_types(false);
+        // - Bulk memory ops may have platform-specific edge cases
+        config.wasm_bulk_memory(false);
+        // - Multi-value returns add complexity
+        config.wasm_multi_value(false);
+        // - Function references can introduce non-determinism
+        config.wasm_function_references(false);
+        // - Tail calls can have stack behavior differences
+        config.wasm_tail_call(false);
+
+        debug!("Deterministic mode enabled: restricted Wasm feature set");
+    }
+
+    // Enable fuel-based execution budgeting if configured
+    if wasm_config.use_fuel {
+        config.consume_fuel(true);
+        debug!(
+            fuel_per_cycle = wasm_config.fuel_per_cycle,
+            "Fuel-based execution budgeting enabled"
+        );
+    }
+
+    config
+}
+
+/// Describe a trap in terms of the PLC program rather than the Wasm engine.
+fn describe_trap(trap: &Trap) -> String {
+    match trap {
+        Trap::IntegerDivisionByZero => "division by zero".into(),
+        Trap::IntegerOverflow => "integer overflow".into(),
+        Trap::BadConversionToInteger => "invalid conversion to integer".into(),
+        Trap::MemoryOutOfBounds => "memory access out of bounds".into(),
+        Trap::StackOverflow => "call stack overflow".into(),
+        Trap::UnreachableCodeReached => "unreachable code reached".into(),
+        Trap::Interrupt => "execution interrupted (timeout)".into(),
+        other => other.to_string(),
+    }
 }
 
 /// Configuration options for WasmtimeHost.
@@ -849,6 +1466,16 @@ pub struct WasmtimeConfig {
     pub use_fuel: bool,
     /// Fuel units to grant per cycle.
     pub fuel_per_cycle: u64,
+    /// Build cache directory for precompiled modules, if any.
+    pub cache_dir: Option<PathBuf>,
+    /// Dimensions of the process image exchanged with the module.
+    pub process_image: ProcessImageConfig,
+    /// Exported functions besides `step` that tasks call through
+    /// [`LogicEngine::step_entry`]; modules without them are refused.
+    pub entry_points: Vec<String>,
+    /// Hex Ed25519 public keys modules must be signed by; empty to accept
+    /// unsigned modules. See [`signing`](crate::signing).
+    pub trusted_keys: Vec<String>,
 }
 
 impl Default for WasmtimeConfig {
@@ -867,6 +1494,35 @@ impl Default for WasmtimeConfig {
             // Default fuel budget per cycle. 1M fuel units provides a reasonable
             // execution budget for typical PLC programs. Can be tuned via config.
             fuel_per_cycle: 1_000_000,
+            cache_dir: None,
+            process_image: ProcessImageConfig::default(),
+            entry_points: Vec::new(),
+            trusted_keys: Vec::new(),
+        }
+    }
+}
+
+impl WasmtimeConfig {
+    /// Engine settings from the `wasm` section of a runtime configuration,
+    /// with the task entry points the configured tasks call.
+    pub fn from_runtime_config(config: &plc_common::config::RuntimeConfig) -> Self {
+        Self {
+            opt_level: OptLevel::Speed,
+            max_memory_bytes: config.wasm.max_memory_bytes,
+            max_table_elements: config.wasm.max_table_elements,
+            enable_simd: config.wasm.enable_simd,
+            deterministic: config.wasm.deterministic,
+            use_fuel: config.wasm.use_fuel,
+            fuel_per_cycle: config.wasm.fuel_per_cycle,
+            cache_dir: config.wasm.cache_dir.clone(),
+            process_image: config.process_image,
+            entry_points: config
+                .scheduled_tasks()
+                .iter()
+                .map(|task| task.entry().to_string())
+                .filter(|entry| entry != "step")
+                .collect(),
+            trusted_keys: config.wasm.trusted_keys.clone(),
         }
     }
 }
@@ -875,6 +1531,7 @@ impl Default for WasmtimeConfig {
 #[derive(Debug, Default)]
 pub struct NullEngine {
     initialized: bool,
+    outputs: ProcessData,
 }
 
 impl LogicEngine for NullEngine {
@@ -883,9 +1540,14 @@ impl LogicEngine for NullEngine {
         Ok(())
     }
 
-    fn step(&mut self, inputs: &ProcessData) -> PlcResult<ProcessData> {
+    fn step(&mut self, inputs: &ProcessData) -> PlcResult<&ProcessData> {
         // Pass-through: inputs become outputs
-        Ok(*inputs)
+        self.outputs.clone_from(inputs);
+        Ok(&self.outputs)
+    }
+
+    fn step_entry(&mut self, _entry: &str, inputs: &ProcessData) -> PlcResult<&ProcessData> {
+        self.step(inputs)
     }
 
     fn fault(&mut self) -> PlcResult<()> {
@@ -900,6 +1562,7 @@ impl LogicEngine for NullEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::signing::SigningKey;
 
     const BLINK_WAT: &str = r#"
         (module
@@ -990,16 +1653,16 @@ mod tests {
 
         // Create input with DI bit 0 set
         let mut inputs = ProcessData::default();
-        inputs.digital_inputs[0] = 0x01;
+        inputs.digital_inputs_mut()[0] = 0x01;
 
         // Step and check output
         let outputs = host.step(&inputs).unwrap();
-        assert_eq!(outputs.digital_outputs[0] & 1, 1);
+        assert_eq!(outputs.digital_outputs()[0] & 1, 1);
 
         // Clear input and verify output clears
-        inputs.digital_inputs[0] = 0x00;
+        inputs.digital_inputs_mut()[0] = 0x00;
         let outputs = host.step(&inputs).unwrap();
-        assert_eq!(outputs.digital_outputs[0] & 1, 0);
+        assert_eq!(outputs.digital_outputs()[0] & 1, 0);
     }
 
     #[test]
@@ -1027,7 +1690,7 @@ mod tests {
 
         // Run some cycles with output set
         let mut inputs = ProcessData::default();
-        inputs.digital_inputs[0] = 1;
+        inputs.digital_inputs_mut()[0] = 1;
         for _ in 0..10 {
             let _ = host.step(&inputs).unwrap();
         }
@@ -1042,6 +1705,40 @@ mod tests {
         assert_eq!(do_value, 0);
     }
 
+    #[test]
+    fn test_fault_zeroes_configured_outputs() {
+        // 64 digital points and 4 analog channels each way: DO at 0x08,
+        // AO at 0x18.
+        let config = WasmtimeConfig {
+            process_image: ProcessImageConfig {
+                digital_inputs: 64,
+                digital_outputs: 64,
+                analog_inputs: 4,
+                analog_outputs: 4,
+            },
+            ..Default::default()
+        };
+        let mut host = WasmtimeHost::with_config(Duration::from_millis(1), config).unwrap();
+        host.load_wat(
+            r#"(module
+                (memory (export "memory") 1)
+                (@custom "plc.abi" "version 1.2\ndi 64\ndo 64\nai 4\nao 4\nsysinfo 32\nuser 64\n")
+                (func (export "step")
+                    (i64.store (i32.const 0x08) (i64.const -1))
+                    (i64.store (i32.const 0x18) (i64.const -1)))
+            )"#,
+        )
+        .unwrap();
+        host.init().unwrap();
+        let outputs = host.step(&host.process_data.clone()).unwrap();
+        assert_eq!(outputs.digital_outputs(), &[u32::MAX, u32::MAX]);
+        assert_eq!(outputs.analog_outputs(), &[-1; 4]);
+
+        host.fault().unwrap();
+        let data = host.memory.unwrap().data(&host.store);
+        assert!(data[0x08..0x20].iter().all(|&b| b == 0));
+    }
+
     #[test]
     fn test_null_engine() {
         let mut engine = NullEngine::default();
@@ -1054,7 +1751,7 @@ mod tests {
         let outputs = engine.step(&inputs).unwrap();
 
         // Null engine passes through inputs
-        assert_eq!(outputs.digital_inputs, inputs.digital_inputs);
+        assert_eq!(outputs.digital_inputs(), inputs.digital_inputs());
     }
 
     #[test]
@@ -1085,6 +1782,194 @@ mod tests {
         assert!(!null_engine.supports_hot_reload());
     }
 
+    #[test]
+    fn test_precompiled_module_reused() {
+        let dir = std::env::temp_dir().join(format!("plc-precompiled-{}", std::process::id()));
+        let config = WasmtimeConfig {
+            cache_dir: Some(dir.clone()),
+            ..WasmtimeConfig::default()
+        };
+        let wasm_bytes = wat::parse_str(PASSTHROUGH_WAT).unwrap();
+
+        let mut first =
+            WasmtimeHost::with_config(Duration::from_millis(1), config.clone()).unwrap();
+        first.load_module(&wasm_bytes).unwrap();
+        assert!(!first.module_precompiled());
+
+        // A second host with the same configuration skips Cranelift.
+        let mut second = WasmtimeHost::with_config(Duration::from_millis(1), config).unwrap();
+        second.load_module(&wasm_bytes).unwrap();
+        assert!(second.module_precompiled());
+        second.init().unwrap();
+        let mut inputs = ProcessData::default();
+        inputs.digital_inputs_mut()[0] = 1;
+        assert_eq!(second.step(&inputs).unwrap().digital_outputs()[0] & 1, 1);
+
+        // So does a hot-reload of an unchanged module.
+        first.init().unwrap();
+        first.reload_module(&wasm_bytes, true).unwrap();
+        assert!(first.module_precompiled());
+
+        // A different engine configuration compiles afresh.
+        let fuel_off = WasmtimeConfig {
+            cache_dir: Some(dir.clone()),
+            use_fuel: false,
+            ..WasmtimeConfig::default()
+        };
+        let mut third = WasmtimeHost::with_config(Duration::from_millis(1), fuel_off).unwrap();
+        third.load_module(&wasm_bytes).unwrap();
+        assert!(!third.module_precompiled());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_altered_precompiled_module_is_recompiled() {
+        let dir = std::env::temp_dir().join(format!("plc-precompiled-alt-{}", std::process::id()));
+        let config = WasmtimeConfig {
+            cache_dir: Some(dir.clone()),
+            ..WasmtimeConfig::default()
+        };
+        let wasm_bytes = wat::parse_str(PASSTHROUGH_WAT).unwrap();
+        let mut first =
+            WasmtimeHost::with_config(Duration::from_millis(1), config.clone()).unwrap();
+        first.load_module(&wasm_bytes).unwrap();
+
+        // Flip one byte of the cached native code
+        let entries: Vec<_> = std::fs::read_dir(dir.join(build_cache::PRECOMPILED))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        let mut code = std::fs::read(&entries[0]).unwrap();
+        let middle = code.len() / 2;
+        code[middle] ^= 0x01;
+        std::fs::write(&entries[0], &code).unwrap();
+
+        let mut second =
+            WasmtimeHost::with_config(Duration::from_millis(1), config.clone()).unwrap();
+        second.load_module(&wasm_bytes).unwrap();
+        assert!(!second.module_precompiled(), "altered entry is not loaded");
+        second.init().unwrap();
+        let mut inputs = ProcessData::default();
+        inputs.digital_inputs_mut()[0] = 1;
+        assert_eq!(second.step(&inputs).unwrap().digital_outputs()[0] & 1, 1);
+
+        // Recompiling replaced the entry
+        let mut third = WasmtimeHost::with_config(Duration::from_millis(1), config).unwrap();
+        third.load_module(&wasm_bytes).unwrap();
+        assert!(third.module_precompiled());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_trusted_keys_bypass_the_precompiled_cache() {
+        let dir =
+            std::env::temp_dir().join(format!("plc-precompiled-signed-{}", std::process::id()));
+        let key = SigningKey::generate().unwrap();
+        let config = WasmtimeConfig {
+            cache_dir: Some(dir.clone()),
+            trusted_keys: vec![key.public_key_hex()],
+            ..WasmtimeConfig::default()
+        };
+        let signed = key.sign(&wat::parse_str(PASSTHROUGH_WAT).unwrap()).unwrap();
+
+        for _ in 0..2 {
+            let mut host =
+                WasmtimeHost::with_config(Duration::from_millis(1), config.clone()).unwrap();
+            host.load_module(&signed).unwrap();
+            assert!(!host.module_precompiled());
+        }
+        assert!(!dir.join(build_cache::PRECOMPILED).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_precompiled_artifact() {
+        let wasm_bytes = wat::parse_str(PASSTHROUGH_WAT).unwrap();
+        let artifact =
+            precompiled::precompile(&wasm_bytes, &WasmtimeConfig::default(), None).unwrap();
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        host.load_module(&artifact).unwrap();
+        assert!(host.module_precompiled());
+        host.init().unwrap();
+        let mut inputs = ProcessData::default();
+        inputs.digital_inputs_mut()[0] = 1;
+        assert_eq!(host.step(&inputs).unwrap().digital_outputs()[0] & 1, 1);
+
+        // Hot-reload accepts artifacts too
+        host.reload_module(&artifact, true).unwrap();
+        assert!(host.module_precompiled());
+
+        // An engine configured differently compiles the embedded module
+        let fuel_off = WasmtimeConfig {
+            use_fuel: false,
+            ..WasmtimeConfig::default()
+        };
+        let mut other = WasmtimeHost::with_config(Duration::from_millis(1), fuel_off).unwrap();
+        other.load_module(&artifact).unwrap();
+        assert!(!other.module_precompiled());
+        other.init().unwrap();
+        assert_eq!(other.step(&inputs).unwrap().digital_outputs()[0] & 1, 1);
+    }
+
+    #[test]
+    fn test_tampered_artifact_refused() {
+        let wasm_bytes = wat::parse_str(PASSTHROUGH_WAT).unwrap();
+        let mut artifact =
+            precompiled::precompile(&wasm_bytes, &WasmtimeConfig::default(), None).unwrap();
+        let middle = artifact.len() / 2;
+        artifact[middle] ^= 0xff;
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        assert!(host.load_module(&artifact).is_err());
+
+        host.load_wat(PASSTHROUGH_WAT).unwrap();
+        host.init().unwrap();
+        assert!(host.reload_module(&artifact, true).is_err());
+        assert!(host.is_ready());
+    }
+
+    #[test]
+    fn test_trusted_keys_require_signed_modules() {
+        let key = SigningKey::generate().unwrap();
+        let config = WasmtimeConfig {
+            trusted_keys: vec![key.public_key_hex()],
+            ..WasmtimeConfig::default()
+        };
+        let wasm_bytes = wat::parse_str(PASSTHROUGH_WAT).unwrap();
+        let signed = key.sign(&wasm_bytes).unwrap();
+
+        let mut host = WasmtimeHost::with_config(Duration::from_millis(1), config).unwrap();
+        assert!(host.load_module(&wasm_bytes).is_err());
+        host.load_module(&signed).unwrap();
+        host.init().unwrap();
+
+        // Unsigned and tampered replacements are refused, the module keeps running
+        assert!(matches!(
+            host.reload_module(&wasm_bytes, true),
+            Err(PlcError::UntrustedModule(_))
+        ));
+        let mut tampered = signed.clone();
+        tampered[wasm_bytes.len() - 2] ^= 0x01;
+        assert!(matches!(
+            host.reload_module(&tampered, true),
+            Err(PlcError::UntrustedModule(_))
+        ));
+        assert!(host.is_ready());
+        host.reload_module(&signed, true).unwrap();
+
+        // Signed artifacts load with their native code
+        let artifact =
+            precompiled::precompile(&wasm_bytes, &WasmtimeConfig::default(), None).unwrap();
+        assert!(host.reload_module(&artifact, true).is_err());
+        host.reload_module(&key.sign(&artifact).unwrap(), true)
+            .unwrap();
+        assert!(host.module_precompiled());
+    }
+
     #[test]
     fn test_hot_reload_basic() {
         let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
@@ -1108,8 +1993,8 @@ mod tests {
 
         // Module should still work
         let outputs = host.step(&inputs).unwrap();
+        assert_eq!(outputs.digital_outputs()[0] & 1, 0); // No input set
         assert_eq!(host.store.data().cycle_count, 6);
-        assert_eq!(outputs.digital_outputs[0] & 1, 0); // No input set
     }
 
     #[test]
@@ -1153,7 +2038,7 @@ mod tests {
         // Run a step to verify output is set based on marker
         let inputs = ProcessData::default();
         let outputs = host.step(&inputs).unwrap();
-        assert_eq!(outputs.digital_outputs[0] & 1, 1); // Marker != 0
+        assert_eq!(outputs.digital_outputs()[0] & 1, 1); // Marker != 0
 
         // Hot-reload with memory preservation
         let wasm_bytes = wat::parse_str(WRITER_WAT).unwrap();
@@ -1167,7 +2052,7 @@ mod tests {
 
         // Module should still work with preserved state
         let outputs = host.step(&inputs).unwrap();
-        assert_eq!(outputs.digital_outputs[0] & 1, 1); // Marker still != 0
+        assert_eq!(outputs.digital_outputs()[0] & 1, 1); // Marker still != 0
     }
 
     #[test]
@@ -1243,7 +2128,53 @@ mod tests {
 
         let inputs = ProcessData::default();
         let outputs = host.step(&inputs).unwrap();
-        assert_eq!(outputs.digital_outputs[0] & 1, 1);
+        assert_eq!(outputs.digital_outputs()[0] & 1, 1);
+    }
+
+    #[test]
+    fn test_hot_reload_migrates_by_name() {
+        // Main.a : INT at 0x1000, Main.b : INT at 0x1002; step counts both up.
+        const OLD_WAT: &str = r#"
+            (module
+                (memory (export "memory") 1)
+                (@custom "plc.symbols" "v1\nMain.a 4096 2 local 0 INT\nMain.b 4098 2 local 0 INT\n")
+                (func (export "step")
+                    (i32.store16 (i32.const 0x1000)
+                        (i32.add (i32.load16_s (i32.const 0x1000)) (i32.const 1)))
+                    (i32.store16 (i32.const 0x1002)
+                        (i32.add (i32.load16_s (i32.const 0x1002)) (i32.const 2))))
+            )
+        "#;
+        // Main.x : INT = 5 is inserted before Main.b, which becomes a DINT.
+        const NEW_WAT: &str = r#"
+            (module
+                (memory (export "memory") 1)
+                (@custom "plc.symbols" "v1\nMain.a 4096 2 local 0 INT\nMain.x 4098 2 local 0 INT\nMain.b 4100 4 local 0 DINT\n")
+                (data (i32.const 0x1002) "\05\00")
+                (func (export "step"))
+            )
+        "#;
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        host.load_wat(OLD_WAT).unwrap();
+        host.init().unwrap();
+        for _ in 0..3 {
+            host.step(&ProcessData::default()).unwrap();
+        }
+
+        // A dry run reports the plan and leaves the old module running
+        let wasm_bytes = wat::parse_str(NEW_WAT).unwrap();
+        let plan = host.plan_reload(&wasm_bytes).unwrap();
+        assert_eq!(plan.copied(), 1);
+        assert_eq!(plan.added(), ["Main.x"]);
+        assert_eq!(plan.converted()[0].to, "DINT");
+        assert!(plan.is_lossless());
+        assert_eq!(host.read_variable("Main.b").unwrap(), VarValue::Int(6));
+
+        host.reload_module(&wasm_bytes, true).unwrap();
+        assert_eq!(host.read_variable("Main.a").unwrap(), VarValue::Int(3));
+        assert_eq!(host.read_variable("Main.x").unwrap(), VarValue::Int(5));
+        assert_eq!(host.read_variable("Main.b").unwrap(), VarValue::Int(6));
     }
 
     #[test]
@@ -1270,6 +2201,38 @@ mod tests {
         let _ = host.step(&inputs).unwrap();
     }
 
+    #[test]
+    fn test_task_entry_points() {
+        let config = WasmtimeConfig {
+            entry_points: vec!["Fast".into()],
+            ..Default::default()
+        };
+        let mut host = WasmtimeHost::with_config(Duration::from_millis(1), config).unwrap();
+        host.load_wat(PASSTHROUGH_WAT).unwrap();
+        assert!(host.init().unwrap_err().to_string().contains("Fast"));
+
+        const TASKS_WAT: &str = r#"
+            (module
+                (memory (export "memory") 1)
+                (func (export "step"))
+                (func (export "Fast")
+                    (i32.store (i32.const 0x04) (i32.const 7)))
+            )
+        "#;
+        host.load_wat(TASKS_WAT).unwrap();
+        host.init().unwrap();
+        let inputs = ProcessData::default();
+        let outputs = host.step_entry("Fast", &inputs).unwrap();
+        assert_eq!(outputs.digital_outputs(), &[7]);
+        assert!(host.step_entry("Slow", &inputs).is_err());
+
+        // A replacement without the entry point is refused
+        let wasm_bytes = wat::parse_str(PASSTHROUGH_WAT).unwrap();
+        let result = host.reload_module(&wasm_bytes, false);
+        assert!(result.unwrap_err().to_string().contains("Fast"));
+        host.step_entry("Fast", &inputs).unwrap();
+    }
+
     #[test]
     fn test_hot_reload_rejects_missing_memory() {
         let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
@@ -1288,4 +2251,148 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("memory"));
     }
+
+    #[test]
+    fn test_active_steps_from_step_table() {
+        // Each step swaps the two flags, like an SFC alternating between steps.
+        const SFC_WAT: &str = r#"
+            (module
+                (memory (export "memory") 1)
+                (@custom "plc.sfc" "Main.Idle 256\nMain.Fill 257\n")
+                (func (export "step")
+                    (i32.store8 (i32.const 257) (i32.load8_u (i32.const 256)))
+                    (i32.store8 (i32.const 256) (i32.eqz (i32.load8_u (i32.const 257))))
+                )
+            )
+        "#;
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        assert!(host.active_steps().is_empty());
+
+        host.load_wat(SFC_WAT).unwrap();
+        host.init().unwrap();
+        let inputs = ProcessData::default();
+
+        host.step(&inputs).unwrap();
+        assert_eq!(host.active_steps(), vec!["Main.Idle"]);
+        host.step(&inputs).unwrap();
+        assert_eq!(host.active_steps(), vec!["Main.Fill"]);
+
+        // A module without a step table reports no steps.
+        let wasm_bytes = wat::parse_str(PASSTHROUGH_WAT).unwrap();
+        host.reload_module(&wasm_bytes, false).unwrap();
+        assert!(host.active_steps().is_empty());
+    }
+
+    #[test]
+    fn test_trap_resolved_to_source_line() {
+        // The single entry at offset 0 covers the whole module.
+        const DIV_WAT: &str = r#"
+            (module
+                (memory (export "memory") 1)
+                (@custom "plc.debug_lines" "0 42 FB Conveyor conveyor.st\n")
+                (func (export "step")
+                    (drop (i32.div_s (i32.const 1000) (i32.load (i32.const 512))))
+                )
+            )
+        "#;
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        host.load_wat(DIV_WAT).unwrap();
+        host.init().unwrap();
+        assert_eq!(host.trap_location(), None);
+
+        let err = host.step(&ProcessData::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "wasm trap: step() failed: division by zero at conveyor.st:42 in FB Conveyor"
+        );
+        let location = host.trap_location().unwrap();
+        assert_eq!(location.line, 42);
+        assert_eq!(location.pou, "Conveyor");
+
+        // Reloading forgets the old location; without a map traps are unresolved.
+        let wasm_bytes = wat::parse_str(
+            r#"(module (memory (export "memory") 1) (func (export "step") unreachable))"#,
+        )
+        .unwrap();
+        host.reload_module(&wasm_bytes, false).unwrap();
+        assert_eq!(host.trap_location(), None);
+        assert!(host.step(&ProcessData::default()).is_err());
+        assert_eq!(host.trap_location(), None);
+    }
+
+    #[test]
+    fn test_variable_access_by_name() {
+        const COUNTER_WAT: &str = r#"
+            (module
+                (memory (export "memory") 1)
+                (@custom "plc.symbols" "v1\nMain.count 512 2 local 0 INT\n")
+                (func (export "step")
+                    (i32.store16 (i32.const 512)
+                        (i32.add (i32.load16_s (i32.const 512)) (i32.const 1)))
+                )
+            )
+        "#;
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        assert!(host.read_variable("Main.count").is_err());
+
+        host.load_wat(COUNTER_WAT).unwrap();
+        host.init().unwrap();
+        assert_eq!(host.symbols().unwrap().len(), 1);
+
+        host.write_variable("Main.count", VarValue::Int(-5))
+            .unwrap();
+        host.step(&ProcessData::default()).unwrap();
+        assert_eq!(host.read_variable("MAIN.COUNT").unwrap(), VarValue::Int(-4));
+        assert!(host.read_variable("Main.other").is_err());
+
+        // A symbol table from a newer compiler is rejected.
+        let wasm_bytes = wat::parse_str(
+            r#"(module (memory (export "memory") 1) (@custom "plc.symbols" "v9\n") (func (export "step")))"#,
+        )
+        .unwrap();
+        assert!(host.reload_module(&wasm_bytes, true).is_err());
+        assert_eq!(host.read_variable("Main.count").unwrap(), VarValue::Int(-4));
+    }
+
+    #[test]
+    fn test_process_image_abi_checked() {
+        fn module(abi: &str) -> Vec<u8> {
+            wat::parse_str(format!(
+                r#"(module
+                    (memory (export "memory") 1)
+                    (@custom "plc.abi" "{abi}")
+                    (func (export "step")
+                        (i32.store (i32.const 0x50)
+                            (i32.add (i32.load (i32.const 0x50)) (i32.const 7))))
+                )"#
+            ))
+            .unwrap()
+        }
+        let v1_0 = "version 1.0\\ndi 32\\ndo 32\\nai 16\\nao 16\\nsysinfo 8\\nuser 80\\n";
+        let v1_1 = "version 1.1\\ndi 32\\ndo 32\\nai 16\\nao 16\\nsysinfo 32\\nuser 104\\n";
+        let v2_0 = "version 2.0\\ndi 32\\ndo 32\\nai 16\\nao 16\\nsysinfo 32\\nuser 104\\n";
+
+        let mut host = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        let err = host.load_module(&module(v2_0)).unwrap_err();
+        assert!(err.to_string().contains("built for ABI 2.0"), "{err}");
+
+        // A 1.0 module keeps its data at 0x50, which 1.1 uses for the cycle count.
+        host.load_module(&module(v1_0)).unwrap();
+        assert_eq!(host.module_abi(), Some(&ProcessImageLayout::V1_0));
+        host.init().unwrap();
+        host.step(&ProcessData::default()).unwrap();
+        host.step(&ProcessData::default()).unwrap();
+        let memory = host.memory.unwrap().data(&host.store);
+        assert_eq!(&memory[0x50..0x54], &14u32.to_le_bytes());
+
+        // Incompatible or layout-changing reloads leave the old module running.
+        assert!(host.reload_module(&module(v2_0), false).is_err());
+        assert!(host.reload_module(&module(v1_1), true).is_err());
+        assert_eq!(host.module_abi(), Some(&ProcessImageLayout::V1_0));
+        host.reload_module(&module(v1_1), false).unwrap();
+        assert_eq!(host.module_abi(), Some(&ProcessImageLayout::V1_1));
+    }
 }
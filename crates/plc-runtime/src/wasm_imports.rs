@@ -5,20 +5,44 @@
 //!
 //! # Imported Functions
 //!
-//! Wasm modules should import these functions from the "plc" module:
+//! The functions and their signatures are described by
+//! [`plc_common::host_api::HOST_FUNCTIONS`]; Wasm modules import them from
+//! the "plc" module:
 //!
 //! ```wat
 //! (import "plc" "read_di" (func $read_di (param i32) (result i32)))
 //! (import "plc" "write_do" (func $write_do (param i32 i32)))
 //! (import "plc" "read_ai" (func $read_ai (param i32) (result i32)))
 //! (import "plc" "write_ao" (func $write_ao (param i32 i32)))
+//! (import "plc" "read_bit" (func $read_bit (param i32 i32) (result i32)))
+//! (import "plc" "write_bit" (func $write_bit (param i32 i32 i32)))
+//! (import "plc" "read_byte" (func $read_byte (param i32) (result i32)))
+//! (import "plc" "write_byte" (func $write_byte (param i32 i32)))
+//! (import "plc" "read_word" (func $read_word (param i32) (result i32)))
+//! (import "plc" "write_word" (func $write_word (param i32 i32)))
+//! (import "plc" "read_dword" (func $read_dword (param i32) (result i32)))
+//! (import "plc" "write_dword" (func $write_dword (param i32 i32)))
+//! (import "plc" "read_real" (func $read_real (param i32) (result f32)))
+//! (import "plc" "write_real" (func $write_real (param i32 f32)))
 //! (import "plc" "get_cycle_time" (func $get_cycle_time (result i32)))
+//! (import "plc" "get_cycle_count" (func $get_cycle_count (result i64)))
+//! (import "plc" "is_first_cycle" (func $is_first_cycle (result i32)))
 //! (import "plc" "log_message" (func $log_message (param i32 i32)))
 //! ```
+//!
+//! The typed accessors (`read_bit` to `write_real`) take a byte offset into
+//! the process image (see [`crate::wasm_memory`]). Reads may cover the whole
+//! image including system info; writes only the digital and analog output
+//! regions. Offsets and bounds follow the loaded module's process-image
+//! layout ([`HostState::layout`]).
 
+use crate::wasm_interp::InterpreterState;
 use crate::wasm_memory::{
-    read_ai_from_memory, read_di_from_memory, write_ao_to_memory, write_do_to_memory,
+    read_ai_from_memory, read_di_from_memory, read_image_bytes, write_ao_to_memory,
+    write_do_to_memory, write_image_bytes,
 };
+use plc_common::abi::ProcessImageLayout;
+use plc_common::host_api;
 use std::collections::VecDeque;
 use tracing::{trace, warn};
 use wasmtime::{Caller, Linker, Memory, ResourceLimiter, StoreLimits, StoreLimitsBuilder};
@@ -53,6 +77,8 @@ pub struct HostState {
     max_host_calls_per_cycle: u32,
     /// Whether rate limit was hit this cycle (for diagnostics).
     pub rate_limit_hit: bool,
+    /// Process-image layout of the loaded module.
+    pub layout: ProcessImageLayout,
 }
 
 impl std::fmt::Debug for HostState {
@@ -81,6 +107,7 @@ impl Default for HostState {
             host_call_count: 0,
             max_host_calls_per_cycle: DEFAULT_MAX_HOST_CALLS_PER_CYCLE,
             rate_limit_hit: false,
+            layout: ProcessImageLayout::CURRENT,
         }
     }
 }
@@ -195,6 +222,44 @@ impl ResourceLimiter for HostState {
     }
 }
 
+/// The calling store as seen by a host function, whichever engine runs the
+/// module.
+///
+/// Host functions are written once against this trait and registered with
+/// both the Wasmtime linker and the interpreter's
+/// ([`register_interpreter_functions`]).
+pub(crate) trait HostContext {
+    /// Host state of the store.
+    fn state(&mut self) -> &mut HostState;
+
+    /// Linear memory of the calling module together with the host state,
+    /// or `None` if the module has no memory.
+    fn memory_and_state(&mut self) -> Option<(&mut [u8], &mut HostState)>;
+}
+
+impl HostContext for Caller<'_, HostState> {
+    fn state(&mut self) -> &mut HostState {
+        self.data_mut()
+    }
+
+    fn memory_and_state(&mut self) -> Option<(&mut [u8], &mut HostState)> {
+        let memory = get_memory(self)?;
+        Some(memory.data_and_store_mut(self))
+    }
+}
+
+impl HostContext for wasmi::Caller<'_, InterpreterState> {
+    fn state(&mut self) -> &mut HostState {
+        &mut self.data_mut().host
+    }
+
+    fn memory_and_state(&mut self) -> Option<(&mut [u8], &mut HostState)> {
+        let memory = self.get_export("memory")?.into_memory()?;
+        let (data, state) = memory.data_and_store_mut(self);
+        Some((data, &mut state.host))
+    }
+}
+
 /// Helper to get memory from caller, either from export or stored reference.
 fn get_memory(caller: &mut Caller<'_, HostState>) -> Option<Memory> {
     // First try to get from export
@@ -208,15 +273,14 @@ fn get_memory(caller: &mut Caller<'_, HostState>) -> Option<Memory> {
 }
 
 /// Read a digital input bit.
-fn host_read_di(mut caller: Caller<'_, HostState>, bit: i32) -> i32 {
+fn host_read_di(mut cx: impl HostContext, bit: i32) -> i32 {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return 0;
     }
 
-    if let Some(memory) = get_memory(&mut caller) {
-        let data = memory.data(&caller);
-        let value = read_di_from_memory(data, bit as u32);
+    if let Some((data, state)) = cx.memory_and_state() {
+        let value = read_di_from_memory(data, &state.layout, bit as u32);
         trace!(bit, value, "read_di");
         if value {
             1
@@ -230,15 +294,14 @@ fn host_read_di(mut caller: Caller<'_, HostState>, bit: i32) -> i32 {
 }
 
 /// Write a digital output bit.
-fn host_write_do(mut caller: Caller<'_, HostState>, bit: i32, value: i32) {
+fn host_write_do(mut cx: impl HostContext, bit: i32, value: i32) {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return;
     }
 
-    if let Some(memory) = get_memory(&mut caller) {
-        let data = memory.data_mut(&mut caller);
-        write_do_to_memory(data, bit as u32, value != 0);
+    if let Some((data, state)) = cx.memory_and_state() {
+        write_do_to_memory(data, &state.layout, bit as u32, value != 0);
         trace!(bit, value, "write_do");
     } else {
         warn!("write_do called without memory");
@@ -246,15 +309,14 @@ fn host_write_do(mut caller: Caller<'_, HostState>, bit: i32, value: i32) {
 }
 
 /// Read an analog input channel.
-fn host_read_ai(mut caller: Caller<'_, HostState>, channel: i32) -> i32 {
+fn host_read_ai(mut cx: impl HostContext, channel: i32) -> i32 {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return 0;
     }
 
-    if let Some(memory) = get_memory(&mut caller) {
-        let data = memory.data(&caller);
-        let value = read_ai_from_memory(data, channel as u32);
+    if let Some((data, state)) = cx.memory_and_state() {
+        let value = read_ai_from_memory(data, &state.layout, channel as u32);
         trace!(channel, value, "read_ai");
         value as i32
     } else {
@@ -264,55 +326,174 @@ fn host_read_ai(mut caller: Caller<'_, HostState>, channel: i32) -> i32 {
 }
 
 /// Write an analog output channel.
-fn host_write_ao(mut caller: Caller<'_, HostState>, channel: i32, value: i32) {
+fn host_write_ao(mut cx: impl HostContext, channel: i32, value: i32) {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return;
     }
 
-    if let Some(memory) = get_memory(&mut caller) {
-        let data = memory.data_mut(&mut caller);
+    if let Some((data, state)) = cx.memory_and_state() {
         // Clamp to i16 range
         let clamped = value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
-        write_ao_to_memory(data, channel as u32, clamped);
+        write_ao_to_memory(data, &state.layout, channel as u32, clamped);
         trace!(channel, value = clamped, "write_ao");
     } else {
         warn!("write_ao called without memory");
     }
 }
 
+/// Read `N` bytes at a process-image byte offset.
+///
+/// Returns `None` (and the host function returns 0) if the call is rate
+/// limited or the range is outside the process image.
+fn read_image<const N: usize>(
+    cx: &mut impl HostContext,
+    name: &str,
+    offset: i32,
+) -> Option<[u8; N]> {
+    // Rate limit check (per threat model AS4.1)
+    if !cx.state().check_rate_limit() {
+        return None;
+    }
+    let Some((data, state)) = cx.memory_and_state() else {
+        warn!("{name} called without memory");
+        return None;
+    };
+    let bytes = u32::try_from(offset)
+        .ok()
+        .and_then(|offset| read_image_bytes(data, &state.layout, offset));
+    if bytes.is_none() {
+        warn!(offset, "{name}: outside the process image");
+    }
+    bytes
+}
+
+/// Write bytes at a process-image byte offset inside an output region.
+fn write_image(cx: &mut impl HostContext, name: &str, offset: i32, bytes: &[u8]) {
+    // Rate limit check (per threat model AS4.1)
+    if !cx.state().check_rate_limit() {
+        return;
+    }
+    let Some((data, state)) = cx.memory_and_state() else {
+        warn!("{name} called without memory");
+        return;
+    };
+    let written = u32::try_from(offset)
+        .is_ok_and(|offset| write_image_bytes(data, &state.layout, offset, bytes));
+    if written {
+        trace!(offset, len = bytes.len(), "{name}");
+    } else {
+        warn!(
+            offset,
+            len = bytes.len(),
+            "{name}: outside the output regions"
+        );
+    }
+}
+
+/// Read a bit of a process-image byte.
+fn host_read_bit(mut cx: impl HostContext, offset: i32, bit: i32) -> i32 {
+    if !(0..8).contains(&bit) {
+        warn!(bit, "read_bit: bit out of range");
+        return 0;
+    }
+    read_image::<1>(&mut cx, "read_bit", offset).map_or(0, |[byte]| (byte >> bit) as i32 & 1)
+}
+
+/// Write a bit of an output byte, leaving the other bits unchanged.
+fn host_write_bit(mut cx: impl HostContext, offset: i32, bit: i32, value: i32) {
+    if !(0..8).contains(&bit) {
+        warn!(bit, "write_bit: bit out of range");
+        return;
+    }
+    let Some((data, state)) = cx.memory_and_state() else {
+        warn!("write_bit called without memory");
+        return;
+    };
+    let current = u32::try_from(offset)
+        .ok()
+        .and_then(|offset| read_image_bytes::<1>(data, &state.layout, offset));
+    let [byte] = current.unwrap_or_default();
+    let byte = if value != 0 {
+        byte | (1 << bit)
+    } else {
+        byte & !(1 << bit)
+    };
+    write_image(&mut cx, "write_bit", offset, &[byte]);
+}
+
+/// Read a process-image byte.
+fn host_read_byte(mut cx: impl HostContext, offset: i32) -> i32 {
+    read_image(&mut cx, "read_byte", offset).map_or(0, |bytes| u8::from_le_bytes(bytes) as i32)
+}
+
+/// Write an output byte; the value is truncated to 8 bits.
+fn host_write_byte(mut cx: impl HostContext, offset: i32, value: i32) {
+    write_image(&mut cx, "write_byte", offset, &(value as u8).to_le_bytes());
+}
+
+/// Read a little-endian 16-bit process-image word.
+fn host_read_word(mut cx: impl HostContext, offset: i32) -> i32 {
+    read_image(&mut cx, "read_word", offset).map_or(0, |bytes| u16::from_le_bytes(bytes) as i32)
+}
+
+/// Write a little-endian 16-bit output word; the value is truncated to 16 bits.
+fn host_write_word(mut cx: impl HostContext, offset: i32, value: i32) {
+    write_image(&mut cx, "write_word", offset, &(value as u16).to_le_bytes());
+}
+
+/// Read a little-endian 32-bit process-image word.
+fn host_read_dword(mut cx: impl HostContext, offset: i32) -> i32 {
+    read_image(&mut cx, "read_dword", offset).map_or(0, i32::from_le_bytes)
+}
+
+/// Write a little-endian 32-bit output word.
+fn host_write_dword(mut cx: impl HostContext, offset: i32, value: i32) {
+    write_image(&mut cx, "write_dword", offset, &value.to_le_bytes());
+}
+
+/// Read a little-endian IEEE 754 float from the process image.
+fn host_read_real(mut cx: impl HostContext, offset: i32) -> f32 {
+    read_image(&mut cx, "read_real", offset).map_or(0.0, f32::from_le_bytes)
+}
+
+/// Write a little-endian IEEE 754 float to an output region.
+fn host_write_real(mut cx: impl HostContext, offset: i32, value: f32) {
+    write_image(&mut cx, "write_real", offset, &value.to_le_bytes());
+}
+
 /// Get the cycle time in nanoseconds.
 ///
 /// Note: Returns i32 for Wasm ABI compatibility. Values > i32::MAX are capped.
-fn host_get_cycle_time(mut caller: Caller<'_, HostState>) -> i32 {
+fn host_get_cycle_time(mut cx: impl HostContext) -> i32 {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return 0;
     }
-    let cycle_time = caller.data().cycle_time_ns;
+    let cycle_time = cx.state().cycle_time_ns;
     trace!(cycle_time_ns = cycle_time, "get_cycle_time");
     // Cap at i32::MAX for ABI compatibility (cycle times > ~2.1s will be capped)
     cycle_time.min(i32::MAX as u64) as i32
 }
 
 /// Get the current cycle count.
-fn host_get_cycle_count(mut caller: Caller<'_, HostState>) -> i64 {
+fn host_get_cycle_count(mut cx: impl HostContext) -> i64 {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return 0;
     }
-    let count = caller.data().cycle_count;
+    let count = cx.state().cycle_count;
     trace!(cycle_count = count, "get_cycle_count");
     count as i64
 }
 
 /// Check if this is the first cycle after initialization.
-fn host_is_first_cycle(mut caller: Caller<'_, HostState>) -> i32 {
+fn host_is_first_cycle(mut cx: impl HostContext) -> i32 {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return 0;
     }
-    let first = caller.data().first_cycle;
+    let first = cx.state().first_cycle;
     trace!(first_cycle = first, "is_first_cycle");
     if first {
         1
@@ -327,9 +508,9 @@ fn host_is_first_cycle(mut caller: Caller<'_, HostState>) -> i32 {
 ///
 /// This function validates that `ptr` and `len` are non-negative and that
 /// the memory range `[ptr, ptr+len)` is within bounds before accessing memory.
-fn host_log_message(mut caller: Caller<'_, HostState>, ptr: i32, len: i32) {
+fn host_log_message(mut cx: impl HostContext, ptr: i32, len: i32) {
     // Rate limit check (per threat model AS4.1)
-    if !caller.data_mut().check_rate_limit() {
+    if !cx.state().check_rate_limit() {
         return;
     }
 
@@ -352,55 +533,130 @@ fn host_log_message(mut caller: Caller<'_, HostState>, ptr: i32, len: i32) {
         }
     };
 
-    let msg = if let Some(memory) = get_memory(&mut caller) {
-        let data = memory.data(&caller);
-        if end <= data.len() {
-            std::str::from_utf8(&data[start..end])
-                .ok()
-                .map(String::from)
-        } else {
-            warn!(
-                ptr,
-                len,
-                memory_size = data.len(),
-                "log_message: out of bounds"
-            );
-            None
-        }
-    } else {
+    let Some((data, state)) = cx.memory_and_state() else {
         warn!("log_message called without memory");
-        None
+        return;
     };
+    if end > data.len() {
+        warn!(
+            ptr,
+            len,
+            memory_size = data.len(),
+            "log_message: out of bounds"
+        );
+        return;
+    }
 
-    if let Some(msg) = msg {
+    if let Ok(msg) = std::str::from_utf8(&data[start..end]) {
         tracing::info!(wasm_log = %msg, "PLC program log");
-        caller.data_mut().push_log(msg);
+        state.push_log(msg.to_string());
     }
 }
 
+/// Wrap every function of [`host_api::HOST_FUNCTIONS`] into `$linker`,
+/// whose host functions receive a `$caller` implementing [`HostContext`].
+macro_rules! wrap_host_functions {
+    ($linker:expr, $caller:ty) => {
+        for func in host_api::HOST_FUNCTIONS {
+            let name = func.name;
+            let module = host_api::MODULE;
+            match name {
+                // I/O functions
+                "read_di" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32| host_read_di(c, a))?
+                }
+                "write_do" => $linker.func_wrap(module, name, |c: $caller, a: i32, b: i32| {
+                    host_write_do(c, a, b)
+                })?,
+                "read_ai" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32| host_read_ai(c, a))?
+                }
+                "write_ao" => $linker.func_wrap(module, name, |c: $caller, a: i32, b: i32| {
+                    host_write_ao(c, a, b)
+                })?,
+                "read_bit" => $linker.func_wrap(module, name, |c: $caller, a: i32, b: i32| {
+                    host_read_bit(c, a, b)
+                })?,
+                "write_bit" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32, b: i32, v: i32| {
+                        host_write_bit(c, a, b, v)
+                    })?
+                }
+                "read_byte" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32| host_read_byte(c, a))?
+                }
+                "write_byte" => $linker.func_wrap(module, name, |c: $caller, a: i32, v: i32| {
+                    host_write_byte(c, a, v)
+                })?,
+                "read_word" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32| host_read_word(c, a))?
+                }
+                "write_word" => $linker.func_wrap(module, name, |c: $caller, a: i32, v: i32| {
+                    host_write_word(c, a, v)
+                })?,
+                "read_dword" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32| host_read_dword(c, a))?
+                }
+                "write_dword" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32, v: i32| {
+                        host_write_dword(c, a, v)
+                    })?
+                }
+                "read_real" => {
+                    $linker.func_wrap(module, name, |c: $caller, a: i32| host_read_real(c, a))?
+                }
+                "write_real" => $linker.func_wrap(module, name, |c: $caller, a: i32, v: f32| {
+                    host_write_real(c, a, v)
+                })?,
+
+                // System functions
+                "get_cycle_time" => {
+                    $linker.func_wrap(module, name, |c: $caller| host_get_cycle_time(c))?
+                }
+                "get_cycle_count" => {
+                    $linker.func_wrap(module, name, |c: $caller| host_get_cycle_count(c))?
+                }
+                "is_first_cycle" => {
+                    $linker.func_wrap(module, name, |c: $caller| host_is_first_cycle(c))?
+                }
+
+                // Logging
+                "log_message" => {
+                    $linker.func_wrap(module, name, |c: $caller, p: i32, l: i32| {
+                        host_log_message(c, p, l)
+                    })?
+                }
+
+                _ => anyhow::bail!("Host function {name} is not implemented"),
+            };
+        }
+    };
+}
+
 /// Register all PLC host functions with a Wasmtime linker.
+///
+/// Registers exactly the functions of [`host_api::HOST_FUNCTIONS`], the
+/// description the compiler generates its imports from.
 pub fn register_host_functions(linker: &mut Linker<HostState>) -> anyhow::Result<()> {
-    // I/O functions
-    linker.func_wrap("plc", "read_di", host_read_di)?;
-    linker.func_wrap("plc", "write_do", host_write_do)?;
-    linker.func_wrap("plc", "read_ai", host_read_ai)?;
-    linker.func_wrap("plc", "write_ao", host_write_ao)?;
-
-    // System functions
-    linker.func_wrap("plc", "get_cycle_time", host_get_cycle_time)?;
-    linker.func_wrap("plc", "get_cycle_count", host_get_cycle_count)?;
-    linker.func_wrap("plc", "is_first_cycle", host_is_first_cycle)?;
-
-    // Logging
-    linker.func_wrap("plc", "log_message", host_log_message)?;
+    wrap_host_functions!(linker, Caller<'_, HostState>);
+    Ok(())
+}
 
+/// Register all PLC host functions with an interpreter linker.
+///
+/// The same functions as [`register_host_functions`], for
+/// [`InterpreterHost`](crate::wasm_interp::InterpreterHost).
+pub fn register_interpreter_functions(
+    linker: &mut wasmi::Linker<InterpreterState>,
+) -> anyhow::Result<()> {
+    wrap_host_functions!(linker, wasmi::Caller<'_, InterpreterState>);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wasmtime::{Config, Engine, Module, Store, TypedFunc};
+    use wasmtime::{Config, Engine, Module, Store, TypedFunc, ValType};
 
     fn create_test_engine() -> Engine {
         let mut config = Config::new();
@@ -479,6 +735,104 @@ mod tests {
         register_host_functions(&mut linker).unwrap();
     }
 
+    #[test]
+    fn test_registered_functions_match_host_api() {
+        let engine = create_test_engine();
+        let mut linker = Linker::new(&engine);
+        register_host_functions(&mut linker).unwrap();
+        let mut store = Store::new(&engine, HostState::default());
+
+        let wasm_type = |ty: host_api::WasmValType| match ty {
+            host_api::WasmValType::I32 => ValType::I32,
+            host_api::WasmValType::I64 => ValType::I64,
+            host_api::WasmValType::F32 => ValType::F32,
+        };
+        let same = |a: Vec<ValType>, b: Vec<ValType>| {
+            a.len() == b.len() && a.iter().zip(&b).all(|(a, b)| ValType::eq(a, b))
+        };
+        for func in host_api::HOST_FUNCTIONS {
+            let ty = linker
+                .get(&mut store, host_api::MODULE, func.name)
+                .and_then(|ext| ext.into_func())
+                .unwrap_or_else(|| panic!("{} not registered", func.name))
+                .ty(&store);
+            let params = func.wasm_params().into_iter().map(wasm_type).collect();
+            let results = func.wasm_results().into_iter().map(wasm_type).collect();
+            assert!(same(ty.params().collect(), params), "{func}");
+            assert!(same(ty.results().collect(), results), "{func}");
+        }
+
+        let registered = linker.iter(&mut store).count();
+        assert_eq!(registered, host_api::HOST_FUNCTIONS.len());
+    }
+
+    #[test]
+    fn test_typed_image_access() {
+        let engine = create_test_engine();
+        let mut linker = Linker::new(&engine);
+        register_host_functions(&mut linker).unwrap();
+
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "plc" "read_bit" (func $read_bit (param i32 i32) (result i32)))
+                (import "plc" "write_bit" (func $write_bit (param i32 i32 i32)))
+                (import "plc" "read_byte" (func $read_byte (param i32) (result i32)))
+                (import "plc" "write_byte" (func $write_byte (param i32 i32)))
+                (import "plc" "read_word" (func $read_word (param i32) (result i32)))
+                (import "plc" "write_word" (func $write_word (param i32 i32)))
+                (import "plc" "read_dword" (func $read_dword (param i32) (result i32)))
+                (import "plc" "write_dword" (func $write_dword (param i32 i32)))
+                (import "plc" "read_real" (func $read_real (param i32) (result f32)))
+                (import "plc" "write_real" (func $write_real (param i32 f32)))
+                (memory (export "memory") 1)
+                (func (export "step")
+                    ;; Counter from DI word to the first two AO channels
+                    (call $write_dword (i32.const 0x28) (call $read_dword (i32.const 0x00)))
+                    ;; Float from AI 2-3 to AO 2-3
+                    (call $write_real (i32.const 0x2C) (call $read_real (i32.const 0x0C)))
+                    ;; Word and byte copies within the outputs
+                    (call $write_word (i32.const 0x30) (call $read_word (i32.const 0x08)))
+                    (call $write_byte (i32.const 0x32) (call $read_byte (i32.const 0x01)))
+                    ;; DO bit 3 from DI bit 9
+                    (call $write_bit (i32.const 0x04) (i32.const 3)
+                        (call $read_bit (i32.const 0x01) (i32.const 1)))
+                    ;; Rejected: input region, user data, negative offset
+                    (call $write_byte (i32.const 0x00) (i32.const 0xFF))
+                    (call $write_dword (i32.const 0x46) (i32.const -1))
+                    (i32.store (i32.const 0x100) (call $read_dword (i32.const 0x68)))
+                    (i32.store (i32.const 0x104) (call $read_byte (i32.const -1)))
+                )
+            )
+            "#,
+        )
+        .unwrap();
+        let module = Module::new(&engine, wasm).unwrap();
+        let mut store = Store::new(&engine, HostState::default());
+        store.set_epoch_deadline(1000);
+        let instance = linker.instantiate(&mut store, &module).unwrap();
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        let step: TypedFunc<(), ()> = instance.get_typed_func(&mut store, "step").unwrap();
+
+        let data = memory.data_mut(&mut store);
+        data[0x00..0x04].copy_from_slice(&0x8765_0201u32.to_le_bytes());
+        data[0x08..0x0A].copy_from_slice(&0xBEEFu16.to_le_bytes());
+        data[0x0C..0x10].copy_from_slice(&21.5f32.to_le_bytes());
+        data[0x68..0x6C].copy_from_slice(&7u32.to_le_bytes());
+        data[0x100..0x108].fill(0xAA);
+        step.call(&mut store, ()).unwrap();
+
+        let data = memory.data(&store);
+        assert_eq!(&data[0x28..0x2C], &0x8765_0201u32.to_le_bytes());
+        assert_eq!(&data[0x2C..0x30], &21.5f32.to_le_bytes());
+        assert_eq!(&data[0x30..0x32], &0xBEEFu16.to_le_bytes());
+        assert_eq!(data[0x32], 0x02);
+        assert_eq!(data[0x04], 0x08);
+        assert_eq!(data[0x00], 0x01);
+        assert_eq!(&data[0x44..0x48], &[0; 4]);
+        assert_eq!(&data[0x100..0x108], &[0; 8]);
+    }
+
     #[test]
     fn test_linker_with_minimal_module() {
         let engine = create_test_engine();
@@ -0,0 +1,960 @@
+//! Interpreter-based logic engine for constrained targets.
+//!
+//! [`InterpreterHost`] runs PLC programs in the [wasmi] interpreter instead
+//! of compiling them with Cranelift. It needs far less memory than
+//! [`WasmtimeHost`](crate::wasm_host::WasmtimeHost) and starts without a
+//! JIT step, at the cost of slower cycles, which suits small ARM boards.
+//! It also serves as a second, independent engine to test the compiler
+//! against.
+//!
+//! The engine offers the same host functions ([`register_interpreter_functions`]),
+//! process-image exchange, memory and table limits, fuel budget and
+//! hot-reload with state migration as the Wasmtime host. Differences:
+//!
+//! - **No epoch interruption**: fuel is the only bound on a cycle, so the
+//!   configuration refuses the interpreter without `use_fuel`.
+//! - **Fuel costs differ**: wasmi charges fuel per bytecode instruction,
+//!   which does not match Wasmtime's count exactly.
+//! - **No trap locations**: traps are reported without a source line.
+//! - **No native code**: modules are translated on every load; of a
+//!   precompiled artifact only the embedded Wasm module is used.
+//!
+//! # Usage
+//!
+//! ```ignore
+//! let mut host = InterpreterHost::new(cycle_time)?;
+//! host.load_module(&wasm_bytes)?;
+//! host.init()?;
+//!
+//! loop {
+//!     host.step(&inputs)?;
+//! }
+//! ```
+
+use crate::io_image::ProcessData;
+use crate::online_change::MigrationPlan;
+use crate::precompiled;
+use crate::signing::TrustedKeys;
+use crate::symbols::{SymbolTable, VarValue};
+use crate::wasm_host::{
+    log_migration, plan_migration, read_layout, read_step_table, LogicEngine, StateShape,
+    WasmtimeConfig, WASM_PAGE_SIZE,
+};
+use crate::wasm_imports::{register_interpreter_functions, HostState};
+use crate::wasm_memory::{
+    copy_inputs_to_wasm, copy_outputs_from_wasm, write_system_info_for, WasmSystemInfo,
+};
+use anyhow::{anyhow, Context, Result};
+use plc_common::abi::ProcessImageLayout;
+use plc_common::error::{PlcError, PlcResult};
+use plc_common::sfc::StepFlag;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, info, trace, warn};
+use wasmi::core::{Pages, TrapCode};
+use wasmi::{
+    Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+/// Store data of the interpreter: the host state shared with the Wasmtime
+/// host functions, plus the interpreter's resource limits.
+pub struct InterpreterState {
+    /// State the host functions work on.
+    pub host: HostState,
+    /// Limits on memory and table growth.
+    limits: StoreLimits,
+}
+
+impl InterpreterState {
+    /// Create the store data with the given cycle time and resource limits.
+    ///
+    /// As for [`HostState::with_limits`], a limit of 0 means unlimited.
+    /// Instance and memory counts are not limited: every hot-reload adds
+    /// an instance to the store.
+    pub fn with_limits(
+        cycle_time_ns: u64,
+        max_memory_bytes: usize,
+        max_table_elements: u32,
+    ) -> Self {
+        let mut limits = StoreLimitsBuilder::new();
+        if max_memory_bytes != 0 {
+            limits = limits.memory_size(max_memory_bytes);
+        }
+        if max_table_elements != 0 {
+            limits = limits.table_elements(max_table_elements);
+        }
+        Self {
+            host: HostState::new(cycle_time_ns),
+            limits: limits.build(),
+        }
+    }
+}
+
+impl std::fmt::Debug for InterpreterState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterpreterState")
+            .field("host", &self.host)
+            .finish()
+    }
+}
+
+/// Interpreter-based logic engine (wasmi).
+pub struct InterpreterHost {
+    /// Store containing host state and instances.
+    store: Store<InterpreterState>,
+    /// Linker with host functions.
+    linker: Linker<InterpreterState>,
+    /// Translated module (set after load_module).
+    module: Option<Module>,
+    /// Instantiated module.
+    instance: Option<Instance>,
+    /// Reference to Wasm memory.
+    memory: Option<Memory>,
+    /// Cached step function.
+    step_fn: Option<TypedFunc<(), ()>>,
+    /// Task entry points besides `step` the module must export.
+    entry_points: Vec<String>,
+    /// Cached task entry functions, by export name.
+    entry_fns: HashMap<String, TypedFunc<(), ()>>,
+    /// Cached init function (optional).
+    init_fn: Option<TypedFunc<(), ()>>,
+    /// Cached fault function (optional).
+    fault_fn: Option<TypedFunc<(), ()>>,
+    /// Cycle time in nanoseconds.
+    cycle_time_ns: u64,
+    /// Whether the engine has been initialized.
+    initialized: bool,
+    /// Outputs of the last step(), sized like the host's process image.
+    process_data: ProcessData,
+    /// Process-image layout of the host, from the configured dimensions.
+    host_layout: ProcessImageLayout,
+    /// Whether fuel-based execution budgeting is enabled.
+    use_fuel: bool,
+    /// Fuel units to grant per cycle.
+    fuel_per_cycle: u64,
+    /// SFC step flags from the module's step table.
+    sfc_steps: Vec<StepFlag>,
+    /// Variables described by the module's symbol table.
+    symbols: SymbolTable,
+    /// Process-image layout declared by the module, if any.
+    layout: Option<ProcessImageLayout>,
+    /// Keys a module must be signed by; empty to accept any module.
+    trusted_keys: TrustedKeys,
+}
+
+impl std::fmt::Debug for InterpreterHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InterpreterHost")
+            .field("module_loaded", &self.module.is_some())
+            .field("initialized", &self.initialized)
+            .field("cycle_time_ns", &self.cycle_time_ns)
+            .finish()
+    }
+}
+
+impl InterpreterHost {
+    /// Create a new interpreter host with the given cycle time.
+    pub fn new(cycle_time: Duration) -> Result<Self> {
+        Self::with_config(cycle_time, WasmtimeConfig::default())
+    }
+
+    /// Create an interpreter host from RuntimeConfig.
+    ///
+    /// Reads Wasm limits from the config's `wasm` section; the epoch
+    /// settings do not apply.
+    pub fn from_runtime_config(config: &plc_common::config::RuntimeConfig) -> Result<Self> {
+        Self::with_config(
+            config.cycle_time,
+            WasmtimeConfig::from_runtime_config(config),
+        )
+    }
+
+    /// Create a new interpreter host with custom configuration.
+    ///
+    /// Takes the same configuration as the Wasmtime host; `opt_level` and
+    /// `cache_dir` only concern Cranelift and are ignored.
+    pub fn with_config(cycle_time: Duration, wasm_config: WasmtimeConfig) -> Result<Self> {
+        let mut config = Config::default();
+        let trusted_keys = TrustedKeys::parse(&wasm_config.trusted_keys)?;
+
+        if wasm_config.enable_simd {
+            warn!("The interpreter does not support SIMD; modules using it are refused");
+        }
+
+        // Apply deterministic mode settings if enabled, as for Wasmtime
+        if wasm_config.deterministic {
+            config.wasm_reference_types(false);
+            config.wasm_bulk_memory(false);
+            config.wasm_multi_value(false);
+            config.wasm_tail_call(false);
+            debug!("Deterministic mode enabled: restricted Wasm feature set");
+        }
+
+        // Fuel is the only way to stop a runaway cycle in the interpreter
+        if wasm_config.use_fuel {
+            config.consume_fuel(true);
+            debug!(
+                fuel_per_cycle = wasm_config.fuel_per_cycle,
+                "Fuel-based execution budgeting enabled"
+            );
+        } else {
+            warn!("Interpreter without fuel: a cycle that does not end is not stopped");
+        }
+
+        // Modules are translated eagerly on load (the default), so no
+        // translation happens inside a cycle.
+        let engine = Engine::new(&config);
+
+        let cycle_time_ns = u64::try_from(cycle_time.as_nanos()).unwrap_or(u64::MAX);
+        let state = InterpreterState::with_limits(
+            cycle_time_ns,
+            wasm_config.max_memory_bytes,
+            wasm_config.max_table_elements,
+        );
+        let mut store = Store::new(&engine, state);
+        store.limiter(|state| &mut state.limits);
+
+        let mut linker = Linker::new(&engine);
+        register_interpreter_functions(&mut linker).context("Failed to register host functions")?;
+
+        info!(
+            cycle_time_ns,
+            max_memory_bytes = wasm_config.max_memory_bytes,
+            max_table_elements = wasm_config.max_table_elements,
+            deterministic = wasm_config.deterministic,
+            use_fuel = wasm_config.use_fuel,
+            fuel_per_cycle = wasm_config.fuel_per_cycle,
+            signatures_required = !trusted_keys.is_empty(),
+            "InterpreterHost created"
+        );
+
+        Ok(Self {
+            store,
+            linker,
+            module: None,
+            instance: None,
+            memory: None,
+            step_fn: None,
+            entry_points: wasm_config.entry_points,
+            entry_fns: HashMap::new(),
+            init_fn: None,
+            fault_fn: None,
+            cycle_time_ns,
+            initialized: false,
+            process_data: ProcessData::new(&wasm_config.process_image),
+            host_layout: ProcessImageLayout::for_config(&wasm_config.process_image),
+            use_fuel: wasm_config.use_fuel,
+            fuel_per_cycle: wasm_config.fuel_per_cycle,
+            sfc_steps: Vec::new(),
+            symbols: SymbolTable::default(),
+            layout: None,
+            trusted_keys,
+        })
+    }
+
+    /// Load a Wasm module from bytes.
+    ///
+    /// Precompiled artifacts are accepted too; their native code does not
+    /// apply here, so the embedded Wasm module is translated instead. With
+    /// trusted keys configured, modules without a valid signature by one of
+    /// them are refused.
+    pub fn load_module(&mut self, wasm_bytes: &[u8]) -> Result<()> {
+        self.trusted_keys.check(wasm_bytes)?;
+        let wasm_bytes = precompiled::wasm_module(wasm_bytes)?;
+        let layout = read_layout(wasm_bytes, &self.host_layout)?;
+        let module = Module::new(self.store.engine(), wasm_bytes)
+            .map_err(|e| anyhow!("Failed to translate Wasm module: {e}"))?;
+        let symbols = SymbolTable::from_wasm(wasm_bytes)?;
+
+        info!(
+            exports = ?module.exports().map(|e| e.name()).collect::<Vec<_>>(),
+            "Wasm module translated"
+        );
+
+        self.module = Some(module);
+        self.sfc_steps = read_step_table(wasm_bytes);
+        self.symbols = symbols;
+        self.layout = layout;
+        self.store.data_mut().host.layout = self.process_image_layout();
+        self.instance = None;
+        self.memory = None;
+        self.step_fn = None;
+        self.entry_fns.clear();
+        self.init_fn = None;
+        self.fault_fn = None;
+        self.initialized = false;
+
+        Ok(())
+    }
+
+    /// Load a Wasm module from WAT text format.
+    pub fn load_wat(&mut self, wat: &str) -> Result<()> {
+        let wasm_bytes = wat::parse_str(wat).context("Failed to parse WAT")?;
+        self.load_module(&wasm_bytes)
+    }
+
+    /// Replace the logic's linear memory with a snapshot taken by
+    /// [`LogicEngine::memory_snapshot`], growing the memory if needed. Memory
+    /// beyond the snapshot is zeroed.
+    pub fn restore_memory(&mut self, snapshot: &[u8]) -> PlcResult<()> {
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        let missing = snapshot
+            .len()
+            .saturating_sub(memory.data(&self.store).len());
+        if missing > 0 {
+            let pages = u32::try_from(missing.div_ceil(WASM_PAGE_SIZE))
+                .ok()
+                .and_then(Pages::new)
+                .ok_or_else(|| PlcError::Fault("Snapshot exceeds the memory limit".into()))?;
+            memory
+                .grow(&mut self.store, pages)
+                .map_err(|e| PlcError::Fault(format!("Cannot grow memory for snapshot: {e}")))?;
+        }
+        let data = memory.data_mut(&mut self.store);
+        data[..snapshot.len()].copy_from_slice(snapshot);
+        data[snapshot.len()..].fill(0);
+        Ok(())
+    }
+
+    /// Set the cycle count and first-cycle flag the next step presents to
+    /// the logic, as when replaying recorded cycles.
+    pub fn set_cycle_state(&mut self, cycle_count: u64, first_cycle: bool) {
+        let state = &mut self.store.data_mut().host;
+        state.cycle_count = cycle_count;
+        state.first_cycle = first_cycle;
+    }
+
+    /// Process-image layout declared by the loaded module, if it has one.
+    pub fn module_abi(&self) -> Option<&ProcessImageLayout> {
+        self.layout.as_ref()
+    }
+
+    /// Process-image layout of the host, from the configured dimensions.
+    pub fn host_abi(&self) -> &ProcessImageLayout {
+        &self.host_layout
+    }
+
+    /// Layout used when exchanging the process image with the module.
+    fn process_image_layout(&self) -> ProcessImageLayout {
+        self.layout.unwrap_or(ProcessImageLayout::CURRENT)
+    }
+
+    /// Grant a cycle's fuel if fuel-based budgeting is enabled.
+    fn ensure_fuel(&mut self) -> PlcResult<()> {
+        if self.use_fuel {
+            self.store
+                .set_fuel(self.fuel_per_cycle)
+                .map_err(|e| PlcError::Config(format!("Failed to set fuel: {e}")))?;
+        }
+        Ok(())
+    }
+
+    /// Instantiate `module` in the store and look up its exports.
+    ///
+    /// Does not touch the current instance, so a failure leaves it active.
+    fn instantiate_module(&mut self, module: &Module) -> PlcResult<Instantiated> {
+        // Start functions may run, so fuel must be available
+        self.ensure_fuel()?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut self.store, module)
+            .and_then(|pre| pre.start(&mut self.store))
+            .map_err(|e| PlcError::Config(format!("Failed to instantiate module: {e}")))?;
+
+        let memory = instance
+            .get_memory(&self.store, "memory")
+            .ok_or_else(|| PlcError::Config("Module must export 'memory'".into()))?;
+        let step_fn = instance
+            .get_typed_func::<(), ()>(&self.store, "step")
+            .map_err(|e| PlcError::Config(format!("Module must export 'step' function: {e}")))?;
+        let entry_fns = self
+            .entry_points
+            .iter()
+            .map(|name| {
+                instance
+                    .get_typed_func::<(), ()>(&self.store, name)
+                    .map(|func| (name.clone(), func))
+                    .map_err(|e| {
+                        PlcError::Config(format!("Module must export '{name}' function: {e}"))
+                    })
+            })
+            .collect::<PlcResult<_>>()?;
+        let init_fn = instance.get_typed_func(&self.store, "init").ok();
+        let fault_fn = instance.get_typed_func(&self.store, "fault").ok();
+
+        Ok(Instantiated {
+            instance,
+            memory,
+            step_fn,
+            entry_fns,
+            init_fn,
+            fault_fn,
+        })
+    }
+
+    /// Make `instantiated` the active instance.
+    fn install(&mut self, instantiated: Instantiated) {
+        debug!(
+            has_init = instantiated.init_fn.is_some(),
+            has_fault = instantiated.fault_fn.is_some(),
+            memory_pages = u32::from(instantiated.memory.current_pages(&self.store)),
+            "Module instantiated"
+        );
+        self.instance = Some(instantiated.instance);
+        self.memory = Some(instantiated.memory);
+        self.step_fn = Some(instantiated.step_fn);
+        self.entry_fns = instantiated.entry_fns;
+        self.init_fn = instantiated.init_fn;
+        self.fault_fn = instantiated.fault_fn;
+    }
+
+    /// Run one cycle calling `func`: copy inputs in, call, copy outputs out.
+    fn run_cycle(
+        &mut self,
+        func: PlcResult<TypedFunc<(), ()>>,
+        inputs: &ProcessData,
+    ) -> PlcResult<&ProcessData> {
+        if !self.initialized {
+            return Err(PlcError::Fault("Engine not initialized".into()));
+        }
+
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        let func = func?;
+
+        let layout = self.process_image_layout();
+        let sys_info = self.cycle_system_info();
+        {
+            let data = memory.data_mut(&mut self.store);
+            copy_inputs_to_wasm(data, inputs, &layout);
+            write_system_info_for(data, &sys_info, &layout);
+        }
+
+        self.ensure_fuel()?;
+
+        if let Err(e) = func.call(&mut self.store, ()) {
+            return Err(self.step_error(&e));
+        }
+
+        copy_outputs_from_wasm(memory.data(&self.store), &mut self.process_data, &layout);
+        self.store.data_mut().host.advance_cycle();
+
+        trace!(cycle = self.store.data().host.cycle_count, "Step completed");
+
+        Ok(&self.process_data)
+    }
+
+    /// System info presented to the logic in the next cycle.
+    fn cycle_system_info(&self) -> WasmSystemInfo {
+        let state = &self.store.data().host;
+        WasmSystemInfo {
+            cycle_time_ns: self.cycle_time_ns,
+            flags: if state.first_cycle {
+                WasmSystemInfo::FLAG_FIRST_CYCLE
+            } else {
+                0
+            },
+            cycle_count: state.cycle_count,
+            fault_code: 0,
+        }
+    }
+
+    /// Translate a failed `step()` call into a `PlcError`.
+    fn step_error(&self, e: &wasmi::Error) -> PlcError {
+        match e.as_trap_code() {
+            Some(TrapCode::OutOfFuel) => PlcError::CycleOverrun {
+                expected_ns: self.cycle_time_ns,
+                actual_ns: 0, // Unknown - fuel exhausted before completion
+            },
+            Some(code) => PlcError::WasmTrap(format!("step() failed: {}", describe_trap(code))),
+            None => PlcError::WasmTrap(format!("step() failed: {e}")),
+        }
+    }
+
+    /// Translate a replacement module and check its exports and layout.
+    fn check_replacement(&self, wasm_bytes: &[u8]) -> PlcResult<Replacement> {
+        self.trusted_keys.check(wasm_bytes)?;
+        let wasm_bytes = precompiled::wasm_module(wasm_bytes)?;
+        let layout = read_layout(wasm_bytes, &self.host_layout)?;
+        let module = Module::new(self.store.engine(), wasm_bytes)
+            .map_err(|e| PlcError::Config(format!("Failed to translate new module: {e}")))?;
+        let symbols = SymbolTable::from_wasm(wasm_bytes)?;
+
+        let required = ["step", "memory"]
+            .into_iter()
+            .chain(self.entry_points.iter().map(String::as_str));
+        for name in required {
+            if !module.exports().any(|e| e.name() == name) {
+                return Err(PlcError::Config(format!(
+                    "New module missing required '{name}' export"
+                )));
+            }
+        }
+
+        Ok(Replacement {
+            module,
+            layout,
+            symbols,
+            steps: read_step_table(wasm_bytes),
+        })
+    }
+
+    /// Plan how the current state carries over to `replacement`.
+    fn migration_plan(&self, replacement: &Replacement) -> PlcResult<MigrationPlan> {
+        let memory_len = self
+            .memory
+            .map_or(0, |memory| memory.data(&self.store).len());
+        plan_migration(
+            StateShape {
+                layout: self.layout,
+                symbols: &self.symbols,
+                steps: &self.sfc_steps,
+            },
+            memory_len,
+            StateShape {
+                layout: replacement.layout,
+                symbols: &replacement.symbols,
+                steps: &replacement.steps,
+            },
+        )
+    }
+}
+
+impl LogicEngine for InterpreterHost {
+    fn init(&mut self) -> PlcResult<()> {
+        let Some(module) = self.module.take() else {
+            return Err(PlcError::Config("No Wasm module loaded".into()));
+        };
+
+        if self.instance.is_none() {
+            let instantiated = self.instantiate_module(&module);
+            self.module = Some(module);
+            self.install(instantiated?);
+        } else {
+            self.module = Some(module);
+        }
+
+        if let Some(init_fn) = self.init_fn {
+            self.ensure_fuel()?;
+            init_fn
+                .call(&mut self.store, ())
+                .map_err(|e| PlcError::WasmTrap(format!("init() failed: {e}")))?;
+        }
+
+        // Write initial system info
+        let layout = self.process_image_layout();
+        let sys_info = WasmSystemInfo {
+            cycle_time_ns: self.cycle_time_ns,
+            flags: WasmSystemInfo::FLAG_FIRST_CYCLE,
+            cycle_count: 0,
+            fault_code: 0,
+        };
+        if let Some(memory) = self.memory {
+            write_system_info_for(memory.data_mut(&mut self.store), &sys_info, &layout);
+        }
+
+        self.initialized = true;
+        info!("InterpreterHost initialized");
+
+        Ok(())
+    }
+
+    fn step(&mut self, inputs: &ProcessData) -> PlcResult<&ProcessData> {
+        let step_fn = self
+            .step_fn
+            .ok_or_else(|| PlcError::Fault("No step function".into()));
+        self.run_cycle(step_fn, inputs)
+    }
+
+    fn step_entry(&mut self, entry: &str, inputs: &ProcessData) -> PlcResult<&ProcessData> {
+        if entry == "step" {
+            return self.step(inputs);
+        }
+        let entry_fn = self
+            .entry_fns
+            .get(entry)
+            .copied()
+            .ok_or_else(|| PlcError::Fault(format!("No entry point '{entry}'")));
+        self.run_cycle(entry_fn, inputs)
+    }
+
+    fn fault(&mut self) -> PlcResult<()> {
+        warn!("Entering fault mode");
+
+        if let Some(fault_fn) = self.fault_fn {
+            // Best effort - we're already in fault mode, don't fail on fuel error
+            let _ = self.ensure_fuel();
+            fault_fn
+                .call(&mut self.store, ())
+                .map_err(|e| PlcError::WasmTrap(format!("fault() failed: {e}")))?;
+        }
+
+        // Zero all outputs in memory and set the fault flag
+        let layout = self.process_image_layout();
+        let sys_info = WasmSystemInfo {
+            cycle_time_ns: self.cycle_time_ns,
+            flags: WasmSystemInfo::FLAG_FAULT_MODE,
+            cycle_count: self.store.data().host.cycle_count,
+            fault_code: 1, // Generic fault
+        };
+        if let Some(memory) = self.memory {
+            let data = memory.data_mut(&mut self.store);
+            let regions = [
+                (layout.do_offset(), layout.do_words() * 4),
+                (layout.ao_offset(), layout.ao_channels * 2),
+            ];
+            for (offset, len) in regions {
+                let range = offset as usize..(offset + len) as usize;
+                if let Some(outputs) = data.get_mut(range) {
+                    outputs.fill(0);
+                }
+            }
+            write_system_info_for(data, &sys_info, &layout);
+        }
+
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.initialized && self.instance.is_some()
+    }
+
+    fn reload_module(&mut self, wasm_bytes: &[u8], preserve_memory: bool) -> PlcResult<()> {
+        info!(
+            preserve_memory,
+            bytes_len = wasm_bytes.len(),
+            "Hot-reloading Wasm module"
+        );
+
+        // Check the new module and plan the migration before touching
+        // current state
+        let replacement = self.check_replacement(wasm_bytes)?;
+        let plan = match self.memory {
+            Some(_) if preserve_memory => Some(self.migration_plan(&replacement)?),
+            _ => None,
+        };
+        let old_memory_data = plan
+            .as_ref()
+            .and(self.memory)
+            .map(|memory| memory.data(&self.store).to_vec());
+        let saved_cycle_count = self.store.data().host.cycle_count;
+
+        // Instantiate the new module BEFORE clearing old state, so a
+        // failure leaves the old module active
+        let instantiated = self.instantiate_module(&replacement.module)?;
+
+        // Migrate into a copy of the new memory before committing
+        let migrated = match (&plan, &old_memory_data) {
+            (Some(plan), Some(old_data)) => {
+                log_migration(plan);
+                let mut data = instantiated.memory.data(&self.store).to_vec();
+                plan.apply(&self.symbols, old_data, &replacement.symbols, &mut data)?;
+                Some(data)
+            }
+            _ => None,
+        };
+
+        // Commit
+        let new_memory = instantiated.memory;
+        self.install(instantiated);
+        self.module = Some(replacement.module);
+        self.sfc_steps = replacement.steps;
+        self.symbols = replacement.symbols;
+        self.layout = replacement.layout;
+        self.store.data_mut().host.layout = self.process_image_layout();
+
+        if let Some(data) = migrated {
+            new_memory.data_mut(&mut self.store).copy_from_slice(&data);
+            debug!(new_size = data.len(), "Memory state migrated");
+        } else if let Some(init_fn) = self.init_fn {
+            // Call init on new module (either couldn't preserve memory or not requested)
+            self.ensure_fuel()?;
+            init_fn
+                .call(&mut self.store, ())
+                .map_err(|e| PlcError::WasmTrap(format!("init() failed after reload: {e}")))?;
+        }
+
+        let state = &mut self.store.data_mut().host;
+        state.cycle_count = saved_cycle_count;
+        state.first_cycle = false;
+
+        info!(cycle_count = saved_cycle_count, "Hot-reload complete");
+
+        Ok(())
+    }
+
+    fn plan_reload(&self, wasm_bytes: &[u8]) -> PlcResult<MigrationPlan> {
+        if self.memory.is_none() {
+            return Err(PlcError::Config("No module loaded".into()));
+        }
+        let replacement = self.check_replacement(wasm_bytes)?;
+        self.migration_plan(&replacement)
+    }
+
+    fn supports_hot_reload(&self) -> bool {
+        true
+    }
+
+    fn exports(&self) -> Vec<String> {
+        self.module
+            .as_ref()
+            .map(|m| m.exports().map(|e| e.name().to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    fn symbols(&self) -> Option<&SymbolTable> {
+        Some(&self.symbols)
+    }
+
+    fn read_variable(&self, name: &str) -> PlcResult<VarValue> {
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        self.symbols.read(memory.data(&self.store), name)
+    }
+
+    fn write_variable(&mut self, name: &str, value: VarValue) -> PlcResult<()> {
+        let memory = self
+            .memory
+            .ok_or_else(|| PlcError::Fault("No memory available".into()))?;
+        self.symbols
+            .write(memory.data_mut(&mut self.store), name, value)
+    }
+
+    fn system_info(&self) -> Option<WasmSystemInfo> {
+        Some(self.cycle_system_info())
+    }
+
+    fn memory_snapshot(&self) -> Option<Vec<u8>> {
+        self.memory.map(|memory| memory.data(&self.store).to_vec())
+    }
+
+    fn memory(&self) -> Option<&[u8]> {
+        self.memory.map(|memory| memory.data(&self.store))
+    }
+
+    fn restore_state(&mut self, memory: &[u8], cycle_count: u64) -> PlcResult<()> {
+        self.restore_memory(memory)?;
+        self.set_cycle_state(cycle_count, false);
+        Ok(())
+    }
+
+    fn active_steps(&self) -> Vec<String> {
+        let Some(memory) = self.memory else {
+            return Vec::new();
+        };
+        let data = memory.data(&self.store);
+        self.sfc_steps
+            .iter()
+            .filter(|step| {
+                data.get(step.offset as usize)
+                    .is_some_and(|&flag| flag != 0)
+            })
+            .map(|step| step.name.clone())
+            .collect()
+    }
+}
+
+/// A newly instantiated module and its exports, not yet active.
+struct Instantiated {
+    instance: Instance,
+    memory: Memory,
+    step_fn: TypedFunc<(), ()>,
+    entry_fns: HashMap<String, TypedFunc<(), ()>>,
+    init_fn: Option<TypedFunc<(), ()>>,
+    fault_fn: Option<TypedFunc<(), ()>>,
+}
+
+/// A translated replacement module and its metadata, checked but not yet
+/// instantiated.
+struct Replacement {
+    module: Module,
+    layout: Option<ProcessImageLayout>,
+    symbols: SymbolTable,
+    steps: Vec<StepFlag>,
+}
+
+/// Describe a trap in terms of the PLC program rather than the Wasm engine,
+/// in the same words as the Wasmtime host.
+fn describe_trap(code: TrapCode) -> String {
+    match code {
+        TrapCode::IntegerDivisionByZero => "division by zero".into(),
+        TrapCode::IntegerOverflow => "integer overflow".into(),
+        TrapCode::BadConversionToInteger => "invalid conversion to integer".into(),
+        TrapCode::MemoryOutOfBounds => "memory access out of bounds".into(),
+        TrapCode::StackOverflow => "call stack overflow".into(),
+        TrapCode::UnreachableCodeReached => "unreachable code reached".into(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::SigningKey;
+    use crate::wasm_host::WasmtimeHost;
+
+    const COUNTER_WAT: &str = r#"
+        (module
+            (import "plc" "read_di" (func $read_di (param i32) (result i32)))
+            (import "plc" "write_do" (func $write_do (param i32 i32)))
+            (import "plc" "write_ao" (func $write_ao (param i32 i32)))
+            (import "plc" "get_cycle_count" (func $get_cycle_count (result i64)))
+
+            (memory (export "memory") 1)
+
+            (func (export "step")
+                ;; Count cycles at 0x100, mirror DI 0 and the count to outputs
+                (i32.store (i32.const 0x100)
+                    (i32.add (i32.load (i32.const 0x100)) (i32.const 1)))
+                (call $write_do (i32.const 0) (call $read_di (i32.const 0)))
+                (call $write_ao (i32.const 0)
+                    (i32.wrap_i64 (call $get_cycle_count)))
+            )
+
+            (func (export "fault")
+                (call $write_do (i32.const 0) (i32.const 0))
+            )
+        )
+    "#;
+
+    const LOOP_WAT: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "step")
+                (loop $forever (br $forever))
+            )
+        )
+    "#;
+
+    fn host_with(wat: &str) -> InterpreterHost {
+        let mut host = InterpreterHost::new(Duration::from_millis(1)).unwrap();
+        host.load_wat(wat).unwrap();
+        host.init().unwrap();
+        host
+    }
+
+    #[test]
+    fn test_step_matches_wasmtime() {
+        let mut interp = host_with(COUNTER_WAT);
+        let mut jit = WasmtimeHost::new(Duration::from_millis(1)).unwrap();
+        jit.load_wat(COUNTER_WAT).unwrap();
+        jit.init().unwrap();
+
+        let mut inputs = ProcessData::default();
+        for cycle in 0..10u16 {
+            inputs.digital_inputs_mut()[0] = u32::from(cycle % 3 == 0);
+            let expected = jit.step(&inputs).unwrap().clone();
+            assert_eq!(interp.step(&inputs).unwrap(), &expected, "cycle {cycle}");
+        }
+        assert_eq!(interp.memory_snapshot(), jit.memory_snapshot());
+        let cycle_count = |info: Option<WasmSystemInfo>| info.map(|info| info.cycle_count);
+        assert_eq!(
+            cycle_count(interp.system_info()),
+            cycle_count(jit.system_info())
+        );
+    }
+
+    #[test]
+    fn test_fuel_exhaustion_is_overrun() {
+        let mut host = host_with(LOOP_WAT);
+        let err = host.step(&ProcessData::default()).unwrap_err();
+        assert!(matches!(err, PlcError::CycleOverrun { .. }), "{err}");
+    }
+
+    #[test]
+    fn test_fault_zeroes_outputs() {
+        let mut host = host_with(COUNTER_WAT);
+        let mut inputs = ProcessData::default();
+        inputs.digital_inputs_mut()[0] = 1;
+        host.step(&inputs).unwrap();
+        host.fault().unwrap();
+
+        let data = host.memory_snapshot().unwrap();
+        let layout = ProcessImageLayout::CURRENT;
+        let do_offset = layout.do_offset() as usize;
+        assert_eq!(&data[do_offset..do_offset + 4], &[0; 4]);
+    }
+
+    #[test]
+    fn test_hot_reload_preserves_memory() {
+        let mut host = host_with(COUNTER_WAT);
+        assert!(host.supports_hot_reload());
+        for _ in 0..3 {
+            host.step(&ProcessData::default()).unwrap();
+        }
+
+        let wasm = wat::parse_str(COUNTER_WAT).unwrap();
+        host.reload_module(&wasm, true).unwrap();
+        host.step(&ProcessData::default()).unwrap();
+
+        let data = host.memory_snapshot().unwrap();
+        assert_eq!(&data[0x100..0x104], &4u32.to_le_bytes());
+        assert_eq!(host.system_info().unwrap().cycle_count, 4);
+    }
+
+    #[test]
+    fn test_hot_reload_rejects_invalid_module() {
+        let mut host = host_with(COUNTER_WAT);
+        let no_step = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        assert!(host.reload_module(&no_step, true).is_err());
+        assert!(host.reload_module(b"not wasm", true).is_err());
+
+        // The old module keeps running
+        host.step(&ProcessData::default()).unwrap();
+    }
+
+    #[test]
+    fn test_loads_precompiled_artifact() {
+        let wasm = wat::parse_str(COUNTER_WAT).unwrap();
+        let artifact = precompiled::precompile(&wasm, &WasmtimeConfig::default(), None).unwrap();
+
+        let mut host = InterpreterHost::new(Duration::from_millis(1)).unwrap();
+        host.load_module(&artifact).unwrap();
+        host.init().unwrap();
+        host.step(&ProcessData::default()).unwrap();
+        host.reload_module(&artifact, true).unwrap();
+        host.step(&ProcessData::default()).unwrap();
+
+        let data = host.memory_snapshot().unwrap();
+        assert_eq!(&data[0x100..0x104], &2u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_trusted_keys_require_signed_modules() {
+        let key = SigningKey::generate().unwrap();
+        let config = WasmtimeConfig {
+            trusted_keys: vec![key.public_key_hex()],
+            ..WasmtimeConfig::default()
+        };
+        let wasm = wat::parse_str(COUNTER_WAT).unwrap();
+        let signed = key.sign(&wasm).unwrap();
+
+        let mut host = InterpreterHost::with_config(Duration::from_millis(1), config).unwrap();
+        assert!(host.load_module(&wasm).is_err());
+        host.load_module(&signed).unwrap();
+        host.init().unwrap();
+        assert!(matches!(
+            host.reload_module(&wasm, true),
+            Err(PlcError::UntrustedModule(_))
+        ));
+        host.reload_module(&signed, true).unwrap();
+    }
+
+    #[test]
+    fn test_memory_limit_enforced() {
+        let config = WasmtimeConfig {
+            max_memory_bytes: WASM_PAGE_SIZE,
+            ..WasmtimeConfig::default()
+        };
+        let mut host = InterpreterHost::with_config(Duration::from_millis(1), config).unwrap();
+        host.load_wat(r#"(module (memory (export "memory") 2) (func (export "step")))"#)
+            .unwrap();
+        assert!(host.init().is_err());
+    }
+}
@@ -30,10 +30,17 @@
 //! 0x005C    12      Reserved (zeroed)
 //! ```
 //!
-//! The host runtime copies I/O data into these fixed offsets before
-//! calling the Wasm step() function, and reads outputs after.
+//! This is the layout with the default dimensions. With a configured
+//! process image (`[process_image]` in the runtime configuration) the
+//! regions keep their order but grow or shrink, and their offsets are taken
+//! from the module's [`ProcessImageLayout`]; the `WASM_*` constants describe
+//! the default layout only.
+//!
+//! The host runtime copies I/O data into these offsets before calling the
+//! Wasm step() function, and reads outputs after.
 
 use crate::io_image::ProcessData;
+use plc_common::abi::ProcessImageLayout;
 
 /// Base offset for digital inputs in Wasm memory.
 pub const WASM_DI_OFFSET: u32 = 0x0000;
@@ -64,8 +71,9 @@ pub const AO_CHANNELS: usize = 16;
 
 /// System info structure in Wasm memory (32 bytes total).
 ///
-/// This structure is written to Wasm memory at `WASM_SYSINFO_OFFSET` and
-/// provides runtime information to the PLC program.
+/// This structure is written to Wasm memory at the layout's system info
+/// offset (`WASM_SYSINFO_OFFSET` by default) and provides runtime
+/// information to the PLC program.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Default)]
 pub struct WasmSystemInfo {
@@ -89,54 +97,52 @@ impl WasmSystemInfo {
 
 /// Copy process data into Wasm linear memory.
 ///
-/// # Safety
-///
-/// The caller must ensure that:
-/// - `memory` points to valid Wasm linear memory
-/// - `memory` has at least `WASM_IO_REGION_SIZE` bytes available
+/// Regions are copied up to the smaller of the layout's and the data's
+/// dimensions; values that do not fit into `memory` are skipped.
 #[inline]
-pub fn copy_inputs_to_wasm(memory: &mut [u8], data: &ProcessData) {
-    // Digital inputs
-    let di_offset = WASM_DI_OFFSET as usize;
-    if memory.len() >= di_offset + 4 {
-        memory[di_offset..di_offset + 4].copy_from_slice(&data.digital_inputs[0].to_le_bytes());
+pub fn copy_inputs_to_wasm(memory: &mut [u8], data: &ProcessData, layout: &ProcessImageLayout) {
+    let words = data
+        .digital_inputs()
+        .iter()
+        .take(layout.di_words() as usize);
+    for (i, &word) in words.enumerate() {
+        let offset = layout.di_offset() as usize + i * 4;
+        if let Some(target) = memory.get_mut(offset..offset + 4) {
+            target.copy_from_slice(&word.to_le_bytes());
+        }
     }
 
-    // Analog inputs (16 × i16 = 32 bytes)
-    let ai_offset = WASM_AI_OFFSET as usize;
-    for (i, &value) in data.analog_inputs.iter().enumerate() {
-        let offset = ai_offset + i * 2;
-        if memory.len() >= offset + 2 {
-            memory[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    let channels = data
+        .analog_inputs()
+        .iter()
+        .take(layout.ai_channels as usize);
+    for (i, &value) in channels.enumerate() {
+        let offset = layout.ai_offset() as usize + i * 2;
+        if let Some(target) = memory.get_mut(offset..offset + 2) {
+            target.copy_from_slice(&value.to_le_bytes());
         }
     }
 }
 
 /// Copy process outputs from Wasm linear memory.
 ///
-/// # Safety
-///
-/// The caller must ensure that:
-/// - `memory` points to valid Wasm linear memory
-/// - `memory` has at least `WASM_IO_REGION_SIZE` bytes available
+/// Like [`copy_inputs_to_wasm`], outputs beyond the layout's dimensions are
+/// left unchanged.
 #[inline]
-pub fn copy_outputs_from_wasm(memory: &[u8], data: &mut ProcessData) {
-    // Digital outputs
-    let do_offset = WASM_DO_OFFSET as usize;
-    if memory.len() >= do_offset + 4 {
-        data.digital_outputs[0] = u32::from_le_bytes(
-            memory[do_offset..do_offset + 4]
-                .try_into()
-                .unwrap_or([0; 4]),
-        );
+pub fn copy_outputs_from_wasm(memory: &[u8], data: &mut ProcessData, layout: &ProcessImageLayout) {
+    let words = data.digital_outputs_mut().iter_mut();
+    for (i, word) in words.take(layout.do_words() as usize).enumerate() {
+        let offset = layout.do_offset() as usize + i * 4;
+        if let Some(bytes) = memory.get(offset..offset + 4) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap_or([0; 4]));
+        }
     }
 
-    // Analog outputs (16 × i16 = 32 bytes)
-    let ao_offset = WASM_AO_OFFSET as usize;
-    for (i, value) in data.analog_outputs.iter_mut().enumerate() {
-        let offset = ao_offset + i * 2;
-        if memory.len() >= offset + 2 {
-            *value = i16::from_le_bytes(memory[offset..offset + 2].try_into().unwrap_or([0; 2]));
+    let channels = data.analog_outputs_mut().iter_mut();
+    for (i, value) in channels.take(layout.ao_channels as usize).enumerate() {
+        let offset = layout.ao_offset() as usize + i * 2;
+        if let Some(bytes) = memory.get(offset..offset + 2) {
+            *value = i16::from_le_bytes(bytes.try_into().unwrap_or([0; 2]));
         }
     }
 }
@@ -148,8 +154,21 @@ pub fn copy_outputs_from_wasm(memory: &[u8], data: &mut ProcessData) {
 /// the get_cycle_time() host function.
 #[inline]
 pub fn write_system_info(memory: &mut [u8], info: &WasmSystemInfo) {
-    let offset = WASM_SYSINFO_OFFSET as usize;
-    let size = WASM_SYSINFO_SIZE as usize;
+    write_system_info_for(memory, info, &ProcessImageLayout::CURRENT);
+}
+
+/// Write system info at the offset of `layout`.
+///
+/// Only the first `layout.sysinfo_size` bytes are written: an ABI 1.0 module
+/// knows only cycle time and flags, and owns the memory from 0x50 onwards.
+#[inline]
+pub fn write_system_info_for(
+    memory: &mut [u8],
+    info: &WasmSystemInfo,
+    layout: &ProcessImageLayout,
+) {
+    let offset = layout.sysinfo_offset() as usize;
+    let size = layout.sysinfo_size.min(WASM_SYSINFO_SIZE) as usize;
 
     if memory.len() >= offset + size {
         // Layout (32 bytes total):
@@ -158,85 +177,157 @@ pub fn write_system_info(memory: &mut [u8], info: &WasmSystemInfo) {
         // 0x08: cycle_count (8 bytes, u64)
         // 0x10: fault_code (4 bytes, u32)
         // 0x14: reserved (12 bytes, zeroed)
+        let mut bytes = [0u8; WASM_SYSINFO_SIZE as usize];
 
         // Cap at i32::MAX for consistency with get_cycle_time host function
         let cycle_time_u32 = info.cycle_time_ns.min(i32::MAX as u64) as u32;
-        memory[offset..offset + 4].copy_from_slice(&cycle_time_u32.to_le_bytes());
-        memory[offset + 4..offset + 8].copy_from_slice(&info.flags.to_le_bytes());
-        memory[offset + 8..offset + 16].copy_from_slice(&info.cycle_count.to_le_bytes());
-        memory[offset + 16..offset + 20].copy_from_slice(&info.fault_code.to_le_bytes());
-        // Zero reserved bytes
-        memory[offset + 20..offset + 32].fill(0);
+        bytes[0..4].copy_from_slice(&cycle_time_u32.to_le_bytes());
+        bytes[4..8].copy_from_slice(&info.flags.to_le_bytes());
+        bytes[8..16].copy_from_slice(&info.cycle_count.to_le_bytes());
+        bytes[16..20].copy_from_slice(&info.fault_code.to_le_bytes());
+
+        memory[offset..offset + size].copy_from_slice(&bytes[..size]);
     }
 }
 
 /// Read a digital input bit from memory.
 #[inline]
-pub fn read_di_from_memory(memory: &[u8], bit: u32) -> bool {
-    let offset = WASM_DI_OFFSET as usize;
-    if bit >= 32 || memory.len() < offset + 4 {
+pub fn read_di_from_memory(memory: &[u8], layout: &ProcessImageLayout, bit: u32) -> bool {
+    if bit >= layout.di_bits {
         return false;
     }
-    let word = u32::from_le_bytes(memory[offset..offset + 4].try_into().unwrap_or([0; 4]));
-    (word >> bit) & 1 != 0
+    let offset = (layout.di_offset() + bit / 8) as usize;
+    memory
+        .get(offset)
+        .is_some_and(|&byte| (byte >> (bit % 8)) & 1 != 0)
 }
 
 /// Write a digital output bit to memory.
 #[inline]
-pub fn write_do_to_memory(memory: &mut [u8], bit: u32, value: bool) {
-    let offset = WASM_DO_OFFSET as usize;
-    if bit >= 32 || memory.len() < offset + 4 {
+pub fn write_do_to_memory(memory: &mut [u8], layout: &ProcessImageLayout, bit: u32, value: bool) {
+    if bit >= layout.do_bits {
         return;
     }
-    let mut word = u32::from_le_bytes(memory[offset..offset + 4].try_into().unwrap_or([0; 4]));
-    if value {
-        word |= 1 << bit;
-    } else {
-        word &= !(1 << bit);
+    let offset = (layout.do_offset() + bit / 8) as usize;
+    if let Some(byte) = memory.get_mut(offset) {
+        if value {
+            *byte |= 1 << (bit % 8);
+        } else {
+            *byte &= !(1 << (bit % 8));
+        }
     }
-    memory[offset..offset + 4].copy_from_slice(&word.to_le_bytes());
 }
 
 /// Read an analog input from memory.
 #[inline]
-pub fn read_ai_from_memory(memory: &[u8], channel: u32) -> i16 {
-    if channel >= AI_CHANNELS as u32 {
+pub fn read_ai_from_memory(memory: &[u8], layout: &ProcessImageLayout, channel: u32) -> i16 {
+    if channel >= layout.ai_channels {
         return 0;
     }
-    let offset = WASM_AI_OFFSET as usize + (channel as usize) * 2;
-    if memory.len() < offset + 2 {
-        return 0;
-    }
-    i16::from_le_bytes(memory[offset..offset + 2].try_into().unwrap_or([0; 2]))
+    let offset = (layout.ai_offset() + channel * 2) as usize;
+    memory.get(offset..offset + 2).map_or(0, |bytes| {
+        i16::from_le_bytes(bytes.try_into().unwrap_or([0; 2]))
+    })
 }
 
 /// Write an analog output to memory.
 #[inline]
-pub fn write_ao_to_memory(memory: &mut [u8], channel: u32, value: i16) {
-    if channel >= AO_CHANNELS as u32 {
+pub fn write_ao_to_memory(
+    memory: &mut [u8],
+    layout: &ProcessImageLayout,
+    channel: u32,
+    value: i16,
+) {
+    if channel >= layout.ao_channels {
         return;
     }
-    let offset = WASM_AO_OFFSET as usize + (channel as usize) * 2;
-    if memory.len() < offset + 2 {
-        return;
+    let offset = (layout.ao_offset() + channel * 2) as usize;
+    if let Some(target) = memory.get_mut(offset..offset + 2) {
+        target.copy_from_slice(&value.to_le_bytes());
     }
-    memory[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
 }
 
-/// Get the cycle time from system info.
+/// Byte range of `len` bytes at `offset`, if it lies inside the process
+/// image (inputs, outputs and system info).
 #[inline]
-pub fn read_cycle_time_from_memory(memory: &[u8]) -> u32 {
-    let offset = WASM_SYSINFO_OFFSET as usize;
-    if memory.len() < offset + 4 {
-        return 0;
+fn image_range(
+    layout: &ProcessImageLayout,
+    offset: u32,
+    len: usize,
+) -> Option<std::ops::Range<usize>> {
+    let start = offset as usize;
+    let end = start.checked_add(len)?;
+    (end <= layout.user_data_offset as usize).then_some(start..end)
+}
+
+/// Whether a byte range lies entirely inside one output region.
+#[inline]
+fn is_output_range(layout: &ProcessImageLayout, range: &std::ops::Range<usize>) -> bool {
+    let regions = [
+        (layout.do_offset(), layout.do_words() * 4),
+        (layout.ao_offset(), layout.ao_channels * 2),
+    ];
+    regions.iter().any(|&(start, size)| {
+        let (start, size) = (start as usize, size as usize);
+        range.start >= start && range.end <= start + size
+    })
+}
+
+/// Read `N` bytes at a process-image byte offset.
+///
+/// Returns `None` if the range is outside the process image or memory.
+#[inline]
+pub fn read_image_bytes<const N: usize>(
+    memory: &[u8],
+    layout: &ProcessImageLayout,
+    offset: u32,
+) -> Option<[u8; N]> {
+    let range = image_range(layout, offset, N)?;
+    memory.get(range)?.try_into().ok()
+}
+
+/// Write bytes at a process-image byte offset.
+///
+/// Only the output regions (digital and analog outputs) are writable; a
+/// write that is not entirely inside one of them is ignored and returns
+/// `false`.
+#[inline]
+pub fn write_image_bytes(
+    memory: &mut [u8],
+    layout: &ProcessImageLayout,
+    offset: u32,
+    bytes: &[u8],
+) -> bool {
+    let Some(range) = image_range(layout, offset, bytes.len()) else {
+        return false;
+    };
+    if !is_output_range(layout, &range) {
+        return false;
+    }
+    match memory.get_mut(range) {
+        Some(target) => {
+            target.copy_from_slice(bytes);
+            true
+        }
+        None => false,
     }
-    u32::from_le_bytes(memory[offset..offset + 4].try_into().unwrap_or([0; 4]))
+}
+
+/// Get the cycle time from system info.
+#[inline]
+pub fn read_cycle_time_from_memory(memory: &[u8], layout: &ProcessImageLayout) -> u32 {
+    let offset = layout.sysinfo_offset() as usize;
+    memory.get(offset..offset + 4).map_or(0, |bytes| {
+        u32::from_le_bytes(bytes.try_into().unwrap_or([0; 4]))
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const LAYOUT: ProcessImageLayout = ProcessImageLayout::CURRENT;
+
     #[test]
     fn test_memory_layout_constants() {
         // Verify offsets don't overlap
@@ -249,17 +340,133 @@ mod tests {
         // Verify threat model alignment: sysinfo is 32 bytes
         assert_eq!(WASM_SYSINFO_SIZE, 32);
         assert_eq!(WASM_USER_DATA_OFFSET, 0x0068);
+
+        // The layout compiled modules declare must match these offsets
+        let layout = ProcessImageLayout::CURRENT;
+        assert_eq!(layout.di_bits, DI_WORDS as u32 * 32);
+        assert_eq!(layout.do_bits, DO_WORDS as u32 * 32);
+        assert_eq!(layout.ai_channels, AI_CHANNELS as u32);
+        assert_eq!(layout.ao_channels, AO_CHANNELS as u32);
+        assert_eq!(layout.sysinfo_size, WASM_SYSINFO_SIZE);
+        assert_eq!(layout.user_data_offset, WASM_USER_DATA_OFFSET);
+        assert_eq!(layout.do_offset(), WASM_DO_OFFSET);
+        assert_eq!(layout.ai_offset(), WASM_AI_OFFSET);
+        assert_eq!(layout.ao_offset(), WASM_AO_OFFSET);
+        assert_eq!(layout.sysinfo_offset(), WASM_SYSINFO_OFFSET);
+    }
+
+    #[test]
+    fn test_configured_layout() {
+        use plc_common::config::ProcessImageConfig;
+
+        let config = ProcessImageConfig {
+            digital_inputs: 400,
+            digital_outputs: 64,
+            analog_inputs: 2,
+            analog_outputs: 2,
+        };
+        let layout = ProcessImageLayout::for_config(&config);
+        let mut memory = vec![0u8; 512];
+        let mut data = ProcessData::new(&config);
+        data.digital_inputs_mut()[12] = 0x8000;
+        data.analog_inputs_mut()[1] = -7;
+        copy_inputs_to_wasm(&mut memory, &data, &layout);
+
+        assert!(read_di_from_memory(&memory, &layout, 399));
+        assert!(!read_di_from_memory(&memory, &layout, 416));
+        assert_eq!(read_ai_from_memory(&memory, &layout, 1), -7);
+
+        write_do_to_memory(&mut memory, &layout, 63, true);
+        write_ao_to_memory(&mut memory, &layout, 1, 300);
+        // Outside the configured image: ignored
+        write_do_to_memory(&mut memory, &layout, 64, true);
+        write_ao_to_memory(&mut memory, &layout, 2, 1);
+        assert!(write_image_bytes(
+            &mut memory,
+            &layout,
+            layout.do_offset(),
+            &[1]
+        ));
+        assert!(!write_image_bytes(
+            &mut memory,
+            &layout,
+            layout.ai_offset(),
+            &[1]
+        ));
+
+        copy_outputs_from_wasm(&memory, &mut data, &layout);
+        assert_eq!(data.digital_outputs(), &[1, 0x8000_0000]);
+        assert_eq!(data.analog_outputs(), &[0, 300]);
+
+        // A module built for the default layout on a larger host image
+        let mut memory = vec![0u8; 256];
+        copy_inputs_to_wasm(&mut memory, &data, &LAYOUT);
+        assert_eq!(read_ai_from_memory(&memory, &LAYOUT, 1), -7);
+        assert_eq!(&memory[0x04..0x08], &[0; 4]);
+    }
+
+    #[test]
+    fn test_image_byte_access() {
+        let mut memory = vec![0u8; 256];
+        memory[0..4].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+
+        assert_eq!(
+            read_image_bytes::<4>(&memory, &LAYOUT, 0),
+            Some(0x1234_5678u32.to_le_bytes())
+        );
+        assert_eq!(read_image_bytes::<1>(&memory, &LAYOUT, 3), Some([0x12]));
+        // System info is readable, user data is not
+        assert!(read_image_bytes::<4>(&memory, &LAYOUT, 0x64).is_some());
+        assert!(read_image_bytes::<4>(&memory, &LAYOUT, 0x65).is_none());
+        assert!(read_image_bytes::<1>(&memory, &LAYOUT, u32::MAX).is_none());
+
+        // Writes must stay inside one output region
+        assert!(write_image_bytes(&mut memory, &LAYOUT, 0x04, &[1, 2, 3, 4]));
+        assert!(write_image_bytes(
+            &mut memory,
+            &LAYOUT,
+            0x44,
+            &1.5f32.to_le_bytes()
+        ));
+        assert_eq!(&memory[0x44..0x48], &1.5f32.to_le_bytes());
+        assert!(!write_image_bytes(&mut memory, &LAYOUT, 0x00, &[0xFF]));
+        assert!(!write_image_bytes(&mut memory, &LAYOUT, 0x06, &[0; 4]));
+        assert!(!write_image_bytes(&mut memory, &LAYOUT, 0x46, &[0; 4]));
+        assert!(!write_image_bytes(&mut memory, &LAYOUT, 0x48, &[0xFF]));
+        assert_eq!(memory[0], 0x78);
+        assert_eq!(&memory[0x04..0x08], &[1, 2, 3, 4]);
+
+        // Memory smaller than the image
+        assert!(read_image_bytes::<4>(&memory[..2], &LAYOUT, 0).is_none());
+        assert!(!write_image_bytes(&mut memory[..6], &LAYOUT, 0x04, &[0; 4]));
+    }
+
+    #[test]
+    fn test_system_info_for_v1_0_layout() {
+        let mut memory = vec![0xAAu8; 256];
+        let info = WasmSystemInfo {
+            cycle_time_ns: 10_000_000,
+            flags: WasmSystemInfo::FLAG_FIRST_CYCLE,
+            cycle_count: 42,
+            fault_code: 0,
+        };
+        write_system_info_for(&mut memory, &info, &ProcessImageLayout::V1_0);
+
+        assert_eq!(read_cycle_time_from_memory(&memory, &LAYOUT), 10_000_000);
+        assert_eq!(&memory[0x4C..0x50], &1u32.to_le_bytes());
+        // User data of a 1.0 module starts at 0x50 and is left alone
+        assert!(memory[0x50..0x68].iter().all(|&b| b == 0xAA));
     }
 
     #[test]
     fn test_copy_inputs() {
         let mut memory = vec![0u8; 256];
         let mut data = ProcessData::default();
-        data.digital_inputs[0] = 0xDEAD_BEEF;
-        data.analog_inputs[0] = 1000;
-        data.analog_inputs[15] = -500;
+        data.digital_inputs_mut()[0] = 0xDEAD_BEEF;
+        data.analog_inputs_mut()[0] = 1000;
+        data.analog_inputs_mut()[15] = -500;
 
-        copy_inputs_to_wasm(&mut memory, &data);
+        copy_inputs_to_wasm(&mut memory, &data, &LAYOUT);
 
         // Check digital inputs
         let di = u32::from_le_bytes(memory[0..4].try_into().unwrap());
@@ -282,11 +489,11 @@ mod tests {
         memory[70..72].copy_from_slice(&(-1000i16).to_le_bytes());
 
         let mut data = ProcessData::default();
-        copy_outputs_from_wasm(&memory, &mut data);
+        copy_outputs_from_wasm(&memory, &mut data, &LAYOUT);
 
-        assert_eq!(data.digital_outputs[0], 0xCAFE_BABE);
-        assert_eq!(data.analog_outputs[0], 2000);
-        assert_eq!(data.analog_outputs[15], -1000);
+        assert_eq!(data.digital_outputs()[0], 0xCAFE_BABE);
+        assert_eq!(data.analog_outputs()[0], 2000);
+        assert_eq!(data.analog_outputs()[15], -1000);
     }
 
     #[test]
@@ -294,18 +501,18 @@ mod tests {
         let mut memory = vec![0u8; 256];
 
         // Write digital output bits
-        write_do_to_memory(&mut memory, 0, true);
-        write_do_to_memory(&mut memory, 7, true);
-        write_do_to_memory(&mut memory, 31, true);
+        write_do_to_memory(&mut memory, &LAYOUT, 0, true);
+        write_do_to_memory(&mut memory, &LAYOUT, 7, true);
+        write_do_to_memory(&mut memory, &LAYOUT, 31, true);
 
         let do_word = u32::from_le_bytes(memory[4..8].try_into().unwrap());
         assert_eq!(do_word, 0x8000_0081);
 
         // Set digital inputs and read
         memory[0..4].copy_from_slice(&0x0000_00FFu32.to_le_bytes());
-        assert!(read_di_from_memory(&memory, 0));
-        assert!(read_di_from_memory(&memory, 7));
-        assert!(!read_di_from_memory(&memory, 8));
+        assert!(read_di_from_memory(&memory, &LAYOUT, 0));
+        assert!(read_di_from_memory(&memory, &LAYOUT, 7));
+        assert!(!read_di_from_memory(&memory, &LAYOUT, 8));
     }
 
     #[test]
@@ -313,8 +520,8 @@ mod tests {
         let mut memory = vec![0u8; 256];
 
         // Write analog outputs
-        write_ao_to_memory(&mut memory, 0, 4095);
-        write_ao_to_memory(&mut memory, 5, -2048);
+        write_ao_to_memory(&mut memory, &LAYOUT, 0, 4095);
+        write_ao_to_memory(&mut memory, &LAYOUT, 5, -2048);
 
         // Verify in memory
         let ao0 = i16::from_le_bytes(memory[40..42].try_into().unwrap());
@@ -324,7 +531,7 @@ mod tests {
 
         // Write analog inputs to memory and read
         memory[8..10].copy_from_slice(&1234i16.to_le_bytes());
-        assert_eq!(read_ai_from_memory(&memory, 0), 1234);
+        assert_eq!(read_ai_from_memory(&memory, &LAYOUT, 0), 1234);
     }
 
     #[test]
@@ -340,7 +547,7 @@ mod tests {
         write_system_info(&mut memory, &info);
 
         // Verify cycle time at offset 0x0048 (72)
-        assert_eq!(read_cycle_time_from_memory(&memory), 1_000_000);
+        assert_eq!(read_cycle_time_from_memory(&memory, &LAYOUT), 1_000_000);
 
         // Verify flags at offset 0x004C (76)
         let flags = u32::from_le_bytes(memory[76..80].try_into().unwrap());
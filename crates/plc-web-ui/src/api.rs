@@ -1,12 +1,16 @@
 //! REST API handlers for the web UI.
 
-use crate::state::{FaultRecord, IoSnapshot, MetricsSnapshot, SharedState, StateSnapshot};
+use crate::control::{ControlRequest, ControlSender, ControlValue, DebugRequest, TraceRequest};
+use crate::state::{
+    AuditRecord, DebugSnapshot, FaultRecord, ForceEntry, IoSnapshot, MetricsSnapshot, SharedState,
+    StateSnapshot, TraceExport, TraceSnapshot,
+};
 use axum::{
-    extract::Extension,
-    http::StatusCode,
+    extract::{Extension, Query},
+    http::{header::CONTENT_TYPE, StatusCode},
     response::{IntoResponse, Json},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 /// Health check response.
@@ -78,6 +82,193 @@ pub async fn get_faults(
     Ok(Json(faults))
 }
 
+/// Get the audit trail.
+///
+/// GET /api/audit
+pub async fn get_audit(
+    Extension(state): Extension<Arc<SharedState>>,
+) -> Result<Json<Vec<AuditRecord>>, StatusCode> {
+    let audit = state
+        .audit
+        .read()
+        .map(|a| a.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(audit))
+}
+
+/// Get the active SFC steps.
+///
+/// GET /api/sfc
+pub async fn get_active_steps(
+    Extension(state): Extension<Arc<SharedState>>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    let steps = state
+        .active_steps
+        .read()
+        .map(|s| s.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(steps))
+}
+
+/// Get the active forces.
+///
+/// GET /api/forces
+pub async fn get_forces(
+    Extension(state): Extension<Arc<SharedState>>,
+) -> Result<Json<Vec<ForceEntry>>, StatusCode> {
+    let forces = state
+        .forces
+        .read()
+        .map(|f| f.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(forces))
+}
+
+/// Body of a force request.
+#[derive(Debug, Deserialize)]
+pub struct ForceRequest {
+    /// Address (`%IX0.1`, `%QW2`) or variable (`Main.speed`).
+    pub target: String,
+    /// Forced value.
+    pub value: ControlValue,
+}
+
+/// Force an I/O point or variable.
+///
+/// POST /api/forces
+pub async fn set_force(
+    Extension(state): Extension<Arc<SharedState>>,
+    Extension(control): Extension<ControlSender>,
+    Json(force): Json<ForceRequest>,
+) -> Result<Json<Vec<ForceEntry>>, ApiError> {
+    let request = ControlRequest::Force {
+        target: force.target,
+        value: force.value,
+    };
+    send_control(&control, request).await?;
+    get_forces(Extension(state)).await.map_err(ApiError::from)
+}
+
+/// Query of a force release.
+#[derive(Debug, Deserialize)]
+pub struct UnforceQuery {
+    /// Target to release; all forces are released without one.
+    pub target: Option<String>,
+}
+
+/// Release one force, or all of them.
+///
+/// DELETE /api/forces?target=%25QX0.1
+/// DELETE /api/forces
+pub async fn clear_forces(
+    Extension(state): Extension<Arc<SharedState>>,
+    Extension(control): Extension<ControlSender>,
+    Query(query): Query<UnforceQuery>,
+) -> Result<Json<Vec<ForceEntry>>, ApiError> {
+    let request = match query.target {
+        Some(target) => ControlRequest::Unforce { target },
+        None => ControlRequest::ClearForces,
+    };
+    send_control(&control, request).await?;
+    get_forces(Extension(state)).await.map_err(ApiError::from)
+}
+
+/// Get the debug mode status; `null` outside of DEBUG.
+///
+/// GET /api/debug
+pub async fn get_debug(
+    Extension(state): Extension<Arc<SharedState>>,
+) -> Result<Json<Option<DebugSnapshot>>, StatusCode> {
+    let debug = state
+        .debug
+        .read()
+        .map(|d| d.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(debug))
+}
+
+/// Enter or leave DEBUG, pause, step or run to a breakpoint.
+///
+/// POST /api/debug
+pub async fn debug_command(
+    Extension(state): Extension<Arc<SharedState>>,
+    Extension(control): Extension<ControlSender>,
+    Json(request): Json<DebugRequest>,
+) -> Result<Json<Option<DebugSnapshot>>, ApiError> {
+    send_control(&control, ControlRequest::Debug(request)).await?;
+    get_debug(Extension(state)).await.map_err(ApiError::from)
+}
+
+/// Get the signal trace status; `null` until a trace is armed.
+///
+/// GET /api/trace
+pub async fn get_trace(
+    Extension(state): Extension<Arc<SharedState>>,
+) -> Result<Json<Option<TraceSnapshot>>, StatusCode> {
+    let trace = state
+        .trace
+        .read()
+        .map(|t| t.clone())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(trace))
+}
+
+/// Arm or stop a signal trace.
+///
+/// POST /api/trace
+pub async fn trace_command(
+    Extension(state): Extension<Arc<SharedState>>,
+    Extension(control): Extension<ControlSender>,
+    Json(request): Json<TraceRequest>,
+) -> Result<Json<Option<TraceSnapshot>>, ApiError> {
+    send_control(&control, ControlRequest::Trace(request)).await?;
+    get_trace(Extension(state)).await.map_err(ApiError::from)
+}
+
+/// Download the last finished trace as CSV.
+///
+/// GET /api/trace/csv
+pub async fn get_trace_csv(
+    Extension(state): Extension<Arc<SharedState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace_download(&state, "text/csv; charset=utf-8", |export| &export.csv)
+}
+
+/// Download the last finished trace as a Value Change Dump.
+///
+/// GET /api/trace/vcd
+pub async fn get_trace_vcd(
+    Extension(state): Extension<Arc<SharedState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    trace_download(&state, "text/plain; charset=utf-8", |export| &export.vcd)
+}
+
+fn trace_download(
+    state: &SharedState,
+    content_type: &'static str,
+    format: impl Fn(&TraceExport) -> &String,
+) -> Result<impl IntoResponse, StatusCode> {
+    let export = state
+        .trace_export
+        .read()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let body = export.as_ref().map(format).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(([(CONTENT_TYPE, content_type)], body.clone()))
+}
+
+async fn send_control(control: &ControlSender, request: ControlRequest) -> Result<(), ApiError> {
+    control.send(request).await.map_err(|error| match error {
+        Some(error) => ApiError {
+            error,
+            code: StatusCode::BAD_REQUEST.as_u16(),
+        },
+        None => ApiError {
+            error: "runtime is not accepting commands".into(),
+            code: StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+        },
+    })
+}
+
 /// API error response.
 #[derive(Serialize)]
 pub struct ApiError {
@@ -85,6 +276,15 @@ pub struct ApiError {
     pub code: u16,
 }
 
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        Self {
+            error: status.canonical_reason().unwrap_or("error").into(),
+            code: status.as_u16(),
+        }
+    }
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> axum::response::Response {
         let status = StatusCode::from_u16(self.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
@@ -0,0 +1,204 @@
+//! Commands from the control plane to the runtime.
+//!
+//! Requests that change the runtime (forcing, debugging, tracing) cannot be served
+//! from the shared state: the handler sends a [`ControlCommand`] to the
+//! runtime loop, which applies it at a cycle boundary and replies.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+/// Number of commands that can be queued for the runtime.
+pub(crate) const CONTROL_CHANNEL_CAPACITY: usize = 16;
+
+/// A value written by the control plane: `BOOL`, integer or `REAL`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ControlValue {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Real(f64),
+}
+
+/// A debug mode command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DebugRequest {
+    /// Enter DEBUG; the logic pauses.
+    Enter,
+    /// Leave DEBUG and resume cyclic execution.
+    Exit,
+    /// Pause the logic.
+    Pause,
+    /// Run the logic for a number of cycles.
+    Step {
+        #[serde(default = "one_cycle")]
+        cycles: u64,
+    },
+    /// Run the logic until a condition (`Main.count >= 10`) holds.
+    RunUntil { condition: String },
+}
+
+fn one_cycle() -> u64 {
+    1
+}
+
+/// A signal trace command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum TraceRequest {
+    /// Arm a new trace. Settings left out are taken from the runtime
+    /// configuration.
+    Arm {
+        /// Addresses (`%IX0.1`) or variables (`Main.speed`) to record.
+        #[serde(default)]
+        signals: Vec<String>,
+        /// Trigger, as in the configuration (`rising %IX0.0`).
+        trigger: Option<String>,
+        /// Samples kept from before the trigger.
+        pre_trigger: Option<usize>,
+        /// Samples recorded after the trigger.
+        post_trigger: Option<usize>,
+        /// Record every Nth scan.
+        divider: Option<u32>,
+    },
+    /// Stop the current trace.
+    Stop,
+}
+
+/// A request to the runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlRequest {
+    /// Force a process image point (`%IX0.1`) or variable (`Main.speed`).
+    Force { target: String, value: ControlValue },
+    /// Release the force on a target.
+    Unforce { target: String },
+    /// Release all forces.
+    ClearForces,
+    /// Control debug mode.
+    Debug(DebugRequest),
+    /// Control signal tracing.
+    Trace(TraceRequest),
+}
+
+/// A request with the channel its reply is sent on.
+#[derive(Debug)]
+pub struct ControlCommand {
+    /// What is requested.
+    pub request: ControlRequest,
+    reply: oneshot::Sender<Result<(), String>>,
+}
+
+impl ControlCommand {
+    /// Report the outcome to the waiting handler.
+    pub fn reply(self, result: Result<(), String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Sending side, held by the HTTP handlers.
+#[derive(Debug, Clone)]
+pub struct ControlSender(mpsc::Sender<ControlCommand>);
+
+impl ControlSender {
+    /// Send a request and wait for the runtime to apply it.
+    ///
+    /// Returns `Err(None)` if no runtime is receiving commands.
+    pub async fn send(&self, request: ControlRequest) -> Result<(), Option<String>> {
+        let (reply, response) = oneshot::channel();
+        self.0
+            .send(ControlCommand { request, reply })
+            .await
+            .map_err(|_| None)?;
+        response.await.map_err(|_| None)?.map_err(Some)
+    }
+}
+
+/// Receiving side, polled by the runtime loop.
+#[derive(Debug)]
+pub struct ControlReceiver(mpsc::Receiver<ControlCommand>);
+
+impl ControlReceiver {
+    /// Take the next pending command without blocking.
+    pub fn try_recv(&mut self) -> Option<ControlCommand> {
+        self.0.try_recv().ok()
+    }
+}
+
+/// Create a connected sender and receiver.
+pub fn control_channel() -> (ControlSender, ControlReceiver) {
+    let (tx, rx) = mpsc::channel(CONTROL_CHANNEL_CAPACITY);
+    (ControlSender(tx), ControlReceiver(rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_from_json() {
+        let parse = |s: &str| serde_json::from_str::<ControlValue>(s).unwrap();
+        assert_eq!(parse("true"), ControlValue::Bool(true));
+        assert_eq!(parse("-3"), ControlValue::Int(-3));
+        assert_eq!(parse("18446744073709551615"), ControlValue::UInt(u64::MAX));
+        assert_eq!(parse("1.5"), ControlValue::Real(1.5));
+    }
+
+    #[test]
+    fn test_debug_request_from_json() {
+        let parse = |s: &str| serde_json::from_str::<DebugRequest>(s).unwrap();
+        assert_eq!(parse(r#"{"command": "enter"}"#), DebugRequest::Enter);
+        assert_eq!(
+            parse(r#"{"command": "step"}"#),
+            DebugRequest::Step { cycles: 1 }
+        );
+        assert_eq!(
+            parse(r#"{"command": "run_until", "condition": "Main.done"}"#),
+            DebugRequest::RunUntil {
+                condition: "Main.done".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_trace_request_from_json() {
+        let parse = |s: &str| serde_json::from_str::<TraceRequest>(s).unwrap();
+        assert_eq!(
+            parse(r#"{"command": "arm", "signals": ["%IX0.0"], "trigger": "rising %IX0.0"}"#),
+            TraceRequest::Arm {
+                signals: vec!["%IX0.0".into()],
+                trigger: Some("rising %IX0.0".into()),
+                pre_trigger: None,
+                post_trigger: None,
+                divider: None,
+            }
+        );
+        assert_eq!(parse(r#"{"command": "stop"}"#), TraceRequest::Stop);
+    }
+
+    #[tokio::test]
+    async fn test_command_round_trip() {
+        let (tx, mut rx) = control_channel();
+        let runtime = tokio::spawn(async move {
+            loop {
+                if let Some(command) = rx.try_recv() {
+                    let result = match &command.request {
+                        ControlRequest::ClearForces => Ok(()),
+                        _ => Err("rejected".to_string()),
+                    };
+                    command.reply(result);
+                    return rx;
+                }
+                tokio::task::yield_now().await;
+            }
+        });
+
+        assert_eq!(tx.send(ControlRequest::ClearForces).await, Ok(()));
+        let rx = runtime.await.unwrap();
+        drop(rx);
+        let request = ControlRequest::Unforce {
+            target: "%IX0.0".into(),
+        };
+        assert_eq!(tx.send(request).await, Err(None));
+    }
+}
@@ -45,6 +45,7 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
         .status-boot { background: #8b5cf6; color: #fff; }
         .status-pre_op { background: #f59e0b; color: #000; }
         .status-safe_stop { background: #fb923c; color: #000; }
+        .status-debug { background: #3b82f6; color: #fff; }
         .status-disconnected, .status-unknown { background: #374151; color: #9ca3af; }
 
         .container { padding: 1.5rem 2rem; }
@@ -162,6 +163,14 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
             50% { opacity: 0.5; }
         }
         .no-faults { color: #6b7280; }
+
+        .forced-badge {
+            background: #eab308;
+            color: #000;
+            display: none;
+        }
+        .forced-badge.active { display: inline-block; }
+        .force-item { color: #facc15; font-family: monospace; }
     </style>
 </head>
 <body>
@@ -172,6 +181,7 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
                 <div class="connection-dot disconnected" id="conn-dot"></div>
                 <span id="conn-text">Connecting...</span>
             </div>
+            <span id="forced-badge" class="status-badge forced-badge">FORCED</span>
             <span id="runtime-state" class="status-badge status-disconnected">--</span>
         </div>
     </div>
@@ -211,6 +221,16 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
                 </div>
             </div>
 
+            <!-- Tasks -->
+            <div class="card">
+                <div class="card-header">Tasks</div>
+                <div class="card-body">
+                    <div class="fault-list" id="task-list">
+                        <div class="fault-item no-faults">No task metrics yet</div>
+                    </div>
+                </div>
+            </div>
+
             <!-- Digital Inputs -->
             <div class="card">
                 <div class="card-header">Digital Inputs</div>
@@ -238,6 +258,46 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
                 <div class="card-body" id="ao-container"></div>
             </div>
 
+            <!-- SFC -->
+            <div class="card">
+                <div class="card-header">Active Steps</div>
+                <div class="card-body">
+                    <div class="fault-list" id="step-list">
+                        <div class="fault-item no-faults">No SFC steps</div>
+                    </div>
+                </div>
+            </div>
+
+            <!-- Forces -->
+            <div class="card">
+                <div class="card-header">Forces</div>
+                <div class="card-body">
+                    <div class="fault-list" id="force-list">
+                        <div class="fault-item no-faults">No active forces</div>
+                    </div>
+                </div>
+            </div>
+
+            <!-- Debug -->
+            <div class="card">
+                <div class="card-header">Debug</div>
+                <div class="card-body">
+                    <div class="fault-list" id="debug-list">
+                        <div class="fault-item no-faults">Not debugging</div>
+                    </div>
+                </div>
+            </div>
+
+            <!-- Trace -->
+            <div class="card">
+                <div class="card-header">Trace</div>
+                <div class="card-body">
+                    <div class="fault-list" id="trace-list">
+                        <div class="fault-item no-faults">No trace armed</div>
+                    </div>
+                </div>
+            </div>
+
             <!-- Faults -->
             <div class="card">
                 <div class="card-header">Recent Faults</div>
@@ -315,16 +375,17 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
             }
         }
 
-        // Update digital I/O display
+        // Update digital I/O display; bits are packed 32 per word
         function updateDigitalIo(di, doVal) {
+            const bit = (words, i) => ((words || [])[Math.floor(i / 32)] >>> (i % 32)) & 1;
             for (let i = 0; i < 32; i++) {
                 const diBit = document.getElementById('di-' + i);
                 const doBit = document.getElementById('do-' + i);
                 if (diBit) {
-                    diBit.className = (di & (1 << i)) ? 'io-bit on' : 'io-bit off';
+                    diBit.className = bit(di, i) ? 'io-bit on' : 'io-bit off';
                 }
                 if (doBit) {
-                    doBit.className = (doVal & (1 << i)) ? 'io-bit on' : 'io-bit off';
+                    doBit.className = bit(doVal, i) ? 'io-bit on' : 'io-bit off';
                 }
             }
         }
@@ -358,6 +419,23 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
             document.getElementById('m-jitter').textContent = m.jitter_us || '0';
             document.getElementById('m-target').textContent = m.target_us || '0';
             document.getElementById('m-overruns').textContent = m.overrun_count || '0';
+            if (m.tasks) updateTasks(m.tasks);
+        }
+
+        // Replace the per-task metrics using safe DOM methods
+        function updateTasks(tasks) {
+            const list = document.getElementById('task-list');
+            while (list.firstChild) {
+                list.removeChild(list.firstChild);
+            }
+            tasks.forEach(function(task) {
+                list.appendChild(createElement('div', {
+                    className: 'fault-item',
+                    text: task.name + ' (' + task.period_us + ' us): ' +
+                        task.cycles.toLocaleString() + ' runs, avg ' + task.avg_us +
+                        ' us, max ' + task.max_us + ' us, ' + task.overrun_count + ' overruns'
+                }));
+            });
         }
 
         // Update state badge
@@ -401,6 +479,107 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
             }
         }
 
+        // Replace the active SFC step list using safe DOM methods
+        function updateSteps(steps) {
+            const list = document.getElementById('step-list');
+            while (list.firstChild) {
+                list.removeChild(list.firstChild);
+            }
+            if (steps.length === 0) {
+                list.appendChild(createElement('div', {
+                    className: 'fault-item no-faults',
+                    text: 'No active steps'
+                }));
+                return;
+            }
+            steps.forEach(function(step) {
+                list.appendChild(createElement('div', { className: 'fault-item', text: step }));
+            });
+        }
+
+        // Replace the force list and header badge using safe DOM methods
+        function updateForces(forces) {
+            const badge = document.getElementById('forced-badge');
+            badge.textContent = forces.length + ' FORCED';
+            badge.className = forces.length
+                ? 'status-badge forced-badge active'
+                : 'status-badge forced-badge';
+
+            const list = document.getElementById('force-list');
+            while (list.firstChild) {
+                list.removeChild(list.firstChild);
+            }
+            if (forces.length === 0) {
+                list.appendChild(createElement('div', {
+                    className: 'fault-item no-faults',
+                    text: 'No active forces'
+                }));
+                return;
+            }
+            forces.forEach(function(force) {
+                list.appendChild(createElement('div', {
+                    className: 'fault-item force-item',
+                    text: force.target + ' := ' + force.value
+                }));
+            });
+        }
+
+        // Show where the logic paused and its variables using safe DOM methods
+        function updateDebug(debug) {
+            const list = document.getElementById('debug-list');
+            while (list.firstChild) {
+                list.removeChild(list.firstChild);
+            }
+            if (!debug) {
+                list.appendChild(createElement('div', {
+                    className: 'fault-item no-faults',
+                    text: 'Not debugging'
+                }));
+                return;
+            }
+            const status = debug.paused
+                ? 'Paused after cycle ' + debug.cycle + ': ' + debug.reason
+                : 'Running on command';
+            list.appendChild(createElement('div', { className: 'fault-item', text: status }));
+            debug.variables.forEach(function(variable) {
+                list.appendChild(createElement('div', {
+                    className: 'fault-item force-item',
+                    text: variable.name + ' = ' + variable.value
+                }));
+            });
+        }
+
+        // Show the trace status, with downloads once it finished
+        function updateTrace(trace) {
+            const list = document.getElementById('trace-list');
+            while (list.firstChild) {
+                list.removeChild(list.firstChild);
+            }
+            if (!trace) {
+                list.appendChild(createElement('div', {
+                    className: 'fault-item no-faults',
+                    text: 'No trace armed'
+                }));
+                return;
+            }
+            list.appendChild(createElement('div', {
+                className: 'fault-item',
+                text: trace.state + ' (' + trace.trigger + '): ' + trace.signals.join(', ')
+            }));
+            if (trace.state === 'COMPLETE' || trace.state === 'STOPPED') {
+                const item = createElement('div', {
+                    className: 'fault-item force-item',
+                    text: trace.samples + ' samples: '
+                });
+                ['csv', 'vcd'].forEach(function(format) {
+                    const link = createElement('a', { text: format.toUpperCase() + ' ' });
+                    link.href = '/api/trace/' + format;
+                    item.appendChild(link);
+                });
+                list.appendChild(item);
+            }
+        }
+
         // WebSocket connection
         let ws = null;
         let reconnectTimer = null;
@@ -447,6 +626,12 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
                         if (msg.faults) {
                             msg.faults.slice(-5).forEach(addFault);
                         }
+                        if (msg.active_steps && msg.active_steps.length) {
+                            updateSteps(msg.active_steps);
+                        }
+                        updateForces(msg.forces || []);
+                        updateDebug(msg.debug);
+                        updateTrace(msg.trace);
                     } else if (msg.type === 'io') {
                         updateDigitalIo(msg.digital_inputs, msg.digital_outputs);
                         updateAnalog(msg.analog_inputs || [], msg.analog_outputs || []);
@@ -456,6 +641,14 @@ const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
                         updateState(msg.state);
                     } else if (msg.type === 'fault') {
                         addFault(msg);
+                    } else if (msg.type === 'sfc') {
+                        updateSteps(msg.active_steps);
+                    } else if (msg.type === 'forces') {
+                        updateForces(msg.forces);
+                    } else if (msg.type === 'debug') {
+                        updateDebug(msg.debug);
+                    } else if (msg.type === 'trace') {
+                        updateTrace(msg.trace);
                     }
                 } catch (e) {
                     console.error('Failed to parse WebSocket message:', e);
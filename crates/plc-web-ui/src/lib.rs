@@ -3,6 +3,7 @@
 //! This crate provides:
 //! - HTTP REST API for reading PLC state and metrics
 //! - WebSocket endpoint for real-time I/O state streaming
+//! - Control commands (forcing, debug mode, tracing) passed to the runtime loop
 //! - Static file serving for the dashboard UI
 //!
 //! # Usage
@@ -16,17 +17,22 @@
 //! // Connect to scheduler state
 //! server.set_state_provider(state_provider);
 //!
+//! // Receive control commands in the runtime loop
+//! let commands = server.control_receiver();
+//!
 //! // Start the server
 //! server.start().await?;
 //! ```
 
 mod api;
+mod control;
 mod dashboard;
 mod metrics;
 mod state;
 mod websocket;
 
 pub use api::*;
+pub use control::*;
 pub use dashboard::*;
 pub use metrics::*;
 pub use state::*;
@@ -72,17 +78,22 @@ pub struct WebUiServer {
     state: Arc<SharedState>,
     broadcast_tx: broadcast::Sender<StateUpdate>,
     metrics: Arc<PlcMetrics>,
+    control_tx: ControlSender,
+    control_rx: Option<ControlReceiver>,
 }
 
 impl WebUiServer {
     /// Create a new web UI server with the given configuration.
     pub fn new(config: WebUiConfig) -> Self {
         let (broadcast_tx, _) = broadcast::channel(config.ws_channel_capacity);
+        let (control_tx, control_rx) = control_channel();
         Self {
             config,
             state: Arc::new(SharedState::default()),
             broadcast_tx,
             metrics: Arc::new(PlcMetrics::new()),
+            control_tx,
+            control_rx: Some(control_rx),
         }
     }
 
@@ -106,6 +117,15 @@ impl WebUiServer {
         Arc::clone(&self.metrics)
     }
 
+    /// Take the receiver of control commands (forcing, debug mode, tracing).
+    ///
+    /// The runtime loop polls it and replies to each command. Returns
+    /// `None` after the first call; while no one holds the receiver,
+    /// control requests fail with `503 Service Unavailable`.
+    pub fn control_receiver(&mut self) -> Option<ControlReceiver> {
+        self.control_rx.take()
+    }
+
     /// Start the web UI server.
     ///
     /// This is an async function that runs until cancelled.
@@ -128,6 +148,7 @@ impl WebUiServer {
         let state = Arc::clone(&self.state);
         let broadcast_tx = self.broadcast_tx.clone();
         let prom_metrics = Arc::clone(&self.metrics);
+        let control_tx = self.control_tx.clone();
         let static_dir = self.config.static_dir.clone();
         let enable_cors = self.config.enable_cors;
 
@@ -142,6 +163,18 @@ impl WebUiServer {
             .route("/api/metrics", get(api::get_metrics))
             .route("/api/io", get(api::get_io_state))
             .route("/api/faults", get(api::get_faults))
+            .route("/api/audit", get(api::get_audit))
+            .route("/api/sfc", get(api::get_active_steps))
+            .route(
+                "/api/forces",
+                get(api::get_forces)
+                    .post(api::set_force)
+                    .delete(api::clear_forces),
+            )
+            .route("/api/debug", get(api::get_debug).post(api::debug_command))
+            .route("/api/trace", get(api::get_trace).post(api::trace_command))
+            .route("/api/trace/csv", get(api::get_trace_csv))
+            .route("/api/trace/vcd", get(api::get_trace_vcd))
             // Prometheus metrics endpoint
             .route("/metrics", get(metrics::metrics_handler))
             // WebSocket endpoint
@@ -149,7 +182,8 @@ impl WebUiServer {
             // Extensions
             .layer(Extension(state))
             .layer(Extension(broadcast_tx))
-            .layer(Extension(prom_metrics));
+            .layer(Extension(prom_metrics))
+            .layer(Extension(control_tx));
 
         // Add CORS layer if enabled
         if enable_cors {
@@ -53,11 +53,14 @@ pub struct PlcMetrics {
     /// Total faults recorded.
     pub faults_total: IntCounter,
 
-    /// Digital inputs state (bit-packed as gauge).
-    pub digital_inputs: Gauge,
+    /// Number of active forces.
+    pub forces_active: IntGauge,
 
-    /// Digital outputs state (bit-packed as gauge).
-    pub digital_outputs: Gauge,
+    /// Digital inputs state by 32-bit word (bit-packed as gauge).
+    pub digital_inputs: GaugeVec,
+
+    /// Digital outputs state by 32-bit word (bit-packed as gauge).
+    pub digital_outputs: GaugeVec,
 
     /// Analog inputs by channel.
     pub analog_inputs: GaugeVec,
@@ -65,6 +68,21 @@ pub struct PlcMetrics {
     /// Analog outputs by channel.
     pub analog_outputs: GaugeVec,
 
+    /// Task period in microseconds, by task.
+    pub task_period_us: GaugeVec,
+
+    /// Times each task ran.
+    pub task_cycles: GaugeVec,
+
+    /// Overruns of each task.
+    pub task_overruns: GaugeVec,
+
+    /// Average task execution time in microseconds, by task.
+    pub task_time_avg_us: GaugeVec,
+
+    /// Maximum task execution time in microseconds, by task.
+    pub task_time_max_us: GaugeVec,
+
     /// Last observed total cycles (for counter synchronization).
     last_cycles_total: AtomicU64,
 
@@ -85,7 +103,7 @@ impl PlcMetrics {
 
         let runtime_state = IntGauge::new(
             "plc_runtime_state",
-            "Current runtime state (0=Boot, 1=Init, 2=PreOp, 3=Run, 4=Fault, 5=SafeStop)",
+            "Current runtime state (0=Boot, 1=Init, 2=PreOp, 3=Run, 4=Fault, 5=SafeStop, 6=Debug)",
         )
         .expect("metric creation should succeed");
 
@@ -160,15 +178,27 @@ impl PlcMetrics {
         let faults_total = IntCounter::new("plc_faults_total", "Total number of faults recorded")
             .expect("metric creation should succeed");
 
-        let digital_inputs = Gauge::new(
-            "plc_digital_inputs",
-            "Digital inputs state (bit-packed as integer)",
+        let forces_active = IntGauge::new(
+            "plc_forces_active",
+            "Number of forced I/O points and variables",
         )
         .expect("metric creation should succeed");
 
-        let digital_outputs = Gauge::new(
-            "plc_digital_outputs",
-            "Digital outputs state (bit-packed as integer)",
+        let digital_inputs = GaugeVec::new(
+            Opts::new(
+                "plc_digital_inputs",
+                "Digital inputs state by word (bit-packed as integer)",
+            ),
+            &["word"],
+        )
+        .expect("metric creation should succeed");
+
+        let digital_outputs = GaugeVec::new(
+            Opts::new(
+                "plc_digital_outputs",
+                "Digital outputs state by word (bit-packed as integer)",
+            ),
+            &["word"],
         )
         .expect("metric creation should succeed");
 
@@ -184,6 +214,24 @@ impl PlcMetrics {
         )
         .expect("metric creation should succeed");
 
+        let task_gauge = |name: &str, help: &str| {
+            GaugeVec::new(Opts::new(name, help), &["task"]).expect("metric creation should succeed")
+        };
+        let task_period_us = task_gauge(
+            "plc_task_period_microseconds",
+            "Configured task period in microseconds",
+        );
+        let task_cycles = task_gauge("plc_task_cycles", "Number of times the task ran");
+        let task_overruns = task_gauge("plc_task_overruns", "Number of task overruns");
+        let task_time_avg_us = task_gauge(
+            "plc_task_time_avg_microseconds",
+            "Average task execution time in microseconds",
+        );
+        let task_time_max_us = task_gauge(
+            "plc_task_time_max_microseconds",
+            "Maximum task execution time in microseconds",
+        );
+
         // Register all metrics
         registry
             .register(Box::new(cycles_total.clone()))
@@ -221,6 +269,9 @@ impl PlcMetrics {
         registry
             .register(Box::new(faults_total.clone()))
             .expect("registration should succeed");
+        registry
+            .register(Box::new(forces_active.clone()))
+            .expect("registration should succeed");
         registry
             .register(Box::new(digital_inputs.clone()))
             .expect("registration should succeed");
@@ -233,6 +284,17 @@ impl PlcMetrics {
         registry
             .register(Box::new(analog_outputs.clone()))
             .expect("registration should succeed");
+        for task_metric in [
+            &task_period_us,
+            &task_cycles,
+            &task_overruns,
+            &task_time_avg_us,
+            &task_time_max_us,
+        ] {
+            registry
+                .register(Box::new(task_metric.clone()))
+                .expect("registration should succeed");
+        }
 
         Self {
             registry,
@@ -248,10 +310,16 @@ impl PlcMetrics {
             cycle_duration,
             websocket_clients,
             faults_total,
+            forces_active,
             digital_inputs,
             digital_outputs,
             analog_inputs,
             analog_outputs,
+            task_period_us,
+            task_cycles,
+            task_overruns,
+            task_time_avg_us,
+            task_time_max_us,
             last_cycles_total: AtomicU64::new(0),
             last_overruns_total: AtomicU64::new(0),
         }
@@ -315,12 +383,39 @@ impl PlcMetrics {
         self.cycle_time_avg_us.set(metrics.avg_us as f64);
         self.cycle_jitter_us.set(metrics.jitter_us as f64);
         self.cycle_target_us.set(metrics.target_us as f64);
+
+        for task in &metrics.tasks {
+            let labels = [task.name.as_str()];
+            self.task_period_us
+                .with_label_values(&labels)
+                .set(task.period_us as f64);
+            self.task_cycles
+                .with_label_values(&labels)
+                .set(task.cycles as f64);
+            self.task_overruns
+                .with_label_values(&labels)
+                .set(task.overrun_count as f64);
+            self.task_time_avg_us
+                .with_label_values(&labels)
+                .set(task.avg_us as f64);
+            self.task_time_max_us
+                .with_label_values(&labels)
+                .set(task.max_us as f64);
+        }
     }
 
     /// Update I/O metrics from an I/O snapshot.
     pub fn update_from_io(&self, io: &crate::state::IoSnapshot) {
-        self.digital_inputs.set(io.digital_inputs as f64);
-        self.digital_outputs.set(io.digital_outputs as f64);
+        for (i, &word) in io.digital_inputs.iter().enumerate() {
+            self.digital_inputs
+                .with_label_values(&[&i.to_string()])
+                .set(f64::from(word));
+        }
+        for (i, &word) in io.digital_outputs.iter().enumerate() {
+            self.digital_outputs
+                .with_label_values(&[&i.to_string()])
+                .set(f64::from(word));
+        }
 
         for (i, &value) in io.analog_inputs.iter().enumerate() {
             self.analog_inputs
@@ -411,16 +506,19 @@ mod tests {
     fn test_io_update() {
         let metrics = PlcMetrics::new();
         let io = crate::state::IoSnapshot {
-            digital_inputs: 0xFF,
-            digital_outputs: 0x0F,
+            digital_inputs: vec![0xFF, 0x01],
+            digital_outputs: vec![0x0F],
             analog_inputs: vec![100, 200, 300],
             analog_outputs: vec![400, 500],
         };
 
         metrics.update_from_io(&io);
 
-        assert!((metrics.digital_inputs.get() - 255.0).abs() < 0.001);
-        assert!((metrics.digital_outputs.get() - 15.0).abs() < 0.001);
+        let di = |word: &str| metrics.digital_inputs.with_label_values(&[word]).get();
+        assert!((di("0") - 255.0).abs() < 0.001);
+        assert!((di("1") - 1.0).abs() < 0.001);
+        let do_word = metrics.digital_outputs.with_label_values(&["0"]).get();
+        assert!((do_word - 15.0).abs() < 0.001);
     }
 
     #[test]
@@ -435,6 +533,7 @@ mod tests {
             target_us: 100,
             overrun_count: 2,
             jitter_us: 100,
+            ..Default::default()
         };
         metrics.update_from_snapshot(&snapshot);
         assert_eq!(metrics.cycles_total.get(), 10);
@@ -448,9 +547,34 @@ mod tests {
             target_us: 100,
             overrun_count: 3,
             jitter_us: 100,
+            ..Default::default()
         };
         metrics.update_from_snapshot(&snapshot);
         assert_eq!(metrics.cycles_total.get(), 15);
         assert_eq!(metrics.overruns_total.get(), 3);
     }
+
+    #[test]
+    fn test_task_metrics() {
+        let metrics = PlcMetrics::new();
+        let snapshot = crate::state::MetricsSnapshot {
+            tasks: vec![crate::state::TaskMetrics {
+                name: "Motion".into(),
+                period_us: 1000,
+                cycles: 500,
+                overrun_count: 1,
+                avg_us: 120,
+                max_us: 900,
+            }],
+            ..Default::default()
+        };
+        metrics.update_from_snapshot(&snapshot);
+
+        let task = |vec: &GaugeVec| vec.with_label_values(&["Motion"]).get();
+        assert!((task(&metrics.task_cycles) - 500.0).abs() < 0.001);
+        assert!((task(&metrics.task_overruns) - 1.0).abs() < 0.001);
+        assert!((task(&metrics.task_time_max_us) - 900.0).abs() < 0.001);
+        let output = metrics.render().expect("should render");
+        assert!(output.contains("plc_task_period_microseconds{task=\"Motion\"} 1000"));
+    }
 }
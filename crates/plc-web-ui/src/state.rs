@@ -13,13 +13,13 @@ use tokio::sync::broadcast;
 /// I/O state snapshot.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IoSnapshot {
-    /// Digital inputs (bit-packed).
-    pub digital_inputs: u32,
-    /// Digital outputs (bit-packed).
-    pub digital_outputs: u32,
-    /// Analog inputs (16 channels).
+    /// Digital inputs (bit-packed, 32 per word).
+    pub digital_inputs: Vec<u32>,
+    /// Digital outputs (bit-packed, 32 per word).
+    pub digital_outputs: Vec<u32>,
+    /// Analog inputs by channel.
     pub analog_inputs: Vec<i16>,
-    /// Analog outputs (16 channels).
+    /// Analog outputs by channel.
     pub analog_outputs: Vec<i16>,
 }
 
@@ -40,6 +40,45 @@ pub struct MetricsSnapshot {
     pub overrun_count: u64,
     /// Jitter (max - min) in microseconds.
     pub jitter_us: u64,
+    /// Metrics of each task.
+    #[serde(default)]
+    pub tasks: Vec<TaskMetrics>,
+}
+
+/// Cycle metrics as the runtime measures them, in nanoseconds.
+#[derive(Debug, Clone, Default)]
+pub struct RawMetrics {
+    /// Total cycles executed.
+    pub total_cycles: u64,
+    /// Minimum cycle time in nanoseconds.
+    pub min_ns: u64,
+    /// Maximum cycle time in nanoseconds.
+    pub max_ns: u64,
+    /// Average cycle time in nanoseconds.
+    pub avg_ns: u64,
+    /// Configured cycle time in nanoseconds.
+    pub target_ns: u64,
+    /// Number of overruns.
+    pub overrun_count: u64,
+    /// Metrics of each task.
+    pub tasks: Vec<TaskMetrics>,
+}
+
+/// Metrics of one task.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskMetrics {
+    /// Task name.
+    pub name: String,
+    /// Task period in microseconds.
+    pub period_us: u64,
+    /// Times the task ran.
+    pub cycles: u64,
+    /// Number of overruns.
+    pub overrun_count: u64,
+    /// Average execution time in microseconds.
+    pub avg_us: u64,
+    /// Maximum execution time in microseconds.
+    pub max_us: u64,
 }
 
 /// Fault record.
@@ -53,6 +92,75 @@ pub struct FaultRecord {
     pub timestamp_ms: u64,
 }
 
+/// Audit trail entry: a security decision, such as accepting or refusing
+/// a logic module's signature.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Cycle number when the decision was taken.
+    pub cycle: u64,
+    /// What was decided.
+    pub event: String,
+    /// Whether the request was accepted.
+    pub accepted: bool,
+    /// Timestamp (relative to session start).
+    pub timestamp_ms: u64,
+}
+
+/// An active force.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForceEntry {
+    /// Forced address (`%QX0.1`) or variable.
+    pub target: String,
+    /// Forced value.
+    pub value: String,
+}
+
+/// A variable value, as shown while debugging.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariableEntry {
+    /// Qualified name.
+    pub name: String,
+    /// Value.
+    pub value: String,
+}
+
+/// Debug mode status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DebugSnapshot {
+    /// Whether the logic is paused.
+    pub paused: bool,
+    /// Cycle the logic last paused after.
+    pub cycle: u64,
+    /// Why it paused.
+    pub reason: String,
+    /// Variables at that pause.
+    pub variables: Vec<VariableEntry>,
+}
+
+/// Signal trace status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceSnapshot {
+    /// `ARMED`, `TRIGGERED`, `COMPLETE` or `STOPPED`.
+    pub state: String,
+    /// Recorded signals.
+    pub signals: Vec<String>,
+    /// Trigger condition.
+    pub trigger: String,
+    /// Samples in the capture, once it is finished.
+    pub samples: usize,
+    /// Cycle the trace triggered at.
+    pub trigger_cycle: Option<u64>,
+}
+
+/// A finished trace, rendered for download.
+#[derive(Debug, Clone, Default)]
+pub struct TraceExport {
+    /// CSV, one row per sample.
+    pub csv: String,
+    /// Value Change Dump.
+    pub vcd: String,
+}
+
 /// Complete state snapshot.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StateSnapshot {
@@ -64,6 +172,17 @@ pub struct StateSnapshot {
     pub metrics: MetricsSnapshot,
     /// Recent faults (last N).
     pub faults: Vec<FaultRecord>,
+    /// Recent audit trail entries (last N).
+    #[serde(default)]
+    pub audit: Vec<AuditRecord>,
+    /// Active SFC steps (`Pou.Step`).
+    pub active_steps: Vec<String>,
+    /// Active forces.
+    pub forces: Vec<ForceEntry>,
+    /// Debug mode status, while in DEBUG.
+    pub debug: Option<DebugSnapshot>,
+    /// Signal trace status, once a trace was armed.
+    pub trace: Option<TraceSnapshot>,
     /// Timestamp of this snapshot (ms since epoch).
     pub timestamp_ms: u64,
 }
@@ -74,7 +193,7 @@ pub struct StateSnapshot {
 pub enum StateUpdate {
     /// Full state snapshot.
     #[serde(rename = "full")]
-    Full(StateSnapshot),
+    Full(Box<StateSnapshot>),
     /// Incremental I/O update.
     #[serde(rename = "io")]
     Io(IoSnapshot),
@@ -84,9 +203,24 @@ pub enum StateUpdate {
     /// New fault.
     #[serde(rename = "fault")]
     Fault(FaultRecord),
+    /// New audit trail entry.
+    #[serde(rename = "audit")]
+    Audit(AuditRecord),
     /// Runtime state change.
     #[serde(rename = "state")]
     StateChange { state: String },
+    /// Active SFC steps changed.
+    #[serde(rename = "sfc")]
+    Sfc { active_steps: Vec<String> },
+    /// Active forces changed.
+    #[serde(rename = "forces")]
+    Forces { forces: Vec<ForceEntry> },
+    /// Debug mode status changed.
+    #[serde(rename = "debug")]
+    Debug { debug: Option<DebugSnapshot> },
+    /// Signal trace status changed.
+    #[serde(rename = "trace")]
+    Trace { trace: Option<TraceSnapshot> },
 }
 
 /// Shared state container.
@@ -100,6 +234,18 @@ pub struct SharedState {
     pub metrics: RwLock<MetricsSnapshot>,
     /// Recent faults.
     pub faults: RwLock<Vec<FaultRecord>>,
+    /// Recent audit trail entries.
+    pub audit: RwLock<Vec<AuditRecord>>,
+    /// Active SFC steps.
+    pub active_steps: RwLock<Vec<String>>,
+    /// Active forces.
+    pub forces: RwLock<Vec<ForceEntry>>,
+    /// Debug mode status.
+    pub debug: RwLock<Option<DebugSnapshot>>,
+    /// Signal trace status.
+    pub trace: RwLock<Option<TraceSnapshot>>,
+    /// The last finished trace.
+    pub trace_export: RwLock<Option<TraceExport>>,
     /// Session start time.
     pub session_start: RwLock<Option<Instant>>,
 }
@@ -115,6 +261,15 @@ impl SharedState {
         let io = self.io.read().map(|i| i.clone()).unwrap_or_default();
         let metrics = self.metrics.read().map(|m| m.clone()).unwrap_or_default();
         let faults = self.faults.read().map(|f| f.clone()).unwrap_or_default();
+        let audit = self.audit.read().map(|a| a.clone()).unwrap_or_default();
+        let active_steps = self
+            .active_steps
+            .read()
+            .map(|s| s.clone())
+            .unwrap_or_default();
+        let forces = self.forces.read().map(|f| f.clone()).unwrap_or_default();
+        let debug = self.debug.read().map(|d| d.clone()).unwrap_or_default();
+        let trace = self.trace.read().map(|t| t.clone()).unwrap_or_default();
 
         let timestamp_ms = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -126,6 +281,11 @@ impl SharedState {
             io,
             metrics,
             faults,
+            audit,
+            active_steps,
+            forces,
+            debug,
+            trace,
             timestamp_ms,
         }
     }
@@ -148,6 +308,7 @@ impl StateUpdater {
             RuntimeState::Run => 3,
             RuntimeState::Fault => 4,
             RuntimeState::SafeStop => 5,
+            RuntimeState::Debug => 6,
         }
     }
 
@@ -196,15 +357,19 @@ impl StateUpdater {
         }
     }
 
-    /// Record a fault.
-    pub fn record_fault(&self, reason: String, cycle: u64) {
-        let timestamp_ms = self
-            .state
+    /// Milliseconds since the session started.
+    fn session_time_ms(&self) -> u64 {
+        self.state
             .session_start
             .read()
             .ok()
             .and_then(|start| start.as_ref().map(|s| s.elapsed().as_millis() as u64))
-            .unwrap_or(0);
+            .unwrap_or(0)
+    }
+
+    /// Record a fault.
+    pub fn record_fault(&self, reason: String, cycle: u64) {
+        let timestamp_ms = self.session_time_ms();
 
         let fault = FaultRecord {
             cycle,
@@ -227,10 +392,86 @@ impl StateUpdater {
         }
     }
 
+    /// Record a security decision in the audit trail.
+    pub fn record_audit(&self, event: String, accepted: bool, cycle: u64) {
+        let record = AuditRecord {
+            cycle,
+            event,
+            accepted,
+            timestamp_ms: self.session_time_ms(),
+        };
+        if let Ok(mut guard) = self.state.audit.write() {
+            guard.push(record.clone());
+            // Keep only the last 100 entries
+            if guard.len() > 100 {
+                guard.remove(0);
+            }
+        }
+        let _ = self.broadcast_tx.send(StateUpdate::Audit(record));
+    }
+
+    /// Update the active SFC steps; only broadcasts when they change.
+    pub fn update_active_steps(&self, steps: Vec<String>) {
+        if let Ok(mut guard) = self.state.active_steps.write() {
+            if *guard == steps {
+                return;
+            }
+            *guard = steps.clone();
+        }
+        let _ = self.broadcast_tx.send(StateUpdate::Sfc {
+            active_steps: steps,
+        });
+    }
+
+    /// Update the active forces; only broadcasts when they change.
+    pub fn update_forces(&self, forces: Vec<ForceEntry>) {
+        if let Ok(mut guard) = self.state.forces.write() {
+            if *guard == forces {
+                return;
+            }
+            *guard = forces.clone();
+        }
+        if let Some(ref metrics) = self.metrics {
+            metrics.forces_active.set(forces.len() as i64);
+        }
+        let _ = self.broadcast_tx.send(StateUpdate::Forces { forces });
+    }
+
+    /// Update the debug mode status; only broadcasts when it changes.
+    pub fn update_debug(&self, debug: Option<DebugSnapshot>) {
+        if let Ok(mut guard) = self.state.debug.write() {
+            if *guard == debug {
+                return;
+            }
+            *guard = debug.clone();
+        }
+        let _ = self.broadcast_tx.send(StateUpdate::Debug { debug });
+    }
+
+    /// Update the signal trace status; only broadcasts when it changes.
+    pub fn update_trace(&self, trace: Option<TraceSnapshot>) {
+        if let Ok(mut guard) = self.state.trace.write() {
+            if *guard == trace {
+                return;
+            }
+            *guard = trace.clone();
+        }
+        let _ = self.broadcast_tx.send(StateUpdate::Trace { trace });
+    }
+
+    /// Publish a finished trace for download.
+    pub fn set_trace_export(&self, export: TraceExport) {
+        if let Ok(mut guard) = self.state.trace_export.write() {
+            *guard = Some(export);
+        }
+    }
+
     /// Send a full state snapshot (useful for new WebSocket connections).
     pub fn broadcast_full_state(&self) {
         let snapshot = self.state.snapshot();
-        let _ = self.broadcast_tx.send(StateUpdate::Full(snapshot));
+        let _ = self
+            .broadcast_tx
+            .send(StateUpdate::Full(Box::new(snapshot)));
     }
 
     /// Mark session start time.
@@ -243,14 +484,14 @@ impl StateUpdater {
     /// Update I/O from raw values (convenience method).
     pub fn update_io_raw(
         &self,
-        digital_inputs: u32,
-        digital_outputs: u32,
+        digital_inputs: &[u32],
+        digital_outputs: &[u32],
         analog_inputs: &[i16],
         analog_outputs: &[i16],
     ) {
         let io = IoSnapshot {
-            digital_inputs,
-            digital_outputs,
+            digital_inputs: digital_inputs.to_vec(),
+            digital_outputs: digital_outputs.to_vec(),
             analog_inputs: analog_inputs.to_vec(),
             analog_outputs: analog_outputs.to_vec(),
         };
@@ -258,23 +499,16 @@ impl StateUpdater {
     }
 
     /// Update metrics from raw values (convenience method).
-    pub fn update_metrics_raw(
-        &self,
-        total_cycles: u64,
-        min_ns: u64,
-        max_ns: u64,
-        avg_ns: u64,
-        target_ns: u64,
-        overrun_count: u64,
-    ) {
+    pub fn update_metrics_raw(&self, raw: &RawMetrics) {
         let metrics = MetricsSnapshot {
-            total_cycles,
-            min_us: min_ns / 1000,
-            max_us: max_ns / 1000,
-            avg_us: avg_ns / 1000,
-            target_us: target_ns / 1000,
-            overrun_count,
-            jitter_us: (max_ns.saturating_sub(min_ns)) / 1000,
+            total_cycles: raw.total_cycles,
+            min_us: raw.min_ns / 1000,
+            max_us: raw.max_ns / 1000,
+            avg_us: raw.avg_ns / 1000,
+            target_us: raw.target_ns / 1000,
+            overrun_count: raw.overrun_count,
+            jitter_us: (raw.max_ns.saturating_sub(raw.min_ns)) / 1000,
+            tasks: raw.tasks.clone(),
         };
         self.update_metrics(metrics);
     }
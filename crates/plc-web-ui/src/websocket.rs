@@ -39,7 +39,7 @@ async fn handle_socket(
 
     // Send initial full state snapshot
     let initial_snapshot = state.snapshot();
-    let initial_msg = StateUpdate::Full(initial_snapshot);
+    let initial_msg = StateUpdate::Full(Box::new(initial_snapshot));
     if let Ok(json) = serde_json::to_string(&initial_msg) {
         if sender.send(Message::Text(json.into())).await.is_err() {
             warn!("Failed to send initial state to WebSocket client");